@@ -1,10 +1,23 @@
+use crate::sql::arrow_sql_gen::statement::IndexBuilder;
+use crate::sql::column_expressions::{self, ColumnExpressions};
+use crate::sql::datetime::DateTimeSyntax;
+use crate::sql::dialect::DialectOverrides;
+use crate::sql::dml::{self, quote_table_reference};
+use crate::sql::json::JsonSyntax;
+use crate::sql::permissions::{probe_permissions, PermissionReport};
+use crate::sql::pushdown::PushdownPolicy;
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::sample::TableSample;
+use crate::sql::schema_drift::SchemaDriftPolicy;
 use crate::sql::sql_provider_datafusion;
 use crate::util::{
     self,
     column_reference::{self, ColumnReference},
     constraints,
-    indexes::IndexType,
+    identifier::IdentifierCase,
+    indexes::{IndexAdvisor, IndexType},
     on_conflict::{self, OnConflict},
+    validation,
 };
 use crate::{
     sql::db_connection_pool::{
@@ -16,6 +29,7 @@ use crate::{
             get_schema, DbConnection,
         },
         duckdbpool::{DuckDbConnectionPool, DuckDbConnectionPoolBuilder},
+        pool_selector::PoolSelector,
         DbConnectionPool, DbInstanceKey, Mode,
     },
     UnsupportedTypeAction,
@@ -23,6 +37,7 @@ use crate::{
 use arrow::datatypes::SchemaRef;
 use async_trait::async_trait;
 use creator::TableManager;
+use datafusion::sql::sqlparser::ast;
 use datafusion::sql::unparser::dialect::{Dialect, DuckDBDialect};
 use datafusion::{
     catalog::{Session, TableProviderFactory},
@@ -30,21 +45,28 @@ use datafusion::{
     datasource::TableProvider,
     error::{DataFusionError, Result as DataFusionResult},
     logical_expr::CreateExternalTable,
+    scalar::ScalarValue,
     sql::TableReference,
 };
 use duckdb::{AccessMode, DuckdbConnectionManager};
 use itertools::Itertools;
+use secrecy::SecretString;
 use snafu::prelude::*;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
-use write::DuckDBTableWriterBuilder;
+use write::{DuckDBTableWriterBuilder, InsertMethod};
 
-use self::sql_table::DuckDBTable;
+pub(crate) use self::sql_table::DuckDBTable;
 
 #[cfg(feature = "duckdb-federation")]
 mod federation;
 
 mod creator;
+pub mod delta;
+pub mod export;
+pub mod iceberg;
+#[cfg(feature = "object-store")]
+pub mod object_store;
 mod sql_table;
 pub mod write;
 pub use creator::{RelationName, TableDefinition};
@@ -109,6 +131,12 @@ pub enum Error {
     #[snafu(display("Unable to delete all data from the DuckDB table: {source}"))]
     UnableToDeleteAllTableData { source: duckdb::Error },
 
+    #[snafu(display("Unable to create the DML statement for the DuckDB table: {source}"))]
+    UnableToCreateDmlStatement { source: dml::Error },
+
+    #[snafu(display("Unable to execute the DML statement on the DuckDB table: {source}"))]
+    UnableToExecuteDmlStatement { source: duckdb::Error },
+
     #[snafu(display("Unable to insert data into the DuckDB table: {source}"))]
     UnableToInsertIntoTableAsync { source: duckdb::Error },
 
@@ -144,6 +172,28 @@ pub enum Error {
     #[snafu(display("Failed to parse the system time: {source}"))]
     UnableToParseSystemTime { source: std::num::ParseIntError },
 
+    #[snafu(display("Unable to drop duplicate rows from the data to insert: {source}"))]
+    UnableToDeduplicateBatch { source: arrow::error::ArrowError },
+
+    #[snafu(display("The data to insert is invalid: {source}"))]
+    InvalidBatch { source: validation::Error },
+
+    #[snafu(display("Error parsing validate_batches: {source}"))]
+    UnableToParseValidateBatches { source: validation::Error },
+
+    #[snafu(display(
+        "Invalid value for insert_method: '{value}', expected 'arrow_scan' or 'appender'"
+    ))]
+    InvalidInsertMethod { value: String },
+
+    #[snafu(display("Invalid column defaults: {source}"))]
+    InvalidColumnExpressions { source: column_expressions::Error },
+
+    #[snafu(display(
+        "Failed to create '{table_name}': DuckDB tables don't support stored generated columns. Compute the column in the query that writes the table instead"
+    ))]
+    GeneratedColumnsNotSupported { table_name: String },
+
     #[snafu(display("A read provider is required to create a DuckDBTableWriter"))]
     MissingReadProvider,
 
@@ -167,6 +217,7 @@ pub struct DuckDBTableProviderFactory {
     instances: Arc<Mutex<HashMap<DbInstanceKey, DuckDbConnectionPool>>>,
     unsupported_type_action: UnsupportedTypeAction,
     dialect: Arc<dyn Dialect>,
+    pools: PoolSelector<DuckDbConnectionPool>,
 }
 
 // Dialect trait does not implement Debug so we implement Debug manually
@@ -176,6 +227,7 @@ impl std::fmt::Debug for DuckDBTableProviderFactory {
             .field("access_mode", &self.access_mode)
             .field("instances", &self.instances)
             .field("unsupported_type_action", &self.unsupported_type_action)
+            .field("pools", &self.pools)
             .finish()
     }
 }
@@ -183,6 +235,7 @@ impl std::fmt::Debug for DuckDBTableProviderFactory {
 const DUCKDB_DB_PATH_PARAM: &str = "open";
 const DUCKDB_DB_BASE_FOLDER_PARAM: &str = "data_directory";
 const DUCKDB_ATTACH_DATABASES_PARAM: &str = "attach_databases";
+const DUCKDB_MOTHERDUCK_TOKEN_PARAM: &str = "motherduck_token";
 const DUCKDB_SETTING_MEMORY_LIMIT: &str = "memory_limit";
 const DUCKDB_SETTING_TEMP_DIRECTORY: &str = "temp_directory";
 const DUCKDB_SETTING_PRESERVE_INSERTION_ORDER: &str = "preserve_insertion_order";
@@ -194,10 +247,31 @@ impl DuckDBTableProviderFactory {
             access_mode,
             instances: Arc::new(Mutex::new(HashMap::new())),
             unsupported_type_action: UnsupportedTypeAction::Error,
-            dialect: Arc::new(DuckDBDialect::new()),
+            dialect: duckdb_dialect(),
+            pools: PoolSelector::new(),
         }
     }
 
+    /// Creates the table `table` with `pool` instead of the pool of the database of its options,
+    /// see [`PoolSelector`].
+    #[must_use]
+    pub fn with_table_pool(
+        mut self,
+        table: impl Into<TableReference>,
+        pool: Arc<DuckDbConnectionPool>,
+    ) -> Self {
+        self.pools = self.pools.with_table_pool(table, pool);
+        self
+    }
+
+    /// Creates the tables with the pools of `pools` instead of the pools of the databases of their
+    /// options, where it has one for them.
+    #[must_use]
+    pub fn with_pool_selector(mut self, pools: PoolSelector<DuckDbConnectionPool>) -> Self {
+        self.pools = pools;
+        self
+    }
+
     #[must_use]
     pub fn with_unsupported_type_action(
         mut self,
@@ -213,6 +287,14 @@ impl DuckDBTableProviderFactory {
         self
     }
 
+    /// Customizes how scalar functions are unparsed on top of the current dialect, see
+    /// [`DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect_overrides(mut self, dialect_overrides: &DialectOverrides) -> Self {
+        self.dialect = dialect_overrides.dialect(self.dialect);
+        self
+    }
+
     #[must_use]
     pub fn attach_databases(&self, options: &HashMap<String, String>) -> Vec<Arc<str>> {
         options
@@ -359,24 +441,52 @@ impl TableProviderFactory for DuckDBTableProviderFactory {
             );
         }
 
-        let pool: DuckDbConnectionPool = match &mode {
-            Mode::File => {
+        let dedup_columns = remove_option(&mut options, "dedup_columns")
+            .map(|dedup_columns| {
+                ColumnReference::try_from(dedup_columns.as_str())
+                    .context(UnableToParseColumnReferenceSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?;
+
+        let validate_batches = remove_option(&mut options, "validate_batches")
+            .map(|value| {
+                validation::parse_validate_batches(&value)
+                    .context(UnableToParseValidateBatchesSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let insert_method = remove_option(&mut options, "insert_method")
+            .map(|value| InsertMethod::from_str(&value).map_err(to_datafusion_error))
+            .transpose()?
+            .unwrap_or_default();
+
+        let motherduck_token =
+            remove_option(&mut options, DUCKDB_MOTHERDUCK_TOKEN_PARAM).map(SecretString::from);
+
+        let pool: DuckDbConnectionPool = match (self.pools.select(&cmd.name, &cmd.options), &mode) {
+            (Some(pool), _) => pool.as_ref().clone(),
+            (None, Mode::File) => {
                 // open duckdb at given path or create a new one
                 let db_path = self
                     .duckdb_file_path(&name, &mut options)
                     .map_err(to_datafusion_error)?;
 
-                self.get_or_init_file_instance(db_path)
+                let pool_builder = DuckDbConnectionPoolBuilder::file(&db_path)
+                    .with_motherduck_token(motherduck_token);
+                self.get_or_init_instance_with_builder(pool_builder)
                     .await
                     .map_err(to_datafusion_error)?
             }
-            Mode::Memory => self
+            (None, Mode::Memory) => self
                 .get_or_init_memory_instance()
                 .await
                 .map_err(to_datafusion_error)?,
         };
 
-        let read_pool = match &mode {
+        let read_pool = match pool.mode() {
             Mode::File => {
                 let read_pool = pool.clone();
 
@@ -387,18 +497,45 @@ impl TableProviderFactory for DuckDBTableProviderFactory {
 
         let schema: SchemaRef = Arc::new(cmd.schema.as_ref().into());
 
+        let column_expressions = ColumnExpressions::try_from_table_options(
+            &cmd.column_defaults,
+            &mut options,
+            &DuckDBDialect::new(),
+        )
+        .and_then(|column_expressions| {
+            column_expressions.validate(&schema)?;
+            Ok(column_expressions)
+        })
+        .context(InvalidColumnExpressionsSnafu)
+        .map_err(to_datafusion_error)?;
+        if column_expressions.has_generated_columns() {
+            GeneratedColumnsNotSupportedSnafu {
+                table_name: name.clone(),
+            }
+            .fail()
+            .map_err(to_datafusion_error)?;
+        }
+
         let table_definition =
             TableDefinition::new(RelationName::new(name.clone()), Arc::clone(&schema))
                 .with_constraints(cmd.constraints.clone())
-                .with_indexes(indexes.clone());
+                .with_indexes(indexes.clone())
+                .with_column_expressions(column_expressions);
 
         let pool = Arc::new(pool);
         make_initial_table(Arc::new(table_definition.clone()), &pool)?;
 
-        let table_writer_builder = DuckDBTableWriterBuilder::new()
+        let mut table_writer_builder = DuckDBTableWriterBuilder::new()
             .with_table_definition(table_definition)
             .with_pool(pool)
-            .set_on_conflict(on_conflict);
+            .set_on_conflict(on_conflict)
+            .with_batch_validation(validate_batches)
+            .with_insert_method(insert_method);
+
+        if let Some(dedup_columns) = dedup_columns {
+            table_writer_builder = table_writer_builder
+                .with_dedup_columns(dedup_columns.iter().map(String::from).collect());
+        }
 
         let dyn_pool: Arc<DynDuckDbConnectionPool> = Arc::new(read_pool);
 
@@ -436,8 +573,13 @@ impl TableProviderFactory for DuckDBTableProviderFactory {
     }
 }
 
+/// The dialect of DuckDB tables, with the JSON and date functions of DuckDB.
+pub(crate) fn duckdb_dialect() -> Arc<dyn Dialect + Send + Sync> {
+    DateTimeSyntax::DuckDB.dialect(JsonSyntax::DuckDB.dialect(Arc::new(DuckDBDialect::new())))
+}
+
 fn to_datafusion_error(error: Error) -> DataFusionError {
-    DataFusionError::External(Box::new(error))
+    DataFusionError::External(crate::util::redact::redact_error(Box::new(error), None))
 }
 
 pub struct DuckDB {
@@ -514,6 +656,20 @@ fn remove_option(options: &mut HashMap<String, String>, key: &str) -> Option<Str
 pub struct DuckDBTableFactory {
     pool: Arc<DuckDbConnectionPool>,
     dialect: Arc<dyn Dialect>,
+    identifier_case: IdentifierCase,
+    dictionary_columns: Vec<String>,
+    validate_batches: bool,
+    insert_method: InsertMethod,
+    schema_drift: SchemaDriftPolicy,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    remote_explain: bool,
+    pushdown_policy: PushdownPolicy,
+    sample: Option<TableSample>,
+    bind_literals: bool,
+    query_context: Option<QueryContextMode>,
+    profiling: bool,
+    index_advisor: Option<Arc<IndexAdvisor>>,
 }
 
 impl DuckDBTableFactory {
@@ -521,7 +677,21 @@ impl DuckDBTableFactory {
     pub fn new(pool: Arc<DuckDbConnectionPool>) -> Self {
         Self {
             pool,
-            dialect: Arc::new(DuckDBDialect::new()),
+            dialect: duckdb_dialect(),
+            identifier_case: IdentifierCase::default(),
+            dictionary_columns: Vec::new(),
+            validate_batches: false,
+            insert_method: InsertMethod::default(),
+            schema_drift: SchemaDriftPolicy::default(),
+            spill_buffer: None,
+            memory_wait: None,
+            remote_explain: false,
+            pushdown_policy: PushdownPolicy::default(),
+            sample: None,
+            bind_literals: false,
+            query_context: None,
+            profiling: false,
+            index_advisor: None,
         }
     }
 
@@ -531,16 +701,192 @@ impl DuckDBTableFactory {
         self
     }
 
+    /// Customizes how scalar functions are unparsed on top of the current dialect, see
+    /// [`DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect_overrides(mut self, dialect_overrides: &DialectOverrides) -> Self {
+        self.dialect = dialect_overrides.dialect(self.dialect);
+        self
+    }
+
+    /// Shows the plan of the remote database for every scan in the `EXPLAIN` output, see
+    /// [`sql_provider_datafusion::SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(mut self, remote_explain: bool) -> Self {
+        self.remote_explain = remote_explain;
+        self
+    }
+
+    /// Sets what scans of the tables push down to the database, see [`PushdownPolicy`].
+    /// The tables are only federated if the policy allows it.
+    #[must_use]
+    pub fn with_pushdown_policy(mut self, pushdown_policy: PushdownPolicy) -> Self {
+        self.pushdown_policy = pushdown_policy;
+        self
+    }
+
+    /// Reads a sample of the rows of the tables, see
+    /// [`sql_provider_datafusion::SqlTable::with_sample`].
+    #[must_use]
+    pub fn with_sample(mut self, sample: Option<TableSample>) -> Self {
+        self.sample = sample;
+        self
+    }
+
+    /// Binds the string literals of scans as parameters instead of quoting them, see
+    /// [`sql_provider_datafusion::SqlTable::with_bind_literals`].
+    /// Queries that are federated inline their literals, so the tables aren't federated then.
+    #[must_use]
+    pub fn with_bind_literals(mut self, bind_literals: bool) -> Self {
+        self.bind_literals = bind_literals;
+        self
+    }
+
+    /// Passes the query context of the session to the database with the queries of the tables,
+    /// see [`sql_provider_datafusion::SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(mut self, query_context: Option<QueryContextMode>) -> Self {
+        self.query_context = query_context;
+        self
+    }
+
+    /// Captures the JSON profile of DuckDB for the scans of the tables, in their metrics, see
+    /// [`crate::sql::db_connection_pool::duckdbprofile::QueryProfile`].
+    /// The queries of the tables that are federated run outside of the scans, so they're only
+    /// profiled when the tables aren't federated.
+    #[must_use]
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        self
+    }
+
+    /// Counts the columns that the scans of the tables filter on in `index_advisor`, which
+    /// recommends indexes for [`Self::create_recommended_indexes`], see [`IndexAdvisor`].
+    #[must_use]
+    pub fn with_index_advisor(mut self, index_advisor: Option<Arc<IndexAdvisor>>) -> Self {
+        self.index_advisor = index_advisor;
+        self
+    }
+
+    /// Sets how table references are normalized before they are looked up.
+    /// Table functions such as `read_parquet('File.parquet')` are never normalized.
+    #[must_use]
+    pub fn with_identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+        self.identifier_case = identifier_case;
+        self
+    }
+
+    /// Returns the given string columns of the tables from [`Self::table_provider`] as
+    /// dictionary-encoded arrays, see [`crate::util::dictionary::dictionary_encode_schema`].
+    /// Read-write table providers always return plain strings, so they can be written to.
+    #[must_use]
+    pub fn with_dictionary_columns(mut self, columns: Vec<String>) -> Self {
+        self.dictionary_columns = columns;
+        self
+    }
+
+    /// Sets what scans of the tables do when the columns returned by the database no longer match
+    /// the schema the table was registered with, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(mut self, schema_drift: SchemaDriftPolicy) -> Self {
+        self.schema_drift = schema_drift;
+        self
+    }
+
+    /// Buffers the unread batches of scans of the tables, keeping up to `memory_batches` in memory
+    /// and the rest on disk, so slow consumers don't hold on to connections, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(mut self, memory_batches: Option<usize>) -> Self {
+        self.spill_buffer = memory_batches;
+        self
+    }
+
+    /// Waits up to `wait` for the memory pool to have the memory of every batch of scans of the
+    /// tables, see [`sql_provider_datafusion::SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(mut self, wait: Option<Duration>) -> Self {
+        self.memory_wait = wait;
+        self
+    }
+
+    /// Validates the batches written through [`Self::read_write_table_provider`] before they are
+    /// appended, see [`crate::util::validation::validate_batch`].
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    /// Sets how the batches written through [`Self::read_write_table_provider`] are loaded into
+    /// the table.
+    #[must_use]
+    pub fn with_insert_method(mut self, insert_method: InsertMethod) -> Self {
+        self.insert_method = insert_method;
+        self
+    }
+
+    fn normalize_table_reference(&self, table_reference: TableReference) -> TableReference {
+        if is_table_function(&table_reference) {
+            table_reference
+        } else {
+            self.identifier_case
+                .normalize_table_reference(table_reference)
+        }
+    }
+
     pub async fn table_provider(
         &self,
         table_reference: TableReference,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        self.read_provider(table_reference, None, &self.dictionary_columns)
+            .await
+    }
+
+    /// A read-only table of the rows of `query`, a DuckDB `SELECT`, named `table_reference` in the
+    /// SQL of its scans, see [`sql_provider_datafusion::SqlTable::from_query`].
+    pub async fn query_table_provider(
+        &self,
+        table_reference: TableReference,
+        query: &str,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        self.read_provider(table_reference, Some(query), &self.dictionary_columns)
+            .await
+    }
+
+    /// A read-only table of the view over `table_function`, a call of a DuckDB table function like
+    /// `read_csv_auto(?)` with `params` bound to its placeholders. The view is created on the pool,
+    /// and dropped when the pool is closed, see [`DuckDbConnectionPool::create_table_function_view`].
+    pub async fn table_function_view_provider(
+        &self,
+        name: Option<&str>,
+        table_function: &str,
+        params: &[ScalarValue],
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        let view = self
+            .pool
+            .create_table_function_view(name, table_function, params)?;
+        self.table_provider(view).await
+    }
+
+    async fn read_provider(
+        &self,
+        table_reference: TableReference,
+        query: Option<&str>,
+        dictionary_columns: &[String],
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
         let pool = Arc::clone(&self.pool);
-        let conn = Arc::clone(&pool).connect().await?;
         let dyn_pool: Arc<DynDuckDbConnectionPool> = pool;
+        let table_reference = self.normalize_table_reference(table_reference);
 
-        let schema = get_schema(conn, &table_reference).await?;
-        let (tbl_ref, cte) = if is_table_function(&table_reference) {
+        let schema = match query {
+            Some(query) => sql_provider_datafusion::get_query_schema(&dyn_pool, query).await?,
+            None => {
+                let conn = Arc::clone(&dyn_pool).connect().await?;
+                get_schema(conn, &table_reference).await?
+            }
+        };
+        let (tbl_ref, cte) = if query.is_none() && is_table_function(&table_reference) {
             let tbl_ref_view = create_table_function_view_name(&table_reference);
             (
                 tbl_ref_view.clone(),
@@ -553,17 +899,36 @@ impl DuckDBTableFactory {
             (table_reference.clone(), None)
         };
 
-        let table_provider = Arc::new(DuckDBTable::new_with_schema(
+        let mut table_provider = DuckDBTable::new_with_schema(
             &dyn_pool,
             schema,
             tbl_ref,
             cte,
-            Some(self.dialect.clone()),
-        ));
+            Some(self.identifier_case.dialect(self.dialect.clone())),
+        )
+        .with_dictionary_columns(dictionary_columns)
+        .with_schema_drift(self.schema_drift)
+        .with_spill_buffer(self.spill_buffer)
+        .with_memory_wait(self.memory_wait)
+        .with_remote_explain(self.remote_explain)
+        .with_pushdown_policy(self.pushdown_policy.clone())
+        .with_sample(self.sample)
+        .with_bind_literals(self.bind_literals)
+        .with_query_context(self.query_context.clone())
+        .with_profiling(self.profiling)
+        .with_index_advisor(self.index_advisor.clone());
+        if let Some(query) = query {
+            table_provider = table_provider.with_query(query);
+        }
+        let table_provider = Arc::new(table_provider);
 
         #[cfg(feature = "duckdb-federation")]
         let table_provider: Arc<dyn TableProvider> =
-            Arc::new(table_provider.create_federated_table_provider()?);
+            if self.pushdown_policy.allows_federation() && !self.bind_literals {
+                Arc::new(table_provider.create_federated_table_provider()?)
+            } else {
+                table_provider
+            };
 
         Ok(table_provider)
     }
@@ -572,7 +937,10 @@ impl DuckDBTableFactory {
         &self,
         table_reference: TableReference,
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
-        let read_provider = Self::table_provider(self, table_reference.clone()).await?;
+        let table_reference = self.normalize_table_reference(table_reference);
+        let read_provider = self
+            .read_provider(table_reference.clone(), None, &[])
+            .await?;
         let schema = read_provider.schema();
 
         let table_name = RelationName::from(table_reference);
@@ -580,10 +948,111 @@ impl DuckDBTableFactory {
         let table_writer_builder = DuckDBTableWriterBuilder::new()
             .with_read_provider(read_provider)
             .with_pool(Arc::clone(&self.pool))
-            .with_table_definition(table_definition);
+            .with_table_definition(table_definition)
+            .with_batch_validation(self.validate_batches)
+            .with_insert_method(self.insert_method);
 
         Ok(Arc::new(table_writer_builder.build()?))
     }
+
+    /// Creates the `declared` indexes of the table and the ones that the index advisor recommends
+    /// on the columns that its scans filter on the most, see [`IndexAdvisor::recommend`], unless
+    /// they exist. Indexes are cheaper to build over the loaded rows than to update with each
+    /// insert, so this is called once a load of the table completes.
+    ///
+    /// Returns the indexes of the table, the declared ones first.
+    pub fn create_recommended_indexes(
+        &self,
+        table_reference: TableReference,
+        declared: &[(ColumnReference, IndexType)],
+    ) -> Result<Vec<(ColumnReference, IndexType)>, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self.normalize_table_reference(table_reference);
+        let indexes = match &self.index_advisor {
+            Some(index_advisor) => index_advisor.recommend(&table_reference, declared),
+            None => declared.to_vec(),
+        };
+        if indexes.is_empty() {
+            return Ok(indexes);
+        }
+
+        let conn = Arc::clone(&self.pool).connect_sync()?;
+        let conn = conn.as_sync().ok_or("DuckDB connections are synchronous")?;
+        for (columns, index_type) in &indexes {
+            let mut index_builder =
+                IndexBuilder::new(&table_reference.to_string(), columns.iter().collect());
+            if *index_type == IndexType::Unique {
+                index_builder = index_builder.unique();
+            }
+            let sql = index_builder.build_postgres();
+            tracing::debug!("Creating index: {sql}");
+            conn.execute(&sql, &[])?;
+        }
+        Ok(indexes)
+    }
+
+    /// Writes the rows of `source` to the Parquet files at `destination`, see [`export`].
+    ///
+    /// Returns the number of rows written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options don't apply to the destination, or if the rows can't be
+    /// written.
+    pub fn export_parquet(
+        &self,
+        source: &export::ExportSource,
+        destination: &str,
+        options: &export::ParquetExportOptions,
+    ) -> export::Result<u64> {
+        let source = match source {
+            export::ExportSource::Table(table_reference) => {
+                export::ExportSource::Table(self.normalize_table_reference(table_reference.clone()))
+            }
+            export::ExportSource::Query(_) => source.clone(),
+        };
+        export::export_parquet(&self.pool, &source, destination, options)
+    }
+
+    /// Checks whether the table can be read, and written to if `writable`, by running statements
+    /// against the table that match no rows. Table functions and databases opened with
+    /// [`AccessMode::ReadOnly`] can't be written to.
+    pub async fn validate_permissions(
+        &self,
+        table_reference: TableReference,
+        writable: bool,
+    ) -> Result<PermissionReport, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self.normalize_table_reference(table_reference);
+        let conn = Arc::clone(&self.pool).connect().await?;
+        let table = if is_table_function(&table_reference) {
+            table_reference.table().to_string()
+        } else {
+            let dialect = self.identifier_case.dialect(self.dialect.clone());
+            quote_table_reference(&table_reference, dialect.as_ref())
+        };
+
+        Ok(probe_permissions(conn, table_reference, &table, writable).await)
+    }
+}
+
+/// `value` as a string literal of DuckDB.
+fn quote_string(value: &str) -> String {
+    ast::Value::SingleQuotedString(value.to_string()).to_string()
+}
+
+/// `identifier` quoted for DuckDB.
+fn quote_identifier(identifier: &str) -> String {
+    ast::Ident::with_quote('"', identifier).to_string()
+}
+
+/// The type of the DuckDB secret for the storage of `location`, if it is in object storage.
+fn secret_type(location: &str) -> Option<&'static str> {
+    let (scheme, _) = location.split_once("://")?;
+    match scheme {
+        "s3" | "s3a" => Some("S3"),
+        "gs" | "gcs" => Some("GCS"),
+        "az" | "azure" | "abfss" => Some("AZURE"),
+        _ => None,
+    }
 }
 
 /// For a [`TableReference`] that is a table function, create a name for a view on the original [`TableReference`]
@@ -787,6 +1256,50 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_create_with_table_pool() {
+        let schema = Schema::new(vec![Field::new("dummy", DataType::Int32, false)]);
+        let pool = Arc::new(
+            DuckDbConnectionPool::new_memory().expect("DuckDB connection pool to be created"),
+        );
+        let factory = DuckDBTableProviderFactory::new(duckdb::AccessMode::ReadWrite)
+            .with_table_pool("test_table", Arc::clone(&pool));
+        let ctx = SessionContext::new();
+        let cmd = CreateExternalTable {
+            schema: Arc::new(schema.to_dfschema().expect("to df schema")),
+            name: TableReference::bare("test_table"),
+            location: "".to_string(),
+            file_type: "".to_string(),
+            table_partition_cols: vec![],
+            if_not_exists: false,
+            definition: None,
+            order_exprs: vec![],
+            unbounded: false,
+            options: HashMap::from([("mode".to_string(), "memory".to_string())]),
+            constraints: Constraints::empty(),
+            column_defaults: HashMap::new(),
+            temporary: false,
+        };
+
+        factory
+            .create(&ctx.state(), &cmd)
+            .await
+            .expect("table provider created");
+
+        // the table is created in the database of the pool, not in the memory instance
+        let mut conn_box = pool.connect_sync().expect("to get connection");
+        let conn = DuckDB::duckdb_conn(&mut conn_box).expect("to get DuckDB connection");
+        let tables = conn
+            .conn
+            .query_row(
+                "SELECT count(*) FROM information_schema.tables WHERE table_name = 'test_table'",
+                [],
+                |row| row.get::<usize, i64>(0),
+            )
+            .expect("to query tables");
+        assert_eq!(tables, 1);
+    }
+
     #[tokio::test]
     async fn test_create_with_temp_directory() {
         let table_name = TableReference::bare("test_table_temp_dir");
@@ -997,4 +1510,71 @@ pub(crate) mod tests {
             assert_eq!(e.to_string(), "External error: Query execution failed.\nInvalid Input Error: Failed to cast value: Could not convert string 'invalid' to BOOL\nFor details, refer to the DuckDB manual: https://duckdb.org/docs/");
         }
     }
+
+    #[tokio::test]
+    async fn test_validate_permissions() {
+        let pool = Arc::new(
+            DuckDbConnectionPool::new_memory().expect("DuckDB connection pool to be created"),
+        );
+        let conn = Arc::clone(&pool)
+            .connect_sync()
+            .expect("DuckDB connection should be established");
+        conn.as_sync()
+            .expect("DuckDB connection is sync")
+            .execute("CREATE TABLE permissions_test (id INTEGER)", &[])
+            .expect("DuckDB table should be created");
+
+        let factory = DuckDBTableFactory::new(pool);
+        let report = factory
+            .validate_permissions(TableReference::bare("permissions_test"), true)
+            .await
+            .expect("permissions validated");
+        assert!(report.is_granted());
+        assert_eq!(report.checks.len(), 2);
+
+        let report = factory
+            .validate_permissions(TableReference::bare("missing_table"), false)
+            .await
+            .expect("permissions validated");
+        assert_eq!(
+            report.missing(),
+            vec![crate::sql::permissions::Privilege::Select]
+        );
+        assert!(report.ensure_granted().is_err());
+    }
+
+    #[cfg(feature = "duckdb-federation")]
+    #[tokio::test]
+    async fn test_pushdown_policy_disables_federation() {
+        use crate::sql::pushdown::PushdownPolicy;
+        use datafusion_federation::FederatedTableProviderAdaptor;
+
+        let pool = Arc::new(
+            DuckDbConnectionPool::new_memory().expect("DuckDB connection pool to be created"),
+        );
+        let conn = Arc::clone(&pool)
+            .connect_sync()
+            .expect("DuckDB connection should be established");
+        conn.as_sync()
+            .expect("DuckDB connection is sync")
+            .execute("CREATE TABLE pushdown_test (id INTEGER)", &[])
+            .expect("DuckDB table should be created");
+
+        let is_federated = |policy: PushdownPolicy| {
+            let factory = DuckDBTableFactory::new(Arc::clone(&pool)).with_pushdown_policy(policy);
+            async move {
+                factory
+                    .table_provider(TableReference::bare("pushdown_test"))
+                    .await
+                    .expect("table provider created")
+                    .as_any()
+                    .downcast_ref::<FederatedTableProviderAdaptor>()
+                    .is_some()
+            }
+        };
+
+        assert!(is_federated(PushdownPolicy::new()).await);
+        assert!(!is_federated(PushdownPolicy::new().with_joins(false)).await);
+        assert!(!is_federated(PushdownPolicy::new().with_aggregates(false)).await);
+    }
 }
@@ -1,6 +1,8 @@
 use crate::sql::arrow_sql_gen::statement::IndexBuilder;
+use crate::sql::column_expressions::ColumnExpressions;
 use crate::sql::db_connection_pool::dbconnection::duckdbconn::DuckDbConnection;
 use crate::sql::db_connection_pool::duckdbpool::DuckDbConnectionPool;
+use crate::util::dedup;
 use crate::util::on_conflict::OnConflict;
 use arrow::{array::RecordBatch, datatypes::SchemaRef};
 use datafusion::common::utils::quote_identifier;
@@ -42,7 +44,7 @@ impl From<TableReference> for RelationName {
     }
 }
 
-/// A table definition, which includes the table name, schema, constraints, and indexes.
+/// A table definition, which includes the table name, schema, constraints, indexes, and column defaults.
 /// This is used to store the definition of a table for a dataset, and can be re-used to create one or more tables (like internal data tables).
 #[derive(Debug, Clone, PartialEq)]
 pub struct TableDefinition {
@@ -50,6 +52,7 @@ pub struct TableDefinition {
     schema: SchemaRef,
     constraints: Option<Constraints>,
     indexes: Vec<(ColumnReference, IndexType)>,
+    column_expressions: ColumnExpressions,
 }
 
 impl TableDefinition {
@@ -60,6 +63,7 @@ impl TableDefinition {
             schema,
             constraints: None,
             indexes: Vec::new(),
+            column_expressions: ColumnExpressions::default(),
         }
     }
 
@@ -75,6 +79,20 @@ impl TableDefinition {
         self
     }
 
+    /// Sets the column defaults of the tables created from this definition. DuckDB only supports
+    /// virtual generated columns, which can't be added to a table that already exists, so generated
+    /// columns are rejected by [`super::DuckDBTableProviderFactory`] instead.
+    #[must_use]
+    pub(crate) fn with_column_expressions(mut self, column_expressions: ColumnExpressions) -> Self {
+        self.column_expressions = column_expressions;
+        self
+    }
+
+    #[must_use]
+    pub fn column_expressions(&self) -> &ColumnExpressions {
+        &self.column_expressions
+    }
+
     #[must_use]
     pub fn name(&self) -> &RelationName {
         &self.name
@@ -266,6 +284,25 @@ impl TableManager {
         tx.execute(&create_stmt, [])
             .context(super::UnableToCreateDuckDBTableSnafu)?;
 
+        // the statement is generated by DuckDB from the Arrow schema, so the defaults are set afterwards
+        for field in self.table_definition.schema.fields() {
+            let Some(default) = self
+                .table_definition
+                .column_expressions
+                .default_sql(field.name())
+            else {
+                continue;
+            };
+            let sql = format!(
+                r#"ALTER TABLE "{table_name}" ALTER COLUMN {column} SET DEFAULT ({default})"#,
+                table_name = self.table_name(),
+                column = quote_identifier(field.name()),
+            );
+            tracing::debug!("{sql}");
+            tx.execute(&sql, [])
+                .context(super::UnableToCreateDuckDBTableSnafu)?;
+        }
+
         Ok(())
     }
 
@@ -708,6 +745,33 @@ impl ViewCreator {
         Ok(rows as u64)
     }
 
+    /// Inserts the rows of this view that don't already exist in the target table, based on `dedup_columns`.
+    pub(crate) fn insert_into_anti_join(
+        &self,
+        table: &TableManager,
+        tx: &Transaction<'_>,
+        dedup_columns: &[String],
+    ) -> super::Result<u64> {
+        let table_name = format!(r#""{}""#, table.table_name());
+        let view_name = format!(r#""{}""#, self.name);
+        let not_exists = dedup::not_exists_predicate(
+            &table_name,
+            &view_name,
+            &table.table_definition.schema,
+            dedup_columns,
+            "IS NOT DISTINCT FROM",
+        );
+        let insert_sql =
+            format!("INSERT INTO {table_name} SELECT * FROM {view_name} WHERE {not_exists}");
+        tracing::debug!("{insert_sql}");
+
+        let rows = tx
+            .execute(&insert_sql, [])
+            .context(super::UnableToInsertToDuckDBTableSnafu)?;
+
+        Ok(rows as u64)
+    }
+
     pub(crate) fn drop(&self, tx: &Transaction<'_>) -> super::Result<()> {
         // drop this view
         tx.execute(
@@ -1569,6 +1633,55 @@ pub(crate) mod tests {
         tx.rollback().expect("should rollback transaction");
     }
 
+    #[tokio::test]
+    async fn test_table_creator_column_defaults() {
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let column_expressions = ColumnExpressions::new()
+            .with_default(
+                "name",
+                datafusion::prelude::lit("unknown"),
+                &datafusion::sql::unparser::dialect::DuckDBDialect::new(),
+            )
+            .expect("default unparsed");
+        let table_definition = Arc::new(
+            TableDefinition::new(
+                RelationName::new("test_table_defaults"),
+                get_basic_table_definition().schema(),
+            )
+            .with_column_expressions(column_expressions),
+        );
+
+        let mut pool_conn = Arc::clone(&pool).connect_sync().expect("to get connection");
+        let conn = pool_conn
+            .as_any_mut()
+            .downcast_mut::<DuckDbConnection>()
+            .expect("to downcast to duckdb connection");
+        let tx = conn
+            .get_underlying_conn_mut()
+            .transaction()
+            .expect("should begin transaction");
+
+        let table_creator = TableManager::new(Arc::clone(&table_definition))
+            .with_internal(false)
+            .expect("to create table creator");
+        table_creator
+            .create_table(Arc::clone(&pool), &tx)
+            .expect("to create table");
+
+        tx.execute(r#"INSERT INTO "test_table_defaults" (id) VALUES (1)"#, [])
+            .expect("to insert a row without a name");
+        let name: String = tx
+            .query_row(r#"SELECT name FROM "test_table_defaults""#, [], |r| {
+                r.get(0)
+            })
+            .expect("to read the name");
+        assert_eq!(name, "unknown");
+
+        tx.rollback().expect("should rollback transaction");
+    }
+
     #[tokio::test]
     async fn test_internal_tables_exclude_subsets_of_other_tables() {
         let _guard = init_tracing(None);
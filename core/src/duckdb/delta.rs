@@ -0,0 +1,269 @@
+//! Delta Lake tables, read through the `delta` extension of DuckDB.
+//!
+//! The table is attached to a DuckDB database, from a local path or from object storage, and read
+//! like the other DuckDB tables, at its latest version or at an earlier one:
+//!
+//! ```rust,ignore
+//! let orders = DeltaTableProviderBuilder::new("s3://bucket/delta/orders")
+//!     .with_credential_chain(true)
+//!     .with_version(DeltaVersion::Version(42))
+//!     .build()
+//!     .await?;
+//! ctx.register_table("orders", orders)?;
+//! ```
+
+use std::sync::Arc;
+
+use datafusion::{datasource::TableProvider, sql::TableReference};
+use snafu::prelude::*;
+
+use super::{quote_identifier, quote_string, secret_type, DuckDBTableFactory};
+use crate::sql::db_connection_pool::{
+    dbconnection::GenericError, duckdbpool::DuckDbConnectionPoolBuilder,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to create the DuckDB database of the Delta table.\n{source}"))]
+    UnableToCreatePool { source: GenericError },
+
+    #[snafu(display("Unable to attach the Delta table at '{location}'.\n{source}\nEnsure the location is a Delta table and the credentials of its storage are configured."))]
+    UnableToAttachTable {
+        location: String,
+        source: GenericError,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The version of a Delta table that its table provider reads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DeltaVersion {
+    /// The latest version of the table, when each scan runs.
+    #[default]
+    Latest,
+    /// The version with this number.
+    Version(i64),
+    /// The version that was the latest at this timestamp, e.g. `2024-06-01 12:00:00`.
+    Timestamp(String),
+}
+
+impl DeltaVersion {
+    /// The `AT` clause of DuckDB that reads the version.
+    fn at_clause(&self) -> Option<String> {
+        match self {
+            Self::Latest => None,
+            Self::Version(version) => Some(format!("AT (VERSION => {version})")),
+            Self::Timestamp(timestamp) => Some(format!(
+                "AT (TIMESTAMP => CAST({} AS TIMESTAMP))",
+                quote_string(timestamp)
+            )),
+        }
+    }
+}
+
+/// Builds the table provider of a Delta table, see the [module documentation](self).
+pub struct DeltaTableProviderBuilder {
+    location: String,
+    name: Option<String>,
+    version: DeltaVersion,
+    credential_chain: bool,
+    table_factory: Option<DuckDBTableFactory>,
+}
+
+impl std::fmt::Debug for DeltaTableProviderBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeltaTableProviderBuilder")
+            .field("location", &self.location)
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .field("credential_chain", &self.credential_chain)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeltaTableProviderBuilder {
+    /// The Delta table at `location`, a local path or a URL of object storage like
+    /// `s3://bucket/path`.
+    #[must_use]
+    pub fn new(location: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            name: None,
+            version: DeltaVersion::Latest,
+            credential_chain: false,
+            table_factory: None,
+        }
+    }
+
+    /// The name that the table is attached as, which is the table in the SQL of its scans. By
+    /// default, the last part of the location.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Reads the table at `version` instead of its latest version.
+    #[must_use]
+    pub fn with_version(mut self, version: DeltaVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Reads the storage of the table with the credentials of the environment, e.g. the AWS
+    /// credentials chain for `s3://` locations. Other credentials are configured by creating a
+    /// secret on the pool of [`Self::with_table_factory`].
+    #[must_use]
+    pub fn with_credential_chain(mut self, credential_chain: bool) -> Self {
+        self.credential_chain = credential_chain;
+        self
+    }
+
+    /// Attaches the table to the database of the pool of `table_factory` and reads it with its
+    /// settings, instead of a new in-memory database.
+    #[must_use]
+    pub fn with_table_factory(mut self, table_factory: DuckDBTableFactory) -> Self {
+        self.table_factory = Some(table_factory);
+        self
+    }
+
+    /// The name that the table is attached as.
+    #[must_use]
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let name = self
+            .location
+            .trim_end_matches('/')
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if name.is_empty() {
+            "delta_table".to_string()
+        } else {
+            name
+        }
+    }
+
+    /// The statements that load the extension and attach the table.
+    fn setup_statements(&self) -> Vec<String> {
+        let name = self.name();
+        let mut statements = vec!["INSTALL delta".to_string(), "LOAD delta".to_string()];
+        if self.credential_chain {
+            if let Some(secret_type) = secret_type(&self.location) {
+                statements.push(format!(
+                    "CREATE OR REPLACE SECRET {} (TYPE {secret_type}, PROVIDER credential_chain)",
+                    quote_identifier(&format!("{name}_secret"))
+                ));
+            }
+        }
+        statements.push(format!(
+            "ATTACH IF NOT EXISTS {} AS {} (TYPE delta)",
+            quote_string(&self.location),
+            quote_identifier(&name)
+        ));
+        statements
+    }
+
+    fn attach(&self, table_factory: &DuckDBTableFactory) -> Result<(), GenericError> {
+        let conn = Arc::clone(&table_factory.pool).connect_sync()?;
+        let conn = conn.as_sync().ok_or("DuckDB connections are synchronous")?;
+        for statement in self.setup_statements() {
+            conn.execute(&statement, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Attaches the table and creates its table provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the extension can't be installed, the table can't be attached or its
+    /// schema can't be read.
+    pub async fn build(
+        mut self,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        let table_factory = match self.table_factory.take() {
+            Some(table_factory) => table_factory,
+            None => DuckDBTableFactory::new(Arc::new(
+                DuckDbConnectionPoolBuilder::memory()
+                    .build()
+                    .context(UnableToCreatePoolSnafu)?,
+            )),
+        };
+
+        self.attach(&table_factory)
+            .map_err(|source| Error::UnableToAttachTable {
+                location: self.location.clone(),
+                source,
+            })?;
+
+        let name = self.name();
+        tracing::debug!(
+            "Attached the Delta table at '{}' as '{name}'",
+            self.location
+        );
+        match self.version.at_clause() {
+            None => {
+                table_factory
+                    .table_provider(TableReference::bare(name))
+                    .await
+            }
+            Some(at_clause) => {
+                let query = format!("SELECT * FROM {} {at_clause}", quote_identifier(&name));
+                table_factory
+                    .query_table_provider(TableReference::bare(name), &query)
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_statements() {
+        let builder =
+            DeltaTableProviderBuilder::new("s3://bucket/delta/orders/").with_credential_chain(true);
+        assert_eq!(builder.name(), "orders");
+        assert_eq!(
+            builder.setup_statements()[2..],
+            [
+                r#"CREATE OR REPLACE SECRET "orders_secret" (TYPE S3, PROVIDER credential_chain)"#,
+                r#"ATTACH IF NOT EXISTS 's3://bucket/delta/orders/' AS "orders" (TYPE delta)"#,
+            ]
+        );
+
+        let builder = DeltaTableProviderBuilder::new("./data/sales-2024").with_name("sales");
+        assert_eq!(
+            builder.setup_statements()[2..],
+            [r#"ATTACH IF NOT EXISTS './data/sales-2024' AS "sales" (TYPE delta)"#]
+        );
+        assert_eq!(
+            DeltaTableProviderBuilder::new("./data/sales-2024").name(),
+            "sales_2024"
+        );
+    }
+
+    #[test]
+    fn test_version_at_clause() {
+        assert_eq!(DeltaVersion::Latest.at_clause(), None);
+        assert_eq!(
+            DeltaVersion::Version(3).at_clause().as_deref(),
+            Some("AT (VERSION => 3)")
+        );
+        assert_eq!(
+            DeltaVersion::Timestamp("2024-06-01".to_string())
+                .at_clause()
+                .as_deref(),
+            Some("AT (TIMESTAMP => CAST('2024-06-01' AS TIMESTAMP))")
+        );
+    }
+}
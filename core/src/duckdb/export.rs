@@ -0,0 +1,298 @@
+//! Exporting DuckDB tables and query results to Parquet files, on a local path or in object
+//! storage, with `COPY ... TO ... (FORMAT PARQUET)`.
+//!
+//! An export can be split into one directory per value of its partition columns, in the Hive
+//! layout that the lake engines read:
+//!
+//! ```rust,ignore
+//! let rows = factory.export_parquet(
+//!     &ExportSource::Table(TableReference::bare("orders")),
+//!     "s3://bucket/lake/orders",
+//!     &ParquetExportOptions::default()
+//!         .with_partition_by(vec!["region".to_string(), "order_date".to_string()])
+//!         .with_compression(ParquetCompression::Zstd)
+//!         .with_existing_files(ExistingFiles::Overwrite)
+//!         .with_credential_chain(true),
+//! )?;
+//! ```
+
+use std::sync::Arc;
+
+use datafusion::sql::{unparser::dialect::DuckDBDialect, TableReference};
+use snafu::prelude::*;
+
+use super::{quote_identifier, quote_string, secret_type};
+use crate::sql::{
+    db_connection_pool::{dbconnection::GenericError, duckdbpool::DuckDbConnectionPool},
+    dml::quote_table_reference,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to export to the Parquet files at '{destination}'.\n{source}\nEnsure the destination is writable and the credentials of its storage are configured."))]
+    UnableToExport {
+        destination: String,
+        source: GenericError,
+    },
+
+    #[snafu(display("The Parquet export to '{destination}' has no partition columns, so it can only replace a single file.\nPartition the export to keep or append to the files of an existing directory."))]
+    UnpartitionedExistingFiles { destination: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The rows that are exported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportSource {
+    /// All the rows of a table of the database.
+    Table(TableReference),
+    /// The rows of a query of the database.
+    Query(String),
+}
+
+impl ExportSource {
+    /// The source in the `COPY` statement.
+    fn to_sql(&self) -> String {
+        match self {
+            Self::Table(table_reference) => {
+                quote_table_reference(table_reference, &DuckDBDialect::new())
+            }
+            Self::Query(query) => format!("({})", query.trim().trim_end_matches(';')),
+        }
+    }
+}
+
+/// The compression codec of the Parquet files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParquetCompression {
+    Uncompressed,
+    #[default]
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+    Brotli,
+}
+
+impl ParquetCompression {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Uncompressed => "uncompressed",
+            Self::Snappy => "snappy",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+            Self::Brotli => "brotli",
+        }
+    }
+}
+
+/// What a partitioned export does with the files already in its destination directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingFiles {
+    /// Fails if the directory isn't empty.
+    #[default]
+    Fail,
+    /// Removes the files of the directory before writing the new ones.
+    Overwrite,
+    /// Writes the new files next to the existing ones, with names that don't collide with them.
+    Append,
+}
+
+/// How a table or a query result is written to Parquet files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParquetExportOptions {
+    partition_by: Vec<String>,
+    compression: ParquetCompression,
+    row_group_size: Option<usize>,
+    existing_files: ExistingFiles,
+    credential_chain: bool,
+}
+
+impl ParquetExportOptions {
+    /// Writes the rows under one directory per value of `columns`, e.g. `region=eu/`, instead of
+    /// a single file. The destination is then a directory.
+    #[must_use]
+    pub fn with_partition_by(mut self, columns: Vec<String>) -> Self {
+        self.partition_by = columns;
+        self
+    }
+
+    /// The compression of the files, Snappy by default.
+    #[must_use]
+    pub fn with_compression(mut self, compression: ParquetCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// The number of rows of the row groups of the files, or the default of DuckDB with `None`.
+    #[must_use]
+    pub fn with_row_group_size(mut self, row_group_size: Option<usize>) -> Self {
+        self.row_group_size = row_group_size;
+        self
+    }
+
+    /// What a partitioned export does with the files already in its destination. An export that
+    /// isn't partitioned always replaces its destination file.
+    #[must_use]
+    pub fn with_existing_files(mut self, existing_files: ExistingFiles) -> Self {
+        self.existing_files = existing_files;
+        self
+    }
+
+    /// Writes to object storage with the credentials of the environment, e.g. the AWS
+    /// credentials chain for `s3://` destinations. Other credentials are configured by creating a
+    /// secret on the pool of the export.
+    #[must_use]
+    pub fn with_credential_chain(mut self, credential_chain: bool) -> Self {
+        self.credential_chain = credential_chain;
+        self
+    }
+
+    /// The statements that load the extension of the object storage of `destination` and write
+    /// the rows of `source` to it.
+    fn statements(&self, source: &ExportSource, destination: &str) -> Result<Vec<String>> {
+        let mut statements = Vec::new();
+        if let Some(secret_type) = secret_type(destination) {
+            statements.push("INSTALL httpfs".to_string());
+            statements.push("LOAD httpfs".to_string());
+            if self.credential_chain {
+                statements.push(format!(
+                    "CREATE OR REPLACE SECRET {} (TYPE {secret_type}, PROVIDER credential_chain)",
+                    quote_identifier("parquet_export_secret")
+                ));
+            }
+        }
+
+        let mut options = vec![
+            "FORMAT PARQUET".to_string(),
+            format!("COMPRESSION {}", self.compression.as_str()),
+        ];
+        if let Some(row_group_size) = self.row_group_size {
+            options.push(format!("ROW_GROUP_SIZE {row_group_size}"));
+        }
+        if self.partition_by.is_empty() {
+            ensure!(
+                self.existing_files == ExistingFiles::Fail,
+                UnpartitionedExistingFilesSnafu { destination }
+            );
+        } else {
+            options.push(format!(
+                "PARTITION_BY ({})",
+                self.partition_by
+                    .iter()
+                    .map(|column| quote_identifier(column))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            match self.existing_files {
+                ExistingFiles::Fail => {}
+                ExistingFiles::Overwrite => options.push("OVERWRITE true".to_string()),
+                ExistingFiles::Append => options.push("APPEND true".to_string()),
+            }
+        }
+
+        statements.push(format!(
+            "COPY {} TO {} ({})",
+            source.to_sql(),
+            quote_string(destination),
+            options.join(", ")
+        ));
+        Ok(statements)
+    }
+}
+
+/// Writes the rows of `source`, read from the database of `pool`, to the Parquet files at
+/// `destination`, and returns the number of rows written.
+///
+/// # Errors
+///
+/// Returns an error if the options don't apply to the destination, or if the rows can't be
+/// written.
+pub fn export_parquet(
+    pool: &Arc<DuckDbConnectionPool>,
+    source: &ExportSource,
+    destination: &str,
+    options: &ParquetExportOptions,
+) -> Result<u64> {
+    let statements = options.statements(source, destination)?;
+    let export_error = |source| Error::UnableToExport {
+        destination: destination.to_string(),
+        source,
+    };
+
+    let conn = Arc::clone(pool).connect_sync().map_err(export_error)?;
+    let Some(conn) = conn.as_sync() else {
+        return Err(export_error("DuckDB connections are synchronous".into()));
+    };
+    let mut rows = 0;
+    for statement in &statements {
+        rows = conn.execute(statement, &[]).map_err(export_error)?;
+    }
+    tracing::debug!("Exported {rows} rows to the Parquet files at '{destination}'");
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::db_connection_pool::duckdbpool::DuckDbConnectionPoolBuilder;
+
+    #[test]
+    fn test_statements() -> Result<(), Box<dyn std::error::Error>> {
+        let source = ExportSource::Table(TableReference::partial("sales", "orders"));
+        let options = ParquetExportOptions::default()
+            .with_partition_by(vec!["region".to_string(), "order_date".to_string()])
+            .with_compression(ParquetCompression::Zstd)
+            .with_row_group_size(Some(100_000))
+            .with_existing_files(ExistingFiles::Overwrite)
+            .with_credential_chain(true);
+        assert_eq!(
+            options.statements(&source, "s3://bucket/lake/orders")?[2..],
+            [
+                r#"CREATE OR REPLACE SECRET "parquet_export_secret" (TYPE S3, PROVIDER credential_chain)"#,
+                r#"COPY "sales"."orders" TO 's3://bucket/lake/orders' (FORMAT PARQUET, COMPRESSION zstd, ROW_GROUP_SIZE 100000, PARTITION_BY ("region", "order_date"), OVERWRITE true)"#,
+            ]
+        );
+
+        let source = ExportSource::Query("SELECT * FROM orders WHERE total > 100;".to_string());
+        assert_eq!(
+            ParquetExportOptions::default().statements(&source, "./it's/orders.parquet")?,
+            [
+                "COPY (SELECT * FROM orders WHERE total > 100) TO './it''s/orders.parquet' (FORMAT PARQUET, COMPRESSION snappy)",
+            ]
+        );
+        assert!(matches!(
+            ParquetExportOptions::default()
+                .with_existing_files(ExistingFiles::Append)
+                .statements(&source, "orders.parquet"),
+            Err(Error::UnpartitionedExistingFiles { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_parquet() -> Result<(), Box<dyn std::error::Error>> {
+        let pool = Arc::new(DuckDbConnectionPoolBuilder::memory().build()?);
+        let conn = Arc::clone(&pool).connect_sync()?;
+        let conn = conn.as_sync().ok_or("DuckDB connections are synchronous")?;
+        conn.execute(
+            "CREATE TABLE orders AS SELECT i AS id, i % 2 AS region FROM range(10) t(i)",
+            &[],
+        )?;
+
+        let dir = tempfile::tempdir()?;
+        let destination = dir.path().join("orders");
+        let destination = destination.to_str().ok_or("non UTF-8 path")?;
+        let rows = export_parquet(
+            &pool,
+            &ExportSource::Table(TableReference::bare("orders")),
+            destination,
+            &ParquetExportOptions::default().with_partition_by(vec!["region".to_string()]),
+        )?;
+        assert_eq!(rows, 10);
+        assert!(dir.path().join("orders/region=0").is_dir());
+        assert!(dir.path().join("orders/region=1").is_dir());
+        Ok(())
+    }
+}
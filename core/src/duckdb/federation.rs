@@ -1,8 +1,8 @@
 use crate::sql::db_connection_pool::dbconnection::{get_schema, Error as DbError};
-use crate::sql::sql_provider_datafusion::{get_stream, to_execution_error};
+use crate::sql::sql_provider_datafusion::{get_stream_with_params, to_execution_error};
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::sql::unparser::dialect::Dialect;
-use datafusion_federation::sql::{SQLExecutor, SQLFederationProvider, SQLTableSource};
+use datafusion_federation::sql::{AstAnalyzer, SQLExecutor, SQLFederationProvider, SQLTableSource};
 use datafusion_federation::{FederatedTableProviderAdaptor, FederatedTableSource};
 use futures::TryStreamExt;
 use snafu::ResultExt;
@@ -58,15 +58,22 @@ impl<T, P> SQLExecutor for DuckDBTable<T, P> {
         self.base_table.dialect()
     }
 
+    fn ast_analyzer(&self) -> Option<AstAnalyzer> {
+        self.base_table.ast_analyzer()
+    }
+
     fn execute(
         &self,
         query: &str,
         schema: SchemaRef,
     ) -> DataFusionResult<SendableRecordBatchStream> {
-        let fut = get_stream(
+        let fut = get_stream_with_params(
             self.base_table.clone_pool(),
             format!("{cte} {query}", cte = get_cte(&self.table_functions)),
+            Vec::new(),
+            self.base_table.current_remote_context(),
             Arc::clone(&schema),
+            self.base_table.schema_drift(),
         );
 
         let stream = futures::stream::once(fut).try_flatten();
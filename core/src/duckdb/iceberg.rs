@@ -0,0 +1,394 @@
+//! The tables of an Iceberg REST catalog, read through the `iceberg` extension of DuckDB.
+//!
+//! The catalog is attached to an in-memory DuckDB database, with the credentials of the catalog
+//! in a DuckDB secret, so its namespaces are schemas and its tables are tables of the database:
+//!
+//! ```rust,ignore
+//! let catalog = IcebergRestCatalog::new("warehouse", "https://catalog.example.com")
+//!     .with_client_credentials("client_id", SecretString::from("client_secret"))
+//!     .with_oauth2_server_uri("https://catalog.example.com/v1/oauth/tokens");
+//! let factory = IcebergTableFactory::new(&catalog)?;
+//!
+//! ctx.register_table(
+//!     "orders",
+//!     factory
+//!         .table_provider(TableReference::partial("sales", "orders"), &IcebergSnapshot::Current)
+//!         .await?,
+//! )?;
+//! ```
+
+use std::sync::Arc;
+
+use datafusion::{
+    datasource::TableProvider,
+    sql::{unparser::dialect::DuckDBDialect, TableReference},
+};
+use secrecy::{ExposeSecret, SecretString};
+use snafu::prelude::*;
+
+use super::{quote_identifier, quote_string, DuckDBTableFactory};
+use crate::sql::{
+    db_connection_pool::{
+        dbconnection::GenericError,
+        duckdbpool::{DuckDbConnectionPool, DuckDbConnectionPoolBuilder},
+    },
+    dml::quote_table_reference,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to create the DuckDB database of the Iceberg catalog.\n{source}"))]
+    UnableToCreatePool { source: GenericError },
+
+    #[snafu(display("Unable to attach the Iceberg catalog '{warehouse}'.\n{source}\nEnsure the endpoint is reachable and the credentials of the catalog are valid."))]
+    UnableToAttachCatalog {
+        warehouse: String,
+        source: GenericError,
+    },
+
+    #[snafu(display("The Iceberg table '{table}' has no namespace.\nName the table with its namespace, e.g. namespace.table."))]
+    MissingNamespace { table: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The name that the catalog is attached as, unless it is set with
+/// [`IcebergRestCatalog::with_alias`].
+const DEFAULT_ALIAS: &str = "iceberg_catalog";
+
+/// An Iceberg REST catalog, and the credentials to read it with.
+#[derive(Debug, Clone)]
+pub struct IcebergRestCatalog {
+    warehouse: String,
+    endpoint: String,
+    alias: String,
+    credentials: Option<IcebergCredentials>,
+}
+
+#[derive(Debug, Clone)]
+enum IcebergCredentials {
+    /// OAuth2 client credentials, exchanged for a token by DuckDB.
+    Client {
+        client_id: String,
+        client_secret: SecretString,
+        oauth2_server_uri: Option<String>,
+        oauth2_scope: Option<String>,
+    },
+    /// A bearer token.
+    Token(SecretString),
+}
+
+impl IcebergRestCatalog {
+    /// The catalog of `warehouse` served at `endpoint`, e.g. `https://catalog.example.com`, which
+    /// is read without credentials.
+    #[must_use]
+    pub fn new(warehouse: impl Into<String>, endpoint: impl Into<String>) -> Self {
+        Self {
+            warehouse: warehouse.into(),
+            endpoint: endpoint.into(),
+            alias: DEFAULT_ALIAS.to_string(),
+            credentials: None,
+        }
+    }
+
+    /// Authenticates with the OAuth2 client credentials flow. The token is requested from the
+    /// endpoint of the catalog, unless another server is set with
+    /// [`Self::with_oauth2_server_uri`].
+    #[must_use]
+    pub fn with_client_credentials(
+        mut self,
+        client_id: impl Into<String>,
+        client_secret: SecretString,
+    ) -> Self {
+        self.credentials = Some(IcebergCredentials::Client {
+            client_id: client_id.into(),
+            client_secret,
+            oauth2_server_uri: None,
+            oauth2_scope: None,
+        });
+        self
+    }
+
+    /// The server that issues the OAuth2 tokens, if the catalog uses client credentials.
+    #[must_use]
+    pub fn with_oauth2_server_uri(mut self, uri: impl Into<String>) -> Self {
+        if let Some(IcebergCredentials::Client {
+            oauth2_server_uri, ..
+        }) = &mut self.credentials
+        {
+            *oauth2_server_uri = Some(uri.into());
+        }
+        self
+    }
+
+    /// The scope of the OAuth2 tokens, if the catalog uses client credentials.
+    #[must_use]
+    pub fn with_oauth2_scope(mut self, scope: impl Into<String>) -> Self {
+        if let Some(IcebergCredentials::Client { oauth2_scope, .. }) = &mut self.credentials {
+            *oauth2_scope = Some(scope.into());
+        }
+        self
+    }
+
+    /// Authenticates with a bearer token instead of client credentials.
+    #[must_use]
+    pub fn with_token(mut self, token: SecretString) -> Self {
+        self.credentials = Some(IcebergCredentials::Token(token));
+        self
+    }
+
+    /// The name of the DuckDB catalog that the Iceberg catalog is attached as, `iceberg_catalog`
+    /// by default, which prefixes the tables of the catalog in the SQL of their scans.
+    #[must_use]
+    pub fn with_alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = alias.into();
+        self
+    }
+
+    #[must_use]
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    fn secret_name(&self) -> String {
+        format!("{}_secret", self.alias)
+    }
+
+    /// The statements that load the extensions, create the secret of the credentials and attach
+    /// the catalog. They contain the credentials, so they are never logged.
+    fn setup_statements(&self) -> Vec<String> {
+        let mut statements = vec![
+            "INSTALL httpfs".to_string(),
+            "LOAD httpfs".to_string(),
+            "INSTALL iceberg".to_string(),
+            "LOAD iceberg".to_string(),
+        ];
+
+        let mut attach_options = vec![
+            "TYPE ICEBERG".to_string(),
+            format!("ENDPOINT {}", quote_string(&self.endpoint)),
+        ];
+        if let Some(credentials) = &self.credentials {
+            let mut secret_options = vec!["TYPE ICEBERG".to_string()];
+            match credentials {
+                IcebergCredentials::Client {
+                    client_id,
+                    client_secret,
+                    oauth2_server_uri,
+                    oauth2_scope,
+                } => {
+                    secret_options.push(format!("CLIENT_ID {}", quote_string(client_id)));
+                    secret_options.push(format!(
+                        "CLIENT_SECRET {}",
+                        quote_string(client_secret.expose_secret())
+                    ));
+                    if let Some(uri) = oauth2_server_uri {
+                        secret_options.push(format!("OAUTH2_SERVER_URI {}", quote_string(uri)));
+                    }
+                    if let Some(scope) = oauth2_scope {
+                        secret_options.push(format!("OAUTH2_SCOPE {}", quote_string(scope)));
+                    }
+                }
+                IcebergCredentials::Token(token) => {
+                    secret_options.push(format!("TOKEN {}", quote_string(token.expose_secret())));
+                }
+            }
+            statements.push(format!(
+                "CREATE OR REPLACE SECRET {} ({})",
+                quote_identifier(&self.secret_name()),
+                secret_options.join(", ")
+            ));
+            attach_options.push(format!("SECRET {}", quote_identifier(&self.secret_name())));
+        } else {
+            attach_options.push("AUTHORIZATION_TYPE 'none'".to_string());
+        }
+
+        statements.push(format!(
+            "ATTACH IF NOT EXISTS {} AS {} ({})",
+            quote_string(&self.warehouse),
+            quote_identifier(&self.alias),
+            attach_options.join(", ")
+        ));
+        statements
+    }
+}
+
+/// The snapshot of an Iceberg table that its table provider reads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum IcebergSnapshot {
+    /// The current snapshot of the table, when each scan runs.
+    #[default]
+    Current,
+    /// The snapshot with this ID.
+    Id(i64),
+    /// The snapshot that was current at this timestamp, e.g. `2024-06-01 12:00:00`.
+    AsOf(String),
+}
+
+impl IcebergSnapshot {
+    /// The `AT` clause of DuckDB that reads the snapshot, which needs a version of the `iceberg`
+    /// extension that supports time travel.
+    fn at_clause(&self) -> Option<String> {
+        match self {
+            Self::Current => None,
+            Self::Id(id) => Some(format!("AT (VERSION => {id})")),
+            Self::AsOf(timestamp) => Some(format!(
+                "AT (TIMESTAMP => CAST({} AS TIMESTAMP))",
+                quote_string(timestamp)
+            )),
+        }
+    }
+}
+
+/// Creates the table providers of the tables of an Iceberg REST catalog, see the
+/// [module documentation](self).
+pub struct IcebergTableFactory {
+    alias: String,
+    table_factory: DuckDBTableFactory,
+}
+
+impl std::fmt::Debug for IcebergTableFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IcebergTableFactory")
+            .field("alias", &self.alias)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IcebergTableFactory {
+    /// Attaches `catalog` to a new in-memory DuckDB database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the extensions can't be installed or the catalog can't be attached.
+    pub fn new(catalog: &IcebergRestCatalog) -> Result<Self> {
+        let pool = DuckDbConnectionPoolBuilder::memory()
+            .build()
+            .context(UnableToCreatePoolSnafu)?;
+        Self::from_pool(Arc::new(pool), catalog)
+    }
+
+    /// Attaches `catalog` to the database of `pool`, so that the Iceberg tables can be joined with
+    /// the other tables of the database in DuckDB.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the extensions can't be installed or the catalog can't be attached.
+    pub fn from_pool(
+        pool: Arc<DuckDbConnectionPool>,
+        catalog: &IcebergRestCatalog,
+    ) -> Result<Self> {
+        let attach_error = |source| Error::UnableToAttachCatalog {
+            warehouse: catalog.warehouse.clone(),
+            source,
+        };
+        let conn = Arc::clone(&pool).connect_sync().map_err(attach_error)?;
+        let Some(conn) = conn.as_sync() else {
+            return Err(attach_error("DuckDB connections are synchronous".into()));
+        };
+        for statement in catalog.setup_statements() {
+            conn.execute(&statement, &[]).map_err(attach_error)?;
+        }
+        tracing::debug!(
+            "Attached the Iceberg catalog '{}' at {} as '{}'",
+            catalog.warehouse,
+            catalog.endpoint,
+            catalog.alias
+        );
+
+        Ok(Self {
+            alias: catalog.alias.clone(),
+            table_factory: DuckDBTableFactory::new(pool),
+        })
+    }
+
+    /// Sets how the tables are read, e.g. with [`DuckDBTableFactory::with_pushdown_policy`]. The
+    /// factory must read the database of the pool that the catalog is attached to.
+    #[must_use]
+    pub fn with_table_factory(
+        mut self,
+        table_factory: impl FnOnce(DuckDBTableFactory) -> DuckDBTableFactory,
+    ) -> Self {
+        self.table_factory = table_factory(self.table_factory);
+        self
+    }
+
+    /// The table provider of the Iceberg table `namespace.table` at `snapshot`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table has no namespace, or if its schema can't be read.
+    pub async fn table_provider(
+        &self,
+        table_reference: TableReference,
+        snapshot: &IcebergSnapshot,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self.catalog_table_reference(&table_reference)?;
+        match snapshot.at_clause() {
+            None => self.table_factory.table_provider(table_reference).await,
+            Some(at_clause) => {
+                let table = quote_table_reference(&table_reference, &DuckDBDialect::new());
+                let name = TableReference::bare(table_reference.table());
+                self.table_factory
+                    .query_table_provider(name, &format!("SELECT * FROM {table} {at_clause}"))
+                    .await
+            }
+        }
+    }
+
+    /// `table_reference`, a table of the catalog named with its namespace, in the DuckDB catalog
+    /// that the Iceberg catalog is attached as.
+    fn catalog_table_reference(&self, table_reference: &TableReference) -> Result<TableReference> {
+        let namespace = table_reference.schema().context(MissingNamespaceSnafu {
+            table: table_reference.to_string(),
+        })?;
+        Ok(TableReference::full(
+            self.alias.as_str(),
+            namespace,
+            table_reference.table(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup_statements() {
+        let catalog = IcebergRestCatalog::new("warehouse", "https://catalog.example.com")
+            .with_client_credentials("client", SecretString::from("it's secret"))
+            .with_oauth2_server_uri("https://auth.example.com/token")
+            .with_alias("lake");
+        assert_eq!(
+            catalog.setup_statements()[4..],
+            [
+                r#"CREATE OR REPLACE SECRET "lake_secret" (TYPE ICEBERG, CLIENT_ID 'client', CLIENT_SECRET 'it''s secret', OAUTH2_SERVER_URI 'https://auth.example.com/token')"#,
+                r#"ATTACH IF NOT EXISTS 'warehouse' AS "lake" (TYPE ICEBERG, ENDPOINT 'https://catalog.example.com', SECRET "lake_secret")"#,
+            ]
+        );
+
+        let catalog = IcebergRestCatalog::new("warehouse", "http://localhost:8181");
+        assert_eq!(
+            catalog.setup_statements()[4..],
+            [
+                r#"ATTACH IF NOT EXISTS 'warehouse' AS "iceberg_catalog" (TYPE ICEBERG, ENDPOINT 'http://localhost:8181', AUTHORIZATION_TYPE 'none')"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_at_clause() {
+        assert_eq!(IcebergSnapshot::Current.at_clause(), None);
+        assert_eq!(
+            IcebergSnapshot::Id(42).at_clause().as_deref(),
+            Some("AT (VERSION => 42)")
+        );
+        assert_eq!(
+            IcebergSnapshot::AsOf("2024-06-01 12:00:00".to_string())
+                .at_clause()
+                .as_deref(),
+            Some("AT (TIMESTAMP => CAST('2024-06-01 12:00:00' AS TIMESTAMP))")
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Parquet and CSV files in object storage or on a local path, read by DataFusion or offloaded
+//! to DuckDB.
+//!
+//! The files are read by the listing table of DataFusion by default, which lists the files of
+//! the location with the object store registered for its URL on the runtime of the session. With
+//! [`ReadEngine::DuckDB`], the scans run in DuckDB with `read_parquet` or `read_csv` instead, so
+//! that DuckDB filters and aggregates the files and DataFusion only receives the result:
+//!
+//! ```rust,ignore
+//! let orders = ObjectStoreTableProviderBuilder::new("s3://bucket/lake/orders/")
+//!     .with_format(ObjectStoreFormat::Parquet)
+//!     .with_engine(ReadEngine::DuckDB)
+//!     .with_credential_chain(true)
+//!     .build(&ctx.state())
+//!     .await?;
+//! ctx.register_table("orders", orders)?;
+//! ```
+
+use std::sync::Arc;
+
+use datafusion::{
+    catalog::Session,
+    datasource::{
+        file_format::{csv::CsvFormat, parquet::ParquetFormat, FileFormat},
+        listing::{ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl},
+        TableProvider,
+    },
+    error::DataFusionError,
+    sql::TableReference,
+};
+use snafu::prelude::*;
+
+use super::{quote_identifier, quote_string, secret_type, DuckDBTableFactory};
+use crate::sql::db_connection_pool::{
+    dbconnection::GenericError, duckdbpool::DuckDbConnectionPoolBuilder,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The location '{location}' is not a valid URL or path.\n{source}"))]
+    InvalidLocation {
+        location: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Unable to infer the schema of the files at '{location}'.\n{source}\nEnsure the object store of the location is registered on the runtime of the session."))]
+    UnableToInferSchema {
+        location: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display(
+        "Unable to create the listing table of the files at '{location}'.\n{source}"
+    ))]
+    UnableToCreateListingTable {
+        location: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Unable to create the DuckDB database that reads the files.\n{source}"))]
+    UnableToCreatePool { source: GenericError },
+
+    #[snafu(display("Unable to configure DuckDB to read the files at '{location}'.\n{source}\nEnsure the credentials of the storage are configured."))]
+    UnableToConfigureStorage {
+        location: String,
+        source: GenericError,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The format of the files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ObjectStoreFormat {
+    #[default]
+    Parquet,
+    Csv {
+        has_header: bool,
+        delimiter: u8,
+    },
+}
+
+impl ObjectStoreFormat {
+    /// CSV files with a header and comma-separated values.
+    #[must_use]
+    pub fn csv() -> Self {
+        Self::Csv {
+            has_header: true,
+            delimiter: b',',
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Parquet => ".parquet",
+            Self::Csv { .. } => ".csv",
+        }
+    }
+
+    fn file_format(&self) -> Arc<dyn FileFormat> {
+        match self {
+            Self::Parquet => Arc::new(ParquetFormat::default()),
+            Self::Csv {
+                has_header,
+                delimiter,
+            } => Arc::new(
+                CsvFormat::default()
+                    .with_has_header(*has_header)
+                    .with_delimiter(*delimiter),
+            ),
+        }
+    }
+
+    /// The DuckDB table function that reads the files of `pattern`.
+    fn table_function(&self, pattern: &str) -> String {
+        match self {
+            Self::Parquet => format!("read_parquet({})", quote_string(pattern)),
+            Self::Csv {
+                has_header,
+                delimiter,
+            } => format!(
+                "read_csv({}, header = {has_header}, delim = {})",
+                quote_string(pattern),
+                quote_string(&char::from(*delimiter).to_string())
+            ),
+        }
+    }
+}
+
+/// The engine that reads the files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadEngine {
+    /// The listing table of DataFusion.
+    #[default]
+    DataFusion,
+    /// DuckDB, which runs the scans with their filters, projections and limits.
+    DuckDB,
+}
+
+/// Builds the table provider of the files at a location, see the
+/// [module documentation](self).
+pub struct ObjectStoreTableProviderBuilder {
+    location: String,
+    name: Option<String>,
+    format: ObjectStoreFormat,
+    engine: ReadEngine,
+    credential_chain: bool,
+    table_factory: Option<DuckDBTableFactory>,
+}
+
+impl std::fmt::Debug for ObjectStoreTableProviderBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectStoreTableProviderBuilder")
+            .field("location", &self.location)
+            .field("name", &self.name)
+            .field("format", &self.format)
+            .field("engine", &self.engine)
+            .field("credential_chain", &self.credential_chain)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ObjectStoreTableProviderBuilder {
+    /// The files at `location`, a local path or a URL of object storage like `s3://bucket/path`.
+    /// A location that ends with `/` is a directory, whose files with the extension of the format
+    /// are read, including those of its subdirectories.
+    #[must_use]
+    pub fn new(location: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            name: None,
+            format: ObjectStoreFormat::Parquet,
+            engine: ReadEngine::DataFusion,
+            credential_chain: false,
+            table_factory: None,
+        }
+    }
+
+    /// The name of the table in the SQL of the scans that run in DuckDB. By default, the last
+    /// part of the location.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// The format of the files, Parquet by default.
+    #[must_use]
+    pub fn with_format(mut self, format: ObjectStoreFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The engine that reads the files, DataFusion by default.
+    #[must_use]
+    pub fn with_engine(mut self, engine: ReadEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Reads the storage with the credentials of the environment in DuckDB, e.g. the AWS
+    /// credentials chain for `s3://` locations. Other credentials are configured by creating a
+    /// secret on the pool of [`Self::with_table_factory`]. DataFusion reads with the object store
+    /// registered on the runtime of the session instead.
+    #[must_use]
+    pub fn with_credential_chain(mut self, credential_chain: bool) -> Self {
+        self.credential_chain = credential_chain;
+        self
+    }
+
+    /// Reads the files in DuckDB with the database of the pool of `table_factory` and its
+    /// settings, instead of a new in-memory database.
+    #[must_use]
+    pub fn with_table_factory(mut self, table_factory: DuckDBTableFactory) -> Self {
+        self.table_factory = Some(table_factory);
+        self
+    }
+
+    /// The name of the table in the SQL of the scans that run in DuckDB.
+    #[must_use]
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let name = self
+            .location
+            .trim_end_matches('/')
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(self.format.extension())
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if name.is_empty() {
+            "object_store_table".to_string()
+        } else {
+            name
+        }
+    }
+
+    /// The files that DuckDB reads, a glob of the files of the directory for a directory.
+    fn pattern(&self) -> String {
+        if self.location.ends_with('/') {
+            format!("{}**/*{}", self.location, self.format.extension())
+        } else {
+            self.location.clone()
+        }
+    }
+
+    /// The statements that load the extension of the object storage of the location.
+    fn setup_statements(&self) -> Vec<String> {
+        let Some(secret_type) = secret_type(&self.location) else {
+            return Vec::new();
+        };
+        let mut statements = vec!["INSTALL httpfs".to_string(), "LOAD httpfs".to_string()];
+        if self.credential_chain {
+            statements.push(format!(
+                "CREATE OR REPLACE SECRET {} (TYPE {secret_type}, PROVIDER credential_chain)",
+                quote_identifier(&format!("{}_secret", self.name()))
+            ));
+        }
+        statements
+    }
+
+    /// The query of the scans that run in DuckDB.
+    fn query(&self) -> String {
+        format!(
+            "SELECT * FROM {}",
+            self.format.table_function(&self.pattern())
+        )
+    }
+
+    /// Creates the table provider of the files, with the engine of the builder. The schema of the
+    /// files is read once, with the object stores of `state` for DataFusion.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the location is invalid, or if the schema of the files can't be read.
+    pub async fn build(
+        mut self,
+        state: &dyn Session,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        match self.engine {
+            ReadEngine::DataFusion => Ok(self.listing_table(state).await?),
+            ReadEngine::DuckDB => {
+                let table_factory = match self.table_factory.take() {
+                    Some(table_factory) => table_factory,
+                    None => DuckDBTableFactory::new(Arc::new(
+                        DuckDbConnectionPoolBuilder::memory()
+                            .build()
+                            .context(UnableToCreatePoolSnafu)?,
+                    )),
+                };
+                self.configure(&table_factory).map_err(|source| {
+                    Error::UnableToConfigureStorage {
+                        location: self.location.clone(),
+                        source,
+                    }
+                })?;
+
+                let query = self.query();
+                tracing::debug!("Reading the files at '{}' in DuckDB", self.location);
+                table_factory
+                    .query_table_provider(TableReference::bare(self.name()), &query)
+                    .await
+            }
+        }
+    }
+
+    async fn listing_table(&self, state: &dyn Session) -> Result<Arc<dyn TableProvider>> {
+        let url = ListingTableUrl::parse(&self.location).context(InvalidLocationSnafu {
+            location: self.location.clone(),
+        })?;
+        let options = ListingOptions::new(self.format.file_format())
+            .with_file_extension(self.format.extension());
+        let schema = options
+            .infer_schema(state, &url)
+            .await
+            .context(UnableToInferSchemaSnafu {
+                location: self.location.clone(),
+            })?;
+        let config = ListingTableConfig::new(url)
+            .with_listing_options(options)
+            .with_schema(schema);
+        let table = ListingTable::try_new(config).context(UnableToCreateListingTableSnafu {
+            location: self.location.clone(),
+        })?;
+        Ok(Arc::new(table))
+    }
+
+    fn configure(&self, table_factory: &DuckDBTableFactory) -> Result<(), GenericError> {
+        let statements = self.setup_statements();
+        if statements.is_empty() {
+            return Ok(());
+        }
+        let conn = Arc::clone(&table_factory.pool).connect_sync()?;
+        let conn = conn.as_sync().ok_or("DuckDB connections are synchronous")?;
+        for statement in statements {
+            conn.execute(&statement, &[])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{arrow::array::Int64Array, prelude::SessionContext};
+
+    use super::*;
+    use crate::duckdb::export::{export_parquet, ExportSource, ParquetExportOptions};
+
+    #[test]
+    fn test_query() {
+        let builder = ObjectStoreTableProviderBuilder::new("s3://bucket/lake/orders/")
+            .with_credential_chain(true);
+        assert_eq!(builder.name(), "orders");
+        assert_eq!(
+            builder.query(),
+            "SELECT * FROM read_parquet('s3://bucket/lake/orders/**/*.parquet')"
+        );
+        assert_eq!(
+            builder.setup_statements()[2..],
+            [r#"CREATE OR REPLACE SECRET "orders_secret" (TYPE S3, PROVIDER credential_chain)"#]
+        );
+
+        let builder = ObjectStoreTableProviderBuilder::new("./data/sales-2024.csv").with_format(
+            ObjectStoreFormat::Csv {
+                has_header: false,
+                delimiter: b';',
+            },
+        );
+        assert_eq!(builder.name(), "sales_2024");
+        assert_eq!(
+            builder.query(),
+            "SELECT * FROM read_csv('./data/sales-2024.csv', header = false, delim = ';')"
+        );
+        assert!(builder.setup_statements().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_engines() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let dir = tempfile::tempdir()?;
+        let location = format!("{}/", dir.path().to_str().ok_or("non UTF-8 path")?);
+        let pool = Arc::new(DuckDbConnectionPoolBuilder::memory().build()?);
+        export_parquet(
+            &pool,
+            &ExportSource::Query("SELECT i AS id, i % 2 AS region FROM range(10) t(i)".to_string()),
+            &format!("{location}orders"),
+            &ParquetExportOptions::default().with_partition_by(vec!["region".to_string()]),
+        )?;
+
+        for engine in [ReadEngine::DataFusion, ReadEngine::DuckDB] {
+            let ctx = SessionContext::new();
+            let table = ObjectStoreTableProviderBuilder::new(&location)
+                .with_engine(engine)
+                .build(&ctx.state())
+                .await?;
+            ctx.register_table("orders", table)?;
+            let batches = ctx
+                .sql("SELECT count(*) AS total FROM orders WHERE id >= 5")
+                .await?
+                .collect()
+                .await?;
+            let total = batches[0]
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .ok_or("the total is not an Int64")?;
+            assert_eq!(total.value(0), 5, "{engine:?}");
+        }
+        Ok(())
+    }
+}
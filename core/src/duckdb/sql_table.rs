@@ -1,14 +1,22 @@
+use crate::sql::db_connection_pool::duckdbprofile::QueryProfile;
 use crate::sql::db_connection_pool::DbConnectionPool;
+use crate::sql::parameters::BoundStatement;
+use crate::sql::pushdown::{FilterSemantics, PushdownPolicy};
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::sample::TableSample;
+use crate::sql::schema_drift::SchemaDriftPolicy;
+use crate::util::indexes::IndexAdvisor;
+use crate::util::memory::limit_stream_memory;
 use async_trait::async_trait;
 use datafusion::catalog::Session;
 use datafusion::sql::unparser::dialect::Dialect;
-use futures::TryStreamExt;
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::{any::Any, fmt, sync::Arc};
+use std::{any::Any, fmt, sync::Arc, time::Duration};
 
 use crate::sql::sql_provider_datafusion::{
-    get_stream, to_execution_error, Result as SqlResult, SqlExec, SqlTable,
+    explain::{RemoteExplainer, RemotePlan},
+    get_stream_with_params, to_execution_error, Result as SqlResult, SqlExec, SqlTable,
 };
 use datafusion::{
     arrow::datatypes::SchemaRef,
@@ -17,10 +25,10 @@ use datafusion::{
     execution::TaskContext,
     logical_expr::{Expr, TableProviderFilterPushDown, TableType},
     physical_plan::{
-        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
-        PlanProperties, SendableRecordBatchStream,
+        metrics::MetricsSet, DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties,
+        SendableRecordBatchStream,
     },
-    sql::{unparser::dialect::DuckDBDialect, TableReference},
+    sql::TableReference,
 };
 
 pub struct DuckDBTable<T: 'static, P: 'static> {
@@ -28,6 +36,9 @@ pub struct DuckDBTable<T: 'static, P: 'static> {
 
     /// A mapping of table/view names to `DuckDB` functions that can instantiate a table (e.g. "`read_parquet`('`my_file.parquet`')").
     pub(crate) table_functions: Option<HashMap<String, String>>,
+
+    /// Whether the JSON profile of DuckDB is captured for every scan, see [`QueryProfile`].
+    profiling: bool,
 }
 
 impl<T, P> std::fmt::Debug for DuckDBTable<T, P> {
@@ -47,27 +58,180 @@ impl<T, P> DuckDBTable<T, P> {
         dialect: Option<Arc<dyn Dialect + Send + Sync>>,
     ) -> Self {
         let base_table = SqlTable::new_with_schema("duckdb", pool, schema, table_reference)
-            .with_dialect(dialect.unwrap_or(Arc::new(DuckDBDialect::new())));
+            .with_dialect(dialect.unwrap_or_else(super::duckdb_dialect));
 
         Self {
             base_table,
             table_functions,
+            profiling: false,
+        }
+    }
+
+    /// Returns the given string columns dictionary-encoded, see
+    /// [`crate::util::dictionary::dictionary_encode_schema`].
+    #[must_use]
+    pub fn with_dictionary_columns(self, columns: &[String]) -> Self {
+        Self {
+            base_table: self.base_table.with_dictionary_columns(columns),
+            ..self
+        }
+    }
+
+    /// Sets what scans do when the columns returned by the database no longer match the schema of
+    /// the table, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(self, schema_drift: SchemaDriftPolicy) -> Self {
+        Self {
+            base_table: self.base_table.with_schema_drift(schema_drift),
+            ..self
+        }
+    }
+
+    /// Buffers up to `memory_batches` batches of each scan in memory and the rest on disk, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(self, memory_batches: Option<usize>) -> Self {
+        Self {
+            base_table: self.base_table.with_spill_buffer(memory_batches),
+            ..self
+        }
+    }
+
+    /// Waits up to `wait` for the memory of every batch of scans, see
+    /// [`SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            base_table: self.base_table.with_memory_wait(wait),
+            ..self
+        }
+    }
+
+    /// Shows the plan of the remote database for every scan in the `EXPLAIN` output, see
+    /// [`SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(self, remote_explain: bool) -> Self {
+        Self {
+            base_table: self.base_table.with_remote_explain(remote_explain),
+            ..self
+        }
+    }
+
+    /// Explains the SQL of the scans of the table on the remote database, with the CTEs of the
+    /// table functions, if remote explains are enabled.
+    pub(crate) fn remote_explainer(&self) -> Option<Arc<dyn RemoteExplainer>> {
+        self.base_table
+            .remote_explainer_with_prefix(get_cte(&self.table_functions))
+    }
+
+    /// The SQL that `plan` runs on the database, with the CTEs of the table functions, if it's a
+    /// scan of a table of this type.
+    pub(crate) fn scan_sql(plan: &dyn ExecutionPlan) -> Option<String>
+    where
+        T: 'static,
+        P: 'static,
+    {
+        plan.as_any()
+            .downcast_ref::<DuckSqlExec<T, P>>()?
+            .sql()
+            .ok()
+    }
+
+    /// Sets what scans push down to the database, see [`SqlTable::with_pushdown_policy`].
+    #[must_use]
+    pub fn with_pushdown_policy(self, pushdown_policy: PushdownPolicy) -> Self {
+        Self {
+            base_table: self.base_table.with_pushdown_policy(pushdown_policy),
+            ..self
+        }
+    }
+
+    /// Sets how the database compares strings in the filters and sorts that are pushed down, see
+    /// [`SqlTable::with_filter_semantics`].
+    #[must_use]
+    pub fn with_filter_semantics(self, filter_semantics: FilterSemantics) -> Self {
+        Self {
+            base_table: self.base_table.with_filter_semantics(filter_semantics),
+            ..self
+        }
+    }
+
+    /// Reads a sample of the rows of the table, see [`SqlTable::with_sample`].
+    #[must_use]
+    pub fn with_sample(self, sample: Option<TableSample>) -> Self {
+        Self {
+            base_table: self.base_table.with_sample(sample),
+            ..self
+        }
+    }
+
+    /// Binds the string literals of scans as parameters, see [`SqlTable::with_bind_literals`].
+    #[must_use]
+    pub fn with_bind_literals(self, bind_literals: bool) -> Self {
+        Self {
+            base_table: self.base_table.with_bind_literals(bind_literals),
+            ..self
         }
     }
 
+    /// Passes the query context of the session to the database, see
+    /// [`SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            base_table: self.base_table.with_query_context(query_context),
+            ..self
+        }
+    }
+
+    /// Counts the columns that the filters of scans use, see [`SqlTable::with_index_advisor`].
+    #[must_use]
+    pub fn with_index_advisor(self, index_advisor: Option<Arc<IndexAdvisor>>) -> Self {
+        Self {
+            base_table: self.base_table.with_index_advisor(index_advisor),
+            ..self
+        }
+    }
+
+    /// Reads the rows of `query` instead of the table, see [`SqlTable::with_query`].
+    #[must_use]
+    pub fn with_query(self, query: impl Into<String>) -> Self {
+        Self {
+            base_table: self.base_table.with_query(query),
+            ..self
+        }
+    }
+
+    /// Captures the JSON profile of DuckDB for every scan, in the metrics of the scan that
+    /// `EXPLAIN ANALYZE` shows, see [`QueryProfile`].
+    #[must_use]
+    pub fn with_profiling(self, profiling: bool) -> Self {
+        Self { profiling, ..self }
+    }
+
     fn create_physical_plan(
         &self,
         projection: Option<&Vec<usize>>,
         schema: &SchemaRef,
-        sql: String,
+        bound: BoundStatement,
+        remote_plan: Option<RemotePlan>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(DuckSqlExec::new(
-            projection,
-            schema,
-            self.base_table.clone_pool(),
-            sql,
-            self.table_functions.clone(),
-        )?))
+        Ok(Arc::new(
+            DuckSqlExec::new(
+                projection,
+                schema,
+                self.base_table.clone_pool(),
+                bound.sql,
+                self.table_functions.clone(),
+                self.base_table.schema_drift(),
+                self.base_table.spill_buffer(),
+            )?
+            .with_params(bound.params)
+            .with_query_context(self.base_table.query_context().cloned())
+            .with_memory_wait(self.base_table.memory_wait())
+            .with_remote_plan(remote_plan)
+            .with_profiling(self.profiling),
+        ))
     }
 }
 
@@ -99,8 +263,15 @@ impl<T, P> TableProvider for DuckDBTable<T, P> {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        let sql = self.base_table.scan_to_sql(projection, filters, limit)?;
-        return self.create_physical_plan(projection, &self.schema(), sql);
+        let statement = self
+            .base_table
+            .scan_to_statement(projection, filters, limit)?;
+        // EXPLAIN can't bind parameters, so the plan is of the statement with its literals inline
+        let remote_plan = self
+            .remote_explainer()
+            .map(|explainer| RemotePlan::new(explainer, statement.to_string()));
+        let bound = self.base_table.bind_statement(statement);
+        return self.create_physical_plan(projection, &self.schema(), bound, remote_plan);
     }
 }
 
@@ -114,6 +285,7 @@ impl<T, P> Display for DuckDBTable<T, P> {
 struct DuckSqlExec<T, P> {
     base_exec: SqlExec<T, P>,
     table_functions: Option<HashMap<String, String>>,
+    profiling: bool,
 }
 
 impl<T, P> DuckSqlExec<T, P> {
@@ -123,15 +295,52 @@ impl<T, P> DuckSqlExec<T, P> {
         pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
         sql: String,
         table_functions: Option<HashMap<String, String>>,
+        schema_drift: SchemaDriftPolicy,
+        spill_buffer: Option<usize>,
     ) -> DataFusionResult<Self> {
-        let base_exec = SqlExec::new(projection, schema, pool, sql)?;
+        let base_exec = SqlExec::new(projection, schema, pool, sql)?
+            .with_schema_drift(schema_drift)
+            .with_spill_buffer(spill_buffer);
 
         Ok(Self {
             base_exec,
             table_functions,
+            profiling: false,
         })
     }
 
+    fn with_params(self, params: Vec<String>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_params(params),
+            ..self
+        }
+    }
+
+    fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_memory_wait(wait),
+            ..self
+        }
+    }
+
+    fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_query_context(query_context),
+            ..self
+        }
+    }
+
+    fn with_remote_plan(self, remote_plan: Option<RemotePlan>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_remote_plan(remote_plan),
+            ..self
+        }
+    }
+
+    fn with_profiling(self, profiling: bool) -> Self {
+        Self { profiling, ..self }
+    }
+
     fn sql(&self) -> SqlResult<String> {
         let sql = self.base_exec.sql()?;
 
@@ -152,7 +361,8 @@ impl<T, P> std::fmt::Debug for DuckSqlExec<T, P> {
 impl<T, P> DisplayAs for DuckSqlExec<T, P> {
     fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> std::fmt::Result {
         let sql = self.sql().unwrap_or_default();
-        write!(f, "DuckSqlExec sql={sql}")
+        write!(f, "DuckSqlExec sql={sql}")?;
+        self.base_exec.fmt_remote_plan(f)
     }
 }
 
@@ -184,20 +394,47 @@ impl<T: 'static, P: 'static> ExecutionPlan for DuckSqlExec<T, P> {
         Ok(self)
     }
 
+    fn metrics(&self) -> Option<MetricsSet> {
+        self.base_exec.metrics()
+    }
+
     fn execute(
         &self,
-        _partition: usize,
-        _context: Arc<TaskContext>,
+        partition: usize,
+        context: Arc<TaskContext>,
     ) -> DataFusionResult<SendableRecordBatchStream> {
         let sql = self.sql().map_err(to_execution_error)?;
         tracing::debug!("DuckSqlExec sql: {sql}");
 
-        let schema = self.schema();
+        let fut = get_stream_with_params(
+            self.base_exec.clone_pool(),
+            sql,
+            self.base_exec.params().to_vec(),
+            self.base_exec.remote_context(&context),
+            self.schema(),
+            self.base_exec.schema_drift(),
+        );
 
-        let fut = get_stream(self.base_exec.clone_pool(), sql, Arc::clone(&schema));
+        let profile = self.profiling.then(|| {
+            Arc::new(QueryProfile::new(
+                self.base_exec.metrics_set().clone(),
+                partition,
+            ))
+        });
+        let fut = async move {
+            match profile {
+                Some(profile) => profile.scope(fut).await,
+                None => fut.await,
+            }
+        };
 
-        let stream = futures::stream::once(fut).try_flatten();
-        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+        Ok(limit_stream_memory(
+            self.base_exec.scan_stream(partition, fut),
+            &context,
+            self.base_exec.spill_buffer(),
+            self.base_exec.memory_wait(),
+            format!("DuckSqlExec[{partition}]"),
+        ))
     }
 }
 
@@ -1,12 +1,18 @@
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{any::Any, fmt, sync::Arc};
 
 use crate::duckdb::DuckDB;
+use crate::sql::db_connection_pool::dbconnection::duckdbconn::contains_map;
 use crate::sql::db_connection_pool::duckdbpool::DuckDbConnectionPool;
+use crate::sql::dml::{DmlOperation, DmlTableProvider};
 use crate::util::{
     constraints,
+    dedup::{self, Deduplicator},
+    normalize::SupportedEncodings,
     on_conflict::OnConflict,
     retriable_error::{check_and_mark_retriable_error, to_retriable_data_write_error},
+    validation,
 };
 use arrow::array::RecordBatchReader;
 use arrow::ffi_stream::FFI_ArrowArrayStream;
@@ -16,6 +22,7 @@ use async_trait::async_trait;
 use datafusion::catalog::Session;
 use datafusion::common::{Constraints, SchemaExt};
 use datafusion::logical_expr::dml::InsertOp;
+use datafusion::sql::{unparser::dialect::DuckDBDialect, TableReference};
 use datafusion::{
     datasource::{TableProvider, TableType},
     error::DataFusionError,
@@ -34,6 +41,7 @@ use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::task::JoinHandle;
 
 use super::creator::{TableDefinition, TableManager, ViewCreator};
+use super::export::{export_parquet, ExportSource, ParquetExportOptions};
 use super::{to_datafusion_error, RelationName};
 
 // checking schemas are equivalent is disabled because it incorrectly marks single-level list fields are different when the name of the field is different
@@ -42,12 +50,40 @@ use super::{to_datafusion_error, RelationName};
 // related: https://github.com/apache/arrow-rs/issues/6733#issuecomment-2482582556
 const SCHEMA_EQUIVALENCE_ENABLED: bool = false;
 
+/// How the incoming batches are loaded into the DuckDB table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InsertMethod {
+    /// Registers the incoming stream as an Arrow scan and runs `INSERT INTO ... SELECT * FROM` it.
+    #[default]
+    ArrowScan,
+    /// Appends the batches with the DuckDB appender, which skips planning an `INSERT` but matches
+    /// the columns of the batches to the ones of the table by position. Inserts with an
+    /// `on_conflict` clause or dedup columns, and tables with `Map` columns, still go through an
+    /// Arrow scan.
+    Appender,
+}
+
+impl FromStr for InsertMethod {
+    type Err = super::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "arrow_scan" => Ok(Self::ArrowScan),
+            "appender" => Ok(Self::Appender),
+            _ => super::InvalidInsertMethodSnafu { value }.fail(),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct DuckDBTableWriterBuilder {
     read_provider: Option<Arc<dyn TableProvider>>,
     pool: Option<Arc<DuckDbConnectionPool>>,
     on_conflict: Option<OnConflict>,
+    dedup_columns: Option<Vec<String>>,
     table_definition: Option<TableDefinition>,
+    validate_batches: bool,
+    insert_method: InsertMethod,
 }
 
 impl DuckDBTableWriterBuilder {
@@ -74,12 +110,34 @@ impl DuckDBTableWriterBuilder {
         self
     }
 
+    /// Drops incoming rows that match an earlier incoming row or an existing row of the table on `dedup_columns`.
+    /// Rows that match existing rows are handled by the `on_conflict` clause instead, if one is set.
+    #[must_use]
+    pub fn with_dedup_columns(mut self, dedup_columns: Vec<String>) -> Self {
+        self.dedup_columns = Some(dedup_columns);
+        self
+    }
+
     #[must_use]
     pub fn with_table_definition(mut self, table_definition: TableDefinition) -> Self {
         self.table_definition = Some(table_definition);
         self
     }
 
+    /// Checks each batch with [`validation::validate_batch`] before it is handed to the DuckDB
+    /// appender, which reads the arrays through FFI and can crash on malformed ones.
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    #[must_use]
+    pub fn with_insert_method(mut self, insert_method: InsertMethod) -> Self {
+        self.insert_method = insert_method;
+        self
+    }
+
     /// Builds a `DuckDBTableWriter` from the provided configuration.
     ///
     /// # Errors
@@ -104,8 +162,11 @@ impl DuckDBTableWriterBuilder {
         Ok(DuckDBTableWriter {
             read_provider,
             on_conflict: self.on_conflict,
+            dedup_columns: self.dedup_columns,
             table_definition: Arc::new(table_definition),
             pool,
+            validate_batches: self.validate_batches,
+            insert_method: self.insert_method,
         })
     }
 }
@@ -116,6 +177,9 @@ pub struct DuckDBTableWriter {
     pool: Arc<DuckDbConnectionPool>,
     table_definition: Arc<TableDefinition>,
     on_conflict: Option<OnConflict>,
+    dedup_columns: Option<Vec<String>>,
+    validate_batches: bool,
+    insert_method: InsertMethod,
 }
 
 impl std::fmt::Debug for DuckDBTableWriter {
@@ -134,6 +198,26 @@ impl DuckDBTableWriter {
     pub fn table_definition(&self) -> Arc<TableDefinition> {
         Arc::clone(&self.table_definition)
     }
+
+    /// Writes the rows of the table to the Parquet files at `destination`, see
+    /// [`super::export`].
+    ///
+    /// Returns the number of rows written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options don't apply to the destination, or if the rows can't be
+    /// written.
+    pub fn export_parquet(
+        &self,
+        destination: &str,
+        options: &ParquetExportOptions,
+    ) -> super::export::Result<u64> {
+        let source = ExportSource::Table(TableReference::bare(
+            self.table_definition.name().to_string(),
+        ));
+        export_parquet(&self.pool, &source, destination, options)
+    }
 }
 
 #[async_trait]
@@ -154,6 +238,12 @@ impl TableProvider for DuckDBTableWriter {
         self.table_definition.constraints()
     }
 
+    fn get_column_default(&self, column: &str) -> Option<&Expr> {
+        self.table_definition
+            .column_expressions()
+            .default_expr(column)
+    }
+
     async fn scan(
         &self,
         state: &dyn Session,
@@ -174,24 +264,50 @@ impl TableProvider for DuckDBTableWriter {
     ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
         Ok(Arc::new(DataSinkExec::new(
             input,
-            Arc::new(DuckDBDataSink::new(
-                Arc::clone(&self.pool),
-                Arc::clone(&self.table_definition),
-                op,
-                self.on_conflict.clone(),
-                self.schema(),
-            )),
+            Arc::new(
+                DuckDBDataSink::new(
+                    Arc::clone(&self.pool),
+                    Arc::clone(&self.table_definition),
+                    op,
+                    self.on_conflict.clone(),
+                    self.schema(),
+                )
+                .set_dedup_columns(self.dedup_columns.clone())
+                .set_batch_validation(self.validate_batches)
+                .set_insert_method(self.insert_method),
+            ),
             None,
         )) as _)
     }
 }
 
+#[async_trait]
+impl DmlTableProvider for DuckDBTableWriter {
+    async fn execute_dml(&self, operation: &DmlOperation) -> datafusion::error::Result<u64> {
+        let pool = Arc::clone(&self.pool);
+        let table_definition = Arc::clone(&self.table_definition);
+        let operation = operation.clone();
+
+        match tokio::task::spawn_blocking(move || execute_dml(pool, &table_definition, &operation))
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => Err(DataFusionError::Execution(format!(
+                "Error writing to DuckDB: {e}"
+            ))),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct DuckDBDataSink {
     pool: Arc<DuckDbConnectionPool>,
     table_definition: Arc<TableDefinition>,
     overwrite: InsertOp,
     on_conflict: Option<OnConflict>,
+    dedup_columns: Option<Vec<String>>,
+    validate_batches: bool,
+    insert_method: InsertMethod,
     schema: SchemaRef,
 }
 
@@ -217,7 +333,24 @@ impl DataSink for DuckDBDataSink {
         let pool = Arc::clone(&self.pool);
         let table_definition = Arc::clone(&self.table_definition);
         let overwrite = self.overwrite;
-        let on_conflict = self.on_conflict.clone();
+        let insert_method = self.insert_method;
+        // dedup columns covered by a unique constraint can skip existing rows with ON CONFLICT,
+        // otherwise existing rows are filtered out with an anti-join when inserting
+        let on_conflict = self.on_conflict.clone().or_else(|| {
+            dedup::on_conflict_for_dedup(
+                &self.schema,
+                self.table_definition.constraints()?,
+                self.dedup_columns.as_deref()?,
+            )
+        });
+        let anti_join_columns = self.dedup_columns.clone().filter(|_| on_conflict.is_none());
+        let mut deduplicator = self
+            .dedup_columns
+            .as_deref()
+            .map(|dedup_columns| Deduplicator::try_new(&self.schema, dedup_columns))
+            .transpose()
+            .context(super::UnableToDeduplicateBatchSnafu)
+            .map_err(to_datafusion_error)?;
 
         // Limit channel size to a maximum of 100 RecordBatches queued for cases when DuckDB is slower than the writer stream,
         // so that we don't significantly increase memory usage. After the maximum RecordBatches are queued, the writer stream will wait
@@ -227,7 +360,9 @@ impl DataSink for DuckDBDataSink {
         // Since the main task/stream can be dropped or fail, we use a oneshot channel to signal that all data is received and we should commit the transaction
         let (notify_commit_transaction, on_commit_transaction) = tokio::sync::oneshot::channel();
 
-        let schema = data.schema();
+        // the batches are converted to the encodings DuckDB takes, like `Utf8` for `Utf8View`
+        let encodings = SupportedEncodings::duckdb();
+        let schema = encodings.normalize_schema(&data.schema());
 
         let duckdb_write_handle: JoinHandle<datafusion::common::Result<u64>> =
             tokio::task::spawn_blocking(move || {
@@ -237,6 +372,8 @@ impl DataSink for DuckDBDataSink {
                         &table_definition,
                         batch_rx,
                         on_conflict.as_ref(),
+                        anti_join_columns.as_deref(),
+                        insert_method,
                         on_commit_transaction,
                         schema,
                     )?,
@@ -245,6 +382,8 @@ impl DataSink for DuckDBDataSink {
                         &table_definition,
                         batch_rx,
                         on_conflict.as_ref(),
+                        anti_join_columns.as_deref(),
+                        insert_method,
                         on_commit_transaction,
                         schema,
                     )?,
@@ -255,6 +394,20 @@ impl DataSink for DuckDBDataSink {
 
         while let Some(batch) = data.next().await {
             let batch = batch.map_err(check_and_mark_retriable_error)?;
+            let mut batch = encodings.normalize_batch(batch)?;
+
+            if self.validate_batches {
+                validation::validate_batch(&batch)
+                    .context(super::InvalidBatchSnafu)
+                    .map_err(to_datafusion_error)?;
+            }
+
+            if let Some(deduplicator) = &mut deduplicator {
+                batch = deduplicator
+                    .dedup(&batch)
+                    .context(super::UnableToDeduplicateBatchSnafu)
+                    .map_err(to_datafusion_error)?;
+            }
 
             if let Some(constraints) = self.table_definition.constraints() {
                 constraints::validate_batch_with_constraints(&[batch.clone()], constraints)
@@ -313,9 +466,30 @@ impl DuckDBDataSink {
             table_definition,
             overwrite,
             on_conflict,
+            dedup_columns: None,
+            validate_batches: false,
+            insert_method: InsertMethod::default(),
             schema,
         }
     }
+
+    #[must_use]
+    pub(crate) fn set_dedup_columns(mut self, dedup_columns: Option<Vec<String>>) -> Self {
+        self.dedup_columns = dedup_columns;
+        self
+    }
+
+    #[must_use]
+    pub(crate) fn set_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    #[must_use]
+    pub(crate) fn set_insert_method(mut self, insert_method: InsertMethod) -> Self {
+        self.insert_method = insert_method;
+        self
+    }
 }
 
 impl std::fmt::Debug for DuckDBDataSink {
@@ -330,11 +504,14 @@ impl DisplayAs for DuckDBDataSink {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn insert_append(
     pool: Arc<DuckDbConnectionPool>,
     table_definition: &Arc<TableDefinition>,
     batch_rx: Receiver<RecordBatch>,
     on_conflict: Option<&OnConflict>,
+    anti_join_columns: Option<&[String]>,
+    insert_method: InsertMethod,
     mut on_commit_transaction: tokio::sync::oneshot::Receiver<()>,
     schema: SchemaRef,
 ) -> datafusion::common::Result<u64> {
@@ -381,8 +558,16 @@ fn insert_append(
         "Append load for {table_name}",
         table_name = append_table.table_name()
     );
-    let num_rows = write_to_table(&append_table, &tx, schema, batch_rx, on_conflict)
-        .map_err(to_retriable_data_write_error)?;
+    let num_rows = write_to_table(
+        &append_table,
+        &tx,
+        schema,
+        batch_rx,
+        on_conflict,
+        anti_join_columns,
+        insert_method,
+    )
+    .map_err(to_retriable_data_write_error)?;
 
     on_commit_transaction
         .try_recv()
@@ -436,12 +621,68 @@ fn insert_append(
     Ok(num_rows)
 }
 
+fn execute_dml(
+    pool: Arc<DuckDbConnectionPool>,
+    table_definition: &Arc<TableDefinition>,
+    operation: &DmlOperation,
+) -> datafusion::common::Result<u64> {
+    let mut db_conn = pool
+        .connect_sync()
+        .context(super::DbConnectionPoolSnafu)
+        .map_err(to_retriable_data_write_error)?;
+
+    let duckdb_conn = DuckDB::duckdb_conn(&mut db_conn).map_err(to_retriable_data_write_error)?;
+
+    let tx = duckdb_conn
+        .conn
+        .transaction()
+        .context(super::UnableToBeginTransactionSnafu)
+        .map_err(to_retriable_data_write_error)?;
+
+    let base_table = TableManager::new(Arc::clone(table_definition))
+        .with_internal(false)
+        .map_err(to_datafusion_error)?;
+
+    // after an overwrite the table is a view over the newest internal table, which is where the data lives
+    let target_table = match base_table
+        .list_other_internal_tables(&tx)
+        .map_err(to_datafusion_error)?
+        .pop()
+    {
+        Some((internal_table, _)) => internal_table,
+        None => base_table,
+    };
+
+    let sql = operation
+        .to_sql(
+            &TableReference::bare(target_table.table_name().to_string()),
+            &DuckDBDialect::new(),
+        )
+        .context(super::UnableToCreateDmlStatementSnafu)
+        .map_err(to_datafusion_error)?;
+    tracing::trace!("{sql}");
+
+    let num_rows = tx
+        .execute(&sql, [])
+        .context(super::UnableToExecuteDmlStatementSnafu)
+        .map_err(to_datafusion_error)?;
+
+    tx.commit()
+        .context(super::UnableToCommitTransactionSnafu)
+        .map_err(to_retriable_data_write_error)?;
+
+    Ok(num_rows as u64)
+}
+
 #[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments)]
 fn insert_overwrite(
     pool: Arc<DuckDbConnectionPool>,
     table_definition: &Arc<TableDefinition>,
     batch_rx: Receiver<RecordBatch>,
     on_conflict: Option<&OnConflict>,
+    anti_join_columns: Option<&[String]>,
+    insert_method: InsertMethod,
     mut on_commit_transaction: tokio::sync::oneshot::Receiver<()>,
     schema: SchemaRef,
 ) -> datafusion::common::Result<u64> {
@@ -536,8 +777,16 @@ fn insert_overwrite(
     }
 
     tracing::debug!("Initial load for {}", new_table.table_name());
-    let num_rows = write_to_table(&new_table, &tx, schema, batch_rx, on_conflict)
-        .map_err(to_retriable_data_write_error)?;
+    let num_rows = write_to_table(
+        &new_table,
+        &tx,
+        schema,
+        batch_rx,
+        on_conflict,
+        anti_join_columns,
+        insert_method,
+    )
+    .map_err(to_retriable_data_write_error)?;
 
     on_commit_transaction
         .try_recv()
@@ -594,7 +843,18 @@ fn write_to_table(
     schema: SchemaRef,
     data_batches: Receiver<RecordBatch>,
     on_conflict: Option<&OnConflict>,
+    anti_join_columns: Option<&[String]>,
+    insert_method: InsertMethod,
 ) -> datafusion::common::Result<u64> {
+    // the appender can't write MAP vectors, which the Arrow scan reads natively
+    if insert_method == InsertMethod::Appender
+        && on_conflict.is_none()
+        && anti_join_columns.is_none()
+        && !schema.fields().iter().any(|f| contains_map(f.data_type()))
+    {
+        return append_to_table(table, tx, data_batches);
+    }
+
     let stream = FFI_ArrowArrayStream::new(Box::new(RecordBatchReaderFromStream::new(
         data_batches,
         schema,
@@ -612,14 +872,44 @@ fn write_to_table(
         .map_err(to_datafusion_error)?;
 
     let view = ViewCreator::from_name(RelationName::new(view_name));
-    let rows = view
-        .insert_into(table, tx, on_conflict)
-        .map_err(to_datafusion_error)?;
+    let rows = match anti_join_columns {
+        Some(anti_join_columns) => view.insert_into_anti_join(table, tx, anti_join_columns),
+        None => view.insert_into(table, tx, on_conflict),
+    }
+    .map_err(to_datafusion_error)?;
     view.drop(tx).map_err(to_datafusion_error)?;
 
     Ok(rows as u64)
 }
 
+#[allow(clippy::doc_markdown)]
+/// Appends a stream of ``RecordBatch``es to a DuckDB table with the DuckDB appender.
+fn append_to_table(
+    table: &TableManager,
+    tx: &Transaction<'_>,
+    mut data_batches: Receiver<RecordBatch>,
+) -> datafusion::common::Result<u64> {
+    let mut appender = tx
+        .appender(&table.table_name().to_string())
+        .context(super::UnableToGetAppenderToDuckDBTableSnafu)
+        .map_err(to_datafusion_error)?;
+
+    let mut num_rows = 0;
+    while let Some(batch) = data_batches.blocking_recv() {
+        num_rows += batch.num_rows() as u64;
+        appender
+            .append_record_batch(batch)
+            .context(super::UnableToInsertToDuckDBTableSnafu)
+            .map_err(to_datafusion_error)?;
+    }
+    appender
+        .flush()
+        .context(super::UnableToInsertToDuckDBTableSnafu)
+        .map_err(to_datafusion_error)?;
+
+    Ok(num_rows)
+}
+
 struct RecordBatchReaderFromStream {
     stream: Receiver<RecordBatch>,
     schema: SchemaRef,
@@ -648,6 +938,7 @@ impl RecordBatchReader for RecordBatchReaderFromStream {
 #[cfg(test)]
 mod test {
     use arrow::array::{Int64Array, StringArray};
+    use datafusion::logical_expr::{col, lit};
     use datafusion::physical_plan::memory::MemoryStream;
 
     use super::*;
@@ -1029,6 +1320,229 @@ mod test {
         tx.rollback().expect("to rollback");
     }
 
+    #[tokio::test]
+    async fn test_write_to_table_append_with_appender() {
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let table_definition = get_basic_table_definition();
+        let append_table = TableManager::new(Arc::clone(&table_definition))
+            .with_internal(false)
+            .expect("to create table");
+
+        let cloned_pool = Arc::clone(&pool);
+        let mut conn = cloned_pool.connect_sync().expect("to connect");
+        let duckdb = DuckDB::duckdb_conn(&mut conn).expect("to get duckdb conn");
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+        append_table
+            .create_table(Arc::clone(&pool), &tx)
+            .expect("to create table");
+        tx.commit().expect("to commit");
+
+        let duckdb_sink = DuckDBDataSink::new(
+            Arc::clone(&pool),
+            Arc::clone(&table_definition),
+            InsertOp::Append,
+            None,
+            table_definition.schema(),
+        )
+        .set_insert_method(InsertMethod::Appender);
+        let data_sink: Arc<dyn DataSink> = Arc::new(duckdb_sink);
+
+        let batches = vec![RecordBatch::try_new(
+            Arc::clone(&table_definition.schema()),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(1), Some(2)])),
+                Arc::new(StringArray::from(vec![Some("a"), Some("b")])),
+            ],
+        )
+        .expect("should create a record batch")];
+
+        let stream = Box::pin(
+            MemoryStream::try_new(batches, table_definition.schema(), None).expect("to get stream"),
+        );
+
+        let written = data_sink
+            .write_all(stream, &Arc::new(TaskContext::default()))
+            .await
+            .expect("to write all");
+        assert_eq!(written, 2);
+
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+        let rows = tx
+            .query_row(
+                &format!(
+                    "SELECT COUNT(1) FROM {table_name}",
+                    table_name = append_table.table_name()
+                ),
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .expect("to get count");
+        assert_eq!(rows, 2);
+
+        tx.rollback().expect("to rollback");
+    }
+
+    #[tokio::test]
+    async fn test_write_to_table_append_with_view_arrays() {
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let table_definition = get_basic_table_definition();
+        let append_table = TableManager::new(Arc::clone(&table_definition))
+            .with_internal(false)
+            .expect("to create table");
+
+        let cloned_pool = Arc::clone(&pool);
+        let mut conn = cloned_pool.connect_sync().expect("to connect");
+        let duckdb = DuckDB::duckdb_conn(&mut conn).expect("to get duckdb conn");
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+        append_table
+            .create_table(Arc::clone(&pool), &tx)
+            .expect("to create table");
+        tx.commit().expect("to commit");
+
+        let duckdb_sink = DuckDBDataSink::new(
+            Arc::clone(&pool),
+            Arc::clone(&table_definition),
+            InsertOp::Append,
+            None,
+            table_definition.schema(),
+        );
+        let data_sink: Arc<dyn DataSink> = Arc::new(duckdb_sink);
+
+        let view_schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("id", arrow::datatypes::DataType::Int64, false),
+            arrow::datatypes::Field::new("name", arrow::datatypes::DataType::Utf8View, false),
+        ]));
+        let batches = vec![RecordBatch::try_new(
+            Arc::clone(&view_schema),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(1), Some(2)])),
+                Arc::new(arrow::array::StringViewArray::from(vec!["a", "b"])),
+            ],
+        )
+        .expect("should create a record batch")];
+
+        let stream =
+            Box::pin(MemoryStream::try_new(batches, view_schema, None).expect("to get stream"));
+
+        let written = data_sink
+            .write_all(stream, &Arc::new(TaskContext::default()))
+            .await
+            .expect("to write all");
+        assert_eq!(written, 2);
+
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+        let name = tx
+            .query_row(
+                &format!(
+                    "SELECT name FROM {table_name} WHERE id = 2",
+                    table_name = append_table.table_name()
+                ),
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .expect("to get name");
+        assert_eq!(name, "b");
+
+        tx.rollback().expect("to rollback");
+    }
+
+    #[tokio::test]
+    async fn test_write_to_table_append_with_map() {
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let mut attributes =
+            arrow::array::MapBuilder::new(None, StringArray::builder(2), Int64Array::builder(2));
+        attributes.keys().append_value("size");
+        attributes.values().append_value(3);
+        attributes.keys().append_value("weight");
+        attributes.values().append_value(7);
+        attributes.append(true).expect("to append map");
+        attributes.append(false).expect("to append map");
+        let attributes = attributes.finish();
+
+        let schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            arrow::datatypes::Field::new("id", arrow::datatypes::DataType::Int64, false),
+            arrow::datatypes::Field::new("attributes", attributes.data_type().clone(), true),
+        ]));
+        let table_definition = Arc::new(TableDefinition::new(
+            RelationName::new("test_map_table"),
+            Arc::clone(&schema),
+        ));
+        let append_table = TableManager::new(Arc::clone(&table_definition))
+            .with_internal(false)
+            .expect("to create table");
+
+        let cloned_pool = Arc::clone(&pool);
+        let mut conn = cloned_pool.connect_sync().expect("to connect");
+        let duckdb = DuckDB::duckdb_conn(&mut conn).expect("to get duckdb conn");
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+        append_table
+            .create_table(Arc::clone(&pool), &tx)
+            .expect("to create table");
+        tx.commit().expect("to commit");
+
+        // the appender can't write maps, so the batches go through an Arrow scan
+        let duckdb_sink = DuckDBDataSink::new(
+            Arc::clone(&pool),
+            Arc::clone(&table_definition),
+            InsertOp::Append,
+            None,
+            Arc::clone(&schema),
+        )
+        .set_insert_method(InsertMethod::Appender);
+        let data_sink: Arc<dyn DataSink> = Arc::new(duckdb_sink);
+
+        let batches = vec![RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int64Array::from(vec![1, 2])), Arc::new(attributes)],
+        )
+        .expect("should create a record batch")];
+
+        let stream = Box::pin(MemoryStream::try_new(batches, schema, None).expect("to get stream"));
+
+        let written = data_sink
+            .write_all(stream, &Arc::new(TaskContext::default()))
+            .await
+            .expect("to write all");
+        assert_eq!(written, 2);
+
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+        let column_type = tx
+            .query_row(
+                "SELECT data_type FROM information_schema.columns \
+                 WHERE table_name = 'test_map_table' AND column_name = 'attributes'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .expect("to get column type");
+        assert_eq!(column_type, "MAP(VARCHAR, BIGINT)");
+
+        let entries = tx
+            .query_row(
+                "SELECT cardinality(attributes) FROM test_map_table WHERE id = 1",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .expect("to get map cardinality");
+        assert_eq!(entries, 2);
+
+        tx.rollback().expect("to rollback");
+    }
+
+    #[test]
+    fn test_parse_insert_method() {
+        assert_eq!(
+            InsertMethod::from_str("appender").expect("valid method"),
+            InsertMethod::Appender
+        );
+        assert!(InsertMethod::from_str("row_by_row").is_err());
+    }
+
     #[tokio::test]
     async fn test_write_to_table_append_with_previous_table_needs_indexes() {
         // Test scenario: Write to a table with append mode with a previous table
@@ -1133,4 +1647,219 @@ mod test {
 
         tx.rollback().expect("to rollback");
     }
+
+    #[tokio::test]
+    async fn test_execute_dml_after_overwrite() {
+        // Test scenario: Delete and update rows of a table that was loaded with overwrite mode
+        // Expected behavior: The statements target the internal table behind the view
+
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let table_definition = get_basic_table_definition();
+
+        let duckdb_sink = DuckDBDataSink::new(
+            Arc::clone(&pool),
+            Arc::clone(&table_definition),
+            InsertOp::Overwrite,
+            None,
+            table_definition.schema(),
+        );
+        let data_sink: Arc<dyn DataSink> = Arc::new(duckdb_sink);
+
+        let batches = vec![RecordBatch::try_new(
+            Arc::clone(&table_definition.schema()),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(1), Some(2), Some(3)])),
+                Arc::new(StringArray::from(vec![Some("a"), Some("b"), Some("c")])),
+            ],
+        )
+        .expect("should create a record batch")];
+
+        let stream = Box::pin(
+            MemoryStream::try_new(batches, table_definition.schema(), None).expect("to get stream"),
+        );
+
+        data_sink
+            .write_all(stream, &Arc::new(TaskContext::default()))
+            .await
+            .expect("to write all");
+
+        let deleted = execute_dml(
+            Arc::clone(&pool),
+            &table_definition,
+            &DmlOperation::Delete {
+                filters: vec![col("id").eq(lit(1_i64))],
+            },
+        )
+        .expect("to delete");
+        assert_eq!(deleted, 1);
+
+        let updated = execute_dml(
+            Arc::clone(&pool),
+            &table_definition,
+            &DmlOperation::Update {
+                assignments: vec![("name".to_string(), lit("z"))],
+                filters: vec![col("id").gt(lit(1_i64))],
+            },
+        )
+        .expect("to update");
+        assert_eq!(updated, 2);
+
+        let mut conn = pool.connect_sync().expect("to connect");
+        let duckdb = DuckDB::duckdb_conn(&mut conn).expect("to get duckdb conn");
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+
+        let rows = tx
+            .query_row(
+                &format!(
+                    "SELECT COUNT(1) FROM {view_name} WHERE name = 'z'",
+                    view_name = table_definition.name()
+                ),
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .expect("to get count");
+        assert_eq!(rows, 2);
+
+        tx.rollback().expect("to rollback");
+    }
+
+    #[tokio::test]
+    async fn test_write_to_table_append_with_dedup_columns() {
+        // Test scenario: Append data with dedup columns to a table without a unique constraint
+        // Expected behavior: Rows that repeat an existing row or an earlier incoming row on the dedup columns are dropped.
+
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let cloned_pool = Arc::clone(&pool);
+        let mut conn = cloned_pool.connect_sync().expect("to connect");
+        let duckdb = DuckDB::duckdb_conn(&mut conn).expect("to get duckdb conn");
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+
+        let table_definition = get_basic_table_definition();
+
+        let append_table = TableManager::new(Arc::clone(&table_definition))
+            .with_internal(false)
+            .expect("to create table");
+
+        append_table
+            .create_table(Arc::clone(&pool), &tx)
+            .expect("to create table");
+
+        tx.execute(
+            &format!(
+                "INSERT INTO {table_name} VALUES (1, 'a')",
+                table_name = append_table.table_name()
+            ),
+            [],
+        )
+        .expect("to insert");
+
+        tx.commit().expect("to commit");
+
+        let duckdb_sink = DuckDBDataSink::new(
+            Arc::clone(&pool),
+            Arc::clone(&table_definition),
+            InsertOp::Append,
+            None,
+            table_definition.schema(),
+        )
+        .set_dedup_columns(Some(vec!["id".to_string()]));
+        let data_sink: Arc<dyn DataSink> = Arc::new(duckdb_sink);
+
+        // id, name
+        // 1, "a" (already in the table)
+        // 2, "b"
+        // 2, "b" (repeated in the same write)
+        // 3, "c"
+        let batches = vec![
+            RecordBatch::try_new(
+                Arc::clone(&table_definition.schema()),
+                vec![
+                    Arc::new(Int64Array::from(vec![Some(1), Some(2)])),
+                    Arc::new(StringArray::from(vec![Some("a"), Some("b")])),
+                ],
+            )
+            .expect("should create a record batch"),
+            RecordBatch::try_new(
+                Arc::clone(&table_definition.schema()),
+                vec![
+                    Arc::new(Int64Array::from(vec![Some(2), Some(3)])),
+                    Arc::new(StringArray::from(vec![Some("b"), Some("c")])),
+                ],
+            )
+            .expect("should create a record batch"),
+        ];
+
+        let stream = Box::pin(
+            MemoryStream::try_new(batches, table_definition.schema(), None).expect("to get stream"),
+        );
+
+        let num_rows = data_sink
+            .write_all(stream, &Arc::new(TaskContext::default()))
+            .await
+            .expect("to write all");
+        assert_eq!(num_rows, 2);
+
+        let tx = duckdb.conn.transaction().expect("to begin transaction");
+
+        let rows = tx
+            .query_row(
+                &format!(
+                    "SELECT COUNT(1) FROM {table_name}",
+                    table_name = append_table.table_name()
+                ),
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .expect("to get count");
+        assert_eq!(rows, 3);
+
+        tx.rollback().expect("to rollback");
+    }
+
+    #[tokio::test]
+    async fn test_write_invalid_batch_with_validation() {
+        let _guard = init_tracing(None);
+        let pool = get_mem_duckdb();
+
+        let table_definition = get_basic_table_definition();
+
+        let duckdb_sink = DuckDBDataSink::new(
+            Arc::clone(&pool),
+            Arc::clone(&table_definition),
+            InsertOp::Append,
+            None,
+            table_definition.schema(),
+        )
+        .set_batch_validation(true);
+        let data_sink: Arc<dyn DataSink> = Arc::new(duckdb_sink);
+
+        // the second string ends past the values buffer
+        let names = arrow::array::ArrayData::builder(arrow::datatypes::DataType::Utf8)
+            .len(2)
+            .add_buffer(arrow::buffer::Buffer::from_slice_ref([0i32, 1, 8]))
+            .add_buffer(arrow::buffer::Buffer::from_slice_ref(b"ab"));
+        let names = arrow::array::make_array(unsafe { names.build_unchecked() });
+        let batches = vec![RecordBatch::try_new(
+            Arc::clone(&table_definition.schema()),
+            vec![Arc::new(Int64Array::from(vec![Some(1), Some(2)])), names],
+        )
+        .expect("should create a record batch")];
+
+        let stream = Box::pin(
+            MemoryStream::try_new(batches, table_definition.schema(), None).expect("to get stream"),
+        );
+
+        let error = data_sink
+            .write_all(stream, &Arc::new(TaskContext::default()))
+            .await
+            .expect_err("invalid batch is rejected");
+        assert!(
+            error.to_string().contains("Invalid data in column 'name'"),
+            "{error}"
+        );
+    }
 }
@@ -25,6 +25,7 @@ use std::fmt::Debug;
 use std::sync::Arc;
 
 use crate::flight::exec::FlightExec;
+use crate::flight::profile::{ServerProfile, SERVER_PROFILE};
 use arrow_flight::error::FlightError;
 use arrow_flight::FlightInfo;
 use async_trait::async_trait;
@@ -40,6 +41,9 @@ use tonic::transport::{Channel, ClientTlsConfig};
 
 pub mod codec;
 mod exec;
+pub mod profile;
+#[cfg(feature = "flight-server")]
+pub mod server;
 pub mod sql;
 
 pub use exec::enforce_schema;
@@ -95,21 +99,33 @@ pub use exec::enforce_schema;
 #[derive(Clone, Debug)]
 pub struct FlightTableFactory {
     driver: Arc<dyn FlightDriver>,
+    server_profile: ServerProfile,
 }
 
 impl FlightTableFactory {
     /// Create a data source using the provided driver
     pub fn new(driver: Arc<dyn FlightDriver>) -> Self {
-        Self { driver }
+        Self {
+            driver,
+            server_profile: ServerProfile::default(),
+        }
+    }
+
+    /// The [ServerProfile] to use for tables that don't set the [SERVER_PROFILE] option.
+    pub fn with_server_profile(mut self, server_profile: ServerProfile) -> Self {
+        self.server_profile = server_profile;
+        self
     }
 
     /// Convenient way to create a [FlightTable] programatically, as an alternative to DDL.
     pub async fn open_table(
         &self,
         entry_point: impl Into<String>,
-        options: HashMap<String, String>,
+        mut options: HashMap<String, String>,
     ) -> datafusion::common::Result<FlightTable> {
         let origin = entry_point.into();
+        let server_profile = ServerProfile::from_options(&options)?.unwrap_or(self.server_profile);
+        options.insert(SERVER_PROFILE.to_string(), server_profile.to_string());
         let channel = flight_channel(&origin).await?;
         let metadata = self
             .driver
@@ -138,6 +154,7 @@ impl FlightTableFactory {
             origin,
             logical_schema,
             stats,
+            server_profile,
         })
     }
 }
@@ -304,6 +321,14 @@ pub struct FlightTable {
     origin: String,
     logical_schema: SchemaRef,
     stats: Statistics,
+    server_profile: ServerProfile,
+}
+
+impl FlightTable {
+    /// The [ServerProfile] of the service this table was opened from.
+    pub fn server_profile(&self) -> ServerProfile {
+        self.server_profile
+    }
 }
 
 impl std::fmt::Debug for FlightTable {
@@ -312,6 +337,7 @@ impl std::fmt::Debug for FlightTable {
             .field("origin", &self.origin)
             .field("logical_schema", &self.logical_schema)
             .field("stats", &self.stats)
+            .field("server_profile", &self.server_profile)
             .finish()
     }
 }
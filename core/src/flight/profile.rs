@@ -0,0 +1,177 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Quirk profiles for popular Flight SQL servers
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::DataFusionError;
+
+/// Table option used to select a [ServerProfile], e.g. `'server_profile' 'influxdb'`.
+pub const SERVER_PROFILE: &str = "server_profile";
+
+/// Known behaviours of a Flight SQL server that differ from the protocol defaults.
+///
+/// A profile controls:
+/// - how the server expects to be authenticated, see [Self::static_authorization]
+/// - how server-specific data types are mapped to the table schema, see [Self::map_schema]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ServerProfile {
+    /// Any server that follows the Flight SQL protocol defaults
+    #[default]
+    Generic,
+    /// InfluxDB 3, which expects a static bearer token instead of a handshake
+    /// and returns tag columns dictionary-encoded
+    InfluxDB,
+}
+
+impl ServerProfile {
+    /// Reads the profile from the [SERVER_PROFILE] table option, if present.
+    pub fn from_options(
+        options: &HashMap<String, String>,
+    ) -> Result<Option<Self>, DataFusionError> {
+        options
+            .get(SERVER_PROFILE)
+            .map(|value| value.parse())
+            .transpose()
+    }
+
+    /// Whether the server authenticates clients through the `Handshake` call.
+    pub fn supports_handshake(&self) -> bool {
+        !matches!(self, Self::InfluxDB)
+    }
+
+    /// The `authorization` header value to send with every call when the server
+    /// doesn't support the handshake, using the password option as the token.
+    pub fn static_authorization(&self, password: &str) -> Option<String> {
+        match self {
+            Self::InfluxDB => Some(format!("Bearer {password}")),
+            Self::Generic => None,
+        }
+    }
+
+    /// Maps the schema reported by the server into the schema exposed by the table.
+    /// Record batches are cast into the mapped schema when they are read.
+    pub fn map_schema(&self, schema: SchemaRef) -> SchemaRef {
+        if !self.decodes_dictionaries() {
+            return schema;
+        }
+
+        let fields: Vec<Field> = schema
+            .fields()
+            .iter()
+            .map(|field| match field.data_type() {
+                DataType::Dictionary(_, value_type) => field
+                    .as_ref()
+                    .clone()
+                    .with_data_type(value_type.as_ref().clone()),
+                _ => field.as_ref().clone(),
+            })
+            .collect();
+
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    }
+
+    fn decodes_dictionaries(&self) -> bool {
+        matches!(self, Self::InfluxDB)
+    }
+}
+
+impl FromStr for ServerProfile {
+    type Err = DataFusionError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "generic" => Ok(Self::Generic),
+            "influxdb" => Ok(Self::InfluxDB),
+            _ => Err(DataFusionError::Configuration(format!(
+                "Invalid {SERVER_PROFILE} '{value}', expected one of: generic, influxdb"
+            ))),
+        }
+    }
+}
+
+impl Display for ServerProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Generic => "generic",
+            Self::InfluxDB => "influxdb",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_profile() {
+        for profile in [ServerProfile::Generic, ServerProfile::InfluxDB] {
+            assert_eq!(
+                profile.to_string().parse::<ServerProfile>().unwrap(),
+                profile
+            );
+        }
+        assert_eq!(
+            "InfluxDB".parse::<ServerProfile>().unwrap(),
+            ServerProfile::InfluxDB
+        );
+        assert!("dremio".parse::<ServerProfile>().is_err());
+
+        let options = HashMap::from([(SERVER_PROFILE.to_string(), "influxdb".to_string())]);
+        assert_eq!(
+            ServerProfile::from_options(&options).unwrap(),
+            Some(ServerProfile::InfluxDB)
+        );
+        assert_eq!(ServerProfile::from_options(&HashMap::new()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_map_schema() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "host",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+            Field::new("usage", DataType::Float64, true),
+        ]));
+
+        let mapped = ServerProfile::InfluxDB.map_schema(Arc::clone(&schema));
+        assert_eq!(mapped.field(0).data_type(), &DataType::Utf8);
+        assert_eq!(mapped.field(1).data_type(), &DataType::Float64);
+
+        let unchanged = ServerProfile::Generic.map_schema(Arc::clone(&schema));
+        assert_eq!(unchanged, schema);
+    }
+
+    #[test]
+    fn test_authorization() {
+        assert!(!ServerProfile::InfluxDB.supports_handshake());
+        assert_eq!(
+            ServerProfile::InfluxDB.static_authorization("token"),
+            Some("Bearer token".to_string())
+        );
+        assert!(ServerProfile::Generic.supports_handshake());
+        assert_eq!(ServerProfile::Generic.static_authorization("token"), None);
+    }
+}
@@ -0,0 +1,511 @@
+//! An Arrow Flight SQL server for the tables registered in a [`SessionContext`], so that other
+//! processes can query them remotely, including the tables of the other table providers of this
+//! crate and the queries that are federated to their databases.
+
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::{FlightService, FlightServiceServer},
+    sql::{
+        server::FlightSqlService, Any, Command, CommandGetTables, CommandStatementQuery,
+        ProstMessageExt, SqlInfo, TicketStatementQuery,
+    },
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, IpcMessage, PollInfo, PutResult, SchemaAsIpc,
+    SchemaResult, Ticket,
+};
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::RecordBatch, datatypes::Schema, error::ArrowError, ipc::writer::IpcWriteOptions,
+    },
+    error::DataFusionError,
+    execution::context::SQLOptions,
+    logical_expr::TableType,
+    prelude::{DataFrame, SessionContext},
+    sql::TableReference,
+};
+use futures::{stream, Stream, TryStreamExt};
+use prost::Message;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+type FlightStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+/// Serves the tables of a [`SessionContext`] over Arrow Flight SQL.
+///
+/// Clients list the tables with `GetTables`, get the schema of a table or a query with
+/// `GetSchema`, and run queries with `CommandStatementQuery`. Only queries are run by default,
+/// see [`Self::with_sql_options`]. Prepared statements and updates aren't supported.
+///
+/// The server doesn't authenticate the clients, so it should only be reachable by trusted
+/// clients, or be wrapped in an interceptor that authenticates them.
+#[derive(Clone)]
+pub struct FlightSqlServer {
+    service: Arc<SessionService>,
+}
+
+impl FlightSqlServer {
+    #[must_use]
+    pub fn new(ctx: SessionContext) -> Self {
+        Self {
+            service: Arc::new(SessionService {
+                ctx,
+                sql_options: SQLOptions::new()
+                    .with_allow_ddl(false)
+                    .with_allow_dml(false)
+                    .with_allow_statements(false),
+            }),
+        }
+    }
+
+    /// Sets the statements that clients can run. By default, DDL, DML and statements like `SET`
+    /// are rejected, so clients can't change the tables or the session of the server.
+    #[must_use]
+    pub fn with_sql_options(self, sql_options: SQLOptions) -> Self {
+        Self {
+            service: Arc::new(SessionService {
+                ctx: self.service.ctx.clone(),
+                sql_options,
+            }),
+        }
+    }
+
+    /// The gRPC service of the server, to be served with other services.
+    #[must_use]
+    pub fn into_service(self) -> FlightServiceServer<Self> {
+        FlightServiceServer::new(self)
+    }
+
+    /// Serves the tables on `addr` until the server fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address can't be bound or the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        Server::builder()
+            .add_service(self.into_service())
+            .serve(addr)
+            .await
+    }
+}
+
+impl std::fmt::Debug for FlightSqlServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FlightSqlServer")
+            .field("sql_options", &self.service.sql_options)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The Flight SQL commands of the server. `FlightSqlService` doesn't let services answer
+/// `GetSchema`, which [`FlightSqlServer`] answers before passing the other calls on to it.
+struct SessionService {
+    ctx: SessionContext,
+    sql_options: SQLOptions,
+}
+
+impl SessionService {
+    async fn dataframe(&self, query: &str) -> Result<DataFrame, Status> {
+        self.ctx
+            .sql_with_options(query, self.sql_options)
+            .await
+            .map_err(to_status)
+    }
+
+    /// The schema of the query of a `CommandStatementQuery` descriptor, or of the table that the
+    /// path of a descriptor names.
+    async fn schema(&self, descriptor: &FlightDescriptor) -> Result<Schema, Status> {
+        if descriptor.path.is_empty() {
+            let message = Any::decode(&*descriptor.cmd)
+                .map_err(|e| Status::invalid_argument(format!("Invalid command: {e}")))?;
+            return match Command::try_from(message)
+                .map_err(|e| Status::invalid_argument(e.to_string()))?
+            {
+                Command::CommandStatementQuery(query) => {
+                    let dataframe = self.dataframe(&query.query).await?;
+                    Ok(dataframe.schema().as_arrow().clone())
+                }
+                command => Err(Status::unimplemented(format!(
+                    "GetSchema isn't supported for {}",
+                    command.type_url()
+                ))),
+            };
+        }
+
+        let table_reference = match descriptor.path.as_slice() {
+            [table] => TableReference::bare(table.as_str()),
+            [schema, table] => TableReference::partial(schema.as_str(), table.as_str()),
+            [catalog, schema, table] => {
+                TableReference::full(catalog.as_str(), schema.as_str(), table.as_str())
+            }
+            _ => {
+                return Err(Status::invalid_argument(
+                    "The path of the descriptor should be the catalog, the schema and the name of a table",
+                ))
+            }
+        };
+        let table = self
+            .ctx
+            .table_provider(table_reference)
+            .await
+            .map_err(to_status)?;
+        Ok(table.schema().as_ref().clone())
+    }
+}
+
+fn data_stream(
+    schema: Arc<Schema>,
+    batches: impl Stream<Item = Result<RecordBatch, FlightError>> + Send + 'static,
+) -> FlightStream<FlightData> {
+    Box::pin(
+        FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(batches)
+            .map_err(Status::from),
+    )
+}
+
+fn to_status(err: DataFusionError) -> Status {
+    match err {
+        DataFusionError::Plan(_)
+        | DataFusionError::SQL(_, _)
+        | DataFusionError::SchemaError(_, _) => Status::invalid_argument(err.to_string()),
+        DataFusionError::NotImplemented(_) => Status::unimplemented(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
+
+fn table_type(table_type: TableType) -> &'static str {
+    match table_type {
+        TableType::Base => "TABLE",
+        TableType::View => "VIEW",
+        TableType::Temporary => "LOCAL TEMPORARY",
+    }
+}
+
+#[async_trait]
+impl FlightSqlService for SessionService {
+    type FlightService = SessionService;
+
+    async fn do_handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<
+        Response<Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>>>,
+        Status,
+    > {
+        // the clients aren't authenticated, so there is no token to hand out
+        Ok(Response::new(Box::pin(stream::empty())))
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let dataframe = self.dataframe(&query.query).await?;
+        // the query is planned again when the ticket is redeemed
+        let ticket = TicketStatementQuery {
+            statement_handle: query.query.into(),
+        };
+        let info = FlightInfo::new()
+            .try_with_schema(dataframe.schema().as_arrow())
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_endpoint(
+                FlightEndpoint::new().with_ticket(Ticket::new(ticket.as_any().encode_to_vec())),
+            )
+            .with_descriptor(request.into_inner());
+        Ok(Response::new(info))
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        let info = FlightInfo::new()
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_endpoint(
+                FlightEndpoint::new().with_ticket(Ticket::new(query.as_any().encode_to_vec())),
+            )
+            .with_descriptor(request.into_inner());
+        Ok(Response::new(info))
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let query = String::from_utf8(ticket.statement_handle.to_vec())
+            .map_err(|_| Status::invalid_argument("Invalid statement handle"))?;
+        let stream = self
+            .dataframe(&query)
+            .await?
+            .execute_stream()
+            .await
+            .map_err(to_status)?;
+        let schema = stream.schema();
+        let batches = stream.map_err(|e| FlightError::ExternalError(Box::new(e)));
+        Ok(Response::new(data_stream(schema, batches)))
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        // the builder only keeps the tables that match the filters of the command
+        let mut builder = query.into_builder();
+        for catalog_name in self.ctx.catalog_names() {
+            let Some(catalog) = self.ctx.catalog(&catalog_name) else {
+                continue;
+            };
+            for schema_name in catalog.schema_names() {
+                let Some(schema) = catalog.schema(&schema_name) else {
+                    continue;
+                };
+                for table_name in schema.table_names() {
+                    let Some(table) = schema.table(&table_name).await.map_err(to_status)? else {
+                        continue;
+                    };
+                    builder
+                        .append(
+                            &catalog_name,
+                            &schema_name,
+                            &table_name,
+                            table_type(table.table_type()),
+                            &table.schema(),
+                        )
+                        .map_err(|e| Status::internal(e.to_string()))?;
+                }
+            }
+        }
+
+        let schema = builder.schema();
+        let batch = builder
+            .build()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(data_stream(
+            schema,
+            stream::once(async { Ok(batch) }),
+        )))
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+#[async_trait]
+impl FlightService for FlightSqlServer {
+    type HandshakeStream = FlightStream<HandshakeResponse>;
+    type ListFlightsStream = FlightStream<FlightInfo>;
+    type DoGetStream = FlightStream<FlightData>;
+    type DoPutStream = FlightStream<PutResult>;
+    type DoActionStream = FlightStream<arrow_flight::Result>;
+    type ListActionsStream = FlightStream<ActionType>;
+    type DoExchangeStream = FlightStream<FlightData>;
+
+    async fn handshake(
+        &self,
+        request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        self.service.handshake(request).await
+    }
+
+    async fn list_flights(
+        &self,
+        request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        self.service.list_flights(request).await
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        self.service.get_flight_info(request).await
+    }
+
+    async fn poll_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        self.service.poll_flight_info(request).await
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let schema = self.service.schema(request.get_ref()).await?;
+        let IpcMessage(schema) = SchemaAsIpc::new(&schema, &IpcWriteOptions::default())
+            .try_into()
+            .map_err(|e: ArrowError| Status::internal(e.to_string()))?;
+        Ok(Response::new(SchemaResult { schema }))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        self.service.do_get(request).await
+    }
+
+    async fn do_put(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        self.service.do_put(request).await
+    }
+
+    async fn do_exchange(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        self.service.do_exchange(request).await
+    }
+
+    async fn do_action(
+        &self,
+        request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        self.service.do_action(request).await
+    }
+
+    async fn list_actions(
+        &self,
+        request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        self.service.list_actions(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use arrow_flight::{
+        flight_service_client::FlightServiceClient, sql::client::FlightSqlServiceClient,
+    };
+    use datafusion::{
+        arrow::{
+            array::{Int64Array, StringArray},
+            datatypes::{DataType, Field},
+        },
+        datasource::MemTable,
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Channel;
+
+    use super::*;
+    use crate::flight::{
+        sql::{FlightSqlDriver, QUERY},
+        FlightTableFactory,
+    };
+
+    async fn serve(ctx: SessionContext) -> Result<String, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let service = FlightSqlServer::new(ctx).into_service();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        Ok(format!("http://{addr}"))
+    }
+
+    #[tokio::test]
+    async fn test_flight_sql_server() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec![Some("a"), None, Some("c")])),
+            ],
+        )?;
+        let ctx = SessionContext::new();
+        ctx.register_table(
+            "items",
+            Arc::new(MemTable::try_new(Arc::clone(&schema), vec![vec![batch]])?),
+        )?;
+        let url = serve(ctx).await?;
+
+        // the tables are queried through the Flight SQL table provider of this crate
+        let factory = FlightTableFactory::new(Arc::new(FlightSqlDriver::new()));
+        let table = factory
+            .open_table(
+                &url,
+                HashMap::from([(
+                    QUERY.to_string(),
+                    "SELECT id, name FROM items WHERE id > 1".to_string(),
+                )]),
+            )
+            .await?;
+        let client_ctx = SessionContext::new();
+        client_ctx.register_table("remote_items", Arc::new(table))?;
+        let batches = client_ctx
+            .sql("SELECT id FROM remote_items ORDER BY id")
+            .await?
+            .collect()
+            .await?;
+        let ids = batches
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .column(0)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .expect("id column")
+                    .values()
+                    .to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3]);
+
+        let channel = Channel::from_shared(url)?.connect().await?;
+        let mut client = FlightSqlServiceClient::new(channel.clone());
+        let info = client
+            .get_tables(CommandGetTables {
+                table_name_filter_pattern: Some("item%".to_string()),
+                include_schema: true,
+                ..Default::default()
+            })
+            .await?;
+        let ticket = info.endpoint[0].ticket.clone().expect("ticket");
+        let tables = client.do_get(ticket).await?.try_collect::<Vec<_>>().await?;
+        let names = tables[0]
+            .column_by_name("table_name")
+            .expect("table_name column")
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .expect("table names")
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![Some("items")]);
+
+        let mut client = FlightServiceClient::new(channel.clone());
+        let result = client
+            .get_schema(FlightDescriptor::new_path(vec!["items".to_string()]))
+            .await?
+            .into_inner();
+        assert_eq!(Schema::try_from(&result)?, *schema);
+
+        // statements that change the tables are rejected
+        let mut client = FlightSqlServiceClient::new(channel);
+        assert!(client
+            .execute("DROP TABLE items".to_string(), None)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -18,12 +18,14 @@
 //! Default [FlightDriver] for Flight SQL
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use arrow_flight::error::Result;
+use arrow_flight::error::{FlightError, Result};
 use arrow_flight::sql::client::FlightSqlServiceClient;
 use async_trait::async_trait;
 use tonic::transport::Channel;
 
+use crate::flight::profile::ServerProfile;
 use crate::flight::{FlightDriver, FlightMetadata, FlightProperties};
 
 pub const QUERY: &str = "flight.sql.query";
@@ -39,6 +41,8 @@ pub const HEADER_PREFIX: &str = "flight.sql.header.";
 /// If a token is returned by the server with the handshake response, it will be
 /// stored as a gRPC authorization header within the returned [FlightMetadata],
 /// to be sent with the subsequent `DoGet` requests.
+/// Server-specific authentication and type mapping conventions are selected through the
+/// [ServerProfile] table option.
 #[derive(Clone, Debug, Default)]
 pub struct FlightSqlDriver {
     properties_template: FlightProperties,
@@ -73,6 +77,9 @@ impl FlightDriver for FlightSqlDriver {
         channel: Channel,
         options: &HashMap<String, String>,
     ) -> Result<FlightMetadata> {
+        let profile = ServerProfile::from_options(options)
+            .map_err(|e| FlightError::ExternalError(Box::new(e)))?
+            .unwrap_or_default();
         let mut client = FlightSqlServiceClient::new(channel);
         let mut handshake_headers = self.properties_template.grpc_headers.clone();
         let headers_overlay = options.iter().filter_map(|(key, value)| {
@@ -80,10 +87,19 @@ impl FlightDriver for FlightSqlDriver {
                 .map(|header_name| (header_name.to_owned(), value.to_owned()))
         });
         handshake_headers.extend(headers_overlay);
+        let static_authorization = options
+            .get(PASSWORD)
+            .and_then(|password| profile.static_authorization(password));
+        if let Some(authorization) = &static_authorization {
+            handshake_headers.insert("authorization".into(), authorization.clone());
+        }
         for (name, value) in &handshake_headers {
             client.set_header(name, value)
         }
-        if let Some(username) = options.get(USERNAME) {
+        let handshake_username = options
+            .get(USERNAME)
+            .filter(|_| profile.supports_handshake());
+        if let Some(username) = handshake_username {
             let default_password = "".to_string();
             let password = options.get(PASSWORD).unwrap_or(&default_password);
             client.handshake(username, password).await.ok();
@@ -96,11 +112,14 @@ impl FlightDriver for FlightSqlDriver {
         };
         if let Some(token) = client.token() {
             partition_headers.insert("authorization".into(), format!("Bearer {token}"));
+        } else if let Some(authorization) = static_authorization {
+            partition_headers.insert("authorization".into(), authorization);
         }
         let props = self
             .properties_template
             .clone()
             .with_grpc_headers(partition_headers);
-        FlightMetadata::try_new(info, props)
+        let schema = profile.map_schema(Arc::new(info.clone().try_decode_schema()?));
+        Ok(FlightMetadata::new(info, props, schema))
     }
 }
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
 
 pub mod common;
+pub mod prelude;
 pub mod sql;
 pub mod util;
 
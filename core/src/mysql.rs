@@ -14,22 +14,42 @@ See the License for the specific language governing permissions and
 limitations under the License.
 */
 use crate::mysql::write::MySQLTableWriter;
+use crate::sql::approx_distinct::ApproxDistinct;
 use crate::sql::arrow_sql_gen::statement::{CreateTableBuilder, IndexBuilder, InsertBuilder};
+use crate::sql::column_expressions::{self, ColumnExpressions};
+use crate::sql::column_mapper::ColumnMapper;
+use crate::sql::datetime::DateTimeSyntax;
 use crate::sql::db_connection_pool::dbconnection::mysqlconn::MySQLConnection;
 use crate::sql::db_connection_pool::dbconnection::DbConnection;
 use crate::sql::db_connection_pool::mysqlpool::MySQLConnectionPool;
+use crate::sql::db_connection_pool::pool_selector::PoolSelector;
 use crate::sql::db_connection_pool::{self, mysqlpool, DbConnectionPool};
+use crate::sql::dialect::DialectOverrides;
+use crate::sql::dml::{self, quote_table_reference, DmlOperation};
+use crate::sql::json::JsonSyntax;
+use crate::sql::null_ordering::NullOrdering;
+use crate::sql::permissions::{probe_permissions, PermissionReport};
+use crate::sql::pushdown::{FilterSemantics, PushdownPolicy};
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::query_settings::QuerySettings;
+use crate::sql::schema_drift::SchemaDriftPolicy;
 use crate::sql::sql_provider_datafusion::{self, SqlTable};
 use crate::util::{
     self, column_reference::ColumnReference, constraints::get_primary_keys_from_constraints,
-    indexes::IndexType, on_conflict::OnConflict, secrets::to_secret_map, to_datafusion_error,
+    identifier::IdentifierCase, indexes::IndexType, on_conflict::OnConflict,
+    retriable_error::MAX_BATCH_RETRIES, secrets::to_secret_map, to_datafusion_error,
+};
+use crate::util::{
+    column_names::{self, ColumnNamePolicy, ColumnRenames, IdentifierRules},
+    column_reference, constraints, on_conflict,
+    schema_mismatch::{self, SchemaMismatchMode},
+    validation,
 };
-use crate::util::{column_reference, constraints, on_conflict};
 use async_trait::async_trait;
 use datafusion::arrow::array::RecordBatch;
 use datafusion::arrow::datatypes::{Schema, SchemaRef};
 use datafusion::catalog::Session;
-use datafusion::sql::unparser::dialect::MySqlDialect;
+use datafusion::sql::unparser::dialect::{Dialect, MySqlDialect};
 use datafusion::{
     catalog::TableProviderFactory, common::Constraints, datasource::TableProvider,
     error::DataFusionError, logical_expr::CreateExternalTable, sql::TableReference,
@@ -41,6 +61,7 @@ use snafu::prelude::*;
 use sql_table::MySQLTable;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub type DynMySQLConnectionPool =
     dyn DbConnectionPool<mysql_async::Conn, &'static (dyn ToValue + Sync)> + Send + Sync;
@@ -50,6 +71,7 @@ pub type DynMySQLConnection = dyn DbConnection<mysql_async::Conn, &'static (dyn
 #[cfg(feature = "mysql-federation")]
 pub mod federation;
 pub(crate) mod mysql_window;
+pub mod sharded;
 pub mod sql_table;
 pub mod write;
 
@@ -68,6 +90,17 @@ pub enum Error {
     #[snafu(display("Unable to delete all data from the MySQL table: {source}"))]
     UnableToDeleteAllTableData { source: mysql_async::Error },
 
+    #[snafu(display("Unable to create the DML statement for the MySQL table: {source}"))]
+    UnableToCreateDmlStatement { source: dml::Error },
+
+    #[snafu(display("Unable to execute the DML statement on the MySQL table: {source}"))]
+    UnableToExecuteDmlStatement {
+        source: db_connection_pool::dbconnection::GenericError,
+    },
+
+    #[snafu(display("Unable to manage the savepoint in the MySQL transaction: {source}"))]
+    UnableToManageSavepoint { source: mysql_async::Error },
+
     #[snafu(display("Unable to insert Arrow batch to MySQL table: {source}"))]
     UnableToInsertArrowBatch { source: mysql_async::Error },
 
@@ -105,37 +138,298 @@ pub enum Error {
 
     #[snafu(display("Error parsing on_conflict: {source}"))]
     UnableToParseOnConflict { source: on_conflict::Error },
+
+    #[snafu(display("The data to insert is invalid: {source}"))]
+    InvalidBatch { source: validation::Error },
+
+    #[snafu(display("Error parsing validate_batches: {source}"))]
+    UnableToParseValidateBatches { source: validation::Error },
+
+    #[snafu(display("Error parsing schema_mismatch: {source}"))]
+    UnableToParseSchemaMismatchMode { source: schema_mismatch::Error },
+
+    #[snafu(display("Unable to write to the MySQL table '{table_name}': {source}"))]
+    SchemaMismatch {
+        table_name: String,
+        source: schema_mismatch::Error,
+    },
+
+    #[snafu(display("Invalid column defaults or generated columns: {source}"))]
+    InvalidColumnExpressions { source: column_expressions::Error },
+
+    #[snafu(display("Invalid column names: {source}"))]
+    InvalidColumnNames { source: column_names::Error },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// MySQL server error code for `ER_LOCK_WAIT_TIMEOUT`, which only rolls back the failing statement.
+const ER_LOCK_WAIT_TIMEOUT: u16 = 1205;
+
+/// Whether a batch that failed with the server error `code` can be retried from a savepoint. A
+/// deadlock (`ER_LOCK_DEADLOCK`) rolls back the whole transaction, savepoints included, so only
+/// lock wait timeouts are retried.
+fn is_transient_server_error(code: u16) -> bool {
+    code == ER_LOCK_WAIT_TIMEOUT
+}
+
 pub struct MySQLTableFactory {
     pool: Arc<MySQLConnectionPool>,
+    identifier_case: IdentifierCase,
+    dictionary_columns: Vec<String>,
+    validate_batches: bool,
+    schema_mismatch: SchemaMismatchMode,
+    schema_drift: SchemaDriftPolicy,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    dialect_overrides: DialectOverrides,
+    remote_explain: bool,
+    pushdown_policy: PushdownPolicy,
+    filter_semantics: FilterSemantics,
+    approx_distinct: Option<ApproxDistinct>,
+    bind_literals: bool,
+    query_context: Option<QueryContextMode>,
+    query_settings: QuerySettings,
+    column_mapper: Option<ColumnMapper>,
 }
 
 impl MySQLTableFactory {
     #[must_use]
     pub fn new(pool: Arc<MySQLConnectionPool>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            identifier_case: IdentifierCase::default(),
+            dictionary_columns: Vec::new(),
+            validate_batches: false,
+            schema_mismatch: SchemaMismatchMode::default(),
+            schema_drift: SchemaDriftPolicy::default(),
+            spill_buffer: None,
+            memory_wait: None,
+            dialect_overrides: DialectOverrides::default(),
+            remote_explain: false,
+            pushdown_policy: PushdownPolicy::default(),
+            filter_semantics: FilterSemantics::mysql(),
+            approx_distinct: None,
+            bind_literals: false,
+            query_context: None,
+            query_settings: QuerySettings::default(),
+            column_mapper: None,
+        }
+    }
+
+    /// Sets how table references are normalized before they are looked up. Whether MySQL table
+    /// names are case-sensitive depends on `lower_case_table_names` and the server's file system.
+    #[must_use]
+    pub fn with_identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+        self.identifier_case = identifier_case;
+        self
+    }
+
+    /// Customizes how scalar functions are unparsed for the remote database, see
+    /// [`DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect_overrides(mut self, dialect_overrides: DialectOverrides) -> Self {
+        self.dialect_overrides = dialect_overrides;
+        self
+    }
+
+    /// Returns the given string columns of the tables from [`Self::table_provider`] as
+    /// dictionary-encoded arrays, see [`crate::util::dictionary::dictionary_encode_schema`].
+    /// Read-write table providers always return plain strings, so they can be written to.
+    #[must_use]
+    pub fn with_dictionary_columns(mut self, columns: Vec<String>) -> Self {
+        self.dictionary_columns = columns;
+        self
+    }
+
+    /// Sets what scans of the tables do when the columns returned by the database no longer match
+    /// the schema the table was registered with, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(mut self, schema_drift: SchemaDriftPolicy) -> Self {
+        self.schema_drift = schema_drift;
+        self
+    }
+
+    /// Buffers the unread batches of scans of the tables, keeping up to `memory_batches` in memory
+    /// and the rest on disk, so slow consumers don't hold on to connections, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(mut self, memory_batches: Option<usize>) -> Self {
+        self.spill_buffer = memory_batches;
+        self
+    }
+
+    /// Waits up to `wait` for the memory pool to have the memory of every batch of scans of the
+    /// tables, see [`SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(mut self, wait: Option<Duration>) -> Self {
+        self.memory_wait = wait;
+        self
+    }
+
+    /// Shows the plan of the remote database for every scan in the `EXPLAIN` output, see
+    /// [`SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(mut self, remote_explain: bool) -> Self {
+        self.remote_explain = remote_explain;
+        self
+    }
+
+    /// Sets what scans of the tables push down to the database, see [`PushdownPolicy`].
+    /// The tables are only federated if the policy allows it.
+    #[must_use]
+    pub fn with_pushdown_policy(mut self, pushdown_policy: PushdownPolicy) -> Self {
+        self.pushdown_policy = pushdown_policy;
+        self
+    }
+
+    /// Sets how MySQL compares strings in the filters and sorts that are pushed down,
+    /// [`FilterSemantics::mysql`] by default, e.g. [`FilterSemantics::exact`] for the tables with
+    /// binary collations, see [`SqlTable::with_filter_semantics`].
+    #[must_use]
+    pub fn with_filter_semantics(mut self, filter_semantics: FilterSemantics) -> Self {
+        self.filter_semantics = filter_semantics;
+        self
+    }
+
+    /// Counts the `approx_distinct` aggregates of federated queries exactly on the database with
+    /// [`ApproxDistinct::Exact`], as it has no estimate of its own, see
+    /// [`SqlTable::with_approx_distinct`].
+    #[must_use]
+    pub fn with_approx_distinct(mut self, approx_distinct: Option<ApproxDistinct>) -> Self {
+        self.approx_distinct = approx_distinct;
+        self
+    }
+
+    /// Binds the string literals of scans as parameters instead of quoting them, see
+    /// [`SqlTable::with_bind_literals`].
+    /// Queries that are federated inline their literals, so the tables aren't federated then.
+    #[must_use]
+    pub fn with_bind_literals(mut self, bind_literals: bool) -> Self {
+        self.bind_literals = bind_literals;
+        self
+    }
+
+    /// Passes the query context of the session to the database with the queries of the tables,
+    /// see [`SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(mut self, query_context: Option<QueryContextMode>) -> Self {
+        self.query_context = query_context;
+        self
+    }
+
+    /// Passes `query_settings` to the database with the queries of the tables, see
+    /// [`SqlTable::with_query_settings`].
+    #[must_use]
+    pub fn with_query_settings(mut self, query_settings: QuerySettings) -> Self {
+        self.query_settings = query_settings;
+        self
+    }
+
+    /// Validates the batches written through [`Self::read_write_table_provider`] before inserting
+    /// them, see [`crate::util::validation::validate_batch`].
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    /// Sets what the writes through [`Self::read_write_table_provider`] do with batches whose
+    /// columns differ from the ones of the table, see [`SchemaMismatchMode`].
+    #[must_use]
+    pub fn with_schema_mismatch_mode(mut self, schema_mismatch: SchemaMismatchMode) -> Self {
+        self.schema_mismatch = schema_mismatch;
+        self
+    }
+
+    /// Exposes the columns of the tables from [`Self::table_provider`] with the names of
+    /// `column_mapper`, see [`ColumnMapper`]. Read-write table providers keep the names of the
+    /// columns in MySQL, so they can be written to.
+    #[must_use]
+    pub fn with_column_mapper(mut self, column_mapper: ColumnMapper) -> Self {
+        self.column_mapper = Some(column_mapper);
+        self
     }
 
     pub async fn table_provider(
         &self,
         table_reference: TableReference,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        self.read_provider(
+            table_reference,
+            None,
+            &self.dictionary_columns,
+            self.column_mapper.as_ref(),
+        )
+        .await
+    }
+
+    /// A read-only table of the rows of `query`, a MySQL `SELECT`, named `table_reference` in the
+    /// SQL of its scans, see [`SqlTable::from_query`].
+    pub async fn query_table_provider(
+        &self,
+        table_reference: TableReference,
+        query: &str,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        self.read_provider(
+            table_reference,
+            Some(query),
+            &self.dictionary_columns,
+            self.column_mapper.as_ref(),
+        )
+        .await
+    }
+
+    async fn read_provider(
+        &self,
+        table_reference: TableReference,
+        query: Option<&str>,
+        dictionary_columns: &[String],
+        column_mapper: Option<&ColumnMapper>,
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
         let pool = Arc::clone(&self.pool);
-        let table_provider = Arc::new(
-            MySQLTable::new(&pool, table_reference)
-                .await
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?,
-        );
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let table_provider = match query {
+            Some(query) => MySQLTable::from_query(&pool, table_reference, query).await,
+            None => MySQLTable::new(&pool, table_reference).await,
+        };
+        let mut table_provider = table_provider
+            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?
+            .with_dialect(
+                self.identifier_case
+                    .dialect(self.dialect_overrides.dialect(mysql_dialect())),
+            )
+            .with_dictionary_columns(dictionary_columns)
+            .with_schema_drift(self.schema_drift)
+            .with_spill_buffer(self.spill_buffer)
+            .with_memory_wait(self.memory_wait)
+            .with_remote_explain(self.remote_explain)
+            .with_pushdown_policy(self.pushdown_policy.clone())
+            .with_filter_semantics(self.filter_semantics)
+            .with_approx_distinct(self.approx_distinct)
+            .with_bind_literals(self.bind_literals)
+            .with_query_context(self.query_context.clone())
+            .with_query_settings(self.query_settings.clone());
+        if let Some(column_mapper) = column_mapper {
+            table_provider = table_provider
+                .with_column_mapper(column_mapper)
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+        }
+        let table_provider = Arc::new(table_provider);
 
         #[cfg(feature = "mysql-federation")]
-        let table_provider = Arc::new(
-            table_provider
-                .create_federated_table_provider()
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?,
-        );
+        let table_provider: Arc<dyn TableProvider> =
+            if self.pushdown_policy.allows_federation() && !self.bind_literals {
+                Arc::new(
+                    table_provider
+                        .create_federated_table_provider()
+                        .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?,
+                )
+            } else {
+                table_provider
+            };
 
         Ok(table_provider)
     }
@@ -144,7 +438,12 @@ impl MySQLTableFactory {
         &self,
         table_reference: TableReference,
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
-        let read_provider = Self::table_provider(self, table_reference.clone()).await?;
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let read_provider = self
+            .read_provider(table_reference.clone(), None, &[], None)
+            .await?;
         let schema = read_provider.schema();
 
         let table_name = table_reference.to_string();
@@ -153,23 +452,73 @@ impl MySQLTableFactory {
             Arc::clone(&self.pool),
             schema,
             Constraints::empty(),
-        );
+        )
+        .with_batch_validation(self.validate_batches)
+        .with_schema_mismatch_mode(self.schema_mismatch);
 
         Ok(MySQLTableWriter::create(read_provider, mysql, None))
     }
 
+    /// Checks whether the configured credentials can `SELECT` from the table, and `INSERT` into it
+    /// if `writable`, by running statements against the table that match no rows.
+    pub async fn validate_permissions(
+        &self,
+        table_reference: TableReference,
+        writable: bool,
+    ) -> Result<PermissionReport, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let conn = self.pool.connect().await?;
+        let dialect = self.identifier_case.dialect(mysql_dialect());
+        let table = quote_table_reference(&table_reference, dialect.as_ref());
+
+        Ok(probe_permissions(conn, table_reference, &table, writable).await)
+    }
+
     pub fn conn_pool_metrics(&self) -> Arc<Metrics> {
         self.pool.metrics()
     }
 }
 
+/// The dialect of MySQL tables, with JSON functions pushed down as `JSON_EXTRACT` and date
+/// functions as `DATE_FORMAT`, see [`DateTimeSyntax`].
+pub(crate) fn mysql_dialect() -> Arc<dyn Dialect + Send + Sync> {
+    let dialect = JsonSyntax::MySql.dialect(Arc::new(MySqlDialect {}));
+    NullOrdering::Smallest.dialect(DateTimeSyntax::MySql.dialect(dialect))
+}
+
 #[derive(Debug)]
-pub struct MySQLTableProviderFactory {}
+pub struct MySQLTableProviderFactory {
+    pools: PoolSelector<MySQLConnectionPool>,
+}
 
 impl MySQLTableProviderFactory {
     #[must_use]
     pub fn new() -> Self {
-        Self {}
+        Self {
+            pools: PoolSelector::new(),
+        }
+    }
+
+    /// Creates the table `table` with `pool` instead of a pool of its options, see
+    /// [`PoolSelector`].
+    #[must_use]
+    pub fn with_table_pool(
+        mut self,
+        table: impl Into<TableReference>,
+        pool: Arc<MySQLConnectionPool>,
+    ) -> Self {
+        self.pools = self.pools.with_table_pool(table, pool);
+        self
+    }
+
+    /// Creates the tables with the pools of `pools` instead of pools of their options, where it
+    /// has one for them.
+    #[must_use]
+    pub fn with_pool_selector(mut self, pools: PoolSelector<MySQLConnectionPool>) -> Self {
+        self.pools = pools;
+        self
     }
 }
 
@@ -190,6 +539,30 @@ impl TableProviderFactory for MySQLTableProviderFactory {
         let mut options = cmd.options.clone();
         let schema: Schema = cmd.schema.as_ref().into();
 
+        // the columns are renamed first, the options that name columns use the names of the
+        // statement
+        let column_name_policy = options
+            .remove("column_name_policy")
+            .map(|value| {
+                value
+                    .parse::<ColumnNamePolicy>()
+                    .context(InvalidColumnNamesSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let (schema, column_renames) = column_name_policy
+            .apply(&Arc::new(schema), &IdentifierRules::mysql())
+            .context(InvalidColumnNamesSnafu)
+            .map_err(to_datafusion_error)?;
+        for rename in column_renames.renames() {
+            tracing::info!(
+                "Creating the column '{}' of the MySQL table '{name}' as '{}'",
+                rename.original,
+                rename.renamed
+            );
+        }
+
         let indexes_option_str = options.remove("indexes");
         let unparsed_indexes: HashMap<String, IndexType> = match indexes_option_str {
             Some(indexes_str) => util::hashmap_from_option_string(&indexes_str),
@@ -200,6 +573,7 @@ impl TableProviderFactory for MySQLTableProviderFactory {
             .into_iter()
             .map(|(key, value)| {
                 let columns = ColumnReference::try_from(key.as_str())
+                    .map(|columns| column_renames.column_reference(&columns))
                     .context(UnableToParseColumnReferenceSnafu)
                     .map_err(util::to_datafusion_error);
                 (columns, value)
@@ -216,26 +590,64 @@ impl TableProviderFactory for MySQLTableProviderFactory {
         if let Some(on_conflict_str) = options.remove("on_conflict") {
             on_conflict = Some(
                 OnConflict::try_from(on_conflict_str.as_str())
+                    .map(|on_conflict| column_renames.on_conflict(&on_conflict))
                     .context(UnableToParseOnConflictSnafu)
                     .map_err(util::to_datafusion_error)?,
             );
         }
 
-        let params = to_secret_map(options);
-
-        let pool = Arc::new(
-            MySQLConnectionPool::new(params)
-                .await
-                .context(UnableToCreateMySQLConnectionPoolSnafu)
-                .map_err(to_datafusion_error)?,
-        );
-        let schema = Arc::new(schema);
+        let validate_batches = options
+            .remove("validate_batches")
+            .map(|value| {
+                validation::parse_validate_batches(&value)
+                    .context(UnableToParseValidateBatchesSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let schema_mismatch = options
+            .remove("schema_mismatch")
+            .map(|value| {
+                SchemaMismatchMode::try_from(value.as_str())
+                    .context(UnableToParseSchemaMismatchModeSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let column_expressions = ColumnExpressions::try_from_table_options(
+            &cmd.column_defaults,
+            &mut options,
+            &MySqlDialect {},
+        )
+        .and_then(|column_expressions| {
+            let column_expressions = column_expressions.with_renamed_columns(&column_renames);
+            column_expressions.validate(&schema)?;
+            Ok(column_expressions)
+        })
+        .context(InvalidColumnExpressionsSnafu)
+        .map_err(to_datafusion_error)?;
+
+        let pool = match self.pools.select(&cmd.name, &cmd.options) {
+            Some(pool) => pool,
+            None => Arc::new(
+                MySQLConnectionPool::new(to_secret_map(options))
+                    .await
+                    .context(UnableToCreateMySQLConnectionPoolSnafu)
+                    .map_err(to_datafusion_error)?,
+            ),
+        };
         let mysql = MySQL::new(
             name.clone(),
             Arc::clone(&pool),
             Arc::clone(&schema),
             cmd.constraints.clone(),
-        );
+        )
+        .with_batch_validation(validate_batches)
+        .with_schema_mismatch_mode(schema_mismatch)
+        .with_column_expressions(column_expressions)
+        .with_column_renames(column_renames);
 
         let mut db_conn = pool
             .connect()
@@ -286,7 +698,7 @@ impl TableProviderFactory for MySQLTableProviderFactory {
                 Arc::clone(&schema),
                 TableReference::bare(name.clone()),
             )
-            .with_dialect(Arc::new(MySqlDialect {})),
+            .with_dialect(mysql_dialect()),
         );
 
         #[cfg(feature = "mysql-federation")]
@@ -301,6 +713,10 @@ pub struct MySQL {
     pool: Arc<MySQLConnectionPool>,
     schema: SchemaRef,
     constraints: Constraints,
+    validate_batches: bool,
+    schema_mismatch: SchemaMismatchMode,
+    column_expressions: ColumnExpressions,
+    column_renames: ColumnRenames,
 }
 
 impl MySQL {
@@ -316,14 +732,80 @@ impl MySQL {
             pool,
             schema,
             constraints,
+            validate_batches: false,
+            schema_mismatch: SchemaMismatchMode::default(),
+            column_expressions: ColumnExpressions::default(),
+            column_renames: ColumnRenames::default(),
         }
     }
 
+    /// Checks each batch with [`validation::validate_batch`] before it is inserted, so malformed
+    /// arrays are rejected with the offending column instead of failing while building the `INSERT`.
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    /// Sets what writes do with batches whose columns differ from the ones of the table: fail
+    /// with the added, missing and retyped columns, or cast the retyped ones.
+    #[must_use]
+    pub fn with_schema_mismatch_mode(mut self, schema_mismatch: SchemaMismatchMode) -> Self {
+        self.schema_mismatch = schema_mismatch;
+        self
+    }
+
+    /// Sets the column defaults and generated columns the table is created with. MySQL rejects
+    /// values for generated columns, so they are dropped from the inserted rows.
+    #[must_use]
+    pub fn with_column_expressions(mut self, column_expressions: ColumnExpressions) -> Self {
+        self.column_expressions = column_expressions;
+        self
+    }
+
+    #[must_use]
+    pub fn column_expressions(&self) -> &ColumnExpressions {
+        &self.column_expressions
+    }
+
+    /// Records the columns that were renamed when the table was created, see
+    /// [`ColumnNamePolicy::Rename`].
+    #[must_use]
+    pub fn with_column_renames(mut self, column_renames: ColumnRenames) -> Self {
+        self.column_renames = column_renames;
+        self
+    }
+
+    /// The columns that were renamed when the table was created, from the names of the schema it
+    /// was created from to the names of the columns in MySQL.
+    #[must_use]
+    pub fn column_renames(&self) -> &ColumnRenames {
+        &self.column_renames
+    }
+
     #[must_use]
     pub fn table_name(&self) -> &str {
         &self.table_name
     }
 
+    /// Checks that the batch has the same column names and types as the table, casting the
+    /// retyped columns if [`Self::with_schema_mismatch_mode`] is lenient.
+    pub fn reconcile_batch_schema(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        schema_mismatch::reconcile_batch(batch, &self.schema, self.schema_mismatch).context(
+            SchemaMismatchSnafu {
+                table_name: self.table_name.clone(),
+            },
+        )
+    }
+
+    /// Validates the batch if [`Self::with_batch_validation`] is enabled.
+    pub fn validate_batch(&self, batch: &RecordBatch) -> Result<()> {
+        if self.validate_batches {
+            validation::validate_batch(batch).context(InvalidBatchSnafu)?;
+        }
+        Ok(())
+    }
+
     #[must_use]
     pub fn constraints(&self) -> &Constraints {
         &self.constraints
@@ -353,6 +835,26 @@ impl MySQL {
         Ok(conn)
     }
 
+    /// Runs a `DELETE` or `UPDATE` against the table, returning the number of affected rows.
+    pub async fn execute_dml(&self, operation: &DmlOperation) -> Result<u64> {
+        let sql = operation
+            .to_sql(
+                &TableReference::bare(self.table_name.clone()),
+                &MySqlDialect {},
+            )
+            .context(UnableToCreateDmlStatementSnafu)?;
+        tracing::trace!("{sql}");
+
+        let db_conn = self.connect().await?;
+        let conn = db_conn
+            .as_async()
+            .context(UnableToDowncastDbConnectionSnafu)?;
+
+        conn.execute(&sql, &[])
+            .await
+            .context(UnableToExecuteDmlStatementSnafu)
+    }
+
     async fn table_exists(&self, mysql_connection: &MySQLConnection) -> bool {
         let sql = format!(
             r#"SELECT EXISTS (
@@ -376,17 +878,69 @@ impl MySQL {
         exists
     }
 
+    /// Inserts the batch under a savepoint, so that a lock wait timeout only retries this batch
+    /// instead of aborting the whole write transaction.
+    ///
+    /// Deadlocks are not retried here: InnoDB rolls back the entire transaction of the deadlock victim.
+    async fn insert_batch_with_retry(
+        &self,
+        transaction: &mut mysql_async::Transaction<'_>,
+        batch: RecordBatch,
+        on_conflict: Option<OnConflict>,
+    ) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            transaction
+                .exec_drop("SAVEPOINT insert_batch", ())
+                .await
+                .context(UnableToManageSavepointSnafu)?;
+
+            match self
+                .insert_batch(transaction, batch.clone(), on_conflict.clone())
+                .await
+            {
+                Ok(()) => {
+                    return transaction
+                        .exec_drop("RELEASE SAVEPOINT insert_batch", ())
+                        .await
+                        .context(UnableToManageSavepointSnafu);
+                }
+                Err(Error::UnableToInsertArrowBatch {
+                    source: mysql_async::Error::Server(server_error),
+                }) if attempt < MAX_BATCH_RETRIES
+                    && is_transient_server_error(server_error.code) =>
+                {
+                    attempt += 1;
+                    tracing::debug!(
+                        "Retrying batch insert into {table_name} ({attempt}/{MAX_BATCH_RETRIES}): {server_error}",
+                        table_name = self.table_name
+                    );
+                    transaction
+                        .exec_drop("ROLLBACK TO SAVEPOINT insert_batch", ())
+                        .await
+                        .context(UnableToManageSavepointSnafu)?;
+                    tokio::time::sleep(util::retriable_error::batch_retry_backoff(attempt)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     async fn insert_batch(
         &self,
         transaction: &mut mysql_async::Transaction<'_>,
         batch: RecordBatch,
         on_conflict: Option<OnConflict>,
     ) -> Result<()> {
+        let batch = self
+            .column_expressions
+            .remove_generated_columns(batch)
+            .context(InvalidColumnExpressionsSnafu)?;
+        let schema = batch.schema();
         let insert_table_builder =
             InsertBuilder::new(&TableReference::bare(self.table_name.clone()), vec![batch]);
 
-        let sea_query_on_conflict =
-            on_conflict.map(|oc| oc.build_sea_query_on_conflict(&self.schema));
+        let sea_query_on_conflict = on_conflict.map(|oc| oc.build_sea_query_on_conflict(&schema));
 
         let sql = insert_table_builder
             .build_mysql(sea_query_on_conflict)
@@ -421,8 +975,9 @@ impl MySQL {
         transaction: &mut mysql_async::Transaction<'_>,
         primary_keys: Vec<String>,
     ) -> Result<()> {
-        let create_table_statement =
-            CreateTableBuilder::new(schema, &self.table_name).primary_keys(primary_keys);
+        let create_table_statement = CreateTableBuilder::new(schema, &self.table_name)
+            .primary_keys(primary_keys)
+            .column_expressions(self.column_expressions.clone());
         let create_stmts = create_table_statement.build_mysql();
 
         transaction
@@ -449,3 +1004,17 @@ impl MySQL {
             .context(UnableToCreateIndexForMySQLTableSnafu)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_server_errors() {
+        assert!(is_transient_server_error(ER_LOCK_WAIT_TIMEOUT));
+        // ER_LOCK_DEADLOCK
+        assert!(!is_transient_server_error(1213));
+        // ER_DUP_ENTRY
+        assert!(!is_transient_server_error(1062));
+    }
+}
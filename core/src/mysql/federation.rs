@@ -1,5 +1,5 @@
 use crate::sql::db_connection_pool::dbconnection::{get_schema, Error as DbError};
-use crate::sql::sql_provider_datafusion::{get_stream, to_execution_error};
+use crate::sql::sql_provider_datafusion::{get_stream_with_params, to_execution_error};
 use arrow::datatypes::SchemaRef;
 use async_trait::async_trait;
 use datafusion::sql::sqlparser::ast::{self, VisitMut};
@@ -75,7 +75,12 @@ impl SQLExecutor for MySQLTable {
     }
 
     fn ast_analyzer(&self) -> Option<AstAnalyzer> {
-        Some(Box::new(mysql_ast_analyzer))
+        let Some(base_analyzer) = self.base_table.ast_analyzer() else {
+            return Some(Box::new(mysql_ast_analyzer));
+        };
+        // the base analyzer rewrites the order of the NULLs of the window functions before the
+        // window visitor removes it
+        Some(Box::new(move |ast| mysql_ast_analyzer(base_analyzer(ast)?)))
     }
 
     fn execute(
@@ -83,10 +88,13 @@ impl SQLExecutor for MySQLTable {
         query: &str,
         schema: SchemaRef,
     ) -> DataFusionResult<SendableRecordBatchStream> {
-        let fut = get_stream(
+        let fut = get_stream_with_params(
             self.base_table.clone_pool(),
-            query.to_string(),
+            self.base_table.apply_query_settings(query),
+            Vec::new(),
+            self.base_table.current_remote_context(),
             Arc::clone(&schema),
+            self.base_table.schema_drift(),
         );
 
         let stream = futures::stream::once(fut).try_flatten();
@@ -0,0 +1,358 @@
+//! A MySQL table that is split across several servers by the value of a shard key column.
+
+use std::{any::Any, collections::BTreeSet, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::datatypes::SchemaRef,
+    catalog::Session,
+    common::{project_schema, ScalarValue},
+    datasource::TableProvider,
+    error::Result as DataFusionResult,
+    logical_expr::{
+        expr::InList, BinaryExpr, Expr, Operator, TableProviderFilterPushDown, TableType,
+    },
+    physical_plan::{empty::EmptyExec, union::UnionExec, ExecutionPlan},
+    sql::TableReference,
+};
+use snafu::prelude::*;
+
+use super::sql_table::MySQLTable;
+use crate::sql::{db_connection_pool::mysqlpool::MySQLConnectionPool, sql_provider_datafusion};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("A sharded table needs at least one shard"))]
+    NoShards,
+
+    #[snafu(display("Unable to construct the table of shard {shard}: {source}"))]
+    UnableToConstructShardTable {
+        shard: usize,
+        source: sql_provider_datafusion::Error,
+    },
+
+    #[snafu(display(
+        "The table of shard {shard} has different columns than the table of the first shard"
+    ))]
+    MismatchedShardSchema { shard: usize },
+
+    #[snafu(display("The shard key column '{column}' is not a column of the table"))]
+    UnknownShardKey { column: String },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Returns the shard of the rows that have a value of the shard key out of the number of shards,
+/// or `None` if the shard can't be told from the value.
+pub type ShardFn = dyn Fn(&ScalarValue, usize) -> Option<usize> + Send + Sync;
+
+/// The column that a table is sharded by and how its values map to the shards.
+#[derive(Clone)]
+pub struct ShardKey {
+    column: String,
+    shard_of: Arc<ShardFn>,
+}
+
+impl ShardKey {
+    #[must_use]
+    pub fn new(
+        column: impl Into<String>,
+        shard_of: impl Fn(&ScalarValue, usize) -> Option<usize> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            column: column.into(),
+            shard_of: Arc::new(shard_of),
+        }
+    }
+
+    /// Shards an integer column by the remainder of its values divided by the number of shards,
+    /// so that the shard of the key `k` is `k mod N`.
+    #[must_use]
+    pub fn modulo(column: impl Into<String>) -> Self {
+        Self::new(column, |value, shards| {
+            let shards = i128::try_from(shards).ok()?;
+            let shard = integer_value(value)?.rem_euclid(shards);
+            usize::try_from(shard).ok()
+        })
+    }
+
+    #[must_use]
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    /// The shards that the rows matching all of `filters` can be on, or `None` if they can be on
+    /// any shard.
+    fn shards_of_filters(&self, filters: &[Expr], shards: usize) -> Option<BTreeSet<usize>> {
+        filters
+            .iter()
+            .filter_map(|filter| self.shards_of(filter, shards))
+            .reduce(intersection)
+    }
+
+    fn shards_of(&self, expr: &Expr, shards: usize) -> Option<BTreeSet<usize>> {
+        match expr {
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::And,
+                right,
+            }) => [self.shards_of(left, shards), self.shards_of(right, shards)]
+                .into_iter()
+                .flatten()
+                .reduce(intersection),
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Or,
+                right,
+            }) => {
+                let left = self.shards_of(left, shards)?;
+                let right = self.shards_of(right, shards)?;
+                Some(left.union(&right).copied().collect())
+            }
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Eq,
+                right,
+            }) => match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(column), Expr::Literal(value))
+                | (Expr::Literal(value), Expr::Column(column))
+                    if column.name == self.column =>
+                {
+                    self.shard_of(value, shards).map(|shard| [shard].into())
+                }
+                _ => None,
+            },
+            Expr::InList(InList {
+                expr,
+                list,
+                negated: false,
+            }) => match expr.as_ref() {
+                Expr::Column(column) if column.name == self.column => list
+                    .iter()
+                    .map(|value| match value {
+                        Expr::Literal(value) => self.shard_of(value, shards),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn shard_of(&self, value: &ScalarValue, shards: usize) -> Option<usize> {
+        (self.shard_of)(value, shards).filter(|shard| *shard < shards)
+    }
+}
+
+impl fmt::Debug for ShardKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardKey")
+            .field("column", &self.column)
+            .finish_non_exhaustive()
+    }
+}
+
+fn intersection(left: BTreeSet<usize>, right: BTreeSet<usize>) -> BTreeSet<usize> {
+    left.intersection(&right).copied().collect()
+}
+
+fn integer_value(value: &ScalarValue) -> Option<i128> {
+    match value {
+        ScalarValue::Int8(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::Int16(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::Int32(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::Int64(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::UInt8(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::UInt16(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::UInt32(Some(value)) => Some(i128::from(*value)),
+        ScalarValue::UInt64(Some(value)) => Some(i128::from(*value)),
+        _ => None,
+    }
+}
+
+/// A table whose rows are split across the same table on several MySQL servers, the shards, by
+/// the value of a shard key column.
+///
+/// Scans read all the shards at the same time, each shard as a partition of the scan. Scans with
+/// filters on the shard key that only match rows of some shards, like `id = 42` or `id IN (1, 2)`,
+/// only read those shards. The table isn't federated, since the queries of a federated table run
+/// on a single server.
+pub struct ShardedMySQLTableProvider {
+    shards: Vec<MySQLTable>,
+    shard_key: ShardKey,
+}
+
+impl ShardedMySQLTableProvider {
+    /// The table `table_reference` on each of the shards of `pools`, in the order of the shards
+    /// that `shard_key` maps its values to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no shards, the table can't be found on a shard, the columns
+    /// of the table differ between shards, or the shard key isn't a column of the table.
+    pub async fn new(
+        pools: Vec<Arc<MySQLConnectionPool>>,
+        table_reference: impl Into<TableReference>,
+        shard_key: ShardKey,
+    ) -> Result<Self> {
+        ensure!(!pools.is_empty(), NoShardsSnafu);
+        let table_reference = table_reference.into();
+
+        let mut shards: Vec<MySQLTable> = Vec::with_capacity(pools.len());
+        for (shard, pool) in pools.iter().enumerate() {
+            let table = MySQLTable::new(pool, table_reference.clone())
+                .await
+                .context(UnableToConstructShardTableSnafu { shard })?;
+            if let Some(first) = shards.first() {
+                ensure!(
+                    first.schema().fields() == table.schema().fields(),
+                    MismatchedShardSchemaSnafu { shard }
+                );
+            }
+            shards.push(table);
+        }
+        ensure!(
+            shards[0]
+                .schema()
+                .column_with_name(&shard_key.column)
+                .is_some(),
+            UnknownShardKeySnafu {
+                column: shard_key.column.clone(),
+            }
+        );
+
+        Ok(Self { shards, shard_key })
+    }
+
+    #[must_use]
+    pub fn shard_key(&self) -> &ShardKey {
+        &self.shard_key
+    }
+
+    /// The shards that a scan with `filters` reads.
+    fn scanned_shards(&self, filters: &[Expr]) -> Vec<&MySQLTable> {
+        match self.shard_key.shards_of_filters(filters, self.shards.len()) {
+            Some(shards) => shards
+                .into_iter()
+                .map(|shard| &self.shards[shard])
+                .collect(),
+            None => self.shards.iter().collect(),
+        }
+    }
+}
+
+impl fmt::Debug for ShardedMySQLTableProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedMySQLTableProvider")
+            .field("shards", &self.shards)
+            .field("shard_key", &self.shard_key)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl TableProvider for ShardedMySQLTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.shards[0].schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        self.shards[0].table_type()
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> DataFusionResult<Vec<TableProviderFilterPushDown>> {
+        // the filters are applied the same way on every shard
+        self.shards[0].supports_filters_pushdown(filters)
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let mut plans = Vec::new();
+        for shard in self.scanned_shards(filters) {
+            plans.push(shard.scan(state, projection, filters, limit).await?);
+        }
+
+        match plans.len() {
+            // the filters on the shard key contradict each other
+            0 => Ok(Arc::new(EmptyExec::new(project_schema(
+                &self.schema(),
+                projection,
+            )?))),
+            1 => Ok(plans.remove(0)),
+            _ => Ok(Arc::new(UnionExec::new(plans))),
+        }
+    }
+}
+
+impl fmt::Display for ShardedMySQLTableProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ShardedMySQLTableProvider {} shards={}",
+            self.shards[0].base_table.name(),
+            self.shards.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use super::*;
+
+    #[test]
+    fn test_shards_of_filters() {
+        let key = ShardKey::modulo("id");
+        let shards = |filters: &[Expr]| {
+            key.shards_of_filters(filters, 4)
+                .map(|shards| shards.into_iter().collect::<Vec<_>>())
+        };
+
+        assert_eq!(shards(&[col("id").eq(lit(6_i64))]), Some(vec![2]));
+        assert_eq!(shards(&[lit(-1_i32).eq(col("id"))]), Some(vec![3]));
+        assert_eq!(
+            shards(&[col("id").in_list(vec![lit(1_i64), lit(5_i64), lit(2_i64)], false)]),
+            Some(vec![1, 2])
+        );
+        assert_eq!(
+            shards(&[col("id").eq(lit(1_i64)).or(col("id").eq(lit(3_i64)))]),
+            Some(vec![1, 3])
+        );
+        assert_eq!(
+            shards(&[
+                col("name").eq(lit("a")),
+                col("id").in_list(vec![lit(1_i64), lit(2_i64)], false),
+                col("id").eq(lit(2_i64)),
+            ]),
+            Some(vec![2])
+        );
+        assert_eq!(
+            shards(&[col("id").eq(lit(1_i64)).and(col("id").eq(lit(2_i64)))]),
+            Some(vec![])
+        );
+
+        // all the shards are scanned when the filters don't tell the shard
+        assert_eq!(shards(&[]), None);
+        assert_eq!(shards(&[col("id").gt(lit(1_i64))]), None);
+        assert_eq!(shards(&[col("id").in_list(vec![lit(1_i64)], true)]), None);
+        assert_eq!(
+            shards(&[col("id").eq(lit(1_i64)).or(col("name").eq(lit("a")))]),
+            None
+        );
+        assert_eq!(shards(&[col("id").eq(lit("1"))]), None);
+    }
+}
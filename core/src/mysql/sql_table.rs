@@ -1,15 +1,25 @@
+use crate::mysql::mysql_dialect;
+use crate::sql::approx_distinct::ApproxDistinct;
+use crate::sql::column_mapper::ColumnMapper;
 use crate::sql::db_connection_pool::mysqlpool::MySQLConnectionPool;
 use crate::sql::db_connection_pool::DbConnectionPool;
+use crate::sql::parameters::BoundStatement;
+use crate::sql::pushdown::{FilterSemantics, PushdownPolicy};
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::query_settings::QuerySettings;
+use crate::sql::schema_drift::SchemaDriftPolicy;
+use crate::util::memory::limit_stream_memory;
 use async_trait::async_trait;
 use datafusion::catalog::Session;
-use datafusion::sql::unparser::dialect::MySqlDialect;
-use futures::TryStreamExt;
+use datafusion::sql::unparser::dialect::Dialect;
 use mysql_async::prelude::ToValue;
 use std::fmt::Display;
-use std::{any::Any, fmt, sync::Arc};
+use std::{any::Any, fmt, sync::Arc, time::Duration};
 
 use crate::sql::sql_provider_datafusion::{
-    self, get_stream, to_execution_error, Result as SqlResult, SqlExec, SqlTable,
+    self,
+    explain::{RemoteExplainer, RemotePlan},
+    get_stream_with_params, to_execution_error, Result as SqlResult, SqlExec, SqlTable,
 };
 use datafusion::{
     arrow::datatypes::SchemaRef,
@@ -18,8 +28,8 @@ use datafusion::{
     execution::TaskContext,
     logical_expr::{Expr, TableProviderFilterPushDown, TableType},
     physical_plan::{
-        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
-        PlanProperties, SendableRecordBatchStream,
+        metrics::MetricsSet, DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties,
+        SendableRecordBatchStream,
     },
     sql::TableReference,
 };
@@ -50,7 +60,8 @@ impl MySQLTable {
             >;
         let base_table = SqlTable::new("mysql", &dyn_pool, table_reference)
             .await?
-            .with_dialect(Arc::new(MySqlDialect {}));
+            .with_dialect(mysql_dialect())
+            .with_filter_semantics(FilterSemantics::mysql());
 
         Ok(Self {
             pool: Arc::clone(pool),
@@ -58,20 +69,199 @@ impl MySQLTable {
         })
     }
 
+    /// A table of the rows of `query`, a MySQL `SELECT`, see [`SqlTable::from_query`].
+    pub async fn from_query(
+        pool: &Arc<MySQLConnectionPool>,
+        table_reference: impl Into<TableReference>,
+        query: &str,
+    ) -> Result<Self, sql_provider_datafusion::Error> {
+        let dyn_pool = Arc::clone(pool)
+            as Arc<
+                dyn DbConnectionPool<mysql_async::Conn, &'static (dyn ToValue + Sync)>
+                    + Send
+                    + Sync,
+            >;
+        let base_table = SqlTable::from_query("mysql", &dyn_pool, table_reference, query)
+            .await?
+            .with_dialect(mysql_dialect())
+            .with_filter_semantics(FilterSemantics::mysql());
+
+        Ok(Self {
+            pool: Arc::clone(pool),
+            base_table,
+        })
+    }
+
+    #[must_use]
+    pub fn with_dialect(self, dialect: Arc<dyn Dialect + Send + Sync>) -> Self {
+        Self {
+            base_table: self.base_table.with_dialect(dialect),
+            ..self
+        }
+    }
+
+    /// Returns the given string columns dictionary-encoded, see
+    /// [`crate::util::dictionary::dictionary_encode_schema`].
+    #[must_use]
+    pub fn with_dictionary_columns(self, columns: &[String]) -> Self {
+        Self {
+            base_table: self.base_table.with_dictionary_columns(columns),
+            ..self
+        }
+    }
+
+    /// Sets what scans do when the columns returned by the database no longer match the schema of
+    /// the table, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(self, schema_drift: SchemaDriftPolicy) -> Self {
+        Self {
+            base_table: self.base_table.with_schema_drift(schema_drift),
+            ..self
+        }
+    }
+
+    /// Buffers up to `memory_batches` batches of each scan in memory and the rest on disk, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(self, memory_batches: Option<usize>) -> Self {
+        Self {
+            base_table: self.base_table.with_spill_buffer(memory_batches),
+            ..self
+        }
+    }
+
+    /// Waits up to `wait` for the memory of every batch of scans, see
+    /// [`SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            base_table: self.base_table.with_memory_wait(wait),
+            ..self
+        }
+    }
+
+    /// Shows the plan of the remote database for every scan in the `EXPLAIN` output, see
+    /// [`SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(self, remote_explain: bool) -> Self {
+        Self {
+            base_table: self.base_table.with_remote_explain(remote_explain),
+            ..self
+        }
+    }
+
+    /// Explains the SQL of the scans of the table on the remote database, if remote explains are
+    /// enabled.
+    pub(crate) fn remote_explainer(&self) -> Option<Arc<dyn RemoteExplainer>> {
+        self.base_table.remote_explainer()
+    }
+
+    /// The SQL that `plan` runs on the database, if it's a scan of a MySQL table.
+    pub(crate) fn scan_sql(plan: &dyn ExecutionPlan) -> Option<String> {
+        plan.as_any().downcast_ref::<MySQLSQLExec>()?.sql().ok()
+    }
+
+    /// Sets what scans push down to the database, see [`SqlTable::with_pushdown_policy`].
+    #[must_use]
+    pub fn with_pushdown_policy(self, pushdown_policy: PushdownPolicy) -> Self {
+        Self {
+            base_table: self.base_table.with_pushdown_policy(pushdown_policy),
+            ..self
+        }
+    }
+
+    /// Sets how MySQL compares the strings of the filters that are pushed down, e.g. exactly for
+    /// tables with binary collations, see [`SqlTable::with_filter_semantics`].
+    #[must_use]
+    pub fn with_filter_semantics(self, filter_semantics: FilterSemantics) -> Self {
+        Self {
+            base_table: self.base_table.with_filter_semantics(filter_semantics),
+            ..self
+        }
+    }
+
+    /// Sets how the `approx_distinct` aggregates of federated queries are pushed down, see
+    /// [`SqlTable::with_approx_distinct`].
+    #[must_use]
+    pub fn with_approx_distinct(self, approx_distinct: Option<ApproxDistinct>) -> Self {
+        Self {
+            base_table: self.base_table.with_approx_distinct(approx_distinct),
+            ..self
+        }
+    }
+
+    /// Binds the string literals of scans as parameters, see [`SqlTable::with_bind_literals`].
+    #[must_use]
+    pub fn with_bind_literals(self, bind_literals: bool) -> Self {
+        Self {
+            base_table: self.base_table.with_bind_literals(bind_literals),
+            ..self
+        }
+    }
+
+    /// Passes the query context of the session to the database, see
+    /// [`SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            base_table: self.base_table.with_query_context(query_context),
+            ..self
+        }
+    }
+
+    /// Reads the rows of `query` instead of the table, see [`SqlTable::with_query`].
+    #[must_use]
+    pub fn with_query(self, query: impl Into<String>) -> Self {
+        Self {
+            base_table: self.base_table.with_query(query),
+            ..self
+        }
+    }
+
+    /// Passes `query_settings` to the database as optimizer hints, see
+    /// [`SqlTable::with_query_settings`].
+    #[must_use]
+    pub fn with_query_settings(self, query_settings: QuerySettings) -> Self {
+        Self {
+            base_table: self.base_table.with_query_settings(query_settings),
+            ..self
+        }
+    }
+
+    /// Exposes the columns with the names of `column_mapper`, see
+    /// [`SqlTable::with_column_mapper`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if two columns are mapped to the same name.
+    pub fn with_column_mapper(self, column_mapper: &ColumnMapper) -> SqlResult<Self> {
+        Ok(Self {
+            base_table: self.base_table.with_column_mapper(column_mapper)?,
+            ..self
+        })
+    }
+
     fn create_physical_plan(
         &self,
         projections: Option<&Vec<usize>>,
         schema: &SchemaRef,
-        filters: &[Expr],
-        limit: Option<usize>,
+        bound: BoundStatement,
+        remote_plan: Option<RemotePlan>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        let sql = self.base_table.scan_to_sql(projections, filters, limit)?;
-        Ok(Arc::new(MySQLSQLExec::new(
-            projections,
-            schema,
-            Arc::clone(&self.pool),
-            sql,
-        )?))
+        Ok(Arc::new(
+            MySQLSQLExec::new(
+                projections,
+                schema,
+                Arc::clone(&self.pool),
+                bound.sql,
+                self.base_table.schema_drift(),
+                self.base_table.spill_buffer(),
+            )?
+            .with_params(bound.params)
+            .with_query_context(self.base_table.query_context().cloned())
+            .with_memory_wait(self.base_table.memory_wait())
+            .with_remote_plan(remote_plan),
+        ))
     }
 }
 
@@ -103,7 +293,12 @@ impl TableProvider for MySQLTable {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        return self.create_physical_plan(projection, &self.schema(), filters, limit);
+        let statement = self
+            .base_table
+            .scan_to_statement(projection, filters, limit)?;
+        let remote_plan = self.base_table.remote_plan_of_statement(&statement);
+        let bound = self.base_table.bind_statement(statement);
+        return self.create_physical_plan(projection, &self.schema(), bound, remote_plan);
     }
 }
 
@@ -123,12 +318,40 @@ impl MySQLSQLExec {
         schema: &SchemaRef,
         pool: Arc<MySQLConnectionPool>,
         sql: String,
+        schema_drift: SchemaDriftPolicy,
+        spill_buffer: Option<usize>,
     ) -> DataFusionResult<Self> {
-        let base_exec = SqlExec::new(projections, schema, pool, sql)?;
+        let base_exec = SqlExec::new(projections, schema, pool, sql)?
+            .with_schema_drift(schema_drift)
+            .with_spill_buffer(spill_buffer);
 
         Ok(Self { base_exec })
     }
 
+    fn with_params(self, params: Vec<String>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_params(params),
+        }
+    }
+
+    fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_memory_wait(wait),
+        }
+    }
+
+    fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_query_context(query_context),
+        }
+    }
+
+    fn with_remote_plan(self, remote_plan: Option<RemotePlan>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_remote_plan(remote_plan),
+        }
+    }
+
     fn sql(&self) -> SqlResult<String> {
         self.base_exec.sql()
     }
@@ -144,7 +367,8 @@ impl std::fmt::Debug for MySQLSQLExec {
 impl DisplayAs for MySQLSQLExec {
     fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> std::fmt::Result {
         let sql = self.sql().unwrap_or_default();
-        write!(f, "MySQLSQLExec sql={sql}")
+        write!(f, "MySQLSQLExec sql={sql}")?;
+        self.base_exec.fmt_remote_plan(f)
     }
 }
 
@@ -176,18 +400,33 @@ impl ExecutionPlan for MySQLSQLExec {
         Ok(self)
     }
 
+    fn metrics(&self) -> Option<MetricsSet> {
+        self.base_exec.metrics()
+    }
+
     fn execute(
         &self,
-        _partition: usize,
-        _context: Arc<TaskContext>,
+        partition: usize,
+        context: Arc<TaskContext>,
     ) -> DataFusionResult<SendableRecordBatchStream> {
         let sql = self.sql().map_err(to_execution_error)?;
         tracing::debug!("MySQLSQLExec sql: {sql}");
 
-        let fut = get_stream(self.base_exec.clone_pool(), sql, Arc::clone(&self.schema()));
+        let fut = get_stream_with_params(
+            self.base_exec.clone_pool(),
+            sql,
+            self.base_exec.params().to_vec(),
+            self.base_exec.remote_context(&context),
+            Arc::clone(&self.schema()),
+            self.base_exec.schema_drift(),
+        );
 
-        let stream = futures::stream::once(fut).try_flatten();
-        let schema = Arc::clone(&self.schema());
-        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+        Ok(limit_stream_memory(
+            self.base_exec.scan_stream(partition, fut),
+            &context,
+            self.base_exec.spill_buffer(),
+            self.base_exec.memory_wait(),
+            format!("MySQLSQLExec[{partition}]"),
+        ))
     }
 }
@@ -1,4 +1,6 @@
 use crate::mysql::MySQL;
+use crate::sql::dml::{DmlOperation, DmlTableProvider};
+use crate::util::column_names::ColumnRenames;
 use crate::util::on_conflict::OnConflict;
 use crate::util::retriable_error::check_and_mark_retriable_error;
 use crate::util::{constraints, to_datafusion_error};
@@ -45,6 +47,12 @@ impl MySQLTableWriter {
     pub fn mysql(&self) -> Arc<MySQL> {
         Arc::clone(&self.mysql)
     }
+
+    /// The columns that were renamed when the table was created, see
+    /// [`ColumnNamePolicy`](crate::util::column_names::ColumnNamePolicy).
+    pub fn column_renames(&self) -> &ColumnRenames {
+        self.mysql.column_renames()
+    }
 }
 
 #[async_trait]
@@ -61,6 +69,10 @@ impl TableProvider for MySQLTableWriter {
         TableType::Base
     }
 
+    fn get_column_default(&self, column: &str) -> Option<&Expr> {
+        self.mysql.column_expressions().default_expr(column)
+    }
+
     async fn scan(
         &self,
         state: &dyn Session,
@@ -92,6 +104,16 @@ impl TableProvider for MySQLTableWriter {
     }
 }
 
+#[async_trait]
+impl DmlTableProvider for MySQLTableWriter {
+    async fn execute_dml(&self, operation: &DmlOperation) -> datafusion::error::Result<u64> {
+        self.mysql
+            .execute_dml(operation)
+            .await
+            .map_err(to_datafusion_error)
+    }
+}
+
 pub struct MySQLDataSink {
     pub mysql: Arc<MySQL>,
     pub overwrite: bool,
@@ -147,6 +169,14 @@ impl DataSink for MySQLDataSink {
 
             num_rows += batch_num_rows as u64;
 
+            let batch = self
+                .mysql
+                .reconcile_batch_schema(batch)
+                .map_err(to_datafusion_error)?;
+            self.mysql
+                .validate_batch(&batch)
+                .map_err(to_datafusion_error)?;
+
             constraints::validate_batch_with_constraints(
                 &[batch.clone()],
                 self.mysql.constraints(),
@@ -156,7 +186,7 @@ impl DataSink for MySQLDataSink {
             .map_err(to_datafusion_error)?;
 
             self.mysql
-                .insert_batch(&mut tx, batch, self.on_conflict.clone())
+                .insert_batch_with_retry(&mut tx, batch, self.on_conflict.clone())
                 .await
                 .map_err(to_datafusion_error)?;
         }
@@ -1,18 +1,34 @@
+use crate::sql::approx_distinct::ApproxDistinct;
 use crate::sql::arrow_sql_gen::statement::{
     CreateTableBuilder, Error as SqlGenError, IndexBuilder, InsertBuilder,
 };
+use crate::sql::column_expressions::{self, ColumnExpressions};
+use crate::sql::column_mapper::ColumnMapper;
+use crate::sql::datetime::DateTimeSyntax;
 use crate::sql::db_connection_pool::{
     self,
     dbconnection::{postgresconn::PostgresConnection, DbConnection},
+    pool_selector::PoolSelector,
     postgrespool::{self, PostgresConnectionPool},
     DbConnectionPool,
 };
+use crate::sql::dialect::DialectOverrides;
+use crate::sql::dml::{self, DmlOperation};
+use crate::sql::full_text::FullTextSearch;
+use crate::sql::json::JsonSyntax;
+use crate::sql::permissions::{PermissionReport, Privilege};
+use crate::sql::pushdown::{FilterSemantics, PushdownPolicy};
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::query_settings::QuerySettings;
+use crate::sql::sample::TableSample;
+use crate::sql::schema_drift::SchemaDriftPolicy;
 use crate::sql::sql_provider_datafusion::SqlTable;
-use crate::util::schema::SchemaValidator;
+use crate::util::{retriable_error::MAX_BATCH_RETRIES, schema::SchemaValidator};
 use crate::UnsupportedTypeAction;
 use arrow::{
     array::RecordBatch,
     datatypes::{Schema, SchemaRef},
+    error::ArrowError,
 };
 use async_trait::async_trait;
 use bb8_postgres::{
@@ -20,10 +36,10 @@ use bb8_postgres::{
     PostgresConnectionManager,
 };
 use datafusion::catalog::Session;
-use datafusion::sql::unparser::dialect::PostgreSqlDialect;
+use datafusion::sql::unparser::dialect::{Dialect, PostgreSqlDialect};
 use datafusion::{
     catalog::TableProviderFactory,
-    common::Constraints,
+    common::{stats::Precision, Constraints, Statistics},
     datasource::TableProvider,
     error::{DataFusionError, Result as DataFusionResult},
     logical_expr::CreateExternalTable,
@@ -31,20 +47,30 @@ use datafusion::{
 };
 use postgres_native_tls::MakeTlsConnector;
 use snafu::prelude::*;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio_postgres::error::SqlState;
 
 use crate::util::{
     self,
+    column_names::{self, ColumnNamePolicy, ColumnRenames, IdentifierRules},
     column_reference::{self, ColumnReference},
     constraints::{self, get_primary_keys_from_constraints},
+    dedup::{self, Deduplicator},
+    identifier::IdentifierCase,
     indexes::IndexType,
     on_conflict::{self, OnConflict},
+    schema_mismatch::{self, SchemaMismatchMode},
     secrets::to_secret_map,
-    to_datafusion_error,
+    to_datafusion_error, validation,
 };
 
+use self::notify::PostgresNotifyListener;
+use self::partition::{Inheritance, PartitionRouter, PartitionRouting};
 use self::write::PostgresTableWriter;
 
+pub mod notify;
+pub mod partition;
+pub mod transaction;
 pub mod write;
 
 pub type DynPostgresConnectionPool = dyn DbConnectionPool<
@@ -93,16 +119,91 @@ pub enum Error {
     #[snafu(display("Unable to generate SQL: {source}"))]
     UnableToGenerateSQL { source: DataFusionError },
 
+    #[snafu(display("Unable to delete data from the Postgres table: {source}"))]
+    UnableToDeleteData {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Unable to create the DML statement for the Postgres table: {source}"))]
+    UnableToCreateDmlStatement { source: dml::Error },
+
+    #[snafu(display("Unable to execute the DML statement on the Postgres table: {source}"))]
+    UnableToExecuteDmlStatement {
+        source: db_connection_pool::dbconnection::GenericError,
+    },
+
     #[snafu(display("Unable to delete all data from the Postgres table: {source}"))]
     UnableToDeleteAllTableData {
         source: tokio_postgres::error::Error,
     },
 
-    #[snafu(display("Unable to delete data from the Postgres table: {source}"))]
-    UnableToDeleteData {
+    #[snafu(display(
+        "The table '{table_name}' does not use the connection pool of the write transaction"
+    ))]
+    WriteTransactionPoolMismatch { table_name: String },
+
+    #[snafu(display("Error routing inserts to the table partitions: {source}"))]
+    PartitionRoutingError { source: partition::Error },
+
+    #[snafu(display("Unable to load the inheritance tree of the Postgres table: {source}"))]
+    UnableToLoadInheritance { source: partition::Error },
+
+    #[snafu(display("Unable to create the staging table for the Postgres table: {source}"))]
+    UnableToCreateStagingTable {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Unable to swap the staging table into the Postgres table: {source}"))]
+    UnableToSwapStagingTable {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Unable to query the objects that depend on the Postgres table: {source}"))]
+    UnableToQueryDependents {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Invalid overwrite_mode value '{value}', expected 'delete' or 'swap'"))]
+    InvalidOverwriteMode { value: String },
+
+    #[snafu(display("Unable to create a savepoint in the Postgres transaction: {source}"))]
+    UnableToCreateSavepoint {
         source: tokio_postgres::error::Error,
     },
 
+    #[snafu(display("Unable to release the savepoint in the Postgres transaction: {source}"))]
+    UnableToReleaseSavepoint {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display(
+        "Unable to roll back to the savepoint in the Postgres transaction: {source}"
+    ))]
+    UnableToRollbackSavepoint {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Failed to get system time since epoch: {source}"))]
+    UnableToGetSystemTime { source: std::time::SystemTimeError },
+
+    #[snafu(display("Unable to drop duplicate rows from the data to insert: {source}"))]
+    UnableToDeduplicateBatch { source: ArrowError },
+
+    #[snafu(display("The data to insert is invalid: {source}"))]
+    InvalidBatch { source: validation::Error },
+
+    #[snafu(display("Error parsing validate_batches: {source}"))]
+    UnableToParseValidateBatches { source: validation::Error },
+
+    #[snafu(display("Error parsing schema_mismatch: {source}"))]
+    UnableToParseSchemaMismatchMode { source: schema_mismatch::Error },
+
+    #[snafu(display("Invalid column defaults or generated columns: {source}"))]
+    InvalidColumnExpressions { source: column_expressions::Error },
+
+    #[snafu(display("Invalid column names: {source}"))]
+    InvalidColumnNames { source: column_names::Error },
+
     #[snafu(display("Unable to insert Arrow batch to Postgres table: {source}"))]
     UnableToInsertArrowBatch {
         source: tokio_postgres::error::Error,
@@ -128,42 +229,390 @@ pub enum Error {
     ))]
     TableWithSchemaCreationNotSupported { table_name: String },
 
-    #[snafu(display("Schema validation error: the provided data schema does not match the expected table schema: '{table_name}'"))]
-    SchemaValidationError { table_name: String },
+    #[snafu(display("Unable to write to the Postgres table '{table_name}': {source}"))]
+    SchemaMismatch {
+        table_name: String,
+        source: schema_mismatch::Error,
+    },
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// How `INSERT OVERWRITE` replaces the rows of a Postgres table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwriteMode {
+    /// Deletes the existing rows in the write transaction before inserting the new ones. Concurrent
+    /// readers keep seeing the previous rows until the transaction commits.
+    #[default]
+    Delete,
+    /// Loads the new rows into a copy of the table made with `LIKE ... INCLUDING ALL`, then drops
+    /// the table and renames the copy in its place, which avoids the dead rows of a `DELETE`.
+    ///
+    /// The swap needs to own the table and takes an `ACCESS EXCLUSIVE` lock on it. The new table
+    /// loses the grants, triggers, row-level security policies and owner of the old one, and its
+    /// indexes and constraints are named after the staging table. Tables that are partitioned,
+    /// referenced by views or foreign keys, or own the sequence of a serial or identity column
+    /// can't be swapped and are overwritten with [`OverwriteMode::Delete`] instead.
+    Swap,
+}
+
+impl TryFrom<&str> for OverwriteMode {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "delete" => Ok(OverwriteMode::Delete),
+            "swap" => Ok(OverwriteMode::Swap),
+            _ => InvalidOverwriteModeSnafu { value }.fail(),
+        }
+    }
+}
+
 pub struct PostgresTableFactory {
     pool: Arc<PostgresConnectionPool>,
+    partition_routing: PartitionRouting,
+    overwrite_mode: OverwriteMode,
+    identifier_case: IdentifierCase,
+    full_text_search: Option<FullTextSearch>,
+    only: bool,
+    dictionary_columns: Vec<String>,
+    validate_batches: bool,
+    schema_mismatch: SchemaMismatchMode,
+    schema_drift: SchemaDriftPolicy,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    dialect_overrides: DialectOverrides,
+    remote_explain: bool,
+    pushdown_policy: PushdownPolicy,
+    filter_semantics: FilterSemantics,
+    sample: Option<TableSample>,
+    approx_distinct: Option<ApproxDistinct>,
+    bind_literals: bool,
+    query_context: Option<QueryContextMode>,
+    query_settings: QuerySettings,
+    column_mapper: Option<ColumnMapper>,
 }
 
 impl PostgresTableFactory {
     #[must_use]
     pub fn new(pool: Arc<PostgresConnectionPool>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            partition_routing: PartitionRouting::default(),
+            overwrite_mode: OverwriteMode::default(),
+            identifier_case: IdentifierCase::default(),
+            full_text_search: None,
+            only: false,
+            dictionary_columns: Vec::new(),
+            validate_batches: false,
+            schema_mismatch: SchemaMismatchMode::default(),
+            schema_drift: SchemaDriftPolicy::default(),
+            spill_buffer: None,
+            memory_wait: None,
+            dialect_overrides: DialectOverrides::default(),
+            remote_explain: false,
+            pushdown_policy: PushdownPolicy::default(),
+            filter_semantics: FilterSemantics::default(),
+            sample: None,
+            approx_distinct: None,
+            bind_literals: false,
+            query_context: None,
+            query_settings: QuerySettings::default(),
+            column_mapper: None,
+        }
+    }
+
+    /// Sets how table references are normalized before they are looked up. Postgres folds unquoted
+    /// names to lower case, so use [`IdentifierCase::Lower`] to find tables created without quotes.
+    #[must_use]
+    pub fn with_identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+        self.identifier_case = identifier_case;
+        self
+    }
+
+    /// Enables pushing down `match()` predicates on the given text columns as full-text searches,
+    /// see [`crate::sql::full_text::match_udf`].
+    #[must_use]
+    pub fn with_full_text_search(mut self, full_text_search: FullTextSearch) -> Self {
+        self.full_text_search = Some(full_text_search);
+        self
+    }
+
+    /// Customizes how scalar functions are unparsed for the remote database, see
+    /// [`DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect_overrides(mut self, dialect_overrides: DialectOverrides) -> Self {
+        self.dialect_overrides = dialect_overrides;
+        self
+    }
+
+    /// Sets how writes from [`Self::read_write_table_provider`] are routed into partitioned tables.
+    #[must_use]
+    pub fn with_partition_routing(mut self, partition_routing: PartitionRouting) -> Self {
+        self.partition_routing = partition_routing;
+        self
+    }
+
+    /// Sets how overwrites from [`Self::read_write_table_provider`] replace the rows of the table.
+    #[must_use]
+    pub fn with_overwrite_mode(mut self, overwrite_mode: OverwriteMode) -> Self {
+        self.overwrite_mode = overwrite_mode;
+        self
+    }
+
+    /// Scans tables with `FROM ONLY`, so the rows of child tables that inherit from a table are
+    /// excluded. By default scans of a parent or partitioned table include the rows of all of its
+    /// children and partitions.
+    #[must_use]
+    pub fn with_only(mut self, only: bool) -> Self {
+        self.only = only;
+        self
+    }
+
+    /// Returns the given string columns of the tables from [`Self::table_provider`] as
+    /// dictionary-encoded arrays, see [`crate::util::dictionary::dictionary_encode_schema`].
+    /// Read-write table providers always return plain strings, so they can be written to.
+    #[must_use]
+    pub fn with_dictionary_columns(mut self, columns: Vec<String>) -> Self {
+        self.dictionary_columns = columns;
+        self
+    }
+
+    /// Sets what scans of the tables do when the columns returned by the database no longer match
+    /// the schema the table was registered with, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(mut self, schema_drift: SchemaDriftPolicy) -> Self {
+        self.schema_drift = schema_drift;
+        self
+    }
+
+    /// Buffers the unread batches of scans of the tables, keeping up to `memory_batches` in memory
+    /// and the rest on disk, so slow consumers don't hold on to connections, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(mut self, memory_batches: Option<usize>) -> Self {
+        self.spill_buffer = memory_batches;
+        self
+    }
+
+    /// Waits up to `wait` for the memory pool to have the memory of every batch of scans of the
+    /// tables, see [`SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(mut self, wait: Option<Duration>) -> Self {
+        self.memory_wait = wait;
+        self
+    }
+
+    /// Shows the plan of the remote database for every scan in the `EXPLAIN` output, see
+    /// [`SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(mut self, remote_explain: bool) -> Self {
+        self.remote_explain = remote_explain;
+        self
+    }
+
+    /// Sets what scans of the tables push down to the database, see [`PushdownPolicy`].
+    /// The tables are only federated if the policy allows it.
+    #[must_use]
+    pub fn with_pushdown_policy(mut self, pushdown_policy: PushdownPolicy) -> Self {
+        self.pushdown_policy = pushdown_policy;
+        self
+    }
+
+    /// Sets how Postgres compares strings in the filters and sorts that are pushed down, e.g.
+    /// [`FilterSemantics::with_collated_ordering`] for databases whose collation isn't `C`, see
+    /// [`SqlTable::with_filter_semantics`].
+    #[must_use]
+    pub fn with_filter_semantics(mut self, filter_semantics: FilterSemantics) -> Self {
+        self.filter_semantics = filter_semantics;
+        self
+    }
+
+    /// Reads a sample of the rows of the tables, see [`SqlTable::with_sample`].
+    #[must_use]
+    pub fn with_sample(mut self, sample: Option<TableSample>) -> Self {
+        self.sample = sample;
+        self
+    }
+
+    /// Counts the `approx_distinct` aggregates of federated queries exactly on the database with
+    /// [`ApproxDistinct::Exact`], as it has no estimate of its own, see
+    /// [`SqlTable::with_approx_distinct`].
+    #[must_use]
+    pub fn with_approx_distinct(mut self, approx_distinct: Option<ApproxDistinct>) -> Self {
+        self.approx_distinct = approx_distinct;
+        self
+    }
+
+    /// Binds the string literals of scans as parameters instead of quoting them, see
+    /// [`SqlTable::with_bind_literals`].
+    /// Queries that are federated inline their literals, so the tables aren't federated then.
+    #[must_use]
+    pub fn with_bind_literals(mut self, bind_literals: bool) -> Self {
+        self.bind_literals = bind_literals;
+        self
+    }
+
+    /// Passes the query context of the session to the database with the queries of the tables,
+    /// see [`SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(mut self, query_context: Option<QueryContextMode>) -> Self {
+        self.query_context = query_context;
+        self
+    }
+
+    /// Passes `query_settings` to the database with the queries of the tables, see
+    /// [`SqlTable::with_query_settings`].
+    #[must_use]
+    pub fn with_query_settings(mut self, query_settings: QuerySettings) -> Self {
+        self.query_settings = query_settings;
+        self
+    }
+
+    /// Validates the batches written through [`Self::read_write_table_provider`] before inserting
+    /// them, see [`crate::util::validation::validate_batch`].
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    /// Sets what the writes through [`Self::read_write_table_provider`] do with batches whose
+    /// columns differ from the ones of the table, see [`SchemaMismatchMode`].
+    #[must_use]
+    pub fn with_schema_mismatch_mode(mut self, schema_mismatch: SchemaMismatchMode) -> Self {
+        self.schema_mismatch = schema_mismatch;
+        self
+    }
+
+    /// Exposes the columns of the tables from [`Self::table_provider`] with the names of
+    /// `column_mapper`, see [`ColumnMapper`]. Read-write table providers keep the names of the
+    /// columns in Postgres, so they can be written to.
+    #[must_use]
+    pub fn with_column_mapper(mut self, column_mapper: ColumnMapper) -> Self {
+        self.column_mapper = Some(column_mapper);
+        self
+    }
+
+    /// A listener on `channel` of the database of the tables, to invalidate their copies when a
+    /// trigger notifies it, see [`PostgresNotifyListener`].
+    #[must_use]
+    pub fn notify_listener(&self, channel: impl Into<String>) -> PostgresNotifyListener {
+        PostgresNotifyListener::new(Arc::clone(&self.pool), channel)
     }
 
     pub async fn table_provider(
         &self,
         table_reference: TableReference,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        self.read_provider(
+            table_reference,
+            None,
+            &self.dictionary_columns,
+            self.column_mapper.as_ref(),
+        )
+        .await
+    }
+
+    /// A read-only table of the rows of `query`, a Postgres `SELECT`, named `table_reference` in
+    /// the SQL of its scans, see [`SqlTable::from_query`].
+    pub async fn query_table_provider(
+        &self,
+        table_reference: TableReference,
+        query: &str,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        self.read_provider(
+            table_reference,
+            Some(query),
+            &self.dictionary_columns,
+            self.column_mapper.as_ref(),
+        )
+        .await
+    }
+
+    async fn read_provider(
+        &self,
+        table_reference: TableReference,
+        query: Option<&str>,
+        dictionary_columns: &[String],
+        column_mapper: Option<&ColumnMapper>,
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
         let pool = Arc::clone(&self.pool);
         let dyn_pool: Arc<DynPostgresConnectionPool> = pool;
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
 
-        let table_provider = Arc::new(
-            SqlTable::new("postgres", &dyn_pool, table_reference)
-                .await
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?
-                .with_dialect(Arc::new(PostgreSqlDialect {})),
-        );
+        let table_provider = match query {
+            Some(query) => {
+                SqlTable::from_query("postgres", &dyn_pool, table_reference.clone(), query).await
+            }
+            None => SqlTable::new("postgres", &dyn_pool, table_reference.clone()).await,
+        };
+        let mut table_provider = table_provider
+            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?
+            .with_dialect(self.dialect())
+            .with_only(self.only)
+            .with_dictionary_columns(dictionary_columns)
+            .with_schema_drift(self.schema_drift)
+            .with_spill_buffer(self.spill_buffer)
+            .with_memory_wait(self.memory_wait)
+            .with_remote_explain(self.remote_explain)
+            .with_pushdown_policy(self.pushdown_policy.clone())
+            .with_filter_semantics(self.filter_semantics)
+            .with_sample(self.sample)
+            .with_approx_distinct(self.approx_distinct)
+            .with_bind_literals(self.bind_literals)
+            .with_query_context(self.query_context.clone())
+            .with_query_settings(self.query_settings.clone());
+        if let Some(column_mapper) = column_mapper {
+            table_provider = table_provider
+                .with_column_mapper(column_mapper)
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+        }
+
+        let inheritance = match query {
+            Some(_) => None,
+            None => Some(self.load_inheritance(&table_reference).await),
+        };
+        match inheritance {
+            None => {}
+            Some(Ok(inheritance)) => {
+                tracing::debug!(
+                    "'{table_reference}' has {} descendant tables, partitioned: {}",
+                    inheritance.descendants,
+                    inheritance.is_partitioned
+                );
+                if self.only && inheritance.is_partitioned {
+                    tracing::warn!("'{table_reference}' is a partitioned table, scanning it with ONLY returns no rows");
+                }
+                if let Some(num_rows) = inheritance.estimated_rows(self.only) {
+                    let mut statistics = Statistics::new_unknown(&table_provider.schema());
+                    statistics.num_rows =
+                        Precision::Inexact(usize::try_from(num_rows).unwrap_or_default());
+                    table_provider = table_provider.with_statistics(statistics);
+                }
+            }
+            Some(Err(e)) => {
+                tracing::debug!(
+                    "Unable to estimate the number of rows in '{table_reference}': {e}"
+                );
+            }
+        }
+
+        let table_provider = Arc::new(table_provider);
 
         #[cfg(feature = "postgres-federation")]
-        let table_provider = Arc::new(
-            table_provider
-                .create_federated_table_provider()
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?,
-        );
+        let table_provider: Arc<dyn TableProvider> =
+            if self.pushdown_policy.allows_federation() && !self.bind_literals {
+                Arc::new(
+                    table_provider
+                        .create_federated_table_provider()
+                        .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?,
+                )
+            } else {
+                table_provider
+            };
 
         Ok(table_provider)
     }
@@ -172,7 +621,12 @@ impl PostgresTableFactory {
         &self,
         table_reference: TableReference,
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
-        let read_provider = Self::table_provider(self, table_reference.clone()).await?;
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let read_provider = self
+            .read_provider(table_reference.clone(), None, &[], None)
+            .await?;
         let schema = read_provider.schema();
 
         let postgres = Postgres::new(
@@ -180,19 +634,113 @@ impl PostgresTableFactory {
             Arc::clone(&self.pool),
             schema,
             Constraints::empty(),
-        );
+        )
+        .with_partition_routing(self.partition_routing)
+        .with_overwrite_mode(self.overwrite_mode)
+        .with_batch_validation(self.validate_batches)
+        .with_schema_mismatch_mode(self.schema_mismatch);
 
         Ok(PostgresTableWriter::create(read_provider, postgres, None))
     }
+
+    /// Checks whether the configured credentials can `SELECT` from the table, and `INSERT` into it
+    /// if `writable`. The privileges are looked up with `has_table_privilege`, so no statement is
+    /// run against the table and no triggers fire.
+    pub async fn validate_permissions(
+        &self,
+        table_reference: TableReference,
+        writable: bool,
+    ) -> Result<PermissionReport, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let mut db_conn = self.pool.connect().await.context(DbConnectionSnafu)?;
+        let postgres_conn = Postgres::postgres_conn(&mut db_conn)?;
+
+        let privileges = postgres_conn
+            .conn
+            .query_one(
+                "SELECT has_table_privilege($1::text::regclass, 'SELECT'), has_table_privilege($1::text::regclass, 'INSERT')",
+                &[&table_reference.to_quoted_string()],
+            )
+            .await;
+
+        let report = PermissionReport::new(table_reference);
+        let report = match privileges {
+            Ok(row) => {
+                let report = report.with_check(Privilege::Select, row.get(0), None);
+                if writable {
+                    report.with_check(Privilege::Insert, row.get(1), None)
+                } else {
+                    report
+                }
+            }
+            // the table doesn't exist or isn't visible to the user
+            Err(e) => report.with_check(Privilege::Select, false, Some(e.to_string())),
+        };
+
+        Ok(report)
+    }
+
+    async fn load_inheritance(&self, table_reference: &TableReference) -> Result<Inheritance> {
+        let mut db_conn = self.pool.connect().await.context(DbConnectionSnafu)?;
+        let postgres_conn = Postgres::postgres_conn(&mut db_conn)?;
+
+        Inheritance::load(&*postgres_conn.conn, table_reference)
+            .await
+            .context(UnableToLoadInheritanceSnafu)
+    }
+
+    fn dialect(&self) -> Arc<dyn Dialect + Send + Sync> {
+        self.identifier_case.dialect(
+            self.dialect_overrides
+                .dialect(postgres_dialect(self.full_text_search.as_ref())),
+        )
+    }
+}
+
+pub(crate) fn postgres_dialect(
+    full_text_search: Option<&FullTextSearch>,
+) -> Arc<dyn Dialect + Send + Sync> {
+    let dialect = DateTimeSyntax::Postgres
+        .dialect(JsonSyntax::Postgres.dialect(Arc::new(PostgreSqlDialect {})));
+    match full_text_search {
+        Some(full_text_search) => full_text_search.dialect(dialect),
+        None => dialect,
+    }
 }
 
 #[derive(Debug)]
-pub struct PostgresTableProviderFactory;
+pub struct PostgresTableProviderFactory {
+    pools: PoolSelector<PostgresConnectionPool>,
+}
 
 impl PostgresTableProviderFactory {
     #[must_use]
     pub fn new() -> Self {
-        Self {}
+        Self {
+            pools: PoolSelector::new(),
+        }
+    }
+
+    /// Creates the table `table` with `pool` instead of a pool of its options, see
+    /// [`PoolSelector`].
+    #[must_use]
+    pub fn with_table_pool(
+        mut self,
+        table: impl Into<TableReference>,
+        pool: Arc<PostgresConnectionPool>,
+    ) -> Self {
+        self.pools = self.pools.with_table_pool(table, pool);
+        self
+    }
+
+    /// Creates the tables with the pools of `pools` instead of pools of their options, where it
+    /// has one for them.
+    #[must_use]
+    pub fn with_pool_selector(mut self, pools: PoolSelector<PostgresConnectionPool>) -> Self {
+        self.pools = pools;
+        self
     }
 }
 
@@ -221,6 +769,30 @@ impl TableProviderFactory for PostgresTableProviderFactory {
         let mut options = cmd.options.clone();
         let schema: Schema = cmd.schema.as_ref().into();
 
+        // the columns are renamed first, the options that name columns use the names of the
+        // statement
+        let column_name_policy = options
+            .remove("column_name_policy")
+            .map(|value| {
+                value
+                    .parse::<ColumnNamePolicy>()
+                    .context(InvalidColumnNamesSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let (schema, column_renames) = column_name_policy
+            .apply(&Arc::new(schema), &IdentifierRules::postgres())
+            .context(InvalidColumnNamesSnafu)
+            .map_err(to_datafusion_error)?;
+        for rename in column_renames.renames() {
+            tracing::info!(
+                "Creating the column '{}' of the Postgres table '{name}' as '{}'",
+                rename.original,
+                rename.renamed
+            );
+        }
+
         let indexes_option_str = options.remove("indexes");
         let unparsed_indexes: HashMap<String, IndexType> = match indexes_option_str {
             Some(indexes_str) => util::hashmap_from_option_string(&indexes_str),
@@ -231,6 +803,7 @@ impl TableProviderFactory for PostgresTableProviderFactory {
             .into_iter()
             .map(|(key, value)| {
                 let columns = ColumnReference::try_from(key.as_str())
+                    .map(|columns| column_renames.column_reference(&columns))
                     .context(UnableToParseColumnReferenceSnafu)
                     .map_err(to_datafusion_error);
                 (columns, value)
@@ -247,30 +820,129 @@ impl TableProviderFactory for PostgresTableProviderFactory {
         if let Some(on_conflict_str) = options.remove("on_conflict") {
             on_conflict = Some(
                 OnConflict::try_from(on_conflict_str.as_str())
+                    .map(|on_conflict| column_renames.on_conflict(&on_conflict))
                     .context(UnableToParseOnConflictSnafu)
                     .map_err(to_datafusion_error)?,
             );
         }
 
-        let params = to_secret_map(options);
-
-        let pool = Arc::new(
-            PostgresConnectionPool::new(params)
-                .await
-                .context(UnableToCreatePostgresConnectionPoolSnafu)
+        let dedup_columns = options
+            .remove("dedup_columns")
+            .map(|dedup_columns| {
+                ColumnReference::try_from(dedup_columns.as_str())
+                    .map(|columns| column_renames.column_reference(&columns))
+                    .context(UnableToParseColumnReferenceSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?;
+
+        let full_text_config = options.remove("fulltext_config");
+        let full_text_tsvector_columns = options
+            .remove("fulltext_tsvector_columns")
+            .map(|columns| {
+                ColumnReference::try_from(columns.as_str())
+                    .map(|columns| column_renames.column_reference(&columns))
+                    .context(UnableToParseColumnReferenceSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?;
+        let full_text_search = options
+            .remove("fulltext_columns")
+            .map(|columns| {
+                let columns = ColumnReference::try_from(columns.as_str())
+                    .map(|columns| column_renames.column_reference(&columns))
+                    .context(UnableToParseColumnReferenceSnafu)
+                    .map_err(to_datafusion_error)?;
+                let mut full_text_search =
+                    FullTextSearch::postgres(columns.iter().map(String::from).collect());
+                if let Some(config) = full_text_config {
+                    full_text_search = full_text_search.with_config(config);
+                }
+                if let Some(tsvector_columns) = full_text_tsvector_columns {
+                    full_text_search = full_text_search
+                        .with_tsvector_columns(tsvector_columns.iter().map(String::from).collect());
+                }
+                Ok::<_, DataFusionError>(full_text_search)
+            })
+            .transpose()?;
+
+        let validate_batches = options
+            .remove("validate_batches")
+            .map(|value| {
+                validation::parse_validate_batches(&value)
+                    .context(UnableToParseValidateBatchesSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let schema_mismatch = options
+            .remove("schema_mismatch")
+            .map(|value| {
+                SchemaMismatchMode::try_from(value.as_str())
+                    .context(UnableToParseSchemaMismatchModeSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let column_expressions = ColumnExpressions::try_from_table_options(
+            &cmd.column_defaults,
+            &mut options,
+            &PostgreSqlDialect {},
+        )
+        .context(InvalidColumnExpressionsSnafu)
+        .map_err(to_datafusion_error)?
+        .with_renamed_columns(&column_renames);
+
+        let partition_routing = match options.remove("partition_routing") {
+            Some(partition_routing) => PartitionRouting::try_from(partition_routing.as_str())
+                .context(PartitionRoutingSnafu)
                 .map_err(to_datafusion_error)?,
-        );
+            None => PartitionRouting::default(),
+        };
+
+        let overwrite_mode = match options.remove("overwrite_mode") {
+            Some(overwrite_mode) => {
+                OverwriteMode::try_from(overwrite_mode.as_str()).map_err(to_datafusion_error)?
+            }
+            None => OverwriteMode::default(),
+        };
+
+        let pool = match self.pools.select(&name, &cmd.options) {
+            Some(pool) => pool,
+            None => Arc::new(
+                PostgresConnectionPool::new(to_secret_map(options))
+                    .await
+                    .context(UnableToCreatePostgresConnectionPoolSnafu)
+                    .map_err(to_datafusion_error)?,
+            ),
+        };
 
-        let schema: SchemaRef = Arc::new(schema);
         PostgresConnection::handle_unsupported_schema(&schema, UnsupportedTypeAction::default())
             .map_err(|e| DataFusionError::External(e.into()))?;
+        column_expressions
+            .validate(&schema)
+            .context(InvalidColumnExpressionsSnafu)
+            .map_err(to_datafusion_error)?;
 
-        let postgres = Postgres::new(
+        let mut postgres = Postgres::new(
             name.clone(),
             Arc::clone(&pool),
             Arc::clone(&schema),
             cmd.constraints.clone(),
-        );
+        )
+        .with_partition_routing(partition_routing)
+        .with_overwrite_mode(overwrite_mode)
+        .with_batch_validation(validate_batches)
+        .with_schema_mismatch_mode(schema_mismatch)
+        .with_column_expressions(column_expressions)
+        .with_column_renames(column_renames);
+
+        if let Some(dedup_columns) = dedup_columns {
+            postgres =
+                postgres.with_dedup_columns(dedup_columns.iter().map(String::from).collect());
+        }
 
         let mut db_conn = pool
             .connect()
@@ -309,7 +981,7 @@ impl TableProviderFactory for PostgresTableProviderFactory {
 
         let read_provider = Arc::new(
             SqlTable::new_with_schema("postgres", &dyn_pool, Arc::clone(&schema), name)
-                .with_dialect(Arc::new(PostgreSqlDialect {})),
+                .with_dialect(postgres_dialect(full_text_search.as_ref())),
         );
 
         #[cfg(feature = "postgres-federation")]
@@ -329,6 +1001,13 @@ pub struct Postgres {
     pool: Arc<PostgresConnectionPool>,
     schema: SchemaRef,
     constraints: Constraints,
+    partition_routing: PartitionRouting,
+    overwrite_mode: OverwriteMode,
+    dedup_columns: Option<Vec<String>>,
+    validate_batches: bool,
+    schema_mismatch: SchemaMismatchMode,
+    column_expressions: ColumnExpressions,
+    column_renames: ColumnRenames,
 }
 
 impl std::fmt::Debug for Postgres {
@@ -337,6 +1016,13 @@ impl std::fmt::Debug for Postgres {
             .field("table_name", &self.table)
             .field("schema", &self.schema)
             .field("constraints", &self.constraints)
+            .field("partition_routing", &self.partition_routing)
+            .field("overwrite_mode", &self.overwrite_mode)
+            .field("dedup_columns", &self.dedup_columns)
+            .field("validate_batches", &self.validate_batches)
+            .field("schema_mismatch", &self.schema_mismatch)
+            .field("column_expressions", &self.column_expressions)
+            .field("column_renames", &self.column_renames)
             .finish()
     }
 }
@@ -354,9 +1040,80 @@ impl Postgres {
             pool,
             schema,
             constraints,
+            partition_routing: PartitionRouting::default(),
+            overwrite_mode: OverwriteMode::default(),
+            dedup_columns: None,
+            validate_batches: false,
+            schema_mismatch: SchemaMismatchMode::default(),
+            column_expressions: ColumnExpressions::default(),
+            column_renames: ColumnRenames::default(),
         }
     }
 
+    #[must_use]
+    pub fn with_partition_routing(mut self, partition_routing: PartitionRouting) -> Self {
+        self.partition_routing = partition_routing;
+        self
+    }
+
+    #[must_use]
+    pub fn with_overwrite_mode(mut self, overwrite_mode: OverwriteMode) -> Self {
+        self.overwrite_mode = overwrite_mode;
+        self
+    }
+
+    /// Drops incoming rows that match an earlier incoming row or an existing row of the table on `dedup_columns`,
+    /// e.g. when a stream is replayed. Existing rows are left to the `on_conflict` clause of the writer instead, if it has one.
+    #[must_use]
+    pub fn with_dedup_columns(mut self, dedup_columns: Vec<String>) -> Self {
+        self.dedup_columns = Some(dedup_columns);
+        self
+    }
+
+    /// Checks each batch with [`validation::validate_batch`] before it is inserted, so malformed
+    /// arrays are rejected with the offending column instead of failing in the binary encoding.
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    /// Sets what writes do with batches whose columns differ from the ones of the table: fail
+    /// with the added, missing and retyped columns, or cast the retyped ones.
+    #[must_use]
+    pub fn with_schema_mismatch_mode(mut self, schema_mismatch: SchemaMismatchMode) -> Self {
+        self.schema_mismatch = schema_mismatch;
+        self
+    }
+
+    /// Sets the column defaults and generated columns the table is created with. The generated
+    /// columns are left out of the inserted rows, Postgres computes them itself.
+    #[must_use]
+    pub fn with_column_expressions(mut self, column_expressions: ColumnExpressions) -> Self {
+        self.column_expressions = column_expressions;
+        self
+    }
+
+    #[must_use]
+    pub fn column_expressions(&self) -> &ColumnExpressions {
+        &self.column_expressions
+    }
+
+    /// Records the columns that were renamed when the table was created, see
+    /// [`ColumnNamePolicy::Rename`].
+    #[must_use]
+    pub fn with_column_renames(mut self, column_renames: ColumnRenames) -> Self {
+        self.column_renames = column_renames;
+        self
+    }
+
+    /// The columns that were renamed when the table was created, from the names of the schema it
+    /// was created from to the names of the columns in Postgres.
+    #[must_use]
+    pub fn column_renames(&self) -> &ColumnRenames {
+        &self.column_renames
+    }
+
     #[must_use]
     pub fn table_name(&self) -> &str {
         self.table.table()
@@ -391,6 +1148,23 @@ impl Postgres {
             .context(UnableToDowncastDbConnectionSnafu)
     }
 
+    /// Runs a `DELETE` or `UPDATE` against the table, returning the number of affected rows.
+    pub async fn execute_dml(&self, operation: &DmlOperation) -> Result<u64> {
+        let sql = operation
+            .to_sql(&self.table, &PostgreSqlDialect {})
+            .context(UnableToCreateDmlStatementSnafu)?;
+        tracing::trace!("{sql}");
+
+        let db_conn = self.connect().await?;
+        let conn = db_conn
+            .as_async()
+            .context(UnableToDowncastDbConnectionSnafu)?;
+
+        conn.execute(&sql, &[])
+            .await
+            .context(UnableToExecuteDmlStatementSnafu)
+    }
+
     async fn table_exists(&self, postgres_conn: &PostgresConnection) -> bool {
         let sql = match self.table.schema() {
             Some(schema) => format!(
@@ -413,16 +1187,143 @@ impl Postgres {
         row.get(0)
     }
 
+    /// Inserts the batch under a savepoint, so that a deadlock only retries this batch instead of
+    /// aborting the whole write transaction. Retries back off and stop after `MAX_BATCH_RETRIES`.
+    ///
+    /// Returns the number of rows inserted, which leaves out the rows skipped for the dedup columns.
+    async fn insert_batch_with_retry(
+        &self,
+        transaction: &mut Transaction<'_>,
+        table: &TableReference,
+        batch: RecordBatch,
+        on_conflict: Option<OnConflict>,
+    ) -> Result<u64> {
+        let mut attempt = 0;
+        loop {
+            let savepoint = transaction
+                .savepoint("insert_batch")
+                .await
+                .context(UnableToCreateSavepointSnafu)?;
+
+            match self
+                .insert_batch_once(&savepoint, table, batch.clone(), on_conflict.clone())
+                .await
+            {
+                Ok(rows) => {
+                    savepoint
+                        .commit()
+                        .await
+                        .context(UnableToReleaseSavepointSnafu)?;
+                    return Ok(rows);
+                }
+                Err(Error::UnableToInsertArrowBatch { source })
+                    if attempt < MAX_BATCH_RETRIES && is_transient_error(&source) =>
+                {
+                    attempt += 1;
+                    tracing::debug!(
+                        "Retrying batch insert into {table} ({attempt}/{MAX_BATCH_RETRIES}): {source}"
+                    );
+                    savepoint
+                        .rollback()
+                        .await
+                        .context(UnableToRollbackSavepointSnafu)?;
+                    tokio::time::sleep(util::retriable_error::batch_retry_backoff(attempt)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn insert_batch_once(
+        &self,
+        transaction: &Transaction<'_>,
+        table: &TableReference,
+        batch: RecordBatch,
+        on_conflict: Option<OnConflict>,
+    ) -> Result<u64> {
+        match (&self.dedup_columns, on_conflict) {
+            (Some(dedup_columns), None) => {
+                match dedup::on_conflict_for_dedup(&self.schema, &self.constraints, dedup_columns) {
+                    Some(on_conflict) => {
+                        self.insert_batch(transaction, table, batch, Some(on_conflict))
+                            .await
+                    }
+                    None => {
+                        self.insert_batch_anti_join(transaction, table, batch, dedup_columns)
+                            .await
+                    }
+                }
+            }
+            (_, on_conflict) => {
+                self.insert_batch(transaction, table, batch, on_conflict)
+                    .await
+            }
+        }
+    }
+
+    /// Inserts the rows of the batch that don't already exist in the table, for dedup columns without a unique constraint
+    /// to use with `ON CONFLICT`. The batch is loaded into a temporary staging table that is anti-joined with the table.
+    async fn insert_batch_anti_join(
+        &self,
+        transaction: &Transaction<'_>,
+        table: &TableReference,
+        batch: RecordBatch,
+        dedup_columns: &[String],
+    ) -> Result<u64> {
+        let staging_table = TableReference::bare(format!("__dedup_{}", self.table.table()));
+        let staging = staging_table.to_quoted_string();
+        let target = table.to_quoted_string();
+
+        transaction
+            .batch_execute(&format!(
+                "CREATE TEMP TABLE IF NOT EXISTS {staging} (LIKE {target}) ON COMMIT DROP; TRUNCATE {staging}"
+            ))
+            .await
+            .context(UnableToCreateStagingTableSnafu)?;
+
+        self.insert_batch(transaction, &staging_table, batch, None)
+            .await?;
+
+        let not_exists = dedup::not_exists_predicate(
+            &target,
+            &staging,
+            &self.schema,
+            dedup_columns,
+            "IS NOT DISTINCT FROM",
+        );
+        transaction
+            .execute(
+                &format!("INSERT INTO {target} SELECT * FROM {staging} WHERE {not_exists}"),
+                &[],
+            )
+            .await
+            .context(UnableToInsertArrowBatchSnafu)
+    }
+
+    /// Returns a [`Deduplicator`] for the rows of a single write, if the table has dedup columns.
+    fn deduplicator(&self) -> Result<Option<Deduplicator>> {
+        self.dedup_columns
+            .as_deref()
+            .map(|dedup_columns| Deduplicator::try_new(&self.schema, dedup_columns))
+            .transpose()
+            .context(UnableToDeduplicateBatchSnafu)
+    }
+
     async fn insert_batch(
         &self,
         transaction: &Transaction<'_>,
+        table: &TableReference,
         batch: RecordBatch,
         on_conflict: Option<OnConflict>,
-    ) -> Result<()> {
-        let insert_table_builder = InsertBuilder::new(&self.table, vec![batch]);
+    ) -> Result<u64> {
+        let batch = self
+            .column_expressions
+            .remove_generated_columns(batch)
+            .context(InvalidColumnExpressionsSnafu)?;
+        let schema = batch.schema();
+        let insert_table_builder = InsertBuilder::new(table, vec![batch]);
 
-        let sea_query_on_conflict =
-            on_conflict.map(|oc| oc.build_sea_query_on_conflict(&self.schema));
+        let sea_query_on_conflict = on_conflict.map(|oc| oc.build_sea_query_on_conflict(&schema));
 
         let sql = insert_table_builder
             .build_postgres(sea_query_on_conflict)
@@ -431,11 +1332,49 @@ impl Postgres {
         transaction
             .execute(&sql, &[])
             .await
-            .context(UnableToInsertArrowBatchSnafu)?;
+            .context(UnableToInsertArrowBatchSnafu)
+    }
 
+    fn validate_batch_data(&self, batch: &RecordBatch) -> Result<()> {
+        if self.validate_batches {
+            validation::validate_batch(batch).context(InvalidBatchSnafu)?;
+        }
         Ok(())
     }
 
+    /// Checks that the batch has the same column names and types as the table, casting the
+    /// retyped columns if the schema mismatch mode is lenient.
+    ///
+    /// Types are compared regardless of their encoding, so `LargeUtf8` matches a `text` column as
+    /// well as `Utf8`, see [`schema_mismatch::SchemaDiff::between`].
+    fn reconcile_batch_schema(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        schema_mismatch::reconcile_batch(batch, &self.schema, self.schema_mismatch).context(
+            SchemaMismatchSnafu {
+                table_name: self.table.to_string(),
+            },
+        )
+    }
+
+    /// Returns the router for inserting directly into the table partitions, if enabled and supported for this table.
+    async fn partition_router(
+        &self,
+        transaction: &Transaction<'_>,
+    ) -> Result<Option<PartitionRouter>> {
+        if self.partition_routing != PartitionRouting::Direct {
+            return Ok(None);
+        }
+
+        PartitionRouter::try_load(transaction, &self.table, &self.schema)
+            .await
+            .context(PartitionRoutingSnafu)
+    }
+
+    async fn is_partitioned(&self, transaction: &Transaction<'_>) -> Result<bool> {
+        partition::is_partitioned(transaction, &self.table)
+            .await
+            .context(PartitionRoutingSnafu)
+    }
+
     async fn delete_all_table_data(&self, transaction: &Transaction<'_>) -> Result<()> {
         transaction
             .execute(
@@ -448,14 +1387,126 @@ impl Postgres {
         Ok(())
     }
 
+    /// Prepares an overwrite of the table according to its [`OverwriteMode`], returning the
+    /// staging table to load the rows into if the table is swapped.
+    async fn prepare_overwrite(
+        &self,
+        transaction: &Transaction<'_>,
+    ) -> Result<Option<TableReference>> {
+        if self.overwrite_mode == OverwriteMode::Swap {
+            if self.is_partitioned(transaction).await? {
+                tracing::debug!(
+                    "'{}' is partitioned, overwriting it by deleting its rows",
+                    self.table
+                );
+            } else if self.has_dependents(transaction).await? {
+                tracing::debug!(
+                    "'{}' has dependent objects, overwriting it by deleting its rows",
+                    self.table
+                );
+            } else {
+                return self.create_staging_table(transaction).await.map(Some);
+            }
+        }
+
+        self.delete_all_table_data(transaction).await?;
+        Ok(None)
+    }
+
+    /// Returns true if views or foreign keys refer to the table, or the table owns the sequence of
+    /// a serial or identity column, any of which would make dropping the table fail.
+    async fn has_dependents(&self, transaction: &Transaction<'_>) -> Result<bool> {
+        let row = transaction
+            .query_one(
+                "SELECT EXISTS (
+                    SELECT 1 FROM pg_depend d
+                    JOIN pg_rewrite r ON d.classid = 'pg_rewrite'::regclass AND d.objid = r.oid
+                    WHERE d.refobjid = $1::text::regclass AND r.ev_class <> d.refobjid
+                ) OR EXISTS (
+                    SELECT 1 FROM pg_constraint
+                    WHERE contype = 'f' AND confrelid = $1::text::regclass
+                ) OR EXISTS (
+                    SELECT 1 FROM pg_depend d
+                    JOIN pg_class s ON d.classid = 'pg_class'::regclass AND d.objid = s.oid
+                    WHERE d.refobjid = $1::text::regclass AND s.relkind = 'S' AND d.deptype IN ('a', 'i')
+                )",
+                &[&self.table.to_quoted_string()],
+            )
+            .await
+            .context(UnableToQueryDependentsSnafu)?;
+
+        Ok(row.get(0))
+    }
+
+    /// Creates an empty copy of the table, including its defaults, constraints and indexes,
+    /// that an overwrite can load into before it is swapped in with [`Self::swap_staging_table`].
+    async fn create_staging_table(&self, transaction: &Transaction<'_>) -> Result<TableReference> {
+        let unix_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context(UnableToGetSystemTimeSnafu)?
+            .as_millis();
+        let staging_name = format!("__staging_{}_{unix_ms}", self.table.table());
+        let staging_table = match self.table.schema() {
+            Some(schema) => TableReference::partial(schema, staging_name),
+            None => TableReference::bare(staging_name),
+        };
+
+        transaction
+            .execute(
+                &format!(
+                    r#"CREATE TABLE {staging} (LIKE {table} INCLUDING ALL)"#,
+                    staging = staging_table.to_quoted_string(),
+                    table = self.table.to_quoted_string()
+                ),
+                &[],
+            )
+            .await
+            .context(UnableToCreateStagingTableSnafu)?;
+
+        Ok(staging_table)
+    }
+
+    /// Replaces the table with the staging table by dropping the table and renaming the staging table in its place.
+    ///
+    /// Both statements run in the write transaction, so concurrent readers see either the previous
+    /// or the new contents of the table, see [`OverwriteMode::Swap`] for what the new table loses.
+    async fn swap_staging_table(
+        &self,
+        transaction: &Transaction<'_>,
+        staging_table: &TableReference,
+    ) -> Result<()> {
+        transaction
+            .execute(
+                &format!(r#"DROP TABLE {}"#, self.table.to_quoted_string()),
+                &[],
+            )
+            .await
+            .context(UnableToSwapStagingTableSnafu)?;
+
+        transaction
+            .execute(
+                &format!(
+                    r#"ALTER TABLE {staging} RENAME TO {table}"#,
+                    staging = staging_table.to_quoted_string(),
+                    table = TableReference::bare(self.table.table()).to_quoted_string()
+                ),
+                &[],
+            )
+            .await
+            .context(UnableToSwapStagingTableSnafu)?;
+
+        Ok(())
+    }
+
     async fn create_table(
         &self,
         schema: SchemaRef,
         transaction: &Transaction<'_>,
         primary_keys: Vec<String>,
     ) -> Result<()> {
-        let create_table_statement =
-            CreateTableBuilder::new(schema, self.table.table()).primary_keys(primary_keys);
+        let create_table_statement = CreateTableBuilder::new(schema, self.table.table())
+            .primary_keys(primary_keys)
+            .column_expressions(self.column_expressions.clone());
         let create_stmts = create_table_statement.build_postgres();
 
         for create_stmt in create_stmts {
@@ -488,3 +1539,31 @@ impl Postgres {
         Ok(())
     }
 }
+
+/// Returns true for errors that abort only the failing statement and are expected to succeed on retry.
+fn is_transient_error(error: &tokio_postgres::error::Error) -> bool {
+    error.code().is_some_and(is_transient_sql_state)
+}
+
+/// Whether a batch that failed with `code` can succeed when it is retried from a savepoint. Only
+/// the deadlock victim's statement is retried: it may succeed once the other transaction is done,
+/// although the locks taken by earlier batches are still held. A serialization failure is never
+/// retried, because the snapshot of the transaction stays the same until the whole transaction is
+/// retried.
+fn is_transient_sql_state(code: &SqlState) -> bool {
+    *code == SqlState::T_R_DEADLOCK_DETECTED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_sql_states() {
+        assert!(is_transient_sql_state(&SqlState::T_R_DEADLOCK_DETECTED));
+        assert!(!is_transient_sql_state(
+            &SqlState::T_R_SERIALIZATION_FAILURE
+        ));
+        assert!(!is_transient_sql_state(&SqlState::UNIQUE_VIOLATION));
+    }
+}
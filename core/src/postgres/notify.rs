@@ -0,0 +1,319 @@
+//! Invalidating the copies of Postgres tables, like the tables accelerated from them, when the
+//! database sends a notification with `NOTIFY`, without replicating its changes.
+//!
+//! A trigger notifies a channel when the table changes, and the listener calls back for each
+//! notification on the channel:
+//!
+//! ```rust,ignore
+//! // CREATE FUNCTION notify_orders() RETURNS trigger AS $$
+//! //     BEGIN PERFORM pg_notify('orders_changed', TG_OP); RETURN NULL; END $$ LANGUAGE plpgsql;
+//! // CREATE TRIGGER orders_changed AFTER INSERT OR UPDATE OR DELETE ON orders
+//! //     FOR EACH STATEMENT EXECUTE FUNCTION notify_orders();
+//! let listener = PostgresNotifyListener::new(pool, "orders_changed")
+//!     .with_debounce(Duration::from_millis(500))
+//!     .listen(move |event| refresh.notify_one())
+//!     .await?;
+//! ```
+//!
+//! The notifications sent while the listener is reconnecting are lost, so the listener calls back
+//! with [`NotifyEvent::Reconnected`] once it listens again, after which the copies should be
+//! refreshed in full.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use datafusion::sql::sqlparser::ast;
+use futures::{stream, StreamExt};
+use snafu::prelude::*;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_postgres::AsyncMessage;
+
+use crate::sql::db_connection_pool::postgrespool::{self, PostgresConnectionPool};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to connect to listen for notifications.\n{source}"))]
+    UnableToConnect { source: postgrespool::Error },
+
+    #[snafu(display("Unable to listen on the channels {channels:?}.\n{source}"))]
+    UnableToListen {
+        channels: Vec<String>,
+        source: tokio_postgres::Error,
+    },
+
+    #[snafu(display("No channel to listen on.\nPass the name of the channel that is notified, e.g. 'orders_changed'."))]
+    MissingChannel,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The default delay before reconnecting when the connection of a listener is lost.
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A notification sent with `NOTIFY` or `pg_notify`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PostgresNotification {
+    /// The channel that was notified.
+    pub channel: String,
+    /// The payload of the notification, empty if there's none.
+    pub payload: String,
+    /// The process ID of the backend that sent the notification.
+    pub process_id: i32,
+}
+
+/// What a [`PostgresNotifyListener`] calls back with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyEvent {
+    /// A notification on one of the channels.
+    Notification(PostgresNotification),
+    /// The listener reconnected after its connection was lost, and may have missed notifications.
+    Reconnected,
+}
+
+type NotifyCallback = Arc<dyn Fn(NotifyEvent) + Send + Sync>;
+
+/// A connection listening on the channels, and the notifications that it receives.
+type Listening = (
+    tokio_postgres::Client,
+    mpsc::UnboundedReceiver<PostgresNotification>,
+);
+
+/// Listens on channels of a Postgres database over a connection of its own, see the
+/// [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct PostgresNotifyListener {
+    pool: Arc<PostgresConnectionPool>,
+    channels: Vec<String>,
+    debounce: Option<Duration>,
+    reconnect_delay: Duration,
+}
+
+impl PostgresNotifyListener {
+    /// Listens on `channel`, whose name is matched exactly: a channel notified with an unquoted
+    /// name, like `NOTIFY Orders`, is named in lowercase.
+    #[must_use]
+    pub fn new(pool: Arc<PostgresConnectionPool>, channel: impl Into<String>) -> Self {
+        Self {
+            pool,
+            channels: vec![channel.into()],
+            debounce: None,
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+        }
+    }
+
+    /// Listens on `channel` too.
+    #[must_use]
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channels.push(channel.into());
+        self
+    }
+
+    /// Waits for `debounce` after a notification before calling back, and calls back once for
+    /// the notifications with the same channel and payload that arrive meanwhile, e.g. for a
+    /// trigger that notifies for each row of a large update.
+    #[must_use]
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = Some(debounce);
+        self
+    }
+
+    /// The delay before reconnecting when the connection is lost or can't be opened, 5 seconds by
+    /// default.
+    #[must_use]
+    pub fn with_reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    /// Starts listening, and calls `callback` for each notification on the channels until the
+    /// returned handle is dropped or the pool is closed.
+    ///
+    /// The callback runs on the task of the listener, so a long refresh should be spawned from it
+    /// rather than run in it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no channel, or if the first connection can't be opened or
+    /// listen on the channels. The later connections are retried until they succeed.
+    pub async fn listen(
+        self,
+        callback: impl Fn(NotifyEvent) + Send + Sync + 'static,
+    ) -> Result<NotifyListenerHandle> {
+        ensure!(!self.channels.is_empty(), MissingChannelSnafu);
+        let connection = self.connect().await?;
+        tracing::debug!("Listening for notifications on {:?}", self.channels);
+        let task = tokio::spawn(self.run(connection, Arc::new(callback)));
+        Ok(NotifyListenerHandle { task })
+    }
+
+    async fn run(self, connection: Listening, callback: NotifyCallback) {
+        let mut connection = Some(connection);
+        loop {
+            let (client, mut notifications) = match connection.take() {
+                Some(connection) => connection,
+                None => match self.connect().await {
+                    Ok(connection) => {
+                        tracing::debug!("Listening for notifications on {:?} again", self.channels);
+                        callback(NotifyEvent::Reconnected);
+                        connection
+                    }
+                    Err(Error::UnableToConnect {
+                        source: postgrespool::Error::Shutdown { .. },
+                    }) => return,
+                    Err(e) => {
+                        tracing::warn!("{e}");
+                        tokio::time::sleep(self.reconnect_delay).await;
+                        continue;
+                    }
+                },
+            };
+
+            while let Some(notification) = notifications.recv().await {
+                let mut received = vec![notification];
+                if let Some(debounce) = self.debounce {
+                    let deadline = tokio::time::Instant::now() + debounce;
+                    while let Ok(Some(notification)) =
+                        tokio::time::timeout_at(deadline, notifications.recv()).await
+                    {
+                        received.push(notification);
+                    }
+                }
+                for notification in deduplicate(received) {
+                    callback(NotifyEvent::Notification(notification));
+                }
+            }
+
+            drop(client);
+            tracing::warn!(
+                "The connection listening for notifications on {:?} was lost, reconnecting",
+                self.channels
+            );
+            tokio::time::sleep(self.reconnect_delay).await;
+        }
+    }
+
+    /// Opens a connection and listens on the channels. The notifications of the connection are
+    /// received until the client is dropped or the connection is lost.
+    async fn connect(&self) -> Result<Listening> {
+        let (client, mut connection) = self
+            .pool
+            .connect_unpooled()
+            .await
+            .context(UnableToConnectSnafu)?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        // the connection has to be polled for the client to run the `LISTEN` too
+        tokio::spawn(async move {
+            let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+            while let Some(message) = messages.next().await {
+                match message {
+                    Ok(AsyncMessage::Notification(notification)) => {
+                        let notification = PostgresNotification {
+                            channel: notification.channel().to_string(),
+                            payload: notification.payload().to_string(),
+                            process_id: notification.process_id(),
+                        };
+                        if sender.send(notification).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::debug!("The connection listening for notifications failed.\n{e}");
+                        return;
+                    }
+                }
+            }
+        });
+
+        client
+            .batch_execute(&listen_statement(&self.channels))
+            .await
+            .context(UnableToListenSnafu {
+                channels: self.channels.clone(),
+            })?;
+        Ok((client, receiver))
+    }
+}
+
+/// Stops the listener when dropped.
+#[derive(Debug)]
+pub struct NotifyListenerHandle {
+    task: JoinHandle<()>,
+}
+
+impl NotifyListenerHandle {
+    /// Whether the listener stopped, because its pool was closed.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stops the listener and closes its connection.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for NotifyListenerHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The statements that listen on `channels`.
+fn listen_statement(channels: &[String]) -> String {
+    channels
+        .iter()
+        .map(|channel| format!("LISTEN {}", ast::Ident::with_quote('"', channel)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// `notifications` without the repeats of a channel and payload, in the order they first arrived.
+fn deduplicate(notifications: Vec<PostgresNotification>) -> Vec<PostgresNotification> {
+    let mut seen = HashSet::new();
+    notifications
+        .into_iter()
+        .filter(|notification| {
+            seen.insert((notification.channel.clone(), notification.payload.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(channel: &str, payload: &str, process_id: i32) -> PostgresNotification {
+        PostgresNotification {
+            channel: channel.to_string(),
+            payload: payload.to_string(),
+            process_id,
+        }
+    }
+
+    #[test]
+    fn test_listen_statement() {
+        assert_eq!(
+            listen_statement(&["orders_changed".to_string(), r#"Sales "EU""#.to_string()]),
+            r#"LISTEN "orders_changed"; LISTEN "Sales ""EU""""#
+        );
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        assert_eq!(
+            deduplicate(vec![
+                notification("orders", "INSERT", 1),
+                notification("orders", "UPDATE", 1),
+                notification("orders", "INSERT", 2),
+                notification("items", "INSERT", 1),
+            ]),
+            vec![
+                notification("orders", "INSERT", 1),
+                notification("orders", "UPDATE", 1),
+                notification("items", "INSERT", 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,538 @@
+use arrow::{
+    array::{Array, ArrayRef, BooleanArray, RecordBatch, Scalar, StringArray},
+    compute::{self, kernels::cmp},
+    datatypes::SchemaRef,
+    error::ArrowError,
+};
+use datafusion::sql::TableReference;
+use snafu::prelude::*;
+use tokio_postgres::{GenericClient, Transaction};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to query the partitions of the Postgres table: {source}"))]
+    UnableToQueryPartitions {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Unable to query the tables that inherit from the Postgres table: {source}"))]
+    UnableToQueryInheritance {
+        source: tokio_postgres::error::Error,
+    },
+
+    #[snafu(display("Unable to route the batch to the table partitions: {source}"))]
+    UnableToRouteBatch { source: ArrowError },
+
+    #[snafu(display("Invalid partition_routing value '{value}', expected 'parent' or 'direct'"))]
+    InvalidPartitionRouting { value: String },
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// How inserts into a declaratively partitioned Postgres table are routed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartitionRouting {
+    /// Insert into the partitioned table and let Postgres route the rows.
+    #[default]
+    Parent,
+    /// Insert directly into the partition each row belongs to, for tables partitioned by
+    /// `RANGE` or `LIST` on a single column. Other tables fall back to [`PartitionRouting::Parent`].
+    Direct,
+}
+
+impl TryFrom<&str> for PartitionRouting {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "parent" => Ok(PartitionRouting::Parent),
+            "direct" => Ok(PartitionRouting::Direct),
+            _ => InvalidPartitionRoutingSnafu { value }.fail(),
+        }
+    }
+}
+
+/// Returns true if the table is declaratively partitioned.
+pub(crate) async fn is_partitioned(
+    transaction: &Transaction<'_>,
+    table: &TableReference,
+) -> Result<bool> {
+    let row = transaction
+        .query_one(
+            "SELECT c.relkind = 'p' FROM pg_class c WHERE c.oid = $1::text::regclass",
+            &[&table.to_quoted_string()],
+        )
+        .await
+        .context(UnableToQueryPartitionsSnafu)?;
+
+    Ok(row.get(0))
+}
+
+/// A Postgres table and the tables that inherit from it, directly or indirectly. The partitions of
+/// a declaratively partitioned table inherit from it too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Inheritance {
+    /// Whether the table is declaratively partitioned, in which case it holds no rows itself.
+    pub(crate) is_partitioned: bool,
+    pub(crate) descendants: i64,
+    /// The estimated number of rows in the table itself, `None` if it has never been analyzed.
+    pub(crate) own_estimated_rows: Option<i64>,
+    /// The estimated number of rows in the table and all of its descendants that have been analyzed.
+    pub(crate) total_estimated_rows: Option<i64>,
+}
+
+impl Inheritance {
+    /// Loads the inheritance tree below `table`, with row estimates from `pg_class.reltuples`.
+    pub(crate) async fn load(client: &impl GenericClient, table: &TableReference) -> Result<Self> {
+        let row = client
+            .query_one(
+                "WITH RECURSIVE tree(oid) AS (
+                    SELECT $1::text::regclass::oid
+                    UNION
+                    SELECT i.inhrelid FROM pg_inherits i JOIN tree t ON i.inhparent = t.oid
+                )
+                SELECT
+                    bool_or(c.relkind = 'p' AND c.oid = $1::text::regclass),
+                    count(*) - 1,
+                    (sum(c.reltuples) FILTER (WHERE c.oid = $1::text::regclass AND c.reltuples >= 0))::bigint,
+                    (sum(c.reltuples) FILTER (WHERE c.reltuples >= 0))::bigint
+                FROM tree t
+                JOIN pg_class c ON c.oid = t.oid",
+                &[&table.to_quoted_string()],
+            )
+            .await
+            .context(UnableToQueryInheritanceSnafu)?;
+
+        Ok(Self {
+            is_partitioned: row.get(0),
+            descendants: row.get(1),
+            own_estimated_rows: row.get(2),
+            total_estimated_rows: row.get(3),
+        })
+    }
+
+    /// The estimated number of rows returned by a scan of the table, with or without `ONLY`.
+    pub(crate) fn estimated_rows(&self, only: bool) -> Option<i64> {
+        match (only, self.is_partitioned) {
+            (true, true) => Some(0),
+            (true, false) => self.own_estimated_rows,
+            (false, _) => self.total_estimated_rows,
+        }
+    }
+}
+
+/// A partition bound, as described by `pg_get_expr(relpartbound)`.
+/// `None` values are `NULL` for list partitions, and `MINVALUE`/`MAXVALUE` for range partitions.
+#[derive(Debug, Clone, PartialEq)]
+enum PartitionBound {
+    Range {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    List {
+        values: Vec<Option<String>>,
+    },
+    Default,
+}
+
+impl PartitionBound {
+    /// Parses single-column bounds like `FOR VALUES FROM ('2024-01-01') TO (MAXVALUE)`,
+    /// `FOR VALUES IN (1, 2, NULL)` or `DEFAULT`.
+    fn parse(bound: &str) -> Option<Self> {
+        let bound = bound.trim();
+        if bound == "DEFAULT" {
+            return Some(PartitionBound::Default);
+        }
+
+        if let Some(list) = bound.strip_prefix("FOR VALUES IN ") {
+            let values = parse_value_list(list)?
+                .into_iter()
+                .map(|value| match value {
+                    BoundValue::Value(value) => Some(Some(value)),
+                    BoundValue::Null => Some(None),
+                    BoundValue::Unbounded => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            return Some(PartitionBound::List { values });
+        }
+
+        let range = bound.strip_prefix("FOR VALUES FROM ")?;
+        let (from, to) = range.split_once(" TO ")?;
+        let range_value = |list: &str| match parse_value_list(list)?.as_slice() {
+            [BoundValue::Value(value)] => Some(Some(value.clone())),
+            [BoundValue::Unbounded] => Some(None),
+            _ => None,
+        };
+
+        Some(PartitionBound::Range {
+            from: range_value(from)?,
+            to: range_value(to)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BoundValue {
+    Value(String),
+    Null,
+    Unbounded,
+}
+
+/// Parses a parenthesized, comma separated list of literals, e.g. `('a', 'it''s', 3, NULL)`.
+fn parse_value_list(list: &str) -> Option<Vec<BoundValue>> {
+    let inner = list.trim().strip_prefix('(')?.strip_suffix(')')?;
+
+    let mut values = Vec::new();
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let value = if chars.next_if_eq(&'\'').is_some() {
+            let mut value = String::new();
+            loop {
+                match chars.next()? {
+                    '\'' if chars.next_if_eq(&'\'').is_some() => value.push('\''),
+                    '\'' => break,
+                    c => value.push(c),
+                }
+            }
+            // drop any type cast following the literal, e.g. '1'::integer
+            while chars.next_if(|c| *c != ',').is_some() {}
+            BoundValue::Value(value)
+        } else {
+            let mut token = String::new();
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                token.push(c);
+            }
+            match token.trim() {
+                "" => return None,
+                "NULL" => BoundValue::Null,
+                "MINVALUE" | "MAXVALUE" => BoundValue::Unbounded,
+                token => BoundValue::Value(token.to_string()),
+            }
+        };
+        values.push(value);
+
+        if chars.next().is_none() {
+            return Some(values);
+        }
+    }
+}
+
+/// A partition bound with its literals cast to the type of the partition key column.
+#[derive(Debug)]
+enum TypedBound {
+    Range {
+        from: Option<ArrayRef>,
+        to: Option<ArrayRef>,
+    },
+    List {
+        values: Vec<ArrayRef>,
+        includes_null: bool,
+    },
+}
+
+impl TypedBound {
+    fn try_new(bound: &PartitionBound, column_type: &arrow::datatypes::DataType) -> Option<Self> {
+        let cast = |value: &String| {
+            let array =
+                compute::cast(&StringArray::from(vec![value.as_str()]), column_type).ok()?;
+            // a value the column type can't represent would silently become NULL
+            (array.null_count() == 0).then_some(array)
+        };
+
+        let cast_range = |value: &Option<String>| match value {
+            Some(value) => cast(value).map(Some),
+            None => Some(None),
+        };
+
+        match bound {
+            PartitionBound::Range { from, to } => Some(TypedBound::Range {
+                from: cast_range(from)?,
+                to: cast_range(to)?,
+            }),
+            PartitionBound::List { values } => Some(TypedBound::List {
+                values: values.iter().flatten().map(cast).collect::<Option<_>>()?,
+                includes_null: values.iter().any(Option::is_none),
+            }),
+            PartitionBound::Default => None,
+        }
+    }
+
+    fn evaluate(&self, column: &dyn Array) -> Result<BooleanArray, ArrowError> {
+        let mask = match self {
+            TypedBound::Range { from, to } => {
+                let mut mask = compute::is_not_null(column)?;
+                if let Some(from) = from {
+                    mask = compute::and(&mask, &cmp::gt_eq(&column, &Scalar::new(from))?)?;
+                }
+                if let Some(to) = to {
+                    mask = compute::and(&mask, &cmp::lt(&column, &Scalar::new(to))?)?;
+                }
+                mask
+            }
+            TypedBound::List {
+                values,
+                includes_null,
+            } => {
+                let mut mask = if *includes_null {
+                    compute::is_null(column)?
+                } else {
+                    BooleanArray::from(vec![false; column.len()])
+                };
+                for value in values {
+                    mask = compute::or(&mask, &cmp::eq(&column, &Scalar::new(value))?)?;
+                }
+                mask
+            }
+        };
+
+        Ok(compute::prep_null_mask_filter(&mask))
+    }
+}
+
+/// Splits incoming batches by the partition of a single-column `RANGE` or `LIST` partitioned table.
+#[derive(Debug)]
+pub(crate) struct PartitionRouter {
+    column_index: usize,
+    partitions: Vec<(TableReference, TypedBound)>,
+    default_partition: Option<TableReference>,
+}
+
+impl PartitionRouter {
+    /// Loads the partitions of `table`.
+    ///
+    /// Returns `None` if the table can't be routed directly: it is not partitioned, is partitioned by
+    /// `HASH`, an expression or several columns, or has a bound that can't be cast to the column type.
+    pub(crate) async fn try_load(
+        transaction: &Transaction<'_>,
+        table: &TableReference,
+        schema: &SchemaRef,
+    ) -> Result<Option<Self>> {
+        let Some(key) = transaction
+            .query_opt(
+                "SELECT pt.partstrat::text, a.attname::text
+                FROM pg_partitioned_table pt
+                JOIN pg_attribute a ON a.attrelid = pt.partrelid AND a.attnum = pt.partattrs[0]
+                WHERE pt.partrelid = $1::text::regclass AND pt.partnatts = 1",
+                &[&table.to_quoted_string()],
+            )
+            .await
+            .context(UnableToQueryPartitionsSnafu)?
+        else {
+            return Ok(None);
+        };
+
+        let strategy: String = key.get(0);
+        let column: String = key.get(1);
+        if strategy != "r" && strategy != "l" {
+            return Ok(None);
+        }
+        let Ok(column_index) = schema.index_of(&column) else {
+            return Ok(None);
+        };
+        let column_type = schema.field(column_index).data_type();
+
+        let rows = transaction
+            .query(
+                "SELECT n.nspname::text, c.relname::text, pg_get_expr(c.relpartbound, c.oid)
+                FROM pg_inherits i
+                JOIN pg_class c ON c.oid = i.inhrelid
+                JOIN pg_namespace n ON n.oid = c.relnamespace
+                WHERE i.inhparent = $1::text::regclass",
+                &[&table.to_quoted_string()],
+            )
+            .await
+            .context(UnableToQueryPartitionsSnafu)?;
+
+        let mut partitions = Vec::with_capacity(rows.len());
+        let mut default_partition = None;
+        for row in rows {
+            let partition =
+                TableReference::partial(row.get::<_, String>(0), row.get::<_, String>(1));
+            let Some(bound) = PartitionBound::parse(row.get::<_, &str>(2)) else {
+                return Ok(None);
+            };
+
+            if bound == PartitionBound::Default {
+                default_partition = Some(partition);
+                continue;
+            }
+
+            let Some(typed_bound) = TypedBound::try_new(&bound, column_type) else {
+                tracing::debug!(
+                    "Unable to route inserts into {table} directly: unsupported bound for partition {partition}"
+                );
+                return Ok(None);
+            };
+            partitions.push((partition, typed_bound));
+        }
+
+        Ok(Some(Self {
+            column_index,
+            partitions,
+            default_partition,
+        }))
+    }
+
+    /// Splits the batch into one batch per partition that has rows in it.
+    /// Rows that match no partition go to the default partition if there is one, or to `parent` otherwise.
+    pub(crate) fn route(
+        &self,
+        batch: &RecordBatch,
+        parent: &TableReference,
+    ) -> Result<Vec<(TableReference, RecordBatch)>> {
+        let column = batch.column(self.column_index);
+        let mut unmatched = BooleanArray::from(vec![true; batch.num_rows()]);
+        let mut routed = Vec::new();
+
+        for (partition, bound) in &self.partitions {
+            let mask = bound.evaluate(column).context(UnableToRouteBatchSnafu)?;
+            if mask.true_count() == 0 {
+                continue;
+            }
+
+            unmatched = compute::and_not(&unmatched, &mask).context(UnableToRouteBatchSnafu)?;
+            routed.push((
+                partition.clone(),
+                compute::filter_record_batch(batch, &mask).context(UnableToRouteBatchSnafu)?,
+            ));
+        }
+
+        if unmatched.true_count() > 0 {
+            let table = self.default_partition.as_ref().unwrap_or(parent);
+            routed.push((
+                table.clone(),
+                compute::filter_record_batch(batch, &unmatched).context(UnableToRouteBatchSnafu)?,
+            ));
+        }
+
+        Ok(routed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use arrow::{
+        array::{Date32Array, Int64Array},
+        datatypes::{DataType, Field, Schema},
+    };
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(
+            PartitionBound::parse("FOR VALUES FROM ('2024-01-01') TO ('2024-02-01')"),
+            Some(PartitionBound::Range {
+                from: Some("2024-01-01".to_string()),
+                to: Some("2024-02-01".to_string()),
+            })
+        );
+        assert_eq!(
+            PartitionBound::parse("FOR VALUES FROM (MINVALUE) TO (10)"),
+            Some(PartitionBound::Range {
+                from: None,
+                to: Some("10".to_string()),
+            })
+        );
+        assert_eq!(
+            PartitionBound::parse("FOR VALUES IN ('a', 'it''s, ok', NULL)"),
+            Some(PartitionBound::List {
+                values: vec![Some("a".to_string()), Some("it's, ok".to_string()), None],
+            })
+        );
+        assert_eq!(
+            PartitionBound::parse("DEFAULT"),
+            Some(PartitionBound::Default)
+        );
+        assert_eq!(
+            PartitionBound::parse("FOR VALUES WITH (modulus 4, remainder 0)"),
+            None
+        );
+        assert_eq!(
+            PartitionBound::parse("FOR VALUES FROM (1, 'a') TO (2, 'b')"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_route_range_partitions() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("day", DataType::Date32, true),
+            Field::new("value", DataType::Int64, false),
+        ]));
+        let router = PartitionRouter {
+            column_index: 0,
+            partitions: vec![
+                (
+                    TableReference::partial("public", "events_2024_01"),
+                    TypedBound::try_new(
+                        &PartitionBound::parse("FOR VALUES FROM ('2024-01-01') TO ('2024-02-01')")
+                            .expect("bound"),
+                        &DataType::Date32,
+                    )
+                    .expect("typed bound"),
+                ),
+                (
+                    TableReference::partial("public", "events_2024_02"),
+                    TypedBound::try_new(
+                        &PartitionBound::parse("FOR VALUES FROM ('2024-02-01') TO (MAXVALUE)")
+                            .expect("bound"),
+                        &DataType::Date32,
+                    )
+                    .expect("typed bound"),
+                ),
+            ],
+            default_partition: None,
+        };
+
+        // 2024-01-15, 2024-02-01, NULL, 2023-12-31
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Date32Array::from(vec![
+                    Some(19737),
+                    Some(19754),
+                    None,
+                    Some(19722),
+                ])),
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4])),
+            ],
+        )
+        .expect("record batch");
+
+        let parent = TableReference::bare("events");
+        let routed = router.route(&batch, &parent).expect("routed");
+
+        let routed = routed
+            .iter()
+            .map(|(table, batch)| {
+                let values = batch
+                    .column(1)
+                    .as_any()
+                    .downcast_ref::<Int64Array>()
+                    .expect("int64")
+                    .values()
+                    .to_vec();
+                (table.to_string(), values)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            routed,
+            vec![
+                ("public.events_2024_01".to_string(), vec![1]),
+                ("public.events_2024_02".to_string(), vec![2]),
+                ("events".to_string(), vec![3, 4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_bound_with_uncastable_value() {
+        let bound = PartitionBound::parse("FOR VALUES IN ('a')").expect("bound");
+        assert!(TypedBound::try_new(&bound, &DataType::Int64).is_none());
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use arrow::array::RecordBatch;
+use datafusion::logical_expr::dml::InsertOp;
+use snafu::prelude::*;
+
+use crate::sql::db_connection_pool::{postgrespool::PostgresConnectionPool, DbConnectionPool};
+use crate::util::{constraints, on_conflict::OnConflict};
+
+use super::{write::PostgresTableWriter, Postgres, Result};
+
+struct StagedWrite {
+    postgres: Arc<Postgres>,
+    op: InsertOp,
+    on_conflict: Option<OnConflict>,
+    batches: Vec<RecordBatch>,
+}
+
+/// Stages writes to several tables on the same connection pool and commits them in a single
+/// Postgres transaction, so that either all of the tables are updated or none of them are.
+///
+/// Writes are applied in the order they were staged, e.g. parent tables before the child tables
+/// that reference them. Overwrites delete the existing rows in the transaction rather than swapping
+/// in a staging table, so that foreign keys between the tables keep working.
+///
+/// ```rust,ignore
+/// let mut transaction = WriteTransaction::new(pool);
+/// transaction
+///     .stage(&orders, InsertOp::Append, order_batches)?
+///     .stage(&order_items, InsertOp::Append, item_batches)?;
+/// let num_rows = transaction.commit().await?;
+/// ```
+pub struct WriteTransaction {
+    pool: Arc<PostgresConnectionPool>,
+    writes: Vec<StagedWrite>,
+}
+
+impl WriteTransaction {
+    #[must_use]
+    pub fn new(pool: Arc<PostgresConnectionPool>) -> Self {
+        Self {
+            pool,
+            writes: Vec::new(),
+        }
+    }
+
+    /// Stages `batches` to be written to the table of `writer` when the transaction is committed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table uses a different connection pool than the transaction,
+    /// or if a batch doesn't match the table schema or fails the table's batch validation. Batches
+    /// with retyped columns are cast if the table's schema mismatch mode is lenient.
+    pub fn stage(
+        &mut self,
+        writer: &PostgresTableWriter,
+        op: InsertOp,
+        batches: Vec<RecordBatch>,
+    ) -> Result<&mut Self> {
+        let postgres = writer.postgres();
+        ensure!(
+            Arc::ptr_eq(&postgres.pool, &self.pool),
+            super::WriteTransactionPoolMismatchSnafu {
+                table_name: postgres.table.to_string(),
+            }
+        );
+
+        let batches = batches
+            .into_iter()
+            .map(|batch| {
+                let batch = postgres.reconcile_batch_schema(batch)?;
+                postgres.validate_batch_data(&batch)?;
+                Ok(batch)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.writes.push(StagedWrite {
+            postgres,
+            op,
+            on_conflict: writer.on_conflict().cloned(),
+            batches,
+        });
+
+        Ok(self)
+    }
+
+    /// Writes all staged batches in one transaction and commits it, returning the number of rows written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the writes fail, in which case none of them are committed.
+    pub async fn commit(self) -> Result<u64> {
+        let mut db_conn = self
+            .pool
+            .connect()
+            .await
+            .context(super::DbConnectionSnafu)?;
+        let postgres_conn = Postgres::postgres_conn(&mut db_conn)?;
+
+        for write in &self.writes {
+            if !write.postgres.table_exists(postgres_conn).await {
+                return super::TableDoesntExistSnafu {
+                    table_name: write.postgres.table.to_string(),
+                }
+                .fail();
+            }
+        }
+
+        let mut tx = postgres_conn
+            .conn
+            .transaction()
+            .await
+            .context(super::UnableToBeginTransactionSnafu)?;
+
+        let mut num_rows = 0;
+        for write in self.writes {
+            let postgres = write.postgres;
+
+            if matches!(write.op, InsertOp::Overwrite) {
+                postgres.delete_all_table_data(&tx).await?;
+            }
+
+            let partition_router = postgres.partition_router(&tx).await?;
+            let mut deduplicator = postgres.deduplicator()?;
+
+            for mut batch in write.batches {
+                if let Some(deduplicator) = &mut deduplicator {
+                    batch = deduplicator
+                        .dedup(&batch)
+                        .context(super::UnableToDeduplicateBatchSnafu)?;
+                }
+
+                if batch.num_rows() == 0 {
+                    continue;
+                }
+
+                constraints::validate_batch_with_constraints(
+                    &[batch.clone()],
+                    postgres.constraints(),
+                )
+                .await
+                .context(super::ConstraintViolationSnafu)?;
+
+                let batches = match &partition_router {
+                    Some(partition_router) => partition_router
+                        .route(&batch, &postgres.table)
+                        .context(super::PartitionRoutingSnafu)?,
+                    None => vec![(postgres.table.clone(), batch)],
+                };
+
+                for (table, batch) in batches {
+                    num_rows += postgres
+                        .insert_batch_with_retry(&mut tx, &table, batch, write.on_conflict.clone())
+                        .await?;
+                }
+            }
+        }
+
+        tx.commit()
+            .await
+            .context(super::UnableToCommitPostgresTransactionSnafu)?;
+
+        Ok(num_rows)
+    }
+}
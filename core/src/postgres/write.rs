@@ -1,11 +1,10 @@
 use std::{any::Any, fmt, sync::Arc};
 
 use arrow::datatypes::SchemaRef;
-use arrow_schema::{DataType, Field, Schema};
 use async_trait::async_trait;
 use datafusion::{
     catalog::Session,
-    common::{Constraints, SchemaExt},
+    common::Constraints,
     datasource::{TableProvider, TableType},
     execution::{SendableRecordBatchStream, TaskContext},
     logical_expr::{dml::InsertOp, Expr},
@@ -18,8 +17,10 @@ use datafusion::{
 use futures::StreamExt;
 use snafu::prelude::*;
 
+use crate::sql::dml::{DmlOperation, DmlTableProvider};
 use crate::util::{
-    constraints, on_conflict::OnConflict, retriable_error::check_and_mark_retriable_error,
+    column_names::ColumnRenames, constraints, on_conflict::OnConflict,
+    retriable_error::check_and_mark_retriable_error,
 };
 
 use crate::postgres::Postgres;
@@ -49,6 +50,16 @@ impl PostgresTableWriter {
     pub fn postgres(&self) -> Arc<Postgres> {
         Arc::clone(&self.postgres)
     }
+
+    pub fn on_conflict(&self) -> Option<&OnConflict> {
+        self.on_conflict.as_ref()
+    }
+
+    /// The columns that were renamed when the table was created, see
+    /// [`ColumnNamePolicy`](crate::util::column_names::ColumnNamePolicy).
+    pub fn column_renames(&self) -> &ColumnRenames {
+        self.postgres.column_renames()
+    }
 }
 
 #[async_trait]
@@ -69,6 +80,10 @@ impl TableProvider for PostgresTableWriter {
         Some(self.postgres.constraints())
     }
 
+    fn get_column_default(&self, column: &str) -> Option<&Expr> {
+        self.postgres.column_expressions().default_expr(column)
+    }
+
     async fn scan(
         &self,
         state: &dyn Session,
@@ -100,6 +115,16 @@ impl TableProvider for PostgresTableWriter {
     }
 }
 
+#[async_trait]
+impl DmlTableProvider for PostgresTableWriter {
+    async fn execute_dml(&self, operation: &DmlOperation) -> datafusion::error::Result<u64> {
+        self.postgres
+            .execute_dml(operation)
+            .await
+            .map_err(to_datafusion_error)
+    }
+}
+
 #[derive(Clone)]
 struct PostgresDataSink {
     postgres: Arc<Postgres>,
@@ -132,68 +157,49 @@ impl DataSink for PostgresDataSink {
         let mut db_conn = self.postgres.connect().await.map_err(to_datafusion_error)?;
         let postgres_conn = Postgres::postgres_conn(&mut db_conn).map_err(to_datafusion_error)?;
 
-        let tx = postgres_conn
+        let mut tx = postgres_conn
             .conn
             .transaction()
             .await
             .context(super::UnableToBeginTransactionSnafu)
             .map_err(to_datafusion_error)?;
 
-        if matches!(self.overwrite, InsertOp::Overwrite) {
+        // overwrites either delete the existing rows up front, or load into a staging table that
+        // replaces the table before the commit
+        let staging_table = if matches!(self.overwrite, InsertOp::Overwrite) {
             self.postgres
-                .delete_all_table_data(&tx)
+                .prepare_overwrite(&tx)
                 .await
-                .map_err(to_datafusion_error)?;
-        }
+                .map_err(to_datafusion_error)?
+        } else {
+            None
+        };
+        let target_table = staging_table.as_ref().unwrap_or(&self.postgres.table);
 
-        let postgres_fields = self
+        let partition_router = self
             .postgres
-            .schema
-            .fields
-            .iter()
-            .map(|f| {
-                Arc::new(Field::new(
-                    f.name(),
-                    if f.data_type() == &DataType::LargeUtf8 {
-                        DataType::Utf8
-                    } else {
-                        f.data_type().clone()
-                    },
-                    f.is_nullable(),
-                ))
-            })
-            .collect::<Vec<_>>();
-
-        let postgres_schema = Arc::new(Schema::new(postgres_fields));
+            .partition_router(&tx)
+            .await
+            .map_err(to_datafusion_error)?;
+
+        let mut deduplicator = self.postgres.deduplicator().map_err(to_datafusion_error)?;
 
         while let Some(batch) = data.next().await {
             let batch = batch.map_err(check_and_mark_retriable_error)?;
 
-            // for the purposes of PostgreSQL, LargeUtf8 is equivalent to Utf8
-            // because Postgres physically cannot store anything larger than 1Gb in text (VARCHAR)
-            // normalize LargeUtf8 fields to Utf8 for both the incoming batch, and Postgres if it happens to specify any
-            let batch_fields = batch
-                .schema_ref()
-                .fields()
-                .iter()
-                .map(|f| {
-                    Arc::new(Field::new(
-                        f.name(),
-                        if f.data_type() == &DataType::LargeUtf8 {
-                            DataType::Utf8
-                        } else {
-                            f.data_type().clone()
-                        },
-                        f.is_nullable(),
-                    ))
-                })
-                .collect::<Vec<_>>();
-            let batch_schema = Arc::new(Schema::new(batch_fields));
-
-            if !Arc::clone(&postgres_schema).equivalent_names_and_types(&batch_schema) {
-                return Err(to_datafusion_error(super::Error::SchemaValidationError {
-                    table_name: self.postgres.table.to_string(),
-                }));
+            let mut batch = self
+                .postgres
+                .reconcile_batch_schema(batch)
+                .map_err(to_datafusion_error)?;
+            self.postgres
+                .validate_batch_data(&batch)
+                .map_err(to_datafusion_error)?;
+
+            if let Some(deduplicator) = &mut deduplicator {
+                batch = deduplicator
+                    .dedup(&batch)
+                    .context(super::UnableToDeduplicateBatchSnafu)
+                    .map_err(to_datafusion_error)?;
             }
 
             let batch_num_rows = batch.num_rows();
@@ -202,8 +208,6 @@ impl DataSink for PostgresDataSink {
                 continue;
             };
 
-            num_rows += batch_num_rows as u64;
-
             constraints::validate_batch_with_constraints(
                 &[batch.clone()],
                 self.postgres.constraints(),
@@ -212,8 +216,37 @@ impl DataSink for PostgresDataSink {
             .context(super::ConstraintViolationSnafu)
             .map_err(to_datafusion_error)?;
 
+            let Some(partition_router) = &partition_router else {
+                num_rows += self
+                    .postgres
+                    .insert_batch_with_retry(&mut tx, target_table, batch, self.on_conflict.clone())
+                    .await
+                    .map_err(to_datafusion_error)?;
+                continue;
+            };
+
+            let partition_batches = partition_router
+                .route(&batch, target_table)
+                .context(super::PartitionRoutingSnafu)
+                .map_err(to_datafusion_error)?;
+
+            for (partition, partition_batch) in partition_batches {
+                num_rows += self
+                    .postgres
+                    .insert_batch_with_retry(
+                        &mut tx,
+                        &partition,
+                        partition_batch,
+                        self.on_conflict.clone(),
+                    )
+                    .await
+                    .map_err(to_datafusion_error)?;
+            }
+        }
+
+        if let Some(staging_table) = &staging_table {
             self.postgres
-                .insert_batch(&tx, batch, self.on_conflict.clone())
+                .swap_staging_table(&tx, staging_table)
                 .await
                 .map_err(to_datafusion_error)?;
         }
@@ -0,0 +1,425 @@
+//! # Prelude
+//!
+//! Builders that create a table provider in one expression, instead of creating a connection
+//! pool, a table factory and then the provider, together with the types needed to configure them.
+//!
+//! ```rust,ignore
+//! use datafusion_table_providers::prelude::*;
+//!
+//! let companies = Postgres::table("public.companies")
+//!     .param("host", "localhost")
+//!     .param("db", "postgres_db")
+//!     .writable()
+//!     .build()
+//!     .await?;
+//! ctx.register_table("companies", companies)?;
+//! ```
+//!
+//! Pass an existing pool with `pool()` to share its connections between tables. The builders only
+//! cover the common options, use the table factories directly for everything else.
+
+pub use crate::util::identifier::IdentifierCase;
+pub use crate::UnsupportedTypeAction;
+pub use datafusion::sql::TableReference;
+
+#[cfg(feature = "duckdb")]
+pub use self::duckdb::{DuckDB, DuckDBTableBuilder};
+#[cfg(feature = "mysql")]
+pub use self::mysql::{MySQL, MySQLTableBuilder};
+#[cfg(feature = "postgres")]
+pub use self::postgres::{Postgres, PostgresTableBuilder};
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::{Sqlite, SqliteTableBuilder};
+
+#[cfg(any(
+    feature = "duckdb",
+    feature = "mysql",
+    feature = "postgres",
+    feature = "sqlite"
+))]
+type BuildResult = Result<
+    std::sync::Arc<dyn datafusion::datasource::TableProvider>,
+    Box<dyn std::error::Error + Send + Sync>,
+>;
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use std::{collections::HashMap, sync::Arc};
+
+    use datafusion::sql::TableReference;
+
+    use super::BuildResult;
+    use crate::{
+        postgres::PostgresTableFactory,
+        sql::db_connection_pool::postgrespool::PostgresConnectionPool,
+        util::{identifier::IdentifierCase, secrets::to_secret_map},
+    };
+
+    /// Entry point for building Postgres table providers, see [`Postgres::table`].
+    pub struct Postgres;
+
+    impl Postgres {
+        /// Starts building a provider for the Postgres table, e.g. `"public.companies"`.
+        #[must_use]
+        pub fn table(table_reference: impl Into<TableReference>) -> PostgresTableBuilder {
+            PostgresTableBuilder {
+                table_reference: table_reference.into(),
+                pool: None,
+                params: HashMap::new(),
+                writable: false,
+                identifier_case: IdentifierCase::default(),
+            }
+        }
+    }
+
+    pub struct PostgresTableBuilder {
+        table_reference: TableReference,
+        pool: Option<Arc<PostgresConnectionPool>>,
+        params: HashMap<String, String>,
+        writable: bool,
+        identifier_case: IdentifierCase,
+    }
+
+    impl PostgresTableBuilder {
+        /// Uses an existing connection pool. Connection parameters are ignored when a pool is set.
+        #[must_use]
+        pub fn pool(mut self, pool: Arc<PostgresConnectionPool>) -> Self {
+            self.pool = Some(pool);
+            self
+        }
+
+        /// Sets a connection parameter of the pool to create, e.g. `host` or `pass`.
+        #[must_use]
+        pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+            self.params.insert(key.into(), value.into());
+            self
+        }
+
+        #[must_use]
+        pub fn params(mut self, params: HashMap<String, String>) -> Self {
+            self.params.extend(params);
+            self
+        }
+
+        /// Builds a table provider that also supports `INSERT INTO`.
+        #[must_use]
+        pub fn writable(mut self) -> Self {
+            self.writable = true;
+            self
+        }
+
+        #[must_use]
+        pub fn identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+            self.identifier_case = identifier_case;
+            self
+        }
+
+        pub async fn build(self) -> BuildResult {
+            let pool = match self.pool {
+                Some(pool) => pool,
+                None => Arc::new(PostgresConnectionPool::new(to_secret_map(self.params)).await?),
+            };
+            let factory =
+                PostgresTableFactory::new(pool).with_identifier_case(self.identifier_case);
+
+            if self.writable {
+                factory
+                    .read_write_table_provider(self.table_reference)
+                    .await
+            } else {
+                factory.table_provider(self.table_reference).await
+            }
+        }
+    }
+}
+
+#[cfg(feature = "mysql")]
+mod mysql {
+    use std::{collections::HashMap, sync::Arc};
+
+    use datafusion::sql::TableReference;
+
+    use super::BuildResult;
+    use crate::{
+        mysql::MySQLTableFactory,
+        sql::db_connection_pool::mysqlpool::MySQLConnectionPool,
+        util::{identifier::IdentifierCase, secrets::to_secret_map},
+    };
+
+    /// Entry point for building MySQL table providers, see [`MySQL::table`].
+    pub struct MySQL;
+
+    impl MySQL {
+        /// Starts building a provider for the MySQL table, e.g. `"shop.orders"`.
+        #[must_use]
+        pub fn table(table_reference: impl Into<TableReference>) -> MySQLTableBuilder {
+            MySQLTableBuilder {
+                table_reference: table_reference.into(),
+                pool: None,
+                params: HashMap::new(),
+                writable: false,
+                identifier_case: IdentifierCase::default(),
+            }
+        }
+    }
+
+    pub struct MySQLTableBuilder {
+        table_reference: TableReference,
+        pool: Option<Arc<MySQLConnectionPool>>,
+        params: HashMap<String, String>,
+        writable: bool,
+        identifier_case: IdentifierCase,
+    }
+
+    impl MySQLTableBuilder {
+        /// Uses an existing connection pool. Connection parameters are ignored when a pool is set.
+        #[must_use]
+        pub fn pool(mut self, pool: Arc<MySQLConnectionPool>) -> Self {
+            self.pool = Some(pool);
+            self
+        }
+
+        /// Sets a connection parameter of the pool to create, e.g. `connection_string`.
+        #[must_use]
+        pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+            self.params.insert(key.into(), value.into());
+            self
+        }
+
+        #[must_use]
+        pub fn params(mut self, params: HashMap<String, String>) -> Self {
+            self.params.extend(params);
+            self
+        }
+
+        /// Builds a table provider that also supports `INSERT INTO`.
+        #[must_use]
+        pub fn writable(mut self) -> Self {
+            self.writable = true;
+            self
+        }
+
+        #[must_use]
+        pub fn identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+            self.identifier_case = identifier_case;
+            self
+        }
+
+        pub async fn build(self) -> BuildResult {
+            let pool = match self.pool {
+                Some(pool) => pool,
+                None => Arc::new(MySQLConnectionPool::new(to_secret_map(self.params)).await?),
+            };
+            let factory = MySQLTableFactory::new(pool).with_identifier_case(self.identifier_case);
+
+            if self.writable {
+                factory
+                    .read_write_table_provider(self.table_reference)
+                    .await
+            } else {
+                factory.table_provider(self.table_reference).await
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::{sync::Arc, time::Duration};
+
+    use datafusion::sql::TableReference;
+
+    use super::BuildResult;
+    use crate::{
+        sql::db_connection_pool::{
+            sqlitepool::{SqliteConnectionPool, SqliteConnectionPoolFactory},
+            Mode,
+        },
+        sqlite::SqliteTableFactory,
+        util::identifier::IdentifierCase,
+    };
+
+    /// Entry point for building SQLite table providers, see [`Sqlite::table`].
+    pub struct Sqlite;
+
+    impl Sqlite {
+        /// Starts building a provider for the SQLite table. Without a [`SqliteTableBuilder::path`]
+        /// or [`SqliteTableBuilder::pool`] the table is looked up in an in-memory database.
+        #[must_use]
+        pub fn table(table_reference: impl Into<TableReference>) -> SqliteTableBuilder {
+            SqliteTableBuilder {
+                table_reference: table_reference.into(),
+                pool: None,
+                path: None,
+                busy_timeout: Duration::from_millis(5000),
+                identifier_case: IdentifierCase::default(),
+            }
+        }
+    }
+
+    pub struct SqliteTableBuilder {
+        table_reference: TableReference,
+        pool: Option<Arc<SqliteConnectionPool>>,
+        path: Option<String>,
+        busy_timeout: Duration,
+        identifier_case: IdentifierCase,
+    }
+
+    impl SqliteTableBuilder {
+        /// Uses an existing connection pool. The path is ignored when a pool is set.
+        #[must_use]
+        pub fn pool(mut self, pool: Arc<SqliteConnectionPool>) -> Self {
+            self.pool = Some(pool);
+            self
+        }
+
+        /// Opens the database file at `path`.
+        #[must_use]
+        pub fn path(mut self, path: impl Into<String>) -> Self {
+            self.path = Some(path.into());
+            self
+        }
+
+        #[must_use]
+        pub fn busy_timeout(mut self, busy_timeout: Duration) -> Self {
+            self.busy_timeout = busy_timeout;
+            self
+        }
+
+        #[must_use]
+        pub fn identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+            self.identifier_case = identifier_case;
+            self
+        }
+
+        pub async fn build(self) -> BuildResult {
+            let pool = match self.pool {
+                Some(pool) => pool,
+                None => {
+                    let (path, mode) = match &self.path {
+                        Some(path) => (path.as_str(), Mode::File),
+                        None => ("", Mode::Memory),
+                    };
+                    Arc::new(
+                        SqliteConnectionPoolFactory::new(path, mode, self.busy_timeout)
+                            .build()
+                            .await?,
+                    )
+                }
+            };
+
+            SqliteTableFactory::new(pool)
+                .with_identifier_case(self.identifier_case)
+                .table_provider(self.table_reference)
+                .await
+        }
+    }
+}
+
+#[cfg(feature = "duckdb")]
+mod duckdb {
+    use std::sync::Arc;
+
+    use datafusion::sql::TableReference;
+    use duckdb::AccessMode;
+
+    use super::BuildResult;
+    use crate::{
+        duckdb::DuckDBTableFactory, sql::db_connection_pool::duckdbpool::DuckDbConnectionPool,
+        util::identifier::IdentifierCase,
+    };
+
+    /// Entry point for building DuckDB table providers, see [`DuckDB::table`].
+    pub struct DuckDB;
+
+    impl DuckDB {
+        /// Starts building a provider for the DuckDB table or table function, e.g.
+        /// `"read_parquet('sales.parquet')"`. Without a [`DuckDBTableBuilder::path`] or
+        /// [`DuckDBTableBuilder::pool`] the table is looked up in an in-memory database.
+        #[must_use]
+        pub fn table(table_reference: impl Into<TableReference>) -> DuckDBTableBuilder {
+            DuckDBTableBuilder {
+                table_reference: table_reference.into(),
+                pool: None,
+                path: None,
+                writable: false,
+                identifier_case: IdentifierCase::default(),
+            }
+        }
+    }
+
+    pub struct DuckDBTableBuilder {
+        table_reference: TableReference,
+        pool: Option<Arc<DuckDbConnectionPool>>,
+        path: Option<String>,
+        writable: bool,
+        identifier_case: IdentifierCase,
+    }
+
+    impl DuckDBTableBuilder {
+        /// Uses an existing connection pool. The path is ignored when a pool is set.
+        #[must_use]
+        pub fn pool(mut self, pool: Arc<DuckDbConnectionPool>) -> Self {
+            self.pool = Some(pool);
+            self
+        }
+
+        /// Opens the database file at `path`, read-only unless the table is [`Self::writable`].
+        #[must_use]
+        pub fn path(mut self, path: impl Into<String>) -> Self {
+            self.path = Some(path.into());
+            self
+        }
+
+        /// Builds a table provider that also supports `INSERT INTO`.
+        #[must_use]
+        pub fn writable(mut self) -> Self {
+            self.writable = true;
+            self
+        }
+
+        #[must_use]
+        pub fn identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+            self.identifier_case = identifier_case;
+            self
+        }
+
+        pub async fn build(self) -> BuildResult {
+            let pool = match (self.pool, &self.path) {
+                (Some(pool), _) => pool,
+                (None, Some(path)) => {
+                    let access_mode = if self.writable {
+                        AccessMode::ReadWrite
+                    } else {
+                        AccessMode::ReadOnly
+                    };
+                    Arc::new(DuckDbConnectionPool::new_file(path, &access_mode)?)
+                }
+                (None, None) => Arc::new(DuckDbConnectionPool::new_memory()?),
+            };
+            let factory = DuckDBTableFactory::new(pool).with_identifier_case(self.identifier_case);
+
+            if self.writable {
+                factory
+                    .read_write_table_provider(self.table_reference)
+                    .await
+            } else {
+                factory.table_provider(self.table_reference).await
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "duckdb"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_duckdb_table_builder() {
+        let table = DuckDB::table("range(3)")
+            .build()
+            .await
+            .expect("table provider built");
+        assert_eq!(table.schema().fields().len(), 1);
+    }
+}
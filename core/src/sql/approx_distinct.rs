@@ -0,0 +1,131 @@
+//! Pushing down `approx_distinct` with the distinct count estimate of the remote database.
+//!
+//! The federation optimizer pushes down the aggregates of the sub-plans it federates with the
+//! names of the DataFusion functions, and no database has an `approx_distinct`. The calls are
+//! rewritten to the HyperLogLog based function of DuckDB. Postgres, MySQL and SQLite have no
+//! estimate, so their calls are only rewritten to an exact `count(DISTINCT ...)` if the table opts
+//! in with [`ApproxDistinct::Exact`].
+
+use std::ops::ControlFlow;
+
+use datafusion::sql::sqlparser::ast::{self, visit_expressions_mut};
+
+use super::dialect::function;
+
+/// How `approx_distinct(x)` is written in the SQL of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApproxDistinct {
+    /// `count(DISTINCT x)`, which is a valid estimate, but sorts or hashes every distinct value on
+    /// the database, in memory and time that grow with the number of distinct values instead of the
+    /// fixed size of a HyperLogLog sketch. Never the default.
+    Exact,
+    /// `approx_count_distinct(x)`.
+    DuckDB,
+}
+
+impl ApproxDistinct {
+    /// The estimate of the database of a table provider, by the name of the provider, if it has
+    /// one.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<Self> {
+        match name {
+            "duckdb" => Some(Self::DuckDB),
+            _ => None,
+        }
+    }
+
+    /// Rewrites the calls of `approx_distinct` with a single argument in `statement`.
+    pub(crate) fn rewrite_statement(self, statement: &mut ast::Statement) {
+        let _ = visit_expressions_mut(statement, |expr| {
+            if let Some(arg) = approx_distinct_arg(expr) {
+                *expr = self.to_sql(arg);
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+
+    fn to_sql(self, arg: ast::Expr) -> ast::Expr {
+        match self {
+            Self::Exact => {
+                let mut count = function("count", vec![arg]);
+                if let ast::Expr::Function(ast::Function {
+                    args: ast::FunctionArguments::List(args),
+                    ..
+                }) = &mut count
+                {
+                    args.duplicate_treatment = Some(ast::DuplicateTreatment::Distinct);
+                }
+                count
+            }
+            Self::DuckDB => function("approx_count_distinct", vec![arg]),
+        }
+    }
+}
+
+/// The argument of `expr` if it's a plain call of `approx_distinct`, without `FILTER` or `OVER`.
+fn approx_distinct_arg(expr: &ast::Expr) -> Option<ast::Expr> {
+    let ast::Expr::Function(function) = expr else {
+        return None;
+    };
+    let [name] = function.name.0.as_slice() else {
+        return None;
+    };
+    if !name.value.eq_ignore_ascii_case("approx_distinct")
+        || function.filter.is_some()
+        || function.over.is_some()
+    {
+        return None;
+    }
+    let ast::FunctionArguments::List(args) = &function.args else {
+        return None;
+    };
+    match args.args.as_slice() {
+        [ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg))] => Some(arg.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::sql::sqlparser::{dialect::GenericDialect, parser::Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_for_database() {
+        assert_eq!(
+            ApproxDistinct::for_database("duckdb"),
+            Some(ApproxDistinct::DuckDB)
+        );
+        // the exact count is opt-in
+        for name in ["postgres", "mysql", "sqlite"] {
+            assert_eq!(ApproxDistinct::for_database(name), None);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let sql = r#"SELECT "region", approx_distinct("user_id") FROM "events" GROUP BY "region" HAVING approx_distinct("session_id") > 10"#;
+        for (approx_distinct, expected) in [
+            (
+                ApproxDistinct::Exact,
+                r#"SELECT "region", count(DISTINCT "user_id") FROM "events" GROUP BY "region" HAVING count(DISTINCT "session_id") > 10"#,
+            ),
+            (
+                ApproxDistinct::DuckDB,
+                r#"SELECT "region", approx_count_distinct("user_id") FROM "events" GROUP BY "region" HAVING approx_count_distinct("session_id") > 10"#,
+            ),
+        ] {
+            let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+            approx_distinct.rewrite_statement(&mut statement);
+            assert_eq!(statement.to_string(), expected);
+        }
+
+        // the window functions aren't rewritten
+        let sql = r#"SELECT approx_distinct("user_id") OVER (PARTITION BY "region") FROM "events""#;
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        ApproxDistinct::DuckDB.rewrite_statement(&mut statement);
+        assert_eq!(statement.to_string(), sql);
+        Ok(())
+    }
+}
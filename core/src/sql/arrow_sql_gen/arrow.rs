@@ -9,7 +9,7 @@ use datafusion::arrow::{
         TimestampNanosecondBuilder, TimestampSecondBuilder, UInt16Builder, UInt32Builder,
         UInt64Builder, UInt8Builder,
     },
-    datatypes::{DataType, TimeUnit, UInt16Type},
+    datatypes::{DataType, Int32Type, TimeUnit, UInt16Type},
 };
 
 pub fn map_data_type_to_array_builder_optional(
@@ -68,6 +68,9 @@ pub fn map_data_type_to_array_builder(data_type: &DataType) -> Box<dyn ArrayBuil
             (DataType::Int8, DataType::Utf8) => {
                 Box::new(StringDictionaryBuilder::<Int8Type>::new())
             }
+            (DataType::Int32, DataType::Utf8) => {
+                Box::new(StringDictionaryBuilder::<Int32Type>::new())
+            }
             (DataType::UInt16, DataType::Utf8) => {
                 Box::new(StringDictionaryBuilder::<UInt16Type>::new())
             }
@@ -92,6 +95,11 @@ pub fn map_data_type_to_array_builder(data_type: &DataType) -> Box<dyn ArrayBuil
                 DataType::Utf8 => Box::new(ListBuilder::new(StringBuilder::new())),
                 DataType::Boolean => Box::new(ListBuilder::new(BooleanBuilder::new())),
                 DataType::Binary => Box::new(ListBuilder::new(BinaryBuilder::new())),
+                DataType::Decimal128(precision, scale) => Box::new(ListBuilder::new(
+                    Decimal128Builder::new()
+                        .with_precision_and_scale(*precision, *scale)
+                        .unwrap_or_default(),
+                )),
                 _ => unimplemented!("Unsupported list value data type {:?}", data_type),
             }
         }
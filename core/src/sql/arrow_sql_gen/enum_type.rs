@@ -0,0 +1,90 @@
+//! The enum type of the dictionary columns read from Postgres enums.
+//!
+//! Enums are read as `Dictionary(Int32, Utf8)` of their labels. The fields carry the name and the
+//! labels of the enum type, so that the tables created from them, like the ones a table is copied
+//! into, declare the same enum instead of `text`.
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use serde::{Deserialize, Serialize};
+
+/// The metadata key of the enum type of a field, as JSON.
+pub const ENUM_TYPE_KEY: &str = "postgres.enum";
+
+/// A Postgres enum type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnumType {
+    /// The name of the type.
+    pub name: String,
+    /// The labels of the type, in their sort order.
+    pub values: Vec<String>,
+}
+
+impl EnumType {
+    #[must_use]
+    pub fn new(name: impl Into<String>, values: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            values,
+        }
+    }
+
+    /// The enum type `field` carries, if it's a dictionary of strings.
+    #[must_use]
+    pub fn of_field(field: &Field) -> Option<Self> {
+        let DataType::Dictionary(_, value_type) = field.data_type() else {
+            return None;
+        };
+        if !matches!(
+            **value_type,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+        ) {
+            return None;
+        }
+        serde_json::from_str(field.metadata().get(ENUM_TYPE_KEY)?).ok()
+    }
+
+    /// `field` carrying this enum type.
+    #[must_use]
+    pub fn apply_to_field(&self, field: Field) -> Field {
+        let Ok(enum_type) = serde_json::to_string(self) else {
+            return field;
+        };
+        let mut metadata = field.metadata().clone();
+        metadata.insert(ENUM_TYPE_KEY.to_string(), enum_type);
+        field.with_metadata(metadata)
+    }
+}
+
+/// An enum field named `name` of the labels of `enum_type`.
+#[must_use]
+pub fn enum_field(name: impl Into<String>, enum_type: &EnumType, nullable: bool) -> Field {
+    enum_type.apply_to_field(Field::new(
+        name,
+        DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+        nullable,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enum_field() {
+        let enum_type = EnumType::new("mood", vec!["sad".to_string(), "happy".to_string()]);
+        let field = enum_field("mood_status", &enum_type, true);
+        assert_eq!(
+            field.metadata().get(ENUM_TYPE_KEY).map(String::as_str),
+            Some(r#"{"name":"mood","values":["sad","happy"]}"#)
+        );
+        assert_eq!(EnumType::of_field(&field), Some(enum_type));
+
+        // plain dictionaries have no enum type
+        let field = Field::new(
+            "mood_status",
+            DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Utf8)),
+            true,
+        );
+        assert_eq!(EnumType::of_field(&field), None);
+    }
+}
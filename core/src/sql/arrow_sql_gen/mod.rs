@@ -43,6 +43,7 @@
 //! ```
 
 pub mod arrow;
+pub mod enum_type;
 #[cfg(feature = "mysql")]
 pub mod mysql;
 #[cfg(feature = "postgres")]
@@ -50,3 +51,5 @@ pub mod postgres;
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 pub mod statement;
+pub mod timestamp;
+pub mod uuid_format;
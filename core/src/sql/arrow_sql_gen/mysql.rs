@@ -62,6 +62,32 @@ pub enum Error {
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// How `BIGINT UNSIGNED` columns are read. The other unsigned integer columns are read as the next
+/// larger signed type, which holds all their values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnsignedBigIntPolicy {
+    /// Read as `UInt64`, which holds all the values.
+    #[default]
+    UInt64,
+    /// Read as `Int64`, failing on values above `i64::MAX`.
+    Int64,
+    /// Read as `Int64`, with values above `i64::MAX` read as null.
+    Int64OrNull,
+}
+
+impl UnsignedBigIntPolicy {
+    /// Parses the `unsigned_bigint` parameter of the pool: `uint64`, `int64` or `int64_or_null`.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "uint64" => Some(Self::UInt64),
+            "int64" => Some(Self::Int64),
+            "int64_or_null" => Some(Self::Int64OrNull),
+            _ => None,
+        }
+    }
+}
+
 macro_rules! handle_primitive_type {
     ($builder:expr, $type:expr, $builder_ty:ty, $value_ty:ty, $row:expr, $index:expr, $column_name:expr) => {{
         let Some(builder) = $builder else {
@@ -94,7 +120,11 @@ macro_rules! handle_primitive_type {
 ///
 /// Returns an error if there is a failure in converting the rows to a `RecordBatch`.
 #[allow(clippy::too_many_lines)]
-pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Result<RecordBatch> {
+pub fn rows_to_arrow(
+    rows: &[Row],
+    projected_schema: &Option<SchemaRef>,
+    unsigned_bigint: UnsignedBigIntPolicy,
+) -> Result<RecordBatch> {
     let mut arrow_fields: Vec<Option<Field>> = Vec::new();
     let mut arrow_columns_builders: Vec<Option<Box<dyn ArrayBuilder>>> = Vec::new();
     let mut mysql_types: Vec<ColumnType> = Vec::new();
@@ -102,6 +132,7 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
     let mut column_is_binary_stats: Vec<bool> = Vec::new();
     let mut column_is_enum_stats: Vec<bool> = Vec::new();
     let mut column_use_large_str_or_blob_stats: Vec<bool> = Vec::new();
+    let mut column_is_unsigned_stats: Vec<bool> = Vec::new();
 
     if !rows.is_empty() {
         let row = &rows[0];
@@ -111,6 +142,7 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
             let column_is_binary = column.flags().contains(ColumnFlags::BINARY_FLAG);
             let column_is_enum = column.flags().contains(ColumnFlags::ENUM_FLAG);
             let column_use_large_str_or_blob = column.column_length() > 2_u32.pow(31) - 1;
+            let column_is_unsigned = column.flags().contains(ColumnFlags::UNSIGNED_FLAG);
 
             let (decimal_precision, decimal_scale) = match column_type {
                 ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
@@ -132,6 +164,8 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
                 column_is_binary,
                 column_is_enum,
                 column_use_large_str_or_blob,
+                column_is_unsigned,
+                unsigned_bigint,
                 decimal_precision,
                 decimal_scale,
             );
@@ -148,6 +182,7 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
             column_is_binary_stats.push(column_is_binary);
             column_is_enum_stats.push(column_is_enum);
             column_use_large_str_or_blob_stats.push(column_use_large_str_or_blob);
+            column_is_unsigned_stats.push(column_is_unsigned);
         }
     }
 
@@ -158,6 +193,7 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
             };
 
             let column_name = column_names.get(i).cloned().unwrap_or_default();
+            let column_is_unsigned = column_is_unsigned_stats.get(i).copied().unwrap_or_default();
 
             match *mysql_type {
                 ColumnType::MYSQL_TYPE_NULL => {
@@ -200,6 +236,83 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
                         _ => builder.append_null(),
                     }
                 }
+                ColumnType::MYSQL_TYPE_TINY if column_is_unsigned => {
+                    handle_primitive_type!(
+                        builder,
+                        ColumnType::MYSQL_TYPE_TINY,
+                        Int16Builder,
+                        i16,
+                        row,
+                        i,
+                        column_name
+                    );
+                }
+                column_type @ (ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_INT24)
+                    if column_is_unsigned =>
+                {
+                    handle_primitive_type!(
+                        builder,
+                        column_type,
+                        Int32Builder,
+                        i32,
+                        row,
+                        i,
+                        column_name
+                    );
+                }
+                ColumnType::MYSQL_TYPE_LONG if column_is_unsigned => {
+                    handle_primitive_type!(
+                        builder,
+                        ColumnType::MYSQL_TYPE_LONG,
+                        Int64Builder,
+                        i64,
+                        row,
+                        i,
+                        column_name
+                    );
+                }
+                ColumnType::MYSQL_TYPE_LONGLONG if column_is_unsigned => match unsigned_bigint {
+                    UnsignedBigIntPolicy::UInt64 => {
+                        handle_primitive_type!(
+                            builder,
+                            ColumnType::MYSQL_TYPE_LONGLONG,
+                            UInt64Builder,
+                            u64,
+                            row,
+                            i,
+                            column_name
+                        );
+                    }
+                    UnsignedBigIntPolicy::Int64 | UnsignedBigIntPolicy::Int64OrNull => {
+                        let Some(builder) = builder else {
+                            return NoBuilderForIndexSnafu { index: i }.fail();
+                        };
+                        let Some(builder) = builder.as_any_mut().downcast_mut::<Int64Builder>()
+                        else {
+                            return FailedToDowncastBuilderSnafu {
+                                mysql_type: format!("{mysql_type:?}"),
+                            }
+                            .fail();
+                        };
+                        let v = handle_null_error(row.get_opt::<u64, usize>(i).transpose())
+                            .context(FailedToGetRowValueSnafu {
+                                column: column_name,
+                                mysql_type: ColumnType::MYSQL_TYPE_LONGLONG,
+                            })?;
+                        match v {
+                            Some(v) => match i64::try_from(v) {
+                                Ok(v) => builder.append_value(v),
+                                Err(_) if unsigned_bigint == UnsignedBigIntPolicy::Int64OrNull => {
+                                    builder.append_null();
+                                }
+                                Err(source) => {
+                                    return Err(Error::FailedToConvertU64toI64 { source });
+                                }
+                            },
+                            None => builder.append_null(),
+                        }
+                    }
+                },
                 ColumnType::MYSQL_TYPE_TINY => {
                     handle_primitive_type!(
                         builder,
@@ -572,15 +685,28 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
 }
 
 #[allow(clippy::unnecessary_wraps)]
+#[allow(clippy::too_many_arguments)]
 pub fn map_column_to_data_type(
     column_type: ColumnType,
     column_is_binary: bool,
     column_is_enum: bool,
     column_use_large_str_or_blob: bool,
+    column_is_unsigned: bool,
+    unsigned_bigint: UnsignedBigIntPolicy,
     column_decimal_precision: Option<u8>,
     column_decimal_scale: Option<i8>,
 ) -> Option<DataType> {
     match column_type {
+        // unsigned integers take the next larger signed type, which holds all their values
+        ColumnType::MYSQL_TYPE_TINY if column_is_unsigned => Some(DataType::Int16),
+        ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_INT24 if column_is_unsigned => {
+            Some(DataType::Int32)
+        }
+        ColumnType::MYSQL_TYPE_LONG if column_is_unsigned => Some(DataType::Int64),
+        ColumnType::MYSQL_TYPE_LONGLONG if column_is_unsigned => match unsigned_bigint {
+            UnsignedBigIntPolicy::UInt64 => Some(DataType::UInt64),
+            UnsignedBigIntPolicy::Int64 | UnsignedBigIntPolicy::Int64OrNull => Some(DataType::Int64),
+        },
         ColumnType::MYSQL_TYPE_NULL => Some(DataType::Null),
         ColumnType::MYSQL_TYPE_BIT => Some(DataType::UInt64),
         ColumnType::MYSQL_TYPE_TINY => Some(DataType::Int8),
@@ -685,3 +811,71 @@ fn handle_null_error<T>(
         err => err,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_integer(column_type: ColumnType, unsigned_bigint: UnsignedBigIntPolicy) -> DataType {
+        map_column_to_data_type(
+            column_type,
+            false,
+            false,
+            false,
+            true,
+            unsigned_bigint,
+            None,
+            None,
+        )
+        .expect("integer types are supported")
+    }
+
+    #[test]
+    fn test_map_unsigned_integers_to_larger_types() {
+        let policy = UnsignedBigIntPolicy::default();
+        assert_eq!(
+            map_integer(ColumnType::MYSQL_TYPE_TINY, policy),
+            DataType::Int16
+        );
+        assert_eq!(
+            map_integer(ColumnType::MYSQL_TYPE_SHORT, policy),
+            DataType::Int32
+        );
+        assert_eq!(
+            map_integer(ColumnType::MYSQL_TYPE_INT24, policy),
+            DataType::Int32
+        );
+        assert_eq!(
+            map_integer(ColumnType::MYSQL_TYPE_LONG, policy),
+            DataType::Int64
+        );
+        assert_eq!(
+            map_integer(ColumnType::MYSQL_TYPE_LONGLONG, policy),
+            DataType::UInt64
+        );
+        assert_eq!(
+            map_integer(
+                ColumnType::MYSQL_TYPE_LONGLONG,
+                UnsignedBigIntPolicy::Int64OrNull
+            ),
+            DataType::Int64
+        );
+    }
+
+    #[test]
+    fn test_parse_unsigned_bigint_policy() {
+        assert_eq!(
+            UnsignedBigIntPolicy::parse("uint64"),
+            Some(UnsignedBigIntPolicy::UInt64)
+        );
+        assert_eq!(
+            UnsignedBigIntPolicy::parse("int64"),
+            Some(UnsignedBigIntPolicy::Int64)
+        );
+        assert_eq!(
+            UnsignedBigIntPolicy::parse("int64_or_null"),
+            Some(UnsignedBigIntPolicy::Int64OrNull)
+        );
+        assert_eq!(UnsignedBigIntPolicy::parse("u64"), None);
+    }
+}
@@ -3,16 +3,19 @@ use std::io::Read;
 use std::sync::Arc;
 
 use crate::sql::arrow_sql_gen::arrow::map_data_type_to_array_builder_optional;
+use crate::sql::arrow_sql_gen::enum_type::EnumType;
 use crate::sql::arrow_sql_gen::statement::map_data_type_to_column_type;
+use crate::sql::arrow_sql_gen::uuid_format::{uuid_field, UuidFormat};
 use arrow::array::{
     ArrayBuilder, ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder,
-    FixedSizeListBuilder, Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder,
-    Int8Builder, IntervalMonthDayNanoBuilder, LargeBinaryBuilder, LargeStringBuilder, ListBuilder,
-    RecordBatch, RecordBatchOptions, StringBuilder, StringDictionaryBuilder, StructBuilder,
-    Time64NanosecondBuilder, TimestampNanosecondBuilder, UInt32Builder,
+    FixedSizeBinaryBuilder, FixedSizeListBuilder, Float32Builder, Float64Builder, Int16Builder,
+    Int32Builder, Int64Builder, Int8Builder, IntervalMonthDayNanoBuilder, LargeBinaryBuilder,
+    LargeStringBuilder, ListBuilder, RecordBatch, RecordBatchOptions, StringBuilder,
+    StringDictionaryBuilder, StructBuilder, Time64NanosecondBuilder, TimestampNanosecondBuilder,
+    UInt32Builder,
 };
 use arrow::datatypes::{
-    DataType, Date32Type, Field, Int8Type, IntervalMonthDayNanoType, IntervalUnit, Schema,
+    DataType, Date32Type, Field, Int32Type, IntervalMonthDayNanoType, IntervalUnit, Schema,
     SchemaRef, TimeUnit,
 };
 use bigdecimal::num_bigint::BigInt;
@@ -27,14 +30,19 @@ use sea_query::{Alias, ColumnType, SeaRc};
 use serde_json::Value;
 use snafu::prelude::*;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_postgres::binary_copy::BinaryCopyOutRow;
 use tokio_postgres::types::FromSql;
 use tokio_postgres::types::Kind;
-use tokio_postgres::{types::Type, Row};
+use tokio_postgres::{types::Type, Column, Row, Statement};
 
 pub mod builder;
 pub mod composite;
 pub mod schema;
 
+/// Scale of the elements of `numeric[]` columns read without a schema, the same as the one
+/// [`schema::pg_data_type_to_arrow_type`] gives `numeric` without a precision.
+const DEFAULT_NUMERIC_ARRAY_SCALE: i8 = 20;
+
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Failed to build record batch: {source}"))]
@@ -185,6 +193,61 @@ macro_rules! handle_composite_types {
     }
 }
 
+/// A row of a query result, read with the extended query protocol or with
+/// `COPY (...) TO STDOUT (FORMAT binary)`, whose values are in the same binary format.
+pub trait PostgresRow {
+    fn columns(&self) -> &[Column];
+
+    /// Decodes the value of the column at `idx`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value can't be decoded as `T`.
+    fn try_get<'a, I: Into<usize>, T: FromSql<'a>>(
+        &'a self,
+        idx: I,
+    ) -> std::result::Result<T, tokio_postgres::Error>;
+}
+
+impl PostgresRow for Row {
+    fn columns(&self) -> &[Column] {
+        Row::columns(self)
+    }
+
+    fn try_get<'a, I: Into<usize>, T: FromSql<'a>>(
+        &'a self,
+        idx: I,
+    ) -> std::result::Result<T, tokio_postgres::Error> {
+        Row::try_get::<usize, T>(self, idx.into())
+    }
+}
+
+/// A row of a binary `COPY`, with the columns of the statement it copies the result of.
+pub struct CopyRow {
+    row: BinaryCopyOutRow,
+    statement: Statement,
+}
+
+impl CopyRow {
+    #[must_use]
+    pub fn new(row: BinaryCopyOutRow, statement: Statement) -> Self {
+        Self { row, statement }
+    }
+}
+
+impl PostgresRow for CopyRow {
+    fn columns(&self) -> &[Column] {
+        self.statement.columns()
+    }
+
+    fn try_get<'a, I: Into<usize>, T: FromSql<'a>>(
+        &'a self,
+        idx: I,
+    ) -> std::result::Result<T, tokio_postgres::Error> {
+        self.row.try_get(idx.into())
+    }
+}
+
 /// Converts Postgres `Row`s to an Arrow `RecordBatch`. Assumes that all rows have the same schema and
 /// sets the schema based on the first row.
 ///
@@ -192,7 +255,10 @@ macro_rules! handle_composite_types {
 ///
 /// Returns an error if there is a failure in converting the rows to a `RecordBatch`.
 #[allow(clippy::too_many_lines)]
-pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Result<RecordBatch> {
+pub fn rows_to_arrow<R: PostgresRow>(
+    rows: &[R],
+    projected_schema: &Option<SchemaRef>,
+) -> Result<RecordBatch> {
     let mut arrow_fields: Vec<Option<Field>> = Vec::new();
     let mut arrow_columns_builders: Vec<Option<Box<dyn ArrayBuilder>>> = Vec::new();
     let mut postgres_types: Vec<Type> = Vec::new();
@@ -219,13 +285,48 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
                 } else {
                     None
                 }
+            } else if *column_type == Type::NUMERIC_ARRAY {
+                // the elements take the scale of the column, or of `numeric` without a typmod
+                let (precision, scale) = projected_schema
+                    .as_ref()
+                    .and_then(|schema| get_decimal_list_precision_and_scale(column_name, schema))
+                    .unwrap_or((38, DEFAULT_NUMERIC_ARRAY_SCALE));
+                numeric_scale = Some(u16::try_from(scale).unwrap_or_default());
+                Some(DataType::List(Arc::new(Field::new(
+                    "item",
+                    DataType::Decimal128(precision, scale),
+                    true,
+                ))))
+            } else if *column_type == Type::UUID {
+                // UUIDs are read as strings unless the projected schema has them as binary
+                let uuid_format = match projected_schema
+                    .as_ref()
+                    .and_then(|schema| schema.field_with_name(column_name).ok())
+                {
+                    Some(field) if field.data_type() == &DataType::FixedSizeBinary(16) => {
+                        UuidFormat::FixedSizeBinary
+                    }
+                    _ => UuidFormat::Utf8,
+                };
+                Some(uuid_format.data_type())
             } else {
                 map_column_type_to_data_type(column_type, column_name)?
             };
 
             match &data_type {
+                Some(data_type) if *column_type == Type::UUID => {
+                    arrow_fields.push(Some(uuid_field(column_name, data_type.clone(), true)));
+                }
                 Some(data_type) => {
-                    arrow_fields.push(Some(Field::new(column_name, data_type.clone(), true)));
+                    let field = Field::new(column_name, data_type.clone(), true);
+                    // enums keep their type, to be created with the tables they're written to
+                    let field = match column_type.kind() {
+                        Kind::Enum(values) => {
+                            EnumType::new(column_type.name(), values.clone()).apply_to_field(field)
+                        }
+                        _ => field,
+                    };
+                    arrow_fields.push(Some(field));
                 }
                 None => arrow_fields.push(None),
             }
@@ -629,17 +730,30 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
                     let Some(builder) = builder else {
                         return NoBuilderForIndexSnafu { index: i }.fail();
                     };
+                    let v = row.try_get::<usize, Option<uuid::Uuid>>(i).context(
+                        FailedToGetRowValueSnafu {
+                            pg_type: Type::UUID,
+                        },
+                    )?;
+
+                    if let Some(builder) = builder
+                        .as_any_mut()
+                        .downcast_mut::<FixedSizeBinaryBuilder>()
+                    {
+                        match v {
+                            Some(v) => builder
+                                .append_value(v.as_bytes())
+                                .context(FailedToBuildRecordBatchSnafu)?,
+                            None => builder.append_null(),
+                        }
+                        continue;
+                    }
                     let Some(builder) = builder.as_any_mut().downcast_mut::<StringBuilder>() else {
                         return FailedToDowncastBuilderSnafu {
                             postgres_type: format!("{postgres_type}"),
                         }
                         .fail();
                     };
-                    let v = row.try_get::<usize, Option<uuid::Uuid>>(i).context(
-                        FailedToGetRowValueSnafu {
-                            pg_type: Type::UUID,
-                        },
-                    )?;
 
                     match v {
                         Some(v) => builder.append_value(v.to_string()),
@@ -694,14 +808,56 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
                     ListBuilder<Float64Builder>,
                     f64
                 ),
-                Type::TEXT_ARRAY => handle_primitive_array_type!(
-                    Type::TEXT_ARRAY,
-                    builder,
-                    row,
-                    i,
-                    ListBuilder<StringBuilder>,
-                    String
-                ),
+                Type::TEXT_ARRAY | Type::VARCHAR_ARRAY | Type::BPCHAR_ARRAY => {
+                    handle_primitive_array_type!(
+                        postgres_type.clone(),
+                        builder,
+                        row,
+                        i,
+                        ListBuilder<StringBuilder>,
+                        String
+                    );
+                }
+                Type::NUMERIC_ARRAY => {
+                    let Some(builder) = builder else {
+                        return NoBuilderForIndexSnafu { index: i }.fail();
+                    };
+                    let Some(builder) = builder
+                        .as_any_mut()
+                        .downcast_mut::<ListBuilder<Decimal128Builder>>()
+                    else {
+                        return FailedToDowncastBuilderSnafu {
+                            postgres_type: format!("{postgres_type}"),
+                        }
+                        .fail();
+                    };
+                    let v: Option<Vec<Option<BigDecimalFromSql>>> =
+                        row.try_get(i).context(FailedToGetRowValueSnafu {
+                            pg_type: Type::NUMERIC_ARRAY,
+                        })?;
+                    let Some(v) = v else {
+                        builder.append_null();
+                        continue;
+                    };
+
+                    let dest_scale = postgres_numeric_scale.unwrap_or_default();
+                    for item in v {
+                        match item {
+                            Some(item) => {
+                                let Some(v_i128) = item.to_decimal_128_with_scale(dest_scale)
+                                else {
+                                    return FailedToConvertBigDecimalToI128Snafu {
+                                        big_decimal: item.inner,
+                                    }
+                                    .fail();
+                                };
+                                builder.values().append_value(v_i128);
+                            }
+                            None => builder.values().append_null(),
+                        }
+                    }
+                    builder.append(true);
+                }
                 Type::BOOL_ARRAY => handle_primitive_array_type!(
                     Type::BOOL_ARRAY,
                     builder,
@@ -830,7 +986,7 @@ pub fn rows_to_arrow(rows: &[Row], projected_schema: &Option<SchemaRef>) -> Resu
                         };
                         let Some(builder) = builder
                             .as_any_mut()
-                            .downcast_mut::<StringDictionaryBuilder<Int8Type>>()
+                            .downcast_mut::<StringDictionaryBuilder<Int32Type>>()
                         else {
                             return FailedToDowncastBuilderSnafu {
                                 postgres_type: format!("{postgres_type}"),
@@ -936,11 +1092,9 @@ fn map_column_type_to_data_type(column_type: &Type, field_name: &str) -> Result<
             DataType::Float64,
             true,
         ))))),
-        Type::TEXT_ARRAY => Ok(Some(DataType::List(Arc::new(Field::new(
-            "item",
-            DataType::Utf8,
-            true,
-        ))))),
+        Type::TEXT_ARRAY | Type::VARCHAR_ARRAY | Type::BPCHAR_ARRAY => Ok(Some(DataType::List(
+            Arc::new(Field::new("item", DataType::Utf8, true)),
+        ))),
         Type::BOOL_ARRAY => Ok(Some(DataType::List(Arc::new(Field::new(
             "item",
             DataType::Boolean,
@@ -977,7 +1131,7 @@ fn map_column_type_to_data_type(column_type: &Type, field_name: &str) -> Result<
                 Ok(Some(DataType::Struct(arrow_fields.into())))
             }
             Kind::Enum(_) => Ok(Some(DataType::Dictionary(
-                Box::new(DataType::Int8),
+                Box::new(DataType::Int32),
                 Box::new(DataType::Utf8),
             ))),
             _ => UnsupportedDataTypeSnafu {
@@ -1215,6 +1369,20 @@ fn get_decimal_column_precision_and_scale(
     }
 }
 
+fn get_decimal_list_precision_and_scale(
+    column_name: &str,
+    projected_schema: &SchemaRef,
+) -> Option<(u8, i8)> {
+    let field = projected_schema.field_with_name(column_name).ok()?;
+    match field.data_type() {
+        DataType::List(item) => match item.data_type() {
+            DataType::Decimal128(precision, scale) => Some((*precision, *scale)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,7 +1,7 @@
 use datafusion::arrow::datatypes::Fields;
 use sea_query::{Alias, ColumnDef, PostgresQueryBuilder, TableBuilder};
 
-use crate::sql::arrow_sql_gen::statement::map_data_type_to_column_type;
+use crate::sql::arrow_sql_gen::{enum_type::EnumType, statement::map_data_type_to_column_type};
 
 pub struct TypeBuilder {
     name: String,
@@ -65,6 +65,56 @@ impl TypeBuilder {
     }
 }
 
+/// Creates the enum type of a dictionary column, unless a type of its name exists.
+pub struct EnumTypeBuilder {
+    enum_type: EnumType,
+}
+
+impl EnumTypeBuilder {
+    #[must_use]
+    pub fn new(enum_type: EnumType) -> Self {
+        Self { enum_type }
+    }
+
+    #[must_use]
+    pub fn build(self) -> String {
+        let labels = self
+            .enum_type
+            .values
+            .iter()
+            .map(|label| quote_literal(label))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // like the composite types, in a DO block as there's no CREATE TYPE IF NOT EXISTS
+        format!(
+            "
+        DO $$ 
+        BEGIN
+            IF NOT EXISTS (
+                SELECT 1
+                FROM pg_type t
+                WHERE t.typname = {}
+            ) THEN
+                CREATE TYPE {} AS ENUM ({labels});
+            END IF;
+        END $$;
+        ",
+            quote_literal(&self.enum_type.name),
+            quote_identifier(&self.enum_type.name),
+        )
+    }
+}
+
+/// `name` as a quoted identifier, which keeps its case.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
 /// Convert a `Fields` struct into a vector of `ColumnDef` without any constraints or other column specs.
 fn fields_to_simple_column_defs(fields: &Fields) -> Vec<ColumnDef> {
     let mut column_defs = Vec::new();
@@ -111,4 +161,33 @@ mod tests {
         "#
         );
     }
+
+    #[test]
+    fn test_enum_type_builder() {
+        let enum_type = EnumType::new(
+            "Mood",
+            vec![
+                "happy".to_string(),
+                "it's ok".to_string(),
+                "sad".to_string(),
+            ],
+        );
+        let sql = EnumTypeBuilder::new(enum_type).build();
+
+        assert_eq!(
+            sql,
+            r#"
+        DO $$ 
+        BEGIN
+            IF NOT EXISTS (
+                SELECT 1
+                FROM pg_type t
+                WHERE t.typname = 'Mood'
+            ) THEN
+                CREATE TYPE "Mood" AS ENUM ('happy', 'it''s ok', 'sad');
+            END IF;
+        END $$;
+        "#
+        );
+    }
 }
@@ -4,12 +4,14 @@ use serde_json::json;
 use serde_json::Value;
 use std::sync::Arc;
 
+use crate::sql::arrow_sql_gen::uuid_format::UuidFormat;
 use crate::UnsupportedTypeAction;
 
 #[derive(Debug, Clone)]
 pub(crate) struct ParseContext {
     pub(crate) unsupported_type_action: UnsupportedTypeAction,
     pub(crate) type_details: Option<serde_json::Value>,
+    pub(crate) uuid_format: UuidFormat,
 }
 
 impl ParseContext {
@@ -17,6 +19,7 @@ impl ParseContext {
         Self {
             unsupported_type_action: UnsupportedTypeAction::Error,
             type_details: None,
+            uuid_format: UuidFormat::default(),
         }
     }
 
@@ -32,6 +35,11 @@ impl ParseContext {
         self.type_details = Some(type_details);
         self
     }
+
+    pub(crate) fn with_uuid_format(mut self, uuid_format: UuidFormat) -> Self {
+        self.uuid_format = uuid_format;
+        self
+    }
 }
 
 impl Default for ParseContext {
@@ -58,8 +66,10 @@ pub(crate) fn pg_data_type_to_arrow_type(
         "real" | "float4" => Ok(DataType::Float32),
         "double precision" | "float8" => Ok(DataType::Float64),
         "\"char\"" => Ok(DataType::Int8),
-        "character" | "char" | "character varying" | "varchar" | "text" | "bpchar" | "uuid"
-        | "name" => Ok(DataType::Utf8),
+        "character" | "char" | "character varying" | "varchar" | "text" | "bpchar" | "name" => {
+            Ok(DataType::Utf8)
+        }
+        "uuid" => Ok(context.uuid_format.data_type()),
         "bytea" => Ok(DataType::Binary),
         "date" => Ok(DataType::Date32),
         "time" | "time without time zone" => Ok(DataType::Time64(TimeUnit::Nanosecond)),
@@ -73,7 +83,7 @@ pub(crate) fn pg_data_type_to_arrow_type(
         "interval" => Ok(DataType::Interval(IntervalUnit::MonthDayNano)),
         "boolean" => Ok(DataType::Boolean),
         "enum" => Ok(DataType::Dictionary(
-            Box::new(DataType::Int8),
+            Box::new(DataType::Int32),
             Box::new(DataType::Utf8),
         )),
         "point" => Ok(DataType::FixedSizeList(
@@ -474,7 +484,7 @@ mod tests {
         }));
         assert_eq!(
             pg_data_type_to_arrow_type("enum", &enum_type_details).expect("Failed to convert enum"),
-            DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Utf8))
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
         );
 
         // Test geometric types
@@ -525,6 +535,13 @@ mod tests {
             pg_data_type_to_arrow_type("uuid", &context).expect("Failed to convert uuid"),
             DataType::Utf8
         );
+        let uuid_context = context
+            .clone()
+            .with_uuid_format(UuidFormat::FixedSizeBinary);
+        assert_eq!(
+            pg_data_type_to_arrow_type("uuid", &uuid_context).expect("Failed to convert uuid"),
+            DataType::FixedSizeBinary(16)
+        );
 
         // Test text search types
         assert_eq!(
@@ -1,3 +1,6 @@
+use crate::sql::arrow_sql_gen::uuid_format::{format_uuid, is_uuid_field};
+use crate::sql::column_expressions::ColumnExpressions;
+use crate::util::normalize::SupportedEncodings;
 use bigdecimal::BigDecimal;
 use chrono::{DateTime, Offset, TimeZone};
 use datafusion::arrow::{
@@ -37,6 +40,8 @@ pub struct CreateTableBuilder {
     schema: SchemaRef,
     table_name: String,
     primary_keys: Vec<String>,
+    column_expressions: ColumnExpressions,
+    temporary: bool,
 }
 
 impl CreateTableBuilder {
@@ -46,9 +51,18 @@ impl CreateTableBuilder {
             schema,
             table_name: table_name.to_string(),
             primary_keys: Vec::new(),
+            column_expressions: ColumnExpressions::default(),
+            temporary: false,
         }
     }
 
+    /// Creates a `TEMPORARY` table, which is only visible to the connection that creates it.
+    #[must_use]
+    pub fn temporary(mut self) -> Self {
+        self.temporary = true;
+        self
+    }
+
     #[must_use]
     pub fn primary_keys<T>(mut self, keys: Vec<T>) -> Self
     where
@@ -58,17 +72,36 @@ impl CreateTableBuilder {
         self
     }
 
+    /// Adds a `DEFAULT` clause to the columns with a default, and creates the generated columns as
+    /// `GENERATED ALWAYS AS (...) STORED`, which PostgreSQL, MySQL and SQLite all support.
+    #[must_use]
+    pub fn column_expressions(mut self, column_expressions: ColumnExpressions) -> Self {
+        self.column_expressions = column_expressions;
+        self
+    }
+
     #[must_use]
     #[cfg(feature = "postgres")]
     pub fn build_postgres(self) -> Vec<String> {
-        use crate::sql::arrow_sql_gen::postgres::{
-            builder::TypeBuilder, get_postgres_composite_type_name,
-            map_data_type_to_column_type_postgres,
+        use crate::sql::arrow_sql_gen::{
+            enum_type::EnumType,
+            postgres::{
+                builder::{quote_identifier, EnumTypeBuilder, TypeBuilder},
+                get_postgres_composite_type_name, map_data_type_to_column_type_postgres,
+            },
         };
         let schema = Arc::clone(&self.schema);
         let table_name = self.table_name.clone();
         let main_table_creation =
             self.build(PostgresQueryBuilder, &|f: &Arc<Field>| -> ColumnType {
+                if is_uuid_field(f) {
+                    return ColumnType::Uuid;
+                }
+                if let Some(enum_type) = EnumType::of_field(f) {
+                    return ColumnType::Custom(SeaRc::new(Alias::new(quote_identifier(
+                        &enum_type.name,
+                    ))));
+                }
                 map_data_type_to_column_type_postgres(f.data_type(), &table_name, f.name())
             });
 
@@ -76,6 +109,12 @@ impl CreateTableBuilder {
         // https://www.postgresql.org/docs/current/rowtypes.html
         let mut creation_stmts = Vec::new();
         for field in schema.fields() {
+            // so do the enums of the dictionary columns read from Postgres enums. Other
+            // dictionaries are created as their value type
+            if let Some(enum_type) = EnumType::of_field(field) {
+                creation_stmts.push(EnumTypeBuilder::new(enum_type).build());
+                continue;
+            }
             let DataType::Struct(struct_inner_fields) = field.data_type() else {
                 continue;
             };
@@ -125,6 +164,9 @@ impl CreateTableBuilder {
         create_stmt
             .table(Alias::new(self.table_name.clone()))
             .if_not_exists();
+        if self.temporary {
+            create_stmt.temporary();
+        }
 
         for field in self.schema.fields() {
             let column_type = map_data_type_to_column_type_fn(field);
@@ -132,6 +174,13 @@ impl CreateTableBuilder {
             if !field.is_nullable() {
                 column_def.not_null();
             }
+            // the parentheses are required around expressions by MySQL and SQLite
+            if let Some(default) = self.column_expressions.default_sql(field.name()) {
+                column_def.default(Expr::cust(format!("({default})")));
+            }
+            if let Some(expression) = self.column_expressions.generated_sql(field.name()) {
+                column_def.extra(format!("GENERATED ALWAYS AS ({expression}) STORED"));
+            }
 
             create_stmt.col(&mut column_def);
         }
@@ -205,6 +254,11 @@ pub fn use_json_insert_for_type<T: QueryBuilder + 'static>(
     false
 }
 
+/// Whether the statements are built for Postgres, which has a UUID type.
+fn is_postgres_query_builder<T: QueryBuilder + 'static>(query_builder: &T) -> bool {
+    (query_builder as &dyn std::any::Any).is::<PostgresQueryBuilder>()
+}
+
 impl InsertBuilder {
     #[must_use]
     pub fn new(table: &TableReference, record_batches: Vec<RecordBatch>) -> Self {
@@ -226,6 +280,12 @@ impl InsertBuilder {
         record_batch: &RecordBatch,
         query_builder: &T,
     ) -> Result<()> {
+        // run-end encoded and dictionary columns are decoded to their values first
+        let record_batch = &SupportedEncodings::insert_statement()
+            .normalize_batch(record_batch.clone())
+            .map_err(|e| Error::FailedToCreateInsertStatement {
+                source: Box::new(e),
+            })?;
         for row in 0..record_batch.num_rows() {
             let mut row_values: Vec<SimpleExpr> = vec![];
             for col in 0..record_batch.num_columns() {
@@ -247,6 +307,25 @@ impl InsertBuilder {
                     DataType::LargeUtf8 => push_value!(row_values, column, row, LargeStringArray),
                     DataType::Utf8View => push_value!(row_values, column, row, StringViewArray),
                     DataType::Boolean => push_value!(row_values, column, row, BooleanArray),
+                    // dictionary encoded strings, like the ones of Postgres enums, are inserted as
+                    // their values
+                    DataType::Dictionary(_, value_type)
+                        if matches!(
+                            **value_type,
+                            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+                        ) =>
+                    {
+                        if column.is_null(row) {
+                            row_values.push(Keyword::Null.into());
+                            continue;
+                        }
+                        let value = array_value_to_string(column, row).map_err(|e| {
+                            Error::FailedToCreateInsertStatement {
+                                source: Box::new(e),
+                            }
+                        })?;
+                        row_values.push(value.into());
+                    }
                     DataType::Decimal128(_, scale) => {
                         let array = column.as_any().downcast_ref::<array::Decimal128Array>();
                         if let Some(valid_array) = array {
@@ -704,6 +783,14 @@ impl InsertBuilder {
                                 continue;
                             }
 
+                            if is_uuid_field(record_batch.schema_ref().field(col))
+                                && is_postgres_query_builder(query_builder)
+                            {
+                                let uuid: SimpleExpr = format_uuid(valid_array.value(row)).into();
+                                row_values.push(uuid.cast_as(Alias::new("uuid")));
+                                continue;
+                            }
+
                             row_values.push(valid_array.value(row).into());
                         }
                     }
@@ -1271,6 +1358,21 @@ fn insert_list_into_row_values(
             bool,
             "boolean[]"
         ),
+        DataType::Decimal128(_, scale) => {
+            let mut list_values: Vec<BigDecimal> = Vec::new();
+            if let Some(valid_array) = list_array.as_any().downcast_ref::<array::Decimal128Array>()
+            {
+                for i in 0..valid_array.len() {
+                    list_values.push(BigDecimal::new(
+                        valid_array.value(i).into(),
+                        i64::from(*scale),
+                    ));
+                }
+            }
+            let expr: SimpleExpr = list_values.into();
+            // We must cast here in case the array is empty which SeaQuery does not handle.
+            row_values.push(expr.cast_as(Alias::new("numeric[]")));
+        }
         DataType::Binary => {
             let mut list_values: Vec<Vec<u8>> = Vec::new();
             for i in 0..list_array.len() {
@@ -1283,6 +1385,17 @@ fn insert_list_into_row_values(
             // We must cast here in case the array is empty which SeaQuery does not handle.
             row_values.push(expr.cast_as(Alias::new("bytea[]")));
         }
+        DataType::BinaryView => {
+            let mut list_values: Vec<Vec<u8>> = Vec::new();
+            for i in 0..list_array.len() {
+                let view_array = list_array.as_any().downcast_ref::<array::BinaryViewArray>();
+                if let Some(valid_view_array) = view_array {
+                    list_values.push(valid_view_array.value(i).to_vec());
+                }
+            }
+            let expr: SimpleExpr = list_values.into();
+            row_values.push(expr.cast_as(Alias::new("bytea[]")));
+        }
         _ => unimplemented!(
             "Data type mapping not implemented for {}",
             list_type.data_type()
@@ -1326,9 +1439,12 @@ pub(crate) fn map_data_type_to_column_type(data_type: &DataType) -> ColumnType {
         // This caused the error: "Row size too large. The maximum row size for the used table type, not counting BLOBs, is 65535.
         // This includes storage overhead, check the manual. You have to change some columns to TEXT or BLOBs."
         // Changing to Blob fixes this issue. This change does not affect Postgres, and for Sqlite, the mapping type changes from varbinary_blob to blob.
-        DataType::Binary | DataType::LargeBinary => ColumnType::Blob,
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => ColumnType::Blob,
         DataType::FixedSizeBinary(num_bytes) => ColumnType::Binary(num_bytes.to_owned() as u32),
         DataType::Interval(_) => ColumnType::Interval(None, None),
+        // Postgres enums are created from the enum type of their fields by `build_postgres`
+        DataType::Dictionary(_, value_type) => map_data_type_to_column_type(value_type),
+        DataType::RunEndEncoded(_, values) => map_data_type_to_column_type(values.data_type()),
         // Add more mappings here as needed
         _ => unimplemented!("Data type mapping not implemented for {:?}", data_type),
     }
@@ -1433,6 +1549,9 @@ mod tests {
     use std::sync::Arc;
 
     use super::*;
+    use datafusion::arrow::array::{
+        Decimal128Builder, LargeStringDictionaryBuilder, ListBuilder, StringDictionaryBuilder,
+    };
     use datafusion::arrow::datatypes::{DataType, Field, Int32Type, Schema};
 
     #[test]
@@ -1447,6 +1566,28 @@ mod tests {
         assert_eq!(sql, "CREATE TABLE IF NOT EXISTS \"users\" ( \"id\" integer NOT NULL, \"name\" text NOT NULL, \"age\" integer )");
     }
 
+    #[test]
+    fn test_table_creation_with_column_expressions() {
+        let schema = Schema::new(vec![
+            Field::new("price", DataType::Int64, false),
+            Field::new("quantity", DataType::Int64, false),
+            Field::new("total", DataType::Int64, true),
+        ]);
+        let column_expressions = ColumnExpressions::new()
+            .with_default(
+                "quantity",
+                datafusion::prelude::lit(1_i64),
+                &datafusion::sql::unparser::dialect::SqliteDialect {},
+            )
+            .expect("default unparsed")
+            .with_generated_column("total", "price * quantity");
+        let sql = CreateTableBuilder::new(SchemaRef::new(schema), "orders")
+            .column_expressions(column_expressions)
+            .build_sqlite();
+
+        assert_eq!(sql, "CREATE TABLE IF NOT EXISTS \"orders\" ( \"price\" bigint NOT NULL, \"quantity\" bigint NOT NULL DEFAULT (1), \"total\" bigint GENERATED ALWAYS AS (price * quantity) STORED )");
+    }
+
     #[test]
     fn test_table_insertion() {
         let schema1 = Schema::new(vec![
@@ -1575,6 +1716,163 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_table_insertion_with_decimal_list() {
+        let mut builder = ListBuilder::new(
+            Decimal128Builder::new()
+                .with_precision_and_scale(10, 2)
+                .expect("valid precision and scale"),
+        );
+        builder.values().append_value(150);
+        builder.values().append_value(225);
+        builder.append(true);
+        builder.append(true);
+        let list_array = builder.finish();
+
+        let schema1 = Schema::new(vec![Field::new(
+            "list",
+            list_array.data_type().clone(),
+            true,
+        )]);
+        let batch = RecordBatch::try_new(Arc::new(schema1), vec![Arc::new(list_array)])
+            .expect("Unable to build record batch");
+
+        let sql = InsertBuilder::new(&TableReference::from("arrays"), vec![batch])
+            .build_postgres(None)
+            .expect("Failed to build insert statement");
+        assert_eq!(
+            sql,
+            "INSERT INTO \"arrays\" (\"list\") VALUES (CAST(ARRAY [1.50,2.25] AS numeric[])), (CAST(ARRAY [] AS numeric[]))"
+        );
+    }
+
+    #[test]
+    fn test_table_insertion_with_dictionary() {
+        let mut builder = StringDictionaryBuilder::<Int32Type>::new();
+        builder.append_value("happy");
+        builder.append_null();
+        builder.append_value("sad");
+        let dictionary_array = builder.finish();
+
+        let schema1 = Schema::new(vec![Field::new(
+            "mood",
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+            true,
+        )]);
+        let batch = RecordBatch::try_new(Arc::new(schema1), vec![Arc::new(dictionary_array)])
+            .expect("Unable to build record batch");
+
+        let sql = InsertBuilder::new(&TableReference::from("moods"), vec![batch])
+            .build_postgres(None)
+            .expect("Failed to build insert statement");
+        assert_eq!(
+            sql,
+            "INSERT INTO \"moods\" (\"mood\") VALUES ('happy'), (NULL), ('sad')"
+        );
+    }
+
+    #[test]
+    fn test_table_insertion_with_large_utf8_dictionary() {
+        let mut builder = LargeStringDictionaryBuilder::<Int32Type>::new();
+        builder.append_value("happy");
+        builder.append_null();
+        let dictionary_array = builder.finish();
+
+        let schema1 = Schema::new(vec![Field::new(
+            "mood",
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::LargeUtf8)),
+            true,
+        )]);
+        let batch = RecordBatch::try_new(Arc::new(schema1), vec![Arc::new(dictionary_array)])
+            .expect("Unable to build record batch");
+
+        let sql = InsertBuilder::new(&TableReference::from("moods"), vec![batch])
+            .build_postgres(None)
+            .expect("Failed to build insert statement");
+        assert_eq!(
+            sql,
+            "INSERT INTO \"moods\" (\"mood\") VALUES ('happy'), (NULL)"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "postgres")]
+    fn test_postgres_table_creation_with_enum() {
+        use crate::sql::arrow_sql_gen::enum_type::{enum_field, EnumType};
+
+        let enum_type = EnumType::new("mood", vec!["sad".to_string(), "happy".to_string()]);
+        let schema = Schema::new(vec![
+            enum_field("mood", &enum_type, true),
+            Field::new(
+                "label",
+                DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Utf8)),
+                false,
+            ),
+        ]);
+        let sql = CreateTableBuilder::new(SchemaRef::new(schema), "moods").build_postgres();
+
+        assert_eq!(sql.len(), 2);
+        assert!(sql[0].contains("CREATE TYPE \"mood\" AS ENUM ('sad', 'happy');"));
+        assert_eq!(
+            sql[1],
+            "CREATE TABLE IF NOT EXISTS \"moods\" ( \"mood\" \"mood\", \"label\" text NOT NULL )"
+        );
+    }
+
+    #[test]
+    fn test_table_insertion_with_run_end_encoded() {
+        let run_ends = array::Int32Array::from(vec![2, 3]);
+        let values = array::StringArray::from(vec!["open", "closed"]);
+        let status_array = array::RunArray::<Int32Type>::try_new(&run_ends, &values)
+            .expect("Unable to build run array");
+
+        let schema1 = Schema::new(vec![Field::new(
+            "status",
+            status_array.data_type().clone(),
+            false,
+        )]);
+        let batch = RecordBatch::try_new(Arc::new(schema1), vec![Arc::new(status_array)])
+            .expect("Unable to build record batch");
+
+        let sql = InsertBuilder::new(&TableReference::from("tickets"), vec![batch])
+            .build_sqlite(None)
+            .expect("Failed to build insert statement");
+        assert_eq!(
+            sql,
+            "INSERT INTO \"tickets\" (\"status\") VALUES ('open'), ('open'), ('closed')"
+        );
+    }
+
+    #[test]
+    fn test_table_insertion_with_uuid() {
+        use crate::sql::arrow_sql_gen::uuid_format::{uuid_field, UuidFormat};
+
+        let uuid = [
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ];
+        let uuid_array = array::FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+            [Some(uuid), None].into_iter(),
+            16,
+        )
+        .expect("Unable to build uuid array");
+        let schema = Schema::new(vec![uuid_field(
+            "id",
+            UuidFormat::FixedSizeBinary.data_type(),
+            true,
+        )]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(uuid_array)])
+            .expect("Unable to build record batch");
+
+        let sql = InsertBuilder::new(&TableReference::from("users"), vec![batch])
+            .build_postgres(None)
+            .expect("Failed to build insert statement");
+        assert_eq!(
+            sql,
+            "INSERT INTO \"users\" (\"id\") VALUES (CAST('67e55044-10b1-426f-9247-bb680e5fe0c8' AS uuid)), (NULL)"
+        );
+    }
+
     #[test]
     fn test_create_index() {
         let sql = IndexBuilder::new("users", vec!["id", "name"]).build_postgres();
@@ -0,0 +1,211 @@
+//! The time zone of the timestamp columns read from the databases.
+//!
+//! The connections read timestamps in a session whose time zone is UTC, so the values are the same
+//! UTC instants whichever time zone the columns are surfaced with.
+
+use std::sync::Arc;
+
+use datafusion::arrow::{
+    array::{ArrayRef, AsArray, RecordBatch},
+    datatypes::{
+        DataType, Field, Schema, SchemaRef, TimeUnit, TimestampMicrosecondType,
+        TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType,
+    },
+    error::ArrowError,
+};
+
+const UTC: &str = "UTC";
+
+/// How the timestamp columns of a provider are surfaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampPolicy {
+    /// Keep the time zone of the column type: `UTC` for types with a time zone like `timestamptz`,
+    /// none for the others.
+    #[default]
+    Source,
+    /// `Timestamp(_, None)` for all the columns, holding the UTC time of the values.
+    Naive,
+    /// `Timestamp(_, Some("UTC"))` for all the columns.
+    Utc,
+}
+
+impl TimestampPolicy {
+    /// Parses the `timestamp_policy` parameter of a pool: `source`, `naive` or `utc`.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "source" => Some(Self::Source),
+            "naive" => Some(Self::Naive),
+            "utc" => Some(Self::Utc),
+            _ => None,
+        }
+    }
+
+    /// The type a column of `data_type` is surfaced as.
+    #[must_use]
+    pub fn apply_to_data_type(self, data_type: &DataType) -> DataType {
+        match (self, data_type) {
+            (Self::Naive, DataType::Timestamp(unit, Some(_))) => DataType::Timestamp(*unit, None),
+            (Self::Utc, DataType::Timestamp(unit, time_zone))
+                if time_zone.as_deref() != Some(UTC) =>
+            {
+                DataType::Timestamp(*unit, Some(Arc::from(UTC)))
+            }
+            _ => data_type.clone(),
+        }
+    }
+
+    /// `schema` with the timestamp columns surfaced as the policy says.
+    #[must_use]
+    pub fn apply_to_schema(self, schema: SchemaRef) -> SchemaRef {
+        if self == Self::Source || !schema.fields().iter().any(|f| self.changes(f.data_type())) {
+            return schema;
+        }
+
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                Arc::new(
+                    Field::clone(field).with_data_type(self.apply_to_data_type(field.data_type())),
+                )
+            })
+            .collect::<Vec<_>>();
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    }
+
+    /// `batch` with the timestamp columns surfaced as the policy says.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the batch can't be rebuilt with the new columns.
+    pub fn apply_to_batch(self, batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+        let schema = batch.schema();
+        if self == Self::Source || !schema.fields().iter().any(|f| self.changes(f.data_type())) {
+            return Ok(batch);
+        }
+
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|column| self.apply_to_array(column))
+            .collect();
+        RecordBatch::try_new(self.apply_to_schema(schema), columns)
+    }
+
+    fn apply_to_array(self, array: &ArrayRef) -> ArrayRef {
+        let DataType::Timestamp(unit, time_zone) = self.apply_to_data_type(array.data_type())
+        else {
+            return Arc::clone(array);
+        };
+        // the values are UTC instants, so only the time zone of the array changes
+        match unit {
+            TimeUnit::Second => Arc::new(
+                array
+                    .as_primitive::<TimestampSecondType>()
+                    .clone()
+                    .with_timezone_opt(time_zone),
+            ),
+            TimeUnit::Millisecond => Arc::new(
+                array
+                    .as_primitive::<TimestampMillisecondType>()
+                    .clone()
+                    .with_timezone_opt(time_zone),
+            ),
+            TimeUnit::Microsecond => Arc::new(
+                array
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .clone()
+                    .with_timezone_opt(time_zone),
+            ),
+            TimeUnit::Nanosecond => Arc::new(
+                array
+                    .as_primitive::<TimestampNanosecondType>()
+                    .clone()
+                    .with_timezone_opt(time_zone),
+            ),
+        }
+    }
+
+    fn changes(self, data_type: &DataType) -> bool {
+        &self.apply_to_data_type(data_type) != data_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{Int32Array, TimestampNanosecondArray};
+
+    fn batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new(
+                "created_at",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some(Arc::from(UTC))),
+                true,
+            ),
+            Field::new(
+                "updated_at",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                true,
+            ),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(TimestampNanosecondArray::from(vec![1_000]).with_timezone(UTC)),
+                Arc::new(TimestampNanosecondArray::from(vec![2_000])),
+            ],
+        )
+        .expect("valid batch")
+    }
+
+    #[test]
+    fn test_source_policy_keeps_batch() {
+        let batch = batch();
+        assert_eq!(
+            TimestampPolicy::Source
+                .apply_to_batch(batch.clone())
+                .expect("policy applies"),
+            batch
+        );
+    }
+
+    #[test]
+    fn test_naive_and_utc_policies() {
+        for (policy, time_zone) in [
+            (TimestampPolicy::Naive, None),
+            (TimestampPolicy::Utc, Some(Arc::from(UTC))),
+        ] {
+            let batch = policy.apply_to_batch(batch()).expect("policy applies");
+            let expected = DataType::Timestamp(TimeUnit::Nanosecond, time_zone);
+            assert_eq!(batch.schema().field(0).data_type(), &DataType::Int32);
+            assert_eq!(batch.schema().field(1).data_type(), &expected);
+            assert_eq!(batch.schema().field(2).data_type(), &expected);
+            assert_eq!(batch.column(1).data_type(), &expected);
+            assert_eq!(
+                batch
+                    .column(2)
+                    .as_primitive::<TimestampNanosecondType>()
+                    .value(0),
+                2_000
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp_policy() {
+        assert_eq!(
+            TimestampPolicy::parse("source"),
+            Some(TimestampPolicy::Source)
+        );
+        assert_eq!(
+            TimestampPolicy::parse("naive"),
+            Some(TimestampPolicy::Naive)
+        );
+        assert_eq!(TimestampPolicy::parse("utc"), Some(TimestampPolicy::Utc));
+        assert_eq!(TimestampPolicy::parse("local"), None);
+    }
+}
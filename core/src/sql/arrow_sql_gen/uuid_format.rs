@@ -0,0 +1,112 @@
+//! The Arrow type of the UUID columns read from the databases.
+//!
+//! UUIDs are read as strings by default. As `FixedSizeBinary(16)`, the fields carry the canonical
+//! `arrow.uuid` extension type, which tells the writes to insert the values as UUIDs rather than
+//! as binary.
+
+use std::collections::HashMap;
+
+use datafusion::arrow::datatypes::{DataType, Field};
+
+/// The metadata key of the extension type of a field.
+pub const EXTENSION_TYPE_NAME_KEY: &str = "ARROW:extension:name";
+
+/// The name of the canonical extension type of UUIDs.
+pub const UUID_EXTENSION_NAME: &str = "arrow.uuid";
+
+/// How UUID columns are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UuidFormat {
+    /// As `Utf8`, in the hyphenated form like `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    #[default]
+    Utf8,
+    /// As `FixedSizeBinary(16)` with the `arrow.uuid` extension type.
+    FixedSizeBinary,
+}
+
+impl UuidFormat {
+    /// Parses the `uuid_format` parameter of a pool: `utf8` or `fixed_size_binary`.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "utf8" => Some(Self::Utf8),
+            "fixed_size_binary" => Some(Self::FixedSizeBinary),
+            _ => None,
+        }
+    }
+
+    /// The Arrow type of UUID columns.
+    #[must_use]
+    pub fn data_type(self) -> DataType {
+        match self {
+            Self::Utf8 => DataType::Utf8,
+            Self::FixedSizeBinary => DataType::FixedSizeBinary(16),
+        }
+    }
+}
+
+/// A UUID field named `name`, which has the `arrow.uuid` extension type as `FixedSizeBinary(16)`.
+#[must_use]
+pub fn uuid_field(name: impl Into<String>, data_type: DataType, nullable: bool) -> Field {
+    let field = Field::new(name, data_type, nullable);
+    if field.data_type() != &DataType::FixedSizeBinary(16) {
+        return field;
+    }
+    field.with_metadata(HashMap::from([(
+        EXTENSION_TYPE_NAME_KEY.to_string(),
+        UUID_EXTENSION_NAME.to_string(),
+    )]))
+}
+
+/// Whether `field` holds UUIDs as `FixedSizeBinary(16)`.
+#[must_use]
+pub fn is_uuid_field(field: &Field) -> bool {
+    field.data_type() == &DataType::FixedSizeBinary(16)
+        && field
+            .metadata()
+            .get(EXTENSION_TYPE_NAME_KEY)
+            .is_some_and(|name| name == UUID_EXTENSION_NAME)
+}
+
+/// The hyphenated form of the UUID of `bytes`.
+#[must_use]
+pub fn format_uuid(bytes: &[u8]) -> String {
+    let mut uuid = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        uuid.push_str(&format!("{byte:02x}"));
+    }
+    uuid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uuid_field() {
+        let field = uuid_field("id", UuidFormat::FixedSizeBinary.data_type(), false);
+        assert!(is_uuid_field(&field));
+
+        let field = uuid_field("id", UuidFormat::Utf8.data_type(), false);
+        assert!(!is_uuid_field(&field));
+        assert!(field.metadata().is_empty());
+
+        assert!(!is_uuid_field(&Field::new(
+            "hash",
+            DataType::FixedSizeBinary(16),
+            false
+        )));
+    }
+
+    #[test]
+    fn test_format_uuid() {
+        let bytes = [
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ];
+        assert_eq!(format_uuid(&bytes), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    }
+}
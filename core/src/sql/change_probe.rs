@@ -0,0 +1,252 @@
+//! Skipping the full refresh of a copy of a remote table, like a table accelerated from it, when
+//! a cheap probe of the table shows that it hasn't changed since the last refresh.
+//!
+//! ```rust,ignore
+//! let detector = ChangeDetector::new(ChangeProbe::CountAndMax("updated_at".to_string()));
+//! if let Some(fingerprint) = detector.changed(&orders).await? {
+//!     refresh(&orders).await?;
+//!     detector.record(fingerprint);
+//! }
+//! ```
+//!
+//! The probe only sees the changes that change its result: a row count misses the updates, and
+//! the maximum of a timestamp column misses the deletes and the updates that don't set it.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use datafusion::{
+    arrow::{
+        array::{Array, RecordBatch},
+        error::ArrowError,
+        util::display::array_value_to_string,
+    },
+    common::Column,
+    error::DataFusionError,
+    functions_aggregate::expr_fn::{count, max},
+    logical_expr::{lit, Expr},
+};
+use futures::TryStreamExt;
+use snafu::prelude::*;
+
+use crate::sql::{
+    db_connection_pool::{
+        self,
+        dbconnection::{self, query_arrow},
+    },
+    sql_provider_datafusion::SqlTable,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to generate the SQL of the change probe.\n{source}"))]
+    UnableToGenerateProbe { source: DataFusionError },
+
+    #[snafu(display("Unable to get a connection to run the change probe.\n{source}"))]
+    UnableToConnect { source: db_connection_pool::Error },
+
+    #[snafu(display("Unable to run the change probe '{sql}'.\n{source}"))]
+    UnableToRunProbe {
+        sql: String,
+        source: dbconnection::Error,
+    },
+
+    #[snafu(display("Unable to read the result of the change probe '{sql}'.\n{source}"))]
+    UnableToReadProbe {
+        sql: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Unable to read the result of the change probe.\n{source}"))]
+    UnableToFormatProbe { source: ArrowError },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// How a [`ChangeDetector`] probes a table for changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeProbe {
+    /// The number of rows of the table.
+    RowCount,
+    /// The number of rows and the maximum of a column of the table, by its name in the schema of
+    /// the table, like an `updated_at` timestamp or an increasing ID.
+    CountAndMax(String),
+    /// A query of the remote database whose first row changes when the table does, like a
+    /// checksum of the table or a change counter kept by a trigger.
+    Query(String),
+}
+
+impl ChangeProbe {
+    /// The SQL of the probe of `table`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SQL can't be generated.
+    pub fn sql<T, P>(&self, table: &SqlTable<T, P>) -> Result<String> {
+        let row_count = || count(lit(1)).alias("row_count");
+        match self {
+            Self::RowCount => table
+                .aggregate_to_sql(vec![row_count()])
+                .context(UnableToGenerateProbeSnafu),
+            Self::CountAndMax(column) => table
+                .aggregate_to_sql(vec![
+                    row_count(),
+                    max(Expr::Column(Column::from_name(column))).alias("watermark"),
+                ])
+                .context(UnableToGenerateProbeSnafu),
+            Self::Query(query) => Ok(query.trim().trim_end_matches(';').to_string()),
+        }
+    }
+}
+
+/// The result of a probe, which is compared with the result of the next probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeFingerprint(Vec<String>);
+
+impl ProbeFingerprint {
+    /// The fingerprint of the first row of `batches`, empty if there's no row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value of the row can't be formatted.
+    pub fn from_batches(batches: &[RecordBatch]) -> Result<Self> {
+        let Some(batch) = batches.iter().find(|batch| batch.num_rows() > 0) else {
+            return Ok(Self(Vec::new()));
+        };
+        batch
+            .columns()
+            .iter()
+            .map(|column| {
+                if column.is_null(0) {
+                    Ok("NULL".to_string())
+                } else {
+                    array_value_to_string(column, 0).context(UnableToFormatProbeSnafu)
+                }
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    /// The values of the first row of the probe, formatted.
+    #[must_use]
+    pub fn values(&self) -> &[String] {
+        &self.0
+    }
+}
+
+/// Probes a table before its refreshes, and tells whether it changed since the last refresh. Each
+/// table has a detector of its own, with the probe that suits it.
+#[derive(Debug)]
+pub struct ChangeDetector {
+    probe: ChangeProbe,
+    last: Mutex<Option<ProbeFingerprint>>,
+}
+
+impl ChangeDetector {
+    #[must_use]
+    pub fn new(probe: ChangeProbe) -> Self {
+        Self {
+            probe,
+            last: Mutex::new(None),
+        }
+    }
+
+    #[must_use]
+    pub fn probe(&self) -> &ChangeProbe {
+        &self.probe
+    }
+
+    /// Probes `table`, and returns the fingerprint of the probe if it differs from the recorded
+    /// one, or `None` if the refresh can be skipped. The table is changed until the first
+    /// fingerprint is recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the probe fails, in which case the table should be refreshed.
+    pub async fn changed<T, P>(&self, table: &SqlTable<T, P>) -> Result<Option<ProbeFingerprint>> {
+        let sql = self.probe.sql(table)?;
+        let conn = table
+            .clone_pool()
+            .connect()
+            .await
+            .context(UnableToConnectSnafu)?;
+        let batches = query_arrow(conn, sql.clone(), None)
+            .await
+            .context(UnableToRunProbeSnafu { sql: sql.clone() })?
+            .try_collect::<Vec<_>>()
+            .await
+            .context(UnableToReadProbeSnafu { sql: sql.clone() })?;
+        let fingerprint = ProbeFingerprint::from_batches(&batches)?;
+
+        if self.lock().as_ref() == Some(&fingerprint) {
+            tracing::debug!("Skipping the refresh of {table}, the probe '{sql}' is unchanged");
+            return Ok(None);
+        }
+        Ok(Some(fingerprint))
+    }
+
+    /// Records `fingerprint`, from [`Self::changed`], once the refresh it triggered succeeded.
+    pub fn record(&self, fingerprint: ProbeFingerprint) {
+        *self.lock() = Some(fingerprint);
+    }
+
+    /// Forgets the recorded fingerprint, so that the next refresh isn't skipped.
+    pub fn reset(&self) {
+        *self.lock() = None;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<ProbeFingerprint>> {
+        self.last.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::{
+        array::{Int64Array, TimestampMicrosecondArray},
+        datatypes::{DataType, Field, Schema, TimeUnit},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_probe_fingerprint() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("row_count", DataType::Int64, false),
+            Field::new(
+                "watermark",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                true,
+            ),
+        ]));
+        let batch = |count: i64, watermark: Option<i64>| {
+            RecordBatch::try_new(
+                Arc::clone(&schema),
+                vec![
+                    Arc::new(Int64Array::from(vec![count])),
+                    Arc::new(TimestampMicrosecondArray::from(vec![watermark])),
+                ],
+            )
+        };
+
+        let fingerprint = ProbeFingerprint::from_batches(&[
+            RecordBatch::new_empty(Arc::clone(&schema)),
+            batch(2, Some(1_717_243_200_000_000))?,
+        ])?;
+        assert_eq!(fingerprint.values(), ["2", "2024-06-01T12:00:00"]);
+        assert_eq!(
+            ProbeFingerprint::from_batches(&[batch(0, None)?])?.values(),
+            ["0", "NULL"]
+        );
+        assert!(ProbeFingerprint::from_batches(&[])?.values().is_empty());
+
+        let detector = ChangeDetector::new(ChangeProbe::RowCount);
+        assert_eq!(detector.lock().as_ref(), None);
+        detector.record(fingerprint.clone());
+        assert_eq!(detector.lock().as_ref(), Some(&fingerprint));
+        detector.reset();
+        assert_eq!(detector.lock().as_ref(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use datafusion::{
+    arrow::{array::RecordBatch, datatypes::Schema, error::ArrowError},
+    error::DataFusionError,
+    logical_expr::Expr,
+    sql::unparser::{dialect::Dialect, Unparser},
+};
+use snafu::prelude::*;
+
+use crate::util::column_names::ColumnRenames;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to write the default of column '{column}' as SQL: {source}"))]
+    UnableToUnparseDefault {
+        column: String,
+        source: Box<DataFusionError>,
+    },
+
+    #[snafu(display("The {kind} column '{column}' is not a column of the table"))]
+    UnknownColumn { kind: &'static str, column: String },
+
+    #[snafu(display("Unable to remove the generated columns from the batch: {source}"))]
+    UnableToRemoveGeneratedColumns { source: ArrowError },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The table option prefix for generated columns, e.g. `'generated_column.total' 'price * quantity'`.
+pub const GENERATED_COLUMN_OPTION_PREFIX: &str = "generated_column.";
+
+#[derive(Debug, Clone, PartialEq)]
+struct ColumnDefault {
+    expr: Expr,
+    sql: String,
+}
+
+/// Column defaults and generated columns of a table that is created by a table writer.
+///
+/// Defaults are DataFusion expressions, so DataFusion can fill in columns that an `INSERT`
+/// leaves out, and are also written into the `CREATE TABLE` statement for other clients of the
+/// database. Generated columns are SQL expressions in the dialect of the database, which computes
+/// their values itself: they are created as stored columns where the database supports it, and
+/// are removed from the batches before they are written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnExpressions {
+    defaults: HashMap<String, ColumnDefault>,
+    generated: HashMap<String, String>,
+}
+
+impl ColumnExpressions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the `DEFAULT` clauses of a `CREATE EXTERNAL TABLE` statement and removes the
+    /// `generated_column.<column>` options from `options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a default can't be written in `dialect`.
+    pub fn try_from_table_options(
+        column_defaults: &HashMap<String, Expr>,
+        options: &mut HashMap<String, String>,
+        dialect: &dyn Dialect,
+    ) -> Result<Self> {
+        let mut column_expressions = Self::new();
+        for (column, expr) in column_defaults {
+            column_expressions =
+                column_expressions.with_default(column.clone(), expr.clone(), dialect)?;
+        }
+
+        let generated = options
+            .keys()
+            .filter(|key| key.starts_with(GENERATED_COLUMN_OPTION_PREFIX))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in generated {
+            if let Some(expression) = options.remove(&key) {
+                column_expressions = column_expressions.with_generated_column(
+                    &key[GENERATED_COLUMN_OPTION_PREFIX.len()..],
+                    expression,
+                );
+            }
+        }
+
+        Ok(column_expressions)
+    }
+
+    /// Sets the default of `column`, written in `dialect` for the `CREATE TABLE` statement.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `expr` can't be written in `dialect`.
+    pub fn with_default(
+        mut self,
+        column: impl Into<String>,
+        expr: Expr,
+        dialect: &dyn Dialect,
+    ) -> Result<Self> {
+        let column = column.into();
+        let sql = Unparser::new(dialect)
+            .expr_to_sql(&expr)
+            .map_err(Box::new)
+            .context(UnableToUnparseDefaultSnafu {
+                column: column.clone(),
+            })?
+            .to_string();
+        self.defaults.insert(column, ColumnDefault { expr, sql });
+        Ok(self)
+    }
+
+    /// Makes `column` a column computed by the database from `expression`.
+    #[must_use]
+    pub fn with_generated_column(
+        mut self,
+        column: impl Into<String>,
+        expression: impl Into<String>,
+    ) -> Self {
+        self.generated.insert(column.into(), expression.into());
+        self
+    }
+
+    /// The defaults and generated columns with the names of the columns in the table, for a table
+    /// created with [`ColumnRenames`].
+    #[must_use]
+    pub fn with_renamed_columns(self, renames: &ColumnRenames) -> Self {
+        Self {
+            defaults: self
+                .defaults
+                .into_iter()
+                .map(|(column, default)| (renames.renamed(&column).to_string(), default))
+                .collect(),
+            generated: self
+                .generated
+                .into_iter()
+                .map(|(column, expression)| (renames.renamed(&column).to_string(), expression))
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.defaults.is_empty() && self.generated.is_empty()
+    }
+
+    #[must_use]
+    pub fn has_generated_columns(&self) -> bool {
+        !self.generated.is_empty()
+    }
+
+    /// The default of `column` as a DataFusion expression.
+    #[must_use]
+    pub fn default_expr(&self, column: &str) -> Option<&Expr> {
+        self.defaults.get(column).map(|default| &default.expr)
+    }
+
+    /// The default of `column` as SQL in the dialect of the database.
+    #[must_use]
+    pub fn default_sql(&self, column: &str) -> Option<&str> {
+        self.defaults
+            .get(column)
+            .map(|default| default.sql.as_str())
+    }
+
+    /// The expression `column` is generated from.
+    #[must_use]
+    pub fn generated_sql(&self, column: &str) -> Option<&str> {
+        self.generated.get(column).map(String::as_str)
+    }
+
+    /// Checks that the defaults and generated columns refer to columns of `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first column that isn't in `schema`.
+    pub fn validate(&self, schema: &Schema) -> Result<()> {
+        let columns = self
+            .defaults
+            .keys()
+            .map(|column| ("default", column))
+            .chain(self.generated.keys().map(|column| ("generated", column)));
+        for (kind, column) in columns {
+            ensure!(
+                schema.field_with_name(column).is_ok(),
+                UnknownColumnSnafu {
+                    kind,
+                    column: column.clone(),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Removes the generated columns from `batch`, which the database would reject on insert.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the remaining columns can't be projected.
+    pub fn remove_generated_columns(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        if self.generated.is_empty() {
+            return Ok(batch);
+        }
+        let projection = batch
+            .schema()
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| !self.generated.contains_key(field.name()))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        batch
+            .project(&projection)
+            .context(UnableToRemoveGeneratedColumnsSnafu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::{
+        arrow::{
+            array::Int64Array,
+            datatypes::{DataType, Field},
+        },
+        prelude::lit,
+        sql::unparser::dialect::PostgreSqlDialect,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_column_expressions_from_table_options() {
+        let defaults = HashMap::from([("quantity".to_string(), lit(1_i64))]);
+        let mut options = HashMap::from([
+            (
+                "generated_column.total".to_string(),
+                "price * quantity".to_string(),
+            ),
+            ("on_conflict".to_string(), "upsert:id".to_string()),
+        ]);
+
+        let column_expressions = ColumnExpressions::try_from_table_options(
+            &defaults,
+            &mut options,
+            &PostgreSqlDialect {},
+        )
+        .expect("column expressions parsed");
+        assert_eq!(column_expressions.default_sql("quantity"), Some("1"));
+        assert_eq!(
+            column_expressions.default_expr("quantity"),
+            Some(&lit(1_i64))
+        );
+        assert_eq!(
+            column_expressions.generated_sql("total"),
+            Some("price * quantity")
+        );
+        assert_eq!(options.len(), 1);
+
+        let schema = Schema::new(vec![
+            Field::new("price", DataType::Int64, false),
+            Field::new("quantity", DataType::Int64, false),
+        ]);
+        assert_eq!(
+            column_expressions
+                .validate(&schema)
+                .expect_err("unknown column")
+                .to_string(),
+            "The generated column 'total' is not a column of the table"
+        );
+    }
+
+    #[test]
+    fn test_remove_generated_columns() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("price", DataType::Int64, false),
+            Field::new("total", DataType::Int64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(Int64Array::from(vec![None, None])),
+            ],
+        )
+        .expect("record batch created");
+
+        let batch = ColumnExpressions::new()
+            .with_generated_column("total", "price * 2")
+            .remove_generated_columns(batch)
+            .expect("generated columns removed");
+        assert_eq!(batch.num_columns(), 1);
+        assert_eq!(batch.schema().field(0).name(), "price");
+    }
+}
@@ -0,0 +1,342 @@
+use std::{collections::HashMap, ops::ControlFlow, sync::Arc};
+
+use datafusion::{
+    arrow::datatypes::{Field, Schema, SchemaRef},
+    sql::{
+        sqlparser::ast::{self, visit_expressions_mut},
+        unparser::dialect::Dialect,
+    },
+};
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The columns '{first}' and '{second}' are both mapped to '{name}'.\nRename one of them explicitly with ColumnMapper::with_rename."))]
+    DuplicateColumnName {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[snafu(display("The column '{original}' is mapped to an empty name.\nRename it explicitly with ColumnMapper::with_rename."))]
+    EmptyColumnName { original: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Renames the columns of a remote table in the schema that DataFusion sees, e.g. `Order Date` to
+/// `order_date`, while the SQL sent to the database keeps using their original names.
+///
+/// A column is renamed with its explicit rename if it has one, otherwise the characters of
+/// [`ColumnMapper::with_stripped_chars`] are removed from its name, then the name is converted
+/// to `snake_case` with [`ColumnMapper::with_snake_case`]. Two columns can't be mapped to the
+/// same name.
+///
+/// ```rust,ignore
+/// let mapper = ColumnMapper::new()
+///     .with_stripped_chars("$")
+///     .with_snake_case(true)
+///     .with_rename("ID#", "id");
+/// let table = SqlTable::new("postgres", &pool, "orders")
+///     .await?
+///     .with_column_mapper(&mapper)?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMapper {
+    renames: HashMap<String, String>,
+    stripped_chars: Vec<char>,
+    snake_case: bool,
+}
+
+impl ColumnMapper {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renames the column `original` to `name`, instead of deriving its name.
+    #[must_use]
+    pub fn with_rename(mut self, original: impl Into<String>, name: impl Into<String>) -> Self {
+        self.renames.insert(original.into(), name.into());
+        self
+    }
+
+    /// Removes the characters of `chars` from the names of the columns, like `$` or spaces.
+    #[must_use]
+    pub fn with_stripped_chars(mut self, chars: &str) -> Self {
+        self.stripped_chars = chars.chars().collect();
+        self
+    }
+
+    /// Converts the names of the columns to `snake_case`: `OrderDate` and `Order Date` become
+    /// `order_date`, and the characters other than letters and digits separate words.
+    #[must_use]
+    pub fn with_snake_case(mut self, snake_case: bool) -> Self {
+        self.snake_case = snake_case;
+        self
+    }
+
+    /// The name that the column `original` is exposed with.
+    #[must_use]
+    pub fn map_name(&self, original: &str) -> String {
+        if let Some(name) = self.renames.get(original) {
+            return name.clone();
+        }
+        let name = original
+            .chars()
+            .filter(|c| !self.stripped_chars.contains(c))
+            .collect::<String>();
+        if self.snake_case {
+            to_snake_case(&name)
+        } else {
+            name
+        }
+    }
+
+    /// `schema` with its columns renamed, and the mapping back to the original names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if two columns are mapped to the same name, or a column to an empty name.
+    pub fn map_schema(&self, schema: &SchemaRef) -> Result<(SchemaRef, ColumnMapping)> {
+        let mut originals: HashMap<String, String> = HashMap::new();
+        let mut columns = Vec::with_capacity(schema.fields().len());
+        let mut fields = Vec::with_capacity(schema.fields().len());
+        for field in schema.fields() {
+            let original = field.name();
+            let name = self.map_name(original);
+            ensure!(
+                !name.is_empty(),
+                EmptyColumnNameSnafu {
+                    original: original.clone()
+                }
+            );
+            if let Some(first) = originals.insert(name.clone(), original.clone()) {
+                return DuplicateColumnNameSnafu {
+                    name,
+                    first,
+                    second: original.clone(),
+                }
+                .fail();
+            }
+            fields.push(Arc::new(Field::clone(field).with_name(name.clone())));
+            columns.push((original.clone(), name));
+        }
+        let schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+        Ok((schema, ColumnMapping { columns }))
+    }
+}
+
+/// `name` in `snake_case`, with a word starting at each uppercase letter that follows a lowercase
+/// letter or a digit, or that starts a word after a run of uppercase letters, like `HTTPServer`.
+fn to_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake_case = String::with_capacity(name.len());
+    for (i, c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !snake_case.is_empty() && !snake_case.ends_with('_') {
+                snake_case.push('_');
+            }
+            continue;
+        }
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            let starts_word = previous.is_lowercase()
+                || previous.is_numeric()
+                || (previous.is_uppercase() && next_is_lower);
+            if starts_word && !snake_case.ends_with('_') {
+                snake_case.push('_');
+            }
+        }
+        snake_case.extend(c.to_lowercase());
+    }
+    snake_case.trim_end_matches('_').to_string()
+}
+
+/// The original names of the columns of a table exposed through a [`ColumnMapper`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    /// The original and exposed names of the columns, in the order of the schema.
+    columns: Vec<(String, String)>,
+}
+
+impl ColumnMapping {
+    /// Whether no column is renamed.
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        self.columns.iter().all(|(original, name)| original == name)
+    }
+
+    /// The original name of the column exposed as `name`.
+    #[must_use]
+    pub fn original(&self, name: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|(_, mapped)| mapped == name)
+            .map(|(original, _)| original.as_str())
+    }
+
+    fn renamed_original(&self, name: &str) -> Option<&str> {
+        self.original(name).filter(|original| *original != name)
+    }
+
+    /// Rewrites `statement`, written with the exposed names of the columns, to use their original
+    /// names. The columns of the outer `SELECT` are aliased back to their exposed names, and a
+    /// `SELECT *` over a single table is expanded to its columns.
+    ///
+    /// The identifiers are matched by name, so an exposed name that is also the name of a column
+    /// of another table in the statement is renamed there too.
+    pub fn rewrite_statement(&self, statement: &mut ast::Statement, dialect: &dyn Dialect) {
+        if self.is_identity() {
+            return;
+        }
+        let ident = |name: &str| {
+            let mut ident = ast::Ident::new(name);
+            ident.quote_style = dialect.identifier_quote_style(name);
+            ident
+        };
+
+        if let ast::Statement::Query(query) = statement {
+            if let ast::SetExpr::Select(select) = query.body.as_mut() {
+                let single_table = select.from.len() == 1 && select.from[0].joins.is_empty();
+                let mut projection = Vec::with_capacity(select.projection.len());
+                for item in select.projection.drain(..) {
+                    match item {
+                        ast::SelectItem::Wildcard(_) if single_table => {
+                            projection.extend(self.columns.iter().map(|(original, name)| {
+                                let expr = ast::Expr::Identifier(ident(original));
+                                if original == name {
+                                    ast::SelectItem::UnnamedExpr(expr)
+                                } else {
+                                    ast::SelectItem::ExprWithAlias {
+                                        expr,
+                                        alias: ident(name),
+                                    }
+                                }
+                            }));
+                        }
+                        ast::SelectItem::UnnamedExpr(expr) => {
+                            let name = match &expr {
+                                ast::Expr::Identifier(column) => Some(&column.value),
+                                ast::Expr::CompoundIdentifier(parts) => {
+                                    parts.last().map(|column| &column.value)
+                                }
+                                _ => None,
+                            };
+                            match name.filter(|name| self.renamed_original(name).is_some()) {
+                                Some(name) => projection.push(ast::SelectItem::ExprWithAlias {
+                                    alias: ident(name),
+                                    expr,
+                                }),
+                                None => projection.push(ast::SelectItem::UnnamedExpr(expr)),
+                            }
+                        }
+                        item => projection.push(item),
+                    }
+                }
+                select.projection = projection;
+            }
+        }
+
+        let _ = visit_expressions_mut(statement, |expr| {
+            let column = match expr {
+                ast::Expr::Identifier(column) => Some(column),
+                ast::Expr::CompoundIdentifier(parts) => parts.last_mut(),
+                _ => None,
+            };
+            if let Some(column) = column {
+                if let Some(original) = self.renamed_original(&column.value) {
+                    *column = ident(original);
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::datatypes::DataType,
+        sql::{
+            sqlparser::{dialect::GenericDialect, parser::Parser},
+            unparser::dialect::PostgreSqlDialect,
+        },
+    };
+
+    use super::*;
+
+    fn schema(names: &[&str]) -> SchemaRef {
+        Arc::new(Schema::new(
+            names
+                .iter()
+                .map(|name| Field::new(*name, DataType::Int64, true))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    #[test]
+    fn test_map_name() {
+        let mapper = ColumnMapper::new()
+            .with_stripped_chars("$")
+            .with_snake_case(true)
+            .with_rename("ID#", "id");
+        assert_eq!(mapper.map_name("OrderDate"), "order_date");
+        assert_eq!(mapper.map_name("Order Date"), "order_date");
+        assert_eq!(mapper.map_name("$amount"), "amount");
+        assert_eq!(mapper.map_name("HTTPServer2Id"), "http_server2_id");
+        assert_eq!(mapper.map_name("ID#"), "id");
+        assert_eq!(
+            ColumnMapper::new()
+                .with_stripped_chars(" $")
+                .map_name("Unit $ Price"),
+            "UnitPrice"
+        );
+    }
+
+    #[test]
+    fn test_map_schema() -> Result<()> {
+        let mapper = ColumnMapper::new().with_snake_case(true);
+        let (schema, mapping) = mapper.map_schema(&schema(&["id", "Order Date"]))?;
+        assert_eq!(schema.field(1).name(), "order_date");
+        assert_eq!(mapping.original("order_date"), Some("Order Date"));
+        assert_eq!(mapping.original("id"), Some("id"));
+
+        assert!(matches!(
+            mapper.map_schema(&self::schema(&["OrderDate", "order_date"])),
+            Err(Error::DuplicateColumnName { name, .. }) if name == "order_date"
+        ));
+        assert!(matches!(
+            mapper.map_schema(&self::schema(&["$"])),
+            Err(Error::EmptyColumnName { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let (_, mapping) = ColumnMapper::new()
+            .with_snake_case(true)
+            .map_schema(&schema(&["id", "Order Date"]))?;
+        let rewrite = |sql: &str| -> Result<String, Box<dyn std::error::Error>> {
+            let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+            mapping.rewrite_statement(&mut statement, &PostgreSqlDialect {});
+            Ok(statement.to_string())
+        };
+
+        assert_eq!(
+            rewrite(r#"SELECT "t"."id", "t"."order_date" FROM "t" WHERE "t"."order_date" > 1"#)?,
+            r#"SELECT "t"."id", "t"."Order Date" AS "order_date" FROM "t" WHERE "t"."Order Date" > 1"#
+        );
+        assert_eq!(
+            rewrite(r#"SELECT * FROM "t" LIMIT 10"#)?,
+            r#"SELECT "id", "Order Date" AS "order_date" FROM "t" LIMIT 10"#
+        );
+        assert_eq!(
+            rewrite(r#"SELECT max("order_date") FROM "t""#)?,
+            r#"SELECT max("Order Date") FROM "t""#
+        );
+        Ok(())
+    }
+}
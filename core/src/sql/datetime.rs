@@ -0,0 +1,508 @@
+use std::sync::Arc;
+
+use datafusion::{
+    common::{not_impl_err, ScalarValue},
+    error::Result as DataFusionResult,
+    logical_expr::Expr,
+    sql::{
+        sqlparser::ast::{self, BinaryOperator, CastKind},
+        unparser::{dialect::Dialect, Unparser},
+    },
+};
+
+use super::dialect::{function, ExtendedDialect};
+
+/// The date and time functions of the remote database, used to push down `date_trunc` and
+/// `date_part`, which `EXTRACT` is planned as.
+///
+/// Postgres and DuckDB have both functions, but not all the abbreviations of the parts that
+/// DataFusion accepts, like `qtr` or `ns`, so the parts are always written in full. MySQL and
+/// SQLite have neither, and the calls are rewritten to the functions they have, like
+/// `DATE_FORMAT` and `strftime`, with the results DataFusion computes: weeks start on Mondays,
+/// `week` is the ISO week and `dow` counts from 0 on Sundays. The parts a database can't compute,
+/// like the microseconds on SQLite, fail to unparse, so the filters that use them aren't pushed
+/// down.
+///
+/// The interval arithmetic is written by the dialects, with their
+/// [`IntervalStyle`](datafusion::sql::unparser::dialect::IntervalStyle).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeSyntax {
+    /// `date_trunc('quarter', ts)`, `CAST(floor(date_part('second', ts)) AS INTEGER)` for the
+    /// seconds and their fractions, which Postgres extracts with the fractions of the seconds
+    Postgres,
+    /// `date_trunc('quarter', ts)`
+    DuckDB,
+    /// `CAST(DATE_FORMAT(ts, '%Y-%m-01') AS DATETIME)`, `DAYOFWEEK(ts) - 1`
+    MySql,
+    /// `datetime(ts, 'start of month')`, `CAST(strftime('%w', ts) AS INTEGER)`
+    Sqlite,
+}
+
+/// A part of a date or a time, which `date_part` extracts or `date_trunc` truncates to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DatePart {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+    DayOfWeek,
+    DayOfYear,
+    Epoch,
+}
+
+impl DatePart {
+    /// The part named by a literal argument, with the abbreviations of DataFusion.
+    fn from_arg(arg: &Expr) -> Option<Self> {
+        let Expr::Literal(
+            ScalarValue::Utf8(Some(part))
+            | ScalarValue::Utf8View(Some(part))
+            | ScalarValue::LargeUtf8(Some(part)),
+        ) = arg
+        else {
+            return None;
+        };
+        match part.to_lowercase().as_str() {
+            "y" | "year" => Some(Self::Year),
+            "qtr" | "quarter" => Some(Self::Quarter),
+            "mon" | "month" => Some(Self::Month),
+            "w" | "week" => Some(Self::Week),
+            "d" | "day" => Some(Self::Day),
+            "h" | "hour" => Some(Self::Hour),
+            "m" | "minute" => Some(Self::Minute),
+            "s" | "second" => Some(Self::Second),
+            "ms" | "millisecond" => Some(Self::Millisecond),
+            "us" | "microsecond" => Some(Self::Microsecond),
+            "ns" | "nanosecond" => Some(Self::Nanosecond),
+            "dow" => Some(Self::DayOfWeek),
+            "doy" => Some(Self::DayOfYear),
+            "epoch" => Some(Self::Epoch),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Year => "year",
+            Self::Quarter => "quarter",
+            Self::Month => "month",
+            Self::Week => "week",
+            Self::Day => "day",
+            Self::Hour => "hour",
+            Self::Minute => "minute",
+            Self::Second => "second",
+            Self::Millisecond => "millisecond",
+            Self::Microsecond => "microsecond",
+            Self::Nanosecond => "nanosecond",
+            Self::DayOfWeek => "dow",
+            Self::DayOfYear => "doy",
+            Self::Epoch => "epoch",
+        }
+    }
+}
+
+impl DateTimeSyntax {
+    /// Wraps `dialect` so that it unparses the date and time functions into the ones of this
+    /// syntax.
+    #[must_use]
+    pub fn dialect(
+        &self,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Arc<dyn Dialect + Send + Sync> {
+        let syntax = *self;
+        Arc::new(
+            ExtendedDialect::new(dialect).with_scalar_function_override(Box::new(
+                move |unparser, func_name, args| match (func_name, args) {
+                    ("date_trunc", [part, value]) => {
+                        syntax.date_trunc_to_sql(unparser, part, value)
+                    }
+                    ("date_part", [part, value]) => syntax.date_part_to_sql(unparser, part, value),
+                    _ => Ok(None),
+                },
+            )),
+        )
+    }
+
+    fn date_trunc_to_sql(
+        self,
+        unparser: &Unparser,
+        part: &Expr,
+        value: &Expr,
+    ) -> DataFusionResult<Option<ast::Expr>> {
+        let Some(part) = DatePart::from_arg(part) else {
+            return self.unsupported("date_trunc", &part.to_string());
+        };
+        let value = unparser.expr_to_sql(value)?;
+        let sql = match (self, part) {
+            (Self::Postgres | Self::DuckDB, _) => {
+                Some(function("date_trunc", vec![string(part.as_str()), value]))
+            }
+
+            (Self::MySql, DatePart::Week) => Some(cast(
+                function(
+                    "SUBDATE",
+                    vec![
+                        function("DATE", vec![value.clone()]),
+                        function("WEEKDAY", vec![value]),
+                    ],
+                ),
+                ast::DataType::Datetime(None),
+            )),
+            (Self::MySql, DatePart::Quarter) => Some(cast(
+                function(
+                    "CONCAT",
+                    vec![
+                        function("YEAR", vec![value.clone()]),
+                        string("-"),
+                        binary(
+                            binary(
+                                function("QUARTER", vec![value]),
+                                BinaryOperator::Multiply,
+                                number(3),
+                            ),
+                            BinaryOperator::Minus,
+                            number(2),
+                        ),
+                        string("-01"),
+                    ],
+                ),
+                ast::DataType::Datetime(None),
+            )),
+            (Self::MySql, _) => self.truncated_format(part).map(|format| {
+                cast(
+                    function("DATE_FORMAT", vec![value, string(format)]),
+                    ast::DataType::Datetime(None),
+                )
+            }),
+
+            (Self::Sqlite, DatePart::Year | DatePart::Month | DatePart::Day) => Some(function(
+                "datetime",
+                vec![value, string(&format!("start of {}", part.as_str()))],
+            )),
+            // back to the Monday on or before the day
+            (Self::Sqlite, DatePart::Week) => Some(function(
+                "datetime",
+                vec![
+                    value,
+                    string("start of day"),
+                    string("-6 days"),
+                    string("weekday 1"),
+                ],
+            )),
+            (Self::Sqlite, DatePart::Quarter) => Some(function(
+                "datetime",
+                vec![
+                    value.clone(),
+                    string("start of month"),
+                    function(
+                        "printf",
+                        vec![
+                            string("-%d months"),
+                            binary(
+                                nested(binary(
+                                    strftime_integer("%m", value),
+                                    BinaryOperator::Minus,
+                                    number(1),
+                                )),
+                                BinaryOperator::Modulo,
+                                number(3),
+                            ),
+                        ],
+                    ),
+                ],
+            )),
+            (Self::Sqlite, _) => self
+                .truncated_format(part)
+                .map(|format| function("strftime", vec![string(format), value])),
+        };
+        match sql {
+            Some(sql) => Ok(Some(sql)),
+            None => self.unsupported("date_trunc", part.as_str()),
+        }
+    }
+
+    /// The format of a time truncated to `part` for MySQL and SQLite, whose formats differ in
+    /// their minutes and seconds.
+    fn truncated_format(self, part: DatePart) -> Option<&'static str> {
+        match (self, part) {
+            (_, DatePart::Year) => Some("%Y-01-01 00:00:00"),
+            (_, DatePart::Month) => Some("%Y-%m-01 00:00:00"),
+            (_, DatePart::Day) => Some("%Y-%m-%d 00:00:00"),
+            (_, DatePart::Hour) => Some("%Y-%m-%d %H:00:00"),
+            (Self::MySql, DatePart::Minute) => Some("%Y-%m-%d %H:%i:00"),
+            (_, DatePart::Minute) => Some("%Y-%m-%d %H:%M:00"),
+            (Self::MySql, DatePart::Second) => Some("%Y-%m-%d %H:%i:%s"),
+            (_, DatePart::Second) => Some("%Y-%m-%d %H:%M:%S"),
+            _ => None,
+        }
+    }
+
+    fn date_part_to_sql(
+        self,
+        unparser: &Unparser,
+        part: &Expr,
+        value: &Expr,
+    ) -> DataFusionResult<Option<ast::Expr>> {
+        let Some(part) = DatePart::from_arg(part) else {
+            return self.unsupported("date_part", &part.to_string());
+        };
+        // DataFusion doesn't extract nanoseconds, so neither is the database asked to
+        if part == DatePart::Nanosecond {
+            return not_impl_err!("date_part of nanosecond is not supported");
+        }
+        let value = unparser.expr_to_sql(value)?;
+        let sql = match (self, part) {
+            (Self::Postgres, DatePart::Second | DatePart::Millisecond | DatePart::Microsecond) => {
+                Some(cast(
+                    function(
+                        "floor",
+                        vec![function("date_part", vec![string(part.as_str()), value])],
+                    ),
+                    ast::DataType::Integer(None),
+                ))
+            }
+            (Self::Postgres | Self::DuckDB, _) => {
+                Some(function("date_part", vec![string(part.as_str()), value]))
+            }
+
+            (Self::MySql, DatePart::Week) => Some(function("WEEK", vec![value, number(3)])),
+            (Self::MySql, DatePart::DayOfWeek) => Some(binary(
+                function("DAYOFWEEK", vec![value]),
+                BinaryOperator::Minus,
+                number(1),
+            )),
+            (Self::MySql, DatePart::Epoch) => Some(function("UNIX_TIMESTAMP", vec![value])),
+            (Self::MySql, DatePart::Millisecond | DatePart::Microsecond) => {
+                // the seconds are part of the fractions, like in DataFusion
+                let (microseconds, scale) = match part {
+                    DatePart::Millisecond => (
+                        function(
+                            "FLOOR",
+                            vec![binary(
+                                function("MICROSECOND", vec![value.clone()]),
+                                BinaryOperator::Divide,
+                                number(1000),
+                            )],
+                        ),
+                        1000,
+                    ),
+                    _ => (function("MICROSECOND", vec![value.clone()]), 1_000_000),
+                };
+                Some(binary(
+                    binary(
+                        function("SECOND", vec![value]),
+                        BinaryOperator::Multiply,
+                        number(scale),
+                    ),
+                    BinaryOperator::Plus,
+                    microseconds,
+                ))
+            }
+            (Self::MySql, _) => {
+                let name = match part {
+                    DatePart::Year => Some("YEAR"),
+                    DatePart::Quarter => Some("QUARTER"),
+                    DatePart::Month => Some("MONTH"),
+                    DatePart::Day => Some("DAY"),
+                    DatePart::Hour => Some("HOUR"),
+                    DatePart::Minute => Some("MINUTE"),
+                    DatePart::Second => Some("SECOND"),
+                    DatePart::DayOfYear => Some("DAYOFYEAR"),
+                    _ => None,
+                };
+                name.map(|name| function(name, vec![value]))
+            }
+
+            (Self::Sqlite, DatePart::Quarter) => Some(binary(
+                nested(binary(
+                    strftime_integer("%m", value),
+                    BinaryOperator::Plus,
+                    number(2),
+                )),
+                BinaryOperator::Divide,
+                number(3),
+            )),
+            (Self::Sqlite, DatePart::Millisecond) => Some(cast(
+                binary(
+                    function("strftime", vec![string("%f"), value]),
+                    BinaryOperator::Multiply,
+                    number(1000),
+                ),
+                ast::DataType::Integer(None),
+            )),
+            // with the fractions of the seconds, like in DataFusion
+            (Self::Sqlite, DatePart::Epoch) => Some(binary(
+                nested(binary(
+                    function("julianday", vec![value]),
+                    BinaryOperator::Minus,
+                    ast::Expr::Value(ast::Value::Number("2440587.5".to_string(), false)),
+                )),
+                BinaryOperator::Multiply,
+                number(86400),
+            )),
+            (Self::Sqlite, _) => {
+                let format = match part {
+                    DatePart::Year => Some("%Y"),
+                    DatePart::Month => Some("%m"),
+                    DatePart::Day => Some("%d"),
+                    DatePart::Hour => Some("%H"),
+                    DatePart::Minute => Some("%M"),
+                    DatePart::Second => Some("%S"),
+                    DatePart::DayOfWeek => Some("%w"),
+                    DatePart::DayOfYear => Some("%j"),
+                    // `%V`, the ISO week, needs SQLite 3.46
+                    _ => None,
+                };
+                format.map(|format| strftime_integer(format, value))
+            }
+        };
+        match sql {
+            Some(sql) => Ok(Some(sql)),
+            None => self.unsupported("date_part", part.as_str()),
+        }
+    }
+
+    /// Fails the unparsing of a call that the database can't compute, except on the databases
+    /// that have the function, which are passed any part as is.
+    fn unsupported(self, func_name: &str, part: &str) -> DataFusionResult<Option<ast::Expr>> {
+        match self {
+            Self::Postgres | Self::DuckDB => Ok(None),
+            Self::MySql | Self::Sqlite => {
+                not_impl_err!("{func_name} of {part} is not supported by {self:?}")
+            }
+        }
+    }
+}
+
+fn string(value: &str) -> ast::Expr {
+    ast::Expr::Value(ast::Value::SingleQuotedString(value.to_string()))
+}
+
+fn number(value: i64) -> ast::Expr {
+    ast::Expr::Value(ast::Value::Number(value.to_string(), false))
+}
+
+fn binary(left: ast::Expr, op: BinaryOperator, right: ast::Expr) -> ast::Expr {
+    ast::Expr::BinaryOp {
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+    }
+}
+
+fn nested(expr: ast::Expr) -> ast::Expr {
+    ast::Expr::Nested(Box::new(expr))
+}
+
+fn cast(expr: ast::Expr, data_type: ast::DataType) -> ast::Expr {
+    ast::Expr::Cast {
+        kind: CastKind::Cast,
+        expr: Box::new(expr),
+        data_type,
+        format: None,
+    }
+}
+
+/// `strftime(format, value)` as an integer, SQLite formats the parts as text.
+fn strftime_integer(format: &str, value: ast::Expr) -> ast::Expr {
+    cast(
+        function("strftime", vec![string(format), value]),
+        ast::DataType::Integer(None),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        functions::expr_fn::{date_part, date_trunc},
+        prelude::{col, lit},
+        sql::unparser::dialect::{DuckDBDialect, MySqlDialect, PostgreSqlDialect, SqliteDialect},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_date_time_functions() {
+        let cases: [(DateTimeSyntax, Arc<dyn Dialect + Send + Sync>, [&str; 4]); 4] = [
+            (
+                DateTimeSyntax::Postgres,
+                Arc::new(PostgreSqlDialect {}),
+                [
+                    r#"date_trunc('quarter', "ts")"#,
+                    r#"date_part('week', "ts")"#,
+                    r#"CAST(floor(date_part('second', "ts")) AS INTEGER)"#,
+                    r#"date_part('dow', "ts")"#,
+                ],
+            ),
+            (
+                DateTimeSyntax::DuckDB,
+                Arc::new(DuckDBDialect::new()),
+                [
+                    r#"date_trunc('quarter', "ts")"#,
+                    r#"date_part('week', "ts")"#,
+                    r#"date_part('second', "ts")"#,
+                    r#"date_part('dow', "ts")"#,
+                ],
+            ),
+            (
+                DateTimeSyntax::MySql,
+                Arc::new(MySqlDialect {}),
+                [
+                    "CAST(CONCAT(YEAR(`ts`), '-', QUARTER(`ts`) * 3 - 2, '-01') AS DATETIME)",
+                    "WEEK(`ts`, 3)",
+                    "SECOND(`ts`)",
+                    "DAYOFWEEK(`ts`) - 1",
+                ],
+            ),
+            (
+                DateTimeSyntax::Sqlite,
+                Arc::new(SqliteDialect {}),
+                [
+                    "datetime(`ts`, 'start of month', printf('-%d months', (CAST(strftime('%m', `ts`) AS INTEGER) - 1) % 3))",
+                    "",
+                    "CAST(strftime('%S', `ts`) AS INTEGER)",
+                    "CAST(strftime('%w', `ts`) AS INTEGER)",
+                ],
+            ),
+        ];
+
+        let exprs = [
+            date_trunc(lit("qtr"), col("ts")),
+            date_part(lit("week"), col("ts")),
+            date_part(lit("s"), col("ts")),
+            date_part(lit("dow"), col("ts")),
+        ];
+        for (syntax, dialect, expected) in cases {
+            let dialect = syntax.dialect(dialect);
+            let unparser = Unparser::new(dialect.as_ref());
+            for (expr, expected) in exprs.iter().zip(expected) {
+                let sql = unparser.expr_to_sql(expr);
+                if expected.is_empty() {
+                    // not pushed down
+                    assert!(sql.is_err(), "{syntax:?}: {expr}");
+                } else {
+                    assert_eq!(sql.expect("expression unparsed").to_string(), expected);
+                }
+            }
+
+            // DataFusion fails on nanoseconds, which are kept local for it to fail
+            let sql = unparser.expr_to_sql(&date_part(lit("ns"), col("ts")));
+            assert!(sql.is_err(), "{syntax:?}");
+        }
+
+        let dialect = DateTimeSyntax::MySql.dialect(Arc::new(MySqlDialect {}));
+        let sql = Unparser::new(dialect.as_ref())
+            .expr_to_sql(&date_trunc(lit("month"), col("ts")))
+            .expect("expression unparsed");
+        assert_eq!(
+            sql.to_string(),
+            "CAST(DATE_FORMAT(`ts`, '%Y-%m-01 00:00:00') AS DATETIME)"
+        );
+    }
+}
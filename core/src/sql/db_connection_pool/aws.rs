@@ -0,0 +1,274 @@
+//! AWS credentials and the Signature Version 4 signing of requests to AWS services.
+//!
+//! See <https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_sigv-create-signed-request.html>.
+
+use std::{fmt, time::SystemTime};
+
+use async_trait::async_trait;
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_credential_types::{
+    provider::{error::CredentialsError, ProvideCredentials},
+    Credentials,
+};
+use aws_sigv4::{
+    http_request::{SignableBody, SignableRequest, SigningInstructions, SigningSettings},
+    sign::v4,
+};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use snafu::prelude::*;
+use tokio::sync::OnceCell;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The environment variable {name} is not set. Set it to the AWS credentials used for IAM authentication."))]
+    MissingCredentials { name: String },
+
+    #[snafu(display("No AWS region is configured. Set the 'aws_region' parameter or the AWS_REGION environment variable."))]
+    MissingRegion,
+
+    #[snafu(display("Unable to load the AWS credentials: {source}"))]
+    UnableToLoadCredentials { source: CredentialsError },
+
+    #[snafu(display("Unable to sign the AWS request: {source}"))]
+    UnableToSign {
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The AWS credentials that sign requests.
+#[derive(Clone)]
+pub struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: SecretString,
+    session_token: Option<SecretString>,
+}
+
+impl AwsCredentials {
+    #[must_use]
+    pub fn new(
+        access_key_id: impl Into<String>,
+        secret_access_key: SecretString,
+        session_token: Option<SecretString>,
+    ) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key,
+            session_token,
+        }
+    }
+
+    /// The credentials of `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the access key or the secret access key isn't set.
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let access_key_id = var("AWS_ACCESS_KEY_ID").context(MissingCredentialsSnafu {
+            name: "AWS_ACCESS_KEY_ID",
+        })?;
+        let secret_access_key = var("AWS_SECRET_ACCESS_KEY").context(MissingCredentialsSnafu {
+            name: "AWS_SECRET_ACCESS_KEY",
+        })?;
+        Ok(Self::new(
+            access_key_id,
+            secret_access_key.into(),
+            var("AWS_SESSION_TOKEN").map(SecretString::from),
+        ))
+    }
+
+    pub(crate) fn session_token(&self) -> Option<&str> {
+        self.session_token.as_ref().map(ExposeSecret::expose_secret)
+    }
+}
+
+impl From<&AwsCredentials> for Credentials {
+    fn from(credentials: &AwsCredentials) -> Self {
+        Self::new(
+            credentials.access_key_id.clone(),
+            credentials.secret_access_key.expose_secret().to_string(),
+            credentials.session_token().map(str::to_string),
+            None,
+            "datafusion-table-providers",
+        )
+    }
+}
+
+impl fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Provides the AWS credentials that sign requests.
+///
+/// The credentials are asked for every time a request or a token is signed, so that a provider of
+/// temporary credentials, like the credentials of an assumed role, returns them renewed before
+/// they expire.
+#[async_trait]
+pub trait AwsCredentialsProvider: fmt::Debug + Send + Sync {
+    /// The current credentials.
+    async fn credentials(&self) -> Result<AwsCredentials>;
+}
+
+/// Credentials that never change.
+#[async_trait]
+impl AwsCredentialsProvider for AwsCredentials {
+    async fn credentials(&self) -> Result<AwsCredentials> {
+        Ok(self.clone())
+    }
+}
+
+/// The credentials of the default credential chain of the AWS SDKs, asked for again every time:
+/// the environment variables, the `~/.aws` profiles, web identity tokens like the ones of IRSA, and
+/// the container (ECS) and instance (IMDS) metadata endpoints, in that order.
+///
+/// See <https://docs.aws.amazon.com/sdkref/latest/guide/standardized-credentials.html>.
+#[derive(Debug, Default)]
+pub struct DefaultCredentialsProvider {
+    chain: OnceCell<DefaultCredentialsChain>,
+}
+
+#[async_trait]
+impl AwsCredentialsProvider for DefaultCredentialsProvider {
+    async fn credentials(&self) -> Result<AwsCredentials> {
+        let chain = self
+            .chain
+            .get_or_init(|| DefaultCredentialsChain::builder().build())
+            .await;
+        let credentials = chain
+            .provide_credentials()
+            .await
+            .context(UnableToLoadCredentialsSnafu)?;
+        Ok(AwsCredentials::new(
+            credentials.access_key_id(),
+            credentials.secret_access_key().to_string().into(),
+            credentials
+                .session_token()
+                .map(|session_token| session_token.to_string().into()),
+        ))
+    }
+}
+
+/// `region`, or the region of `AWS_REGION` or `AWS_DEFAULT_REGION`.
+///
+/// # Errors
+///
+/// Returns an error if no region is set.
+pub fn region_or_env(region: Option<&str>) -> Result<String> {
+    region
+        .map(str::to_string)
+        .or_else(|| std::env::var("AWS_REGION").ok())
+        .or_else(|| std::env::var("AWS_DEFAULT_REGION").ok())
+        .filter(|region| !region.is_empty())
+        .context(MissingRegionSnafu)
+}
+
+/// Signs a request to `service` in `region` at `now`, as `settings` tell where the signature goes.
+pub(crate) fn sign(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    now: DateTime<Utc>,
+    settings: SigningSettings,
+    request: SignableRequest<'_>,
+) -> Result<SigningInstructions> {
+    let identity = Credentials::from(credentials).into();
+    let params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region)
+        .name(service)
+        .time(SystemTime::from(now))
+        .settings(settings)
+        .build()
+        .map_err(|source| Error::UnableToSign {
+            source: source.into(),
+        })?;
+    let (instructions, _) = aws_sigv4::http_request::sign(request, &params.into())
+        .map_err(|source| Error::UnableToSign {
+            source: source.into(),
+        })?
+        .into_parts();
+    Ok(instructions)
+}
+
+/// A request to sign, see [`sign`].
+pub(crate) fn signable_request<'a>(
+    method: &'a str,
+    url: &'a str,
+    headers: &'a [(&'a str, &'a str)],
+    body: &'a [u8],
+) -> Result<SignableRequest<'a>> {
+    SignableRequest::new(
+        method,
+        url,
+        headers.iter().copied(),
+        SignableBody::Bytes(body),
+    )
+    .map_err(|source| Error::UnableToSign {
+        source: source.into(),
+    })
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+pub(crate) fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_sign_headers() {
+        // the `get-vanilla` request of the Signature Version 4 test suite
+        let now = Utc
+            .with_ymd_and_hms(2015, 8, 30, 12, 36, 0)
+            .single()
+            .expect("valid date");
+        let credentials = AwsCredentials::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            None,
+        );
+        let request = signable_request("GET", "https://example.amazonaws.com/", &[], b"")
+            .expect("valid request");
+        let instructions = sign(
+            &credentials,
+            "us-east-1",
+            "service",
+            now,
+            SigningSettings::default(),
+            request,
+        )
+        .expect("request signed");
+        let headers = instructions.headers().collect::<Vec<_>>();
+        assert!(headers.contains(&("x-amz-date", "20150830T123600Z")));
+        assert!(headers.contains(&(
+            "authorization",
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        )));
+    }
+
+    #[test]
+    fn test_uri_encode() {
+        assert_eq!(uri_encode("a b/c=d~e"), "a%20b%2Fc%3Dd~e");
+    }
+}
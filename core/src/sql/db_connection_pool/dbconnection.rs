@@ -7,6 +7,7 @@ use snafu::prelude::*;
 
 #[cfg(feature = "duckdb")]
 pub mod duckdbconn;
+pub mod mockconn;
 #[cfg(feature = "mysql")]
 pub mod mysqlconn;
 #[cfg(feature = "odbc")]
@@ -49,6 +50,12 @@ pub enum Error {
 
     #[snafu(display("Unable to get tables: {source}"))]
     UnableToGetTables { source: GenericError },
+
+    #[snafu(display("The connection can't bind string parameters to a query."))]
+    UnsupportedStringParameters {},
+
+    #[snafu(display("The connection can't set session variables."))]
+    UnsupportedSessionVariables {},
 }
 
 pub trait SyncDbConnection<T, P>: DbConnection<T, P> {
@@ -89,6 +96,25 @@ pub trait SyncDbConnection<T, P>: DbConnection<T, P> {
         projected_schema: Option<SchemaRef>,
     ) -> Result<SendableRecordBatchStream>;
 
+    /// Query the database with the given SQL statement and string parameters, which are bound to
+    /// the placeholders of the statement, see [`crate::sql::parameters::bind_string_literals`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails, or if there are parameters and the connection can't
+    /// bind strings.
+    fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        if !params.is_empty() {
+            return Err(Box::new(Error::UnsupportedStringParameters {}));
+        }
+        self.query_arrow(sql, &[], projected_schema)
+    }
+
     /// Execute the given SQL statement with parameters, returning the number of affected rows.
     ///
     /// # Arguments
@@ -137,6 +163,39 @@ pub trait AsyncDbConnection<T, P>: DbConnection<T, P> + Sync {
         projected_schema: Option<SchemaRef>,
     ) -> Result<SendableRecordBatchStream>;
 
+    /// Query the database with the given SQL statement and string parameters, which are bound to
+    /// the placeholders of the statement, see [`crate::sql::parameters::bind_string_literals`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails, or if there are parameters and the connection can't
+    /// bind strings.
+    async fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        if !params.is_empty() {
+            return Err(Box::new(Error::UnsupportedStringParameters {}));
+        }
+        self.query_arrow(sql, &[], projected_schema).await
+    }
+
+    /// Set these session variables on the connection, by name and value, for the next query only,
+    /// see [`crate::sql::query_context::QueryContextMode::SessionVariables`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a variable can't be set, or if there are variables and the connection
+    /// doesn't support them.
+    async fn set_session_variables(&self, variables: &[(String, String)]) -> Result<()> {
+        if !variables.is_empty() {
+            return Err(Box::new(Error::UnsupportedSessionVariables {}));
+        }
+        Ok(())
+    }
+
     /// Execute the given SQL statement with parameters, returning the number of affected rows.
     ///
     /// # Arguments
@@ -238,3 +297,27 @@ pub async fn query_arrow<T, P>(
         return Err(Error::UnableToDowncastConnection {});
     }
 }
+
+/// Query the database with the given SQL statement and string parameters, see
+/// [`SyncDbConnection::query_arrow_with_string_params`].
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub async fn query_arrow_with_string_params<T, P>(
+    conn: Box<dyn DbConnection<T, P>>,
+    sql: String,
+    params: &[String],
+    projected_schema: Option<SchemaRef>,
+) -> Result<SendableRecordBatchStream, Error> {
+    if let Some(conn) = conn.as_sync() {
+        conn.query_arrow_with_string_params(&sql, params, projected_schema)
+            .context(UnableToQueryArrowSnafu {})
+    } else if let Some(conn) = conn.as_async() {
+        conn.query_arrow_with_string_params(&sql, params, projected_schema)
+            .await
+            .context(UnableToQueryArrowSnafu {})
+    } else {
+        Err(Error::UnableToDowncastConnection {})
+    }
+}
@@ -1,9 +1,12 @@
 use std::any::Any;
-use std::sync::Arc;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 use arrow::array::RecordBatch;
-use arrow_schema::{DataType, Field};
+use arrow::compute::cast;
+use arrow::error::ArrowError;
+use arrow_schema::{DataType, Field, Schema};
 use async_stream::stream;
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::error::DataFusionError;
@@ -21,6 +24,8 @@ use rand::distr::{Alphanumeric, SampleString};
 use snafu::{prelude::*, ResultExt};
 use tokio::sync::mpsc::Sender;
 
+use crate::sql::db_connection_pool::duckdbprofile::QueryProfile;
+use crate::sql::db_connection_pool::duckdbworkers::{self, DuckDbWorkers};
 use crate::sql::db_connection_pool::runtime::run_sync_with_tokio;
 use crate::util::schema::SchemaValidator;
 use crate::UnsupportedTypeAction;
@@ -49,6 +54,21 @@ pub enum Error {
         path: Arc<str>,
         source: std::io::Error,
     },
+
+    #[snafu(display(
+        "Unable to attach DuckDB database {path}.\nThe database is already attached with other options for another query.\nUse the same attachment options for all providers of the DuckDB instance."
+    ))]
+    ConflictingAttachmentOptions { path: Arc<str> },
+
+    #[snafu(display(
+        "Unable to attach DuckDB database {path} as '{alias}'.\nAnother database is already attached as '{alias}'.\nUse a different alias for each attached database."
+    ))]
+    ConflictingAttachmentAlias { path: Arc<str>, alias: Arc<str> },
+
+    #[snafu(display(
+        "Invalid alias '{alias}' for attached DuckDB database.\nAn alias must start with a letter or an underscore and may only contain letters, digits and underscores."
+    ))]
+    InvalidAttachmentAlias { alias: Arc<str> },
 }
 
 pub trait DuckDBSyncParameter: ToSql + Sync + Send + DynClone {
@@ -63,39 +83,451 @@ impl<T: ToSql + Sync + Send + DynClone> DuckDBSyncParameter for T {
 dyn_clone::clone_trait_object!(DuckDBSyncParameter);
 pub type DuckDBParameter = Box<dyn DuckDBSyncParameter>;
 
+/// The prefix of MotherDuck database paths, e.g. `md:my_db`.
+pub const MOTHERDUCK_PREFIX: &str = "md:";
+
+/// The database MotherDuck connects to if the path doesn't name one.
+const MOTHERDUCK_DEFAULT_DATABASE: &str = "my_db";
+
+/// Returns the name of the MotherDuck database of `path`, or `None` if `path` isn't a MotherDuck path.
+#[must_use]
+pub fn motherduck_database(path: &str) -> Option<&str> {
+    let database = path.strip_prefix(MOTHERDUCK_PREFIX)?;
+    // the token and other settings can be given as query parameters, e.g. `md:my_db?motherduck_token=...`
+    let database = database.split('?').next().unwrap_or_default();
+    if database.is_empty() {
+        Some(MOTHERDUCK_DEFAULT_DATABASE)
+    } else {
+        Some(database)
+    }
+}
+
+/// The URL schemes of database files that DuckDB reads through the `httpfs` extension.
+const REMOTE_PATH_PREFIXES: [&str; 6] =
+    ["s3://", "s3a://", "gcs://", "gs://", "http://", "https://"];
+
+/// Returns whether `path` is the URL of a database file in object storage or on a web server.
+#[must_use]
+pub fn is_remote_path(path: &str) -> bool {
+    REMOTE_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// The kind of database file that is attached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DuckDBAttachmentType {
+    #[default]
+    DuckDB,
+    /// A SQLite file, read through DuckDB's `sqlite` extension. The extension is installed and
+    /// loaded automatically on the first attachment.
+    Sqlite,
+}
+
+/// How a database is attached, see [`DuckDBAttachments::new_with_options`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DuckDBAttachmentOptions {
+    read_only: bool,
+    attachment_type: DuckDBAttachmentType,
+    alias: Option<Arc<str>>,
+}
+
+impl Default for DuckDBAttachmentOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DuckDBAttachmentOptions {
+    /// Attaches a DuckDB database in read-only mode.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            read_only: true,
+            attachment_type: DuckDBAttachmentType::DuckDB,
+            alias: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    #[must_use]
+    pub fn with_attachment_type(mut self, attachment_type: DuckDBAttachmentType) -> Self {
+        self.attachment_type = attachment_type;
+        self
+    }
+
+    /// Attaches the database under `alias`, so queries can refer to its tables as `alias.table`.
+    ///
+    /// Without an alias, the database is attached under a name generated by the
+    /// [`DuckDBAttachmentRegistry`].
+    #[must_use]
+    pub fn with_alias(mut self, alias: impl Into<Arc<str>>) -> Self {
+        self.alias = Some(alias.into());
+        self
+    }
+
+    #[must_use]
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    #[must_use]
+    pub fn attachment_type(&self) -> DuckDBAttachmentType {
+        self.attachment_type
+    }
+
+    #[must_use]
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    /// Returns the option list of the `ATTACH` statement, e.g. ` (TYPE SQLITE, READ_ONLY)`.
+    fn to_sql(&self) -> String {
+        let mut options = Vec::new();
+        if self.attachment_type == DuckDBAttachmentType::Sqlite {
+            options.push("TYPE SQLITE");
+        }
+        if self.read_only {
+            options.push("READ_ONLY");
+        }
+
+        if options.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", options.join(", "))
+        }
+    }
+}
+
+/// The databases attached to a DuckDB instance.
+///
+/// Attached databases are shared by all connections to a DuckDB instance, so the providers that use
+/// the same connection pool also share their attachments. Each file is attached under one alias while
+/// any query uses it, and detached when the last of them finishes, unless the registry keeps the
+/// attachments for later queries.
+#[derive(Debug)]
+pub struct DuckDBAttachmentRegistry {
+    random_id: String,
+    keep_attached: AtomicBool,
+    state: Mutex<AttachmentState>,
+}
+
+#[derive(Debug, Default)]
+struct AttachmentState {
+    aliases: HashMap<Arc<str>, String>,
+    /// The index of the next generated alias, which is never reused for another database.
+    next_alias: usize,
+    references: HashMap<Arc<str>, usize>,
+    /// The options of the attached databases, which includes the ones that are kept attached.
+    options: HashMap<Arc<str>, DuckDBAttachmentOptions>,
+}
+
+impl Default for DuckDBAttachmentRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DuckDBAttachmentRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            random_id: Alphanumeric.sample_string(&mut rand::rng(), 8),
+            keep_attached: AtomicBool::new(false),
+            state: Mutex::new(AttachmentState::default()),
+        }
+    }
+
+    /// Keeps the databases attached after the last query that uses them has finished, so that
+    /// later queries don't attach them again.
+    ///
+    /// The kept databases are detached when another query needs them with other options or needs
+    /// their alias for another database, or when the DuckDB instance is closed. Whether they are
+    /// still attached is checked against `duckdb_databases()` whenever a new database is attached.
+    pub fn set_keep_attached(&self, keep_attached: bool) {
+        self.keep_attached.store(keep_attached, Ordering::Relaxed);
+    }
+
+    /// Returns whether a database is attached to the DuckDB instance through the registry, either
+    /// for a running query or kept from an earlier one.
+    #[must_use]
+    pub fn is_attached(&self, db: &str) -> bool {
+        self.lock().options.contains_key(db)
+    }
+
+    /// Returns the generated alias the database is attached as if no alias is given in its
+    /// [`DuckDBAttachmentOptions`], which stays the same for the lifetime of the registry.
+    #[must_use]
+    pub fn alias(&self, db: &Arc<str>) -> String {
+        let mut state = self.lock();
+        Self::alias_in(&mut state, &self.random_id, db)
+    }
+
+    /// Returns the number of queries that use the attached database.
+    #[must_use]
+    pub fn references(&self, db: &str) -> usize {
+        self.lock().references.get(db).copied().unwrap_or_default()
+    }
+
+    fn alias_in(state: &mut AttachmentState, random_id: &str, db: &Arc<str>) -> String {
+        // MotherDuck databases keep their name, which is how they are known in the cloud catalog
+        if let Some(database) = motherduck_database(db) {
+            return database.to_string();
+        }
+        if let Some(alias) = state.aliases.get(db) {
+            return alias.clone();
+        }
+        let alias = DuckDBAttachments::get_attachment_name(random_id, state.next_alias);
+        state.next_alias += 1;
+        state.aliases.insert(Arc::clone(db), alias.clone());
+        alias
+    }
+
+    fn attachment_alias(
+        state: &mut AttachmentState,
+        random_id: &str,
+        db: &Arc<str>,
+        options: &DuckDBAttachmentOptions,
+    ) -> String {
+        match &options.alias {
+            Some(alias) => alias.to_string(),
+            None => Self::alias_in(state, random_id, db),
+        }
+    }
+
+    /// Attaches the database if no other query uses it yet, or if it isn't kept attached from an
+    /// earlier query, see [`Self::set_keep_attached`].
+    ///
+    /// A database that is already attached can only be shared with the same options, as it would
+    /// otherwise be writable for a query that attached it read-only, or the other way around.
+    fn acquire(
+        &self,
+        conn: &Connection,
+        db: &Arc<str>,
+        options: &DuckDBAttachmentOptions,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        if let Some(attached) = state.options.get(db) {
+            if attached == options {
+                *state.references.entry(Arc::clone(db)).or_default() += 1;
+                return Ok(());
+            }
+            ensure!(
+                !state.references.contains_key(db),
+                ConflictingAttachmentOptionsSnafu {
+                    path: Arc::clone(db),
+                }
+            );
+            // the database was kept attached with other options, but no query uses it anymore
+            Self::detach_in(&mut state, &self.random_id, conn, db)?;
+        }
+
+        // check the db file exists, remote databases are checked by the attachment itself
+        if motherduck_database(db).is_none() && !is_remote_path(db) {
+            std::fs::metadata(db.as_ref()).context(UnableToAttachDatabaseSnafu {
+                path: Arc::clone(db),
+            })?;
+        }
+        if let Some(alias) = &options.alias {
+            ensure!(
+                is_valid_alias(alias),
+                InvalidAttachmentAliasSnafu {
+                    alias: Arc::clone(alias),
+                }
+            );
+        }
+        if self.keep_attached.load(Ordering::Relaxed) {
+            // the set of attachments changes, so forget the ones that were detached by other means
+            Self::refresh_in(&mut state, &self.random_id, conn)?;
+        }
+
+        let alias = Self::attachment_alias(&mut state, &self.random_id, db, options);
+        // `ATTACH IF NOT EXISTS` would silently reuse the other database attached under the alias
+        let alias_holder = state
+            .options
+            .iter()
+            .find(|(path, attached)| {
+                *path != db
+                    && attached
+                        .alias
+                        .as_deref()
+                        .or_else(|| state.aliases.get(*path).map(String::as_str))
+                        == Some(alias.as_str())
+            })
+            .map(|(path, _)| Arc::clone(path));
+        if let Some(holder) = alias_holder {
+            ensure!(
+                !state.references.contains_key(&holder),
+                ConflictingAttachmentAliasSnafu {
+                    path: Arc::clone(db),
+                    alias: alias.as_str(),
+                }
+            );
+            Self::detach_in(&mut state, &self.random_id, conn, &holder)?;
+        }
+
+        let sql = format!(
+            "ATTACH IF NOT EXISTS '{}' AS {alias}{}",
+            db.replace('\'', "''"),
+            options.to_sql()
+        );
+        tracing::trace!("Attaching {db} using: {sql}");
+
+        conn.execute(&sql, []).context(DuckDBConnectionSnafu)?;
+        state.references.insert(Arc::clone(db), 1);
+        state.options.insert(Arc::clone(db), options.clone());
+        Ok(())
+    }
+
+    /// Detaches the database once the last query that uses it has released it, unless attachments
+    /// are kept for later queries.
+    fn release(&self, conn: &Connection, db: &Arc<str>) -> Result<()> {
+        let mut state = self.lock();
+        let Some(references) = state.references.get_mut(db) else {
+            return Ok(());
+        };
+        *references -= 1;
+        if *references > 0 {
+            return Ok(());
+        }
+
+        state.references.remove(db);
+        if self.keep_attached.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        Self::detach_in(&mut state, &self.random_id, conn, db)
+    }
+
+    fn detach_in(
+        state: &mut AttachmentState,
+        random_id: &str,
+        conn: &Connection,
+        db: &Arc<str>,
+    ) -> Result<()> {
+        let options = state.options.remove(db).unwrap_or_default();
+        let alias = Self::attachment_alias(state, random_id, db, &options);
+        tracing::trace!("Detaching {db} attached as {alias}");
+        conn.execute(&format!("DETACH DATABASE IF EXISTS {alias}"), [])
+            .context(DuckDBConnectionSnafu)?;
+        Ok(())
+    }
+
+    /// Removes the databases that aren't attached to the DuckDB instance anymore, e.g. because a
+    /// query detached them, from the attachments that are kept.
+    fn refresh_in(state: &mut AttachmentState, random_id: &str, conn: &Connection) -> Result<()> {
+        let mut stmt = conn
+            .prepare("SELECT database_name FROM duckdb_databases()")
+            .context(DuckDBConnectionSnafu)?;
+        let databases = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .context(DuckDBConnectionSnafu)?
+            .collect::<Result<HashSet<_>, _>>()
+            .context(DuckDBConnectionSnafu)?;
+
+        let attached = state.options.clone();
+        for (db, options) in attached {
+            let alias = Self::attachment_alias(state, random_id, &db, &options);
+            if !databases.contains(&alias) {
+                state.options.remove(&db);
+                state.references.remove(&db);
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, AttachmentState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn is_valid_alias(alias: &str) -> bool {
+    let mut chars = alias.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 #[derive(Debug)]
 pub struct DuckDBAttachments {
     attachments: HashSet<Arc<str>>,
+    options: HashMap<Arc<str>, DuckDBAttachmentOptions>,
     search_path: Arc<str>,
-    random_id: String,
+    registry: Arc<DuckDBAttachmentRegistry>,
 }
 
 impl DuckDBAttachments {
     /// Creates a new instance of a `DuckDBAttachments`, which instructs DuckDB connections to attach other DuckDB databases for queries.
     #[must_use]
+    ///
+    /// The databases are attached read-only, see [`Self::new_with_options`] to attach them otherwise.
     pub fn new(id: &str, attachments: &[Arc<str>]) -> Self {
-        let random_id = Alphanumeric.sample_string(&mut rand::rng(), 8);
-        let attachments: HashSet<Arc<str>> = attachments.iter().cloned().collect();
-        let search_path = Self::get_search_path(id, &random_id, &attachments);
+        Self::new_with_registry(id, attachments, Arc::new(DuckDBAttachmentRegistry::new()))
+    }
+
+    /// Creates a `DuckDBAttachments` that attaches the databases through a registry shared with the
+    /// other attachments of the same DuckDB instance, see [`DuckDBAttachmentRegistry`].
+    ///
+    /// Only the databases given here are added to the search path, so unqualified table names resolve
+    /// to these attachments even if other providers attached more databases to the instance.
+    #[must_use]
+    pub fn new_with_registry(
+        id: &str,
+        attachments: &[Arc<str>],
+        registry: Arc<DuckDBAttachmentRegistry>,
+    ) -> Self {
+        let attachments = attachments
+            .iter()
+            .map(|db| (Arc::clone(db), DuckDBAttachmentOptions::default()))
+            .collect::<Vec<_>>();
+        Self::new_with_options(id, &attachments, registry)
+    }
+
+    /// Creates a `DuckDBAttachments` with the options each database is attached with, e.g. to
+    /// attach a database in read-write mode or to attach a SQLite file.
+    ///
+    /// If a database is given more than once, the options given last are used.
+    #[must_use]
+    pub fn new_with_options(
+        id: &str,
+        attachments: &[(Arc<str>, DuckDBAttachmentOptions)],
+        registry: Arc<DuckDBAttachmentRegistry>,
+    ) -> Self {
+        let options: HashMap<Arc<str>, DuckDBAttachmentOptions> =
+            attachments.iter().cloned().collect();
+        let attachments: HashSet<Arc<str>> = options.keys().cloned().collect();
+        let search_path = Self::get_search_path(id, &options, &registry);
         Self {
             attachments,
+            options,
             search_path,
-            random_id,
+            registry,
         }
     }
 
     /// Returns the search path for the given database and attachments.
     /// The given database needs to be included separately, as search path by default do not include the main database.
     #[must_use]
-    fn get_search_path(id: &str, random_id: &str, attachments: &HashSet<Arc<str>>) -> Arc<str> {
+    fn get_search_path(
+        id: &str,
+        attachments: &HashMap<Arc<str>, DuckDBAttachmentOptions>,
+        registry: &DuckDBAttachmentRegistry,
+    ) -> Arc<str> {
         // search path includes the main database and all attached databases
         let mut search_path: Vec<Arc<str>> = vec![id.into()];
 
         search_path.extend(
             attachments
                 .iter()
-                .enumerate()
-                .map(|(i, _)| Self::get_attachment_name(random_id, i).into()),
+                .map(|(db, options)| match &options.alias {
+                    Some(alias) => Arc::clone(alias),
+                    None => registry.alias(db).into(),
+                }),
         );
 
         search_path.join(",").into()
@@ -125,46 +557,68 @@ impl DuckDBAttachments {
 
     /// Attaches the databases to the given connection and sets the search path for the newly attached databases.
     ///
+    /// Databases that are already attached for other queries are reused, and the attachments are
+    /// released again if one of them fails.
+    ///
     /// # Errors
     ///
     /// Returns an error if a specific attachment is missing, cannot be attached, search path cannot be set or the connection fails.
     pub fn attach(&self, conn: &Connection) -> Result<()> {
-        for (i, db) in self.attachments.iter().enumerate() {
-            // check the db file exists
-            std::fs::metadata(db.as_ref()).context(UnableToAttachDatabaseSnafu {
-                path: Arc::clone(db),
-            })?;
-            let sql = format!(
-                "ATTACH IF NOT EXISTS '{db}' AS {} (READ_ONLY)",
-                Self::get_attachment_name(&self.random_id, i)
-            );
-            tracing::trace!("Attaching {db} using: {sql}");
+        let mut attached = Vec::with_capacity(self.attachments.len());
+        for db in &self.attachments {
+            let options = self.options.get(db).cloned().unwrap_or_default();
+            if let Err(e) = self.registry.acquire(conn, db, &options) {
+                self.release(conn, &attached);
+                return Err(e);
+            }
+            attached.push(db);
+        }
 
-            conn.execute(&sql, []).context(DuckDBConnectionSnafu)?;
+        if let Err(e) = self.set_search_path(conn) {
+            self.release(conn, &attached);
+            return Err(e);
         }
+        Ok(())
+    }
 
-        self.set_search_path(conn)?;
+    /// Attaches the databases and sets the search path for a connection that is used without
+    /// [`Self::attach`] and [`Self::detach`] around each query, e.g. to read the schemas of tables.
+    ///
+    /// The databases stay attached after this returns only if the registry keeps them, see
+    /// [`DuckDBAttachmentRegistry::set_keep_attached`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a specific attachment is missing, cannot be attached, search path cannot be set or the connection fails.
+    pub fn attach_for_connection(&self, conn: &Connection) -> Result<()> {
+        self.attach(conn)?;
+        let attached = self.attachments.iter().collect::<Vec<_>>();
+        self.release(conn, &attached);
         Ok(())
     }
 
-    /// Detaches the databases from the given connection and resets the search path to default.
+    /// Detaches the databases that no other query uses from the given connection and resets the search path to default.
     ///
     /// # Errors
     ///
     /// Returns an error if an attachment cannot be detached, search path cannot be set or the connection fails.
     pub fn detach(&self, conn: &Connection) -> Result<()> {
-        for (i, _) in self.attachments.iter().enumerate() {
-            conn.execute(
-                &format!("DETACH {}", Self::get_attachment_name(&self.random_id, i)),
-                [],
-            )
-            .context(DuckDBConnectionSnafu)?;
+        for db in &self.attachments {
+            self.registry.release(conn, db)?;
         }
 
         self.reset_search_path(conn)?;
         Ok(())
     }
 
+    fn release(&self, conn: &Connection, attached: &[&Arc<str>]) {
+        for db in attached {
+            if let Err(e) = self.registry.release(conn, db) {
+                tracing::warn!("Failed to detach {db}: {e}");
+            }
+        }
+    }
+
     #[must_use]
     fn get_attachment_name(random_id: &str, index: usize) -> String {
         format!("attachment_{random_id}_{index}")
@@ -175,6 +629,7 @@ pub struct DuckDbConnection {
     pub conn: r2d2::PooledConnection<DuckdbConnectionManager>,
     attachments: Option<Arc<DuckDBAttachments>>,
     unsupported_type_action: UnsupportedTypeAction,
+    workers: Option<Arc<DuckDbWorkers>>,
 }
 
 impl SchemaValidator for DuckDbConnection {
@@ -192,12 +647,15 @@ impl SchemaValidator for DuckDbConnection {
                     | DataType::Utf8View
                     | DataType::BinaryView
                     | DataType::Boolean => true,
+                    inner @ DataType::Map(_, _) => Self::is_data_type_supported(inner),
                     _ => false, // nested lists don't support anything else yet
                 }
             }
             DataType::Struct(inner_fields) => inner_fields
                 .iter()
                 .all(|field| Self::is_data_type_supported(field.data_type())),
+            // the entries of a map are a struct of its key and value
+            DataType::Map(entries, _) => Self::is_data_type_supported(entries.data_type()),
             _ => true,
         }
     }
@@ -237,6 +695,13 @@ impl DuckDbConnection {
         self
     }
 
+    /// Runs the queries of `query_arrow` on the given worker threads instead of `spawn_blocking`.
+    #[must_use]
+    pub fn with_workers(mut self, workers: Option<Arc<DuckDbWorkers>>) -> Self {
+        self.workers = workers;
+        self
+    }
+
     /// Passthrough if Option is Some for `DuckDBAttachments::attach`
     ///
     /// # Errors
@@ -290,6 +755,7 @@ impl SyncDbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBPar
             conn,
             attachments: None,
             unsupported_type_action: UnsupportedTypeAction::default(),
+            workers: None,
         }
     }
 
@@ -365,27 +831,37 @@ impl SyncDbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBPar
         &self,
         sql: &str,
         params: &[DuckDBParameter],
-        _projected_schema: Option<SchemaRef>,
+        projected_schema: Option<SchemaRef>,
     ) -> Result<SendableRecordBatchStream> {
         let (batch_tx, mut batch_rx) = tokio::sync::mpsc::channel::<RecordBatch>(4);
 
         Self::attach(&self.conn, &self.attachments)?;
-        let fetch_schema_sql =
-            format!("WITH fetch_schema AS ({sql}) SELECT * FROM fetch_schema LIMIT 0");
+        // an EXPLAIN can't be the body of a CTE, but it's cheap enough to run twice
+        let fetch_schema_sql = if is_explain(sql) {
+            sql.to_string()
+        } else {
+            format!("WITH fetch_schema AS ({sql}) SELECT * FROM fetch_schema LIMIT 0")
+        };
         let mut stmt = self
             .conn
             .prepare(&fetch_schema_sql)
             .boxed()
             .context(super::UnableToGetSchemaSnafu)?;
 
+        // the placeholders of the query are bound for the schema as well
+        let schema_params = params
+            .iter()
+            .map(|f| f.as_input_parameter())
+            .collect::<Vec<_>>();
         let result: duckdb::Arrow<'_> = stmt
-            .query_arrow([])
+            .query_arrow(schema_params.as_slice())
             .boxed()
             .context(super::UnableToGetSchemaSnafu)?;
 
         Self::detach(&self.conn, &self.attachments)?;
 
-        let schema = result.get_schema();
+        let duckdb_schema = result.get_schema();
+        let schema = with_projected_map_fields(&duckdb_schema, projected_schema.as_ref());
 
         let params = params.iter().map(dyn_clone::clone).collect::<Vec<_>>();
 
@@ -396,24 +872,83 @@ impl SyncDbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBPar
         let cloned_schema = schema.clone();
         let attachments = self.attachments.clone();
 
-        let create_stream = || -> Result<SendableRecordBatchStream> {
-            let join_handle = tokio::task::spawn_blocking(move || {
-                Self::attach(&conn, &attachments)?; // this attach could happen when we clone the connection, but we can't detach after the thread closes because the connection isn't thread safe
+        let workers = self.workers.clone();
+        // the profile of the task running the plan, as the query runs on another thread
+        let profile = QueryProfile::current();
+
+        let query = move || {
+            Self::attach(&conn, &attachments)?; // this attach could happen when we clone the connection, but we can't detach after the thread closes because the connection isn't thread safe
+            let profile_path = match &profile {
+                Some(_) => Some(enable_profiling(&conn)?),
+                None => None,
+            };
+            {
                 let mut stmt = conn.prepare(&sql).context(DuckDBQuerySnafu)?;
                 let params: &[&dyn ToSql] = &params
                     .iter()
                     .map(|f| f.as_input_parameter())
                     .collect::<Vec<_>>();
                 let result: duckdb::ArrowStream<'_> = stmt
-                    .stream_arrow(params, cloned_schema)
+                    .stream_arrow(params, duckdb_schema)
                     .context(DuckDBQuerySnafu)?;
                 for i in result {
-                    blocking_channel_send(&batch_tx, i)?;
+                    blocking_channel_send(&batch_tx, cast_map_columns(i, &cloned_schema)?)?;
                 }
+            }
+            // the profile is written once the statement is finalized
+            if let (Some(profile), Some(profile_path)) = (&profile, profile_path) {
+                match std::fs::read_to_string(&profile_path) {
+                    Ok(json) => profile.record(&json),
+                    Err(e) => tracing::warn!("Unable to read the DuckDB query profile: {e}"),
+                }
+                let _ = std::fs::remove_file(&profile_path);
+            }
 
-                Self::detach(&conn, &attachments)?;
-                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
-            });
+            Self::detach(&conn, &attachments)?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        };
+
+        if let Some(workers) = workers {
+            // the query starts when the stream is first polled
+            let output_stream = stream! {
+                let result_rx = match workers.spawn(query).await {
+                    Ok(result_rx) => result_rx,
+                    Err(e) => {
+                        yield Err(DataFusionError::Execution(format!(
+                            "Failed to execute DuckDB query: {e}"
+                        )));
+                        return;
+                    }
+                };
+
+                while let Some(batch) = batch_rx.recv().await {
+                    yield Ok(batch);
+                }
+
+                match result_rx.await {
+                    Ok(Err(task_error)) => {
+                        yield Err(DataFusionError::Execution(format!(
+                            "Failed to execute DuckDB query: {task_error}"
+                        )))
+                    },
+                    Err(_) => {
+                        yield Err(DataFusionError::Execution(format!(
+                            "Failed to execute DuckDB query: {}",
+                            duckdbworkers::Error::WorkerPanicked
+                        )))
+                    },
+                    _ => {}
+                }
+            };
+
+            return Ok(Box::pin(RecordBatchStreamAdapter::new(
+                schema,
+                output_stream,
+            )));
+        }
+
+        let create_stream = || -> Result<SendableRecordBatchStream> {
+            let join_handle = tokio::task::spawn_blocking(query);
 
             let output_stream = stream! {
                 while let Some(batch) = batch_rx.recv().await {
@@ -444,6 +979,19 @@ impl SyncDbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBPar
         run_sync_with_tokio(create_stream)
     }
 
+    fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        let params = params
+            .iter()
+            .map(|param| Box::new(param.clone()) as DuckDBParameter)
+            .collect::<Vec<_>>();
+        self.query_arrow(sql, &params, projected_schema)
+    }
+
     fn execute(&self, sql: &str, params: &[DuckDBParameter]) -> Result<u64> {
         let params: &[&dyn ToSql] = &params
             .iter()
@@ -455,6 +1003,87 @@ impl SyncDbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBPar
     }
 }
 
+/// Enables the JSON profiling of the queries of `conn`, which is a session of its own, and returns
+/// the file the profile of the next query is written to.
+fn enable_profiling(conn: &Connection) -> Result<std::path::PathBuf> {
+    let path = QueryProfile::output_path();
+    conn.execute_batch(&format!(
+        "PRAGMA enable_profiling = 'json'; PRAGMA profiling_output = '{}';",
+        path.display().to_string().replace('\'', "''")
+    ))
+    .context(DuckDBQuerySnafu)?;
+    Ok(path)
+}
+
+fn is_explain(sql: &str) -> bool {
+    sql.trim_start()
+        .get(..7)
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("EXPLAIN"))
+}
+
+/// `schema` with the `Map` columns typed as in `projected_schema`.
+///
+/// DuckDB names the fields of the entries of a `MAP` `key` and `value`, which can differ from the
+/// names of the schema the table was created with.
+fn with_projected_map_fields(
+    schema: &SchemaRef,
+    projected_schema: Option<&SchemaRef>,
+) -> SchemaRef {
+    let Some(projected_schema) = projected_schema else {
+        return Arc::clone(schema);
+    };
+
+    let fields = schema
+        .fields()
+        .iter()
+        .map(
+            |field| match projected_schema.field_with_name(field.name()) {
+                Ok(projected)
+                    if projected.data_type() != field.data_type()
+                        && contains_map(projected.data_type()) =>
+                {
+                    Arc::new(Field::clone(field).with_data_type(projected.data_type().clone()))
+                }
+                _ => Arc::clone(field),
+            },
+        )
+        .collect::<Vec<_>>();
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// `batch` with its columns cast to the types of `schema`, which only differ in `Map` fields.
+fn cast_map_columns(batch: RecordBatch, schema: &SchemaRef) -> Result<RecordBatch, ArrowError> {
+    if batch.schema() == *schema {
+        return Ok(batch);
+    }
+
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| {
+            if column.data_type() == field.data_type() {
+                Ok(Arc::clone(column))
+            } else {
+                cast(column, field.data_type())
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    RecordBatch::try_new(Arc::clone(schema), columns)
+}
+
+/// Whether `data_type` is a `Map` or has one nested in it.
+pub(crate) fn contains_map(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Map(_, _) => true,
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            contains_map(field.data_type())
+        }
+        DataType::Struct(fields) => fields.iter().any(|field| contains_map(field.data_type())),
+        _ => false,
+    }
+}
+
 fn blocking_channel_send<T>(channel: &Sender<T>, item: T) -> Result<()> {
     match channel.blocking_send(item) {
         Ok(()) => Ok(()),
@@ -547,6 +1176,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_map_fields_are_supported() {
+        let map = |value: DataType| {
+            DataType::Map(
+                Arc::new(Field::new(
+                    "entries",
+                    DataType::Struct(
+                        vec![
+                            Field::new("key", DataType::Utf8, false),
+                            Field::new("value", value, true),
+                        ]
+                        .into(),
+                    ),
+                    false,
+                )),
+                false,
+            )
+        };
+
+        for data_type in [
+            map(DataType::Int64),
+            map(map(DataType::Utf8)),
+            map(DataType::List(Arc::new(Field::new(
+                "item",
+                DataType::Int32,
+                true,
+            )))),
+            DataType::List(Arc::new(Field::new("item", map(DataType::Int64), true))),
+        ] {
+            assert!(
+                DuckDbConnection::is_data_type_supported(&data_type),
+                "{data_type} should be supported"
+            );
+        }
+    }
+
+    #[test]
+    fn test_map_columns_are_cast_to_projected_schema() {
+        let mut builder = arrow::array::MapBuilder::new(
+            Some(arrow::array::MapFieldNames {
+                entry: "entries".to_string(),
+                key: "key".to_string(),
+                value: "value".to_string(),
+            }),
+            arrow::array::StringBuilder::new(),
+            arrow::array::Int64Builder::new(),
+        );
+        builder.keys().append_value("size");
+        builder.values().append_value(3);
+        builder.append(true).expect("to append map");
+        let duckdb_map = builder.finish();
+
+        let mut builder = arrow::array::MapBuilder::new(
+            None,
+            arrow::array::StringBuilder::new(),
+            arrow::array::Int64Builder::new(),
+        );
+        builder.append(true).expect("to append map");
+        let projected_type = builder.finish().data_type().clone();
+
+        let duckdb_schema = Arc::new(Schema::new(vec![Field::new(
+            "attributes",
+            duckdb_map.data_type().clone(),
+            true,
+        )]));
+        let projected_schema = Arc::new(Schema::new(vec![Field::new(
+            "attributes",
+            projected_type.clone(),
+            true,
+        )]));
+
+        let schema = with_projected_map_fields(&duckdb_schema, Some(&projected_schema));
+        assert_eq!(schema.field(0).data_type(), &projected_type);
+
+        let batch =
+            RecordBatch::try_new(duckdb_schema, vec![Arc::new(duckdb_map)]).expect("valid batch");
+        let batch = cast_map_columns(batch, &schema).expect("map columns are cast");
+        assert_eq!(batch.column(0).data_type(), &projected_type);
+        assert_eq!(batch.num_rows(), 1);
+    }
+
     #[test]
     fn test_fields_are_supported() {
         // test that the usual field types are supported, string, numbers, etc
@@ -696,6 +1406,262 @@ mod tests {
         assert_eq!(search_path.split(',').count(), 4); // main_db + 3 unique attachments
     }
 
+    #[test]
+    fn test_duckdb_attachments_shared_registry() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("shared.duckdb");
+        {
+            let conn = Connection::open(&db_path)?;
+            conn.execute("CREATE TABLE shared (id INTEGER)", [])?;
+            conn.execute("INSERT INTO shared VALUES (1)", [])?;
+        }
+        let db: Arc<str> = Arc::from(db_path.to_str().unwrap());
+
+        let conn = Connection::open_in_memory()?;
+        let other_conn = conn.try_clone()?;
+        let registry = Arc::new(DuckDBAttachmentRegistry::new());
+        let first = DuckDBAttachments::new_with_registry(
+            "memory",
+            &[Arc::clone(&db)],
+            Arc::clone(&registry),
+        );
+        let second = DuckDBAttachments::new_with_registry(
+            "memory",
+            &[Arc::clone(&db)],
+            Arc::clone(&registry),
+        );
+        assert_eq!(first.search_path, second.search_path);
+
+        first.attach(&conn)?;
+        second.attach(&other_conn)?;
+        assert_eq!(registry.references(&db), 2);
+
+        // the database stays attached while the second query uses it
+        first.detach(&conn)?;
+        let count: i64 =
+            other_conn.query_row("SELECT count(*) FROM shared", [], |row| row.get(0))?;
+        assert_eq!(count, 1);
+
+        second.detach(&other_conn)?;
+        assert_eq!(registry.references(&db), 0);
+        let sql = format!("SELECT count(*) FROM {}.shared", registry.alias(&db));
+        assert!(conn
+            .query_row(&sql, [], |row| row.get::<_, i64>(0))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_duckdb_attachment_options() {
+        assert_eq!(DuckDBAttachmentOptions::new().to_sql(), " (READ_ONLY)");
+        assert_eq!(
+            DuckDBAttachmentOptions::new()
+                .with_read_only(false)
+                .to_sql(),
+            ""
+        );
+        assert_eq!(
+            DuckDBAttachmentOptions::new()
+                .with_attachment_type(DuckDBAttachmentType::Sqlite)
+                .to_sql(),
+            " (TYPE SQLITE, READ_ONLY)"
+        );
+    }
+
+    #[test]
+    fn test_duckdb_attachments_generated_aliases() {
+        let registry = DuckDBAttachmentRegistry::new();
+        let sales: Arc<str> = Arc::from("./sales.duckdb");
+        let orders: Arc<str> = Arc::from("./orders.duckdb");
+
+        let sales_alias = registry.alias(&sales);
+        let orders_alias = registry.alias(&orders);
+        assert_ne!(sales_alias, orders_alias);
+        assert_eq!(registry.alias(&sales), sales_alias);
+
+        // an alias isn't reused once the database that had it is forgotten
+        registry.lock().aliases.remove(&sales);
+        let customers_alias = registry.alias(&Arc::from("./customers.duckdb"));
+        assert_ne!(customers_alias, sales_alias);
+        assert_ne!(customers_alias, orders_alias);
+    }
+
+    #[test]
+    fn test_duckdb_attachments_path_with_quote() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("o'brien.duckdb");
+        {
+            let conn = Connection::open(&db_path)?;
+            conn.execute("CREATE TABLE customers (id INTEGER)", [])?;
+            conn.execute("INSERT INTO customers VALUES (1)", [])?;
+        }
+        let db: Arc<str> = Arc::from(db_path.to_str().unwrap());
+
+        let conn = Connection::open_in_memory()?;
+        let attachments = DuckDBAttachments::new("memory", &[Arc::clone(&db)]);
+        attachments.attach(&conn)?;
+        let count: i64 = conn.query_row("SELECT count(*) FROM customers", [], |row| row.get(0))?;
+        assert_eq!(count, 1);
+        attachments.detach(&conn)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_duckdb_attachments_read_write() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("writable.duckdb");
+        {
+            let conn = Connection::open(&db_path)?;
+            conn.execute("CREATE TABLE writable (id INTEGER)", [])?;
+        }
+        let db: Arc<str> = Arc::from(db_path.to_str().unwrap());
+
+        let conn = Connection::open_in_memory()?;
+        let registry = Arc::new(DuckDBAttachmentRegistry::new());
+        let writable = DuckDBAttachments::new_with_options(
+            "memory",
+            &[(
+                Arc::clone(&db),
+                DuckDBAttachmentOptions::new().with_read_only(false),
+            )],
+            Arc::clone(&registry),
+        );
+        writable.attach(&conn)?;
+        conn.execute("INSERT INTO writable VALUES (1)", [])?;
+
+        // a read-only attachment can't share the read-write one
+        let read_only =
+            DuckDBAttachments::new_with_registry("memory", &[Arc::clone(&db)], registry);
+        assert!(matches!(
+            read_only.attach(&conn.try_clone()?),
+            Err(e) if e.to_string().contains("already attached with other options")
+        ));
+
+        writable.detach(&conn)?;
+        let conn = Connection::open(&db_path)?;
+        let count: i64 = conn.query_row("SELECT count(*) FROM writable", [], |row| row.get(0))?;
+        assert_eq!(count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_duckdb_attachments_with_alias() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let sales_path = temp_dir.path().join("sales.duckdb");
+        let other_path = temp_dir.path().join("other.duckdb");
+        {
+            let conn = Connection::open(&sales_path)?;
+            conn.execute("CREATE TABLE orders (id INTEGER)", [])?;
+            conn.execute("INSERT INTO orders VALUES (1)", [])?;
+            Connection::open(&other_path)?;
+        }
+        let sales: Arc<str> = Arc::from(sales_path.to_str().unwrap());
+        let other: Arc<str> = Arc::from(other_path.to_str().unwrap());
+
+        let conn = Connection::open_in_memory()?;
+        let registry = Arc::new(DuckDBAttachmentRegistry::new());
+        let attachments = DuckDBAttachments::new_with_options(
+            "memory",
+            &[(
+                Arc::clone(&sales),
+                DuckDBAttachmentOptions::new().with_alias("sales"),
+            )],
+            Arc::clone(&registry),
+        );
+        assert_eq!(attachments.search_path.as_ref(), "memory,sales");
+
+        attachments.attach(&conn)?;
+        let count: i64 =
+            conn.query_row("SELECT count(*) FROM sales.orders", [], |row| row.get(0))?;
+        assert_eq!(count, 1);
+
+        // another database can't be attached under the same alias
+        let conflicting = DuckDBAttachments::new_with_options(
+            "memory",
+            &[(other, DuckDBAttachmentOptions::new().with_alias("sales"))],
+            Arc::clone(&registry),
+        );
+        assert!(conflicting.attach(&conn).is_err());
+
+        let invalid = DuckDBAttachments::new_with_options(
+            "memory",
+            &[(sales, DuckDBAttachmentOptions::new().with_alias("1; DROP"))],
+            Arc::new(DuckDBAttachmentRegistry::new()),
+        );
+        assert!(invalid.attach(&Connection::open_in_memory()?).is_err());
+
+        attachments.detach(&conn)?;
+        assert!(conn
+            .query_row("SELECT count(*) FROM sales.orders", [], |row| row
+                .get::<_, i64>(0))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_duckdb_attachments_kept_attached() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("kept.duckdb");
+        {
+            let conn = Connection::open(&db_path)?;
+            conn.execute("CREATE TABLE kept (id INTEGER)", [])?;
+        }
+        let db: Arc<str> = Arc::from(db_path.to_str().unwrap());
+
+        let conn = Connection::open_in_memory()?;
+        let registry = Arc::new(DuckDBAttachmentRegistry::new());
+        registry.set_keep_attached(true);
+        let attachments = DuckDBAttachments::new_with_registry(
+            "memory",
+            &[Arc::clone(&db)],
+            Arc::clone(&registry),
+        );
+
+        attachments.attach(&conn)?;
+        attachments.detach(&conn)?;
+        assert_eq!(registry.references(&db), 0);
+        assert!(registry.is_attached(&db));
+        let sql = format!("SELECT count(*) FROM {}.kept", registry.alias(&db));
+        assert!(conn.query_row(&sql, [], |row| row.get::<_, i64>(0)).is_ok());
+
+        // a database that was detached by a query is attached again
+        conn.execute(&format!("DETACH {}", registry.alias(&db)), [])?;
+        let other = temp_dir.path().join("other.duckdb");
+        Connection::open(&other)?;
+        let other_attachments = DuckDBAttachments::new_with_registry(
+            "memory",
+            &[Arc::from(other.to_str().unwrap())],
+            Arc::clone(&registry),
+        );
+        other_attachments.attach(&conn)?;
+        assert!(!registry.is_attached(&db));
+        attachments.attach(&conn)?;
+        assert!(conn.query_row(&sql, [], |row| row.get::<_, i64>(0)).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_remote_path() {
+        assert!(is_remote_path("s3://bucket/sales.duckdb"));
+        assert!(is_remote_path("https://example.com/sales.duckdb"));
+        assert!(!is_remote_path("./sales.duckdb"));
+        assert!(!is_remote_path("md:sales"));
+    }
+
+    #[test]
+    fn test_motherduck_database() {
+        assert_eq!(motherduck_database("md:sales"), Some("sales"));
+        assert_eq!(
+            motherduck_database("md:sales?motherduck_token=secret"),
+            Some("sales")
+        );
+        assert_eq!(motherduck_database("md:"), Some("my_db"));
+        assert_eq!(motherduck_database("./sales.db"), None);
+
+        let registry = DuckDBAttachmentRegistry::new();
+        assert_eq!(registry.alias(&Arc::from("md:sales")), "sales");
+    }
+
     #[test]
     fn test_duckdb_attachments_empty() {
         let duckdb_attachments = DuckDBAttachments::new("main_db", &[]);
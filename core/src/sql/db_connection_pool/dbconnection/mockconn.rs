@@ -0,0 +1,197 @@
+//! A connection to an in-memory database that serves canned batches and records the statements
+//! run on it, to unit test code built on the connection traits without a database.
+
+use std::{
+    any::Any,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        datatypes::{Schema, SchemaRef},
+    },
+    execution::SendableRecordBatchStream,
+    physical_plan::stream::RecordBatchStreamAdapter,
+    sql::TableReference,
+};
+use futures::stream;
+use snafu::prelude::*;
+
+use super::{AsyncDbConnection, DbConnection, GenericError};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The mock database has no results for the query: {sql}"))]
+    NoResults { sql: String },
+
+    #[snafu(display("The mock database has no table '{table_name}'"))]
+    NoTable { table_name: String },
+}
+
+#[derive(Debug, Default)]
+struct State {
+    tables: Vec<(TableReference, SchemaRef)>,
+    results: Vec<(String, Vec<RecordBatch>)>,
+    rows_affected: u64,
+    statements: Vec<String>,
+}
+
+/// The database behind a [`MockConnection`], shared by the connections of a
+/// [`MockConnectionPool`](crate::sql::db_connection_pool::mockpool::MockConnectionPool).
+#[derive(Debug, Clone, Default)]
+pub struct MockDatabase {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockDatabase {
+    /// Adds a table, whose schema is returned by [`AsyncDbConnection::get_schema`].
+    pub fn add_table(&self, table_reference: impl Into<TableReference>, schema: SchemaRef) {
+        self.state().tables.push((table_reference.into(), schema));
+    }
+
+    /// Serves `batches` for the queries containing `pattern`. The queries are matched against the
+    /// patterns in the order they were added.
+    pub fn add_results(&self, pattern: impl Into<String>, batches: Vec<RecordBatch>) {
+        self.state().results.push((pattern.into(), batches));
+    }
+
+    /// Sets the number of rows that [`AsyncDbConnection::execute`] returns.
+    pub fn set_rows_affected(&self, rows_affected: u64) {
+        self.state().rows_affected = rows_affected;
+    }
+
+    /// The queries and statements run on the database, in order.
+    #[must_use]
+    pub fn statements(&self) -> Vec<String> {
+        self.state().statements.clone()
+    }
+
+    /// Forgets the queries and statements run so far.
+    pub fn clear_statements(&self) {
+        self.state().statements.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        // the state stays consistent if a test panics while holding the lock
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A connection to a [`MockDatabase`].
+#[derive(Debug)]
+pub struct MockConnection {
+    database: MockDatabase,
+}
+
+impl MockConnection {
+    fn query(
+        &self,
+        sql: &str,
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream, GenericError> {
+        let mut state = self.database.state();
+        state.statements.push(sql.to_string());
+        let batches = state
+            .results
+            .iter()
+            .find(|(pattern, _)| sql.contains(pattern.as_str()))
+            .map(|(_, batches)| batches.clone())
+            .context(NoResultsSnafu { sql })?;
+
+        let schema = projected_schema
+            .or_else(|| batches.first().map(RecordBatch::schema))
+            .unwrap_or_else(|| Arc::new(Schema::empty()));
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream::iter(batches.into_iter().map(Ok)),
+        )))
+    }
+}
+
+impl DbConnection<MockDatabase, String> for MockConnection {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_async(&self) -> Option<&dyn AsyncDbConnection<MockDatabase, String>> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl AsyncDbConnection<MockDatabase, String> for MockConnection {
+    fn new(database: MockDatabase) -> Self {
+        MockConnection { database }
+    }
+
+    async fn tables(&self, schema: &str) -> Result<Vec<String>, super::Error> {
+        Ok(self
+            .database
+            .state()
+            .tables
+            .iter()
+            .filter(|(table, _)| table.schema() == Some(schema))
+            .map(|(table, _)| table.table().to_string())
+            .collect())
+    }
+
+    async fn schemas(&self) -> Result<Vec<String>, super::Error> {
+        let mut schemas: Vec<String> = Vec::new();
+        for (table, _) in &self.database.state().tables {
+            if let Some(schema) = table.schema() {
+                if !schemas.iter().any(|s| s == schema) {
+                    schemas.push(schema.to_string());
+                }
+            }
+        }
+        Ok(schemas)
+    }
+
+    async fn get_schema(
+        &self,
+        table_reference: &TableReference,
+    ) -> Result<SchemaRef, super::Error> {
+        self.database
+            .state()
+            .tables
+            .iter()
+            .find(|(table, _)| table.resolved_eq(table_reference))
+            .map(|(_, schema)| Arc::clone(schema))
+            .ok_or_else(|| super::Error::UndefinedTable {
+                table_name: table_reference.to_string(),
+                source: Box::new(Error::NoTable {
+                    table_name: table_reference.to_string(),
+                }),
+            })
+    }
+
+    async fn query_arrow(
+        &self,
+        sql: &str,
+        _params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream, GenericError> {
+        self.query(sql, projected_schema)
+    }
+
+    async fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        _params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream, GenericError> {
+        self.query(sql, projected_schema)
+    }
+
+    async fn execute(&self, sql: &str, _params: &[String]) -> Result<u64, GenericError> {
+        let mut state = self.database.state();
+        state.statements.push(sql.to_string());
+        Ok(state.rows_affected)
+    }
+}
@@ -1,6 +1,7 @@
 use std::{any::Any, sync::Arc};
 
-use crate::sql::arrow_sql_gen::mysql::map_column_to_data_type;
+use crate::sql::arrow_sql_gen::mysql::{map_column_to_data_type, UnsignedBigIntPolicy};
+use crate::sql::arrow_sql_gen::timestamp::TimestampPolicy;
 use crate::sql::arrow_sql_gen::{self, mysql::rows_to_arrow};
 use async_stream::stream;
 use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
@@ -13,7 +14,7 @@ use futures::lock::Mutex;
 use futures::{stream, StreamExt};
 use mysql_async::consts::ColumnType;
 use mysql_async::prelude::Queryable;
-use mysql_async::{prelude::ToValue, Conn, Params, Row};
+use mysql_async::{prelude::ToValue, Conn, Params, Row, Value};
 use snafu::prelude::*;
 
 use super::Result;
@@ -27,6 +28,11 @@ pub enum Error {
     #[snafu(display("Failed to convert query result to Arrow.\n{source}.\nReport a bug to request support: https://github.com/datafusion-contrib/datafusion-table-providers/issues"))]
     ConversionError { source: arrow_sql_gen::mysql::Error },
 
+    #[snafu(display("Failed to apply the timestamp policy to the query result.\n{source}"))]
+    TimestampPolicyError {
+        source: datafusion::arrow::error::ArrowError,
+    },
+
     #[snafu(display("An unexpected error occurred. Verify the configuration and try again."))]
     QueryResultStreamError {},
 
@@ -43,11 +49,40 @@ pub enum Error {
     MissingField { field: String },
 }
 
+/// The number of rows read into each record batch of a query result by default.
+pub const DEFAULT_RESULT_BUFFER_ROWS: usize = 4_000;
+
 pub struct MySQLConnection {
     pub conn: Arc<Mutex<Conn>>,
+    unsigned_bigint: UnsignedBigIntPolicy,
+    timestamp_policy: TimestampPolicy,
+    result_buffer_rows: usize,
 }
 
 impl MySQLConnection {
+    /// Sets how `BIGINT UNSIGNED` columns are read, as `UInt64` by default.
+    #[must_use]
+    pub fn with_unsigned_bigint_policy(mut self, unsigned_bigint: UnsignedBigIntPolicy) -> Self {
+        self.unsigned_bigint = unsigned_bigint;
+        self
+    }
+
+    /// Sets the time zone the timestamp columns are surfaced with.
+    #[must_use]
+    pub fn with_timestamp_policy(mut self, timestamp_policy: TimestampPolicy) -> Self {
+        self.timestamp_policy = timestamp_policy;
+        self
+    }
+
+    /// Sets the number of rows read into each record batch, [`DEFAULT_RESULT_BUFFER_ROWS`] by
+    /// default. The rows of a result are streamed from the server as they're read, so this is
+    /// the most rows of a query held in memory at once. 0 is treated as 1.
+    #[must_use]
+    pub fn with_result_buffer_rows(mut self, result_buffer_rows: usize) -> Self {
+        self.result_buffer_rows = result_buffer_rows.max(1);
+        self
+    }
+
     /// Create a [`TableReference`] in a manner that properly handles the unique quote style of MySQL.
     ///
     /// [`TableReference::from`] uses `DefaultDialect` and therefore gets quoting incorrect.
@@ -69,6 +104,67 @@ impl MySQLConnection {
             .collect::<Vec<_>>()
             .join(".")
     }
+
+    async fn query_values_arrow(
+        &self,
+        sql: &str,
+        params_vec: Vec<Value>,
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        let sql = sql.replace('"', "");
+
+        let conn = Arc::clone(&self.conn);
+        let unsigned_bigint = self.unsigned_bigint;
+        let timestamp_policy = self.timestamp_policy;
+        let result_buffer_rows = self.result_buffer_rows;
+
+        let mut stream = Box::pin(stream! {
+            let mut conn = conn.lock().await;
+            let mut exec_iter = conn
+                .exec_iter(sql, Params::from(params_vec))
+                .await
+                .context(QuerySnafu)?;
+
+            // the rows are read from the connection as the stream is polled, not buffered up front
+            let Some(stream) = exec_iter.stream::<Row>().await.context(QuerySnafu)? else {
+                yield Err(Error::QueryResultStreamError {});
+                return;
+            };
+
+            let mut chunked_stream = stream.chunks(result_buffer_rows).boxed();
+
+            while let Some(chunk) = chunked_stream.next().await {
+                let rows = chunk
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+                    .context(QuerySnafu)?;
+
+                let rec = rows_to_arrow(&rows, &projected_schema, unsigned_bigint).context(ConversionSnafu)?;
+                let rec = timestamp_policy.apply_to_batch(rec).context(TimestampPolicySnafu)?;
+                yield Ok::<_, Error>(rec)
+            }
+        });
+
+        let Some(first_chunk) = stream.next().await else {
+            return Ok(Box::pin(RecordBatchStreamAdapter::new(
+                Arc::new(Schema::empty()),
+                stream::empty(),
+            )));
+        };
+
+        let first_chunk = first_chunk?;
+        let schema = first_chunk.schema();
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, {
+            stream! {
+                yield Ok(first_chunk);
+                while let Some(batch) = stream.next().await {
+                    yield batch
+                        .map_err(|e| DataFusionError::Execution(format!("Failed to fetch batch: {e}")))
+                }
+            }
+        })))
+    }
 }
 
 impl<'a> DbConnection<Conn, &'a (dyn ToValue + Sync)> for MySQLConnection {
@@ -90,6 +186,9 @@ impl<'a> AsyncDbConnection<Conn, &'a (dyn ToValue + Sync)> for MySQLConnection {
     fn new(conn: Conn) -> Self {
         MySQLConnection {
             conn: Arc::new(Mutex::new(conn)),
+            unsigned_bigint: UnsignedBigIntPolicy::default(),
+            timestamp_policy: TimestampPolicy::default(),
+            result_buffer_rows: DEFAULT_RESULT_BUFFER_ROWS,
         }
     }
 
@@ -175,7 +274,9 @@ impl<'a> AsyncDbConnection<Conn, &'a (dyn ToValue + Sync)> for MySQLConnection {
             },
         };
 
-        Ok(columns_meta_to_schema(columns_meta).context(super::UnableToGetSchemaSnafu)?)
+        let schema = columns_meta_to_schema(columns_meta, self.unsigned_bigint)
+            .context(super::UnableToGetSchemaSnafu)?;
+        Ok(self.timestamp_policy.apply_to_schema(schema))
     }
 
     async fn query_arrow(
@@ -185,54 +286,19 @@ impl<'a> AsyncDbConnection<Conn, &'a (dyn ToValue + Sync)> for MySQLConnection {
         projected_schema: Option<SchemaRef>,
     ) -> Result<SendableRecordBatchStream> {
         let params_vec: Vec<_> = params.iter().map(|&p| p.to_value()).collect();
-        let sql = sql.replace('"', "");
-
-        let conn = Arc::clone(&self.conn);
-
-        let mut stream = Box::pin(stream! {
-            let mut conn = conn.lock().await;
-            let mut exec_iter = conn
-                .exec_iter(sql, Params::from(params_vec))
-                .await
-                .context(QuerySnafu)?;
-
-            let Some(stream) = exec_iter.stream::<Row>().await.context(QuerySnafu)? else {
-                yield Err(Error::QueryResultStreamError {});
-                return;
-            };
-
-            let mut chunked_stream = stream.chunks(4_000).boxed();
-
-            while let Some(chunk) = chunked_stream.next().await {
-                let rows = chunk
-                    .into_iter()
-                    .collect::<Result<Vec<_>, _>>()
-                    .context(QuerySnafu)?;
-
-                let rec = rows_to_arrow(&rows, &projected_schema).context(ConversionSnafu)?;
-                yield Ok::<_, Error>(rec)
-            }
-        });
-
-        let Some(first_chunk) = stream.next().await else {
-            return Ok(Box::pin(RecordBatchStreamAdapter::new(
-                Arc::new(Schema::empty()),
-                stream::empty(),
-            )));
-        };
-
-        let first_chunk = first_chunk?;
-        let schema = first_chunk.schema();
+        self.query_values_arrow(sql, params_vec, projected_schema)
+            .await
+    }
 
-        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, {
-            stream! {
-                yield Ok(first_chunk);
-                while let Some(batch) = stream.next().await {
-                    yield batch
-                        .map_err(|e| DataFusionError::Execution(format!("Failed to fetch batch: {e}")))
-                }
-            }
-        })))
+    async fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        let params_vec: Vec<_> = params.iter().map(ToValue::to_value).collect();
+        self.query_values_arrow(sql, params_vec, projected_schema)
+            .await
     }
 
     async fn execute(&self, query: &str, params: &[&'a (dyn ToValue + Sync)]) -> Result<u64> {
@@ -247,7 +313,10 @@ impl<'a> AsyncDbConnection<Conn, &'a (dyn ToValue + Sync)> for MySQLConnection {
     }
 }
 
-fn columns_meta_to_schema(columns_meta: Vec<Row>) -> Result<SchemaRef> {
+fn columns_meta_to_schema(
+    columns_meta: Vec<Row>,
+    unsigned_bigint: UnsignedBigIntPolicy,
+) -> Result<SchemaRef> {
     let mut fields = Vec::new();
 
     for row in columns_meta.iter() {
@@ -263,6 +332,7 @@ fn columns_meta_to_schema(columns_meta: Vec<Row>) -> Result<SchemaRef> {
         let column_is_binary = map_str_type_to_is_binary(&data_type);
         let column_is_enum = map_str_type_to_is_enum(&data_type);
         let column_use_large_str_or_blob = map_str_type_to_use_large_str_or_blob(&data_type);
+        let column_is_unsigned = map_str_type_to_is_unsigned(&data_type);
 
         let (precision, scale) = match column_type {
             ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
@@ -278,6 +348,8 @@ fn columns_meta_to_schema(columns_meta: Vec<Row>) -> Result<SchemaRef> {
             column_is_binary,
             column_is_enum,
             column_use_large_str_or_blob,
+            column_is_unsigned,
+            unsigned_bigint,
             precision,
             scale,
         )
@@ -342,6 +414,10 @@ fn map_str_type_to_column_type(column_name: &str, data_type: &str) -> Result<Col
     Ok(column_type)
 }
 
+fn map_str_type_to_is_unsigned(data_type: &str) -> bool {
+    data_type.to_lowercase().contains("unsigned")
+}
+
 fn map_str_type_to_is_binary(data_type: &str) -> bool {
     if data_type.starts_with("binary")
         | data_type.starts_with("varbinary")
@@ -419,4 +495,12 @@ mod tests {
             assert_eq!(scale, expected_scale, "Incorrect scale for: {}", data_type);
         }
     }
+
+    #[test]
+    fn test_map_str_type_to_is_unsigned() {
+        assert!(map_str_type_to_is_unsigned("bigint unsigned"));
+        assert!(map_str_type_to_is_unsigned("INT(10) UNSIGNED ZEROFILL"));
+        assert!(!map_str_type_to_is_unsigned("bigint"));
+        assert!(!map_str_type_to_is_unsigned("decimal(10,2)"));
+    }
 }
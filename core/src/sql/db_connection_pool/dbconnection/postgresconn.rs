@@ -1,10 +1,14 @@
 use std::any::Any;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crate::sql::arrow_sql_gen::postgres::rows_to_arrow;
+use crate::sql::arrow_sql_gen::enum_type::EnumType;
 use crate::sql::arrow_sql_gen::postgres::schema::pg_data_type_to_arrow_type;
 use crate::sql::arrow_sql_gen::postgres::schema::ParseContext;
+use crate::sql::arrow_sql_gen::postgres::{rows_to_arrow, CopyRow, PostgresRow};
+use crate::sql::arrow_sql_gen::timestamp::TimestampPolicy;
+use crate::sql::arrow_sql_gen::uuid_format::{uuid_field, UuidFormat};
 use crate::util::handle_unsupported_type_error;
 use crate::util::schema::SchemaValidator;
 use arrow::datatypes::Field;
@@ -12,6 +16,7 @@ use arrow::datatypes::Schema;
 use arrow::datatypes::SchemaRef;
 use arrow_schema::DataType;
 use async_stream::stream;
+use bb8_postgres::tokio_postgres::binary_copy::BinaryCopyOutStream;
 use bb8_postgres::tokio_postgres::types::ToSql;
 use bb8_postgres::PostgresConnectionManager;
 use datafusion::error::DataFusionError;
@@ -19,6 +24,8 @@ use datafusion::execution::SendableRecordBatchStream;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::sql::TableReference;
 use futures::stream;
+use futures::FutureExt;
+use futures::Stream;
 use futures::StreamExt;
 use postgres_native_tls::MakeTlsConnector;
 use snafu::prelude::*;
@@ -38,6 +45,7 @@ CASE
     WHEN t.typtype = 'e' THEN
         jsonb_build_object(
             'type', 'enum',
+            'name', t.typname,
             'values', (
                 SELECT jsonb_agg(e.enumlabel ORDER BY e.enumsortorder)
                 FROM pg_enum e
@@ -97,7 +105,7 @@ LEFT JOIN pg_type t ON t.oid = a.atttypid
 LEFT JOIN custom_type_details custom ON custom.typname = t.typname
 WHERE ns.nspname = $1
     AND cls.relname = $2
-    AND cls.relkind IN ('r','v','m')  -- covers tables, normal views, & materialized views
+    AND cls.relkind IN ('r','p','f','v','m')  -- covers tables, partitioned & foreign tables, normal views, & materialized views
     AND a.attnum > 0
     AND NOT a.attisdropped
 ORDER BY a.attnum;
@@ -129,11 +137,46 @@ pub enum PostgresError {
     ConversionError {
         source: crate::sql::arrow_sql_gen::postgres::Error,
     },
+
+    #[snafu(display("Failed to apply the timestamp policy to the query result.\n{source}"))]
+    TimestampPolicyError {
+        source: datafusion::arrow::error::ArrowError,
+    },
+}
+
+/// How the results of queries without parameters are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadProtocol {
+    /// Row by row with the extended query protocol.
+    #[default]
+    Extended,
+    /// With `COPY (...) TO STDOUT (FORMAT binary)`, which streams the rows without a message per
+    /// row and is much faster for large scans. Queries with parameters still use the extended
+    /// protocol, which binds them.
+    BinaryCopy,
+}
+
+impl ReadProtocol {
+    /// Parses the `read_protocol` parameter of a pool: `extended` or `binary_copy`.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "extended" => Some(Self::Extended),
+            "binary_copy" => Some(Self::BinaryCopy),
+            _ => None,
+        }
+    }
 }
 
 pub struct PostgresConnection {
     pub conn: bb8::PooledConnection<'static, PostgresConnectionManager<MakeTlsConnector>>,
     unsupported_type_action: UnsupportedTypeAction,
+    timestamp_policy: TimestampPolicy,
+    uuid_format: UuidFormat,
+    read_protocol: ReadProtocol,
+    /// Whether the session variables of the next query opened a transaction, which ends once the
+    /// query has been sent.
+    in_transaction: AtomicBool,
 }
 
 impl SchemaValidator for PostgresConnection {
@@ -190,6 +233,10 @@ impl<'a>
         PostgresConnection {
             conn,
             unsupported_type_action: UnsupportedTypeAction::default(),
+            timestamp_policy: TimestampPolicy::default(),
+            uuid_format: UuidFormat::default(),
+            read_protocol: ReadProtocol::default(),
+            in_transaction: AtomicBool::new(false),
         }
     }
 
@@ -254,8 +301,15 @@ impl<'a>
             let nullable_str = row.get::<usize, String>(2);
             let nullable = nullable_str == "YES";
             let type_details = row.get::<usize, Option<serde_json::Value>>(3);
-            let mut context =
-                ParseContext::new().with_unsupported_type_action(self.unsupported_type_action);
+            let mut context = ParseContext::new()
+                .with_unsupported_type_action(self.unsupported_type_action)
+                .with_uuid_format(self.uuid_format);
+            let enum_type = match &type_details {
+                Some(type_details) if pg_type == "enum" => {
+                    serde_json::from_value::<EnumType>(type_details.clone()).ok()
+                }
+                _ => None,
+            };
 
             if let Some(type_details) = type_details {
                 context = context.with_type_details(type_details);
@@ -273,11 +327,21 @@ impl<'a>
                 continue;
             };
 
-            fields.push(Field::new(column_name, arrow_type, nullable));
+            if pg_type == "uuid" {
+                fields.push(uuid_field(column_name, arrow_type, nullable));
+            } else if let Some(enum_type) = enum_type {
+                fields.push(enum_type.apply_to_field(Field::new(
+                    column_name,
+                    arrow_type,
+                    nullable,
+                )));
+            } else {
+                fields.push(Field::new(column_name, arrow_type, nullable));
+            }
         }
 
         let schema = Arc::new(Schema::new(fields));
-        Ok(schema)
+        Ok(self.timestamp_policy.apply_to_schema(schema))
     }
 
     async fn query_arrow(
@@ -286,21 +350,144 @@ impl<'a>
         params: &[&'a (dyn ToSql + Sync)],
         projected_schema: Option<SchemaRef>,
     ) -> Result<SendableRecordBatchStream> {
-        // TODO: We should have a way to detect if params have been passed
-        // if they haven't we should use .copy_out instead, because it should be much faster
-        let streamable = self
+        self.query_raw_arrow(sql, params, projected_schema).await
+    }
+
+    async fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        let params = params
+            .iter()
+            .map(|param| param as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+        self.query_raw_arrow(sql, &params, projected_schema).await
+    }
+
+    /// Sets the variables in a transaction that ends with the next query, like `SET LOCAL`, so
+    /// that they never carry over to the later queries of the pooled connection.
+    async fn set_session_variables(&self, variables: &[(String, String)]) -> Result<()> {
+        if variables.is_empty() {
+            return Ok(());
+        }
+        if !self.in_transaction.swap(true, Ordering::SeqCst) {
+            if let Err(e) = self.conn.batch_execute("BEGIN").await {
+                self.in_transaction.store(false, Ordering::SeqCst);
+                return Err(e.into());
+            }
+        }
+        for (name, value) in variables {
+            let set = self
+                .conn
+                .execute("SELECT set_config($1, $2, true)", &[name, value])
+                .await;
+            if let Err(e) = set {
+                self.end_transaction();
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, sql: &str, params: &[&'a (dyn ToSql + Sync)]) -> Result<u64> {
+        Ok(self.conn.execute(sql, params).await?)
+    }
+}
+
+impl Drop for PostgresConnection {
+    fn drop(&mut self) {
+        // the variables were set, but no query was sent
+        self.end_transaction();
+    }
+}
+
+impl PostgresConnection {
+    async fn query_raw_arrow(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        let stream = self
+            .query_raw_arrow_in_transaction(sql, params, projected_schema)
+            .await;
+        self.end_transaction();
+        stream
+    }
+
+    /// Ends the transaction of the session variables, if they opened one, without waiting for it.
+    ///
+    /// tokio-postgres sends a request when its future is first polled, and the server runs the
+    /// requests of a connection in order, so the `COMMIT` runs once the rows of the query have been
+    /// read, and before the queries of the next user of the pooled connection. A failed query has
+    /// aborted the transaction, which the `COMMIT` then rolls back.
+    fn end_transaction(&self) {
+        if self.in_transaction.swap(false, Ordering::SeqCst) {
+            let _ = self.conn.batch_execute("COMMIT").now_or_never();
+        }
+    }
+
+    async fn query_raw_arrow_in_transaction(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        // The rows are read in binary either way: the extended query protocol used by .query_raw
+        // requests the binary format for every result column, and COPY writes the same binary
+        // values, which rows_to_arrow decodes with FromSql. Only types whose binary format is
+        // text, like json and enums, are read as strings.
+        if params.is_empty() && self.read_protocol == ReadProtocol::BinaryCopy {
+            // the types of the columns are needed to decode the COPY stream
+            let statement = self.conn.prepare(sql).await.context(QuerySnafu)?;
+            let types: Vec<_> = statement
+                .columns()
+                .iter()
+                .map(|column| column.type_().clone())
+                .collect();
+            let copy_sql = format!(
+                "COPY ({}) TO STDOUT (FORMAT binary)",
+                sql.trim_end().trim_end_matches(';')
+            );
+            let copy = self
+                .conn
+                .copy_out(copy_sql.as_str())
+                .await
+                .context(QuerySnafu)?;
+            let rows = BinaryCopyOutStream::new(copy, &types)
+                .map(move |row| row.map(|row| CopyRow::new(row, statement.clone())));
+            return self.rows_to_stream(rows, projected_schema).await;
+        }
+
+        let rows = self
             .conn
             .query_raw(sql, params.iter().copied()) // use .query_raw to get access to the underlying RowStream
             .await
             .context(QuerySnafu)?;
+        self.rows_to_stream(rows, projected_schema).await
+    }
 
+    /// Converts a stream of rows to a stream of record batches.
+    async fn rows_to_stream<R: PostgresRow + Send + 'static>(
+        &self,
+        rows: impl Stream<Item = std::result::Result<R, bb8_postgres::tokio_postgres::Error>>
+            + Send
+            + 'static,
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
         // chunk the stream into groups of rows
-        let mut stream = streamable.chunks(4_000).boxed().map(move |rows| {
+        let timestamp_policy = self.timestamp_policy;
+        let mut stream = rows.chunks(4_000).boxed().map(move |rows| {
             let rows = rows
                 .into_iter()
                 .collect::<std::result::Result<Vec<_>, _>>()
                 .context(QuerySnafu)?;
             let rec = rows_to_arrow(rows.as_slice(), &projected_schema).context(ConversionSnafu)?;
+            let rec = timestamp_policy
+                .apply_to_batch(rec)
+                .context(TimestampPolicySnafu)?;
             Ok::<_, PostgresError>(rec)
         });
 
@@ -334,15 +521,49 @@ impl<'a>
         )))
     }
 
-    async fn execute(&self, sql: &str, params: &[&'a (dyn ToSql + Sync)]) -> Result<u64> {
-        Ok(self.conn.execute(sql, params).await?)
-    }
-}
-
-impl PostgresConnection {
     #[must_use]
     pub fn with_unsupported_type_action(mut self, action: UnsupportedTypeAction) -> Self {
         self.unsupported_type_action = action;
         self
     }
+
+    /// Sets the time zone the timestamp columns are surfaced with.
+    #[must_use]
+    pub fn with_timestamp_policy(mut self, timestamp_policy: TimestampPolicy) -> Self {
+        self.timestamp_policy = timestamp_policy;
+        self
+    }
+
+    /// Sets the Arrow type of the UUID columns, `Utf8` by default.
+    #[must_use]
+    pub fn with_uuid_format(mut self, uuid_format: UuidFormat) -> Self {
+        self.uuid_format = uuid_format;
+        self
+    }
+
+    /// Sets how the results of queries without parameters are read, with the extended protocol by
+    /// default.
+    #[must_use]
+    pub fn with_read_protocol(mut self, read_protocol: ReadProtocol) -> Self {
+        self.read_protocol = read_protocol;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_read_protocol() {
+        assert_eq!(
+            ReadProtocol::parse("extended"),
+            Some(ReadProtocol::Extended)
+        );
+        assert_eq!(
+            ReadProtocol::parse("binary_copy"),
+            Some(ReadProtocol::BinaryCopy)
+        );
+        assert_eq!(ReadProtocol::parse("copy"), None);
+    }
 }
@@ -89,13 +89,17 @@ impl AsyncDbConnection<Connection, &'static (dyn ToSql + Sync)> for SqliteConnec
         SqliteConnection { conn }
     }
 
-    async fn tables(&self, _schema: &str) -> Result<Vec<String>, super::Error> {
+    async fn tables(&self, schema: &str) -> Result<Vec<String>, super::Error> {
+        // the tables of attached databases are listed in their own `sqlite_master`
+        let schema = if schema.is_empty() { "main" } else { schema };
+        let sql = format!(
+            "SELECT name FROM \"{}\".sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
+            schema.replace('"', "\"\"")
+        );
         let tables = self
             .conn
             .call(move |conn| {
-                let mut stmt = conn.prepare(
-                    "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
-                )?;
+                let mut stmt = conn.prepare(&sql)?;
                 let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
                 let tables: Result<Vec<_>, rusqlite::Error> = rows.collect();
                 Ok(tables?)
@@ -108,7 +112,21 @@ impl AsyncDbConnection<Connection, &'static (dyn ToSql + Sync)> for SqliteConnec
     }
 
     async fn schemas(&self) -> Result<Vec<String>, super::Error> {
-        Ok(vec!["main".to_string()])
+        // `main` and the attached databases, whose tables are qualified with their name
+        let schemas = self
+            .conn
+            .call(move |conn| {
+                let mut stmt =
+                    conn.prepare("SELECT name FROM pragma_database_list WHERE name != 'temp'")?;
+                let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+                let schemas: Result<Vec<_>, rusqlite::Error> = rows.collect();
+                Ok(schemas?)
+            })
+            .await
+            .boxed()
+            .context(super::UnableToGetSchemasSnafu)?;
+
+        Ok(schemas)
     }
 
     async fn get_schema(
@@ -141,9 +159,49 @@ impl AsyncDbConnection<Connection, &'static (dyn ToSql + Sync)> for SqliteConnec
         params: &[&'static (dyn ToSql + Sync)],
         projected_schema: Option<SchemaRef>,
     ) -> Result<SendableRecordBatchStream> {
+        self.query_bound_arrow(sql, params.to_vec(), projected_schema)
+            .await
+    }
+
+    async fn query_arrow_with_string_params(
+        &self,
+        sql: &str,
+        params: &[String],
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        self.query_bound_arrow(sql, params.to_vec(), projected_schema)
+            .await
+    }
+
+    async fn execute(&self, sql: &str, params: &[&'static (dyn ToSql + Sync)]) -> Result<u64> {
         let sql = sql.to_string();
         let params = params.to_vec();
 
+        let rows_modified = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(sql.as_str())?;
+                for (i, param) in params.iter().enumerate() {
+                    stmt.raw_bind_parameter(i + 1, param)?;
+                }
+                let rows_modified = stmt.raw_execute()?;
+                Ok(rows_modified)
+            })
+            .await
+            .context(ConnectionSnafu)?;
+        Ok(rows_modified as u64)
+    }
+}
+
+impl SqliteConnection {
+    async fn query_bound_arrow<P: ToSql + Send + 'static>(
+        &self,
+        sql: &str,
+        params: Vec<P>,
+        projected_schema: Option<SchemaRef>,
+    ) -> Result<SendableRecordBatchStream> {
+        let sql = sql.to_string();
+
         let rec = self
             .conn
             .call(move |conn| {
@@ -170,25 +228,6 @@ impl AsyncDbConnection<Connection, &'static (dyn ToSql + Sync)> for SqliteConnec
         };
         Ok(Box::pin(MemoryStream::try_new(recs, schema, None)?))
     }
-
-    async fn execute(&self, sql: &str, params: &[&'static (dyn ToSql + Sync)]) -> Result<u64> {
-        let sql = sql.to_string();
-        let params = params.to_vec();
-
-        let rows_modified = self
-            .conn
-            .call(move |conn| {
-                let mut stmt = conn.prepare(sql.as_str())?;
-                for (i, param) in params.iter().enumerate() {
-                    stmt.raw_bind_parameter(i + 1, param)?;
-                }
-                let rows_modified = stmt.raw_execute()?;
-                Ok(rows_modified)
-            })
-            .await
-            .context(ConnectionSnafu)?;
-        Ok(rows_modified as u64)
-    }
 }
 
 fn to_tokio_rusqlite_error(e: impl Into<Error>) -> tokio_rusqlite::Error {
@@ -0,0 +1,418 @@
+//! The maintenance of long-lived DuckDB database files, see [`DuckDbMaintenance`].
+//!
+//! DuckDB reuses the blocks freed by deletes and updates, but only gives the space back to the
+//! file system when the free blocks are at the end of the file. A file that was once much larger
+//! than its data stays large, until it is compacted by copying its data into a new file.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, PoisonError, Weak};
+use std::thread;
+use std::time::Duration;
+
+use duckdb::DuckdbConnectionManager;
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to start the DuckDB maintenance thread.\n{source}"))]
+    UnableToStartMaintenance { source: std::io::Error },
+
+    #[snafu(display("Unable to read the size of the DuckDB database.\n{source}"))]
+    UnableToReadDatabaseSize { source: duckdb::Error },
+
+    #[snafu(display("Unable to read the size of the DuckDB file {}.\n{source}", path.display()))]
+    UnableToReadFileSize {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Unable to checkpoint the DuckDB database.\n{source}\nThe checkpoint is retried at the next maintenance."))]
+    UnableToCheckpoint { source: duckdb::Error },
+
+    #[snafu(display("Unable to vacuum the DuckDB database.\n{source}"))]
+    UnableToVacuum { source: duckdb::Error },
+
+    #[snafu(display("Unable to compact the DuckDB file {path}.\n{source}"))]
+    UnableToCompact { path: String, source: duckdb::Error },
+
+    #[snafu(display(
+        "Unable to replace the DuckDB file {path} with its compacted copy.\n{source}"
+    ))]
+    UnableToReplaceFile {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The default interval between two maintenances.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// What the maintenance does when the database file is larger than its maximum size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MaxFileSizeAction {
+    /// Checkpoint the database, which truncates the free blocks at the end of the file.
+    #[default]
+    Checkpoint,
+    /// Compact the file into a new file when the pool of the file is built next, before it's
+    /// opened, see [`compact_file`]. Compacting reclaims all the free blocks, but can't be done
+    /// while the file is open.
+    CompactOnOpen,
+}
+
+/// The maintenance of the database file of a pool, run on a thread of its own every interval
+/// while the pool is open, see
+/// [`super::duckdbpool::DuckDbConnectionPoolBuilder::with_maintenance`].
+///
+/// Each maintenance checkpoints the database, so that the write-ahead log doesn't grow between
+/// the automatic checkpoints of DuckDB, and reads the size of the file, which is logged. With a
+/// maximum file size, the file is checkpointed or compacted once it grows beyond it.
+///
+/// ```rust,ignore
+/// let pool = DuckDbConnectionPoolBuilder::file("accelerated.db")
+///     .with_maintenance(Some(
+///         DuckDbMaintenance::new(Duration::from_secs(600))
+///             .with_max_file_size(Some(10 << 30), MaxFileSizeAction::CompactOnOpen),
+///     ))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuckDbMaintenance {
+    interval: Duration,
+    checkpoint: bool,
+    vacuum: bool,
+    max_file_size: Option<u64>,
+    max_file_size_action: MaxFileSizeAction,
+}
+
+impl Default for DuckDbMaintenance {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERVAL)
+    }
+}
+
+impl DuckDbMaintenance {
+    /// Runs the maintenance every `interval`, 5 minutes by default.
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            checkpoint: true,
+            vacuum: false,
+            max_file_size: None,
+            max_file_size_action: MaxFileSizeAction::default(),
+        }
+    }
+
+    /// Whether each maintenance checkpoints the database, `true` by default. A checkpoint fails
+    /// while another connection is writing, in which case it's retried at the next maintenance.
+    #[must_use]
+    pub fn with_checkpoint(mut self, checkpoint: bool) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Whether each maintenance rebuilds the statistics of the tables with `VACUUM ANALYZE`,
+    /// `false` by default.
+    #[must_use]
+    pub fn with_vacuum(mut self, vacuum: bool) -> Self {
+        self.vacuum = vacuum;
+        self
+    }
+
+    /// The size in bytes beyond which the file is reclaimed with `action`.
+    #[must_use]
+    pub fn with_max_file_size(
+        mut self,
+        max_file_size: Option<u64>,
+        action: MaxFileSizeAction,
+    ) -> Self {
+        self.max_file_size = max_file_size;
+        self.max_file_size_action = action;
+        self
+    }
+
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    #[must_use]
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.max_file_size
+    }
+
+    /// Whether the file is compacted before it's opened, when it's larger than its maximum size.
+    #[must_use]
+    pub fn compacts_on_open(&self) -> bool {
+        self.max_file_size.is_some()
+            && self.max_file_size_action == MaxFileSizeAction::CompactOnOpen
+    }
+
+    /// Runs the maintenance of the database of `conn`, whose file is `path`, and returns the size
+    /// of the database after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a step of the maintenance fails.
+    pub fn run(&self, conn: &duckdb::Connection, path: &Path) -> Result<DatabaseSize> {
+        if self.checkpoint {
+            conn.execute_batch("CHECKPOINT")
+                .context(UnableToCheckpointSnafu)?;
+        }
+        if self.vacuum {
+            conn.execute_batch("VACUUM ANALYZE")
+                .context(UnableToVacuumSnafu)?;
+        }
+
+        let mut size = DatabaseSize::read(conn, Some(path))?;
+        tracing::debug!("The DuckDB file {} has the size {size:?}", path.display());
+        let Some(max_file_size) = self.max_file_size.filter(|max| size.file_size > *max) else {
+            return Ok(size);
+        };
+        match self.max_file_size_action {
+            MaxFileSizeAction::Checkpoint => {
+                if !self.checkpoint {
+                    conn.execute_batch("CHECKPOINT")
+                        .context(UnableToCheckpointSnafu)?;
+                    size = DatabaseSize::read(conn, Some(path))?;
+                }
+                if size.file_size > max_file_size {
+                    tracing::warn!(
+                        "The DuckDB file {} is larger than {max_file_size} bytes after a checkpoint, with {} bytes in free blocks.",
+                        path.display(),
+                        size.free_bytes()
+                    );
+                }
+            }
+            MaxFileSizeAction::CompactOnOpen => {
+                tracing::warn!(
+                    "The DuckDB file {} is larger than {max_file_size} bytes, with {} bytes in free blocks. It is compacted when it's opened next.",
+                    path.display(),
+                    size.free_bytes()
+                );
+            }
+        }
+        Ok(size)
+    }
+}
+
+/// The size of a DuckDB database and of its file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DatabaseSize {
+    /// The size of the database file, 0 for in-memory databases.
+    pub file_size: u64,
+    /// The size of the write-ahead log of the file, 0 if there's none.
+    pub wal_size: u64,
+    pub block_size: u64,
+    pub total_blocks: u64,
+    /// The blocks that are reused by the next writes.
+    pub free_blocks: u64,
+}
+
+impl DatabaseSize {
+    /// Reads the size of the database of `conn`, whose file is `path`, if it has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size can't be queried or the size of the file can't be read.
+    pub fn read(conn: &duckdb::Connection, path: Option<&Path>) -> Result<Self> {
+        let (block_size, total_blocks, free_blocks) = conn
+            .query_row(
+                "SELECT block_size, total_blocks, free_blocks FROM pragma_database_size() WHERE database_name = current_database()",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+            )
+            .context(UnableToReadDatabaseSizeSnafu)?;
+        let (file_size, wal_size) = match path {
+            Some(path) => (
+                file_size(path)?,
+                file_size(&PathBuf::from(format!("{}.wal", path.display())))?,
+            ),
+            None => (0, 0),
+        };
+        Ok(Self {
+            file_size,
+            wal_size,
+            block_size: u64::try_from(block_size).unwrap_or_default(),
+            total_blocks: u64::try_from(total_blocks).unwrap_or_default(),
+            free_blocks: u64::try_from(free_blocks).unwrap_or_default(),
+        })
+    }
+
+    /// The bytes of the free blocks of the file.
+    #[must_use]
+    pub fn free_bytes(&self) -> u64 {
+        self.free_blocks.saturating_mul(self.block_size)
+    }
+}
+
+/// The size of the file at `path`, 0 if there's none.
+fn file_size(path: &Path) -> Result<u64> {
+    match std::fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(source) => Err(Error::UnableToReadFileSize {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
+
+/// Compacts the DuckDB file at `path` if it's larger than `max_file_size`, by copying its data into
+/// a new file that replaces it. The file must not be open. Returns whether it was compacted.
+///
+/// # Errors
+///
+/// Returns an error if the data can't be copied or the file can't be replaced, in which case the
+/// file is left as it was.
+pub fn compact_file(path: &str, max_file_size: u64) -> Result<bool> {
+    if file_size(Path::new(path))? <= max_file_size {
+        return Ok(false);
+    }
+    let compacted = format!("{path}.compacted");
+    let remove = |file: &str| match std::fs::remove_file(file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    };
+    // a copy left by a compaction that failed
+    remove(&compacted).context(UnableToReplaceFileSnafu { path })?;
+    remove(&format!("{compacted}.wal")).context(UnableToReplaceFileSnafu { path })?;
+
+    {
+        let conn = duckdb::Connection::open(path).context(UnableToCompactSnafu { path })?;
+        conn.execute_batch(&format!(
+            "ATTACH '{}' AS compaction; COPY FROM DATABASE {} TO compaction; DETACH compaction; CHECKPOINT;",
+            compacted.replace('\'', "''"),
+            quote_identifier(&current_database(&conn).context(UnableToCompactSnafu { path })?),
+        ))
+        .context(UnableToCompactSnafu { path })?;
+    }
+
+    // the log of the file was replayed into the copy, so it isn't replayed into the copy again
+    remove(&format!("{path}.wal")).context(UnableToReplaceFileSnafu { path })?;
+    std::fs::rename(&compacted, path).context(UnableToReplaceFileSnafu { path })?;
+    tracing::info!(
+        "Compacted the DuckDB file {path} to {} bytes",
+        file_size(Path::new(path))?
+    );
+    Ok(true)
+}
+
+fn current_database(conn: &duckdb::Connection) -> duckdb::Result<String> {
+    conn.query_row("SELECT current_database()", [], |row| row.get(0))
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The thread that runs the maintenance of a pool. It stops once the pool is closed or dropped.
+pub(crate) struct MaintenanceTask {
+    stop: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl std::fmt::Debug for MaintenanceTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaintenanceTask")
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+impl MaintenanceTask {
+    /// Starts the thread that runs `maintenance` on the connections of `pool`, whose file is
+    /// `path`.
+    pub(crate) fn start(
+        pool: Weak<r2d2::Pool<DuckdbConnectionManager>>,
+        path: PathBuf,
+        maintenance: DuckDbMaintenance,
+    ) -> Result<Self> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        thread::Builder::new()
+            .name("duckdb-maintenance".to_string())
+            .spawn(move || loop {
+                match stopped.recv_timeout(maintenance.interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let Some(pool) = pool.upgrade() else {
+                    break;
+                };
+                let result = pool
+                    .get()
+                    .map_err(|e| e.to_string())
+                    .and_then(|conn| maintenance.run(&conn, &path).map_err(|e| e.to_string()));
+                if let Err(e) = result {
+                    tracing::warn!(
+                        "The maintenance of the DuckDB file {} failed: {e}",
+                        path.display()
+                    );
+                }
+            })
+            .context(UnableToStartMaintenanceSnafu)?;
+        Ok(Self {
+            stop: Mutex::new(Some(stop)),
+        })
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.stop
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some()
+    }
+
+    /// Stops the thread, after the maintenance it's running if there's one.
+    pub(crate) fn stop(&self) {
+        self.stop
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+    }
+}
+
+impl Drop for MaintenanceTask {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_and_compaction() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("maintenance.db");
+        let path_str = path.to_str().ok_or("the path isn't UTF-8")?;
+
+        {
+            let conn = duckdb::Connection::open(&path)?;
+            conn.execute_batch(
+                "CREATE TABLE numbers AS SELECT range AS n FROM range(1000000);
+                 DELETE FROM numbers WHERE n >= 10;",
+            )?;
+            let size = DuckDbMaintenance::new(Duration::from_secs(60))
+                .with_vacuum(true)
+                .run(&conn, &path)?;
+            assert!(size.file_size > 0);
+            assert!(size.block_size > 0);
+            assert_eq!(size.wal_size, 0);
+        }
+
+        let size = file_size(&path)?;
+        assert!(!compact_file(path_str, size)?);
+        assert!(compact_file(path_str, 0)?);
+        assert!(file_size(&path)? <= size);
+        assert!(!Path::new(&format!("{path_str}.compacted")).exists());
+
+        let conn = duckdb::Connection::open(&path)?;
+        let count: i64 = conn.query_row("SELECT count(*) FROM numbers", [], |row| row.get(0))?;
+        assert_eq!(count, 10);
+        Ok(())
+    }
+}
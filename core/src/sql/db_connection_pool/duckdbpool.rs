@@ -1,10 +1,24 @@
 use async_trait::async_trait;
+use datafusion::{scalar::ScalarValue, sql::TableReference};
 use duckdb::{vtab::arrow::ArrowVTab, AccessMode, DuckdbConnectionManager};
+use secrecy::{ExposeSecret, SecretString};
 use snafu::{prelude::*, ResultExt};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError, Weak};
+use std::time::Duration;
 
 use super::{
-    dbconnection::duckdbconn::{DuckDBAttachments, DuckDBParameter},
+    dbconnection::duckdbconn::{
+        flatten_table_function_name, is_remote_path, motherduck_database, DuckDBAttachmentOptions,
+        DuckDBAttachmentRegistry, DuckDBAttachments, DuckDBParameter, MOTHERDUCK_PREFIX,
+    },
+    duckdbmaintenance::{self, compact_file, DatabaseSize, DuckDbMaintenance, MaintenanceTask},
+    duckdbviews::{bind_table_function, TableFunctionViews},
+    duckdbworkers::{self, DuckDbWorkers},
+    pool_options::PoolOptions,
+    query_limit::QueryPermit,
+    shutdown::QueryTracker,
     DbConnectionPool, Mode, Result,
 };
 use crate::{
@@ -29,37 +43,106 @@ pub enum Error {
         "Invalid DuckDB file path: {path}. Ensure it contains a valid database name."
     ))]
     UnableToExtractDatabaseNameFromPath { path: Arc<str> },
+
+    #[snafu(display("Unable to start the DuckDB worker threads.\n{source}"))]
+    UnableToStartWorkers { source: duckdbworkers::Error },
+
+    #[snafu(display("{source}"))]
+    Maintenance { source: duckdbmaintenance::Error },
+
+    #[snafu(display("Unable to open the remote DuckDB database {path}.\n{source}\nEnsure the URL is reachable and the credentials for it are configured, e.g. with CREATE SECRET."))]
+    UnableToOpenRemoteDatabase {
+        path: Arc<str>,
+        source: super::dbconnection::GenericError,
+    },
+}
+
+/// The in-memory databases that are shared by name, see [`DuckDbConnectionPoolBuilder::shared_memory`].
+/// An entry is removed once the last pool of the database is dropped.
+static SHARED_MEMORY_DATABASES: LazyLock<Mutex<HashMap<String, SharedMemoryDatabase>>> =
+    LazyLock::new(Mutex::default);
+
+struct SharedMemoryDatabase {
+    pool: Weak<r2d2::Pool<DuckdbConnectionManager>>,
+    attachment_registry: Weak<DuckDBAttachmentRegistry>,
 }
 
 pub struct DuckDbConnectionPoolBuilder {
     path: String,
-    max_size: Option<u32>,
+    options: PoolOptions,
     access_mode: AccessMode,
-    min_idle: Option<u32>,
     mode: Mode,
+    worker_threads: Option<usize>,
+    max_extra_worker_threads: Option<usize>,
+    motherduck_token: Option<SecretString>,
+    maintenance: Option<DuckDbMaintenance>,
 }
 
 impl DuckDbConnectionPoolBuilder {
     pub fn memory() -> Self {
         Self {
             path: String::default(),
-            max_size: None,
+            options: PoolOptions::default(),
             access_mode: AccessMode::ReadWrite,
-            min_idle: None,
             mode: Mode::Memory,
+            worker_threads: None,
+            max_extra_worker_threads: None,
+            motherduck_token: None,
+            maintenance: None,
+        }
+    }
+
+    /// An in-memory database that is shared by all pools built with the same `name` in this process,
+    /// like `:memory:name` in the DuckDB CLI. The connections of a single pool always share their
+    /// database, this also lets separately built pools read the same tables.
+    ///
+    /// The database is created with the settings of the first pool and lives until the last pool
+    /// using it is dropped. Later pools share its connections, with their own workers.
+    pub fn shared_memory(name: &str) -> Self {
+        Self {
+            path: name.to_string(),
+            ..Self::memory()
         }
     }
 
     pub fn file(path: &str) -> Self {
         Self {
             path: path.to_string(),
-            max_size: None,
+            options: PoolOptions::default(),
             access_mode: AccessMode::ReadWrite,
-            min_idle: None,
             mode: Mode::File,
+            worker_threads: None,
+            max_extra_worker_threads: None,
+            motherduck_token: None,
+            maintenance: None,
         }
     }
 
+    /// A MotherDuck database, opened through DuckDB's `motherduck` extension. `database` is the name
+    /// of the database, with or without the `md:` prefix, or empty for the default database of the
+    /// account.
+    ///
+    /// Without [`Self::with_motherduck_token`], the extension reads the token from the
+    /// `motherduck_token` environment variable.
+    pub fn motherduck(database: &str) -> Self {
+        let database = database.strip_prefix(MOTHERDUCK_PREFIX).unwrap_or(database);
+        Self::file(&format!("{MOTHERDUCK_PREFIX}{database}"))
+    }
+
+    /// Authenticates to MotherDuck with `token`, for pools of `md:` databases.
+    ///
+    /// The token is passed to DuckDB as a setting, so it doesn't need to be part of the path, which
+    /// is logged and used to identify the database.
+    pub fn with_motherduck_token(mut self, token: Option<SecretString>) -> Self {
+        self.motherduck_token = token;
+        self
+    }
+
+    /// Returns whether the pool connects to MotherDuck instead of a local file.
+    pub fn is_motherduck(&self) -> bool {
+        self.mode == Mode::File && motherduck_database(&self.path).is_some()
+    }
+
     pub fn get_path(&self) -> String {
         self.path.clone()
     }
@@ -69,7 +152,7 @@ impl DuckDbConnectionPoolBuilder {
     }
 
     pub fn with_max_size(mut self, size: Option<u32>) -> Self {
-        self.max_size = size;
+        self.options = self.options.with_max_size(size);
         self
     }
 
@@ -79,57 +162,205 @@ impl DuckDbConnectionPoolBuilder {
     }
 
     pub fn with_min_idle(mut self, min_idle: Option<u32>) -> Self {
-        self.min_idle = min_idle;
+        self.options = self.options.with_min_idle(min_idle);
         self
     }
 
-    fn build_memory_pool(&self) -> Result<DuckDbConnectionPool> {
-        let config = get_config(&AccessMode::ReadWrite)?;
-        let manager =
-            DuckdbConnectionManager::memory_with_flags(config).context(DuckDBConnectionSnafu)?;
+    /// How long a connection stays idle before it's closed, see [`PoolOptions::with_idle_timeout`].
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.options = self.options.with_idle_timeout(idle_timeout);
+        self
+    }
+
+    /// How long a connection is used before it's replaced, see [`PoolOptions::with_max_lifetime`].
+    pub fn with_max_lifetime(mut self, max_lifetime: Option<Duration>) -> Self {
+        self.options = self.options.with_max_lifetime(max_lifetime);
+        self
+    }
+
+    /// Sets all the options of the pool at once, replacing the ones that were set before.
+    pub fn with_pool_options(mut self, options: PoolOptions) -> Self {
+        self.options = options;
+        self
+    }
 
+    /// The builder of the pool, which waits for the `min_idle` connections to be opened when the
+    /// pool is built.
+    fn pool_builder(&self) -> r2d2::Builder<DuckdbConnectionManager> {
         let mut pool_builder = r2d2::Pool::builder();
 
-        if let Some(size) = self.max_size {
+        if let Some(size) = self.options.max_size() {
             pool_builder = pool_builder.max_size(size)
         }
-        if self.min_idle.is_some() {
-            pool_builder = pool_builder.min_idle(self.min_idle)
+        if self.options.min_idle().is_some() {
+            pool_builder = pool_builder.min_idle(self.options.min_idle())
+        }
+        if let Some(idle_timeout) = self.options.idle_timeout() {
+            pool_builder = pool_builder.idle_timeout(Some(idle_timeout))
+        }
+        if let Some(max_lifetime) = self.options.max_lifetime() {
+            pool_builder = pool_builder.max_lifetime(Some(max_lifetime))
         }
+        pool_builder
+    }
 
-        let pool = Arc::new(pool_builder.build(manager).context(ConnectionPoolSnafu)?);
+    /// Runs queries on `threads` dedicated threads instead of Tokio's blocking thread pool.
+    ///
+    /// The threads are sized separately from the pool, because a connection can run any number of
+    /// queries at once. Queries that start while every thread is busy run on a temporary thread,
+    /// up to [`Self::with_max_extra_worker_threads`] of them. See [`DuckDbWorkers`].
+    pub fn with_dedicated_workers(mut self, threads: Option<usize>) -> Self {
+        self.worker_threads = threads;
+        self
+    }
 
-        let conn = pool.get().context(ConnectionPoolSnafu)?;
-        conn.register_table_function::<ArrowVTab>("arrow")
-            .context(DuckDBConnectionSnafu)?;
+    /// Runs at most `max_extra_threads` queries on temporary threads while every dedicated thread
+    /// is busy, as many as there are dedicated threads by default. Queries that start while these
+    /// are busy too wait for a thread, so the threads must cover the scans that a query reads at
+    /// once. See [`DuckDbWorkers::with_max_extra_threads`].
+    pub fn with_max_extra_worker_threads(mut self, max_extra_threads: Option<usize>) -> Self {
+        self.max_extra_worker_threads = max_extra_threads;
+        self
+    }
 
-        test_connection(&conn)?;
+    /// Runs `maintenance` on the database file every interval while the pool is open, see
+    /// [`DuckDbMaintenance`]. The file is compacted before it's opened if it's larger than the
+    /// maximum size of [`super::duckdbmaintenance::MaxFileSizeAction::CompactOnOpen`]. In-memory,
+    /// read-only and remote databases have no maintenance.
+    pub fn with_maintenance(mut self, maintenance: Option<DuckDbMaintenance>) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    /// The maintenance of the file, unless it can't be written to.
+    fn file_maintenance(&self) -> Option<&DuckDbMaintenance> {
+        self.maintenance
+            .as_ref()
+            .filter(|_| !self.is_motherduck() && !matches!(self.access_mode, AccessMode::ReadOnly))
+    }
+
+    fn build_workers(&self) -> Result<Option<Arc<DuckDbWorkers>>> {
+        let Some(threads) = self.worker_threads else {
+            return Ok(None);
+        };
+        let mut workers = DuckDbWorkers::new(threads).context(UnableToStartWorkersSnafu)?;
+        if let Some(max_extra_threads) = self.max_extra_worker_threads {
+            workers = workers.with_max_extra_threads(max_extra_threads);
+        }
+        Ok(Some(Arc::new(workers)))
+    }
+
+    fn build_memory_pool(&self) -> Result<DuckDbConnectionPool> {
+        let (path, pool, attachment_registry) = if self.path.is_empty() {
+            (
+                ":memory:".to_string(),
+                self.build_memory_database()?,
+                Arc::new(DuckDBAttachmentRegistry::new()),
+            )
+        } else {
+            let (pool, attachment_registry) = self.shared_memory_database()?;
+            (format!(":memory:{}", self.path), pool, attachment_registry)
+        };
+
+        let workers = self.build_workers()?;
 
         Ok(DuckDbConnectionPool {
-            path: ":memory:".into(),
+            path: path.as_str().into(),
             pool,
-            join_push_down: JoinPushDown::AllowedFor(":memory:".to_string()),
+            join_push_down: JoinPushDown::AllowedFor(path),
             attached_databases: Vec::new(),
+            remote_database: None,
             mode: Mode::Memory,
             unsupported_type_action: UnsupportedTypeAction::Error,
+            workers,
+            queries: QueryTracker::new(),
+            attachment_registry,
+            table_function_views: Arc::new(TableFunctionViews::new()),
+            maintenance: None,
         })
     }
 
-    fn build_file_pool(&self) -> Result<DuckDbConnectionPool> {
-        let config = get_config(&self.access_mode)?;
-        let manager = DuckdbConnectionManager::file_with_flags(&self.path, config)
+    /// Returns the pool and attachments of the shared in-memory database, creating it if this is
+    /// the first pool that uses it.
+    fn shared_memory_database(
+        &self,
+    ) -> Result<(
+        Arc<r2d2::Pool<DuckdbConnectionManager>>,
+        Arc<DuckDBAttachmentRegistry>,
+    )> {
+        let mut databases = SHARED_MEMORY_DATABASES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        databases.retain(|_, database| database.pool.strong_count() > 0);
+
+        if let Some(database) = databases.get(&self.path) {
+            if let (Some(pool), Some(attachment_registry)) = (
+                database.pool.upgrade(),
+                database.attachment_registry.upgrade(),
+            ) {
+                return Ok((pool, attachment_registry));
+            }
+        }
+
+        let pool = self.build_memory_database()?;
+        let attachment_registry = Arc::new(DuckDBAttachmentRegistry::new());
+        databases.insert(
+            self.path.clone(),
+            SharedMemoryDatabase {
+                pool: Arc::downgrade(&pool),
+                attachment_registry: Arc::downgrade(&attachment_registry),
+            },
+        );
+        Ok((pool, attachment_registry))
+    }
+
+    fn build_memory_database(&self) -> Result<Arc<r2d2::Pool<DuckdbConnectionManager>>> {
+        let config = get_config(&AccessMode::ReadWrite)?;
+        let manager =
+            DuckdbConnectionManager::memory_with_flags(config).context(DuckDBConnectionSnafu)?;
+
+        let pool = Arc::new(
+            self.pool_builder()
+                .build(manager)
+                .context(ConnectionPoolSnafu)?,
+        );
+
+        let conn = pool.get().context(ConnectionPoolSnafu)?;
+        conn.register_table_function::<ArrowVTab>("arrow")
             .context(DuckDBConnectionSnafu)?;
 
-        let mut pool_builder = r2d2::Pool::builder();
+        test_connection(&conn)?;
 
-        if let Some(size) = self.max_size {
-            pool_builder = pool_builder.max_size(size)
+        Ok(pool)
+    }
+
+    fn build_file_pool(&self) -> Result<DuckDbConnectionPool> {
+        if let Some(max_file_size) = self
+            .file_maintenance()
+            .filter(|maintenance| maintenance.compacts_on_open())
+            .and_then(DuckDbMaintenance::max_file_size)
+        {
+            compact_file(&self.path, max_file_size).context(MaintenanceSnafu)?;
         }
-        if self.min_idle.is_some() {
-            pool_builder = pool_builder.min_idle(self.min_idle)
+
+        let mut config = get_config(&self.access_mode)?;
+        if let Some(token) = self
+            .motherduck_token
+            .as_ref()
+            .filter(|_| self.is_motherduck())
+        {
+            config = config
+                .with("motherduck_token", token.expose_secret())
+                .context(DuckDBConnectionSnafu)?;
         }
+        let manager = DuckdbConnectionManager::file_with_flags(&self.path, config)
+            .context(DuckDBConnectionSnafu)?;
 
-        let pool = Arc::new(pool_builder.build(manager).context(ConnectionPoolSnafu)?);
+        let pool = Arc::new(
+            self.pool_builder()
+                .build(manager)
+                .context(ConnectionPoolSnafu)?,
+        );
 
         let conn = pool.get().context(ConnectionPoolSnafu)?;
         conn.register_table_function::<ArrowVTab>("arrow")
@@ -137,20 +368,86 @@ impl DuckDbConnectionPoolBuilder {
 
         test_connection(&conn)?;
 
+        let workers = self.build_workers()?;
+        let maintenance = self
+            .file_maintenance()
+            .map(|maintenance| {
+                MaintenanceTask::start(
+                    Arc::downgrade(&pool),
+                    PathBuf::from(&self.path),
+                    maintenance.clone(),
+                )
+                .map(Arc::new)
+            })
+            .transpose()
+            .context(MaintenanceSnafu)?;
+
         Ok(DuckDbConnectionPool {
             path: self.path.as_str().into(),
             pool,
             // Allow join-push down for any other instances that connect to the same underlying file.
             join_push_down: JoinPushDown::AllowedFor(self.path.clone()),
             attached_databases: Vec::new(),
+            remote_database: None,
+            mode: Mode::File,
+            unsupported_type_action: UnsupportedTypeAction::Error,
+            workers,
+            queries: QueryTracker::new(),
+            attachment_registry: Arc::new(DuckDBAttachmentRegistry::new()),
+            table_function_views: Arc::new(TableFunctionViews::new()),
+            maintenance,
+        })
+    }
+
+    /// Opens a database file in object storage or on a web server, which DuckDB can only attach
+    /// read-only. The file is attached to an in-memory database through the `httpfs` extension and
+    /// is kept attached for the lifetime of the pool, so it is not fetched again for every query.
+    fn build_remote_pool(&self) -> Result<DuckDbConnectionPool> {
+        let pool = self.build_memory_database()?;
+        let path: Arc<str> = self.path.as_str().into();
+
+        let conn = pool.get().context(ConnectionPoolSnafu)?;
+        conn.execute_batch("INSTALL httpfs; LOAD httpfs;")
+            .context(DuckDBConnectionSnafu)?;
+
+        let attachment_registry = Arc::new(DuckDBAttachmentRegistry::new());
+        attachment_registry.set_keep_attached(true);
+        let remote_database = (
+            Arc::clone(&path),
+            DuckDBAttachmentOptions::new().with_alias(remote_database_alias(&path)?),
+        );
+        DuckDBAttachments::new_with_options(
+            REMOTE_MAIN_DATABASE,
+            std::slice::from_ref(&remote_database),
+            Arc::clone(&attachment_registry),
+        )
+        .attach_for_connection(&conn)
+        .context(UnableToOpenRemoteDatabaseSnafu {
+            path: Arc::clone(&path),
+        })?;
+
+        let workers = self.build_workers()?;
+
+        Ok(DuckDbConnectionPool {
+            path: Arc::clone(&path),
+            pool,
+            join_push_down: JoinPushDown::AllowedFor(self.path.clone()),
+            attached_databases: Vec::new(),
+            remote_database: Some(remote_database),
             mode: Mode::File,
             unsupported_type_action: UnsupportedTypeAction::Error,
+            workers,
+            queries: QueryTracker::new(),
+            attachment_registry,
+            table_function_views: Arc::new(TableFunctionViews::new()),
+            maintenance: None,
         })
     }
 
     pub fn build(self) -> Result<DuckDbConnectionPool> {
         match self.mode {
             Mode::Memory => self.build_memory_pool(),
+            Mode::File if is_remote_path(&self.path) => self.build_remote_pool(),
             Mode::File => self.build_file_pool(),
         }
     }
@@ -161,9 +458,16 @@ pub struct DuckDbConnectionPool {
     path: Arc<str>,
     pool: Arc<r2d2::Pool<DuckdbConnectionManager>>,
     join_push_down: JoinPushDown,
-    attached_databases: Vec<Arc<str>>,
+    attached_databases: Vec<(Arc<str>, DuckDBAttachmentOptions)>,
+    /// The database file a remote pool reads, which is attached to its in-memory database.
+    remote_database: Option<(Arc<str>, DuckDBAttachmentOptions)>,
     mode: Mode,
     unsupported_type_action: UnsupportedTypeAction,
+    workers: Option<Arc<DuckDbWorkers>>,
+    attachment_registry: Arc<DuckDBAttachmentRegistry>,
+    queries: QueryTracker,
+    table_function_views: Arc<TableFunctionViews>,
+    maintenance: Option<Arc<MaintenanceTask>>,
 }
 
 impl std::fmt::Debug for DuckDbConnectionPool {
@@ -172,8 +476,12 @@ impl std::fmt::Debug for DuckDbConnectionPool {
             .field("path", &self.path)
             .field("join_push_down", &self.join_push_down)
             .field("attached_databases", &self.attached_databases)
+            .field("remote_database", &self.remote_database)
             .field("mode", &self.mode)
             .field("unsupported_type_action", &self.unsupported_type_action)
+            .field("workers", &self.workers)
+            .field("table_function_views", &self.table_function_views.names())
+            .field("maintenance", &self.maintenance)
             .finish()
     }
 }
@@ -202,8 +510,22 @@ impl DuckDbConnectionPool {
         DuckDbConnectionPoolBuilder::memory().build()
     }
 
+    /// Create a new `DuckDbConnectionPool` for the in-memory database `name`, which is shared with
+    /// the other pools of the same name, see [`DuckDbConnectionPoolBuilder::shared_memory`].
+    ///
+    /// # Errors
+    ///
+    /// * `DuckDBConnectionSnafu` - If there is an error creating the database
+    /// * `ConnectionPoolSnafu` - If there is an error creating the connection pool
+    pub fn new_shared_memory(name: &str) -> Result<Self> {
+        DuckDbConnectionPoolBuilder::shared_memory(name).build()
+    }
+
     /// Create a new `DuckDbConnectionPool` from a file.
     ///
+    /// The file can also be read from object storage or a web server with an `s3://`, `gs://` or
+    /// `https://` URL, in which case it is attached read-only and `access_mode` is not used.
+    ///
     /// # Arguments
     ///
     /// * `path` - The path to the file
@@ -234,12 +556,35 @@ impl DuckDbConnectionPool {
         self
     }
 
+    /// Sets the databases attached for the queries of this pool.
+    ///
+    /// Clones of a pool share its connections, so several table providers can each attach their own
+    /// databases to one DuckDB instance. The attachments are shared between the clones, see
+    /// [`DuckDBAttachmentRegistry`].
+    #[must_use]
+    pub fn set_attached_databases(self, databases: &[Arc<str>]) -> Self {
+        let databases = databases
+            .iter()
+            .map(|db| (Arc::clone(db), DuckDBAttachmentOptions::default()))
+            .collect::<Vec<_>>();
+        self.set_attached_databases_with_options(&databases)
+    }
+
+    /// Sets the databases attached for the queries of this pool, with the options each of them is
+    /// attached with, e.g. to attach SQLite files or to write to an attached database.
     #[must_use]
-    pub fn set_attached_databases(mut self, databases: &[Arc<str>]) -> Self {
+    pub fn set_attached_databases_with_options(
+        mut self,
+        databases: &[(Arc<str>, DuckDBAttachmentOptions)],
+    ) -> Self {
         self.attached_databases = databases.to_vec();
 
         if !databases.is_empty() {
-            let mut paths = self.attached_databases.clone();
+            let mut paths = self
+                .attached_databases
+                .iter()
+                .map(|(db, _)| Arc::clone(db))
+                .collect::<Vec<_>>();
             paths.push(Arc::clone(&self.path));
             paths.sort();
             let push_down_context = paths.join(";");
@@ -249,6 +594,16 @@ impl DuckDbConnectionPool {
         self
     }
 
+    /// Keeps the attached databases attached between queries instead of attaching them for every
+    /// query, see [`DuckDBAttachmentRegistry::set_keep_attached`].
+    ///
+    /// The setting applies to the DuckDB instance, so it is shared with the clones of the pool.
+    #[must_use]
+    pub fn keep_attached_databases(self, keep_attached: bool) -> Self {
+        self.attachment_registry.set_keep_attached(keep_attached);
+        self
+    }
+
     /// Create a new `DuckDbConnectionPool` from a database URL.
     ///
     /// # Errors
@@ -259,16 +614,24 @@ impl DuckDbConnectionPool {
     ) -> Result<
         Box<dyn DbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBParameter>>,
     > {
+        self.queries.ensure_open()?;
         let pool = Arc::clone(&self.pool);
         let conn: r2d2::PooledConnection<DuckdbConnectionManager> =
             pool.get().context(ConnectionPoolSnafu)?;
 
         let attachments = self.get_attachments()?;
+        if self.remote_database.is_some() {
+            if let Some(attachments) = &attachments {
+                // the schemas of the tables are read without attaching the databases per query
+                attachments.attach_for_connection(&conn)?;
+            }
+        }
 
         Ok(Box::new(
             DuckDbConnection::new(conn)
                 .with_attachments(attachments)
-                .with_unsupported_type_action(self.unsupported_type_action),
+                .with_unsupported_type_action(self.unsupported_type_action)
+                .with_workers(self.workers.clone()),
         ))
     }
 
@@ -277,7 +640,95 @@ impl DuckDbConnectionPool {
         self.mode
     }
 
+    /// Creates a view over `table_function`, a call of a DuckDB table function like
+    /// `read_csv_auto(?)`, with `params` bound to its placeholders, see [`bind_table_function`].
+    /// The view is named `name`, or after the bound table function with
+    /// [`flatten_table_function_name`], e.g. `read_csv_auto('sales.csv')` is read through
+    /// `readcsvauto_salescsv__view`.
+    ///
+    /// The view replaces the view of the same name, and is dropped with
+    /// [`Self::drop_table_function_view`] or when the pool is closed. Returns the name of the
+    /// view, the table reference to read it with, e.g. from
+    /// [`crate::duckdb::DuckDBTableFactory::table_provider`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parameters don't match the placeholders of the table function, or
+    /// if DuckDB can't create the view.
+    pub fn create_table_function_view(
+        &self,
+        name: Option<&str>,
+        table_function: &str,
+        params: &[ScalarValue],
+    ) -> Result<TableReference> {
+        self.queries.ensure_open()?;
+        let table_function = bind_table_function(table_function, params)?;
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => flatten_table_function_name(&TableReference::bare(table_function.as_str())),
+        };
+        let name = name.as_str();
+        let conn = self.pool.get().context(ConnectionPoolSnafu)?;
+        self.table_function_views
+            .create(&conn, name, &table_function)?;
+        tracing::debug!("Created the view '{name}' over {table_function}");
+        Ok(TableReference::bare(name))
+    }
+
+    /// Drops the view `name` created with [`Self::create_table_function_view`]. Returns whether
+    /// there was one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if DuckDB can't drop the view.
+    pub fn drop_table_function_view(&self, name: &str) -> Result<bool> {
+        let conn = self.pool.get().context(ConnectionPoolSnafu)?;
+        Ok(self.table_function_views.drop_view(&conn, name)?)
+    }
+
+    /// The names of the views created with [`Self::create_table_function_view`] through this pool
+    /// and its clones.
+    #[must_use]
+    pub fn table_function_views(&self) -> Vec<String> {
+        self.table_function_views.names()
+    }
+
+    /// The size of the database and of its file, see [`DatabaseSize`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size can't be read.
+    pub fn database_size(&self) -> Result<DatabaseSize> {
+        let conn = self.pool.get().context(ConnectionPoolSnafu)?;
+        let path = (self.mode == Mode::File
+            && self.remote_database.is_none()
+            && motherduck_database(&self.path).is_none())
+        .then(|| Path::new(&*self.path));
+        Ok(DatabaseSize::read(&conn, path)?)
+    }
+
+    fn drop_table_function_views(&self) -> Result<()> {
+        if self.table_function_views.names().is_empty() {
+            return Ok(());
+        }
+        let conn = self.pool.get().context(ConnectionPoolSnafu)?;
+        Ok(self.table_function_views.drop_all(&conn)?)
+    }
+
     pub fn get_attachments(&self) -> Result<Option<Arc<DuckDBAttachments>>> {
+        if let Some(remote_database) = &self.remote_database {
+            // the remote database is always attached, as the pool has no tables of its own
+            let mut attachments = vec![remote_database.clone()];
+            if cfg!(feature = "duckdb-federation") {
+                attachments.extend(self.attached_databases.iter().cloned());
+            }
+            return Ok(Some(Arc::new(DuckDBAttachments::new_with_options(
+                REMOTE_MAIN_DATABASE,
+                &attachments,
+                Arc::clone(&self.attachment_registry),
+            ))));
+        }
+
         if self.attached_databases.is_empty() {
             Ok(None)
         } else {
@@ -285,9 +736,10 @@ impl DuckDbConnectionPool {
             return Ok(None);
 
             #[cfg(feature = "duckdb-federation")]
-            Ok(Some(Arc::new(DuckDBAttachments::new(
+            Ok(Some(Arc::new(DuckDBAttachments::new_with_options(
                 &extract_db_name(Arc::clone(&self.path))?,
                 &self.attached_databases,
+                Arc::clone(&self.attachment_registry),
             ))))
         }
     }
@@ -302,22 +754,55 @@ impl DbConnectionPool<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBPar
     ) -> Result<
         Box<dyn DbConnection<r2d2::PooledConnection<DuckdbConnectionManager>, DuckDBParameter>>,
     > {
+        self.queries.ensure_open()?;
         let pool = Arc::clone(&self.pool);
         let conn: r2d2::PooledConnection<DuckdbConnectionManager> =
             pool.get().context(ConnectionPoolSnafu)?;
 
         let attachments = self.get_attachments()?;
+        if self.remote_database.is_some() {
+            if let Some(attachments) = &attachments {
+                // the schemas of the tables are read without attaching the databases per query
+                attachments.attach_for_connection(&conn)?;
+            }
+        }
 
         Ok(Box::new(
             DuckDbConnection::new(conn)
                 .with_attachments(attachments)
-                .with_unsupported_type_action(self.unsupported_type_action),
+                .with_unsupported_type_action(self.unsupported_type_action)
+                .with_workers(self.workers.clone()),
         ))
     }
 
     fn join_push_down(&self) -> JoinPushDown {
         self.join_push_down.clone()
     }
+
+    async fn acquire_query_permit(&self) -> Result<Option<QueryPermit>> {
+        Ok(Some(self.queries.start(None)?))
+    }
+
+    /// Waits for the queries in flight, then drops the views over table functions created through
+    /// the pool and stops its worker threads and its maintenance. The connections are closed once the last pool of the
+    /// database is dropped, as the pools of a database share its connections.
+    async fn close(&self, timeout: Option<Duration>) -> Result<()> {
+        let closed = self.queries.close(timeout).await;
+        // the views are kept while queries that may read them are still running
+        let dropped = if closed.is_ok() {
+            self.drop_table_function_views()
+        } else {
+            Ok(())
+        };
+        if let Some(workers) = &self.workers {
+            workers.stop();
+        }
+        if let Some(maintenance) = &self.maintenance {
+            maintenance.stop();
+        }
+        closed?;
+        dropped
+    }
 }
 
 fn test_connection(conn: &r2d2::PooledConnection<DuckdbConnectionManager>) -> Result<()> {
@@ -338,8 +823,29 @@ fn get_config(access_mode: &AccessMode) -> Result<duckdb::Config> {
     Ok(config)
 }
 
+/// The main database of remote pools, which is the in-memory database the remote file is attached to.
+const REMOTE_MAIN_DATABASE: &str = "memory";
+
+/// Returns the alias the remote database file is attached as, the name of the file with the
+/// characters that aren't valid in an alias replaced.
+fn remote_database_alias(path: &Arc<str>) -> Result<String> {
+    let name = extract_db_name(Arc::clone(path))?;
+    let mut alias = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !alias.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        alias.insert(0, '_');
+    }
+    Ok(alias)
+}
+
 // Helper function to extract the duckdb database name from the duckdb file path
 fn extract_db_name(file_path: Arc<str>) -> Result<String> {
+    if let Some(database) = motherduck_database(&file_path) {
+        return Ok(database.to_string());
+    }
+
     let path = std::path::Path::new(file_path.as_ref());
 
     let db_name = match path.file_stem().and_then(|name| name.to_str()) {
@@ -356,6 +862,7 @@ fn extract_db_name(file_path: Arc<str>) -> Result<String> {
 
 #[cfg(test)]
 mod test {
+    use futures::StreamExt;
     use rand::Rng;
 
     use super::*;
@@ -393,6 +900,200 @@ mod test {
             .expect("Query should be successful");
     }
 
+    #[tokio::test]
+    async fn test_table_function_views() -> Result<()> {
+        let pool = DuckDbConnectionPool::new_memory()?;
+        let view = pool.create_table_function_view(
+            Some("numbers"),
+            "range(?)",
+            &[ScalarValue::Int64(Some(5))],
+        )?;
+        assert_eq!(view, TableReference::bare("numbers"));
+        assert_eq!(pool.clone().table_function_views(), vec!["numbers"]);
+
+        let conn = pool.connect().await?;
+        let rows = conn
+            .as_sync()
+            .expect("DuckDB connection should be synchronous")
+            .query_arrow("SELECT * FROM numbers", &[], None)?
+            .map(|batch| batch.map(|batch| batch.num_rows()).unwrap_or_default())
+            .fold(0, |rows, batch_rows| async move { rows + batch_rows })
+            .await;
+        assert_eq!(rows, 5);
+        drop(conn);
+
+        pool.close(None).await?;
+        assert!(pool.table_function_views().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("maintenance.db");
+        let path = path.to_str().expect("the path is UTF-8");
+        let pool = DuckDbConnectionPoolBuilder::file(path)
+            .with_maintenance(Some(DuckDbMaintenance::new(Duration::from_secs(60))))
+            .build()?;
+        assert!(pool
+            .maintenance
+            .as_ref()
+            .is_some_and(|task| task.is_running()));
+        assert!(pool.database_size()?.file_size > 0);
+
+        pool.close(None).await?;
+        assert!(pool
+            .maintenance
+            .as_ref()
+            .is_some_and(|task| !task.is_running()));
+
+        let memory = DuckDbConnectionPoolBuilder::memory()
+            .with_maintenance(Some(DuckDbMaintenance::default()))
+            .build()?;
+        assert!(memory.maintenance.is_none());
+        assert_eq!(memory.database_size()?.file_size, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_shared_memory_connection_pool() {
+        let writer = DuckDbConnectionPool::new_shared_memory("test_shared")
+            .expect("DuckDB connection pool to be created");
+        let reader = DuckDbConnectionPool::new_shared_memory("test_shared")
+            .expect("DuckDB connection pool to be created");
+        let other = DuckDbConnectionPool::new_shared_memory("test_other")
+            .expect("DuckDB connection pool to be created");
+        assert_eq!(reader.db_path(), ":memory:test_shared");
+
+        let conn = writer.connect().await.expect("connection established");
+        conn.as_sync()
+            .expect("DuckDB connection should be synchronous")
+            .execute("CREATE TABLE shared (a INTEGER)", &[])
+            .expect("Table should be created");
+
+        let conn = reader.connect().await.expect("connection established");
+        conn.as_sync()
+            .expect("DuckDB connection should be synchronous")
+            .execute("INSERT INTO shared VALUES (1)", &[])
+            .expect("Table of the other pool should be visible");
+
+        let conn = other.connect().await.expect("connection established");
+        assert!(conn
+            .as_sync()
+            .expect("DuckDB connection should be synchronous")
+            .execute("INSERT INTO shared VALUES (1)", &[])
+            .is_err());
+
+        // the database is dropped with its last pool
+        drop((writer, reader));
+        let pool = DuckDbConnectionPool::new_shared_memory("test_shared")
+            .expect("DuckDB connection pool to be created");
+        let conn = pool.connect().await.expect("connection established");
+        assert!(conn
+            .as_sync()
+            .expect("DuckDB connection should be synchronous")
+            .execute("INSERT INTO shared VALUES (1)", &[])
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_connection_pool_with_dedicated_workers() {
+        let pool = DuckDbConnectionPoolBuilder::memory()
+            .with_max_size(Some(2))
+            .with_dedicated_workers(Some(1))
+            .build()
+            .expect("DuckDB connection pool to be created");
+
+        let mut streams = Vec::new();
+        for _ in 0..4 {
+            let conn = pool
+                .connect()
+                .await
+                .expect("DuckDB connection should be established");
+            let stream = conn
+                .as_sync()
+                .expect("DuckDB connection should be synchronous")
+                .query_arrow("SELECT * FROM range(10000)", &[], None)
+                .expect("Query should be successful");
+            streams.push(datafusion::physical_plan::common::collect(stream));
+        }
+
+        for batches in futures::future::join_all(streams).await {
+            let batches = batches.expect("Batches should be read");
+            let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows, 10000);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedicated_workers_more_streams_than_threads() {
+        let pool = DuckDbConnectionPoolBuilder::memory()
+            .with_max_size(Some(2))
+            .with_dedicated_workers(Some(2))
+            .with_max_extra_worker_threads(Some(4))
+            .build()
+            .expect("DuckDB connection pool to be created");
+
+        let mut streams = Vec::new();
+        for _ in 0..6 {
+            let conn = pool
+                .connect()
+                .await
+                .expect("DuckDB connection should be established");
+            let mut stream = conn
+                .as_sync()
+                .expect("DuckDB connection should be synchronous")
+                .query_arrow("SELECT * FROM range(100000)", &[], None)
+                .expect("Query should be successful");
+            // every scan is started, and none of them is read until the last one has started
+            let first = tokio::time::timeout(std::time::Duration::from_secs(30), stream.next())
+                .await
+                .expect("Scan should start while the other scans are blocked on their consumers");
+            assert!(first.is_some_and(|batch| batch.is_ok()));
+            streams.push(stream);
+        }
+
+        for stream in streams {
+            let batches = datafusion::physical_plan::common::collect(stream)
+                .await
+                .expect("Batches should be read");
+            assert!(!batches.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_remote_database_alias() {
+        assert_eq!(
+            remote_database_alias(&Arc::from("s3://bucket/data/sales-2024.duckdb")).expect("alias"),
+            "sales_2024"
+        );
+        assert_eq!(
+            remote_database_alias(&Arc::from("https://example.com/2024.db")).expect("alias"),
+            "_2024"
+        );
+    }
+
+    #[test]
+    fn test_duckdb_motherduck_builder() {
+        let builder = DuckDbConnectionPoolBuilder::motherduck("md:sales");
+        assert_eq!(builder.get_path(), "md:sales");
+        assert!(builder.is_motherduck());
+        assert_eq!(
+            DuckDbConnectionPoolBuilder::motherduck("").get_path(),
+            "md:"
+        );
+        assert!(!DuckDbConnectionPoolBuilder::file("./sales.db").is_motherduck());
+
+        assert_eq!(
+            extract_db_name(Arc::from("md:sales?motherduck_token=secret")).expect("db name"),
+            "sales"
+        );
+        assert_eq!(
+            extract_db_name(Arc::from("./data/sales.db")).expect("db name"),
+            "sales"
+        );
+    }
+
     #[tokio::test]
     #[cfg(feature = "duckdb-federation")]
     async fn test_duckdb_connection_pool_with_attached_databases() {
@@ -0,0 +1,136 @@
+use std::{future::Future, path::PathBuf, sync::Arc, time::Duration};
+
+use datafusion::physical_plan::metrics::{ExecutionPlanMetricsSet, MetricBuilder};
+use serde_json::Value;
+
+tokio::task_local! {
+    static QUERY_PROFILE: Arc<QueryProfile>;
+}
+
+/// Where the JSON profile of a DuckDB query goes, see [`QueryProfile::scope`].
+///
+/// The profile is written by DuckDB with `PRAGMA enable_profiling = 'json'` when the query
+/// finishes, and recorded in the metrics of the execution plan of the query, which
+/// `EXPLAIN ANALYZE` shows:
+///
+/// * `duckdb_latency` and `duckdb_cpu_time`, the time DuckDB took to run the query and the CPU time
+///   of its threads
+/// * `duckdb_rows_scanned`, the rows read from the tables
+/// * `duckdb_peak_buffer_memory`, the most memory of the buffer manager during the query
+///
+/// The whole profile, with the timings of the operators, is logged at the debug level.
+#[derive(Debug)]
+pub struct QueryProfile {
+    metrics: ExecutionPlanMetricsSet,
+    partition: usize,
+}
+
+impl QueryProfile {
+    #[must_use]
+    pub fn new(metrics: ExecutionPlanMetricsSet, partition: usize) -> Self {
+        Self { metrics, partition }
+    }
+
+    /// Runs `future`, with the DuckDB queries that it starts on this task profiled into `self`.
+    pub async fn scope<F: Future>(self: Arc<Self>, future: F) -> F::Output {
+        QUERY_PROFILE.scope(self, future).await
+    }
+
+    /// The profile of the queries started on this task, if they're profiled.
+    #[must_use]
+    pub fn current() -> Option<Arc<Self>> {
+        QUERY_PROFILE.try_with(Arc::clone).ok()
+    }
+
+    /// A file for DuckDB to write the profile of a query to.
+    #[must_use]
+    pub fn output_path() -> PathBuf {
+        std::env::temp_dir().join(format!("duckdb_profile_{}.json", uuid::Uuid::new_v4()))
+    }
+
+    /// Records the metrics of `profile`, the JSON that DuckDB wrote for a query.
+    pub fn record(&self, profile: &str) {
+        tracing::debug!("DuckDB query profile: {profile}");
+        let profile: Value = match serde_json::from_str(profile) {
+            Ok(profile) => profile,
+            Err(e) => {
+                tracing::warn!("Unable to parse the DuckDB query profile: {e}");
+                return;
+            }
+        };
+
+        let seconds = |name: &str| {
+            profile
+                .get(name)
+                .and_then(Value::as_f64)
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                .map(Duration::from_secs_f64)
+        };
+        let count = |name: &str| {
+            profile
+                .get(name)
+                .and_then(Value::as_u64)
+                .and_then(|count| usize::try_from(count).ok())
+        };
+
+        for (metric, name) in [
+            ("duckdb_latency", "latency"),
+            ("duckdb_cpu_time", "cpu_time"),
+        ] {
+            if let Some(duration) = seconds(name) {
+                MetricBuilder::new(&self.metrics)
+                    .subset_time(metric, self.partition)
+                    .add_duration(duration);
+            }
+        }
+        if let Some(rows) = count("cumulative_rows_scanned") {
+            MetricBuilder::new(&self.metrics)
+                .counter("duckdb_rows_scanned", self.partition)
+                .add(rows);
+        }
+        if let Some(bytes) = count("system_peak_buffer_memory") {
+            MetricBuilder::new(&self.metrics)
+                .gauge("duckdb_peak_buffer_memory", self.partition)
+                .set(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let metrics = ExecutionPlanMetricsSet::new();
+        let profile = QueryProfile::new(metrics.clone(), 0);
+        profile.record(
+            r#"{"query_name": "SELECT 1", "latency": 0.5, "cpu_time": 0.25,
+                "cumulative_rows_scanned": 1000, "system_peak_buffer_memory": 4096,
+                "children": []}"#,
+        );
+        profile.record("not a profile");
+
+        let metrics = metrics.clone_inner();
+        let value = |name: &str| {
+            metrics
+                .sum_by_name(name)
+                .map(|value| value.as_usize())
+                .expect("metric recorded")
+        };
+        assert_eq!(value("duckdb_latency"), 500_000_000);
+        assert_eq!(value("duckdb_cpu_time"), 250_000_000);
+        assert_eq!(value("duckdb_rows_scanned"), 1000);
+        assert_eq!(value("duckdb_peak_buffer_memory"), 4096);
+    }
+
+    #[tokio::test]
+    async fn test_scope() {
+        assert!(QueryProfile::current().is_none());
+        let profile = Arc::new(QueryProfile::new(ExecutionPlanMetricsSet::new(), 1));
+        let current = Arc::clone(&profile)
+            .scope(async { QueryProfile::current() })
+            .await;
+        assert!(current.is_some_and(|current| Arc::ptr_eq(&current, &profile)));
+    }
+}
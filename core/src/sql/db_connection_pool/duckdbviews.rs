@@ -0,0 +1,324 @@
+//! Named views over the table functions of DuckDB, like `read_csv_auto('data.csv')`, see
+//! [`super::duckdbpool::DuckDbConnectionPool::create_table_function_view`].
+//!
+//! DuckDB can't prepare the parameters of a view, so the parameters of the table function are
+//! written into the SQL of the view as literals, and the view reads the same files for as long as
+//! it exists.
+
+use std::{
+    collections::BTreeMap,
+    ops::ControlFlow,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use datafusion::{
+    error::DataFusionError,
+    logical_expr::Expr,
+    scalar::ScalarValue,
+    sql::{
+        sqlparser::{
+            ast::{self, visit_expressions_mut},
+            dialect::DuckDbDialect,
+            parser::Parser,
+            tokenizer::Token,
+        },
+        unparser::{dialect::DuckDBDialect, Unparser},
+    },
+};
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("'{table_function}' is not a call of a table function.\nPass a table function with its arguments, e.g. read_csv_auto(?)."))]
+    InvalidTableFunction { table_function: String },
+
+    #[snafu(display(
+        "No parameter is given for the placeholder {placeholder} of the table function."
+    ))]
+    MissingParameter { placeholder: String },
+
+    #[snafu(display(
+        "{given} parameters are given for a table function with {used} placeholders."
+    ))]
+    UnusedParameters { given: usize, used: usize },
+
+    #[snafu(display(
+        "Unable to write the parameter {placeholder} of the table function as SQL.\n{source}"
+    ))]
+    UnableToWriteParameter {
+        placeholder: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Unable to create the view '{name}'.\n{source}"))]
+    UnableToCreateView { name: String, source: duckdb::Error },
+
+    #[snafu(display("Unable to drop the view '{name}'.\n{source}"))]
+    UnableToDropView { name: String, source: duckdb::Error },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// `table_function`, a call of a DuckDB table function, with the parameters bound to its
+/// placeholders as literals. The placeholders are either `?`, bound in order, or `$1`, `$2`, ...
+///
+/// ```rust,ignore
+/// let sql = bind_table_function(
+///     "read_csv_auto(?, header = ?)",
+///     &[ScalarValue::from("sales.csv"), ScalarValue::Boolean(Some(true))],
+/// )?;
+/// assert_eq!(sql, "read_csv_auto('sales.csv', header = true)");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `table_function` isn't a call of a table function, or if the parameters
+/// don't match its placeholders.
+pub fn bind_table_function(table_function: &str, params: &[ScalarValue]) -> Result<String> {
+    let invalid = || InvalidTableFunctionSnafu {
+        table_function: table_function.to_string(),
+    };
+    let mut table_factor = Parser::new(&DuckDbDialect {})
+        .try_with_sql(table_function)
+        .and_then(|mut parser| {
+            let table_factor = parser.parse_table_factor()?;
+            parser.expect_token(&Token::EOF)?;
+            Ok(table_factor)
+        })
+        .ok()
+        .context(invalid())?;
+    ensure!(
+        matches!(
+            &table_factor,
+            ast::TableFactor::Table {
+                args: Some(_),
+                alias: None,
+                ..
+            }
+        ),
+        invalid()
+    );
+
+    let unparser = Unparser::new(&DuckDBDialect::new());
+    let mut next_param = 0;
+    let mut used = 0;
+    let mut error = None;
+    let _ = visit_expressions_mut(&mut table_factor, |expr| {
+        let ast::Expr::Value(ast::Value::Placeholder(placeholder)) = expr else {
+            return ControlFlow::Continue(());
+        };
+        let index = match placeholder.strip_prefix('$') {
+            Some(number) => number.parse::<usize>().ok().and_then(|n| n.checked_sub(1)),
+            None if placeholder == "?" => {
+                next_param += 1;
+                Some(next_param - 1)
+            }
+            None => None,
+        };
+        let bound = index
+            .and_then(|index| params.get(index).map(|param| (index, param)))
+            .context(MissingParameterSnafu {
+                placeholder: placeholder.clone(),
+            })
+            .and_then(|(index, param)| {
+                used = used.max(index + 1);
+                unparser.expr_to_sql(&Expr::Literal(param.clone())).context(
+                    UnableToWriteParameterSnafu {
+                        placeholder: placeholder.clone(),
+                    },
+                )
+            });
+        match bound {
+            Ok(literal) => {
+                *expr = literal;
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+    ensure!(
+        used == params.len(),
+        UnusedParametersSnafu {
+            given: params.len(),
+            used,
+        }
+    );
+
+    Ok(table_factor.to_string())
+}
+
+/// The views over table functions created through a pool and its clones, by name, with the table
+/// function each of them reads.
+///
+/// The views are dropped when the pool is closed, see
+/// [`super::DbConnectionPool::close`].
+#[derive(Debug, Default)]
+pub struct TableFunctionViews {
+    views: Mutex<BTreeMap<String, String>>,
+}
+
+impl TableFunctionViews {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the view `name` over `table_function`, a table function with its parameters bound,
+    /// replacing the view of the same name if there's one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if DuckDB can't create the view, e.g. if the table function fails.
+    pub fn create(
+        &self,
+        conn: &duckdb::Connection,
+        name: &str,
+        table_function: &str,
+    ) -> Result<()> {
+        let mut views = self.lock();
+        conn.execute(
+            &format!(
+                "CREATE OR REPLACE VIEW {} AS SELECT * FROM {table_function}",
+                quote_identifier(name)
+            ),
+            [],
+        )
+        .context(UnableToCreateViewSnafu { name })?;
+        views.insert(name.to_string(), table_function.to_string());
+        Ok(())
+    }
+
+    /// Drops the view `name`, if it was created through the pool. Returns whether it was.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if DuckDB can't drop the view.
+    pub fn drop_view(&self, conn: &duckdb::Connection, name: &str) -> Result<bool> {
+        let mut views = self.lock();
+        if !views.contains_key(name) {
+            return Ok(false);
+        }
+        conn.execute(
+            &format!("DROP VIEW IF EXISTS {}", quote_identifier(name)),
+            [],
+        )
+        .context(UnableToDropViewSnafu { name })?;
+        views.remove(name);
+        Ok(true)
+    }
+
+    /// Drops every view created through the pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first view that DuckDB can't drop, after trying to drop the others.
+    pub fn drop_all(&self, conn: &duckdb::Connection) -> Result<()> {
+        let names = self.lock().keys().cloned().collect::<Vec<_>>();
+        let mut first_error = None;
+        for name in names {
+            if let Err(e) = self.drop_view(conn, &name) {
+                tracing::warn!("{e}");
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// The table function that the view `name` reads, if it was created through the pool.
+    #[must_use]
+    pub fn table_function(&self, name: &str) -> Option<String> {
+        self.lock().get(name).cloned()
+    }
+
+    /// The names of the views created through the pool, in order.
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.lock().keys().cloned().collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, String>> {
+        self.views.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    ast::Ident::with_quote('"', name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_table_function() -> Result<()> {
+        assert_eq!(
+            bind_table_function(
+                "read_csv_auto(?, header = ?)",
+                &[
+                    ScalarValue::from("it's.csv"),
+                    ScalarValue::Boolean(Some(true))
+                ],
+            )?,
+            "read_csv_auto('it''s.csv', header = true)"
+        );
+        assert_eq!(
+            bind_table_function(
+                "read_parquet($2, filename = $1)",
+                &[
+                    ScalarValue::Boolean(Some(false)),
+                    ScalarValue::from("a.parquet")
+                ],
+            )?,
+            "read_parquet('a.parquet', filename = false)"
+        );
+        assert_eq!(bind_table_function("range(10)", &[])?, "range(10)");
+
+        assert!(matches!(
+            bind_table_function("sales", &[]),
+            Err(Error::InvalidTableFunction { .. })
+        ));
+        assert!(matches!(
+            bind_table_function("range(1); DROP TABLE sales", &[]),
+            Err(Error::InvalidTableFunction { .. })
+        ));
+        assert!(matches!(
+            bind_table_function("read_csv_auto(?)", &[]),
+            Err(Error::MissingParameter { .. })
+        ));
+        assert!(matches!(
+            bind_table_function(
+                "read_csv_auto(?)",
+                &[ScalarValue::from("a"), ScalarValue::from("b")]
+            ),
+            Err(Error::UnusedParameters { given: 2, used: 1 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_function_views() -> Result<(), Box<dyn std::error::Error>> {
+        let conn = duckdb::Connection::open_in_memory()?;
+        let views = TableFunctionViews::new();
+        views.create(
+            &conn,
+            "numbers",
+            &bind_table_function("range(?)", &[ScalarValue::Int64(Some(3))])?,
+        )?;
+        let count: i64 = conn.query_row("SELECT count(*) FROM numbers", [], |row| row.get(0))?;
+        assert_eq!(count, 3);
+        assert_eq!(views.names(), vec!["numbers".to_string()]);
+        assert_eq!(views.table_function("numbers").as_deref(), Some("range(3)"));
+
+        assert!(!views.drop_view(&conn, "other")?);
+        views.drop_all(&conn)?;
+        assert!(views.names().is_empty());
+        assert!(conn.execute("SELECT * FROM numbers", []).is_err());
+        Ok(())
+    }
+}
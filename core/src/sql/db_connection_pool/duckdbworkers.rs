@@ -0,0 +1,293 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use snafu::prelude::*;
+use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to start a DuckDB worker thread.\n{source}"))]
+    UnableToStartWorker { source: std::io::Error },
+
+    #[snafu(display("The DuckDB worker threads have stopped"))]
+    WorkersStopped,
+
+    #[snafu(display("A DuckDB worker thread panicked while running a query"))]
+    WorkerPanicked,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A job and the permit it runs under, released once the thread that ran it is idle again.
+type PermittedJob = (Job, OwnedSemaphorePermit);
+
+/// Dedicated threads that run DuckDB queries instead of Tokio's blocking thread pool.
+///
+/// A DuckDB scan occupies its thread until the last batch has been read by the consumer. With
+/// `spawn_blocking`, many concurrent scans can use up the blocking threads that the rest of the
+/// process relies on for file I/O and DNS.
+///
+/// A query is only handed to a worker thread that is idle. When every worker is busy, the query
+/// runs on an extra thread that exits once the query is done, up to
+/// [`DuckDbWorkers::with_max_extra_threads`] of them. Once those are busy too, a query waits for
+/// a worker or an extra thread to be done. The consumer of a scan may not read from it until
+/// another scan has produced a batch, like a merge of sorted partitions does, so the threads must
+/// cover the scans that are read at once, or the scans that wait are never started.
+///
+/// The worker threads exit once the workers are dropped or stopped.
+pub struct DuckDbWorkers {
+    /// The queue of the jobs of the workers, until they're stopped.
+    jobs: Mutex<Option<mpsc::Sender<PermittedJob>>>,
+    /// A permit for every job that runs at once, on a worker or an extra thread.
+    permits: Arc<Semaphore>,
+    idle: Arc<AtomicUsize>,
+    threads: usize,
+    max_extra_threads: usize,
+}
+
+impl std::fmt::Debug for DuckDbWorkers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuckDbWorkers")
+            .field("threads", &self.threads)
+            .field("max_extra_threads", &self.max_extra_threads)
+            .field("idle", &self.idle.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl DuckDbWorkers {
+    /// Starts `threads` worker threads, with as many extra threads for the queries that start
+    /// while every worker is busy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a thread can't be spawned.
+    pub fn new(threads: usize) -> Result<Self> {
+        let threads = threads.max(1);
+        // every queued job has claimed an idle worker, so the queue never holds more than that
+        let (jobs, receiver) = mpsc::channel::<PermittedJob>(threads);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let idle = Arc::new(AtomicUsize::new(threads));
+
+        for i in 0..threads {
+            let receiver = Arc::clone(&receiver);
+            let idle = Arc::clone(&idle);
+            thread::Builder::new()
+                .name(format!("duckdb-worker-{i}"))
+                .spawn(move || loop {
+                    // idle threads wait for the lock, only one of them waits for the next job
+                    let job = receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .blocking_recv();
+                    let Some((job, permit)) = job else {
+                        break;
+                    };
+                    run_job(job);
+                    idle.fetch_add(1, Ordering::SeqCst);
+                    drop(permit);
+                })
+                .context(UnableToStartWorkerSnafu)?;
+        }
+
+        Ok(Self {
+            jobs: Mutex::new(Some(jobs)),
+            permits: Arc::new(Semaphore::new(threads * 2)),
+            idle,
+            threads,
+            max_extra_threads: threads,
+        })
+    }
+
+    /// Runs at most `max_extra_threads` queries on extra threads while every worker is busy. The
+    /// queries that start while these are busy too wait for a thread to be done.
+    #[must_use]
+    pub fn with_max_extra_threads(mut self, max_extra_threads: usize) -> Self {
+        let permits = self.threads.saturating_add(max_extra_threads);
+        self.permits = Arc::new(Semaphore::new(permits.min(Semaphore::MAX_PERMITS)));
+        self.max_extra_threads = max_extra_threads;
+        self
+    }
+
+    /// Starts `f` on an idle worker thread, or on an extra thread if every worker is busy, once
+    /// fewer than [`Self::with_max_extra_threads`] extra threads are running.
+    ///
+    /// The returned receiver resolves to the result of `f`, or errors if `f` panicked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the worker threads have stopped or a new thread can't be spawned.
+    pub async fn spawn<F, T>(&self, f: F) -> Result<oneshot::Receiver<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let jobs = self
+            .jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .context(WorkersStoppedSnafu)?;
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::WorkersStopped)?;
+        let (result_tx, result_rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = result_tx.send(f());
+        });
+
+        // every queued job has claimed a worker that isn't running a job, so it starts right away,
+        // and the workers release their permits once they're idle again, so a job that finds no
+        // idle worker holds one of the permits of the extra threads
+        let claimed = self
+            .idle
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |idle| {
+                idle.checked_sub(1)
+            })
+            .is_ok();
+        if claimed {
+            jobs.send((job, permit))
+                .await
+                .map_err(|_| Error::WorkersStopped)?;
+        } else {
+            thread::Builder::new()
+                .name("duckdb-worker-extra".to_string())
+                .spawn(move || {
+                    run_job(job);
+                    drop(permit);
+                })
+                .context(UnableToStartWorkerSnafu)?;
+        }
+        Ok(result_rx)
+    }
+
+    /// Runs `f` on a worker thread and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the worker threads have stopped or `f` panicked.
+    pub async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawn(f)
+            .await?
+            .await
+            .map_err(|_| Error::WorkerPanicked)
+    }
+
+    /// Stops the worker threads, which exit once their current job is done. Jobs can't be
+    /// started anymore, and the jobs that wait for a thread fail.
+    pub fn stop(&self) {
+        self.jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        self.permits.close();
+    }
+
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    #[must_use]
+    pub fn max_extra_threads(&self) -> usize {
+        self.max_extra_threads
+    }
+}
+
+fn run_job(job: Job) {
+    // the panic is reported to the caller through the dropped result sender
+    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_duckdb_workers() {
+        let workers = DuckDbWorkers::new(2).expect("workers started");
+        let name = workers
+            .run(|| thread::current().name().map(str::to_string))
+            .await
+            .expect("job ran");
+        assert!(name.is_some_and(|name| name.starts_with("duckdb-worker-")));
+
+        let result: Result<()> = workers.run(|| panic!("query panicked")).await;
+        assert!(matches!(result, Err(Error::WorkerPanicked)));
+
+        // the threads keep running after a panic
+        let results = futures::future::join_all((0..4).map(|i| workers.run(move || i * 2))).await;
+        assert_eq!(
+            results.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            vec![0, 2, 4, 6]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_workers_busy() {
+        let workers = DuckDbWorkers::new(1)
+            .expect("workers started")
+            .with_max_extra_threads(1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (extra_release_tx, extra_release_rx) = std::sync::mpsc::channel::<()>();
+
+        // occupies the only worker until it's released
+        let blocked = workers
+            .spawn(move || {
+                let _ = release_rx.recv();
+                thread::current().name().map(str::to_string)
+            })
+            .await
+            .expect("job started");
+
+        let name = workers
+            .run(|| thread::current().name().map(str::to_string))
+            .await
+            .expect("job ran while the worker is busy");
+        assert_eq!(name.as_deref(), Some("duckdb-worker-extra"));
+
+        // occupies the only extra thread until it's released
+        let extra = workers
+            .spawn(move || {
+                let _ = extra_release_rx.recv();
+            })
+            .await
+            .expect("job started");
+        let waiting =
+            tokio::time::timeout(std::time::Duration::from_millis(100), workers.run(|| ())).await;
+        assert!(waiting.is_err(), "job waits while every thread is busy");
+
+        release_tx.send(()).expect("job is waiting");
+        assert_eq!(
+            blocked.await.expect("job ran").as_deref(),
+            Some("duckdb-worker-0")
+        );
+        let name = workers
+            .run(|| thread::current().name().map(str::to_string))
+            .await
+            .expect("job ran once the worker is idle");
+        assert_eq!(name.as_deref(), Some("duckdb-worker-0"));
+
+        extra_release_tx.send(()).expect("job is waiting");
+        extra.await.expect("job ran");
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_workers_stop() {
+        let workers = DuckDbWorkers::new(1).expect("workers started");
+        workers.stop();
+        assert!(matches!(
+            workers.run(|| ()).await,
+            Err(Error::WorkersStopped)
+        ));
+    }
+}
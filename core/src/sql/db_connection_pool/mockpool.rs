@@ -0,0 +1,182 @@
+//! A connection pool of an in-memory [`MockDatabase`], to unit test code built on
+//! [`DbConnectionPool`] without a database.
+//!
+//! ```rust,ignore
+//! let mock = MockConnectionPool::new()
+//!     .with_table("users", schema)
+//!     .with_results(r#"FROM "users""#, vec![batch]);
+//! let pool: Arc<dyn DbConnectionPool<MockDatabase, String> + Send + Sync> = Arc::new(mock.clone());
+//! let table = SqlTable::new("mock", &pool, "users").await?;
+//! // ... query the table
+//! assert!(mock.executed_sql()[0].contains(r#"FROM "users""#));
+//! ```
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{array::RecordBatch, datatypes::SchemaRef},
+    sql::TableReference,
+};
+
+use super::{
+    dbconnection::{
+        mockconn::{MockConnection, MockDatabase},
+        AsyncDbConnection, DbConnection,
+    },
+    DbConnectionPool, JoinPushDown, Result,
+};
+
+/// A pool whose connections serve the batches registered with
+/// [`MockConnectionPool::with_results`] and record the SQL they run. Clones share the database.
+#[derive(Debug, Clone)]
+pub struct MockConnectionPool {
+    database: MockDatabase,
+    join_push_down: JoinPushDown,
+}
+
+impl Default for MockConnectionPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockConnectionPool {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            database: MockDatabase::default(),
+            join_push_down: JoinPushDown::Disallow,
+        }
+    }
+
+    /// Adds a table with `schema`, for the providers to get the schema of.
+    #[must_use]
+    pub fn with_table(self, table_reference: impl Into<TableReference>, schema: SchemaRef) -> Self {
+        self.database.add_table(table_reference, schema);
+        self
+    }
+
+    /// Serves `batches` for the queries containing `pattern`. The first matching pattern wins, and
+    /// queries matching none of them fail.
+    #[must_use]
+    pub fn with_results(self, pattern: impl Into<String>, batches: Vec<RecordBatch>) -> Self {
+        self.database.add_results(pattern, batches);
+        self
+    }
+
+    /// The number of rows that the statements run on the connections affect, 0 by default.
+    #[must_use]
+    pub fn with_rows_affected(self, rows_affected: u64) -> Self {
+        self.database.set_rows_affected(rows_affected);
+        self
+    }
+
+    #[must_use]
+    pub fn with_join_push_down(mut self, join_push_down: JoinPushDown) -> Self {
+        self.join_push_down = join_push_down;
+        self
+    }
+
+    /// The database of the connections.
+    #[must_use]
+    pub fn database(&self) -> &MockDatabase {
+        &self.database
+    }
+
+    /// The queries and statements run on the connections of the pool, in order.
+    #[must_use]
+    pub fn executed_sql(&self) -> Vec<String> {
+        self.database.statements()
+    }
+}
+
+#[async_trait]
+impl DbConnectionPool<MockDatabase, String> for MockConnectionPool {
+    async fn connect(&self) -> Result<Box<dyn DbConnection<MockDatabase, String>>> {
+        Ok(Box::new(MockConnection::new(self.database.clone())))
+    }
+
+    fn join_push_down(&self) -> JoinPushDown {
+        self.join_push_down.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::{
+        arrow::{
+            array::{Int64Array, StringArray},
+            datatypes::{DataType, Field, Schema},
+        },
+        prelude::SessionContext,
+    };
+
+    use super::*;
+    use crate::sql::{
+        db_connection_pool::dbconnection::{self, get_schema},
+        sql_provider_datafusion::SqlTable,
+    };
+
+    fn users() -> (SchemaRef, RecordBatch) {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["alice", "bob"])),
+            ],
+        )
+        .expect("record batch created");
+        (schema, batch)
+    }
+
+    #[tokio::test]
+    async fn test_scan_serves_canned_results(
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (schema, batch) = users();
+        let mock = MockConnectionPool::new()
+            .with_table("users", schema)
+            .with_results(r#"FROM "users""#, vec![batch.clone()]);
+        let pool: Arc<dyn DbConnectionPool<MockDatabase, String> + Send + Sync> =
+            Arc::new(mock.clone());
+
+        let ctx = SessionContext::new();
+        let table = SqlTable::new("mock", &pool, "users").await?;
+        ctx.register_table("users", Arc::new(table))?;
+        let batches = ctx.sql("SELECT * FROM users").await?.collect().await?;
+
+        assert_eq!(batches, vec![batch]);
+        let executed_sql = mock.executed_sql();
+        assert_eq!(executed_sql.len(), 1);
+        assert!(
+            executed_sql[0].starts_with("SELECT") && executed_sql[0].contains(r#"FROM "users""#),
+            "{executed_sql:?}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_table_and_query() {
+        let pool = MockConnectionPool::new().with_rows_affected(3);
+
+        let error = get_schema(pool.connect().await.expect("connected"), &"users".into())
+            .await
+            .expect_err("no table");
+        assert!(matches!(error, dbconnection::Error::UndefinedTable { .. }));
+
+        let conn = pool.connect().await.expect("connected");
+        let conn = conn.as_async().expect("async connection");
+        assert!(conn.query_arrow("SELECT 1", &[], None).await.is_err());
+        assert_eq!(
+            conn.execute("DELETE FROM users", &[])
+                .await
+                .expect("executed"),
+            3
+        );
+        assert_eq!(pool.executed_sql(), vec!["SELECT 1", "DELETE FROM users"]);
+    }
+}
@@ -1,17 +1,44 @@
 use async_trait::async_trait;
 use dbconnection::DbConnection;
+use query_limit::QueryPermit;
 use std::sync::Arc;
+use std::time::Duration;
 
+#[cfg(any(
+    feature = "mysql",
+    feature = "postgres",
+    feature = "aws-secrets-manager"
+))]
+pub mod aws;
 pub mod dbconnection;
 #[cfg(feature = "duckdb")]
+pub mod duckdbmaintenance;
+#[cfg(feature = "duckdb")]
 pub mod duckdbpool;
+#[cfg(feature = "duckdb")]
+pub mod duckdbprofile;
+#[cfg(feature = "duckdb")]
+pub mod duckdbviews;
+#[cfg(feature = "duckdb")]
+pub mod duckdbworkers;
+pub mod mockpool;
 #[cfg(feature = "mysql")]
 pub mod mysqlpool;
 #[cfg(feature = "odbc")]
 pub mod odbcpool;
+pub mod pool_options;
+pub mod pool_selector;
 #[cfg(feature = "postgres")]
 pub mod postgrespool;
+pub mod query_limit;
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+pub mod rds_iam;
 pub mod runtime;
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+pub mod secrets;
+pub mod shutdown;
+#[cfg(feature = "sqlite")]
+pub mod sqlitemaintenance;
 #[cfg(feature = "sqlite")]
 pub mod sqlitepool;
 
@@ -33,7 +60,36 @@ pub enum JoinPushDown {
 pub trait DbConnectionPool<T, P: 'static> {
     async fn connect(&self) -> Result<Box<dyn DbConnection<T, P>>>;
 
+    /// A connection for queries that only read, like the scans of tables, which pools with read
+    /// replicas can route to a replica instead of the primary. Pools without replicas return a
+    /// connection of [`DbConnectionPool::connect`].
+    async fn connect_read_only(&self) -> Result<Box<dyn DbConnection<T, P>>> {
+        self.connect().await
+    }
+
     fn join_push_down(&self) -> JoinPushDown;
+
+    /// Opens the `min_idle` connections of the pool ahead of the first queries, so that they don't
+    /// wait for the connections to be established. Pools that open their idle connections when
+    /// they're built don't need to do anything.
+    async fn warm_up(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Waits for one of the `max_concurrent_queries` of the pool to be free, see
+    /// [`query_limit::QueryLimiter`]. The query runs while it holds the permit. Pools that don't
+    /// cap their queries return `None` right away.
+    async fn acquire_query_permit(&self) -> Result<Option<QueryPermit>> {
+        Ok(None)
+    }
+
+    /// Shuts the pool down, see [`shutdown`]: new queries and connections are refused, the queries
+    /// in flight are waited for, for `timeout` at most, then the connections of the pool are
+    /// closed and its background threads are stopped. Pools that don't keep connections or
+    /// threads around don't need to do anything.
+    async fn close(&self, _timeout: Option<Duration>) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
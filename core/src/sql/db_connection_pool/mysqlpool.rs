@@ -1,4 +1,10 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use mysql_async::{
@@ -7,17 +13,31 @@ use mysql_async::{
     DEFAULT_POOL_CONSTRAINTS,
 };
 use secrecy::{ExposeSecret, SecretBox, SecretString};
-use snafu::{ResultExt, Snafu};
+use snafu::{OptionExt, ResultExt, Snafu};
 
 use crate::{
-    sql::db_connection_pool::{
-        dbconnection::{mysqlconn::MySQLConnection, AsyncDbConnection, DbConnection},
-        JoinPushDown,
+    sql::{
+        arrow_sql_gen::{mysql::UnsignedBigIntPolicy, timestamp::TimestampPolicy},
+        db_connection_pool::{
+            dbconnection::{
+                mysqlconn::{MySQLConnection, DEFAULT_RESULT_BUFFER_ROWS},
+                AsyncDbConnection, DbConnection,
+            },
+            JoinPushDown,
+        },
     },
     util::{self, ns_lookup::verify_ns_lookup_and_tcp_connect},
 };
 
-use super::DbConnectionPool;
+use super::{
+    aws,
+    pool_options::{self, PoolOptions},
+    query_limit::{QueryLimiter, QueryPermit},
+    rds_iam::{RdsIamAuth, RdsIamTokenProvider},
+    secrets::{self, PasswordRefresh, SecretProvider},
+    shutdown::{self, QueryTracker},
+    DbConnectionPool,
+};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -49,12 +69,37 @@ pub enum Error {
 
     #[snafu(display("{message}\nEnsure the given MySQL database exists"))]
     UnknownMySQLDatabase { message: String },
+
+    #[snafu(display("IAM authentication failed.\n{source}"))]
+    RdsIamAuthError { source: aws::Error },
+
+    #[snafu(display("IAM authentication requires the '{parameter_name}' parameter."))]
+    MissingRdsIamParameter { parameter_name: String },
+
+    #[snafu(display("Unable to get the password of the connections.\n{source}"))]
+    SecretProviderError { source: secrets::Error },
+
+    #[snafu(display("Invalid connection pool options.\n{source}"))]
+    InvalidPoolOptions { source: pool_options::Error },
+
+    #[snafu(display("{source}"))]
+    Shutdown { source: shutdown::Error },
 }
 
+/// The server error of a rejected user name or password.
+const ACCESS_DENIED: u16 = 1045;
+
 #[derive(Debug, Clone)]
 pub struct MySQLConnectionPool {
-    pool: Arc<mysql_async::Pool>,
+    pool: Arc<RwLock<mysql_async::Pool>>,
+    password_refresh: Option<Arc<PoolRefresh>>,
+    min_idle: usize,
+    query_limiter: Option<QueryLimiter>,
+    queries: QueryTracker,
     join_push_down: JoinPushDown,
+    unsigned_bigint: UnsignedBigIntPolicy,
+    timestamp_policy: TimestampPolicy,
+    result_buffer_rows: usize,
 }
 
 impl MySQLConnectionPool {
@@ -69,19 +114,95 @@ impl MySQLConnectionPool {
     ///   * `db` - The database to connect to.
     ///   * `pass` - The password to use when connecting to the MySQL database.
     ///   * `tcp_port` - The TCP port to use when connecting to the MySQL database.
+    ///   * `socket` - The path of the Unix domain socket to connect over instead of TCP, like `/var/run/mysqld/mysqld.sock`. TLS isn't used over the socket, which doesn't leave the host. Also set with the `socket` parameter of the connection string.
     ///   * `sslmode` - The SSL mode to use when connecting to the MySQL database. Can be "disabled", "required", or "preferred".
     ///   * `sslrootcert` - The path to the root certificate to use when connecting to the MySQL database.
     ///   * `pool_min` - The minimum number of connections to keep open in the pool, lazily created when requested.
     ///   * `pool_max` - The maximum number of connections to allow in the pool.
+    ///   * `min_idle`, `max_size` - The same as `pool_min` and `pool_max`, which they take precedence over, see [`PoolOptions::from_params`].
+    ///   * `idle_timeout` - How long a connection above `pool_min` stays idle before it's closed, like `30s`.
+    ///   * `max_lifetime` - How long a connection is used before it's closed, like `30m`.
+    ///   * `max_concurrent_queries` - The maximum number of queries that run at once on the pool. The queries beyond it wait for a running one to finish, see [`QueryLimiter`].
+    ///   * `query_queue_timeout` - How long a query waits to run when `max_concurrent_queries` are running before it fails, like `30s`. Without it, queries wait as long as it takes.
+    ///   * `auth` - `rds_iam` to authenticate with an IAM token of AWS RDS instead of a password, signed with the AWS credentials of the default credential chain (the environment, `~/.aws` profiles, IRSA, ECS or IMDS). The pool is rebuilt with a new token before the token expires, see [`super::rds_iam`].
+    ///   * `aws_region` - The AWS region of the database for IAM authentication, `AWS_REGION` by default.
+    ///   * `unsigned_bigint` - How `BIGINT UNSIGNED` columns are read: `uint64` (the default), `int64` to fail on values above `i64::MAX` or `int64_or_null` to read them as null. The other unsigned integer columns are read as the next larger signed type.
+    ///   * `timestamp_policy` - The time zone of the timestamp columns: `source` (the default) keeps the columns without a time zone, `naive` is the same and `utc` surfaces them in `UTC`. The session time zone of the connections is UTC, so the values are UTC times either way.
+    ///   * `result_buffer_rows` - The number of rows read into each record batch, 4000 by default. The rows of a result are streamed from the server as the scan reads them, so this bounds the rows of a query held in memory.
     ///
     /// # Errors
     ///
     /// Returns an error if there is a problem creating the connection pool.
-    #[allow(clippy::unused_async)]
     pub async fn new(params: HashMap<String, SecretString>) -> Result<Self> {
+        Self::build(params, None).await
+    }
+
+    /// Creates a new instance of `MySQLConnectionPool` whose connections authenticate with the
+    /// password of `provider` instead of the `pass` parameter.
+    ///
+    /// The password is fetched again after the refresh interval of the provider, and right away
+    /// when the server denies access with it. A new password replaces the pool, while the
+    /// connections of the previous pool stay open until they're returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is a problem creating the connection pool or fetching the
+    /// password.
+    pub async fn new_with_password_provider(
+        params: HashMap<String, SecretString>,
+        provider: Arc<dyn SecretProvider>,
+    ) -> Result<Self> {
+        Self::build(params, Some(provider)).await
+    }
+
+    #[allow(clippy::unused_async)]
+    async fn build(
+        params: HashMap<String, SecretString>,
+        password_provider: Option<Arc<dyn SecretProvider>>,
+    ) -> Result<Self> {
         // Remove the "mysql_" prefix from the keys to keep backward compatibility
         let params = util::remove_prefix_from_hashmap_keys(params, "mysql_");
 
+        let iam_auth = match params.get("auth").map(SecretBox::expose_secret) {
+            Some("rds_iam") => {
+                let region = params.get("aws_region").map(SecretBox::expose_secret);
+                Some(RdsIamAuth::from_env(region).context(RdsIamAuthSnafu)?)
+            }
+            Some(_) => {
+                return InvalidParameterSnafu {
+                    parameter_name: "auth".to_string(),
+                }
+                .fail();
+            }
+            None => None,
+        };
+
+        let unsigned_bigint = match params.get("unsigned_bigint").map(SecretBox::expose_secret) {
+            Some(value) => UnsignedBigIntPolicy::parse(value).context(InvalidParameterSnafu {
+                parameter_name: "unsigned_bigint".to_string(),
+            })?,
+            None => UnsignedBigIntPolicy::default(),
+        };
+        let timestamp_policy = match params.get("timestamp_policy").map(SecretBox::expose_secret) {
+            Some(value) => TimestampPolicy::parse(value).context(InvalidParameterSnafu {
+                parameter_name: "timestamp_policy".to_string(),
+            })?,
+            None => TimestampPolicy::default(),
+        };
+        let result_buffer_rows = match params
+            .get("result_buffer_rows")
+            .map(SecretBox::expose_secret)
+        {
+            Some(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|rows| *rows > 0)
+                .context(InvalidParameterSnafu {
+                    parameter_name: "result_buffer_rows".to_string(),
+                })?,
+            None => DEFAULT_RESULT_BUFFER_ROWS,
+        };
+
         let mut connection_string = mysql_async::OptsBuilder::default();
         let mut ssl_mode = "required";
         let mut ssl_rootcert_path: Option<PathBuf> = None;
@@ -107,6 +228,9 @@ impl MySQLConnectionPool {
             if let Some(mysql_pass) = params.get("pass").map(SecretBox::expose_secret) {
                 connection_string = connection_string.pass(Some(mysql_pass));
             }
+            if let Some(mysql_socket) = params.get("socket").map(SecretBox::expose_secret) {
+                connection_string = connection_string.socket(Some(mysql_socket));
+            }
             if let Some(mysql_tcp_port) = params.get("tcp_port").map(SecretBox::expose_secret) {
                 connection_string =
                     connection_string.tcp_port(mysql_tcp_port.parse::<u16>().unwrap_or(3306));
@@ -159,14 +283,55 @@ impl MySQLConnectionPool {
         let ssl_opts = get_ssl_opts(ssl_mode, ssl_rootcert_path);
 
         connection_string = connection_string.ssl_opts(ssl_opts);
+        if iam_auth.is_some() {
+            // the token is sent as a cleartext password, over TLS
+            connection_string = connection_string.enable_cleartext_plugin(true);
+        }
 
-        let opts = mysql_async::Opts::from(connection_string);
+        let mut opts = mysql_async::Opts::from(connection_string);
+        let pool_options = PoolOptions::from_params(&params).context(InvalidPoolOptionsSnafu)?;
+        if pool_options != PoolOptions::default() {
+            let pool_opts = with_pool_options(opts.pool_opts().clone(), &pool_options)?;
+            opts = mysql_async::OptsBuilder::from_opts(opts)
+                .pool_opts(pool_opts)
+                .into();
+        }
+        if opts.socket().is_some() {
+            // the TLS hostname of the server can't be verified over the socket
+            opts = mysql_async::OptsBuilder::from_opts(opts)
+                .ssl_opts(None::<SslOpts>)
+                .into();
+        }
 
         verify_mysql_opts(&opts).await?;
 
         let join_push_down = get_join_context(&opts);
-
-        let pool = mysql_async::Pool::new(opts);
+        let min_idle = opts.pool_opts().constraints().min();
+        let query_limiter = QueryLimiter::from_options(&pool_options);
+
+        let password_provider = match (password_provider, iam_auth) {
+            (Some(provider), _) => Some(provider),
+            (None, Some(auth)) => {
+                let user = opts.user().context(MissingRdsIamParameterSnafu {
+                    parameter_name: "user",
+                })?;
+                let provider =
+                    RdsIamTokenProvider::new(auth, opts.ip_or_hostname(), opts.tcp_port(), user);
+                Some(Arc::new(provider) as _)
+            }
+            (None, None) => None,
+        };
+        let (pool, password_refresh) = match password_provider {
+            Some(provider) => {
+                let password = PasswordRefresh::new(provider)
+                    .await
+                    .context(SecretProviderSnafu)?;
+                let refresh = PoolRefresh { password, opts };
+                let pool = refresh.new_pool(&refresh.password.password().await);
+                (pool, Some(Arc::new(refresh)))
+            }
+            None => (mysql_async::Pool::new(opts), None),
+        };
 
         // Test the connection
         let mut conn = pool.get_conn().await.map_err(|err| match err {
@@ -182,7 +347,7 @@ impl MySQLConnectionPool {
                         message: server_error.message,
                     },
                     // Code 1045: Server error: ERROR 1045 (28000): Access denied for user <user> (using password: YES / NO)
-                    1045 => Error::InvalidUsernameOrPassword,
+                    ACCESS_DENIED => Error::InvalidUsernameOrPassword,
                     _ => Error::MySQLConnectionError {
                         source: mysql_async::Error::Server(server_error),
                     },
@@ -197,8 +362,15 @@ impl MySQLConnectionPool {
             .context(MySQLConnectionSnafu)?;
 
         Ok(Self {
-            pool: Arc::new(pool),
+            pool: Arc::new(RwLock::new(pool)),
+            password_refresh,
+            min_idle,
+            query_limiter,
+            queries: QueryTracker::new(),
             join_push_down,
+            unsigned_bigint,
+            timestamp_policy,
+            result_buffer_rows,
         })
     }
 
@@ -208,8 +380,7 @@ impl MySQLConnectionPool {
     ///
     /// Returns an error if there is a problem creating the connection pool.
     pub async fn connect_direct(&self) -> super::Result<MySQLConnection> {
-        let pool = Arc::clone(&self.pool);
-        let mut conn = pool.get_conn().await.context(MySQLConnectionSnafu)?;
+        let mut conn = self.get_conn().await?;
 
         // Set MySQL session default time zone to UTC to match Datafusion
         let _: Vec<Row> = conn
@@ -217,15 +388,116 @@ impl MySQLConnectionPool {
             .await
             .context(MySQLConnectionSnafu)?;
 
-        Ok(MySQLConnection::new(conn))
+        Ok(MySQLConnection::new(conn)
+            .with_unsigned_bigint_policy(self.unsigned_bigint)
+            .with_timestamp_policy(self.timestamp_policy)
+            .with_result_buffer_rows(self.result_buffer_rows))
     }
 
     pub fn metrics(&self) -> Arc<Metrics> {
-        self.pool.metrics()
+        self.pool
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .metrics()
+    }
+
+    /// A connection of the pool. When the server denies access with the password of the
+    /// connection, the password is fetched again and the connection is retried once with the new
+    /// one.
+    async fn get_conn(&self) -> Result<mysql_async::Conn> {
+        let pool = self.current_pool(false).await?;
+        match pool.get_conn().await {
+            Err(mysql_async::Error::Server(err))
+                if self.password_refresh.is_some() && err.code == ACCESS_DENIED =>
+            {
+                tracing::debug!("The password was rejected, fetching it again");
+                let pool = self.current_pool(true).await?;
+                pool.get_conn().await.context(MySQLConnectionSnafu)
+            }
+            result => result.context(MySQLConnectionSnafu),
+        }
+    }
+
+    /// The pool of the connections, which is replaced by a pool with a new password first if the
+    /// password is due for a refresh, or the refresh is `forced`, and it changed.
+    async fn current_pool(&self, forced: bool) -> Result<mysql_async::Pool> {
+        self.queries.ensure_open().context(ShutdownSnafu)?;
+        if let Some(refresh) = &self.password_refresh {
+            if let Some(pool) = refresh.refreshed_pool(forced).await? {
+                // the connections of the previous pool are closed once they're returned
+                *self.pool.write().unwrap_or_else(PoisonError::into_inner) = pool;
+            }
+        }
+        Ok(self
+            .pool
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+}
+
+/// Rebuilds the pool with the new password of its provider. Passwords only authenticate new
+/// connections, so the connections of the previous pool stay open until they're returned.
+struct PoolRefresh {
+    password: PasswordRefresh,
+    opts: Opts,
+}
+
+impl PoolRefresh {
+    fn new_pool(&self, password: &SecretString) -> mysql_async::Pool {
+        let opts = mysql_async::OptsBuilder::from_opts(self.opts.clone())
+            .pass(Some(password.expose_secret()));
+        mysql_async::Pool::new(opts)
+    }
+
+    async fn refreshed_pool(&self, forced: bool) -> Result<Option<mysql_async::Pool>> {
+        let password = self
+            .password
+            .refreshed(forced)
+            .await
+            .context(SecretProviderSnafu)?;
+        Ok(password.map(|password| self.new_pool(&password)))
+    }
+}
+
+impl fmt::Debug for PoolRefresh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolRefresh")
+            .field("password", &self.password)
+            .finish_non_exhaustive()
     }
 }
 
+/// `pool_opts` with the options that are set in `options`.
+fn with_pool_options(pool_opts: PoolOpts, options: &PoolOptions) -> Result<PoolOpts> {
+    let constraints = pool_opts.constraints();
+    let max_size = u32::try_from(constraints.max()).unwrap_or(u32::MAX);
+    options
+        .validate_with_default_max_size(max_size)
+        .context(InvalidPoolOptionsSnafu)?;
+    let max = options
+        .max_size()
+        .map_or(constraints.max(), |max_size| max_size as usize);
+    // the default minimum is lowered to a smaller maximum
+    let min = options
+        .min_idle()
+        .map_or(constraints.min().min(max), |min_idle| min_idle as usize);
+    let mut pool_opts =
+        pool_opts.with_constraints(PoolConstraints::new(min, max).unwrap_or(constraints));
+    if let Some(idle_timeout) = options.idle_timeout() {
+        pool_opts = pool_opts.with_inactive_connection_ttl(idle_timeout);
+    }
+    if let Some(max_lifetime) = options.max_lifetime() {
+        pool_opts = pool_opts.with_abs_conn_ttl(Some(max_lifetime));
+    }
+    Ok(pool_opts)
+}
+
 async fn verify_mysql_opts(opts: &Opts) -> Result<()> {
+    if opts.socket().is_some() {
+        return Ok(());
+    }
+
     // Verify the host and port are correct
     let host = opts.ip_or_hostname();
     let port = opts.tcp_port();
@@ -238,7 +510,10 @@ async fn verify_mysql_opts(opts: &Opts) -> Result<()> {
 }
 
 fn get_join_context(opts: &mysql_async::Opts) -> JoinPushDown {
-    let mut join_context = format!("host={},port={}", opts.ip_or_hostname(), opts.tcp_port());
+    let mut join_context = match opts.socket() {
+        Some(socket) => format!("socket={socket}"),
+        None => format!("host={},port={}", opts.ip_or_hostname(), opts.tcp_port()),
+    };
     if let Some(db_name) = opts.db_name() {
         join_context.push_str(&format!(",db={db_name}"));
     }
@@ -278,8 +553,7 @@ impl DbConnectionPool<mysql_async::Conn, &'static (dyn ToValue + Sync)> for MySQ
         &self,
     ) -> super::Result<Box<dyn DbConnection<mysql_async::Conn, &'static (dyn ToValue + Sync)>>>
     {
-        let pool = Arc::clone(&self.pool);
-        let mut conn = pool.get_conn().await.context(MySQLConnectionSnafu)?;
+        let mut conn = self.get_conn().await?;
 
         // Set MySQL session default time zone to UTC to match Datafusion
         let _: Vec<Row> = conn
@@ -287,10 +561,78 @@ impl DbConnectionPool<mysql_async::Conn, &'static (dyn ToValue + Sync)> for MySQ
             .await
             .context(MySQLConnectionSnafu)?;
 
-        Ok(Box::new(MySQLConnection::new(conn)))
+        Ok(Box::new(
+            MySQLConnection::new(conn)
+                .with_unsigned_bigint_policy(self.unsigned_bigint)
+                .with_timestamp_policy(self.timestamp_policy)
+                .with_result_buffer_rows(self.result_buffer_rows),
+        ))
     }
 
     fn join_push_down(&self) -> JoinPushDown {
         self.join_push_down.clone()
     }
+
+    async fn acquire_query_permit(&self) -> super::Result<Option<QueryPermit>> {
+        self.queries.ensure_open()?;
+        let permit = match &self.query_limiter {
+            Some(limiter) => Some(limiter.acquire().await?),
+            None => None,
+        };
+        Ok(Some(self.queries.start(permit)?))
+    }
+
+    /// Waits for the queries in flight, then disconnects the connections of the pool. The pool is
+    /// only disconnected once every query is done, so the connections are left to close as the
+    /// queries finish when they take longer than `timeout`.
+    async fn close(&self, timeout: Option<Duration>) -> super::Result<()> {
+        self.queries.close(timeout).await?;
+        let pool = self
+            .pool
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        pool.disconnect().await.context(MySQLConnectionSnafu)?;
+        Ok(())
+    }
+
+    async fn warm_up(&self) -> super::Result<()> {
+        let pool = self.current_pool(false).await?;
+        let min_idle = self.min_idle;
+        // the connections return to the pool as idle connections when they're dropped
+        futures::future::try_join_all((0..min_idle).map(|_| pool.get_conn()))
+            .await
+            .context(MySQLConnectionSnafu)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_pool_options() -> Result<()> {
+        let options = PoolOptions::new()
+            .with_max_size(Some(4))
+            .with_idle_timeout(Some(Duration::from_secs(30)))
+            .with_max_lifetime(Some(Duration::from_secs(600)));
+        let pool_opts = with_pool_options(PoolOpts::default(), &options)?;
+        assert_eq!(pool_opts.constraints().max(), 4);
+        assert_eq!(pool_opts.constraints().min(), 4);
+        assert_eq!(pool_opts.inactive_connection_ttl(), Duration::from_secs(30));
+        assert_eq!(pool_opts.abs_conn_ttl(), Some(Duration::from_secs(600)));
+
+        let options = PoolOptions::new().with_min_idle(Some(2));
+        let pool_opts = with_pool_options(PoolOpts::default(), &options)?;
+        assert_eq!(pool_opts.constraints().min(), 2);
+        assert_eq!(
+            pool_opts.constraints().max(),
+            DEFAULT_POOL_CONSTRAINTS.max()
+        );
+
+        let options = PoolOptions::new().with_min_idle(Some(1000));
+        assert!(with_pool_options(PoolOpts::default(), &options).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,255 @@
+use std::{collections::HashMap, time::Duration};
+
+use secrecy::{ExposeSecret, SecretString};
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Could not parse {parameter_name} into a valid integer. Ensure it is configured with a valid value."))]
+    InvalidIntegerParameter {
+        parameter_name: String,
+        source: std::num::ParseIntError,
+    },
+
+    #[snafu(display(
+        "Could not parse {parameter_name} into a valid duration, like '30s' or '10m'.\n{source}"
+    ))]
+    InvalidDurationParameter {
+        parameter_name: String,
+        source: fundu::ParseError,
+    },
+
+    #[snafu(display("min_idle ({min_idle}) can't be larger than max_size ({max_size})."))]
+    MinIdleLargerThanMaxSize { min_idle: u32, max_size: u32 },
+
+    #[snafu(display("max_concurrent_queries must be at least 1."))]
+    NoConcurrentQueries {},
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The sizing and the lifetime of the connections of a pool, and the number of queries that run on
+/// them at once, which all the pools that keep connections open accept in their builder or their
+/// parameters.
+///
+/// Options that aren't set keep the default of the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolOptions {
+    min_idle: Option<u32>,
+    max_size: Option<u32>,
+    idle_timeout: Option<Duration>,
+    max_lifetime: Option<Duration>,
+    max_concurrent_queries: Option<u32>,
+    query_queue_timeout: Option<Duration>,
+}
+
+impl PoolOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The options of the `min_idle`, `max_size`, `idle_timeout`, `max_lifetime`,
+    /// `max_concurrent_queries` and `query_queue_timeout` parameters. The timeouts are durations
+    /// like `30s` or `10m`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a parameter isn't valid, `min_idle` is larger than `max_size` or
+    /// `max_concurrent_queries` is 0.
+    pub fn from_params(params: &HashMap<String, SecretString>) -> Result<Self> {
+        let options = Self {
+            min_idle: integer_param(params, "min_idle")?,
+            max_size: integer_param(params, "max_size")?,
+            idle_timeout: duration_param(params, "idle_timeout")?,
+            max_lifetime: duration_param(params, "max_lifetime")?,
+            max_concurrent_queries: integer_param(params, "max_concurrent_queries")?,
+            query_queue_timeout: duration_param(params, "query_queue_timeout")?,
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// The number of idle connections that the pool keeps open, which
+    /// [`super::DbConnectionPool::warm_up`] opens ahead of the first queries.
+    #[must_use]
+    pub fn with_min_idle(mut self, min_idle: Option<u32>) -> Self {
+        self.min_idle = min_idle;
+        self
+    }
+
+    /// The maximum number of connections of the pool.
+    #[must_use]
+    pub fn with_max_size(mut self, max_size: Option<u32>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// How long a connection stays idle before it's closed, while the pool has more than
+    /// `min_idle` connections.
+    #[must_use]
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// How long a connection is used before it's closed and replaced.
+    #[must_use]
+    pub fn with_max_lifetime(mut self, max_lifetime: Option<Duration>) -> Self {
+        self.max_lifetime = max_lifetime;
+        self
+    }
+
+    /// The maximum number of queries that run at once on the connections of the pool, see
+    /// [`super::query_limit::QueryLimiter`]. The queries beyond it wait for a running one to
+    /// finish.
+    #[must_use]
+    pub fn with_max_concurrent_queries(mut self, max_concurrent_queries: Option<u32>) -> Self {
+        self.max_concurrent_queries = max_concurrent_queries;
+        self
+    }
+
+    /// How long a query waits to run when `max_concurrent_queries` are running, without a timeout
+    /// by default.
+    #[must_use]
+    pub fn with_query_queue_timeout(mut self, query_queue_timeout: Option<Duration>) -> Self {
+        self.query_queue_timeout = query_queue_timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn min_idle(&self) -> Option<u32> {
+        self.min_idle
+    }
+
+    #[must_use]
+    pub fn max_size(&self) -> Option<u32> {
+        self.max_size
+    }
+
+    #[must_use]
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    #[must_use]
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    #[must_use]
+    pub fn max_concurrent_queries(&self) -> Option<u32> {
+        self.max_concurrent_queries
+    }
+
+    #[must_use]
+    pub fn query_queue_timeout(&self) -> Option<Duration> {
+        self.query_queue_timeout
+    }
+
+    /// Checks that `min_idle` isn't larger than `max_size`, or `default_max_size` when the
+    /// maximum isn't set, and that `max_concurrent_queries` isn't 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `min_idle` is larger than the maximum size or `max_concurrent_queries`
+    /// is 0.
+    pub fn validate_with_default_max_size(&self, default_max_size: u32) -> Result<()> {
+        ensure!(
+            self.max_concurrent_queries != Some(0),
+            NoConcurrentQueriesSnafu
+        );
+        if let Some(min_idle) = self.min_idle {
+            let max_size = self.max_size.unwrap_or(default_max_size);
+            ensure!(
+                min_idle <= max_size,
+                MinIdleLargerThanMaxSizeSnafu { min_idle, max_size }
+            );
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        // without a maximum size, `min_idle` is checked against the default of the pool later
+        self.validate_with_default_max_size(self.max_size.unwrap_or(u32::MAX))
+    }
+}
+
+fn integer_param(params: &HashMap<String, SecretString>, name: &str) -> Result<Option<u32>> {
+    params
+        .get(name)
+        .map(|value| {
+            value
+                .expose_secret()
+                .parse()
+                .context(InvalidIntegerParameterSnafu {
+                    parameter_name: name,
+                })
+        })
+        .transpose()
+}
+
+fn duration_param(params: &HashMap<String, SecretString>, name: &str) -> Result<Option<Duration>> {
+    params
+        .get(name)
+        .map(|value| {
+            fundu::parse_duration(value.expose_secret()).context(InvalidDurationParameterSnafu {
+                parameter_name: name,
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(params: &[(&str, &str)]) -> HashMap<String, SecretString> {
+        params
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), SecretString::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_params() -> Result<()> {
+        let options = PoolOptions::from_params(&params(&[
+            ("min_idle", "2"),
+            ("max_size", "8"),
+            ("idle_timeout", "30s"),
+            ("max_lifetime", "10m"),
+            ("max_concurrent_queries", "4"),
+            ("query_queue_timeout", "5s"),
+            ("host", "localhost"),
+        ]))?;
+        assert_eq!(
+            options,
+            PoolOptions::new()
+                .with_min_idle(Some(2))
+                .with_max_size(Some(8))
+                .with_idle_timeout(Some(Duration::from_secs(30)))
+                .with_max_lifetime(Some(Duration::from_secs(600)))
+                .with_max_concurrent_queries(Some(4))
+                .with_query_queue_timeout(Some(Duration::from_secs(5)))
+        );
+        assert_eq!(PoolOptions::from_params(&params(&[]))?, PoolOptions::new());
+
+        assert!(matches!(
+            PoolOptions::from_params(&params(&[("idle_timeout", "soon")])),
+            Err(Error::InvalidDurationParameter { .. })
+        ));
+        assert!(matches!(
+            PoolOptions::from_params(&params(&[("min_idle", "9"), ("max_size", "8")])),
+            Err(Error::MinIdleLargerThanMaxSize { .. })
+        ));
+        assert!(matches!(
+            PoolOptions::from_params(&params(&[("max_concurrent_queries", "0")])),
+            Err(Error::NoConcurrentQueries {})
+        ));
+        assert!(PoolOptions::new()
+            .with_min_idle(Some(20))
+            .validate_with_default_max_size(10)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use datafusion::sql::TableReference;
+
+/// Selects the pool of a table from its name and the options of `CREATE EXTERNAL TABLE`, or
+/// returns `None` for the pool that the factory creates from the options.
+pub type PoolSelectorFn<P> =
+    dyn Fn(&TableReference, &HashMap<String, String>) -> Option<Arc<P>> + Send + Sync;
+
+/// The pools that a table provider factory uses for some of its tables instead of the pool that it
+/// creates from the options of the table, e.g. a larger pool for heavy analytical tables than for
+/// the small dimension tables of the same catalog.
+///
+/// A pool that is set for the name of a table takes precedence over the selector.
+pub struct PoolSelector<P> {
+    table_pools: HashMap<TableReference, Arc<P>>,
+    selector: Option<Arc<PoolSelectorFn<P>>>,
+}
+
+impl<P> PoolSelector<P> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `pool` for the table `table`.
+    #[must_use]
+    pub fn with_table_pool(mut self, table: impl Into<TableReference>, pool: Arc<P>) -> Self {
+        self.table_pools.insert(table.into(), pool);
+        self
+    }
+
+    /// Uses the pool that `selector` returns for the tables that don't have a pool of their own.
+    #[must_use]
+    pub fn with_selector(
+        mut self,
+        selector: impl Fn(&TableReference, &HashMap<String, String>) -> Option<Arc<P>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.selector = Some(Arc::new(selector));
+        self
+    }
+
+    /// The pool of `table`, if one is set for it or the selector returns one.
+    #[must_use]
+    pub fn select(
+        &self,
+        table: &TableReference,
+        options: &HashMap<String, String>,
+    ) -> Option<Arc<P>> {
+        self.table_pools.get(table).cloned().or_else(|| {
+            self.selector
+                .as_ref()
+                .and_then(|selector| selector(table, options))
+        })
+    }
+}
+
+impl<P> Default for PoolSelector<P> {
+    fn default() -> Self {
+        Self {
+            table_pools: HashMap::new(),
+            selector: None,
+        }
+    }
+}
+
+impl<P> Clone for PoolSelector<P> {
+    fn clone(&self) -> Self {
+        Self {
+            table_pools: self.table_pools.clone(),
+            selector: self.selector.clone(),
+        }
+    }
+}
+
+impl<P> fmt::Debug for PoolSelector<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolSelector")
+            .field("tables", &self.table_pools.keys().collect::<Vec<_>>())
+            .field("selector", &self.selector.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let selector = PoolSelector::new()
+            .with_table_pool("events", Arc::new("large"))
+            .with_selector(|table, options| {
+                if options.get("pool").map(String::as_str) == Some("large") {
+                    return Some(Arc::new("large"));
+                }
+                (table.table() == "orders").then(|| Arc::new("selected"))
+            });
+        let options = HashMap::new();
+
+        let select = |table: &str, options: &HashMap<String, String>| {
+            selector
+                .select(&TableReference::from(table), options)
+                .map(|pool| *pool)
+        };
+        assert_eq!(select("events", &options), Some("large"));
+        assert_eq!(select("orders", &options), Some("selected"));
+        assert_eq!(select("customers", &options), None);
+        let options = HashMap::from([("pool".to_string(), "large".to_string())]);
+        assert_eq!(select("customers", &options), Some("large"));
+    }
+}
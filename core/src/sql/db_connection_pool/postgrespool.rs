@@ -1,13 +1,29 @@
-use std::{collections::HashMap, path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+    time::Duration,
+};
 
 use crate::{
+    sql::arrow_sql_gen::{timestamp::TimestampPolicy, uuid_format::UuidFormat},
     util::{self, ns_lookup::verify_ns_lookup_and_tcp_connect},
     UnsupportedTypeAction,
 };
 use async_trait::async_trait;
 use bb8::ErrorSink;
 use bb8_postgres::{
-    tokio_postgres::{config::Host, types::ToSql, Config},
+    tokio_postgres::{
+        config::{Host, SslMode, TargetSessionAttrs},
+        tls::MakeTlsConnect,
+        types::ToSql,
+        Config,
+    },
     PostgresConnectionManager,
 };
 use native_tls::{Certificate, TlsConnector};
@@ -16,9 +32,21 @@ use secrecy::{ExposeSecret, SecretBox, SecretString};
 use snafu::{prelude::*, ResultExt};
 use tokio_postgres;
 
-use super::{runtime::run_async_with_tokio, DbConnectionPool};
+use super::{
+    aws,
+    pool_options::{self, PoolOptions},
+    query_limit::{QueryLimiter, QueryPermit},
+    rds_iam::{RdsIamAuth, RdsIamTokenProvider},
+    runtime::run_async_with_tokio,
+    secrets::{self, PasswordRefresh, SecretProvider},
+    shutdown::{self, QueryTracker},
+    DbConnectionPool,
+};
 use crate::sql::db_connection_pool::{
-    dbconnection::{postgresconn::PostgresConnection, AsyncDbConnection, DbConnection},
+    dbconnection::{
+        postgresconn::{PostgresConnection, ReadProtocol},
+        AsyncDbConnection, DbConnection,
+    },
     JoinPushDown,
 };
 
@@ -75,27 +103,199 @@ pub enum Error {
 
     #[snafu(display("Authentication failed. Verify username and password."))]
     InvalidUsernameOrPassword { source: tokio_postgres::Error },
+
+    #[snafu(display("GSSAPI (Kerberos) authentication is not supported, '{parameter_name}' can't be used. Connect with a password, SCRAM or a client certificate instead."))]
+    UnsupportedGssapiParameter { parameter_name: String },
+
+    #[snafu(display("The server requested GSSAPI, SSPI or Kerberos authentication, which is not supported.\n{source}\nAllow password, SCRAM or certificate authentication for this user in pg_hba.conf."))]
+    UnsupportedAuthenticationMethod { source: tokio_postgres::Error },
+
+    #[snafu(display("IAM authentication failed.\n{source}"))]
+    RdsIamAuthError { source: aws::Error },
+
+    #[snafu(display("IAM authentication requires the '{parameter_name}' parameter."))]
+    MissingRdsIamParameter { parameter_name: String },
+
+    #[snafu(display("Unable to get the password of the connections.\n{source}"))]
+    SecretProviderError { source: secrets::Error },
+
+    #[snafu(display("Invalid connection pool options.\n{source}"))]
+    InvalidPoolOptions { source: pool_options::Error },
+
+    #[snafu(display("IAM tokens are signed for a single host, 'replica_hosts' can't be used with IAM authentication. Create a pool for each replica instead."))]
+    UnsupportedReplicaHostsWithRdsIam,
+
+    #[snafu(display("{source}"))]
+    Shutdown { source: shutdown::Error },
 }
 
+/// The connection parameters of GSSAPI, which the driver can't authenticate with.
+const GSSAPI_PARAMETERS: [&str; 4] = ["krbsrvname", "gsslib", "gssencmode", "gssdelegation"];
+
+/// The default maximum number of connections of the pool, the default of bb8.
+const DEFAULT_POOL_SIZE: u32 = 10;
+
+/// The parameters of the connection string that select the hosts, which the read replicas replace.
+const HOST_PARAMETERS: [&str; 4] = ["host", "hostaddr", "port", "target_session_attrs"];
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+type Pool = bb8::Pool<PostgresConnectionManager<MakeTlsConnector>>;
+type PooledConnection = bb8::PooledConnection<'static, PostgresConnectionManager<MakeTlsConnector>>;
+
+/// The pool of the primary and the pools of the read replicas.
+#[derive(Debug)]
+struct Pools {
+    primary: Pool,
+    replicas: Vec<Pool>,
+}
+
+impl Pools {
+    /// The pool of the replica at `replica`, or of the primary.
+    fn get(&self, replica: Option<usize>) -> &Pool {
+        replica.map_or(&self.primary, |replica| &self.replicas[replica])
+    }
+}
+
 #[derive(Debug)]
 pub struct PostgresConnectionPool {
-    pool: Arc<bb8::Pool<PostgresConnectionManager<MakeTlsConnector>>>,
+    /// The pools, until the pool is closed.
+    pools: RwLock<Option<Arc<Pools>>>,
+    next_replica: AtomicUsize,
+    password_refresh: Option<PoolRefresh>,
+    unpooled: UnpooledConnect,
+    pool_options: PoolOptions,
+    query_limiter: Option<QueryLimiter>,
+    queries: QueryTracker,
     join_push_down: JoinPushDown,
     unsupported_type_action: UnsupportedTypeAction,
+    timestamp_policy: TimestampPolicy,
+    uuid_format: UuidFormat,
+    read_protocol: ReadProtocol,
 }
 
 impl PostgresConnectionPool {
     /// Creates a new instance of `PostgresConnectionPool`.
     ///
+    /// The pool is sized with the `min_idle`, `max_size`, `idle_timeout` and `max_lifetime`
+    /// parameters, see [`PoolOptions::from_params`]. `connection_pool_size` is the maximum size
+    /// when `max_size` isn't set, 10 by default.
+    ///
+    /// With `max_concurrent_queries` set, the queries beyond it wait for a running one to finish,
+    /// for `query_queue_timeout` at most, like `30s`, see [`QueryLimiter`]. This keeps the
+    /// partitions of large scans from overloading a small database.
+    ///
+    /// With `socket` set to the directory of the Unix domain socket of the server, like
+    /// `/var/run/postgresql`, connections are opened over the socket instead of TCP, as they are
+    /// for a `host` of the connection string that starts with `/`. TLS isn't used over the socket,
+    /// which Postgres doesn't support.
+    ///
+    /// With several hosts in `host`, like `primary.example.com,standby.example.com`, connections
+    /// are opened to the first host that matches `target_session_attrs`: `read-write` for the
+    /// primary, `read-only` for a standby or `any`. It is `read-write` by default with several
+    /// hosts, so that the pool fails over to the standby that is promoted when the primary goes
+    /// down: the connections to the previous primary are dropped when they're checked out, and
+    /// their replacements are opened to the new primary.
+    ///
+    /// With `replica_hosts` set to a comma-separated list of `host` or `host:port`, the scans of
+    /// tables run on the read replicas in turn, each with a pool of its own, while writes run on
+    /// the primary. A replica that can't be reached is skipped for the next one, and the scan runs
+    /// on the primary when none can.
+    ///
+    /// With `auth` set to `rds_iam`, connections authenticate with an IAM token of AWS RDS instead
+    /// of a password, signed with the AWS credentials of the default credential chain (the
+    /// environment, `~/.aws` profiles, IRSA, ECS or IMDS) for the region of `aws_region` or
+    /// `AWS_REGION`. The pool is rebuilt with a new token before the token expires, see
+    /// [`super::rds_iam`].
+    ///
+    /// The session time zone of the connections is UTC. With `timestamp_policy` set to `naive`,
+    /// the timestamp columns are surfaced without a time zone, with `utc` in `UTC`, and with
+    /// `source`, the default, `timestamptz` columns in `UTC` and `timestamp` columns without one.
+    ///
+    /// UUID columns are read as `Utf8`, or with `uuid_format` set to `fixed_size_binary` as
+    /// `FixedSizeBinary(16)` with the `arrow.uuid` extension type, which is written back as UUIDs.
+    ///
+    /// With `read_protocol` set to `binary_copy`, the scans without parameters are read with
+    /// `COPY (...) TO STDOUT (FORMAT binary)` instead of row by row, which is much faster for
+    /// large scans, see [`ReadProtocol`].
+    ///
     /// # Errors
     ///
     /// Returns an error if there is a problem creating the connection pool.
     pub async fn new(params: HashMap<String, SecretString>) -> Result<Self> {
+        Self::build(params, None).await
+    }
+
+    /// Creates a new instance of `PostgresConnectionPool` whose connections authenticate with the
+    /// password of `provider` instead of the `pass` parameter.
+    ///
+    /// The password is fetched again after the refresh interval of the provider, and right away
+    /// when the server rejects it. A new password replaces the pool, while the connections of the
+    /// previous pool stay open until they're returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is a problem creating the connection pool or fetching the
+    /// password.
+    pub async fn new_with_password_provider(
+        params: HashMap<String, SecretString>,
+        provider: Arc<dyn SecretProvider>,
+    ) -> Result<Self> {
+        Self::build(params, Some(provider)).await
+    }
+
+    async fn build(
+        params: HashMap<String, SecretString>,
+        password_provider: Option<Arc<dyn SecretProvider>>,
+    ) -> Result<Self> {
         // Remove the "pg_" prefix from the keys to keep backward compatibility
         let params = util::remove_prefix_from_hashmap_keys(params, "pg_");
 
+        if let Some(parameter_name) = find_gssapi_parameter(&params) {
+            return UnsupportedGssapiParameterSnafu { parameter_name }.fail();
+        }
+
+        let replica_hosts = params
+            .get("replica_hosts")
+            .map(SecretBox::expose_secret)
+            .filter(|replica_hosts| !replica_hosts.trim().is_empty());
+
+        let iam_auth = match params.get("auth").map(SecretBox::expose_secret) {
+            Some("rds_iam") => {
+                let region = params.get("aws_region").map(SecretBox::expose_secret);
+                Some(RdsIamAuth::from_env(region).context(RdsIamAuthSnafu)?)
+            }
+            Some(_) => {
+                return InvalidParameterSnafu {
+                    parameter_name: "auth".to_string(),
+                }
+                .fail();
+            }
+            None => None,
+        };
+        ensure!(
+            iam_auth.is_none() || replica_hosts.is_none(),
+            UnsupportedReplicaHostsWithRdsIamSnafu
+        );
+        let timestamp_policy = match params.get("timestamp_policy").map(SecretBox::expose_secret) {
+            Some(value) => TimestampPolicy::parse(value).context(InvalidParameterSnafu {
+                parameter_name: "timestamp_policy".to_string(),
+            })?,
+            None => TimestampPolicy::default(),
+        };
+        let uuid_format = match params.get("uuid_format").map(SecretBox::expose_secret) {
+            Some(value) => UuidFormat::parse(value).context(InvalidParameterSnafu {
+                parameter_name: "uuid_format".to_string(),
+            })?,
+            None => UuidFormat::default(),
+        };
+        let read_protocol = match params.get("read_protocol").map(SecretBox::expose_secret) {
+            Some(value) => ReadProtocol::parse(value).context(InvalidParameterSnafu {
+                parameter_name: "read_protocol".to_string(),
+            })?,
+            None => ReadProtocol::default(),
+        };
+
         let mut connection_string = String::new();
         let mut ssl_mode = "verify-full".to_string();
         let mut ssl_rootcert_path: Option<PathBuf> = None;
@@ -116,7 +316,11 @@ impl PostgresConnectionPool {
                 ssl_rootcert_path = Some(PathBuf::from(sslrootcert));
             }
         } else {
-            if let Some(pg_host) = params.get("host").map(SecretBox::expose_secret) {
+            if let Some(pg_host) = params
+                .get("socket")
+                .or_else(|| params.get("host"))
+                .map(SecretBox::expose_secret)
+            {
                 connection_string.push_str(format!("host={pg_host} ").as_str());
             }
             if let Some(pg_user) = params.get("user").map(SecretBox::expose_secret) {
@@ -156,6 +360,18 @@ impl PostgresConnectionPool {
 
             ssl_rootcert_path = Some(PathBuf::from(pg_sslrootcert));
         }
+        if let Some(attrs) = params
+            .get("target_session_attrs")
+            .map(SecretBox::expose_secret)
+        {
+            ensure!(
+                matches!(attrs, "any" | "read-write" | "read-only"),
+                InvalidParameterSnafu {
+                    parameter_name: "target_session_attrs".to_string(),
+                }
+            );
+            connection_string.push_str(format!("target_session_attrs={attrs} ").as_str());
+        }
 
         let mode = match ssl_mode.as_str() {
             "disable" => "disable",
@@ -165,8 +381,49 @@ impl PostgresConnectionPool {
         };
 
         connection_string.push_str(format!("sslmode={mode} ").as_str());
-        let config = Config::from_str(connection_string.as_str()).context(ConnectionPoolSnafu)?;
+        // timestamps are read and written in UTC whatever the time zone of the server
+        if !connection_string.contains("options=") {
+            connection_string.push_str("options='-c TimeZone=UTC' ");
+        }
+        let mut config =
+            Config::from_str(connection_string.as_str()).context(ConnectionPoolSnafu)?;
+        if connects_over_unix_socket(&config) {
+            config.ssl_mode(SslMode::Disable);
+        }
+        // with several hosts or with replicas, the writes go to the primary unless the
+        // attributes are set
+        if !connection_string.contains("target_session_attrs=")
+            && (config.get_hosts().len() > 1 || replica_hosts.is_some())
+        {
+            config.target_session_attrs(TargetSessionAttrs::ReadWrite);
+        }
+        let default_port = config.get_ports().first().copied().unwrap_or(5432);
+        let mut replica_configs = replica_hosts
+            .map(|replica_hosts| replica_configs(&connection_string, replica_hosts, default_port))
+            .transpose()?
+            .unwrap_or_default();
         verify_postgres_config(&config).await?;
+        let password_provider = match (password_provider, iam_auth) {
+            (Some(provider), _) => Some(provider),
+            (None, Some(iam_auth)) => {
+                Some(Arc::new(rds_iam_token_provider(&config, iam_auth)?) as _)
+            }
+            (None, None) => None,
+        };
+        let password_refresh = match password_provider {
+            Some(provider) => {
+                let refresh = PasswordRefresh::new(provider)
+                    .await
+                    .context(SecretProviderSnafu)?;
+                let password = refresh.password().await;
+                config.password(password.expose_secret());
+                for replica_config in &mut replica_configs {
+                    replica_config.password(password.expose_secret());
+                }
+                Some(refresh)
+            }
+            None => None,
+        };
 
         let mut certs: Option<Vec<Certificate>> = None;
 
@@ -177,25 +434,32 @@ impl PostgresConnectionPool {
 
         let tls_connector = get_tls_connector(ssl_mode.as_str(), certs)?;
         let connector = MakeTlsConnector::new(tls_connector);
-        test_postgres_connection(connection_string.as_str(), connector.clone()).await?;
+        test_postgres_connection(&config, connector.clone()).await?;
 
         let join_push_down = get_join_context(&config);
 
-        let manager = PostgresConnectionManager::new(config, connector);
+        let manager = PostgresConnectionManager::new(config.clone(), connector.clone());
         let error_sink = PostgresErrorSink::new();
 
-        let mut connection_pool_size = 10; // The BB8 default is 10
-        if let Some(pg_pool_size) = params
-            .get("connection_pool_size")
-            .map(SecretBox::expose_secret)
-        {
-            connection_pool_size = pg_pool_size.parse().context(InvalidIntegerParameterSnafu {
-                parameter_name: "pool_size".to_string(),
-            })?;
+        let mut pool_options =
+            PoolOptions::from_params(&params).context(InvalidPoolOptionsSnafu)?;
+        if pool_options.max_size().is_none() {
+            if let Some(pg_pool_size) = params
+                .get("connection_pool_size")
+                .map(SecretBox::expose_secret)
+            {
+                let connection_pool_size =
+                    pg_pool_size.parse().context(InvalidIntegerParameterSnafu {
+                        parameter_name: "pool_size".to_string(),
+                    })?;
+                pool_options = pool_options.with_max_size(Some(connection_pool_size));
+            }
         }
+        pool_options
+            .validate_with_default_max_size(DEFAULT_POOL_SIZE)
+            .context(InvalidPoolOptionsSnafu)?;
 
-        let pool = bb8::Pool::builder()
-            .max_size(connection_pool_size)
+        let pool = pool_builder(&pool_options)
             .error_sink(Box::new(error_sink))
             .build(manager)
             .await
@@ -206,11 +470,47 @@ impl PostgresConnectionPool {
         conn.execute("SELECT 1", &[])
             .await
             .context(ConnectionPoolSnafu)?;
+        drop(conn);
+
+        // the replicas aren't required, so they aren't connected to until they're used
+        let replicas = replica_configs
+            .iter()
+            .map(|replica_config| {
+                replica_pool_builder(&pool_options).build_unchecked(PostgresConnectionManager::new(
+                    replica_config.clone(),
+                    connector.clone(),
+                ))
+            })
+            .collect();
+
+        let unpooled = UnpooledConnect {
+            config: config.clone(),
+            connector: connector.clone(),
+        };
+        let password_refresh = password_refresh.map(|password| PoolRefresh {
+            password,
+            config,
+            replica_configs,
+            connector,
+            pool_options,
+        });
 
         Ok(PostgresConnectionPool {
-            pool: Arc::new(pool.clone()),
+            pools: RwLock::new(Some(Arc::new(Pools {
+                primary: pool,
+                replicas,
+            }))),
+            next_replica: AtomicUsize::new(0),
+            password_refresh,
+            unpooled,
+            query_limiter: QueryLimiter::from_options(&pool_options),
+            queries: QueryTracker::new(),
+            pool_options,
             join_push_down,
             unsupported_type_action: UnsupportedTypeAction::default(),
+            timestamp_policy,
+            uuid_format,
+            read_protocol,
         })
     }
 
@@ -227,10 +527,292 @@ impl PostgresConnectionPool {
     ///
     /// Returns an error if there is a problem creating the connection pool.
     pub async fn connect_direct(&self) -> super::Result<PostgresConnection> {
-        let pool = Arc::clone(&self.pool);
-        let conn = pool.get_owned().await.context(ConnectionPoolRunSnafu)?;
-        Ok(PostgresConnection::new(conn))
+        let conn = self.get_connection().await?;
+        Ok(PostgresConnection::new(conn)
+            .with_timestamp_policy(self.timestamp_policy)
+            .with_uuid_format(self.uuid_format)
+            .with_read_protocol(self.read_protocol))
+    }
+
+    /// Opens a connection to the primary outside of the pool, which the caller drives by polling
+    /// the returned [`tokio_postgres::Connection`], e.g. to receive its notifications. It
+    /// authenticates with the current password of the pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool is closed or the connection can't be opened.
+    pub async fn connect_unpooled(&self) -> Result<(tokio_postgres::Client, UnpooledConnection)> {
+        self.queries.ensure_open().context(ShutdownSnafu)?;
+        let mut config = self.unpooled.config.clone();
+        if let Some(refresh) = &self.password_refresh {
+            config.password(refresh.password.password().await.expose_secret());
+        }
+        config
+            .connect(self.unpooled.connector.clone())
+            .await
+            .context(PostgresConnectionSnafu)
+    }
+
+    /// A connection of the primary.
+    async fn get_connection(&self) -> Result<PooledConnection> {
+        self.get_connection_from(None).await
+    }
+
+    /// A connection of the next read replica in turn, or of the primary when no replica can be
+    /// reached.
+    async fn get_read_only_connection(&self) -> Result<PooledConnection> {
+        let replicas = self.current_pools(false).await?.replicas.len();
+        let first = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..replicas {
+            let replica = (first + offset) % replicas;
+            match self.get_connection_from(Some(replica)).await {
+                Ok(conn) => return Ok(conn),
+                Err(err) => {
+                    tracing::warn!(
+                        "Read replica {replica} can't be reached, trying the next host.\n{err}"
+                    );
+                }
+            }
+        }
+        self.get_connection().await
+    }
+
+    /// A connection of the replica at `replica`, or of the primary. When the server rejects the
+    /// password of the connection, the password is fetched again and the connection is retried
+    /// once with the new one.
+    async fn get_connection_from(&self, replica: Option<usize>) -> Result<PooledConnection> {
+        let pools = self.current_pools(false).await?;
+        match pools.get(replica).get_owned().await {
+            Err(bb8::RunError::User(err))
+                if self.password_refresh.is_some() && is_invalid_password(&err) =>
+            {
+                tracing::debug!("The password was rejected, fetching it again");
+                let pools = self.current_pools(true).await?;
+                pools
+                    .get(replica)
+                    .get_owned()
+                    .await
+                    .context(ConnectionPoolRunSnafu)
+            }
+            result => result.context(ConnectionPoolRunSnafu),
+        }
     }
+
+    /// The pools of the connections, which are replaced by pools with a new password first if the
+    /// password is due for a refresh, or the refresh is `forced`, and it changed.
+    async fn current_pools(&self, forced: bool) -> Result<Arc<Pools>> {
+        self.queries.ensure_open().context(ShutdownSnafu)?;
+        if let Some(refresh) = &self.password_refresh {
+            if let Some(pools) = refresh.refreshed_pools(forced).await? {
+                let mut current = self.pools.write().unwrap_or_else(PoisonError::into_inner);
+                if current.is_some() {
+                    *current = Some(Arc::new(pools));
+                }
+            }
+        }
+        self.pools
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .ok_or(Error::Shutdown {
+                source: shutdown::Error::PoolClosed,
+            })
+    }
+}
+
+/// Provides the IAM tokens of the user and the first TCP host of `config`.
+fn rds_iam_token_provider(config: &Config, auth: RdsIamAuth) -> Result<RdsIamTokenProvider> {
+    let host = config
+        .get_hosts()
+        .iter()
+        .find_map(|host| match host {
+            Host::Tcp(host) => Some(host.clone()),
+            #[cfg(unix)]
+            Host::Unix(_) => None,
+        })
+        .context(MissingRdsIamParameterSnafu {
+            parameter_name: "host",
+        })?;
+    let user = config.get_user().context(MissingRdsIamParameterSnafu {
+        parameter_name: "user",
+    })?;
+    let port = config.get_ports().first().copied().unwrap_or(5432);
+    Ok(RdsIamTokenProvider::new(auth, host, port, user))
+}
+
+/// Whether all the hosts of `config` are Unix domain sockets, which the server doesn't accept TLS
+/// connections on.
+fn connects_over_unix_socket(config: &Config) -> bool {
+    !config.get_hosts().is_empty()
+        && config.get_hosts().iter().all(|host| match host {
+            Host::Tcp(_) => false,
+            #[cfg(unix)]
+            Host::Unix(_) => true,
+        })
+}
+
+/// The configs of the read replicas of `replica_hosts`, a comma-separated list of `host` or
+/// `host:port`, which connect like `connection_string` but to their replica instead of its hosts.
+fn replica_configs(
+    connection_string: &str,
+    replica_hosts: &str,
+    default_port: u16,
+) -> Result<Vec<Config>> {
+    let connection_string = connection_string
+        .split_whitespace()
+        .filter(|param| {
+            param
+                .split_once('=')
+                .is_none_or(|(name, _)| !HOST_PARAMETERS.contains(&name))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    replica_hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|replica| !replica.is_empty())
+        .map(|replica| {
+            let (host, port) = match replica.split_once(':') {
+                Some((host, port)) => (
+                    host,
+                    port.parse().context(InvalidIntegerParameterSnafu {
+                        parameter_name: "replica_hosts".to_string(),
+                    })?,
+                ),
+                None => (replica, default_port),
+            };
+            let mut config =
+                Config::from_str(&format!("{connection_string} host={host} port={port}"))
+                    .context(ConnectionPoolSnafu)?;
+            if connects_over_unix_socket(&config) {
+                config.ssl_mode(SslMode::Disable);
+            }
+            Ok(config)
+        })
+        .collect()
+}
+
+/// The builder of a pool with `options`, which keeps the defaults of bb8 for the options that aren't
+/// set.
+fn pool_builder(
+    options: &PoolOptions,
+) -> bb8::Builder<PostgresConnectionManager<MakeTlsConnector>> {
+    let mut builder = bb8::Pool::builder()
+        .max_size(options.max_size().unwrap_or(DEFAULT_POOL_SIZE))
+        .min_idle(options.min_idle());
+    if let Some(idle_timeout) = options.idle_timeout() {
+        builder = builder.idle_timeout(Some(idle_timeout));
+    }
+    if let Some(max_lifetime) = options.max_lifetime() {
+        builder = builder.max_lifetime(Some(max_lifetime));
+    }
+    builder
+}
+
+/// The builder of the pool of a read replica, which fails right away when the replica can't be
+/// reached so that the next host is tried.
+fn replica_pool_builder(
+    options: &PoolOptions,
+) -> bb8::Builder<PostgresConnectionManager<MakeTlsConnector>> {
+    pool_builder(options).retry_connection(false)
+}
+
+fn is_invalid_password(err: &tokio_postgres::Error) -> bool {
+    err.code() == Some(&tokio_postgres::error::SqlState::INVALID_PASSWORD)
+}
+
+/// A connection opened by [`PostgresConnectionPool::connect_unpooled`].
+pub type UnpooledConnection = tokio_postgres::Connection<
+    tokio_postgres::Socket,
+    <MakeTlsConnector as MakeTlsConnect<tokio_postgres::Socket>>::Stream,
+>;
+
+/// How the connections outside of the pools are opened, see
+/// [`PostgresConnectionPool::connect_unpooled`].
+struct UnpooledConnect {
+    config: Config,
+    connector: MakeTlsConnector,
+}
+
+impl fmt::Debug for UnpooledConnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnpooledConnect")
+            .field("hosts", &self.config.get_hosts())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Rebuilds the pools with the new password of its provider. Passwords only authenticate new
+/// connections, so the connections of the previous pools stay open until they're returned.
+struct PoolRefresh {
+    password: PasswordRefresh,
+    config: Config,
+    replica_configs: Vec<Config>,
+    connector: MakeTlsConnector,
+    pool_options: PoolOptions,
+}
+
+impl PoolRefresh {
+    async fn refreshed_pools(&self, forced: bool) -> Result<Option<Pools>> {
+        let Some(password) = self
+            .password
+            .refreshed(forced)
+            .await
+            .context(SecretProviderSnafu)?
+        else {
+            return Ok(None);
+        };
+
+        let manager = |config: &Config| {
+            let mut config = config.clone();
+            config.password(password.expose_secret());
+            PostgresConnectionManager::new(config, self.connector.clone())
+        };
+        // bb8 spawns the reapers of the new pools on the runtime
+        Ok(Some(Pools {
+            primary: pool_builder(&self.pool_options)
+                .error_sink(Box::new(PostgresErrorSink::new()))
+                .build_unchecked(manager(&self.config)),
+            replicas: self
+                .replica_configs
+                .iter()
+                .map(|config| {
+                    replica_pool_builder(&self.pool_options).build_unchecked(manager(config))
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl fmt::Debug for PoolRefresh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolRefresh")
+            .field("password", &self.password)
+            .field("replicas", &self.replica_configs.len())
+            .field("pool_options", &self.pool_options)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The first GSSAPI parameter in `params` or in their connection string. `gssencmode=disable` is
+/// allowed, since it turns GSSAPI off.
+fn find_gssapi_parameter(params: &HashMap<String, SecretString>) -> Option<String> {
+    let connection_string = params
+        .get("connection_string")
+        .map(SecretBox::expose_secret)
+        .unwrap_or_default();
+    let connection_string_params = connection_string
+        .split_whitespace()
+        .filter_map(|param| param.split_once('='));
+    params
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.expose_secret()))
+        .chain(connection_string_params)
+        .find(|(name, value)| {
+            GSSAPI_PARAMETERS.contains(name) && !(*name == "gssencmode" && *value == "disable")
+        })
+        .map(|(name, _)| name.to_string())
 }
 
 fn parse_connection_string(pg_connection_string: &str) -> (String, String, Option<String>) {
@@ -278,11 +860,8 @@ fn get_join_context(config: &Config) -> JoinPushDown {
     JoinPushDown::AllowedFor(join_push_context_str)
 }
 
-async fn test_postgres_connection(
-    connection_string: &str,
-    connector: MakeTlsConnector,
-) -> Result<()> {
-    match tokio_postgres::connect(connection_string, connector).await {
+async fn test_postgres_connection(config: &Config, connector: MakeTlsConnector) -> Result<()> {
+    match config.connect(connector).await {
         Ok(_) => Ok(()),
         Err(err) => {
             if let Some(code) = err.code() {
@@ -290,24 +869,41 @@ async fn test_postgres_connection(
                     return Err(Error::InvalidUsernameOrPassword { source: err });
                 }
             }
+            // the driver only reports the authentication requests that it can't answer like this
+            if err
+                .to_string()
+                .contains("unsupported authentication method")
+            {
+                return Err(Error::UnsupportedAuthenticationMethod { source: err });
+            }
 
             Err(Error::PostgresConnectionError { source: err })
         }
     }
 }
 
+/// Checks that a host of `config` can be reached, since the connections fail over to the other
+/// hosts when one is down.
 async fn verify_postgres_config(config: &Config) -> Result<()> {
+    let mut first_error = None;
     for host in config.get_hosts() {
         for port in config.get_ports() {
-            if let Host::Tcp(host) = host {
-                verify_ns_lookup_and_tcp_connect(host, *port)
-                    .await
-                    .context(InvalidHostOrPortSnafu { host, port: *port })?;
+            let Host::Tcp(host) = host else {
+                return Ok(());
+            };
+            match verify_ns_lookup_and_tcp_connect(host, *port)
+                .await
+                .context(InvalidHostOrPortSnafu { host, port: *port })
+            {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
             }
         }
     }
 
-    Ok(())
+    first_error.map_or(Ok(()), Err)
 }
 
 fn get_tls_connector(ssl_mode: &str, rootcerts: Option<Vec<Certificate>>) -> Result<TlsConnector> {
@@ -384,16 +980,157 @@ impl
             >,
         >,
     > {
-        let pool = Arc::clone(&self.pool);
-        let get_conn = async || pool.get_owned().await.context(ConnectionPoolRunSnafu);
-        let conn = run_async_with_tokio(get_conn).await?;
+        let conn = run_async_with_tokio(async || self.get_connection().await).await?;
+        Ok(Box::new(
+            PostgresConnection::new(conn)
+                .with_unsupported_type_action(self.unsupported_type_action)
+                .with_timestamp_policy(self.timestamp_policy)
+                .with_uuid_format(self.uuid_format)
+                .with_read_protocol(self.read_protocol),
+        ))
+    }
+
+    async fn connect_read_only(
+        &self,
+    ) -> super::Result<
+        Box<
+            dyn DbConnection<
+                bb8::PooledConnection<'static, PostgresConnectionManager<MakeTlsConnector>>,
+                &'static (dyn ToSql + Sync),
+            >,
+        >,
+    > {
+        let conn = run_async_with_tokio(async || self.get_read_only_connection().await).await?;
         Ok(Box::new(
             PostgresConnection::new(conn)
-                .with_unsupported_type_action(self.unsupported_type_action),
+                .with_unsupported_type_action(self.unsupported_type_action)
+                .with_timestamp_policy(self.timestamp_policy)
+                .with_uuid_format(self.uuid_format)
+                .with_read_protocol(self.read_protocol),
         ))
     }
 
     fn join_push_down(&self) -> JoinPushDown {
         self.join_push_down.clone()
     }
+
+    async fn acquire_query_permit(&self) -> super::Result<Option<QueryPermit>> {
+        self.queries.ensure_open()?;
+        let permit = match &self.query_limiter {
+            Some(limiter) => Some(limiter.acquire().await?),
+            None => None,
+        };
+        Ok(Some(self.queries.start(permit)?))
+    }
+
+    /// Waits for the queries in flight, then drops the pools of the primary and the replicas,
+    /// which closes their idle connections and the connections of the queries once they're done.
+    async fn close(&self, timeout: Option<Duration>) -> super::Result<()> {
+        let closed = self.queries.close(timeout).await;
+        self.pools
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        Ok(closed?)
+    }
+
+    async fn warm_up(&self) -> super::Result<()> {
+        let min_idle = self.pool_options.min_idle().unwrap_or_default();
+        let pools = self.current_pools(false).await?;
+        // the connections return to the pool as idle connections when they're dropped
+        futures::future::try_join_all((0..min_idle).map(|_| pools.primary.get()))
+            .await
+            .context(ConnectionPoolRunSnafu)?;
+        for (replica, pool) in pools.replicas.iter().enumerate() {
+            if let Err(err) = futures::future::try_join_all((0..min_idle).map(|_| pool.get())).await
+            {
+                tracing::warn!("Read replica {replica} can't be reached to warm up.\n{err}");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(params: &[(&str, &str)]) -> HashMap<String, SecretString> {
+        params
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), SecretString::from(*value)))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_connects_over_unix_socket() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config::from_str("host=/var/run/postgresql user=postgres sslmode=require")?;
+        assert!(connects_over_unix_socket(&config));
+        let config = Config::from_str("host=/var/run/postgresql,localhost user=postgres")?;
+        assert!(!connects_over_unix_socket(&config));
+        let config = Config::from_str("user=postgres")?;
+        assert!(!connects_over_unix_socket(&config));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replica_configs() -> Result<()> {
+        let configs = replica_configs(
+            "host=primary port=5433 user=postgres target_session_attrs=read-write sslmode=disable",
+            "replica-1:5434, replica-2,",
+            5433,
+        )?;
+        let hosts = configs
+            .iter()
+            .map(|config| (config.get_hosts().to_vec(), config.get_ports().to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hosts,
+            vec![
+                (vec![Host::Tcp("replica-1".to_string())], vec![5434]),
+                (vec![Host::Tcp("replica-2".to_string())], vec![5433]),
+            ]
+        );
+        for config in &configs {
+            assert_eq!(config.get_user(), Some("postgres"));
+            assert_eq!(config.get_ssl_mode(), SslMode::Disable);
+            assert_eq!(config.get_target_session_attrs(), TargetSessionAttrs::Any);
+        }
+
+        assert!(matches!(
+            replica_configs("user=postgres", "replica:port", 5432),
+            Err(Error::InvalidIntegerParameterError { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_gssapi_parameter() {
+        assert_eq!(
+            find_gssapi_parameter(&params(&[
+                ("host", "localhost"),
+                ("krbsrvname", "postgres")
+            ])),
+            Some("krbsrvname".to_string())
+        );
+        assert_eq!(
+            find_gssapi_parameter(&params(&[(
+                "connection_string",
+                "host=localhost gssencmode=require"
+            )])),
+            Some("gssencmode".to_string())
+        );
+        assert_eq!(
+            find_gssapi_parameter(&params(&[(
+                "connection_string",
+                "host=localhost gssencmode=disable"
+            )])),
+            None
+        );
+        assert_eq!(
+            find_gssapi_parameter(&params(&[("host", "localhost"), ("user", "gsslib")])),
+            None
+        );
+    }
 }
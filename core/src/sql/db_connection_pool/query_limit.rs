@@ -0,0 +1,158 @@
+//! A cap on the number of queries that run at once on the database of a pool, so that the
+//! partitions of a large scan don't overload a small database. The queries beyond the cap wait for
+//! a running one to finish, for `query_queue_timeout` at most.
+
+use std::{sync::Arc, time::Duration};
+
+use datafusion::{
+    execution::SendableRecordBatchStream, physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::StreamExt;
+use snafu::prelude::*;
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+use super::pool_options::PoolOptions;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The query waited more than {timeout:?} for one of the {max_concurrent_queries} concurrent queries of the pool to finish.\nRaise max_concurrent_queries or query_queue_timeout, or run fewer queries at once."))]
+    QueryQueueTimeout {
+        max_concurrent_queries: u32,
+        timeout: Duration,
+    },
+
+    #[snafu(display("The query limiter of the pool is closed.\n{source}"))]
+    QueryLimiterClosed { source: AcquireError },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Limits the queries that run at once on a database to `max_concurrent_queries`.
+#[derive(Debug, Clone)]
+pub struct QueryLimiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent_queries: u32,
+    queue_timeout: Option<Duration>,
+}
+
+impl QueryLimiter {
+    /// A limiter of `max_concurrent_queries`, whose queries wait for `queue_timeout` at most, or
+    /// as long as it takes without a timeout.
+    #[must_use]
+    pub fn new(max_concurrent_queries: u32, queue_timeout: Option<Duration>) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent_queries as usize)),
+            max_concurrent_queries,
+            queue_timeout,
+        }
+    }
+
+    /// The limiter of the `max_concurrent_queries` and `query_queue_timeout` of `options`, or
+    /// `None` if the queries aren't capped.
+    #[must_use]
+    pub fn from_options(options: &PoolOptions) -> Option<Self> {
+        options
+            .max_concurrent_queries()
+            .map(|max| Self::new(max, options.query_queue_timeout()))
+    }
+
+    /// Waits for a query to be allowed to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::QueryQueueTimeout`] if no query finished within the queue timeout.
+    pub async fn acquire(&self) -> Result<QueryPermit> {
+        let acquire = Arc::clone(&self.semaphore).acquire_owned();
+        let permit = match self.queue_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire).await.map_err(|_| {
+                QueryQueueTimeoutSnafu {
+                    max_concurrent_queries: self.max_concurrent_queries,
+                    timeout,
+                }
+                .build()
+            })?,
+            None => acquire.await,
+        }
+        .context(QueryLimiterClosedSnafu)?;
+        Ok(QueryPermit::new(permit))
+    }
+
+    /// The number of queries that can start without waiting.
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.semaphore.availablepermits()
+    }
+}
+
+/// Allows a query to run until it's dropped.
+#[derive(Debug)]
+pub struct QueryPermit {
+    permits: Vec<OwnedSemaphorePermit>,
+}
+
+impl QueryPermit {
+    pub(crate) fn new(permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            permits: vec![permit],
+        }
+    }
+
+    /// The permit, which also holds `permit` until it's dropped.
+    #[must_use]
+    pub(crate) fn with_permit(mut self, permit: OwnedSemaphorePermit) -> Self {
+        self.permits.push(permit);
+        self
+    }
+
+    /// `stream`, which holds the permit until it's dropped.
+    #[must_use]
+    pub fn hold(self, stream: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        let schema = stream.schema();
+        let stream = stream.map(move |batch| {
+            let _ = &self;
+            batch
+        });
+        Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queries_wait_for_a_permit() -> Result<()> {
+        let limiter = QueryLimiter::new(2, Some(Duration::from_millis(50)));
+        let first = limiter.acquire().await?;
+        let _second = limiter.acquire().await?;
+        assert_eq!(limiter.available(), 0);
+
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(Error::QueryQueueTimeout {
+                max_concurrent_queries: 2,
+                ..
+            })
+        ));
+
+        drop(first);
+        let _third = limiter.acquire().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_permit() -> Result<()> {
+        let limiter = QueryLimiter::new(1, None);
+        let schema = Arc::new(datafusion::arrow::datatypes::Schema::empty());
+        let stream: SendableRecordBatchStream = Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            futures::stream::empty(),
+        ));
+
+        let stream = limiter.acquire().await?.hold(stream);
+        assert_eq!(limiter.available(), 0);
+        drop(stream);
+        assert_eq!(limiter.available(), 1);
+        Ok(())
+    }
+}
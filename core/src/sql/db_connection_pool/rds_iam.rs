@@ -0,0 +1,250 @@
+//! IAM authentication for AWS RDS and Aurora, where a short-lived token signed with the AWS
+//! credentials of the client is the password of the database user.
+//!
+//! See <https://docs.aws.amazon.com/AmazonRDS/latest/UserGuide/UsingWithRDS.IAMDBAuth.html>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use aws_sigv4::http_request::{SignatureLocation, SigningSettings};
+use chrono::{DateTime, Utc};
+use secrecy::SecretString;
+
+use super::{
+    aws::{
+        self, region_or_env, sign, signable_request, uri_encode, AwsCredentials,
+        AwsCredentialsProvider, DefaultCredentialsProvider,
+    },
+    secrets::{self, SecretProvider},
+};
+
+/// How long a token can be used to open connections.
+pub const TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// How long a token is used before a new one is generated, so that connections are never opened
+/// with a token that is about to expire.
+pub const TOKEN_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+const SERVICE: &str = "rds-db";
+
+/// Generates the tokens that authenticate a database user with IAM.
+#[derive(Debug, Clone)]
+pub struct RdsIamAuth {
+    region: String,
+    credentials: Arc<dyn AwsCredentialsProvider>,
+}
+
+impl RdsIamAuth {
+    #[must_use]
+    pub fn new(region: impl Into<String>, credentials: AwsCredentials) -> Self {
+        Self::from_provider(region, Arc::new(credentials))
+    }
+
+    /// Signs every token with the credentials `credentials` provides at the time, so that
+    /// temporary credentials are renewed along with the tokens.
+    #[must_use]
+    pub fn from_provider(
+        region: impl Into<String>,
+        credentials: Arc<dyn AwsCredentialsProvider>,
+    ) -> Self {
+        Self {
+            region: region.into(),
+            credentials,
+        }
+    }
+
+    /// Signs tokens with the credentials of the default credential chain, loaded again for every
+    /// token, in `region` or the region of `AWS_REGION` or `AWS_DEFAULT_REGION`. See
+    /// [`DefaultCredentialsProvider`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the region isn't set.
+    pub fn from_env(region: Option<&str>) -> aws::Result<Self> {
+        let region = region_or_env(region)?;
+        Ok(Self::from_provider(
+            region,
+            Arc::new(DefaultCredentialsProvider::default()),
+        ))
+    }
+
+    /// A token for `user` on the database at `host` and `port`, valid for [`TOKEN_LIFETIME`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider of the credentials fails or the token can't be signed.
+    pub async fn generate_token(
+        &self,
+        host: &str,
+        port: u16,
+        user: &str,
+    ) -> aws::Result<SecretString> {
+        let credentials = self.credentials.credentials().await?;
+        self.generate_token_at(&credentials, host, port, user, Utc::now())
+    }
+
+    /// The URL of a `connect` request presigned with Signature Version 4, without its scheme.
+    fn generate_token_at(
+        &self,
+        credentials: &AwsCredentials,
+        host: &str,
+        port: u16,
+        user: &str,
+        now: DateTime<Utc>,
+    ) -> aws::Result<SecretString> {
+        let mut settings = SigningSettings::default();
+        settings.expires_in = Some(TOKEN_LIFETIME);
+        settings.signature_location = SignatureLocation::QueryParams;
+
+        let token = format!("{host}:{port}/?Action=connect&DBUser={}", uri_encode(user));
+        let url = format!("https://{token}");
+        // the payload of the request is empty
+        let request = signable_request("GET", &url, &[], b"")?;
+        let instructions = sign(credentials, &self.region, SERVICE, now, settings, request)?;
+
+        let query = instructions
+            .params()
+            .iter()
+            .map(|(name, value)| format!("&{name}={}", uri_encode(value)))
+            .collect::<String>();
+        Ok(format!("{token}{query}").into())
+    }
+}
+
+/// Provides the IAM tokens of a database user as its password, see [`SecretProvider`].
+#[derive(Debug, Clone)]
+pub struct RdsIamTokenProvider {
+    auth: RdsIamAuth,
+    host: String,
+    port: u16,
+    user: String,
+}
+
+impl RdsIamTokenProvider {
+    #[must_use]
+    pub fn new(
+        auth: RdsIamAuth,
+        host: impl Into<String>,
+        port: u16,
+        user: impl Into<String>,
+    ) -> Self {
+        Self {
+            auth,
+            host: host.into(),
+            port,
+            user: user.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for RdsIamTokenProvider {
+    async fn secret(&self) -> secrets::Result<SecretString> {
+        self.auth
+            .generate_token(&self.host, self.port, &self.user)
+            .await
+            .map_err(|source| secrets::Error::Aws { source })
+    }
+
+    fn refresh_interval(&self) -> Duration {
+        TOKEN_REFRESH_INTERVAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::TimeZone;
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    #[test]
+    fn test_generate_token() {
+        let credentials = AwsCredentials::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            Some("session/token=".into()),
+        );
+        let auth = RdsIamAuth::new("us-west-2", credentials.clone());
+        let now = Utc
+            .with_ymd_and_hms(2024, 1, 2, 3, 4, 5)
+            .single()
+            .expect("valid date");
+        let token = auth
+            .generate_token_at(&credentials, "db.example.com", 5432, "app user", now)
+            .expect("token generated");
+        let token = token.expose_secret();
+
+        // the token of botocore's `generate_db_auth_token` with the same credentials and time
+        let (endpoint, query) = token.split_once("/?").expect("presigned URL");
+        assert_eq!(endpoint, "db.example.com:5432");
+        let mut params = query
+            .split('&')
+            .map(|param| param.split_once('=').expect("query parameter"))
+            .collect::<Vec<_>>();
+        params.sort_unstable();
+        assert_eq!(
+            params,
+            [
+                ("Action", "connect"),
+                ("DBUser", "app%20user"),
+                ("X-Amz-Algorithm", "AWS4-HMAC-SHA256"),
+                (
+                    "X-Amz-Credential",
+                    "AKIDEXAMPLE%2F20240102%2Fus-west-2%2Frds-db%2Faws4_request"
+                ),
+                ("X-Amz-Date", "20240102T030405Z"),
+                ("X-Amz-Expires", "900"),
+                ("X-Amz-Security-Token", "session%2Ftoken%3D"),
+                (
+                    "X-Amz-Signature",
+                    "2828af4f5c319db776d7522edc78b2a64606963909ef71e840ae506064e3078f"
+                ),
+                ("X-Amz-SignedHeaders", "host"),
+            ]
+        );
+    }
+
+    /// Temporary credentials, renewed by replacing them.
+    #[derive(Debug)]
+    struct RotatingCredentials(Mutex<AwsCredentials>);
+
+    #[async_trait]
+    impl AwsCredentialsProvider for RotatingCredentials {
+        async fn credentials(&self) -> aws::Result<AwsCredentials> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotated_credentials() {
+        let temporary = |access_key_id: &str| {
+            AwsCredentials::new(access_key_id, "secret".into(), Some("token".into()))
+        };
+        let credentials = Arc::new(RotatingCredentials(Mutex::new(temporary("ASIAFIRST"))));
+        let provider = RdsIamTokenProvider::new(
+            RdsIamAuth::from_provider("us-west-2", Arc::clone(&credentials) as _),
+            "db.example.com",
+            5432,
+            "app",
+        );
+
+        let token = provider.secret().await.expect("token generated");
+        assert!(token
+            .expose_secret()
+            .contains("X-Amz-Credential=ASIAFIRST%2F"));
+
+        // the next token is signed with the renewed credentials
+        *credentials.0.lock().expect("credentials unlocked") = temporary("ASIASECOND");
+        let token = provider.secret().await.expect("token generated");
+        assert!(token
+            .expose_secret()
+            .contains("X-Amz-Credential=ASIASECOND%2F"));
+    }
+}
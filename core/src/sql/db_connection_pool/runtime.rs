@@ -3,7 +3,7 @@
 // tokio runtime, so we need to start a new one.
 use std::{future::Future, sync::OnceLock};
 
-use tokio::runtime::Handle;
+use tokio::runtime::{Handle, RuntimeFlavor};
 
 pub(crate) struct TokioRuntime(tokio::runtime::Runtime);
 
@@ -21,6 +21,12 @@ where
     get_tokio_runtime().0.block_on(f())
 }
 
+/// Returns a handle to the runtime of [`execute_in_tokio`], for hosts without a tokio runtime
+/// that have to run the streams of the table providers, like the ones loading them over FFI.
+pub fn tokio_handle() -> Handle {
+    get_tokio_runtime().0.handle().clone()
+}
+
 pub async fn run_async_with_tokio<F, Fut, T, E>(f: F) -> Result<T, E>
 where
     F: FnOnce() -> Fut,
@@ -38,3 +44,24 @@ pub fn run_sync_with_tokio<T, E>(f: impl FnOnce() -> Result<T, E>) -> Result<T,
         Err(_) => execute_in_tokio(|| async { f() }),
     }
 }
+
+/// Runs the future of `f` to completion from synchronous code, like the display of an execution
+/// plan, that may itself run on a thread of the runtime.
+///
+/// Returns `None` without calling `f` on a current-thread runtime, whose only thread can't be
+/// blocked on a future that needs the runtime.
+pub(crate) fn block_on_runtime<F, Fut>(f: F) -> Option<Fut::Output>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future,
+{
+    match Handle::try_current() {
+        Ok(handle) => match handle.runtime_flavor() {
+            RuntimeFlavor::MultiThread => {
+                Some(tokio::task::block_in_place(|| handle.block_on(f())))
+            }
+            _ => None,
+        },
+        Err(_) => Some(execute_in_tokio(f)),
+    }
+}
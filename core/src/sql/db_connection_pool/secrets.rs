@@ -0,0 +1,479 @@
+//! Providers of the passwords of connection pools, so that passwords are fetched from where they're
+//! stored and rotated instead of being passed in the parameters of the pool.
+//!
+//! The pools fetch the password again after the [`SecretProvider::refresh_interval`] of the
+//! provider, and right away when the database rejects it. A pool that gets a different password
+//! replaces its connections with ones opened with the new password.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use snafu::prelude::*;
+
+/// How long the password of a provider is used before it's fetched again by default.
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The environment variable {name} is not set."))]
+    MissingEnvironmentVariable { name: String },
+
+    #[snafu(display("Unable to read the secret from {}.\n{source}", path.display()))]
+    UnableToReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("The secret is not a JSON object.\n{source}"))]
+    InvalidJson { source: serde_json::Error },
+
+    #[snafu(display("The secret has no key '{key}'."))]
+    MissingKey { key: String },
+
+    #[cfg(any(feature = "aws-secrets-manager", feature = "vault"))]
+    #[snafu(display("Unable to fetch the secret.\n{source}"))]
+    UnableToFetch { source: reqwest::Error },
+
+    #[snafu(display("Unable to fetch the secret, the server returned {status}: {body}"))]
+    UnexpectedResponse { status: u16, body: String },
+
+    #[snafu(display("{source}"))]
+    Aws { source: super::aws::Error },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Provides a secret, like the password of a database user.
+#[async_trait]
+pub trait SecretProvider: fmt::Debug + Send + Sync {
+    /// The current value of the secret.
+    async fn secret(&self) -> Result<SecretString>;
+
+    /// How long a value is used before it's fetched again, to pick up rotated secrets.
+    fn refresh_interval(&self) -> Duration {
+        DEFAULT_REFRESH_INTERVAL
+    }
+}
+
+/// Reads the secret from an environment variable.
+#[derive(Debug, Clone)]
+pub struct EnvSecretProvider {
+    name: String,
+}
+
+impl EnvSecretProvider {
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for EnvSecretProvider {
+    async fn secret(&self) -> Result<SecretString> {
+        std::env::var(&self.name)
+            .ok()
+            .map(SecretString::from)
+            .context(MissingEnvironmentVariableSnafu { name: &self.name })
+    }
+}
+
+/// Reads the secret from a file, like the secrets that Kubernetes or Docker mount and update in
+/// place when they're rotated. A trailing newline isn't part of the secret.
+#[derive(Debug, Clone)]
+pub struct FileSecretProvider {
+    path: PathBuf,
+    json_key: Option<String>,
+}
+
+impl FileSecretProvider {
+    #[must_use]
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            json_key: None,
+        }
+    }
+
+    /// Reads the secret from the value of `key` in the JSON object of the file.
+    #[must_use]
+    pub fn with_json_key(mut self, key: impl Into<String>) -> Self {
+        self.json_key = Some(key.into());
+        self
+    }
+}
+
+#[async_trait]
+impl SecretProvider for FileSecretProvider {
+    async fn secret(&self) -> Result<SecretString> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .context(UnableToReadFileSnafu { path: &self.path })?;
+        let secret = contents.trim_end_matches(['\n', '\r']);
+        match &self.json_key {
+            Some(key) => json_value(secret, key),
+            None => Ok(secret.into()),
+        }
+    }
+}
+
+/// The value of `key` in the JSON object `json`.
+fn json_value(json: &str, key: &str) -> Result<SecretString> {
+    let object: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(json).context(InvalidJsonSnafu)?;
+    match object.get(key) {
+        Some(serde_json::Value::String(value)) => Ok(value.as_str().into()),
+        Some(value) => Ok(value.to_string().into()),
+        None => MissingKeySnafu { key }.fail(),
+    }
+}
+
+#[cfg(feature = "aws-secrets-manager")]
+pub use aws_secrets_manager::AwsSecretsManagerProvider;
+
+#[cfg(feature = "aws-secrets-manager")]
+mod aws_secrets_manager {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use aws_sigv4::http_request::SigningSettings;
+    use chrono::Utc;
+    use secrecy::{ExposeSecret, SecretString};
+    use snafu::prelude::*;
+
+    use super::{
+        json_value, AwsSnafu, Result, SecretProvider, UnableToFetchSnafu, UnexpectedResponseSnafu,
+    };
+    use crate::sql::db_connection_pool::aws::{
+        region_or_env, sign, signable_request, AwsCredentials, AwsCredentialsProvider,
+        DefaultCredentialsProvider,
+    };
+
+    /// Fetches the secret from AWS Secrets Manager.
+    #[derive(Debug, Clone)]
+    pub struct AwsSecretsManagerProvider {
+        secret_id: String,
+        region: String,
+        credentials: Arc<dyn AwsCredentialsProvider>,
+        json_key: Option<String>,
+        client: reqwest::Client,
+    }
+
+    impl AwsSecretsManagerProvider {
+        #[must_use]
+        pub fn new(
+            secret_id: impl Into<String>,
+            region: impl Into<String>,
+            credentials: AwsCredentials,
+        ) -> Self {
+            Self::from_provider(secret_id, region, Arc::new(credentials))
+        }
+
+        /// Signs every request with the credentials `credentials` provides at the time, so that
+        /// temporary credentials are renewed between the fetches of the secret.
+        #[must_use]
+        pub fn from_provider(
+            secret_id: impl Into<String>,
+            region: impl Into<String>,
+            credentials: Arc<dyn AwsCredentialsProvider>,
+        ) -> Self {
+            Self {
+                secret_id: secret_id.into(),
+                region: region.into(),
+                credentials,
+                json_key: None,
+                client: reqwest::Client::new(),
+            }
+        }
+
+        /// Fetches the secret with the credentials of the default credential chain, loaded again
+        /// for every fetch, in `region` or the region of `AWS_REGION`. See
+        /// [`DefaultCredentialsProvider`].
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the region isn't set.
+        pub fn from_env(secret_id: impl Into<String>, region: Option<&str>) -> Result<Self> {
+            let region = region_or_env(region).context(AwsSnafu)?;
+            Ok(Self::from_provider(
+                secret_id,
+                region,
+                Arc::new(DefaultCredentialsProvider::default()),
+            ))
+        }
+
+        /// Reads the secret from the value of `key` in the JSON object of the secret, like the
+        /// `password` of the secrets that Secrets Manager rotates for RDS.
+        #[must_use]
+        pub fn with_json_key(mut self, key: impl Into<String>) -> Self {
+            self.json_key = Some(key.into());
+            self
+        }
+    }
+
+    #[async_trait]
+    impl SecretProvider for AwsSecretsManagerProvider {
+        async fn secret(&self) -> Result<SecretString> {
+            let url = format!("https://secretsmanager.{}.amazonaws.com/", self.region);
+            let body = serde_json::json!({ "SecretId": self.secret_id }).to_string();
+            let headers = [
+                ("content-type", "application/x-amz-json-1.1"),
+                ("x-amz-target", "secretsmanager.GetSecretValue"),
+            ];
+            let credentials = self.credentials.credentials().await.context(AwsSnafu)?;
+            let request =
+                signable_request("POST", &url, &headers, body.as_bytes()).context(AwsSnafu)?;
+            // the `authorization`, `x-amz-date` and `x-amz-security-token` headers
+            let instructions = sign(
+                &credentials,
+                &self.region,
+                "secretsmanager",
+                Utc::now(),
+                SigningSettings::default(),
+                request,
+            )
+            .context(AwsSnafu)?;
+
+            let mut request = self.client.post(url).body(body);
+            for (name, value) in headers.into_iter().chain(instructions.headers()) {
+                request = request.header(name, value);
+            }
+            let response = request.send().await.context(UnableToFetchSnafu)?;
+            let status = response.status();
+            let body = response.text().await.context(UnableToFetchSnafu)?;
+            ensure!(
+                status.is_success(),
+                UnexpectedResponseSnafu {
+                    status: status.as_u16(),
+                    body
+                }
+            );
+
+            // binary secrets have a `SecretBinary` instead, which isn't a password
+            let secret = json_value(&body, "SecretString")?;
+            match &self.json_key {
+                Some(key) => json_value(secret.expose_secret(), key),
+                None => Ok(secret),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "vault")]
+pub use vault::VaultSecretProvider;
+
+#[cfg(feature = "vault")]
+mod vault {
+    use async_trait::async_trait;
+    use secrecy::{ExposeSecret, SecretString};
+    use snafu::prelude::*;
+
+    use super::{
+        vault_value, MissingEnvironmentVariableSnafu, Result, SecretProvider, UnableToFetchSnafu,
+        UnexpectedResponseSnafu,
+    };
+
+    /// Reads the secret from a key of a secret of HashiCorp Vault, in either version of the KV
+    /// secrets engine.
+    #[derive(Debug, Clone)]
+    pub struct VaultSecretProvider {
+        address: String,
+        token: SecretString,
+        path: String,
+        key: String,
+        client: reqwest::Client,
+    }
+
+    impl VaultSecretProvider {
+        /// Reads `key` of the secret at `path`, like `secret/data/postgres` for the secret
+        /// `postgres` of the KV version 2 engine mounted at `secret`.
+        #[must_use]
+        pub fn new(
+            address: impl Into<String>,
+            token: SecretString,
+            path: impl Into<String>,
+            key: impl Into<String>,
+        ) -> Self {
+            Self {
+                address: address.into().trim_end_matches('/').to_string(),
+                token,
+                path: path.into().trim_start_matches('/').to_string(),
+                key: key.into(),
+                client: reqwest::Client::new(),
+            }
+        }
+
+        /// Connects to the Vault of `VAULT_ADDR` with the token of `VAULT_TOKEN`.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if either environment variable isn't set.
+        pub fn from_env(path: impl Into<String>, key: impl Into<String>) -> Result<Self> {
+            let var = |name: &str| {
+                std::env::var(name)
+                    .ok()
+                    .context(MissingEnvironmentVariableSnafu { name })
+            };
+            Ok(Self::new(
+                var("VAULT_ADDR")?,
+                var("VAULT_TOKEN")?.into(),
+                path,
+                key,
+            ))
+        }
+    }
+
+    #[async_trait]
+    impl SecretProvider for VaultSecretProvider {
+        async fn secret(&self) -> Result<SecretString> {
+            let response = self
+                .client
+                .get(format!("{}/v1/{}", self.address, self.path))
+                .header("X-Vault-Token", self.token.expose_secret())
+                .send()
+                .await
+                .context(UnableToFetchSnafu)?;
+            let status = response.status();
+            let body = response.text().await.context(UnableToFetchSnafu)?;
+            ensure!(
+                status.is_success(),
+                UnexpectedResponseSnafu {
+                    status: status.as_u16(),
+                    body
+                }
+            );
+            vault_value(&body, &self.key)
+        }
+    }
+}
+
+/// The value of `key` in the response of Vault for a secret, which is nested in `data.data` for
+/// the KV version 2 engine and in `data` for version 1.
+#[cfg_attr(not(any(feature = "vault", test)), allow(dead_code))]
+fn vault_value(response: &str, key: &str) -> Result<SecretString> {
+    let response: serde_json::Value = serde_json::from_str(response).context(InvalidJsonSnafu)?;
+    let data = &response["data"];
+    let data = match &data["data"] {
+        serde_json::Value::Object(_) => &data["data"],
+        _ => data,
+    };
+    json_value(&data.to_string(), key)
+}
+
+/// The password of a connection pool, and when it was fetched from its provider.
+#[derive(Debug)]
+pub(crate) struct PasswordRefresh {
+    provider: Arc<dyn SecretProvider>,
+    fetched: tokio::sync::Mutex<(SecretString, Instant)>,
+}
+
+impl PasswordRefresh {
+    pub(crate) async fn new(provider: Arc<dyn SecretProvider>) -> Result<Self> {
+        let password = provider.secret().await?;
+        Ok(Self {
+            provider,
+            fetched: tokio::sync::Mutex::new((password, Instant::now())),
+        })
+    }
+
+    pub(crate) async fn password(&self) -> SecretString {
+        self.fetched.lock().await.0.clone()
+    }
+
+    /// The new password of the provider, if the current one is due for a refresh or the refresh
+    /// is `forced` after the database rejected it, and the provider returns a different one.
+    pub(crate) async fn refreshed(&self, forced: bool) -> Result<Option<SecretString>> {
+        let mut fetched = self.fetched.lock().await;
+        if !forced && fetched.1.elapsed() < self.provider.refresh_interval() {
+            return Ok(None);
+        }
+        let password = self.provider.secret().await?;
+        fetched.1 = Instant::now();
+        if password.expose_secret() == fetched.0.expose_secret() {
+            return Ok(None);
+        }
+        fetched.0 = password.clone();
+        Ok(Some(password))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_secret_provider() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("password");
+        std::fs::write(&path, "s3cr3t\n")?;
+        let secret = FileSecretProvider::new(&path).secret().await?;
+        assert_eq!(secret.expose_secret(), "s3cr3t");
+
+        std::fs::write(&path, r#"{"username": "app", "password": "rotated"}"#)?;
+        let provider = FileSecretProvider::new(&path).with_json_key("password");
+        assert_eq!(provider.secret().await?.expose_secret(), "rotated");
+        let provider = FileSecretProvider::new(&path).with_json_key("port");
+        assert!(matches!(
+            provider.secret().await,
+            Err(Error::MissingKey { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vault_value() -> Result<(), Box<dyn std::error::Error>> {
+        let kv2 = r#"{"data": {"data": {"password": "v2"}, "metadata": {"version": 3}}}"#;
+        assert_eq!(vault_value(kv2, "password")?.expose_secret(), "v2");
+        let kv1 = r#"{"data": {"password": "v1"}, "lease_duration": 3600}"#;
+        assert_eq!(vault_value(kv1, "password")?.expose_secret(), "v1");
+        Ok(())
+    }
+
+    /// Returns the next password of a list every time.
+    #[derive(Debug)]
+    struct RotatingProvider {
+        passwords: Vec<&'static str>,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SecretProvider for RotatingProvider {
+        async fn secret(&self) -> Result<SecretString> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.passwords[call.min(self.passwords.len() - 1)].into())
+        }
+
+        fn refresh_interval(&self) -> Duration {
+            Duration::from_secs(3600)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_password_refresh() -> Result<(), Box<dyn std::error::Error>> {
+        let provider = Arc::new(RotatingProvider {
+            passwords: vec!["first", "first", "second"],
+            calls: AtomicUsize::new(0),
+        });
+        let refresh = PasswordRefresh::new(Arc::clone(&provider) as _).await?;
+        assert_eq!(refresh.password().await.expose_secret(), "first");
+
+        // not due for a refresh yet
+        assert!(refresh.refreshed(false).await?.is_none());
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+        // the same password doesn't replace the connections
+        assert!(refresh.refreshed(true).await?.is_none());
+        let password = refresh.refreshed(true).await?.expect("rotated password");
+        assert_eq!(password.expose_secret(), "second");
+        assert_eq!(refresh.password().await.expose_secret(), "second");
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+//! The graceful shutdown of the pools, see [`super::DbConnectionPool::close`]. A pool that is
+//! closed refuses new queries and connections, waits for its queries in flight to finish, then
+//! closes its connections and stops its background threads.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use snafu::prelude::*;
+use tokio::sync::Semaphore;
+
+use super::query_limit::QueryPermit;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The connection pool is closed.\nCreate a new pool to run more queries."))]
+    PoolClosed,
+
+    #[snafu(display("{in_flight} queries were still running when the pool was closed, after waiting {timeout:?} for them to finish.\nTheir connections are closed once they finish."))]
+    ShutdownTimeout { in_flight: usize, timeout: Duration },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// More queries than a pool can run at once.
+const MAX_IN_FLIGHT: u32 = 1 << 28;
+
+/// The queries in flight on a pool, which its clones share, so that closing the pool can wait for
+/// them to finish.
+#[derive(Debug, Clone)]
+pub struct QueryTracker {
+    queries: Arc<Semaphore>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Default for QueryTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            queries: Arc::new(Semaphore::new(MAX_IN_FLIGHT as usize)),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts a query, which is in flight until `permit` is dropped, along with the permit of the
+    /// query limiter of the pool if it has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PoolClosed`] if the pool is closed.
+    pub fn start(&self, permit: Option<QueryPermit>) -> Result<QueryPermit> {
+        self.ensure_open()?;
+        let in_flight = Arc::clone(&self.queries)
+            .try_acquire_owned()
+            .map_err(|_| Error::PoolClosed)?;
+        Ok(match permit {
+            Some(permit) => permit.with_permit(in_flight),
+            None => QueryPermit::new(in_flight),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`Error::PoolClosed`] if the pool is closed.
+    pub fn ensure_open(&self) -> Result<()> {
+        ensure!(!self.is_closed(), PoolClosedSnafu);
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// The number of queries in flight.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        if self.queries.is_closed() {
+            return 0;
+        }
+        (MAX_IN_FLIGHT as usize).saturating_sub(self.queries.available_permits())
+    }
+
+    /// Refuses new queries and waits for the queries in flight to finish, for `timeout` at most,
+    /// or as long as it takes without a timeout. Closing a closed tracker returns right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ShutdownTimeout`] if queries are still running after `timeout`.
+    pub async fn close(&self, timeout: Option<Duration>) -> Result<()> {
+        self.closed.store(true, Ordering::SeqCst);
+        let all_queries = Arc::clone(&self.queries).acquire_many_owned(MAX_IN_FLIGHT);
+        let acquired = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, all_queries)
+                .await
+                .map_err(|_| {
+                    ShutdownTimeoutSnafu {
+                        in_flight: self.in_flight(),
+                        timeout,
+                    }
+                    .build()
+                })?,
+            None => all_queries.await,
+        };
+        // the semaphore is only closed once every query is done
+        if let Ok(permits) = acquired {
+            permits.forget();
+            self.queries.close();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_close_waits_for_queries() -> Result<()> {
+        let tracker = QueryTracker::new();
+        let query = tracker.start(None)?;
+        assert_eq!(tracker.in_flight(), 1);
+
+        assert!(matches!(
+            tracker.close(Some(Duration::from_millis(50))).await,
+            Err(Error::ShutdownTimeout { in_flight: 1, .. })
+        ));
+        assert!(matches!(tracker.start(None), Err(Error::PoolClosed)));
+
+        drop(query);
+        tracker.close(Some(Duration::from_millis(50))).await?;
+        assert_eq!(tracker.in_flight(), 0);
+        tracker.close(None).await?;
+        Ok(())
+    }
+}
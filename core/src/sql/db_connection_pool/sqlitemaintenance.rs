@@ -0,0 +1,250 @@
+//! The maintenance of long-lived SQLite databases, see [`SqliteMaintenance`].
+//!
+//! SQLite keeps the pages freed by deletes and updates in the file for its next writes, and only
+//! plans queries with the statistics gathered by the last `ANALYZE`. A database that is rewritten
+//! for a long time grows larger than its data, and its query plans go stale as its tables grow.
+
+use std::time::Duration;
+
+use snafu::prelude::*;
+use tokio::task::JoinHandle;
+use tokio_rusqlite::Connection;
+
+use super::shutdown::QueryTracker;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to optimize the SQLite database.\n{source}"))]
+    UnableToOptimize { source: rusqlite::Error },
+
+    #[snafu(display("Unable to analyze the SQLite database.\n{source}"))]
+    UnableToAnalyze { source: rusqlite::Error },
+
+    #[snafu(display("Unable to vacuum the SQLite database incrementally.\n{source}"))]
+    UnableToVacuum { source: rusqlite::Error },
+
+    #[snafu(display("Unable to set the auto-vacuum mode of the SQLite database.\n{source}"))]
+    UnableToSetAutoVacuum { source: rusqlite::Error },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The default interval between two maintenances.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The value of `PRAGMA auto_vacuum` for incremental vacuums.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+/// The maintenance of the database of a pool, run on the runtime every interval while the pool is
+/// open, see [`super::sqlitepool::SqliteConnectionPoolFactory::with_maintenance`].
+///
+/// Each maintenance runs `PRAGMA optimize`, which analyzes the tables whose statistics are stale.
+/// It can also analyze all the tables, and give the free pages of the file back to the file system
+/// with an incremental vacuum.
+///
+/// ```rust,ignore
+/// let pool = SqliteConnectionPoolFactory::new("accelerated.db", Mode::File, busy_timeout)
+///     .with_maintenance(Some(
+///         SqliteMaintenance::new(Duration::from_secs(600)).with_incremental_vacuum(Some(1000)),
+///     ))
+///     .build()
+///     .await?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteMaintenance {
+    interval: Duration,
+    optimize: bool,
+    analyze: bool,
+    incremental_vacuum: Option<u32>,
+}
+
+impl Default for SqliteMaintenance {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERVAL)
+    }
+}
+
+impl SqliteMaintenance {
+    /// Runs the maintenance every `interval`, 1 hour by default.
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            optimize: true,
+            analyze: false,
+            incremental_vacuum: None,
+        }
+    }
+
+    /// Whether each maintenance runs `PRAGMA optimize`, `true` by default.
+    #[must_use]
+    pub fn with_optimize(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Whether each maintenance gathers the statistics of all the tables with `ANALYZE`, `false`
+    /// by default. `ANALYZE` reads every index, so it's slow for large databases.
+    #[must_use]
+    pub fn with_analyze(mut self, analyze: bool) -> Self {
+        self.analyze = analyze;
+        self
+    }
+
+    /// Frees up to `pages` free pages of the file at each maintenance with
+    /// `PRAGMA incremental_vacuum`, or all of them with `Some(0)`.
+    ///
+    /// The pool sets the database to `PRAGMA auto_vacuum = INCREMENTAL`, which only applies to a
+    /// database with no table yet. An existing database keeps its mode, and isn't vacuumed, until
+    /// it's rebuilt with `VACUUM`.
+    #[must_use]
+    pub fn with_incremental_vacuum(mut self, pages: Option<u32>) -> Self {
+        self.incremental_vacuum = pages;
+        self
+    }
+
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Sets the auto-vacuum mode that the incremental vacuum needs on the database of `conn`, when
+    /// it's enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mode can't be set.
+    pub fn setup(&self, conn: &rusqlite::Connection) -> Result<()> {
+        if self.incremental_vacuum.is_none() {
+            return Ok(());
+        }
+        conn.pragma_update(None, "auto_vacuum", "INCREMENTAL")
+            .context(UnableToSetAutoVacuumSnafu)?;
+        if auto_vacuum(conn).context(UnableToSetAutoVacuumSnafu)? != AUTO_VACUUM_INCREMENTAL {
+            tracing::warn!("The SQLite database has tables and doesn't use incremental auto-vacuum, so it isn't vacuumed incrementally until it's rebuilt with VACUUM.");
+        }
+        Ok(())
+    }
+
+    /// Runs the maintenance of the database of `conn`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a step of the maintenance fails.
+    pub fn run(&self, conn: &rusqlite::Connection) -> Result<()> {
+        if self.analyze {
+            conn.execute_batch("ANALYZE")
+                .context(UnableToAnalyzeSnafu)?;
+        }
+        if self.optimize {
+            drain(conn, "PRAGMA optimize").context(UnableToOptimizeSnafu)?;
+        }
+        if let Some(pages) = self.incremental_vacuum {
+            if auto_vacuum(conn).context(UnableToVacuumSnafu)? == AUTO_VACUUM_INCREMENTAL {
+                drain(conn, &format!("PRAGMA incremental_vacuum({pages})"))
+                    .context(UnableToVacuumSnafu)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn auto_vacuum(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+    conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))
+}
+
+/// Runs `sql` to its end. `PRAGMA incremental_vacuum` frees a page at each step, so it can't be
+/// run with a single step like `execute`.
+fn drain(conn: &rusqlite::Connection, sql: &str) -> rusqlite::Result<()> {
+    let mut statement = conn.prepare(sql)?;
+    let mut rows = statement.query([])?;
+    while rows.next()?.is_some() {}
+    Ok(())
+}
+
+/// The task that runs the maintenance of a pool. It stops once the pool is closed or dropped.
+#[derive(Debug)]
+pub(crate) struct MaintenanceTask {
+    task: JoinHandle<()>,
+}
+
+impl MaintenanceTask {
+    /// Starts the task that runs `maintenance` on `conn`, as a query of `queries` so that closing
+    /// the pool waits for it.
+    pub(crate) fn start(
+        conn: Connection,
+        queries: QueryTracker,
+        maintenance: SqliteMaintenance,
+    ) -> Self {
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(maintenance.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // the first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Ok(_permit) = queries.start(None) else {
+                    break;
+                };
+                let maintenance = maintenance.clone();
+                let result = conn
+                    .call(move |conn| {
+                        maintenance
+                            .run(conn)
+                            .map_err(|e| tokio_rusqlite::Error::Other(Box::new(e)))
+                    })
+                    .await;
+                match result {
+                    Ok(()) => tracing::debug!("Ran the maintenance of the SQLite database"),
+                    Err(tokio_rusqlite::Error::ConnectionClosed) => break,
+                    Err(e) => tracing::warn!("The maintenance of the SQLite database failed: {e}"),
+                }
+            }
+        });
+        Self { task }
+    }
+
+    /// Stops the task, and the maintenance it's running if there's one.
+    pub(crate) fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for MaintenanceTask {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance() -> Result<(), Box<dyn std::error::Error>> {
+        let conn = rusqlite::Connection::open_in_memory()?;
+        let maintenance = SqliteMaintenance::new(Duration::from_secs(60))
+            .with_analyze(true)
+            .with_incremental_vacuum(Some(0));
+        maintenance.setup(&conn)?;
+        assert_eq!(auto_vacuum(&conn)?, AUTO_VACUUM_INCREMENTAL);
+
+        conn.execute_batch(
+            "CREATE TABLE numbers (n INTEGER PRIMARY KEY, padding TEXT);
+             WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 10000)
+             INSERT INTO numbers SELECT n, printf('%.100c', 'x') FROM seq;
+             DELETE FROM numbers WHERE n > 10;",
+        )?;
+        let free_pages = |conn: &rusqlite::Connection| -> rusqlite::Result<i64> {
+            conn.pragma_query_value(None, "freelist_count", |row| row.get(0))
+        };
+        assert!(free_pages(&conn)? > 0);
+
+        maintenance.run(&conn)?;
+        assert_eq!(free_pages(&conn)?, 0);
+        let analyzed: i64 =
+            conn.query_row("SELECT count(*) FROM sqlite_stat1", [], |row| row.get(0))?;
+        assert!(analyzed > 0);
+        Ok(())
+    }
+}
@@ -1,10 +1,15 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use snafu::{prelude::*, ResultExt};
 use tokio_rusqlite::{Connection, ToSql};
 
-use super::{DbConnectionPool, Result};
+use super::{
+    query_limit::QueryPermit,
+    shutdown::QueryTracker,
+    sqlitemaintenance::{self, MaintenanceTask, SqliteMaintenance},
+    DbConnectionPool, Result,
+};
 use crate::sql::db_connection_pool::{
     dbconnection::{sqliteconn::SqliteConnection, AsyncDbConnection, DbConnection},
     JoinPushDown, Mode,
@@ -20,6 +25,15 @@ pub enum Error {
 
     #[snafu(display("Database to attach does not exist: {path}"))]
     DatabaseDoesNotExist { path: String },
+
+    #[snafu(display("Unable to set up the maintenance of the SQLite database.\n{source}"))]
+    Maintenance { source: sqlitemaintenance::Error },
+}
+
+/// Returns whether `path` is an SQLite URI like `file:data.db?mode=ro`, which can't be checked on the
+/// file system.
+fn is_uri(path: &str) -> bool {
+    path.starts_with("file:")
 }
 
 pub struct SqliteConnectionPoolFactory {
@@ -27,24 +41,80 @@ pub struct SqliteConnectionPoolFactory {
     mode: Mode,
     attach_databases: Option<Vec<Arc<str>>>,
     busy_timeout: Duration,
+    pragmas: Vec<(String, String)>,
+    database_aliases: HashMap<Arc<str>, Arc<str>>,
+    maintenance: Option<SqliteMaintenance>,
 }
 
 impl SqliteConnectionPoolFactory {
+    /// Creates a factory for the database at `path`, which can also be an SQLite URI in file-mode,
+    /// e.g. `file:data.db?mode=ro` or `file:shared?mode=memory&cache=shared`.
     pub fn new(path: &str, mode: Mode, busy_timeout: Duration) -> Self {
         SqliteConnectionPoolFactory {
             path: path.into(),
             mode,
             attach_databases: None,
             busy_timeout,
+            pragmas: Vec::new(),
+            database_aliases: HashMap::new(),
+            maintenance: None,
         }
     }
 
+    /// An in-memory database that is shared by all pools of the same `name` in this process, through
+    /// SQLite's shared cache. Unlike [`Mode::Memory`], pools that are built separately or cloned with
+    /// [`SqliteConnectionPool::try_clone`] open their own connection to the same database.
+    ///
+    /// The database lives until the last connection to it is closed.
+    pub fn shared_memory(name: &str, busy_timeout: Duration) -> Self {
+        Self::new(
+            &format!("file:{name}?mode=memory&cache=shared"),
+            Mode::File,
+            busy_timeout,
+        )
+    }
+
+    /// Sets `PRAGMA`s on the connections of the pool, after the defaults of the pool, e.g.
+    /// `("cache_size", "-64000")`.
+    #[must_use]
+    pub fn with_pragmas(mut self, pragmas: Vec<(String, String)>) -> Self {
+        self.pragmas = pragmas;
+        self
+    }
+
     #[must_use]
     pub fn with_databases(mut self, attach_databases: Option<Vec<Arc<str>>>) -> Self {
         self.attach_databases = attach_databases;
         self
     }
 
+    /// Attaches the databases of `databases`, given as `(name, path)`, under their name, so their
+    /// tables can be referenced as `name.table` and are listed as the tables of schema `name`.
+    ///
+    /// Unlike the databases of [`Self::with_databases`], which are only attached with the
+    /// `sqlite-federation` feature to push down joins, named databases are always attached.
+    #[must_use]
+    pub fn with_named_databases(mut self, databases: Vec<(Arc<str>, Arc<str>)>) -> Self {
+        let attach_databases = self.attach_databases.get_or_insert_with(Vec::new);
+        for (name, path) in databases {
+            if !attach_databases.contains(&path) {
+                attach_databases.push(Arc::clone(&path));
+            }
+            self.database_aliases.insert(path, name);
+        }
+        self
+    }
+
+    /// Runs `maintenance` on the database while the pool is open, see [`SqliteMaintenance`].
+    ///
+    /// The pools cloned from a file-mode pool open their own connection to the database, and don't
+    /// run the maintenance again.
+    #[must_use]
+    pub fn with_maintenance(mut self, maintenance: Option<SqliteMaintenance>) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
     pub async fn build(&self) -> Result<SqliteConnectionPool> {
         let join_push_down = match (self.mode, &self.attach_databases) {
             (Mode::File, Some(attach_databases)) => {
@@ -55,7 +125,7 @@ impl SqliteConnectionPoolFactory {
 
                     for database in &attach_databases {
                         // check if the database file exists
-                        if std::fs::metadata(database.as_ref()).is_err() {
+                        if !is_uri(database) && std::fs::metadata(database.as_ref()).is_err() {
                             return Err(Error::DatabaseDoesNotExist {
                                 path: database.to_string(),
                             }
@@ -82,17 +152,33 @@ impl SqliteConnectionPoolFactory {
             vec![]
         };
 
-        let pool = SqliteConnectionPool::new(
+        let mut pool = SqliteConnectionPool::new(
             &self.path,
             self.mode,
             join_push_down,
             attach_databases,
             self.busy_timeout,
         )
-        .await?;
+        .await?
+        .with_pragmas(self.pragmas.clone());
+        pool.database_aliases = self.database_aliases.clone();
 
         pool.setup().await?;
 
+        if let Some(maintenance) = self.maintenance.clone() {
+            let setup = maintenance.clone();
+            pool.conn
+                .call(move |conn| Ok(setup.setup(conn)))
+                .await
+                .context(ConnectionPoolSnafu)?
+                .context(MaintenanceSnafu)?;
+            pool.maintenance = Some(Arc::new(MaintenanceTask::start(
+                pool.conn.clone(),
+                pool.queries.clone(),
+                maintenance,
+            )));
+        }
+
         Ok(pool)
     }
 }
@@ -105,6 +191,10 @@ pub struct SqliteConnectionPool {
     path: Arc<str>,
     attach_databases: Vec<Arc<str>>,
     busy_timeout: Duration,
+    pragmas: Vec<(String, String)>,
+    database_aliases: HashMap<Arc<str>, Arc<str>>,
+    queries: QueryTracker,
+    maintenance: Option<Arc<MaintenanceTask>>,
 }
 
 impl SqliteConnectionPool {
@@ -141,9 +231,20 @@ impl SqliteConnectionPool {
             attach_databases,
             path: path.into(),
             busy_timeout,
+            pragmas: Vec::new(),
+            database_aliases: HashMap::new(),
+            queries: QueryTracker::new(),
+            maintenance: None,
         })
     }
 
+    /// Sets `PRAGMA`s on the connection in [`Self::setup`], see [`SqliteConnectionPoolFactory::with_pragmas`].
+    #[must_use]
+    pub fn with_pragmas(mut self, pragmas: Vec<(String, String)>) -> Self {
+        self.pragmas = pragmas;
+        self
+    }
+
     /// Initializes an SQLite database on-disk without creating a connection pool.
     /// No-op if the database is in-memory.
     pub async fn init(path: &str, mode: Mode) -> Result<()> {
@@ -181,29 +282,42 @@ impl SqliteConnectionPool {
             .context(ConnectionPoolSnafu)?;
 
             // database attachments are only supported for file-mode databases
-            #[cfg(feature = "sqlite-federation")]
-            {
-                let attach_databases = self
-                    .attach_databases
-                    .iter()
-                    .enumerate()
-                    .map(|(i, db)| format!("ATTACH DATABASE '{db}' AS attachment_{i}"));
-
-                for attachment in attach_databases {
-                    if attachment == *self.path {
-                        continue;
+            let attach_databases = self
+                .attach_databases
+                .iter()
+                .enumerate()
+                .filter(|(_, db)| **db != self.path)
+                .filter_map(|(i, db)| match self.database_aliases.get(db) {
+                    Some(name) => Some(format!(
+                        "ATTACH DATABASE '{db}' AS \"{}\"",
+                        name.replace('"', "\"\"")
+                    )),
+                    None if cfg!(feature = "sqlite-federation") => {
+                        Some(format!("ATTACH DATABASE '{db}' AS attachment_{i}"))
                     }
+                    None => None,
+                });
+
+            for attachment in attach_databases {
+                conn.call(move |conn| {
+                    conn.execute(&attachment, [])?;
+                    Ok(())
+                })
+                .await
+                .context(ConnectionPoolSnafu)?;
+            }
+        }
 
-                    conn.call(move |conn| {
-                        conn.execute(&attachment, [])?;
-                        Ok(())
-                    })
-                    .await
-                    .context(ConnectionPoolSnafu)?;
+        if !self.pragmas.is_empty() {
+            let pragmas = self.pragmas.clone();
+            conn.call(move |conn| {
+                for (name, value) in &pragmas {
+                    conn.pragma_update(None, name, value)?;
                 }
-
-                Ok::<(), super::Error>(())
-            }?;
+                Ok(())
+            })
+            .await
+            .context(ConnectionPoolSnafu)?;
         }
 
         Ok(())
@@ -228,6 +342,10 @@ impl SqliteConnectionPool {
                 path: Arc::clone(&self.path),
                 attach_databases: self.attach_databases.clone(),
                 busy_timeout: self.busy_timeout,
+                pragmas: self.pragmas.clone(),
+                database_aliases: self.database_aliases.clone(),
+                queries: self.queries.clone(),
+                maintenance: self.maintenance.clone(),
             }),
             Mode::File => {
                 let attach_databases = if self.attach_databases.is_empty() {
@@ -236,10 +354,12 @@ impl SqliteConnectionPool {
                     Some(self.attach_databases.clone())
                 };
 
-                SqliteConnectionPoolFactory::new(&self.path, self.mode, self.busy_timeout)
-                    .with_databases(attach_databases)
-                    .build()
-                    .await
+                let mut factory =
+                    SqliteConnectionPoolFactory::new(&self.path, self.mode, self.busy_timeout)
+                        .with_databases(attach_databases)
+                        .with_pragmas(self.pragmas.clone());
+                factory.database_aliases = self.database_aliases.clone();
+                factory.build().await
             }
         }
     }
@@ -250,6 +370,7 @@ impl DbConnectionPool<Connection, &'static (dyn ToSql + Sync)> for SqliteConnect
     async fn connect(
         &self,
     ) -> Result<Box<dyn DbConnection<Connection, &'static (dyn ToSql + Sync)>>> {
+        self.queries.ensure_open()?;
         let conn = self.conn.clone();
 
         Ok(Box::new(SqliteConnection::new(conn)))
@@ -258,6 +379,23 @@ impl DbConnectionPool<Connection, &'static (dyn ToSql + Sync)> for SqliteConnect
     fn join_push_down(&self) -> JoinPushDown {
         self.join_push_down.clone()
     }
+
+    async fn acquire_query_permit(&self) -> Result<Option<QueryPermit>> {
+        Ok(Some(self.queries.start(None)?))
+    }
+
+    /// Waits for the queries in flight, then closes the connection, which stops its thread. The
+    /// connection of an in-memory pool is shared with its clones, which are closed with it.
+    async fn close(&self, timeout: Option<Duration>) -> Result<()> {
+        if let Some(maintenance) = &self.maintenance {
+            maintenance.stop();
+        }
+        self.queries.close(timeout).await?;
+        match self.conn.clone().close().await {
+            Ok(()) | Err(tokio_rusqlite::Error::ConnectionClosed) => Ok(()),
+            Err(source) => Err(Error::ConnectionPoolError { source }.into()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -279,6 +417,60 @@ mod tests {
         format!("./{name}.sqlite")
     }
 
+    #[tokio::test]
+    async fn test_sqlite_connection_pool_close() {
+        let pool = SqliteConnectionPoolFactory::new(":memory:", Mode::Memory, Duration::ZERO)
+            .build()
+            .await
+            .expect("pool built");
+        let permit = pool.acquire_query_permit().await.expect("query started");
+
+        assert!(pool.close(Some(Duration::from_millis(50))).await.is_err());
+        assert!(pool.connect().await.is_err());
+
+        drop(permit);
+        pool.close(Some(Duration::from_millis(50)))
+            .await
+            .expect("pool closed");
+        pool.close(None).await.expect("closing again succeeds");
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_connection_pool_maintenance() {
+        let pool = SqliteConnectionPoolFactory::new(":memory:", Mode::Memory, Duration::ZERO)
+            .with_maintenance(Some(
+                SqliteMaintenance::new(Duration::from_millis(20))
+                    .with_analyze(true)
+                    .with_incremental_vacuum(Some(0)),
+            ))
+            .build()
+            .await
+            .expect("pool built");
+        pool.conn
+            .call(|conn| {
+                conn.execute_batch(
+                    "CREATE TABLE numbers (n INTEGER); INSERT INTO numbers VALUES (1);",
+                )?;
+                Ok(())
+            })
+            .await
+            .expect("table created");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let analyzed: i64 = pool
+            .conn
+            .call(|conn| {
+                Ok(conn.query_row("SELECT count(*) FROM sqlite_stat1", [], |row| row.get(0))?)
+            })
+            .await
+            .expect("statistics queried");
+        assert_eq!(analyzed, 1);
+
+        pool.close(Some(Duration::from_millis(50)))
+            .await
+            .expect("pool closed");
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_sqlite_connection_pool_factory() {
@@ -373,6 +565,93 @@ mod tests {
         assert!(std::fs::metadata("./test2.sqlite").is_err());
     }
 
+    #[tokio::test]
+    async fn test_sqlite_connection_pool_factory_shared_memory() {
+        let factory =
+            SqliteConnectionPoolFactory::shared_memory("test_shared", Duration::from_secs(5))
+                .with_pragmas(vec![("user_version".to_string(), "7".to_string())]);
+        let pool = factory.build().await.unwrap();
+        pool.conn
+            .call(|conn| {
+                conn.execute("CREATE TABLE shared (id INTEGER)", [])?;
+                conn.execute("INSERT INTO shared VALUES (1)", [])?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        // a cloned pool opens another connection to the same in-memory database
+        let other = pool.try_clone().await.unwrap();
+        let (count, user_version): (i64, i64) = other
+            .conn
+            .call(|conn| {
+                let count = conn.query_row("SELECT count(*) FROM shared", [], |row| row.get(0))?;
+                let user_version =
+                    conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+                Ok((count, user_version))
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(user_version, 7);
+
+        // the database is never written to disk
+        assert!(std::fs::metadata("file:test_shared").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_connection_pool_factory_with_named_databases() {
+        let db_names = [random_db_name(), random_db_name()];
+        {
+            let conn = Connection::open(db_names[1].clone()).await.unwrap();
+            conn.call(|conn| {
+                conn.execute("CREATE TABLE orders (id INTEGER)", [])?;
+                // the schema is inferred from the rows
+                conn.execute("INSERT INTO orders VALUES (1)", [])?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        }
+
+        let pool =
+            SqliteConnectionPoolFactory::new(&db_names[0], Mode::File, Duration::from_secs(5))
+                .with_named_databases(vec![("aux1".into(), db_names[1].clone().into())])
+                .build()
+                .await
+                .unwrap();
+
+        let schemas = crate::sql::db_connection_pool::dbconnection::get_schemas(
+            pool.connect().await.unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(schemas, vec!["main".to_string(), "aux1".to_string()]);
+
+        let tables = crate::sql::db_connection_pool::dbconnection::get_tables(
+            pool.connect().await.unwrap(),
+            "aux1",
+        )
+        .await
+        .unwrap();
+        assert_eq!(tables, vec!["orders".to_string()]);
+
+        let schema = crate::sql::db_connection_pool::dbconnection::get_schema(
+            pool.connect().await.unwrap(),
+            &datafusion::sql::TableReference::partial("aux1", "orders"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(schema.fields().len(), 1);
+
+        drop(pool);
+
+        // cleanup
+        for db in &db_names {
+            std::fs::remove_file(db).unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn test_sqlite_connection_pool_factory_errors_with_missing_attachments() {
         let mut db_names = [random_db_name(), random_db_name(), random_db_name()];
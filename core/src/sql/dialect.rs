@@ -0,0 +1,312 @@
+use std::{collections::HashMap, sync::Arc};
+
+use datafusion::{
+    arrow::datatypes::TimeUnit,
+    error::Result as DataFusionResult,
+    logical_expr::Expr,
+    sql::{
+        sqlparser::ast::{self, BinaryOperator, WindowFrameBound},
+        unparser::{
+            dialect::{CharacterLengthStyle, DateFieldExtractStyle, Dialect, IntervalStyle},
+            Unparser,
+        },
+    },
+};
+
+/// Unparses a scalar function call, returning `None` to fall back to the wrapped dialect.
+pub(crate) type ScalarFunctionOverride =
+    Box<dyn Fn(&Unparser, &str, &[Expr]) -> DataFusionResult<Option<ast::Expr>> + Send + Sync>;
+
+/// Unparses a call of a scalar function from its arguments, returning `None` to unparse the call
+/// the way the dialect would without the override.
+pub type FunctionOverride =
+    Arc<dyn Fn(&Unparser, &[Expr]) -> DataFusionResult<Option<ast::Expr>> + Send + Sync>;
+
+/// Custom unparsing of scalar functions for one remote database, layered on top of the dialect a
+/// provider uses for it.
+///
+/// This is for functions whose SQL differs between versions or forks of a database, e.g. to
+/// unparse `to_timestamp` as `STR_TO_DATE` for MySQL 5.7 but keep the default for MySQL 8.0:
+///
+/// ```rust
+/// use datafusion::sql::sqlparser::ast;
+/// use datafusion_table_providers::sql::dialect::{function, DialectOverrides};
+///
+/// let overrides = DialectOverrides::new().with_scalar_function("to_timestamp", |unparser, args| {
+///     let [value] = args else {
+///         return Ok(None);
+///     };
+///     let format = ast::Expr::Value(ast::Value::SingleQuotedString("%Y-%m-%d %H:%i:%s".into()));
+///     Ok(Some(function(
+///         "STR_TO_DATE",
+///         vec![unparser.expr_to_sql(value)?, format],
+///     )))
+/// });
+/// ```
+///
+/// Function names are matched against the name of the DataFusion function, which is lower case
+/// for the built-in functions.
+#[derive(Clone, Default)]
+pub struct DialectOverrides {
+    scalar_functions: HashMap<String, FunctionOverride>,
+}
+
+impl std::fmt::Debug for DialectOverrides {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut scalar_functions = self.scalar_functions.keys().collect::<Vec<_>>();
+        scalar_functions.sort();
+        f.debug_struct("DialectOverrides")
+            .field("scalar_functions", &scalar_functions)
+            .finish()
+    }
+}
+
+impl DialectOverrides {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unparses calls of the scalar function `name` with `f`, replacing an earlier override of it.
+    #[must_use]
+    pub fn with_scalar_function(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&Unparser, &[Expr]) -> DataFusionResult<Option<ast::Expr>> + Send + Sync + 'static,
+    ) -> Self {
+        self.scalar_functions.insert(name.into(), Arc::new(f));
+        self
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.scalar_functions.is_empty()
+    }
+
+    /// Wraps `dialect` so that it unparses the overridden functions with their overrides.
+    #[must_use]
+    pub fn dialect(
+        &self,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Arc<dyn Dialect + Send + Sync> {
+        if self.is_empty() {
+            return dialect;
+        }
+        let scalar_functions = self.scalar_functions.clone();
+        Arc::new(
+            ExtendedDialect::new(dialect).with_scalar_function_override(Box::new(
+                move |unparser, func_name, args| match scalar_functions.get(func_name) {
+                    Some(scalar_function) => scalar_function(unparser, args),
+                    None => Ok(None),
+                },
+            )),
+        )
+    }
+}
+
+/// Wraps a dialect to change how identifiers are quoted or how scalar functions are unparsed,
+/// forwarding everything else to the wrapped dialect.
+///
+/// Most DataFusion dialects can't be extended with custom scalar function overrides, so this is
+/// how providers layer their own unparsing rules on top of the dialect of the remote database.
+pub(crate) struct ExtendedDialect {
+    inner: Arc<dyn Dialect + Send + Sync>,
+    quote_all_identifiers: bool,
+    nulls_first_in_sort: bool,
+    scalar_function_override: Option<ScalarFunctionOverride>,
+}
+
+impl ExtendedDialect {
+    pub(crate) fn new(inner: Arc<dyn Dialect + Send + Sync>) -> Self {
+        Self {
+            inner,
+            quote_all_identifiers: false,
+            nulls_first_in_sort: false,
+            scalar_function_override: None,
+        }
+    }
+
+    /// Quotes every identifier. Identifiers the wrapped dialect wouldn't quote are quoted with `"`.
+    pub(crate) fn with_quote_all_identifiers(mut self) -> Self {
+        self.quote_all_identifiers = true;
+        self
+    }
+
+    /// Keeps `NULLS FIRST` and `NULLS LAST` in the sorts, for databases without them whose
+    /// statements are rewritten after unparsing, see [`super::null_ordering::NullOrdering`].
+    pub(crate) fn with_nulls_first_in_sort(mut self) -> Self {
+        self.nulls_first_in_sort = true;
+        self
+    }
+
+    pub(crate) fn with_scalar_function_override(
+        mut self,
+        scalar_function_override: ScalarFunctionOverride,
+    ) -> Self {
+        self.scalar_function_override = Some(scalar_function_override);
+        self
+    }
+}
+
+impl Dialect for ExtendedDialect {
+    fn identifier_quote_style(&self, identifier: &str) -> Option<char> {
+        let quote_style = self.inner.identifier_quote_style(identifier);
+        if self.quote_all_identifiers {
+            quote_style.or(Some('"'))
+        } else {
+            quote_style
+        }
+    }
+
+    fn supports_nulls_first_in_sort(&self) -> bool {
+        self.nulls_first_in_sort || self.inner.supports_nulls_first_in_sort()
+    }
+
+    fn use_timestamp_for_date64(&self) -> bool {
+        self.inner.use_timestamp_for_date64()
+    }
+
+    fn interval_style(&self) -> IntervalStyle {
+        self.inner.interval_style()
+    }
+
+    fn float64_ast_dtype(&self) -> ast::DataType {
+        self.inner.float64_ast_dtype()
+    }
+
+    fn utf8_cast_dtype(&self) -> ast::DataType {
+        self.inner.utf8_cast_dtype()
+    }
+
+    fn large_utf8_cast_dtype(&self) -> ast::DataType {
+        self.inner.large_utf8_cast_dtype()
+    }
+
+    fn date_field_extract_style(&self) -> DateFieldExtractStyle {
+        self.inner.date_field_extract_style()
+    }
+
+    fn character_length_style(&self) -> CharacterLengthStyle {
+        self.inner.character_length_style()
+    }
+
+    fn int64_cast_dtype(&self) -> ast::DataType {
+        self.inner.int64_cast_dtype()
+    }
+
+    fn int32_cast_dtype(&self) -> ast::DataType {
+        self.inner.int32_cast_dtype()
+    }
+
+    fn timestamp_cast_dtype(&self, time_unit: &TimeUnit, tz: &Option<Arc<str>>) -> ast::DataType {
+        self.inner.timestamp_cast_dtype(time_unit, tz)
+    }
+
+    fn date32_cast_dtype(&self) -> ast::DataType {
+        self.inner.date32_cast_dtype()
+    }
+
+    fn supports_column_alias_in_table_alias(&self) -> bool {
+        self.inner.supports_column_alias_in_table_alias()
+    }
+
+    fn requires_derived_table_alias(&self) -> bool {
+        self.inner.requires_derived_table_alias()
+    }
+
+    fn division_operator(&self) -> BinaryOperator {
+        self.inner.division_operator()
+    }
+
+    fn scalar_function_to_sql_overrides(
+        &self,
+        unparser: &Unparser,
+        func_name: &str,
+        args: &[Expr],
+    ) -> DataFusionResult<Option<ast::Expr>> {
+        if let Some(scalar_function_override) = &self.scalar_function_override {
+            if let Some(expr) = scalar_function_override(unparser, func_name, args)? {
+                return Ok(Some(expr));
+            }
+        }
+
+        self.inner
+            .scalar_function_to_sql_overrides(unparser, func_name, args)
+    }
+
+    fn window_func_support_window_frame(
+        &self,
+        func_name: &str,
+        start_bound: &WindowFrameBound,
+        end_bound: &WindowFrameBound,
+    ) -> bool {
+        self.inner
+            .window_func_support_window_frame(func_name, start_bound, end_bound)
+    }
+
+    fn full_qualified_col(&self) -> bool {
+        self.inner.full_qualified_col()
+    }
+
+    fn unnest_as_table_factor(&self) -> bool {
+        self.inner.unnest_as_table_factor()
+    }
+}
+
+/// Builds a call of the function `name` with positional arguments.
+#[must_use]
+pub fn function(name: &str, args: Vec<ast::Expr>) -> ast::Expr {
+    ast::Expr::Function(ast::Function {
+        name: ast::ObjectName(vec![ast::Ident::new(name)]),
+        uses_odbc_syntax: false,
+        parameters: ast::FunctionArguments::None,
+        args: ast::FunctionArguments::List(ast::FunctionArgumentList {
+            duplicate_treatment: None,
+            args: args
+                .into_iter()
+                .map(|arg| ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg)))
+                .collect(),
+            clauses: vec![],
+        }),
+        filter: None,
+        null_treatment: None,
+        over: None,
+        within_group: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        functions::expr_fn::to_timestamp,
+        prelude::{col, lower},
+        sql::unparser::dialect::MySqlDialect,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_dialect_overrides() {
+        let overrides =
+            DialectOverrides::new().with_scalar_function("to_timestamp", |unparser, args| {
+                let args = args
+                    .iter()
+                    .map(|arg| unparser.expr_to_sql(arg))
+                    .collect::<DataFusionResult<Vec<_>>>()?;
+                Ok(Some(function("FROM_UNIXTIME", args)))
+            });
+        let dialect = overrides.dialect(Arc::new(MySqlDialect {}));
+        let unparser = Unparser::new(dialect.as_ref());
+
+        let sql = unparser
+            .expr_to_sql(&to_timestamp(vec![col("created_at")]))
+            .expect("expression unparsed");
+        assert_eq!(sql.to_string(), "FROM_UNIXTIME(`created_at`)");
+
+        // other functions are unparsed by the wrapped dialect
+        let sql = unparser
+            .expr_to_sql(&lower(col("name")))
+            .expect("expression unparsed");
+        assert_eq!(sql.to_string(), "lower(`name`)");
+    }
+}
@@ -0,0 +1,535 @@
+use std::{any::Any, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::{RecordBatch, UInt64Array},
+        datatypes::{DataType, Field, Schema, SchemaRef},
+    },
+    datasource::{DefaultTableSource, TableProvider},
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::{
+        context::{QueryPlanner, SessionState},
+        SendableRecordBatchStream, TaskContext,
+    },
+    logical_expr::{
+        expr_rewriter::unnormalize_col,
+        utils::{conjunction, split_conjunction},
+        DmlStatement, Expr, LogicalPlan, WriteOp,
+    },
+    physical_expr::EquivalenceProperties,
+    physical_plan::{
+        execution_plan::{Boundedness, EmissionType},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, PlanProperties,
+    },
+    physical_planner::{DefaultPhysicalPlanner, PhysicalPlanner},
+    sql::{
+        sqlparser::ast,
+        unparser::{dialect::Dialect, Unparser},
+        TableReference,
+    },
+};
+use futures::stream;
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("DML operation '{op}' is not supported"))]
+    UnsupportedOperation { op: String },
+
+    #[snafu(display(
+        "Unable to push down the {op} statement: the plan contains an unsupported '{node}' node"
+    ))]
+    UnsupportedPlan { op: String, node: String },
+
+    #[snafu(display("Unable to generate SQL for the DML statement: {source}"))]
+    UnableToGenerateSQL { source: DataFusionError },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A `DELETE` or `UPDATE` extracted from a DataFusion [`DmlStatement`], ready to be unparsed
+/// into a statement for the remote database.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DmlOperation {
+    Delete {
+        filters: Vec<Expr>,
+    },
+    Update {
+        assignments: Vec<(String, Expr)>,
+        filters: Vec<Expr>,
+    },
+}
+
+impl DmlOperation {
+    /// Extracts the filters (and assignments, for `UPDATE`) from a logical DML statement.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statement is not a `DELETE` or `UPDATE`, or if its input
+    /// contains anything other than projections, filters and a scan of the target table.
+    pub fn try_from_statement(dml: &DmlStatement) -> Result<Self> {
+        match dml.op {
+            WriteOp::Delete => Ok(DmlOperation::Delete {
+                filters: collect_filters(&dml.op, &dml.input)?,
+            }),
+            WriteOp::Update => {
+                let LogicalPlan::Projection(projection) = dml.input.as_ref() else {
+                    return UnsupportedPlanSnafu {
+                        op: dml.op.to_string(),
+                        node: dml.input.display().to_string(),
+                    }
+                    .fail();
+                };
+
+                let mut assignments = Vec::new();
+                for (expr, field) in projection.expr.iter().zip(projection.schema.fields()) {
+                    let value = match expr {
+                        Expr::Alias(alias) => alias.expr.as_ref(),
+                        expr => expr,
+                    };
+
+                    // columns that are not assigned are projected as themselves
+                    if matches!(value, Expr::Column(column) if column.name == *field.name()) {
+                        continue;
+                    }
+
+                    assignments.push((field.name().to_string(), unnormalize_col(value.clone())));
+                }
+
+                Ok(DmlOperation::Update {
+                    assignments,
+                    filters: collect_filters(&dml.op, &projection.input)?,
+                })
+            }
+            ref op => UnsupportedOperationSnafu { op: op.to_string() }.fail(),
+        }
+    }
+
+    #[must_use]
+    pub fn filters(&self) -> &[Expr] {
+        match self {
+            DmlOperation::Delete { filters } | DmlOperation::Update { filters, .. } => filters,
+        }
+    }
+
+    /// Unparses the operation into a `DELETE` or `UPDATE` statement against `table`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the filter or assignment expressions can't be unparsed
+    /// for the given dialect.
+    pub fn to_sql(&self, table: &TableReference, dialect: &dyn Dialect) -> Result<String> {
+        let unparser = Unparser::new(dialect);
+        let table = quote_table_reference(table, dialect);
+
+        let selection = match conjunction(self.filters().to_vec()) {
+            Some(predicate) => {
+                let predicate = unparser
+                    .expr_to_sql(&predicate)
+                    .context(UnableToGenerateSQLSnafu)?;
+                format!(" WHERE {predicate}")
+            }
+            None => String::new(),
+        };
+
+        match self {
+            DmlOperation::Delete { .. } => Ok(format!("DELETE FROM {table}{selection}")),
+            DmlOperation::Update { assignments, .. } => {
+                let assignments = assignments
+                    .iter()
+                    .map(|(column, value)| {
+                        let value = unparser
+                            .expr_to_sql(value)
+                            .context(UnableToGenerateSQLSnafu)?;
+                        Ok(format!("{} = {value}", quote_identifier(column, dialect)))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(format!(
+                    "UPDATE {table} SET {assignments}{selection}",
+                    assignments = assignments.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+impl fmt::Display for DmlOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filters = self
+            .filters()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match self {
+            DmlOperation::Delete { .. } => write!(f, "Delete: filters=[{filters}]"),
+            DmlOperation::Update { assignments, .. } => {
+                let assignments = assignments
+                    .iter()
+                    .map(|(column, value)| format!("{column} = {value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Update: assignments=[{assignments}] filters=[{filters}]")
+            }
+        }
+    }
+}
+
+fn collect_filters(op: &WriteOp, plan: &LogicalPlan) -> Result<Vec<Expr>> {
+    match plan {
+        LogicalPlan::Filter(filter) => {
+            let mut filters: Vec<Expr> = split_conjunction(&filter.predicate)
+                .into_iter()
+                .map(|expr| unnormalize_col(expr.clone()))
+                .collect();
+            filters.extend(collect_filters(op, &filter.input)?);
+            Ok(filters)
+        }
+        // the optimizer may have pushed the filters into the scan, or wrapped it in a projection
+        LogicalPlan::TableScan(scan) => Ok(scan
+            .filters
+            .iter()
+            .map(|expr| unnormalize_col(expr.clone()))
+            .collect()),
+        LogicalPlan::Projection(projection) => collect_filters(op, &projection.input),
+        LogicalPlan::SubqueryAlias(alias) => collect_filters(op, &alias.input),
+        plan => UnsupportedPlanSnafu {
+            op: op.to_string(),
+            node: plan.display().to_string(),
+        }
+        .fail(),
+    }
+}
+
+fn quote_identifier(identifier: &str, dialect: &dyn Dialect) -> String {
+    match dialect.identifier_quote_style(identifier) {
+        Some(quote) => ast::Ident::with_quote(quote, identifier).to_string(),
+        None => ast::Ident::new(identifier).to_string(),
+    }
+}
+
+pub(crate) fn quote_table_reference(table: &TableReference, dialect: &dyn Dialect) -> String {
+    [table.catalog(), table.schema(), Some(table.table())]
+        .into_iter()
+        .flatten()
+        .map(|part| quote_identifier(part, dialect))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// A table provider that can apply `DELETE` and `UPDATE` statements to its remote table.
+#[async_trait]
+pub trait DmlTableProvider: fmt::Debug + Send + Sync {
+    /// Executes the operation against the remote table, returning the number of affected rows.
+    async fn execute_dml(&self, operation: &DmlOperation) -> DataFusionResult<u64>;
+}
+
+/// Returns the [`DmlTableProvider`] for a table provider created by this crate, if it supports DML.
+#[must_use]
+pub fn dml_table_provider(provider: &Arc<dyn TableProvider>) -> Option<Arc<dyn DmlTableProvider>> {
+    let any = provider.as_any();
+
+    #[cfg(feature = "duckdb")]
+    if let Some(writer) = any.downcast_ref::<crate::duckdb::write::DuckDBTableWriter>() {
+        return Some(Arc::new(writer.clone()));
+    }
+
+    #[cfg(feature = "mysql")]
+    if let Some(writer) = any.downcast_ref::<crate::mysql::write::MySQLTableWriter>() {
+        return Some(Arc::new(writer.clone()));
+    }
+
+    #[cfg(feature = "postgres")]
+    if let Some(writer) = any.downcast_ref::<crate::postgres::write::PostgresTableWriter>() {
+        return Some(Arc::new(writer.clone()));
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let Some(writer) = any.downcast_ref::<crate::sqlite::write::SqliteTableWriter>() {
+        return Some(Arc::new(writer.clone()));
+    }
+
+    let _ = any;
+    None
+}
+
+/// A [`QueryPlanner`] that plans `DELETE` and `UPDATE` statements against tables from this crate
+/// as a [`DmlExec`], and delegates every other plan to the wrapped planner.
+///
+/// DataFusion does not plan DML other than `INSERT` on its own, so this planner must be
+/// registered on the session for `DELETE`/`UPDATE` to work:
+///
+/// ```rust,ignore
+/// let state = SessionStateBuilder::new()
+///     .with_default_features()
+///     .with_query_planner(Arc::new(DmlQueryPlanner::new()))
+///     .build();
+/// ```
+#[derive(Debug)]
+pub struct DmlQueryPlanner {
+    inner: Option<Arc<dyn QueryPlanner + Send + Sync>>,
+}
+
+impl Default for DmlQueryPlanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DmlQueryPlanner {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { inner: None }
+    }
+
+    /// Delegates non-DML plans to `planner` instead of the [`DefaultPhysicalPlanner`],
+    /// e.g. to combine DML support with federation.
+    #[must_use]
+    pub fn with_query_planner(mut self, planner: Arc<dyn QueryPlanner + Send + Sync>) -> Self {
+        self.inner = Some(planner);
+        self
+    }
+}
+
+#[async_trait]
+impl QueryPlanner for DmlQueryPlanner {
+    async fn create_physical_plan(
+        &self,
+        logical_plan: &LogicalPlan,
+        session_state: &SessionState,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if let LogicalPlan::Dml(dml) = logical_plan {
+            if matches!(dml.op, WriteOp::Delete | WriteOp::Update) {
+                let provider = dml
+                    .target
+                    .as_any()
+                    .downcast_ref::<DefaultTableSource>()
+                    .and_then(|source| dml_table_provider(&source.table_provider));
+
+                if let Some(provider) = provider {
+                    let operation = DmlOperation::try_from_statement(dml)
+                        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+                    return Ok(Arc::new(DmlExec::new(provider, operation)));
+                }
+            }
+        }
+
+        match &self.inner {
+            Some(planner) => {
+                planner
+                    .create_physical_plan(logical_plan, session_state)
+                    .await
+            }
+            None => {
+                DefaultPhysicalPlanner::default()
+                    .create_physical_plan(logical_plan, session_state)
+                    .await
+            }
+        }
+    }
+}
+
+/// Executes a [`DmlOperation`] against a remote table, producing a single row with the number
+/// of affected rows in a `count` column.
+pub struct DmlExec {
+    provider: Arc<dyn DmlTableProvider>,
+    operation: DmlOperation,
+    properties: PlanProperties,
+}
+
+impl DmlExec {
+    #[must_use]
+    pub fn new(provider: Arc<dyn DmlTableProvider>, operation: DmlOperation) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(make_count_schema()),
+            Partitioning::UnknownPartitioning(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+
+        Self {
+            provider,
+            operation,
+            properties,
+        }
+    }
+
+    #[must_use]
+    pub fn operation(&self) -> &DmlOperation {
+        &self.operation
+    }
+}
+
+impl fmt::Debug for DmlExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DmlExec {}", self.operation)
+    }
+}
+
+impl DisplayAs for DmlExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DmlExec {}", self.operation)
+    }
+}
+
+impl ExecutionPlan for DmlExec {
+    fn name(&self) -> &'static str {
+        "DmlExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        _partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let provider = Arc::clone(&self.provider);
+        let operation = self.operation.clone();
+        let schema = make_count_schema();
+
+        let stream = stream::once({
+            let schema = Arc::clone(&schema);
+            async move {
+                let count = provider.execute_dml(&operation).await?;
+                let array = Arc::new(UInt64Array::from(vec![count]));
+                Ok(RecordBatch::try_new(schema, vec![array])?)
+            }
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+    }
+}
+
+fn make_count_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![Field::new(
+        "count",
+        DataType::UInt64,
+        false,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::{
+        datasource::{provider_as_source, MemTable},
+        logical_expr::{col, lit, LogicalPlanBuilder},
+        sql::unparser::dialect::{MySqlDialect, PostgreSqlDialect},
+    };
+
+    fn table_scan() -> LogicalPlanBuilder {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let table = MemTable::try_new(Arc::clone(&schema), vec![vec![]]).expect("mem table");
+
+        LogicalPlanBuilder::scan("users", provider_as_source(Arc::new(table)), None)
+            .expect("table scan")
+    }
+
+    fn dml(op: WriteOp, input: LogicalPlan) -> DmlStatement {
+        let target = match &input {
+            LogicalPlan::TableScan(scan) => Arc::clone(&scan.source),
+            _ => provider_as_source(Arc::new(
+                MemTable::try_new(Arc::new(Schema::empty()), vec![vec![]]).expect("mem table"),
+            )),
+        };
+        DmlStatement::new(TableReference::bare("users"), target, op, Arc::new(input))
+    }
+
+    #[test]
+    fn test_delete_to_sql() {
+        let plan = table_scan()
+            .filter(col("users.id").gt(lit(10)).and(col("name").is_null()))
+            .expect("filter")
+            .build()
+            .expect("plan");
+
+        let operation =
+            DmlOperation::try_from_statement(&dml(WriteOp::Delete, plan)).expect("delete");
+
+        assert_eq!(
+            operation
+                .to_sql(
+                    &TableReference::partial("public", "users"),
+                    &PostgreSqlDialect {}
+                )
+                .expect("sql"),
+            r#"DELETE FROM "public"."users" WHERE (("id" > 10) AND "name" IS NULL)"#
+        );
+    }
+
+    #[test]
+    fn test_delete_without_filter_to_sql() {
+        let plan = table_scan().build().expect("plan");
+
+        let operation =
+            DmlOperation::try_from_statement(&dml(WriteOp::Delete, plan)).expect("delete");
+
+        assert_eq!(operation, DmlOperation::Delete { filters: vec![] });
+        assert_eq!(
+            operation
+                .to_sql(&TableReference::bare("users"), &MySqlDialect {})
+                .expect("sql"),
+            "DELETE FROM `users`"
+        );
+    }
+
+    #[test]
+    fn test_update_to_sql() {
+        let plan = table_scan()
+            .filter(col("id").eq(lit(1)))
+            .expect("filter")
+            .project(vec![col("users.id"), lit("alice").alias("name")])
+            .expect("projection")
+            .build()
+            .expect("plan");
+
+        let operation =
+            DmlOperation::try_from_statement(&dml(WriteOp::Update, plan)).expect("update");
+
+        assert_eq!(
+            operation
+                .to_sql(&TableReference::bare("users"), &PostgreSqlDialect {})
+                .expect("sql"),
+            r#"UPDATE "users" SET "name" = 'alice' WHERE ("id" = 1)"#
+        );
+    }
+
+    #[test]
+    fn test_unsupported_plan() {
+        let plan = table_scan()
+            .limit(0, Some(1))
+            .expect("limit")
+            .build()
+            .expect("plan");
+
+        let err = DmlOperation::try_from_statement(&dml(WriteOp::Delete, plan))
+            .expect_err("limit is not supported");
+        assert!(matches!(err, Error::UnsupportedPlan { .. }));
+    }
+}
@@ -0,0 +1,438 @@
+//! Writing the same inserts into several tables, e.g. into the current and the new database of a
+//! migration, see [`FanoutTableWriter`].
+
+use std::{
+    any::Any,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{array::RecordBatch, datatypes::SchemaRef},
+    catalog::Session,
+    common::Constraints,
+    datasource::{TableProvider, TableType},
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::{SendableRecordBatchStream, TaskContext},
+    logical_expr::{dml::InsertOp, Expr},
+    physical_plan::{
+        collect,
+        insert::{DataSink, DataSinkExec},
+        metrics::MetricsSet,
+        stream::RecordBatchStreamAdapter,
+        streaming::{PartitionStream, StreamingTableExec},
+        DisplayAs, DisplayFormatType, ExecutionPlan,
+    },
+};
+use futures::{stream, StreamExt};
+use snafu::prelude::*;
+use tokio::{sync::mpsc, task::JoinSet};
+use tokio_stream::wrappers::ReceiverStream;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("A FanoutTableWriter needs at least one table to write to."))]
+    NoWriters,
+
+    #[snafu(display("The table {index} of the FanoutTableWriter has the columns {found:?}, while the first one has {expected:?}.\nThe tables need the same columns, with the same types, in the same order."))]
+    SchemaMismatch {
+        index: usize,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The number of batches buffered for each table while it is slower than the others.
+const BUFFERED_BATCHES: usize = 2;
+
+/// What a [`FanoutTableWriter`] does when the insert into one of its tables fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FanoutFailurePolicy {
+    /// Fail the insert, and abort the inserts into the other tables that haven't committed yet.
+    #[default]
+    AbortAll,
+    /// Log the failure and keep inserting into the other tables. The insert only fails if it
+    /// fails for all of the tables.
+    BestEffort,
+}
+
+/// Writes each insert into several tables, e.g. a DuckDB and a Postgres table during a migration
+/// that writes to both databases. The batches of the insert are sent to the table writers of all
+/// the tables at once, and each of them commits when the insert ends.
+///
+/// Scans read the first table, which is the source of truth. The tables commit on their own, so
+/// with [`FanoutFailurePolicy::AbortAll`] a table that fails to commit after another table
+/// committed leaves the inserted rows in the other table.
+///
+/// ```rust,ignore
+/// let writer = FanoutTableWriter::try_new(vec![duckdb_writer, postgres_writer])?
+///     .with_failure_policy(FanoutFailurePolicy::AbortAll);
+/// ctx.register_table("orders", Arc::new(writer))?;
+/// ctx.sql("INSERT INTO orders SELECT * FROM new_orders").await?.collect().await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FanoutTableWriter {
+    writers: Vec<Arc<dyn TableProvider>>,
+    failure_policy: FanoutFailurePolicy,
+}
+
+impl FanoutTableWriter {
+    /// Writes into `writers`, the table providers of the tables, which have the same columns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no table, or if the columns of the tables differ.
+    pub fn try_new(writers: Vec<Arc<dyn TableProvider>>) -> Result<Self> {
+        let first = writers.first().context(NoWritersSnafu)?.schema();
+        let columns = |schema: &SchemaRef| {
+            schema
+                .fields()
+                .iter()
+                .map(|field| format!("{} {}", field.name(), field.data_type()))
+                .collect::<Vec<_>>()
+        };
+        let expected = columns(&first);
+        for (index, writer) in writers.iter().enumerate().skip(1) {
+            let found = columns(&writer.schema());
+            ensure!(
+                found == expected,
+                SchemaMismatchSnafu {
+                    index,
+                    expected: expected.clone(),
+                    found,
+                }
+            );
+        }
+        Ok(Self {
+            writers,
+            failure_policy: FanoutFailurePolicy::default(),
+        })
+    }
+
+    #[must_use]
+    pub fn with_failure_policy(mut self, failure_policy: FanoutFailurePolicy) -> Self {
+        self.failure_policy = failure_policy;
+        self
+    }
+
+    #[must_use]
+    pub fn failure_policy(&self) -> FanoutFailurePolicy {
+        self.failure_policy
+    }
+
+    /// The table providers of the tables, in order.
+    #[must_use]
+    pub fn writers(&self) -> &[Arc<dyn TableProvider>] {
+        &self.writers
+    }
+}
+
+#[async_trait]
+impl TableProvider for FanoutTableWriter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.writers[0].schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn constraints(&self) -> Option<&Constraints> {
+        self.writers[0].constraints()
+    }
+
+    fn get_column_default(&self, column: &str) -> Option<&Expr> {
+        self.writers[0].get_column_default(column)
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        self.writers[0]
+            .scan(state, projection, filters, limit)
+            .await
+    }
+
+    async fn insert_into(
+        &self,
+        state: &dyn Session,
+        input: Arc<dyn ExecutionPlan>,
+        op: InsertOp,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let schema = self.schema();
+        let mut senders = Vec::with_capacity(self.writers.len());
+        let mut plans = Vec::with_capacity(self.writers.len());
+        for writer in &self.writers {
+            let (sender, source) = channel_source(&schema)?;
+            plans.push(writer.insert_into(state, source, op).await?);
+            senders.push(sender);
+        }
+
+        Ok(Arc::new(DataSinkExec::new(
+            input,
+            Arc::new(FanoutDataSink {
+                writes: Mutex::new(Some((senders, plans))),
+                failure_policy: self.failure_policy,
+                schema,
+            }),
+            None,
+        )) as _)
+    }
+}
+
+pub(crate) type BatchSender = mpsc::Sender<DataFusionResult<RecordBatch>>;
+
+/// A source of the batches sent to the returned sender, as the input of an insert into a table
+/// that runs while the batches are produced. The source ends when the sender is dropped.
+pub(crate) fn channel_source(
+    schema: &SchemaRef,
+) -> DataFusionResult<(BatchSender, Arc<dyn ExecutionPlan>)> {
+    let (sender, receiver) = mpsc::channel(BUFFERED_BATCHES);
+    let source = StreamingTableExec::try_new(
+        Arc::clone(schema),
+        vec![Arc::new(FanoutPartition {
+            schema: Arc::clone(schema),
+            receiver: Mutex::new(Some(receiver)),
+        })],
+        None,
+        Vec::new(),
+        false,
+        None,
+    )?;
+    Ok((sender, Arc::new(source)))
+}
+
+/// The input of the insert into one of the tables, which receives the batches of the insert.
+struct FanoutPartition {
+    schema: SchemaRef,
+    receiver: Mutex<Option<mpsc::Receiver<DataFusionResult<RecordBatch>>>>,
+}
+
+impl fmt::Debug for FanoutPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FanoutPartition")
+    }
+}
+
+impl PartitionStream for FanoutPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let receiver = self
+            .receiver
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let batches = match receiver {
+            Some(receiver) => ReceiverStream::new(receiver).boxed(),
+            None => stream::once(async {
+                Err(DataFusionError::Execution(
+                    "The input of the insert can only be read once".to_string(),
+                ))
+            })
+            .boxed(),
+        };
+        Box::pin(RecordBatchStreamAdapter::new(
+            Arc::clone(&self.schema),
+            batches,
+        ))
+    }
+}
+
+/// Sends the batches of the insert to the inserts into each of the tables.
+struct FanoutDataSink {
+    writes: Mutex<Option<(Vec<BatchSender>, Vec<Arc<dyn ExecutionPlan>>)>>,
+    failure_policy: FanoutFailurePolicy,
+    schema: SchemaRef,
+}
+
+impl FanoutDataSink {
+    /// Fails the inserts of `senders` with `reason`, so that they abort instead of committing.
+    async fn abort(senders: &mut [Option<BatchSender>], reason: &str) {
+        for sender in senders.iter_mut().filter_map(Option::take) {
+            let _ = sender
+                .send(Err(DataFusionError::Execution(format!(
+                    "The insert was aborted: {reason}"
+                ))))
+                .await;
+        }
+    }
+}
+
+#[async_trait]
+impl DataSink for FanoutDataSink {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        None
+    }
+
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    async fn write_all(
+        &self,
+        mut data: SendableRecordBatchStream,
+        context: &Arc<TaskContext>,
+    ) -> DataFusionResult<u64> {
+        let (senders, plans) = self
+            .writes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .ok_or_else(|| {
+                DataFusionError::Execution(
+                    "The insert of a FanoutTableWriter can only run once".to_string(),
+                )
+            })?;
+        let mut senders = senders.into_iter().map(Some).collect::<Vec<_>>();
+        // dropping the set when the insert is cancelled aborts the inserts before their inputs
+        // end, so that they don't commit
+        let mut writes = JoinSet::new();
+        for (index, plan) in plans.into_iter().enumerate() {
+            let context = Arc::clone(context);
+            writes.spawn(async move { (index, collect(plan, context).await) });
+        }
+
+        let mut num_rows = 0;
+        let mut aborted_by = None;
+        while let Some(batch) = data.next().await {
+            let batch = match batch {
+                Ok(batch) => batch,
+                Err(e) => {
+                    Self::abort(&mut senders, "the input of the insert failed").await;
+                    while writes.join_next().await.is_some() {}
+                    return Err(e);
+                }
+            };
+            num_rows += batch.num_rows() as u64;
+            for (index, slot) in senders.iter_mut().enumerate() {
+                let Some(sender) = slot else {
+                    continue;
+                };
+                if sender.send(Ok(batch.clone())).await.is_err() {
+                    // the insert into the table failed and dropped its input
+                    *slot = None;
+                    if self.failure_policy == FanoutFailurePolicy::AbortAll {
+                        aborted_by = Some(index);
+                        break;
+                    }
+                }
+            }
+            if let Some(index) = aborted_by {
+                Self::abort(
+                    &mut senders,
+                    &format!("the insert into the table {index} failed"),
+                )
+                .await;
+            }
+            if senders.iter().all(Option::is_none) {
+                break;
+            }
+        }
+        // the inputs end, and the tables commit
+        drop(senders);
+
+        let num_writes = writes.len();
+        let mut errors = Vec::new();
+        while let Some(write) = writes.join_next().await {
+            let (index, result) = write.map_err(|e| DataFusionError::External(Box::new(e)))?;
+            if let Err(e) = result {
+                tracing::warn!("The insert into the table {index} of the fanout failed: {e}");
+                errors.push((index, e));
+            }
+        }
+        if self.failure_policy == FanoutFailurePolicy::BestEffort && errors.len() < num_writes {
+            return Ok(num_rows);
+        }
+        // the error of the insert that aborted the others, rather than the errors of the aborts
+        errors.sort_by_key(|(index, _)| Some(*index) != aborted_by);
+        match errors.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(num_rows),
+        }
+    }
+}
+
+impl fmt::Debug for FanoutDataSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FanoutDataSink")
+    }
+}
+
+impl DisplayAs for FanoutDataSink {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FanoutDataSink({:?})", self.failure_policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::{
+            array::Int64Array,
+            datatypes::{DataType, Field, Schema},
+        },
+        datasource::MemTable,
+        prelude::SessionContext,
+    };
+
+    use super::*;
+
+    fn table(name: &str) -> Result<Arc<dyn TableProvider>, DataFusionError> {
+        let schema = Arc::new(Schema::new(vec![Field::new(name, DataType::Int64, true)]));
+        Ok(Arc::new(MemTable::try_new(schema, vec![vec![]])?))
+    }
+
+    #[tokio::test]
+    async fn test_fanout_insert() -> Result<(), Box<dyn std::error::Error>> {
+        let first = table("id")?;
+        let second = table("id")?;
+        let writer = FanoutTableWriter::try_new(vec![Arc::clone(&first), Arc::clone(&second)])?;
+
+        let ctx = SessionContext::new();
+        ctx.register_table("ids", Arc::new(writer))?;
+        ctx.sql("INSERT INTO ids VALUES (1), (2), (3)")
+            .await?
+            .collect()
+            .await?;
+
+        for table in [first, second] {
+            let ctx = SessionContext::new();
+            ctx.register_table("ids", table)?;
+            let batches = ctx.sql("SELECT sum(id) FROM ids").await?.collect().await?;
+            let sum = batches[0]
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .map(|sums| sums.value(0));
+            assert_eq!(sum, Some(6));
+        }
+
+        assert!(matches!(
+            FanoutTableWriter::try_new(vec![table("id")?, table("name")?]),
+            Err(Error::SchemaMismatch { index: 1, .. })
+        ));
+        assert!(matches!(
+            FanoutTableWriter::try_new(Vec::new()),
+            Err(Error::NoWriters)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use std::{any::Any, sync::Arc};
+
+use datafusion::{
+    arrow::datatypes::DataType,
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        ColumnarValue, Expr, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+    },
+    sql::{
+        sqlparser::ast::{self, BinaryOperator},
+        unparser::{dialect::Dialect, Unparser},
+    },
+};
+
+use super::dialect::{function, ExtendedDialect};
+
+/// Name of the full-text search function, see [`match_udf`].
+pub const MATCH_FUNCTION: &str = "match";
+
+/// Returns the `match(column, query)` scalar function, which is true for rows where `column`
+/// matches the full-text search `query`.
+///
+/// The function has no local implementation: register it with the session, and enable full-text
+/// search on the column with [`FullTextSearch`] so that it's pushed down to the remote database.
+///
+/// ```rust,ignore
+/// ctx.register_udf(match_udf());
+/// ctx.sql("SELECT * FROM posts WHERE match(body, 'rust & datafusion')").await?;
+/// ```
+#[must_use]
+pub fn match_udf() -> ScalarUDF {
+    ScalarUDF::new_from_impl(MatchUdf::new())
+}
+
+#[derive(Debug)]
+struct MatchUdf {
+    signature: Signature,
+}
+
+impl MatchUdf {
+    fn new() -> Self {
+        Self {
+            signature: Signature::string(2, Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for MatchUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        MATCH_FUNCTION
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        Err(DataFusionError::Execution(format!(
+            "{MATCH_FUNCTION}() can't be evaluated locally, it must be pushed down to a table with full-text search enabled on the column"
+        )))
+    }
+}
+
+/// The Postgres text search configuration that text columns are converted with, if none is set.
+pub const POSTGRES_DEFAULT_CONFIG: &str = "simple";
+
+/// The full-text search syntax of the remote database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FullTextSyntax {
+    /// `to_tsvector(config, column) @@ to_tsquery(config, query)`, or `column @@ to_tsquery(query)`
+    /// for columns that are already a `tsvector`
+    Postgres {
+        config: Option<String>,
+        tsvector_columns: Vec<String>,
+    },
+    /// `column MATCH query`, for columns of an FTS5 virtual table
+    SqliteFts5,
+}
+
+/// The columns of a table that support full-text search, and how to query them.
+///
+/// `match(column, query)` predicates on these columns are pushed down in the syntax of the remote
+/// database. On any other column `match()` can't be pushed down, and the query fails. `LIKE`
+/// predicates are never rewritten, because full-text matching doesn't have substring semantics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullTextSearch {
+    columns: Vec<String>,
+    syntax: FullTextSyntax,
+}
+
+impl FullTextSearch {
+    #[must_use]
+    pub fn postgres(columns: Vec<String>) -> Self {
+        Self {
+            columns,
+            syntax: FullTextSyntax::Postgres {
+                config: None,
+                tsvector_columns: Vec::new(),
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn sqlite_fts5(columns: Vec<String>) -> Self {
+        Self {
+            columns,
+            syntax: FullTextSyntax::SqliteFts5,
+        }
+    }
+
+    /// Sets the Postgres text search configuration, e.g. `english`. Has no effect for SQLite.
+    ///
+    /// Text columns are converted with [`POSTGRES_DEFAULT_CONFIG`] if no configuration is set,
+    /// because an expression index on `to_tsvector()` is only used for the configuration it was
+    /// created with.
+    #[must_use]
+    pub fn with_config(mut self, config: impl Into<String>) -> Self {
+        if let FullTextSyntax::Postgres {
+            config: current, ..
+        } = &mut self.syntax
+        {
+            *current = Some(config.into());
+        }
+        self
+    }
+
+    /// Marks the full-text search columns that have the Postgres `tsvector` type, which are
+    /// matched as they are instead of being converted with `to_tsvector()`. Has no effect for
+    /// SQLite.
+    #[must_use]
+    pub fn with_tsvector_columns(mut self, columns: Vec<String>) -> Self {
+        if let FullTextSyntax::Postgres {
+            tsvector_columns, ..
+        } = &mut self.syntax
+        {
+            *tsvector_columns = columns;
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Wraps `dialect` so that it unparses `match()` on the full-text search columns.
+    #[must_use]
+    pub fn dialect(
+        &self,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Arc<dyn Dialect + Send + Sync> {
+        let full_text_search = self.clone();
+        Arc::new(
+            ExtendedDialect::new(dialect).with_scalar_function_override(Box::new(
+                move |unparser, func_name, args| {
+                    if func_name != MATCH_FUNCTION {
+                        return Ok(None);
+                    }
+                    full_text_search.match_to_sql(unparser, args).map(Some)
+                },
+            )),
+        )
+    }
+
+    fn match_to_sql(&self, unparser: &Unparser, args: &[Expr]) -> DataFusionResult<ast::Expr> {
+        let [Expr::Column(column), query] = args else {
+            return Err(DataFusionError::Plan(format!(
+                "{MATCH_FUNCTION}() expects a column and a query, got {} arguments",
+                args.len()
+            )));
+        };
+
+        if !self.columns.contains(&column.name) {
+            return Err(DataFusionError::Plan(format!(
+                "Full-text search is not enabled on the column '{}'",
+                column.name
+            )));
+        }
+
+        let is_tsvector = matches!(
+            &self.syntax,
+            FullTextSyntax::Postgres { tsvector_columns, .. } if tsvector_columns.contains(&column.name)
+        );
+        let column = unparser.expr_to_sql(&Expr::Column(column.clone()))?;
+        let query = unparser.expr_to_sql(query)?;
+
+        match &self.syntax {
+            FullTextSyntax::Postgres { config, .. } => {
+                let with_config = |config: &str, arg: ast::Expr| {
+                    vec![
+                        ast::Expr::Value(ast::Value::SingleQuotedString(config.to_string())),
+                        arg,
+                    ]
+                };
+
+                // there is no to_tsvector(tsvector), and the query of a tsvector column is parsed
+                // with the default configuration of the server unless one is set
+                let (document, query) = if is_tsvector {
+                    let query = match config {
+                        Some(config) => with_config(config, query),
+                        None => vec![query],
+                    };
+                    (column, function("to_tsquery", query))
+                } else {
+                    let config = config.as_deref().unwrap_or(POSTGRES_DEFAULT_CONFIG);
+                    (
+                        function("to_tsvector", with_config(config, column)),
+                        function("to_tsquery", with_config(config, query)),
+                    )
+                };
+
+                Ok(ast::Expr::BinaryOp {
+                    left: Box::new(document),
+                    op: BinaryOperator::AtAt,
+                    right: Box::new(query),
+                })
+            }
+            FullTextSyntax::SqliteFts5 => Ok(ast::Expr::BinaryOp {
+                left: Box::new(column),
+                op: BinaryOperator::Custom("MATCH".to_string()),
+                right: Box::new(query),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        prelude::{col, lit, SessionContext},
+        sql::unparser::dialect::{PostgreSqlDialect, SqliteDialect},
+    };
+
+    use super::*;
+
+    fn match_to_sql(dialect: &dyn Dialect, column: &str) -> DataFusionResult<String> {
+        let expr = match_udf().call(vec![col(column), lit("rust & datafusion")]);
+        Ok(Unparser::new(dialect).expr_to_sql(&expr)?.to_string())
+    }
+
+    #[test]
+    fn test_postgres_match_to_sql() {
+        let full_text_search = FullTextSearch::postgres(vec!["body".to_string()]);
+        let dialect = full_text_search.dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            match_to_sql(dialect.as_ref(), "body").expect("match is unparsed"),
+            r#"to_tsvector('simple', "body") @@ to_tsquery('simple', 'rust & datafusion')"#
+        );
+
+        let dialect = full_text_search
+            .clone()
+            .with_config("english")
+            .dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            match_to_sql(dialect.as_ref(), "body").expect("match is unparsed"),
+            r#"to_tsvector('english', "body") @@ to_tsquery('english', 'rust & datafusion')"#
+        );
+    }
+
+    #[test]
+    fn test_postgres_tsvector_match_to_sql() {
+        let full_text_search =
+            FullTextSearch::postgres(vec!["body".to_string(), "search".to_string()])
+                .with_tsvector_columns(vec!["search".to_string()]);
+        let dialect = full_text_search
+            .clone()
+            .dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            match_to_sql(dialect.as_ref(), "search").expect("match is unparsed"),
+            r#""search" @@ to_tsquery('rust & datafusion')"#
+        );
+        assert_eq!(
+            match_to_sql(dialect.as_ref(), "body").expect("match is unparsed"),
+            r#"to_tsvector('simple', "body") @@ to_tsquery('simple', 'rust & datafusion')"#
+        );
+
+        let dialect = full_text_search
+            .with_config("english")
+            .dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            match_to_sql(dialect.as_ref(), "search").expect("match is unparsed"),
+            r#""search" @@ to_tsquery('english', 'rust & datafusion')"#
+        );
+    }
+
+    #[test]
+    fn test_sqlite_match_to_sql() {
+        let dialect = FullTextSearch::sqlite_fts5(vec!["body".to_string()])
+            .dialect(Arc::new(SqliteDialect {}));
+        assert_eq!(
+            match_to_sql(dialect.as_ref(), "body").expect("match is unparsed"),
+            "`body` MATCH 'rust & datafusion'"
+        );
+        assert!(match_to_sql(dialect.as_ref(), "title").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_match_is_not_evaluated_locally() {
+        let ctx = SessionContext::new();
+        ctx.register_udf(match_udf());
+
+        let df = ctx
+            .sql("SELECT match('rust', 'rust')")
+            .await
+            .expect("match() is planned");
+        assert!(df.collect().await.is_err());
+    }
+}
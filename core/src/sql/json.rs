@@ -0,0 +1,461 @@
+use std::sync::Arc;
+
+use datafusion::{
+    arrow::datatypes::DataType,
+    common::ScalarValue,
+    error::Result as DataFusionResult,
+    logical_expr::Expr,
+    sql::{
+        sqlparser::ast::{self, BinaryOperator, CastKind},
+        unparser::{dialect::Dialect, Unparser},
+    },
+};
+
+use super::dialect::{function, ExtendedDialect};
+
+/// The JSON operators of the remote database, used to push down JSON functions.
+///
+/// The `json_get_*` and `json_as_text` functions of the
+/// [`datafusion-functions-json`](https://crates.io/crates/datafusion-functions-json) crate, which
+/// also plans the `->` and `->>` operators, are unparsed into the native JSON extraction of the
+/// remote database, so the values are extracted remotely instead of fetching whole documents.
+/// The typed `json_get_int`, `json_get_float` and `json_get_bool` functions are only cast when the
+/// value has the matching JSON type, so they return `NULL` for other values like they do locally,
+/// instead of failing the remote query. Calls with a path that isn't made of string keys and
+/// non-negative integer indices are unparsed as is, because negative indices count from the end of
+/// the array in Postgres but are invalid in MySQL and DuckDB paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonSyntax {
+    /// `json::jsonb -> 'key' ->> 0`
+    Postgres,
+    /// `JSON_UNQUOTE(JSON_EXTRACT(json, '$."key"[0]'))`
+    MySql,
+    /// `json_extract_string(json, '$."key"[0]')`
+    DuckDB,
+}
+
+/// The value a JSON function extracts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Extract {
+    Json,
+    Text,
+    Int,
+    Float,
+    Bool,
+}
+
+impl Extract {
+    fn from_function(func_name: &str) -> Option<Self> {
+        match func_name {
+            "json_get_json" => Some(Self::Json),
+            "json_get_str" | "json_as_text" => Some(Self::Text),
+            "json_get_int" => Some(Self::Int),
+            "json_get_float" => Some(Self::Float),
+            "json_get_bool" => Some(Self::Bool),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathElement {
+    Key(String),
+    Index(i64),
+}
+
+impl JsonSyntax {
+    /// Wraps `dialect` so that it unparses JSON functions into the operators of this syntax.
+    #[must_use]
+    pub fn dialect(
+        &self,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Arc<dyn Dialect + Send + Sync> {
+        let syntax = *self;
+        let inner = Arc::clone(&dialect);
+        Arc::new(
+            ExtendedDialect::new(dialect).with_scalar_function_override(Box::new(
+                move |unparser, func_name, args| {
+                    let Some(extract) = Extract::from_function(func_name) else {
+                        return Ok(None);
+                    };
+                    syntax.json_to_sql(unparser, inner.as_ref(), extract, args)
+                },
+            )),
+        )
+    }
+
+    fn json_to_sql(
+        &self,
+        unparser: &Unparser,
+        dialect: &dyn Dialect,
+        extract: Extract,
+        args: &[Expr],
+    ) -> DataFusionResult<Option<ast::Expr>> {
+        let Some((json, path)) = args.split_first() else {
+            return Ok(None);
+        };
+        let Some(path) = path
+            .iter()
+            .map(path_element)
+            .collect::<Option<Vec<PathElement>>>()
+        else {
+            return Ok(None);
+        };
+        let json = unparser.expr_to_sql(json)?;
+
+        let cast_type = match extract {
+            Extract::Json | Extract::Text => None,
+            Extract::Int => Some(dialect.int64_cast_dtype()),
+            Extract::Float => Some(dialect.float64_ast_dtype()),
+            // MySQL can't cast to a boolean
+            Extract::Bool if *self == Self::MySql => return Ok(None),
+            Extract::Bool => Some(ast::DataType::Boolean),
+        };
+
+        let expr = match self {
+            Self::Postgres => postgres_extract(json, &path, extract),
+            Self::MySql => {
+                let extracted = function("JSON_EXTRACT", vec![json, json_path(&path)]);
+                match extract {
+                    Extract::Json => extracted,
+                    Extract::Text => function("JSON_UNQUOTE", vec![extracted]),
+                    Extract::Int | Extract::Float | Extract::Bool => {
+                        let json_type = function("JSON_TYPE", vec![extracted.clone()]);
+                        let types: &[&str] = match extract {
+                            Extract::Int => &["INTEGER", "UNSIGNED INTEGER"],
+                            _ => &["INTEGER", "UNSIGNED INTEGER", "DOUBLE", "DECIMAL"],
+                        };
+                        guarded(
+                            type_in(json_type, types),
+                            function("JSON_UNQUOTE", vec![extracted]),
+                        )
+                    }
+                }
+            }
+            Self::DuckDB => {
+                let path = json_path(&path);
+                match extract {
+                    Extract::Json => function("json_extract", vec![json, path]),
+                    Extract::Text => function("json_extract_string", vec![json, path]),
+                    Extract::Int | Extract::Float | Extract::Bool => {
+                        let json_type = function("json_type", vec![json.clone(), path.clone()]);
+                        let types: &[&str] = match extract {
+                            Extract::Int => &["BIGINT", "UBIGINT"],
+                            Extract::Float => &["BIGINT", "UBIGINT", "DOUBLE"],
+                            _ => &["BOOLEAN"],
+                        };
+                        guarded(
+                            type_in(json_type, types),
+                            function("json_extract_string", vec![json, path]),
+                        )
+                    }
+                }
+            }
+        };
+
+        Ok(Some(match cast_type {
+            Some(data_type) => cast_result(expr, data_type),
+            None => expr,
+        }))
+    }
+}
+
+/// `CASE WHEN condition THEN value END`, which is `NULL` when the condition doesn't hold.
+fn guarded(condition: ast::Expr, value: ast::Expr) -> ast::Expr {
+    ast::Expr::Case {
+        operand: None,
+        conditions: vec![condition],
+        results: vec![value],
+        else_result: None,
+    }
+}
+
+fn type_in(json_type: ast::Expr, types: &[&str]) -> ast::Expr {
+    let mut types = types
+        .iter()
+        .map(|json_type| ast::Expr::Value(ast::Value::SingleQuotedString(json_type.to_string())))
+        .collect::<Vec<_>>();
+    if types.len() == 1 {
+        return ast::Expr::BinaryOp {
+            left: Box::new(json_type),
+            op: BinaryOperator::Eq,
+            right: Box::new(types.remove(0)),
+        };
+    }
+    ast::Expr::InList {
+        expr: Box::new(json_type),
+        list: types,
+        negated: false,
+    }
+}
+
+/// Casts `expr`, or the result of `expr` if it's a [`guarded`] value, to `data_type`.
+fn cast_result(expr: ast::Expr, data_type: ast::DataType) -> ast::Expr {
+    let cast = |expr: ast::Expr| ast::Expr::Cast {
+        kind: CastKind::Cast,
+        expr: Box::new(expr),
+        data_type: data_type.clone(),
+        format: None,
+    };
+    match expr {
+        ast::Expr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => ast::Expr::Case {
+            operand,
+            conditions,
+            results: results.into_iter().map(cast).collect(),
+            else_result,
+        },
+        expr => cast(expr),
+    }
+}
+
+/// Chains `->` for each path element, or `->>` for the last one when extracting text.
+/// The value is cast to `jsonb` first, because JSON columns are read as text.
+///
+/// Typed values are extracted as `jsonb` and guarded by their `jsonb_typeof()`, and the text of the
+/// value is taken with `#>> '{}'`, which also works for the whole document.
+fn postgres_extract(json: ast::Expr, path: &[PathElement], extract: Extract) -> ast::Expr {
+    let json = ast::Expr::Cast {
+        kind: CastKind::Cast,
+        expr: Box::new(json),
+        data_type: ast::DataType::JSONB,
+        format: None,
+    };
+    let as_text = |value: ast::Expr| ast::Expr::BinaryOp {
+        left: Box::new(value),
+        op: BinaryOperator::HashLongArrow,
+        right: Box::new(ast::Expr::Value(ast::Value::SingleQuotedString(
+            "{}".to_string(),
+        ))),
+    };
+
+    let json_type = match extract {
+        Extract::Json => return postgres_path(json, path, false),
+        Extract::Text if path.is_empty() => return as_text(json),
+        Extract::Text => return postgres_path(json, path, true),
+        Extract::Int | Extract::Float => "number",
+        Extract::Bool => "boolean",
+    };
+
+    let value = postgres_path(json, path, false);
+    let text = ast::Expr::Nested(Box::new(as_text(value.clone())));
+    let mut condition = type_in(function("jsonb_typeof", vec![value]), &[json_type]);
+    // json_get_int() is NULL for numbers with a fraction or an exponent
+    if extract == Extract::Int {
+        condition = ast::Expr::BinaryOp {
+            left: Box::new(condition),
+            op: BinaryOperator::And,
+            right: Box::new(ast::Expr::BinaryOp {
+                left: Box::new(text.clone()),
+                op: BinaryOperator::PGRegexMatch,
+                right: Box::new(ast::Expr::Value(ast::Value::SingleQuotedString(
+                    "^-?[0-9]+$".to_string(),
+                ))),
+            }),
+        };
+    }
+    guarded(condition, text)
+}
+
+fn postgres_path(json: ast::Expr, path: &[PathElement], text: bool) -> ast::Expr {
+    path.iter().enumerate().fold(json, |expr, (i, element)| {
+        let op = if text && i == path.len() - 1 {
+            BinaryOperator::LongArrow
+        } else {
+            BinaryOperator::Arrow
+        };
+        let element = match element {
+            PathElement::Key(key) => ast::Value::SingleQuotedString(key.clone()),
+            PathElement::Index(index) => ast::Value::Number(index.to_string(), false),
+        };
+        ast::Expr::BinaryOp {
+            left: Box::new(expr),
+            op,
+            right: Box::new(ast::Expr::Value(element)),
+        }
+    })
+}
+
+/// Formats the path as a JSON path like `$."key"[0]`, as used by MySQL and DuckDB.
+fn json_path(path: &[PathElement]) -> ast::Expr {
+    let mut json_path = "$".to_string();
+    for element in path {
+        match element {
+            PathElement::Key(key) => {
+                json_path.push_str(&format!(".\"{}\"", key.replace('"', "\\\"")));
+            }
+            PathElement::Index(index) => json_path.push_str(&format!("[{index}]")),
+        }
+    }
+    ast::Expr::Value(ast::Value::SingleQuotedString(json_path))
+}
+
+fn path_element(expr: &Expr) -> Option<PathElement> {
+    match expr {
+        Expr::Literal(
+            ScalarValue::Utf8(Some(key))
+            | ScalarValue::LargeUtf8(Some(key))
+            | ScalarValue::Utf8View(Some(key)),
+        ) => Some(PathElement::Key(key.clone())),
+        Expr::Literal(scalar) if scalar.data_type().is_integer() && !scalar.is_null() => {
+            match scalar.cast_to(&DataType::Int64).ok()? {
+                ScalarValue::Int64(Some(index)) if index >= 0 => Some(PathElement::Index(index)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use datafusion::{
+        logical_expr::{
+            ColumnarValue, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+        },
+        prelude::{col, lit},
+        sql::unparser::dialect::{DuckDBDialect, MySqlDialect, PostgreSqlDialect},
+    };
+
+    use super::*;
+
+    /// Stands in for the functions of `datafusion-functions-json`, only the name matters.
+    #[derive(Debug)]
+    struct JsonUdf {
+        name: String,
+        signature: Signature,
+    }
+
+    impl ScalarUDFImpl for JsonUdf {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn signature(&self) -> &Signature {
+            &self.signature
+        }
+
+        fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+            Ok(DataType::Utf8)
+        }
+
+        fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+            unimplemented!()
+        }
+    }
+
+    fn json_to_sql(dialect: &dyn Dialect, name: &str, path: Vec<Expr>) -> String {
+        let mut args = vec![col("doc")];
+        args.extend(path);
+        let expr = ScalarUDF::new_from_impl(JsonUdf {
+            name: name.to_string(),
+            signature: Signature::variadic_any(Volatility::Immutable),
+        })
+        .call(args);
+        Unparser::new(dialect)
+            .expr_to_sql(&expr)
+            .expect("expression is unparsed")
+            .to_string()
+    }
+
+    #[test]
+    fn test_postgres_json_to_sql() {
+        let dialect = JsonSyntax::Postgres.dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_str", vec![lit("tags"), lit(0)]),
+            r#"CAST("doc" AS JSONB) -> 'tags' ->> 0"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_json", vec![lit("user")]),
+            r#"CAST("doc" AS JSONB) -> 'user'"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_int", vec![lit("id")]),
+            r#"CASE WHEN jsonb_typeof(CAST("doc" AS JSONB) -> 'id') = 'number' AND (CAST("doc" AS JSONB) -> 'id' #>> '{}') ~ '^-?[0-9]+$' THEN CAST((CAST("doc" AS JSONB) -> 'id' #>> '{}') AS BIGINT) END"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_bool", vec![lit("active")]),
+            r#"CASE WHEN jsonb_typeof(CAST("doc" AS JSONB) -> 'active') = 'boolean' THEN CAST((CAST("doc" AS JSONB) -> 'active' #>> '{}') AS BOOLEAN) END"#
+        );
+    }
+
+    #[test]
+    fn test_postgres_json_to_sql_empty_path() {
+        let dialect = JsonSyntax::Postgres.dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_str", vec![]),
+            r#"CAST("doc" AS JSONB) #>> '{}'"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_float", vec![]),
+            r#"CASE WHEN jsonb_typeof(CAST("doc" AS JSONB)) = 'number' THEN CAST((CAST("doc" AS JSONB) #>> '{}') AS DOUBLE PRECISION) END"#
+        );
+    }
+
+    #[test]
+    fn test_json_to_sql_negative_index() {
+        let dialect = JsonSyntax::Postgres.dialect(Arc::new(PostgreSqlDialect {}));
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_str", vec![lit("tags"), lit(-1)]),
+            r#"json_get_str("doc", 'tags', -1)"#
+        );
+        let dialect = JsonSyntax::MySql.dialect(Arc::new(MySqlDialect {}));
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_str", vec![lit("tags"), lit(-1)]),
+            "json_get_str(`doc`, 'tags', -1)"
+        );
+    }
+
+    #[test]
+    fn test_mysql_json_to_sql() {
+        let dialect = JsonSyntax::MySql.dialect(Arc::new(MySqlDialect {}));
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_as_text", vec![lit("tags"), lit(0)]),
+            r#"JSON_UNQUOTE(JSON_EXTRACT(`doc`, '$."tags"[0]'))"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_int", vec![lit("id")]),
+            r#"CASE WHEN JSON_TYPE(JSON_EXTRACT(`doc`, '$."id"')) IN ('INTEGER', 'UNSIGNED INTEGER') THEN CAST(JSON_UNQUOTE(JSON_EXTRACT(`doc`, '$."id"')) AS SIGNED) END"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_float", vec![]),
+            r#"CASE WHEN JSON_TYPE(JSON_EXTRACT(`doc`, '$')) IN ('INTEGER', 'UNSIGNED INTEGER', 'DOUBLE', 'DECIMAL') THEN CAST(JSON_UNQUOTE(JSON_EXTRACT(`doc`, '$')) AS DOUBLE) END"#
+        );
+        // not pushed down, so unparsed as is
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_bool", vec![lit("active")]),
+            "json_get_bool(`doc`, 'active')"
+        );
+    }
+
+    #[test]
+    fn test_duckdb_json_to_sql() {
+        let dialect = JsonSyntax::DuckDB.dialect(Arc::new(DuckDBDialect::new()));
+        assert_eq!(
+            json_to_sql(
+                dialect.as_ref(),
+                "json_get_str",
+                vec![lit("user"), lit("name")]
+            ),
+            r#"json_extract_string("doc", '$."user"."name"')"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_int", vec![lit("id")]),
+            r#"CASE WHEN json_type("doc", '$."id"') IN ('BIGINT', 'UBIGINT') THEN CAST(json_extract_string("doc", '$."id"') AS BIGINT) END"#
+        );
+        assert_eq!(
+            json_to_sql(dialect.as_ref(), "json_get_str", vec![col("key")]),
+            r#"json_get_str("doc", "key")"#
+        );
+    }
+}
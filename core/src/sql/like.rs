@@ -0,0 +1,108 @@
+//! Pushing down `ILIKE` to the databases without it.
+//!
+//! DataFusion unparses its case insensitive `LIKE` as `ILIKE`, which Postgres and DuckDB have, but
+//! MySQL and SQLite don't, so the filters with it failed on these databases. They're rewritten to
+//! a `LIKE` that ignores case:
+//!
+//! ```sql
+//! -- "name" ILIKE 'a%'
+//! LOWER("name") LIKE LOWER('a%')
+//! "name" LIKE 'a%'
+//! ```
+//!
+//! The `LIKE` of SQLite ignores the case of the ASCII characters only, and none at all with
+//! `PRAGMA case_sensitive_like`, so its `ILIKE` is only pushed down with the patterns it matches
+//! like DataFusion does, see [`FilterSemantics::sqlite`](super::pushdown::FilterSemantics::sqlite).
+
+use std::ops::ControlFlow;
+
+use datafusion::sql::sqlparser::ast::{self, visit_expressions_mut};
+
+use super::dialect::function;
+
+/// How `x ILIKE y` is written in the SQL of a database without `ILIKE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseInsensitiveLike {
+    /// `LOWER(x) LIKE LOWER(y)`.
+    Lower,
+    /// `x LIKE y`, for the databases whose `LIKE` ignores case, like SQLite, where `COLLATE
+    /// NOCASE` has no effect on `LIKE`.
+    Like,
+}
+
+impl CaseInsensitiveLike {
+    /// The syntax of the database of a table provider, by the name of the provider, or `None` if
+    /// the database has `ILIKE`.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<Self> {
+        match name {
+            "mysql" => Some(Self::Lower),
+            "sqlite" => Some(Self::Like),
+            _ => None,
+        }
+    }
+
+    /// Rewrites the `ILIKE` and `NOT ILIKE` of `statement`.
+    pub(crate) fn rewrite_statement(self, statement: &mut ast::Statement) {
+        let _ = visit_expressions_mut(statement, |expr| {
+            if let ast::Expr::ILike {
+                negated,
+                any: false,
+                expr: value,
+                pattern,
+                escape_char,
+            } = expr
+            {
+                *expr = ast::Expr::Like {
+                    negated: *negated,
+                    any: false,
+                    expr: Box::new(self.value_to_sql(value.as_ref().clone())),
+                    pattern: Box::new(self.pattern_to_sql(pattern.as_ref().clone())),
+                    escape_char: escape_char.clone(),
+                };
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+
+    fn value_to_sql(self, value: ast::Expr) -> ast::Expr {
+        match self {
+            Self::Lower => function("LOWER", vec![value]),
+            Self::Like => value,
+        }
+    }
+
+    fn pattern_to_sql(self, pattern: ast::Expr) -> ast::Expr {
+        match self {
+            Self::Lower => function("LOWER", vec![pattern]),
+            Self::Like => pattern,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::sql::sqlparser::{dialect::GenericDialect, parser::Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_rewrite_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let sql = r#"SELECT "name" FROM "users" WHERE "name" ILIKE 'a%' AND "email" NOT ILIKE 'b' || "domain" OR "name" LIKE 'c%'"#;
+        for (like, expected) in [
+            (
+                CaseInsensitiveLike::Lower,
+                r#"SELECT "name" FROM "users" WHERE LOWER("name") LIKE LOWER('a%') AND LOWER("email") NOT LIKE LOWER('b' || "domain") OR "name" LIKE 'c%'"#,
+            ),
+            (
+                CaseInsensitiveLike::Like,
+                r#"SELECT "name" FROM "users" WHERE "name" LIKE 'a%' AND "email" NOT LIKE 'b' || "domain" OR "name" LIKE 'c%'"#,
+            ),
+        ] {
+            let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+            like.rewrite_statement(&mut statement);
+            assert_eq!(statement.to_string(), expected);
+        }
+        Ok(())
+    }
+}
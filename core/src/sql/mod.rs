@@ -1,3 +1,28 @@
+pub mod approx_distinct;
 pub mod arrow_sql_gen;
+pub mod change_probe;
+pub mod column_expressions;
+pub mod column_mapper;
+pub mod datetime;
 pub mod db_connection_pool;
+pub mod dialect;
+pub mod dml;
+pub mod fanout;
+pub mod full_text;
+pub mod json;
+pub mod like;
+pub mod null_ordering;
+pub mod parameters;
+pub mod partitioned;
+pub mod permissions;
+pub mod pushdown;
+pub mod query_attribution;
+pub mod query_context;
+pub mod query_settings;
+pub mod regexp;
+pub mod sample;
+pub mod schema_drift;
 pub mod sql_provider_datafusion;
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+pub mod temp_table;
+pub mod udf_pushdown;
@@ -0,0 +1,199 @@
+//! Pushing down sorts with `NULLS FIRST` and `NULLS LAST` to the databases without them.
+//!
+//! DataFusion sorts the NULLs last in ascending order and first in descending order, unless a sort
+//! says otherwise. MySQL, and SQLite before 3.30, sort them the other way around and can't be told
+//! otherwise, so their dialects used to drop the order of the NULLs and the sorted rows differed
+//! from DataFusion's. The dialects of these databases now keep it, see [`NullOrdering::dialect`],
+//! and the sorts whose NULLs aren't where the database puts them are sorted by whether the key is
+//! NULL first:
+//!
+//! ```sql
+//! -- ORDER BY "name" ASC NULLS LAST
+//! ORDER BY "name" IS NULL ASC, "name" ASC
+//! ```
+//!
+//! The positions of the SELECT list, like `ORDER BY 1`, are sorted by the expression they select.
+//! The positions that can't be resolved, like those of a `UNION`, keep their `NULLS FIRST` or
+//! `NULLS LAST`, which fails on the databases without them instead of returning the NULLs
+//! elsewhere.
+
+use std::{ops::ControlFlow, sync::Arc};
+
+use datafusion::sql::{sqlparser::ast, unparser::dialect::Dialect};
+
+use super::dialect::ExtendedDialect;
+
+/// Where a database without `NULLS FIRST` and `NULLS LAST` sorts the NULLs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullOrdering {
+    /// NULLs are smaller than the other values, first in ascending order and last in descending
+    /// order, like on MySQL and SQLite.
+    Smallest,
+}
+
+impl NullOrdering {
+    /// The ordering of the database of a table provider, by the name of the provider, or `None`
+    /// if the database has `NULLS FIRST` and `NULLS LAST`.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<Self> {
+        match name {
+            "mysql" | "sqlite" => Some(Self::Smallest),
+            _ => None,
+        }
+    }
+
+    /// Wraps `dialect` so that it keeps the order of the NULLs of the sorts it unparses, for
+    /// [`NullOrdering::rewrite_statement`] to rewrite.
+    #[must_use]
+    pub fn dialect(
+        &self,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Arc<dyn Dialect + Send + Sync> {
+        Arc::new(ExtendedDialect::new(dialect).with_nulls_first_in_sort())
+    }
+
+    /// Rewrites the `NULLS FIRST` and `NULLS LAST` of the sorts of `statement`, of the query and
+    /// of the window and aggregate functions.
+    pub(crate) fn rewrite_statement(self, statement: &mut ast::Statement) {
+        struct Rewrite(NullOrdering);
+
+        impl ast::VisitorMut for Rewrite {
+            type Break = ();
+
+            fn post_visit_query(&mut self, query: &mut ast::Query) -> ControlFlow<Self::Break> {
+                let ast::Query { body, order_by, .. } = query;
+                if let Some(order_by) = order_by {
+                    self.0.rewrite_order_by(&mut order_by.exprs, Some(body));
+                }
+                ControlFlow::Continue(())
+            }
+
+            fn post_visit_expr(&mut self, expr: &mut ast::Expr) -> ControlFlow<Self::Break> {
+                let ast::Expr::Function(function) = expr else {
+                    return ControlFlow::Continue(());
+                };
+                if let Some(ast::WindowType::WindowSpec(spec)) = &mut function.over {
+                    self.0.rewrite_order_by(&mut spec.order_by, None);
+                }
+                if let ast::FunctionArguments::List(args) = &mut function.args {
+                    for clause in &mut args.clauses {
+                        if let ast::FunctionArgumentClause::OrderBy(order_by) = clause {
+                            self.0.rewrite_order_by(order_by, None);
+                        }
+                    }
+                }
+                self.0.rewrite_order_by(&mut function.within_group, None);
+                ControlFlow::Continue(())
+            }
+        }
+
+        let _ = ast::VisitMut::visit(statement, &mut Rewrite(self));
+    }
+
+    /// Rewrites the sorts of `order_by`, the `ORDER BY` of a query whose SELECT list is in
+    /// `body`, or of a function if `body` is `None`, whose numbers are constants.
+    fn rewrite_order_by(self, order_by: &mut Vec<ast::OrderByExpr>, body: Option<&ast::SetExpr>) {
+        let mut rewritten = Vec::with_capacity(order_by.len());
+        for mut order_by_expr in order_by.drain(..) {
+            let Some(nulls_first) = order_by_expr.nulls_first else {
+                rewritten.push(order_by_expr);
+                continue;
+            };
+            let key = match (&order_by_expr.expr, body) {
+                _ if nulls_first == self.nulls_first(order_by_expr.asc.unwrap_or(true)) => None,
+                (ast::Expr::Value(ast::Value::Number(position, _)), Some(body)) => {
+                    match selected_expr(body, position) {
+                        Some(expr) => Some(expr),
+                        // kept for the database to fail on
+                        None => {
+                            rewritten.push(order_by_expr);
+                            continue;
+                        }
+                    }
+                }
+                // a constant sorts nothing
+                (ast::Expr::Value(ast::Value::Number(..)), None) => None,
+                (expr, _) => Some(expr.clone()),
+            };
+            if let Some(key) = key {
+                let key = match key {
+                    key @ (ast::Expr::Identifier(_)
+                    | ast::Expr::CompoundIdentifier(_)
+                    | ast::Expr::Function(_)
+                    | ast::Expr::Nested(_)) => key,
+                    key => ast::Expr::Nested(Box::new(key)),
+                };
+                rewritten.push(ast::OrderByExpr {
+                    expr: ast::Expr::IsNull(Box::new(key)),
+                    // `true` is sorted after `false`
+                    asc: Some(!nulls_first),
+                    nulls_first: None,
+                    with_fill: None,
+                });
+            }
+            order_by_expr.nulls_first = None;
+            rewritten.push(order_by_expr);
+        }
+        *order_by = rewritten;
+    }
+
+    /// Whether the database sorts the NULLs first when a sort doesn't say.
+    fn nulls_first(self, asc: bool) -> bool {
+        match self {
+            Self::Smallest => asc,
+        }
+    }
+}
+
+/// The expression at the 1-based `position` of the SELECT list of `body`, if `body` is a `SELECT`
+/// that selects an expression there.
+fn selected_expr(body: &ast::SetExpr, position: &str) -> Option<ast::Expr> {
+    let ast::SetExpr::Select(select) = body else {
+        return None;
+    };
+    let index = position.parse::<usize>().ok()?.checked_sub(1)?;
+    match select.projection.get(index)? {
+        ast::SelectItem::UnnamedExpr(expr) | ast::SelectItem::ExprWithAlias { expr, .. } => {
+            Some(expr.clone())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::sql::sqlparser::{dialect::GenericDialect, parser::Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_rewrite_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let sql = r#"SELECT "name", rank() OVER (ORDER BY "score" DESC NULLS FIRST) FROM "users" ORDER BY "name" ASC NULLS LAST, "age" DESC NULLS LAST, "id" NULLS FIRST, "a" + "b" DESC NULLS FIRST, 1 NULLS LAST"#;
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        NullOrdering::Smallest.rewrite_statement(&mut statement);
+        assert_eq!(
+            statement.to_string(),
+            r#"SELECT "name", rank() OVER (ORDER BY "score" IS NULL DESC, "score" DESC) FROM "users" ORDER BY "name" IS NULL ASC, "name" ASC, "age" DESC, "id", ("a" + "b") IS NULL DESC, "a" + "b" DESC, "name" IS NULL ASC, 1"#
+        );
+
+        // the positions are sorted by the expressions they select
+        let sql = r#"SELECT "name", "a" + "b" AS "total" FROM "users" ORDER BY 2 DESC NULLS FIRST"#;
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        NullOrdering::Smallest.rewrite_statement(&mut statement);
+        assert_eq!(
+            statement.to_string(),
+            r#"SELECT "name", "a" + "b" AS "total" FROM "users" ORDER BY ("a" + "b") IS NULL DESC, 2 DESC"#
+        );
+        let sql = r#"SELECT "a" FROM "t" UNION SELECT "b" FROM "u" ORDER BY 1 NULLS LAST"#;
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        NullOrdering::Smallest.rewrite_statement(&mut statement);
+        assert_eq!(statement.to_string(), sql);
+
+        // the sorts without an order of the NULLs are left to the database
+        let sql = r#"SELECT "name" FROM "users" ORDER BY "name" DESC"#;
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        NullOrdering::Smallest.rewrite_statement(&mut statement);
+        assert_eq!(statement.to_string(), sql);
+        Ok(())
+    }
+}
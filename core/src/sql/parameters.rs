@@ -0,0 +1,253 @@
+use std::ops::ControlFlow;
+
+use datafusion::sql::sqlparser::{
+    ast::{self, VisitMut, VisitorMut},
+    dialect::GenericDialect,
+    tokenizer::{Token, Tokenizer},
+};
+
+/// How the placeholders of bound parameters are written in the SQL of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    /// `$1`, `$2`, ... as used by Postgres and DuckDB.
+    Dollar,
+    /// `?1`, `?2`, ... as used by SQLite.
+    NumberedQuestionMark,
+    /// `?` as used by MySQL, where the parameters are bound in the order of the placeholders.
+    QuestionMark,
+}
+
+impl PlaceholderStyle {
+    /// The placeholder style and the maximum number of parameters of a statement for the
+    /// database of a table provider, by the name of the provider.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<(Self, usize)> {
+        match name {
+            "postgres" => Some((Self::Dollar, 65_535)),
+            "duckdb" => Some((Self::Dollar, 65_535)),
+            // SQLITE_MAX_VARIABLE_NUMBER of SQLite 3.32 and later
+            "sqlite" => Some((Self::NumberedQuestionMark, 32_766)),
+            "mysql" => Some((Self::QuestionMark, 65_535)),
+            _ => None,
+        }
+    }
+
+    fn placeholder(self, index: usize) -> String {
+        match self {
+            Self::Dollar => format!("${index}"),
+            // numbered markers are replaced with `?` once the statement is rendered
+            Self::NumberedQuestionMark | Self::QuestionMark => format!("?{index}"),
+        }
+    }
+}
+
+/// A statement with its string literals replaced by placeholders, and the values to bind to them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BoundStatement {
+    pub sql: String,
+    pub params: Vec<String>,
+}
+
+/// Replaces the string literals of `statement` with placeholders, so that their values are bound
+/// as parameters instead of being quoted in the SQL.
+///
+/// Literals that are cast to another type, like `CAST('2024-01-01' AS DATE)`, stay inline so
+/// that the database doesn't infer the type of the parameter from the cast. Only the first
+/// `max_params` literals are bound when there are more.
+#[must_use]
+pub fn bind_string_literals(
+    mut statement: ast::Statement,
+    style: PlaceholderStyle,
+    max_params: usize,
+) -> BoundStatement {
+    let mut binder = LiteralBinder {
+        style,
+        max_params,
+        params: Vec::new(),
+    };
+    let _ = statement.visit(&mut binder);
+    let params = binder.params;
+    let sql = statement.to_string();
+
+    if style != PlaceholderStyle::QuestionMark || params.is_empty() {
+        return BoundStatement { sql, params };
+    }
+
+    // the AST isn't visited in the order the SQL is written, e.g. for `CASE`, so the parameters
+    // are reordered to match the markers in the rendered SQL
+    let Ok(tokens) = Tokenizer::new(&GenericDialect {}, &sql)
+        .with_unescape(false)
+        .tokenize()
+    else {
+        return inline_params(statement, params);
+    };
+    let mut ordered = Vec::with_capacity(params.len());
+    let mut rendered = String::with_capacity(sql.len());
+    for token in tokens {
+        match &token {
+            Token::Placeholder(marker) => {
+                let Some(param) = marker
+                    .strip_prefix('?')
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| params.get(index - 1))
+                else {
+                    return inline_params(statement, params);
+                };
+                ordered.push(param.clone());
+                rendered.push('?');
+            }
+            token => rendered.push_str(&token.to_string()),
+        }
+    }
+
+    BoundStatement {
+        sql: rendered,
+        params: ordered,
+    }
+}
+
+/// Puts the literals back, for SQL whose placeholders can't be ordered.
+fn inline_params(mut statement: ast::Statement, params: Vec<String>) -> BoundStatement {
+    let _ = statement.visit(&mut LiteralInliner { params });
+    BoundStatement {
+        sql: statement.to_string(),
+        params: Vec::new(),
+    }
+}
+
+struct LiteralBinder {
+    style: PlaceholderStyle,
+    max_params: usize,
+    params: Vec<String>,
+}
+
+impl VisitorMut for LiteralBinder {
+    type Break = ();
+
+    fn post_visit_expr(&mut self, expr: &mut ast::Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            ast::Expr::Value(ast::Value::SingleQuotedString(value))
+                if self.params.len() < self.max_params =>
+            {
+                self.params.push(std::mem::take(value));
+                *expr = ast::Expr::Value(ast::Value::Placeholder(
+                    self.style.placeholder(self.params.len()),
+                ));
+            }
+            // the literal of the cast was the last expression visited
+            ast::Expr::Cast { expr: inner, .. } => {
+                if let ast::Expr::Value(ast::Value::Placeholder(placeholder)) = inner.as_ref() {
+                    if *placeholder == self.style.placeholder(self.params.len()) {
+                        if let Some(value) = self.params.pop() {
+                            **inner = ast::Expr::Value(ast::Value::SingleQuotedString(value));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+struct LiteralInliner {
+    params: Vec<String>,
+}
+
+impl VisitorMut for LiteralInliner {
+    type Break = ();
+
+    fn post_visit_expr(&mut self, expr: &mut ast::Expr) -> ControlFlow<Self::Break> {
+        if let ast::Expr::Value(ast::Value::Placeholder(marker)) = expr {
+            let param = marker
+                .strip_prefix('?')
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| self.params.get(index - 1));
+            if let Some(param) = param {
+                *expr = ast::Expr::Value(ast::Value::SingleQuotedString(param.clone()));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::sql::sqlparser::parser::Parser;
+
+    use super::*;
+
+    fn bind(sql: &str, style: PlaceholderStyle, max_params: usize) -> BoundStatement {
+        let statement = Parser::parse_sql(&GenericDialect {}, sql)
+            .expect("valid SQL")
+            .remove(0);
+        bind_string_literals(statement, style, max_params)
+    }
+
+    #[test]
+    fn test_bind_string_literals() {
+        let bound = bind(
+            "SELECT * FROM t WHERE name = 'O''Brien' AND age > 30 AND note LIKE '%\"%'",
+            PlaceholderStyle::Dollar,
+            10,
+        );
+        assert_eq!(
+            bound.sql,
+            "SELECT * FROM t WHERE name = $1 AND age > 30 AND note LIKE $2"
+        );
+        assert_eq!(
+            bound.params,
+            vec!["O'Brien".to_string(), "%\"%".to_string()]
+        );
+
+        let bound = bind(
+            "SELECT * FROM t WHERE name IN ('a', 'b')",
+            PlaceholderStyle::NumberedQuestionMark,
+            10,
+        );
+        assert_eq!(bound.sql, "SELECT * FROM t WHERE name IN (?1, ?2)");
+        assert_eq!(bound.params, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_bind_string_literals_keeps_casts_inline() {
+        let bound = bind(
+            "SELECT * FROM t WHERE d > CAST('2024-01-01' AS DATE) AND name = 'a'",
+            PlaceholderStyle::Dollar,
+            10,
+        );
+        assert_eq!(
+            bound.sql,
+            "SELECT * FROM t WHERE d > CAST('2024-01-01' AS DATE) AND name = $1"
+        );
+        assert_eq!(bound.params, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn test_bind_string_literals_max_params() {
+        let bound = bind(
+            "SELECT * FROM t WHERE name IN ('a', 'b', 'c')",
+            PlaceholderStyle::Dollar,
+            2,
+        );
+        assert_eq!(bound.sql, "SELECT * FROM t WHERE name IN ($1, $2, 'c')");
+        assert_eq!(bound.params, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_bind_string_literals_question_marks_in_sql_order() {
+        let bound = bind(
+            "SELECT CASE WHEN a = 'x' THEN 'y' WHEN a = 'z' THEN 'w' END FROM t WHERE b = 'it''s ?1'",
+            PlaceholderStyle::QuestionMark,
+            3,
+        );
+        assert_eq!(
+            bound.sql,
+            "SELECT CASE WHEN a = ? THEN ? WHEN a = ? THEN 'w' END FROM t WHERE b = 'it''s ?1'"
+        );
+        assert_eq!(
+            bound.params,
+            vec!["x".to_string(), "y".to_string(), "z".to_string()]
+        );
+    }
+}
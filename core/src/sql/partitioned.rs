@@ -0,0 +1,577 @@
+//! Keeping a local copy of a table, like a table accelerated from a remote one, as one table per
+//! value of a partition column, see [`PartitionedTable`].
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    fmt,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::{Array, RecordBatch},
+        compute::{partition, sort_to_indices, take_record_batch},
+        datatypes::{Schema, SchemaRef},
+    },
+    catalog::Session,
+    common::{cast::as_boolean_array, project_schema, DFSchema, ScalarValue},
+    datasource::{TableProvider, TableType},
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::{session_state::SessionStateBuilder, SendableRecordBatchStream, TaskContext},
+    logical_expr::{
+        dml::InsertOp, expr_rewriter::unnormalize_col, utils::split_conjunction, Expr,
+        TableProviderFilterPushDown,
+    },
+    physical_expr::PhysicalExpr,
+    physical_plan::{
+        collect,
+        empty::EmptyExec,
+        insert::{DataSink, DataSinkExec},
+        metrics::MetricsSet,
+        union::UnionExec,
+        DisplayAs, DisplayFormatType, ExecutionPlan,
+    },
+};
+use futures::StreamExt;
+use snafu::prelude::*;
+use tokio::task::JoinSet;
+
+use super::fanout::{channel_source, BatchSender};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The partition column '{column}' is not a column of the table"))]
+    UnknownPartitionColumn { column: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Creates the tables of the partitions of a [`PartitionedTable`], e.g. a DuckDB table named after
+/// the partition value.
+#[async_trait]
+pub trait PartitionTableFactory: fmt::Debug + Send + Sync {
+    /// The table of the rows whose partition column is `value`, with the columns of `schema`.
+    /// The table is created if it doesn't exist, and is written to with `insert_into`.
+    async fn create_partition(
+        &self,
+        value: &ScalarValue,
+        schema: SchemaRef,
+    ) -> DataFusionResult<Arc<dyn TableProvider>>;
+}
+
+/// A table whose rows are split into one table per value of a partition column, like the date of
+/// an event, so that the scans that filter on the column only read the tables of the matching
+/// values.
+///
+/// Inserts split their batches by the value of the partition column, create the tables of the
+/// values that have none yet with the [`PartitionTableFactory`], and insert into all the tables
+/// at the same time. An overwrite only overwrites the partitions that the inserted rows belong to.
+/// Each table commits on its own, so an insert that fails for one table can leave the rows of the
+/// tables that committed before the failure.
+///
+/// Scans evaluate their filters that only use the partition column, like `day >= '2024-06-01'` or
+/// `day IN (...)`, against the value of each partition, and only scan the partitions they match,
+/// each as a partition of the scan.
+///
+/// ```rust,ignore
+/// // `DailyTables` implements `PartitionTableFactory`, and creates a table for each day
+/// let table = PartitionedTable::try_new(schema, "day", Arc::new(DailyTables::new(pool)))?
+///     .with_partitions(existing_partitions);
+/// ctx.register_table("events", Arc::new(table))?;
+/// ```
+#[derive(Debug)]
+pub struct PartitionedTable {
+    schema: SchemaRef,
+    column: String,
+    column_index: usize,
+    partitions: Arc<Partitions>,
+}
+
+impl PartitionedTable {
+    /// A table with the columns of `schema`, partitioned by `column`, with no partition yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the partition column isn't a column of `schema`.
+    pub fn try_new(
+        schema: SchemaRef,
+        column: impl Into<String>,
+        factory: Arc<dyn PartitionTableFactory>,
+    ) -> Result<Self> {
+        let column = column.into();
+        let column_index = schema
+            .index_of(&column)
+            .ok()
+            .context(UnknownPartitionColumnSnafu {
+                column: column.clone(),
+            })?;
+        Ok(Self {
+            schema,
+            column,
+            column_index,
+            partitions: Arc::new(Partitions {
+                factory,
+                tables: RwLock::new(Vec::new()),
+                creating: tokio::sync::Mutex::new(()),
+            }),
+        })
+    }
+
+    /// Adds the tables of existing partitions, by their partition value, e.g. the tables that an
+    /// earlier process created.
+    #[must_use]
+    pub fn with_partitions(self, partitions: Vec<(ScalarValue, Arc<dyn TableProvider>)>) -> Self {
+        self.partitions.write().extend(partitions);
+        self
+    }
+
+    #[must_use]
+    pub fn partition_column(&self) -> &str {
+        &self.column
+    }
+
+    /// The values of the partitions of the table.
+    #[must_use]
+    pub fn partition_values(&self) -> Vec<ScalarValue> {
+        self.partitions
+            .read()
+            .iter()
+            .map(|(value, _)| value.clone())
+            .collect()
+    }
+
+    /// The partitions whose value matches all of `filters`, or can't be told not to.
+    fn pruned_partitions(
+        &self,
+        state: &dyn Session,
+        filters: &[Expr],
+    ) -> DataFusionResult<Vec<Arc<dyn TableProvider>>> {
+        let partitions = self.partitions.read().clone();
+        let field = self.schema.field(self.column_index).clone();
+        let schema = Arc::new(Schema::new(vec![field]));
+        let df_schema = DFSchema::try_from(Arc::clone(&schema))?;
+        let predicates = filters
+            .iter()
+            .flat_map(split_conjunction)
+            .filter(|predicate| {
+                let columns = predicate.column_refs();
+                !columns.is_empty() && columns.iter().all(|column| column.name == self.column)
+            })
+            .filter_map(|predicate| {
+                let predicate = unnormalize_col(predicate.clone());
+                state.create_physical_expr(predicate, &df_schema).ok()
+            })
+            .collect::<Vec<_>>();
+        if predicates.is_empty() {
+            return Ok(partitions.into_iter().map(|(_, table)| table).collect());
+        }
+
+        let mut pruned = Vec::new();
+        for (value, table) in partitions {
+            let batch = RecordBatch::try_new(Arc::clone(&schema), vec![value.to_array()?])?;
+            if predicates
+                .iter()
+                .all(|predicate| may_match(predicate.as_ref(), &batch))
+            {
+                pruned.push(table);
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+/// Whether `predicate` doesn't rule out the single row of `batch`. A predicate that can't be
+/// evaluated doesn't rule it out.
+fn may_match(predicate: &dyn PhysicalExpr, batch: &RecordBatch) -> bool {
+    let matches = predicate
+        .evaluate(batch)
+        .and_then(|result| result.into_array(1))
+        .and_then(|result| {
+            let result = as_boolean_array(&result)?;
+            Ok(result.is_valid(0) && result.value(0))
+        });
+    matches.unwrap_or(true)
+}
+
+#[async_trait]
+impl TableProvider for PartitionedTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> DataFusionResult<Vec<TableProviderFilterPushDown>> {
+        // the filters are passed to the scans of the partitions, whose tables may not apply them
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let mut plans = Vec::new();
+        for table in self.pruned_partitions(state, filters)? {
+            plans.push(table.scan(state, projection, filters, limit).await?);
+        }
+
+        match plans.len() {
+            0 => Ok(Arc::new(EmptyExec::new(project_schema(
+                &self.schema,
+                projection,
+            )?))),
+            1 => Ok(plans.remove(0)),
+            _ => Ok(Arc::new(UnionExec::new(plans))),
+        }
+    }
+
+    async fn insert_into(
+        &self,
+        _state: &dyn Session,
+        input: Arc<dyn ExecutionPlan>,
+        op: InsertOp,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(DataSinkExec::new(
+            input,
+            Arc::new(PartitionedDataSink {
+                schema: Arc::clone(&self.schema),
+                column_index: self.column_index,
+                partitions: Arc::clone(&self.partitions),
+                op,
+            }),
+            None,
+        )) as _)
+    }
+}
+
+impl fmt::Display for PartitionedTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PartitionedTable column={} partitions={}",
+            self.column,
+            self.partitions.read().len()
+        )
+    }
+}
+
+/// The tables of the partitions, shared by a table and its inserts.
+#[derive(Debug)]
+struct Partitions {
+    factory: Arc<dyn PartitionTableFactory>,
+    tables: RwLock<Vec<(ScalarValue, Arc<dyn TableProvider>)>>,
+    /// Held while a table is created, so that concurrent inserts create it once.
+    creating: tokio::sync::Mutex<()>,
+}
+
+impl Partitions {
+    fn read(&self) -> RwLockReadGuard<'_, Vec<(ScalarValue, Arc<dyn TableProvider>)>> {
+        self.tables.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Vec<(ScalarValue, Arc<dyn TableProvider>)>> {
+        self.tables.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self, value: &ScalarValue) -> Option<Arc<dyn TableProvider>> {
+        self.read()
+            .iter()
+            .find(|(partition, _)| partition == value)
+            .map(|(_, table)| Arc::clone(table))
+    }
+
+    /// The table of the partition of `value`, which is created if there's none.
+    async fn get_or_create(
+        &self,
+        value: &ScalarValue,
+        schema: SchemaRef,
+    ) -> DataFusionResult<Arc<dyn TableProvider>> {
+        if let Some(table) = self.get(value) {
+            return Ok(table);
+        }
+        let _creating = self.creating.lock().await;
+        if let Some(table) = self.get(value) {
+            return Ok(table);
+        }
+        let table = self.factory.create_partition(value, schema).await?;
+        tracing::debug!("Created the partition {value} of a partitioned table");
+        self.write().push((value.clone(), Arc::clone(&table)));
+        Ok(table)
+    }
+}
+
+/// `batch` split by the values of its column `column_index`, with the rows of each value.
+fn split_batch(
+    batch: &RecordBatch,
+    column_index: usize,
+) -> DataFusionResult<Vec<(ScalarValue, RecordBatch)>> {
+    let indices = sort_to_indices(batch.column(column_index), None, None)?;
+    let sorted = take_record_batch(batch, &indices)?;
+    let column = sorted.column(column_index);
+    partition(&[Arc::clone(column)])?
+        .ranges()
+        .into_iter()
+        .map(|range| {
+            Ok((
+                ScalarValue::try_from_array(column, range.start)?,
+                sorted.slice(range.start, range.end - range.start),
+            ))
+        })
+        .collect()
+}
+
+/// Splits the batches of an insert by partition, into the inserts into the tables of the
+/// partitions.
+struct PartitionedDataSink {
+    schema: SchemaRef,
+    column_index: usize,
+    partitions: Arc<Partitions>,
+    op: InsertOp,
+}
+
+impl PartitionedDataSink {
+    /// Starts the insert into the partition of `value`, whose input is sent to the sender.
+    async fn start_insert(
+        &self,
+        state: &dyn Session,
+        value: &ScalarValue,
+    ) -> DataFusionResult<(BatchSender, Arc<dyn ExecutionPlan>)> {
+        let table = self
+            .partitions
+            .get_or_create(value, Arc::clone(&self.schema))
+            .await?;
+        let (sender, source) = channel_source(&self.schema)?;
+        let plan = table.insert_into(state, source, self.op).await?;
+        Ok((sender, plan))
+    }
+
+    /// Fails the inserts of `senders`, so that they abort instead of committing.
+    async fn abort(senders: HashMap<ScalarValue, BatchSender>) {
+        for sender in senders.into_values() {
+            let _ = sender
+                .send(Err(DataFusionError::Execution(
+                    "The insert into another partition failed".to_string(),
+                )))
+                .await;
+        }
+    }
+}
+
+#[async_trait]
+impl DataSink for PartitionedDataSink {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        None
+    }
+
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    async fn write_all(
+        &self,
+        mut data: SendableRecordBatchStream,
+        context: &Arc<TaskContext>,
+    ) -> DataFusionResult<u64> {
+        // the inserts into the partitions only see the configuration of the session
+        let state = SessionStateBuilder::new()
+            .with_config(context.session_config().clone())
+            .with_runtime_env(context.runtime_env())
+            .build();
+
+        let mut senders = HashMap::new();
+        // dropping the set when the insert is cancelled aborts the inserts into the partitions
+        // before their inputs end, so that they don't commit
+        let mut writes = JoinSet::new();
+        let mut num_rows = 0;
+        let mut failed = None;
+        let mut aborted_by = None;
+        'batches: while let Some(batch) = data.next().await {
+            let split = batch.and_then(|batch| {
+                num_rows += batch.num_rows() as u64;
+                split_batch(&batch, self.column_index)
+            });
+            let split = match split {
+                Ok(split) => split,
+                Err(e) => {
+                    failed = Some(e);
+                    break;
+                }
+            };
+            for (value, rows) in split {
+                if !senders.contains_key(&value) {
+                    let (sender, plan) = match self.start_insert(&state, &value).await {
+                        Ok(started) => started,
+                        Err(e) => {
+                            failed = Some(e);
+                            break 'batches;
+                        }
+                    };
+                    let context = Arc::clone(context);
+                    let partition = value.clone();
+                    writes.spawn(async move { (partition, collect(plan, context).await) });
+                    senders.insert(value.clone(), sender);
+                }
+                let sender = &senders[&value];
+                if sender.send(Ok(rows)).await.is_err() {
+                    // the insert into the partition failed and dropped its input, its error is
+                    // returned below
+                    aborted_by = Some(value);
+                    break 'batches;
+                }
+            }
+        }
+
+        if failed.is_some() || aborted_by.is_some() {
+            Self::abort(senders).await;
+        } else {
+            // the inputs end, and the partitions commit
+            drop(senders);
+        }
+
+        let mut errors = Vec::new();
+        while let Some(write) = writes.join_next().await {
+            let (value, result) = write.map_err(|e| DataFusionError::External(Box::new(e)))?;
+            if let Err(e) = result {
+                tracing::warn!("The insert into the partition {value} failed: {e}");
+                errors.push((value, e));
+            }
+        }
+        // the error of the insert that aborted the others, rather than the errors of the aborts
+        errors.sort_by_key(|(value, _)| Some(value) != aborted_by.as_ref());
+        match failed.or_else(|| errors.into_iter().next().map(|(_, e)| e)) {
+            Some(e) => Err(e),
+            None => Ok(num_rows),
+        }
+    }
+}
+
+impl fmt::Debug for PartitionedDataSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PartitionedDataSink")
+    }
+}
+
+impl DisplayAs for PartitionedDataSink {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PartitionedDataSink(column={})",
+            self.schema.field(self.column_index).name()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::{
+            array::Int64Array,
+            datatypes::{DataType, Field},
+        },
+        datasource::MemTable,
+        prelude::{col, lit, SessionContext},
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct MemPartitions;
+
+    #[async_trait]
+    impl PartitionTableFactory for MemPartitions {
+        async fn create_partition(
+            &self,
+            _value: &ScalarValue,
+            schema: SchemaRef,
+        ) -> DataFusionResult<Arc<dyn TableProvider>> {
+            Ok(Arc::new(MemTable::try_new(schema, vec![vec![]])?))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_table() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("day", DataType::Utf8, true),
+            Field::new("amount", DataType::Int64, true),
+        ]));
+        let table = Arc::new(PartitionedTable::try_new(
+            schema,
+            "day",
+            Arc::new(MemPartitions),
+        )?);
+
+        let ctx = SessionContext::new();
+        ctx.register_table("sales", Arc::clone(&table) as Arc<dyn TableProvider>)?;
+        ctx.sql("INSERT INTO sales VALUES ('2024-06-01', 1), ('2024-06-02', 2), ('2024-06-01', 3)")
+            .await?
+            .collect()
+            .await?;
+        ctx.sql("INSERT INTO sales VALUES ('2024-06-02', 4), ('2024-06-03', 5)")
+            .await?
+            .collect()
+            .await?;
+
+        let mut values = table.partition_values();
+        values.sort_by_key(ToString::to_string);
+        assert_eq!(
+            values,
+            ["2024-06-01", "2024-06-02", "2024-06-03"].map(ScalarValue::from)
+        );
+
+        let batches = ctx
+            .sql("SELECT sum(amount) FROM sales WHERE day >= '2024-06-02'")
+            .await?
+            .collect()
+            .await?;
+        let sum = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .map(|sums| sums.value(0));
+        assert_eq!(sum, Some(11));
+
+        let state = ctx.state();
+        let pruned = |filters: &[Expr]| {
+            table
+                .pruned_partitions(&state, filters)
+                .map(|partitions| partitions.len())
+        };
+        assert_eq!(pruned(&[col("day").eq(lit("2024-06-02"))])?, 1);
+        assert_eq!(
+            pruned(&[col("day").in_list(vec![lit("2024-06-01"), lit("2024-06-03")], false)])?,
+            2
+        );
+        assert_eq!(pruned(&[col("day").gt(lit("2024-07-01"))])?, 0);
+        assert_eq!(pruned(&[col("amount").gt(lit(1_i64))])?, 3);
+        assert_eq!(
+            pruned(&[col("day")
+                .eq(lit("2024-06-01"))
+                .or(col("amount").gt(lit(1_i64)))])?,
+            3
+        );
+
+        assert!(matches!(
+            PartitionedTable::try_new(table.schema(), "month", Arc::new(MemPartitions)),
+            Err(Error::UnknownPartitionColumn { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt::{self, Display, Formatter};
+
+use datafusion::sql::TableReference;
+use snafu::prelude::*;
+
+#[cfg(any(feature = "duckdb", feature = "mysql", feature = "sqlite"))]
+use {
+    super::db_connection_pool::dbconnection::{self, DbConnection},
+    futures::TryStreamExt,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Missing {privileges} privileges on the table '{table_reference}'"))]
+    MissingPrivileges {
+        table_reference: String,
+        privileges: String,
+    },
+}
+
+/// A table privilege needed by a table provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Privilege {
+    /// Needed to scan the table.
+    Select,
+    /// Needed to write to the table with a read-write table provider.
+    Insert,
+}
+
+impl Display for Privilege {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Select => write!(f, "SELECT"),
+            Self::Insert => write!(f, "INSERT"),
+        }
+    }
+}
+
+/// Whether a privilege is granted, with the error returned by the database if it isn't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionCheck {
+    pub privilege: Privilege,
+    pub granted: bool,
+    pub message: Option<String>,
+}
+
+/// The privileges the configured credentials hold on a table, as checked by the
+/// `validate_permissions` method of a table factory.
+///
+/// The checks run when they are requested, not when the table is scanned, so call
+/// [`PermissionReport::ensure_granted`] while registering tables to fail fast on credentials that
+/// can't read (or write) them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionReport {
+    pub table_reference: TableReference,
+    pub checks: Vec<PermissionCheck>,
+}
+
+impl PermissionReport {
+    #[must_use]
+    pub fn new(table_reference: TableReference) -> Self {
+        Self {
+            table_reference,
+            checks: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_check(
+        mut self,
+        privilege: Privilege,
+        granted: bool,
+        message: Option<String>,
+    ) -> Self {
+        self.checks.push(PermissionCheck {
+            privilege,
+            granted,
+            message,
+        });
+        self
+    }
+
+    /// Returns true if every checked privilege is granted.
+    #[must_use]
+    pub fn is_granted(&self) -> bool {
+        self.checks.iter().all(|check| check.granted)
+    }
+
+    /// The privileges that were checked but aren't granted.
+    #[must_use]
+    pub fn missing(&self) -> Vec<Privilege> {
+        self.checks
+            .iter()
+            .filter(|check| !check.granted)
+            .map(|check| check.privilege)
+            .collect()
+    }
+
+    /// Returns the report if every checked privilege is granted, and an error naming the missing
+    /// privileges otherwise.
+    pub fn ensure_granted(self) -> Result<Self, Error> {
+        let missing = self.missing();
+        ensure!(
+            missing.is_empty(),
+            MissingPrivilegesSnafu {
+                table_reference: self.table_reference.to_string(),
+                privileges: missing
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }
+        );
+        Ok(self)
+    }
+}
+
+/// Checks the privileges on `table` by running statements that touch no rows: a `SELECT` with a
+/// false predicate, and if `writable`, an `INSERT` of that empty result into the table itself.
+///
+/// `table` is the table as it appears in SQL, quoted for the dialect of the database.
+/// Only use this for databases without statement-level triggers, which would fire on the `INSERT`.
+#[cfg(any(feature = "duckdb", feature = "mysql", feature = "sqlite"))]
+pub(crate) async fn probe_permissions<T, P>(
+    conn: Box<dyn DbConnection<T, P>>,
+    table_reference: TableReference,
+    table: &str,
+    writable: bool,
+) -> PermissionReport {
+    let select_sql = format!("SELECT * FROM {table} WHERE 1 = 0");
+    let insert_sql = format!("INSERT INTO {table} SELECT * FROM {table} WHERE 1 = 0");
+
+    let (select, insert) = if let Some(conn) = conn.as_async() {
+        let select = conn.query_arrow(&select_sql, &[], None).await;
+        let insert = if writable {
+            Some(conn.execute(&insert_sql, &[]).await)
+        } else {
+            None
+        };
+        (select, insert)
+    } else if let Some(conn) = conn.as_sync() {
+        let select = conn.query_arrow(&select_sql, &[], None);
+        let insert = writable.then(|| conn.execute(&insert_sql, &[]));
+        (select, insert)
+    } else {
+        let error = dbconnection::Error::UnableToDowncastConnection {}.to_string();
+        return PermissionReport::new(table_reference).with_check(
+            Privilege::Select,
+            false,
+            Some(error),
+        );
+    };
+
+    // errors in the query may only surface once the stream is polled
+    let select = match select {
+        Ok(stream) => stream
+            .try_collect::<Vec<_>>()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let mut report = PermissionReport::new(table_reference).with_check(
+        Privilege::Select,
+        select.is_ok(),
+        select.err(),
+    );
+    if let Some(insert) = insert {
+        let insert = insert.map_err(|e| e.to_string()).err();
+        report = report.with_check(Privilege::Insert, insert.is_none(), insert);
+    }
+
+    tracing::debug!("Permissions on '{}': {report:?}", report.table_reference);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_granted() {
+        let report = PermissionReport::new(TableReference::bare("users"))
+            .with_check(Privilege::Select, true, None)
+            .with_check(
+                Privilege::Insert,
+                false,
+                Some("permission denied".to_string()),
+            );
+
+        assert!(!report.is_granted());
+        assert_eq!(report.missing(), vec![Privilege::Insert]);
+        assert_eq!(
+            report.ensure_granted().unwrap_err().to_string(),
+            "Missing INSERT privileges on the table 'users'"
+        );
+
+        let report = PermissionReport::new(TableReference::bare("users")).with_check(
+            Privilege::Select,
+            true,
+            None,
+        );
+        assert!(report.ensure_granted().is_ok());
+    }
+}
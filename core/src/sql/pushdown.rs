@@ -0,0 +1,742 @@
+use std::{collections::HashSet, ops::ControlFlow};
+
+use datafusion::{
+    arrow::datatypes::DataType,
+    common::{
+        tree_node::{TreeNode, TreeNodeRecursion},
+        DFSchema, ScalarValue,
+    },
+    logical_expr::{
+        expr::{InList, Like},
+        expr_rewriter::unnormalize_col,
+        Between, BinaryExpr, Expr, ExprSchemable, Operator, TableProviderFilterPushDown,
+    },
+    sql::sqlparser::ast::{self, visit_expressions_mut},
+};
+
+/// Which filters are pushed down to the remote database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FilterPushdown {
+    /// Pushes down every filter that can be written in the dialect of the database.
+    #[default]
+    All,
+    /// Evaluates all filters locally.
+    None,
+    /// Pushes down the filters whose binary operators, e.g. `=` or `~`, are all in the set.
+    /// Filters without binary operators, like `a IS NULL` or `a LIKE 'x%'`, are pushed down.
+    Operators(HashSet<Operator>),
+}
+
+/// The parts of a query a table provider pushes down to the remote database, so that expressions
+/// known to behave differently on a particular engine can be evaluated locally instead.
+///
+/// Scans of a table provider only push down projections, filters and limits. Sorts, aggregates
+/// and joins are pushed down by the federation optimizer, which federates whole sub-plans, so
+/// when any of them, the limit or some filters are disabled, the factories register the table
+/// without federation. Window functions are kept local by a rule that runs before the federation
+/// optimizer instead, so that the rest of the plan is still federated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushdownPolicy {
+    filters: FilterPushdown,
+    limit: bool,
+    sort: bool,
+    aggregates: bool,
+    joins: bool,
+    windows: bool,
+    max_in_list_size: Option<usize>,
+}
+
+impl Default for PushdownPolicy {
+    fn default() -> Self {
+        Self {
+            filters: FilterPushdown::All,
+            limit: true,
+            sort: true,
+            aggregates: true,
+            joins: true,
+            windows: true,
+            max_in_list_size: None,
+        }
+    }
+}
+
+impl PushdownPolicy {
+    /// Pushes down everything the provider supports.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_filters(mut self, filters: FilterPushdown) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    #[must_use]
+    pub fn with_limit(mut self, limit: bool) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    #[must_use]
+    pub fn with_sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    #[must_use]
+    pub fn with_aggregates(mut self, aggregates: bool) -> Self {
+        self.aggregates = aggregates;
+        self
+    }
+
+    #[must_use]
+    pub fn with_joins(mut self, joins: bool) -> Self {
+        self.joins = joins;
+        self
+    }
+
+    /// Whether window functions, like `ROW_NUMBER() OVER (...)`, are pushed down with the plans
+    /// that are federated, for the databases that support them, like PostgreSQL, DuckDB or MySQL
+    /// 8.0. Without them, the windows run locally on the rows of their federated inputs, which
+    /// needs the optimizer rules of `federation_optimizer_rules`.
+    #[must_use]
+    pub fn with_windows(mut self, windows: bool) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// Splits the `IN` lists of pushed down filters with more than `max_in_list_size` values into
+    /// lists of at most that many values, for databases that limit the size of a list. The lists
+    /// are combined with `OR`, or with `AND` for `NOT IN`.
+    #[must_use]
+    pub fn with_max_in_list_size(mut self, max_in_list_size: Option<usize>) -> Self {
+        self.max_in_list_size = max_in_list_size.filter(|&size| size > 0);
+        self
+    }
+
+    #[must_use]
+    pub fn filters(&self) -> &FilterPushdown {
+        &self.filters
+    }
+
+    #[must_use]
+    pub fn limit(&self) -> bool {
+        self.limit
+    }
+
+    #[must_use]
+    pub fn sort(&self) -> bool {
+        self.sort
+    }
+
+    #[must_use]
+    pub fn aggregates(&self) -> bool {
+        self.aggregates
+    }
+
+    #[must_use]
+    pub fn joins(&self) -> bool {
+        self.joins
+    }
+
+    #[must_use]
+    pub fn windows(&self) -> bool {
+        self.windows
+    }
+
+    #[must_use]
+    pub fn max_in_list_size(&self) -> Option<usize> {
+        self.max_in_list_size
+    }
+
+    /// Rewrites the SQL sent to the database to fit the policy, see [`Self::with_max_in_list_size`].
+    pub(crate) fn rewrite_statement(&self, statement: &mut ast::Statement) {
+        if let Some(max_in_list_size) = self.max_in_list_size {
+            chunk_in_lists(statement, max_in_list_size);
+        }
+    }
+
+    /// Whether `filter` may be pushed down.
+    #[must_use]
+    pub fn supports_filter(&self, filter: &Expr) -> bool {
+        match &self.filters {
+            FilterPushdown::All => true,
+            FilterPushdown::None => false,
+            FilterPushdown::Operators(operators) => !filter
+                .exists(|expr| {
+                    Ok(matches!(
+                        expr,
+                        Expr::BinaryExpr(BinaryExpr { op, .. }) if !operators.contains(op)
+                    ))
+                })
+                .unwrap_or(true),
+        }
+    }
+
+    /// Whether tables may be federated, which pushes down sorts, aggregates, joins and any filters.
+    #[must_use]
+    pub fn allows_federation(&self) -> bool {
+        self.filters == FilterPushdown::All
+            && self.limit
+            && self.sort
+            && self.aggregates
+            && self.joins
+    }
+}
+
+/// How the remote database compares strings in the filters it's pushed down, where it differs
+/// from DataFusion.
+///
+/// A database that matches more rows than DataFusion, like MySQL, whose default collations ignore
+/// case and trailing spaces, still filters most of the rows out, so its filters are pushed down
+/// as [`TableProviderFilterPushDown::Inexact`] and DataFusion evaluates them again on the rows it
+/// returns. The filters for which it would match fewer rows, like the negation of a filter that
+/// ignores case, aren't pushed down.
+///
+/// A database that orders strings by a collation instead of by their bytes, like Postgres with a
+/// locale other than `C`, matches different rows with `<` or `BETWEEN` on strings, which aren't
+/// pushed down then, and sorts them differently, so the sorts by strings are kept local by a rule
+/// that runs before the federation optimizer, like the windows of
+/// [`PushdownPolicy::with_windows`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterSemantics {
+    case_insensitive_like: bool,
+    case_insensitive_equality: bool,
+    collated_ordering: bool,
+    ascii_case_insensitive_ilike: bool,
+}
+
+impl FilterSemantics {
+    /// A database that evaluates every filter it can write like DataFusion does.
+    #[must_use]
+    pub fn exact() -> Self {
+        Self::default()
+    }
+
+    /// MySQL with its default collations, like `utf8mb4_general_ci`, which ignore case and
+    /// trailing spaces in `LIKE` and in comparisons, and order strings by their collation.
+    #[must_use]
+    pub fn mysql() -> Self {
+        Self {
+            case_insensitive_like: true,
+            case_insensitive_equality: true,
+            collated_ordering: true,
+            ascii_case_insensitive_ilike: false,
+        }
+    }
+
+    /// SQLite, whose `LIKE` ignores the case of ASCII characters, and writes `ILIKE` with it. For
+    /// the databases with `PRAGMA case_sensitive_like`, whose `LIKE` doesn't ignore case, use
+    /// `FilterSemantics::sqlite().with_case_insensitive_like(false)`.
+    #[must_use]
+    pub fn sqlite() -> Self {
+        Self {
+            case_insensitive_like: true,
+            case_insensitive_equality: false,
+            collated_ordering: false,
+            ascii_case_insensitive_ilike: true,
+        }
+    }
+
+    /// Whether `LIKE` ignores case, so that `NOT LIKE` isn't pushed down.
+    #[must_use]
+    pub fn with_case_insensitive_like(mut self, case_insensitive_like: bool) -> Self {
+        self.case_insensitive_like = case_insensitive_like;
+        self
+    }
+
+    /// Whether string comparisons ignore case, so that only `=`, `IN` and `ILIKE` are pushed down
+    /// on strings, and not `<>`, `NOT IN`, `NOT ILIKE` or the comparisons that depend on the order
+    /// of strings.
+    #[must_use]
+    pub fn with_case_insensitive_equality(mut self, case_insensitive_equality: bool) -> Self {
+        self.case_insensitive_equality = case_insensitive_equality;
+        self
+    }
+
+    /// Whether `ILIKE` is written as a `LIKE` that ignores the case of the ASCII characters only,
+    /// if [`Self::with_case_insensitive_like`], so that it's only pushed down with the patterns
+    /// whose letters are ASCII, and not at all otherwise.
+    #[must_use]
+    pub fn with_ascii_case_insensitive_ilike(mut self, ascii_case_insensitive_ilike: bool) -> Self {
+        self.ascii_case_insensitive_ilike = ascii_case_insensitive_ilike;
+        self
+    }
+
+    /// Whether strings are ordered by a collation instead of by their bytes, so that the
+    /// comparisons that depend on the order of strings aren't pushed down on strings, and neither
+    /// are the sorts by strings of federated queries.
+    #[must_use]
+    pub fn with_collated_ordering(mut self, collated_ordering: bool) -> Self {
+        self.collated_ordering = collated_ordering;
+        self
+    }
+
+    /// Whether strings are ordered like DataFusion does, by their bytes.
+    #[must_use]
+    pub fn orders_strings_exactly(&self) -> bool {
+        !self.case_insensitive_equality && !self.collated_ordering
+    }
+
+    /// Whether every filter is evaluated like DataFusion does.
+    #[must_use]
+    pub fn is_exact(&self) -> bool {
+        *self == Self::exact()
+    }
+
+    /// How `filter`, which the dialect of the database can write, is pushed down. The types of
+    /// its columns are looked up in `schema` by their unqualified names.
+    #[must_use]
+    pub fn pushdown(&self, filter: &Expr, schema: &DFSchema) -> TableProviderFilterPushDown {
+        if self.is_exact() {
+            return TableProviderFilterPushDown::Exact;
+        }
+        let is_string = |expr: &Expr| {
+            matches!(
+                unnormalize_col(expr.clone()).get_type(schema),
+                Ok(data_type) if is_string_type(&data_type)
+            )
+        };
+        self.classify(filter, &is_string)
+    }
+
+    fn classify(
+        &self,
+        expr: &Expr,
+        is_string: &impl Fn(&Expr) -> bool,
+    ) -> TableProviderFilterPushDown {
+        use TableProviderFilterPushDown::{Exact, Inexact, Unsupported};
+
+        // a filter that matches more rows remotely can't be negated
+        let superset = |negated: bool| if negated { Unsupported } else { Inexact };
+        let string_comparison = |left: &Expr, right: &Expr| {
+            !self.orders_strings_exactly() && (is_string(left) || is_string(right))
+        };
+        match expr {
+            Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::And | Operator::Or,
+                right,
+            }) => combine(
+                self.classify(left, is_string),
+                self.classify(right, is_string),
+            ),
+            Expr::Not(inner) => match self.classify(inner, is_string) {
+                Exact => Exact,
+                Inexact | Unsupported => Unsupported,
+            },
+            Expr::Like(Like {
+                expr,
+                pattern,
+                case_insensitive: true,
+                ..
+            }) if self.ascii_case_insensitive_ilike => {
+                if self.case_insensitive_like && folds_case_like_ascii(pattern) {
+                    self.compare([expr.as_ref()], is_string, Exact)
+                } else {
+                    Unsupported
+                }
+            }
+            Expr::Like(Like {
+                negated,
+                expr,
+                pattern,
+                case_insensitive,
+                ..
+            }) if self.like_matches_more(*case_insensitive) => self.compare(
+                [expr.as_ref(), pattern.as_ref()],
+                is_string,
+                superset(*negated),
+            ),
+            Expr::BinaryExpr(BinaryExpr { left, op, right })
+                if string_comparison(left.as_ref(), right.as_ref()) =>
+            {
+                match op {
+                    Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq => Unsupported,
+                    Operator::Eq | Operator::IsNotDistinctFrom | Operator::RegexMatch
+                        if self.case_insensitive_equality =>
+                    {
+                        self.compare([left.as_ref(), right.as_ref()], is_string, Inexact)
+                    }
+                    Operator::NotEq | Operator::IsDistinctFrom | Operator::RegexNotMatch
+                        if self.case_insensitive_equality =>
+                    {
+                        Unsupported
+                    }
+                    _ => self.classify_children(expr, is_string),
+                }
+            }
+            // written as the `REGEXP` of MySQL, which ignores case like `=`
+            Expr::ScalarFunction(call)
+                if self.case_insensitive_equality && call.name() == "regexp_like" =>
+            {
+                self.compare(&call.args, is_string, Inexact)
+            }
+            Expr::InList(InList {
+                expr: value,
+                list,
+                negated,
+            }) if self.case_insensitive_equality && is_string(value.as_ref()) => self.compare(
+                list.iter().chain([value.as_ref()]),
+                is_string,
+                superset(*negated),
+            ),
+            Expr::Between(Between { expr: value, .. })
+                if !self.orders_strings_exactly() && is_string(value.as_ref()) =>
+            {
+                Unsupported
+            }
+            _ => self.classify_children(expr, is_string),
+        }
+    }
+
+    /// Whether a `LIKE`, or an `ILIKE` if `case_insensitive`, matches more rows remotely. The
+    /// databases without `ILIKE` write it as a `LIKE` that ignores case, which ignores more than
+    /// case with the collations of [`Self::with_case_insensitive_equality`], like the accents with
+    /// `utf8mb4_general_ci`.
+    fn like_matches_more(&self, case_insensitive: bool) -> bool {
+        if case_insensitive {
+            self.case_insensitive_equality
+        } else {
+            self.case_insensitive_like
+        }
+    }
+
+    /// `pushdown` for a comparison whose operands are evaluated exactly, or `Unsupported`.
+    fn compare<'a>(
+        &self,
+        operands: impl IntoIterator<Item = &'a Expr>,
+        is_string: &impl Fn(&Expr) -> bool,
+        pushdown: TableProviderFilterPushDown,
+    ) -> TableProviderFilterPushDown {
+        let exact = operands
+            .into_iter()
+            .all(|operand| self.classify(operand, is_string) == TableProviderFilterPushDown::Exact);
+        if exact {
+            pushdown
+        } else {
+            TableProviderFilterPushDown::Unsupported
+        }
+    }
+
+    /// The rows that an expression that isn't a predicate on its children, like a `CASE`, matches
+    /// can't be bounded, so it's only pushed down if its children are evaluated exactly.
+    fn classify_children(
+        &self,
+        expr: &Expr,
+        is_string: &impl Fn(&Expr) -> bool,
+    ) -> TableProviderFilterPushDown {
+        let mut pushdown = TableProviderFilterPushDown::Exact;
+        let _ = expr.apply_children(|child| {
+            if self.classify(child, is_string) == TableProviderFilterPushDown::Exact {
+                Ok(TreeNodeRecursion::Continue)
+            } else {
+                pushdown = TableProviderFilterPushDown::Unsupported;
+                Ok(TreeNodeRecursion::Stop)
+            }
+        });
+        pushdown
+    }
+}
+
+/// The least exact of two pushdowns, for `AND` and `OR`, which match more rows remotely when
+/// either side does.
+fn combine(
+    left: TableProviderFilterPushDown,
+    right: TableProviderFilterPushDown,
+) -> TableProviderFilterPushDown {
+    use TableProviderFilterPushDown::{Exact, Inexact, Unsupported};
+    match (left, right) {
+        (Unsupported, _) | (_, Unsupported) => Unsupported,
+        (Inexact, _) | (_, Inexact) => Inexact,
+        (Exact, Exact) => Exact,
+    }
+}
+
+/// Whether `pattern` is a string whose letters are ASCII, and match the same letters whether case
+/// is ignored for the ASCII characters only or by DataFusion, which also matches `k` and `s` with
+/// the Kelvin sign and the long s.
+fn folds_case_like_ascii(pattern: &Expr) -> bool {
+    match pattern {
+        Expr::Literal(
+            ScalarValue::Utf8(Some(pattern))
+            | ScalarValue::LargeUtf8(Some(pattern))
+            | ScalarValue::Utf8View(Some(pattern)),
+        ) => pattern
+            .chars()
+            .all(|c| c.is_ascii() && !matches!(c.to_ascii_lowercase(), 'k' | 's')),
+        _ => false,
+    }
+}
+
+pub(crate) fn is_string_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => true,
+        DataType::Dictionary(_, value_type) => is_string_type(value_type),
+        _ => false,
+    }
+}
+
+fn chunk_in_lists(statement: &mut ast::Statement, max_size: usize) {
+    let _ = visit_expressions_mut(statement, |expr| {
+        if let ast::Expr::InList {
+            expr: value,
+            list,
+            negated,
+        } = expr
+        {
+            if list.len() > max_size {
+                let op = if *negated {
+                    ast::BinaryOperator::And
+                } else {
+                    ast::BinaryOperator::Or
+                };
+                let chunks = list.chunks(max_size).map(|chunk| ast::Expr::InList {
+                    expr: value.clone(),
+                    list: chunk.to_vec(),
+                    negated: *negated,
+                });
+                if let Some(chunked) = chunks.reduce(|left, right| ast::Expr::BinaryOp {
+                    left: Box::new(left),
+                    op: op.clone(),
+                    right: Box::new(right),
+                }) {
+                    *expr = ast::Expr::Nested(Box::new(chunked));
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use datafusion::{
+        error::Result as DataFusionResult,
+        logical_expr::{
+            ColumnarValue, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+        },
+        prelude::{col, lit},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_pushdown_policy_filters() {
+        let eq = col("a").eq(lit(1));
+        let like = col("b").like(lit("x%"));
+        let regex = Expr::BinaryExpr(BinaryExpr::new(
+            Box::new(col("b")),
+            Operator::RegexMatch,
+            Box::new(lit("^x")),
+        ));
+        let nested = eq.clone().and(regex.clone());
+
+        let policy = PushdownPolicy::new();
+        assert!(policy.supports_filter(&regex));
+        assert!(policy.allows_federation());
+
+        let policy = policy.with_filters(FilterPushdown::Operators(HashSet::from([
+            Operator::Eq,
+            Operator::And,
+        ])));
+        assert!(policy.supports_filter(&eq));
+        assert!(policy.supports_filter(&like));
+        assert!(!policy.supports_filter(&regex));
+        assert!(!policy.supports_filter(&nested));
+        assert!(!policy.allows_federation());
+
+        let policy = PushdownPolicy::new().with_filters(FilterPushdown::None);
+        assert!(!policy.supports_filter(&eq));
+
+        assert!(!PushdownPolicy::new().with_joins(false).allows_federation());
+        assert!(!PushdownPolicy::new()
+            .with_aggregates(false)
+            .allows_federation());
+        assert!(!PushdownPolicy::new().with_sort(false).allows_federation());
+    }
+
+    #[derive(Debug)]
+    struct RegexpLike {
+        signature: Signature,
+    }
+
+    impl ScalarUDFImpl for RegexpLike {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn name(&self) -> &str {
+            "regexp_like"
+        }
+
+        fn signature(&self) -> &Signature {
+            &self.signature
+        }
+
+        fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+            Ok(DataType::Boolean)
+        }
+
+        fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+            unimplemented!("only classified")
+        }
+    }
+
+    fn regexp_like(args: Vec<Expr>) -> Expr {
+        ScalarUDF::new_from_impl(RegexpLike {
+            signature: Signature::variadic_any(Volatility::Immutable),
+        })
+        .call(args)
+    }
+
+    #[test]
+    fn test_filter_semantics() -> Result<(), Box<dyn std::error::Error>> {
+        use datafusion::arrow::datatypes::{Field, Schema};
+        use TableProviderFilterPushDown::{Exact, Inexact, Unsupported};
+
+        let schema = DFSchema::try_from(Schema::new(vec![
+            Field::new("name", DataType::Utf8, true),
+            Field::new("age", DataType::Int32, true),
+        ]))?;
+        let name = || col("users.name");
+        let like = name().like(lit("a%"));
+        let eq = name().eq(lit("alice"));
+        let age = col("age").gt(lit(30));
+
+        let exact = FilterSemantics::exact();
+        assert_eq!(exact.pushdown(&like.clone().not(), &schema), Exact);
+
+        let sqlite = FilterSemantics::sqlite();
+        assert_eq!(sqlite.pushdown(&like, &schema), Inexact);
+        assert_eq!(
+            sqlite.pushdown(&name().not_like(lit("a%")), &schema),
+            Unsupported
+        );
+        assert_eq!(sqlite.pushdown(&like.clone().not(), &schema), Unsupported);
+        assert_eq!(sqlite.pushdown(&name().ilike(lit("a%")), &schema), Exact);
+        assert_eq!(
+            sqlite.pushdown(&name().not_ilike(lit("a%")), &schema),
+            Exact
+        );
+        assert_eq!(
+            sqlite.pushdown(&name().ilike(lit("é%")), &schema),
+            Unsupported
+        );
+        assert_eq!(
+            sqlite.pushdown(&name().ilike(lit("s%")), &schema),
+            Unsupported
+        );
+        assert_eq!(sqlite.pushdown(&name().ilike(name()), &schema), Unsupported);
+        assert_eq!(
+            sqlite
+                .with_case_insensitive_like(false)
+                .pushdown(&name().ilike(lit("a%")), &schema),
+            Unsupported
+        );
+        assert_eq!(sqlite.pushdown(&eq, &schema), Exact);
+        assert_eq!(
+            sqlite.pushdown(&age.clone().or(like.clone()), &schema),
+            Inexact
+        );
+
+        let mysql = FilterSemantics::mysql();
+        assert_eq!(mysql.pushdown(&eq, &schema), Inexact);
+        assert_eq!(
+            mysql.pushdown(&name().not_eq(lit("alice")), &schema),
+            Unsupported
+        );
+        assert_eq!(mysql.pushdown(&name().lt(lit("b")), &schema), Unsupported);
+        assert_eq!(mysql.pushdown(&name().ilike(lit("a%")), &schema), Inexact);
+        assert_eq!(
+            mysql.pushdown(&name().not_ilike(lit("a%")), &schema),
+            Unsupported
+        );
+        assert_eq!(
+            mysql.pushdown(&name().in_list(vec![lit("a"), lit("b")], false), &schema),
+            Inexact
+        );
+        assert_eq!(
+            mysql.pushdown(&name().in_list(vec![lit("a"), lit("b")], true), &schema),
+            Unsupported
+        );
+        assert_eq!(
+            mysql.pushdown(&name().between(lit("a"), lit("b")), &schema),
+            Unsupported
+        );
+        let matches = regexp_like(vec![name(), lit("^a")]);
+        assert_eq!(mysql.pushdown(&matches, &schema), Inexact);
+        assert_eq!(mysql.pushdown(&matches.clone().not(), &schema), Unsupported);
+        assert_eq!(
+            mysql.pushdown(&regexp_like(vec![name(), lit("^a"), lit("i")]), &schema),
+            Inexact
+        );
+        assert_eq!(FilterSemantics::exact().pushdown(&matches, &schema), Exact);
+        assert_eq!(mysql.pushdown(&age, &schema), Exact);
+        assert_eq!(
+            mysql.pushdown(&age.clone().and(eq.clone()), &schema),
+            Inexact
+        );
+        assert_eq!(mysql.pushdown(&eq.clone().is_true(), &schema), Unsupported);
+        assert_eq!(
+            mysql.pushdown(&col("age").not_eq(lit(30)).not(), &schema),
+            Exact
+        );
+
+        let collated = FilterSemantics::exact().with_collated_ordering(true);
+        assert_eq!(collated.pushdown(&eq, &schema), Exact);
+        assert_eq!(
+            collated.pushdown(&name().not_eq(lit("alice")), &schema),
+            Exact
+        );
+        assert_eq!(
+            collated.pushdown(&name().gt_eq(lit("b")), &schema),
+            Unsupported
+        );
+        assert_eq!(
+            collated.pushdown(&name().between(lit("a"), lit("b")), &schema),
+            Unsupported
+        );
+        assert_eq!(collated.pushdown(&age, &schema), Exact);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_in_lists() {
+        use datafusion::sql::sqlparser::{dialect::GenericDialect, parser::Parser};
+
+        let rewrite = |sql: &str, max_in_list_size| {
+            let mut statement = Parser::parse_sql(&GenericDialect {}, sql)
+                .expect("valid SQL")
+                .remove(0);
+            PushdownPolicy::new()
+                .with_max_in_list_size(max_in_list_size)
+                .rewrite_statement(&mut statement);
+            statement.to_string()
+        };
+
+        assert_eq!(
+            rewrite("SELECT * FROM t WHERE a IN (1, 2, 3, 4, 5)", Some(2)),
+            "SELECT * FROM t WHERE (a IN (1, 2) OR a IN (3, 4) OR a IN (5))"
+        );
+        assert_eq!(
+            rewrite(
+                "SELECT * FROM t WHERE a NOT IN (1, 2, 3) AND b = 1",
+                Some(2)
+            ),
+            "SELECT * FROM t WHERE (a NOT IN (1, 2) AND a NOT IN (3)) AND b = 1"
+        );
+        assert_eq!(
+            rewrite("SELECT * FROM t WHERE a IN (1, 2)", Some(2)),
+            "SELECT * FROM t WHERE a IN (1, 2)"
+        );
+        assert_eq!(
+            rewrite("SELECT * FROM t WHERE a IN (1, 2, 3)", Some(0)),
+            "SELECT * FROM t WHERE a IN (1, 2, 3)"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use super::query_context::comment_safe;
+
+type AttributesFn = dyn Fn(&str) -> Vec<(String, String)> + Send + Sync;
+
+static QUERY_ATTRIBUTION: RwLock<Option<QueryAttribution>> = RwLock::new(None);
+
+/// A comment prepended to the SQL of the queries that the providers send to their databases, like
+/// `/* app=myservice, trace_id=4bf92f35 */`, so that the load seen in `pg_stat_activity` or the
+/// query logs of a database can be attributed to the service and request that caused it.
+///
+/// The attribution is set for the whole process with [`set_query_attribution`]:
+///
+/// ```rust,ignore
+/// set_query_attribution(Some(
+///     QueryAttribution::new()
+///         .with_attribute("app", "myservice")
+///         .with_attributes_fn(|_sql| vec![("trace_id".to_string(), current_trace_id())]),
+/// ));
+/// ```
+///
+/// With the `opentelemetry` feature, [`QueryAttribution::with_traceparent`] adds the W3C
+/// `traceparent` of the span of each query, which tools that read the sqlcommenter format link to
+/// the trace of the request.
+#[derive(Clone, Default)]
+pub struct QueryAttribution {
+    attributes: Vec<(String, String)>,
+    attributes_fn: Option<Arc<AttributesFn>>,
+    format: CommentFormat,
+    #[cfg(feature = "opentelemetry")]
+    traceparent: bool,
+}
+
+/// How the attributes are written in the comment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommentFormat {
+    /// `/* app=myservice, trace_id=4bf92f35 */`, in the order the attributes were added.
+    #[default]
+    Plain,
+    /// The [sqlcommenter](https://google.github.io/sqlcommenter/spec/) format,
+    /// `/*app='myservice',traceparent='00-...-01'*/`, with URL-encoded names and values sorted by
+    /// name.
+    SqlCommenter,
+}
+
+impl fmt::Debug for QueryAttribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryAttribution")
+            .field("attributes", &self.attributes)
+            .field("attributes_fn", &self.attributes_fn.is_some())
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl QueryAttribution {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attribute with the same value for every query.
+    #[must_use]
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds the attributes that `attributes_fn` returns for the SQL of each query, like the trace
+    /// id of the request. It's called on the thread that creates the query, when its execution
+    /// plan is executed, after the attributes of [`QueryAttribution::with_attribute`].
+    #[must_use]
+    pub fn with_attributes_fn(
+        mut self,
+        attributes_fn: impl Fn(&str) -> Vec<(String, String)> + Send + Sync + 'static,
+    ) -> Self {
+        self.attributes_fn = Some(Arc::new(attributes_fn));
+        self
+    }
+
+    #[must_use]
+    pub fn with_format(mut self, format: CommentFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Adds the `traceparent` of the current span to the comment, the span of the query when the
+    /// query is created by the providers. Spans without an OpenTelemetry context, like when no
+    /// `tracing-opentelemetry` layer is installed, don't add one.
+    #[cfg(feature = "opentelemetry")]
+    #[must_use]
+    pub fn with_traceparent(mut self, traceparent: bool) -> Self {
+        self.traceparent = traceparent;
+        self
+    }
+
+    /// The comment for `sql`, or `None` without attributes.
+    #[must_use]
+    pub fn comment(&self, sql: &str) -> Option<String> {
+        let mut attributes = self.attributes.clone();
+        if let Some(attributes_fn) = &self.attributes_fn {
+            attributes.extend(attributes_fn(sql));
+        }
+        #[cfg(feature = "opentelemetry")]
+        if self.traceparent {
+            attributes
+                .extend(current_traceparent().map(|value| ("traceparent".to_string(), value)));
+        }
+        if attributes.is_empty() {
+            return None;
+        }
+        Some(match self.format {
+            CommentFormat::Plain => {
+                let attributes = attributes
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", comment_safe(name), comment_safe(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("/* {attributes} */")
+            }
+            CommentFormat::SqlCommenter => {
+                // the encoding leaves no `*`, `/` or `'` that could end the comment or the value
+                let mut attributes = attributes
+                    .iter()
+                    .map(|(name, value)| format!("{}='{}'", url_encode(name), url_encode(value)))
+                    .collect::<Vec<_>>();
+                attributes.sort();
+                format!("/*{}*/", attributes.join(","))
+            }
+        })
+    }
+
+    /// `sql` with its comment prepended.
+    #[must_use]
+    pub fn apply(&self, sql: &str) -> String {
+        match self.comment(sql) {
+            Some(comment) => format!("{comment} {sql}"),
+            None => sql.to_string(),
+        }
+    }
+}
+
+/// `value` with the characters other than the unreserved ones of RFC 3986 percent-encoded.
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// The W3C `traceparent` of the OpenTelemetry context of the current span, if it has one.
+#[cfg(feature = "opentelemetry")]
+fn current_traceparent() -> Option<String> {
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context.is_valid().then(|| {
+        format!(
+            "00-{}-{}-{:02x}",
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8()
+        )
+    })
+}
+
+/// Sets the comment prepended to the queries of all the providers, or removes it with `None`.
+pub fn set_query_attribution(attribution: Option<QueryAttribution>) {
+    *QUERY_ATTRIBUTION
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = attribution;
+}
+
+/// The comment prepended to the queries, see [`set_query_attribution`].
+#[must_use]
+pub fn query_attribution() -> Option<QueryAttribution> {
+    QUERY_ATTRIBUTION
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// `sql` with the comment of [`set_query_attribution`] prepended, if there is one.
+#[must_use]
+pub fn attribute_sql(sql: &str) -> String {
+    match query_attribution() {
+        Some(attribution) => attribution.apply(sql),
+        None => sql.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment() {
+        let attribution = QueryAttribution::new()
+            .with_attribute("app", "myservice")
+            .with_attributes_fn(|sql| vec![("sql_len".to_string(), sql.len().to_string())]);
+        assert_eq!(
+            attribution.apply("SELECT 1"),
+            "/* app=myservice, sql_len=8 */ SELECT 1"
+        );
+
+        let attribution = QueryAttribution::new().with_attribute("app", "*/ DROP TABLE t; /*");
+        assert_eq!(
+            attribution.apply("SELECT 1"),
+            "/* app=* / DROP TABLE t; / * */ SELECT 1"
+        );
+
+        assert_eq!(QueryAttribution::new().apply("SELECT 1"), "SELECT 1");
+    }
+
+    #[test]
+    fn test_sqlcommenter_comment() {
+        let attribution = QueryAttribution::new()
+            .with_format(CommentFormat::SqlCommenter)
+            .with_attribute("route", "/users/{id}")
+            .with_attribute("app", "my service */");
+        assert_eq!(
+            attribution.apply("SELECT 1"),
+            "/*app='my%20service%20%2A%2F',route='%2Fusers%2F%7Bid%7D'*/ SELECT 1"
+        );
+    }
+}
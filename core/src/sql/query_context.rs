@@ -0,0 +1,398 @@
+use std::{any::Any, cell::RefCell, collections::BTreeMap};
+
+use datafusion::{
+    common::config::{ConfigEntry, ConfigExtension, ExtensionOptions},
+    config::ConfigOptions,
+    error::Result as DataFusionResult,
+};
+
+thread_local! {
+    static CURRENT: RefCell<Option<QueryContext>> = const { RefCell::new(None) };
+}
+
+/// Values of a query like its user or tenant, which are passed to the remote database with the
+/// SQL of the query, e.g. for the row-level security policies of Postgres.
+///
+/// The context is a config extension of the session, so it's registered with
+/// `SessionConfig::with_option_extension` and its variables are set there or with SQL, like
+/// `SET query_context.app.tenant_id = '42'`. Federated queries only see the context with a
+/// [`QueryContextRule`] in the physical optimizer rules of the session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryContext {
+    variables: BTreeMap<String, String>,
+}
+
+impl QueryContext {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    /// The query context of a session, if it has one.
+    #[must_use]
+    pub fn from_config(config: &ConfigOptions) -> Option<&Self> {
+        config.extensions.get::<Self>()
+    }
+}
+
+impl ConfigExtension for QueryContext {
+    const PREFIX: &'static str = "query_context";
+}
+
+impl ExtensionOptions for QueryContext {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn cloned(&self) -> Box<dyn ExtensionOptions> {
+        Box::new(self.clone())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> DataFusionResult<()> {
+        self.variables.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn entries(&self) -> Vec<ConfigEntry> {
+        self.variables
+            .iter()
+            .map(|(name, value)| ConfigEntry {
+                key: name.clone(),
+                value: Some(value.clone()),
+                description: "A variable of the query context",
+            })
+            .collect()
+    }
+}
+
+/// How the [`QueryContext`] of a query is passed to the database of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryContextMode {
+    /// Sets these session variables on the connection before every query, to their values in the
+    /// query context or to an empty string when the context doesn't have them.
+    ///
+    /// Only Postgres supports session variables. They're set with `set_config(name, value, true)`
+    /// in a transaction that ends with the query, like `SET LOCAL`, so that a value never carries
+    /// over to the later queries, writes or other tables on the pooled connection. Policies read
+    /// them with `current_setting('app.tenant_id', true)`.
+    SessionVariables(Vec<String>),
+    /// Prepends the variables of the query context to the SQL as a comment, like
+    /// `/* app.tenant_id='42' */`, for proxies and query logs that read them.
+    Comment,
+}
+
+/// The query context of a query and how it's passed to the database, with the settings of the
+/// query that are set on the session, see [`crate::sql::query_settings::QuerySettings`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteContext {
+    mode: Option<QueryContextMode>,
+    variables: BTreeMap<String, String>,
+    settings: Vec<(String, String)>,
+}
+
+impl RemoteContext {
+    #[must_use]
+    pub fn new(mode: &QueryContextMode, context: Option<&QueryContext>) -> Self {
+        Self {
+            mode: Some(mode.clone()),
+            variables: context
+                .map(|context| context.variables.clone())
+                .unwrap_or_default(),
+            settings: Vec::new(),
+        }
+    }
+
+    /// Sets `settings` on the session before the query, after the variables of the context.
+    #[must_use]
+    pub fn with_settings(mut self, settings: Vec<(String, String)>) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// The context of the query that is being executed on this thread, see [`QueryContextRule`].
+    #[must_use]
+    pub fn current(mode: &QueryContextMode) -> Self {
+        CURRENT.with(|current| Self::new(mode, current.borrow().as_ref()))
+    }
+
+    /// The session variables to set before the query, with an empty value for the ones that the
+    /// context doesn't have, followed by the settings of the query.
+    #[must_use]
+    pub fn session_variables(&self) -> Option<Vec<(String, String)>> {
+        let mut variables = match &self.mode {
+            Some(QueryContextMode::SessionVariables(names)) => names
+                .iter()
+                .map(|name| {
+                    let value = self.variables.get(name).cloned().unwrap_or_default();
+                    (name.clone(), value)
+                })
+                .collect(),
+            _ if self.settings.is_empty() => return None,
+            _ => Vec::new(),
+        };
+        variables.extend(self.settings.iter().cloned());
+        Some(variables)
+    }
+
+    /// `sql` with the context prepended as a comment, if the context is passed as a comment.
+    #[must_use]
+    pub fn prefix_sql(&self, sql: &str) -> String {
+        if self.mode != Some(QueryContextMode::Comment) || self.variables.is_empty() {
+            return sql.to_string();
+        }
+        let variables = self
+            .variables
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{}='{}'",
+                    comment_safe(name),
+                    comment_safe(value).replace('\'', "''")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("/* {variables} */ {sql}")
+    }
+}
+
+/// Breaks up the sequences that open or close a comment, comments nest in Postgres.
+pub(crate) fn comment_safe(value: &str) -> String {
+    value.replace("*/", "* /").replace("/*", "/ *")
+}
+
+/// Runs `f` with `context` as the query context of the queries that are created on this thread.
+#[cfg(any(feature = "federation", test))]
+fn with_current<R>(context: Option<&QueryContext>, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT.with(|current| current.replace(context.cloned()));
+    let result = f();
+    CURRENT.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+#[cfg(feature = "federation")]
+mod federation {
+    use std::{any::Any, fmt, sync::Arc};
+
+    use datafusion::{
+        common::tree_node::{Transformed, TreeNode, TreeNodeRecursion},
+        config::ConfigOptions,
+        error::Result as DataFusionResult,
+        execution::TaskContext,
+        physical_optimizer::PhysicalOptimizerRule,
+        physical_plan::{
+            DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties, SendableRecordBatchStream,
+        },
+    };
+
+    use super::{with_current, QueryContext};
+
+    /// Passes the [`QueryContext`] of the session to federated queries.
+    ///
+    /// Federated scans don't get the task context of the query, so the rule wraps them in a
+    /// [`QueryContextExec`], which makes the context available while the scan creates its query:
+    ///
+    /// ```rust,ignore
+    /// let state = SessionStateBuilder::new()
+    ///     .with_config(SessionConfig::new().with_option_extension(QueryContext::new()))
+    ///     .with_query_planner(Arc::new(FederatedQueryPlanner::new()))
+    ///     .with_optimizer_rules(datafusion_federation::default_optimizer_rules())
+    ///     .with_physical_optimizer_rule(Arc::new(QueryContextRule::new()))
+    ///     .with_default_features()
+    ///     .build();
+    /// ```
+    #[derive(Debug, Default)]
+    pub struct QueryContextRule {}
+
+    impl QueryContextRule {
+        #[must_use]
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    impl PhysicalOptimizerRule for QueryContextRule {
+        fn optimize(
+            &self,
+            plan: Arc<dyn ExecutionPlan>,
+            _config: &ConfigOptions,
+        ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+            plan.transform_down(|plan| {
+                if plan.as_any().is::<QueryContextExec>() {
+                    return Ok(Transformed::new(plan, false, TreeNodeRecursion::Jump));
+                }
+                if plan.name() == "sql_federation_exec" {
+                    let plan: Arc<dyn ExecutionPlan> = Arc::new(QueryContextExec { input: plan });
+                    return Ok(Transformed::new(plan, true, TreeNodeRecursion::Jump));
+                }
+                Ok(Transformed::no(plan))
+            })
+            .map(|transformed| transformed.data)
+        }
+
+        fn name(&self) -> &str {
+            "query_context"
+        }
+
+        fn schema_check(&self) -> bool {
+            true
+        }
+    }
+
+    /// Executes a federated scan with the query context of the session.
+    #[derive(Debug)]
+    pub struct QueryContextExec {
+        input: Arc<dyn ExecutionPlan>,
+    }
+
+    impl DisplayAs for QueryContextExec {
+        fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "QueryContextExec")
+        }
+    }
+
+    impl ExecutionPlan for QueryContextExec {
+        fn name(&self) -> &'static str {
+            "QueryContextExec"
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn properties(&self) -> &PlanProperties {
+            self.input.properties()
+        }
+
+        fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+            vec![&self.input]
+        }
+
+        fn with_new_children(
+            self: Arc<Self>,
+            mut children: Vec<Arc<dyn ExecutionPlan>>,
+        ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+            Ok(Arc::new(Self {
+                input: children.swap_remove(0),
+            }))
+        }
+
+        fn execute(
+            &self,
+            partition: usize,
+            context: Arc<TaskContext>,
+        ) -> DataFusionResult<SendableRecordBatchStream> {
+            let query_context = QueryContext::from_config(context.session_config().options());
+            // the federated scan creates the future of its query before returning the stream
+            with_current(query_context, || {
+                self.input.execute(partition, Arc::clone(&context))
+            })
+        }
+    }
+}
+
+#[cfg(feature = "federation")]
+pub use federation::{QueryContextExec, QueryContextRule};
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::SessionConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_query_context_config() -> DataFusionResult<()> {
+        let mut config = SessionConfig::new().with_option_extension(QueryContext::new());
+        config
+            .options_mut()
+            .set("query_context.app.tenant_id", "42")?;
+
+        let context = QueryContext::from_config(config.options());
+        assert_eq!(
+            context,
+            Some(&QueryContext::new().with_variable("app.tenant_id", "42"))
+        );
+        assert!(QueryContext::from_config(SessionConfig::new().options()).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_variables() {
+        let mode = QueryContextMode::SessionVariables(vec![
+            "app.tenant_id".to_string(),
+            "app.user_id".to_string(),
+        ]);
+        let context = QueryContext::new()
+            .with_variable("app.tenant_id", "42")
+            .with_variable("app.other", "x");
+
+        let remote_context = RemoteContext::new(&mode, Some(&context));
+        assert_eq!(
+            remote_context.session_variables(),
+            Some(vec![
+                ("app.tenant_id".to_string(), "42".to_string()),
+                ("app.user_id".to_string(), String::new()),
+            ])
+        );
+        assert_eq!(remote_context.prefix_sql("SELECT 1"), "SELECT 1");
+
+        // variables are reset for queries without a context
+        let remote_context = RemoteContext::new(&mode, None);
+        assert_eq!(
+            remote_context.session_variables(),
+            Some(vec![
+                ("app.tenant_id".to_string(), String::new()),
+                ("app.user_id".to_string(), String::new()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_comment() {
+        let context = QueryContext::new()
+            .with_variable("app.tenant_id", "42")
+            .with_variable("app.user", "o'neil */ DROP TABLE t; /*");
+
+        let remote_context = RemoteContext::new(&QueryContextMode::Comment, Some(&context));
+        assert_eq!(remote_context.session_variables(), None);
+        assert_eq!(
+            remote_context.prefix_sql("SELECT 1"),
+            "/* app.tenant_id='42', app.user='o''neil * / DROP TABLE t; / *' */ SELECT 1"
+        );
+
+        let remote_context = RemoteContext::new(&QueryContextMode::Comment, None);
+        assert_eq!(remote_context.prefix_sql("SELECT 1"), "SELECT 1");
+
+        let settings = vec![("work_mem".to_string(), "256MB".to_string())];
+        let remote_context = RemoteContext::new(&QueryContextMode::Comment, Some(&context))
+            .with_settings(settings.clone());
+        assert_eq!(remote_context.session_variables(), Some(settings));
+
+        let remote_context = with_current(Some(&context), || {
+            RemoteContext::current(&QueryContextMode::Comment)
+        });
+        assert_eq!(
+            remote_context,
+            RemoteContext::new(&QueryContextMode::Comment, Some(&context))
+        );
+        assert_eq!(
+            RemoteContext::current(&QueryContextMode::Comment),
+            RemoteContext::new(&QueryContextMode::Comment, None)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+/// How the settings of a query are passed to a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsSyntax {
+    /// Settings of the transaction of the query, set on the connection before the query with
+    /// `set_config(name, value, true)`, like `SET LOCAL`, so that they end with the query.
+    Postgres,
+    /// `SET_VAR` optimizer hints after the `SELECT` keyword, like
+    /// `SELECT /*+ SET_VAR(sort_buffer_size = 16777216) */ ...`.
+    MySql,
+}
+
+impl SettingsSyntax {
+    /// The syntax of the database of a table provider, by the name of the provider.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<Self> {
+        match name {
+            "postgres" => Some(Self::Postgres),
+            "mysql" => Some(Self::MySql),
+            _ => None,
+        }
+    }
+}
+
+/// Settings of the remote database for the queries of a table, to tune them per query, like the
+/// memory of the sorts of large scans: `work_mem` on Postgres or `sort_buffer_size` on MySQL.
+///
+/// The settings are passed in the syntax of the database, see [`SettingsSyntax`], and ignored for
+/// the databases that don't have one. Settings with a name that isn't made of letters, digits,
+/// `_` and `.` are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuerySettings {
+    settings: BTreeMap<String, String>,
+}
+
+impl QuerySettings {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.insert(name.into(), value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> &BTreeMap<String, String> {
+        &self.settings
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+
+    /// `sql` with the settings of the syntaxes that are part of the query.
+    #[must_use]
+    pub fn apply_to_sql(&self, sql: &str, syntax: SettingsSyntax) -> String {
+        let settings = self.valid_settings().collect::<Vec<_>>();
+        if settings.is_empty() {
+            return sql.to_string();
+        }
+        match syntax {
+            SettingsSyntax::MySql => {
+                let Some(select) = sql.get(..6).filter(|s| s.eq_ignore_ascii_case("SELECT")) else {
+                    tracing::warn!("Query settings are only passed to MySQL for SELECT queries");
+                    return sql.to_string();
+                };
+                let hints = settings
+                    .iter()
+                    .map(|(name, value)| format!("SET_VAR({name} = {})", literal(value)))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{select} /*+ {hints} */{}", &sql[6..])
+            }
+            SettingsSyntax::Postgres => sql.to_string(),
+        }
+    }
+
+    /// The settings to set on the connection before the query, for the syntaxes that set them on
+    /// the session.
+    #[must_use]
+    pub fn session_settings(&self, syntax: SettingsSyntax) -> Vec<(String, String)> {
+        match syntax {
+            SettingsSyntax::Postgres => self
+                .valid_settings()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            SettingsSyntax::MySql => Vec::new(),
+        }
+    }
+
+    fn valid_settings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.settings.iter().filter_map(|(name, value)| {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+            if !valid {
+                tracing::warn!("Skipping the query setting '{name}', which isn't a valid name");
+            }
+            valid.then_some((name.as_str(), value.as_str()))
+        })
+    }
+}
+
+/// `value` as a SQL literal: numbers as they are, and the other values as strings.
+fn literal(value: &str) -> String {
+    if !value.is_empty() && value.parse::<f64>().is_ok() {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> QuerySettings {
+        QuerySettings::new()
+            .with_setting("sort_buffer_size", "16777216")
+            .with_setting("optimizer_switch", "mrr=on")
+            .with_setting("bad name; DROP TABLE t", "1")
+    }
+
+    #[test]
+    fn test_mysql_hints() {
+        assert_eq!(
+            settings().apply_to_sql("SELECT * FROM t", SettingsSyntax::MySql),
+            "SELECT /*+ SET_VAR(optimizer_switch = 'mrr=on') SET_VAR(sort_buffer_size = 16777216) */ * FROM t"
+        );
+        assert_eq!(
+            settings().apply_to_sql(
+                "WITH c AS (SELECT 1) SELECT * FROM c",
+                SettingsSyntax::MySql
+            ),
+            "WITH c AS (SELECT 1) SELECT * FROM c"
+        );
+        assert!(settings()
+            .session_settings(SettingsSyntax::MySql)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_postgres_session_settings() {
+        let settings = QuerySettings::new().with_setting("work_mem", "256MB");
+        assert_eq!(
+            settings.apply_to_sql("SELECT 1", SettingsSyntax::Postgres),
+            "SELECT 1"
+        );
+        assert_eq!(
+            settings.session_settings(SettingsSyntax::Postgres),
+            vec![("work_mem".to_string(), "256MB".to_string())]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Pushing down the regex matches of DataFusion, `~`, `~*`, `!~`, `!~*` and `regexp_like`, with
+//! the regex operators of the remote database.
+//!
+//! DataFusion unparses the operators as they are written in Postgres, and `regexp_like` as a call
+//! of a function that few databases have. The matches are rewritten to the operators of the
+//! database, and the filters with the matches that it can't write, like all of them on SQLite,
+//! whose `REGEXP` needs a `regexp()` function to be loaded, aren't pushed down, see
+//! [`SqlTable::with_regex`](super::sql_provider_datafusion::SqlTable::with_regex).
+//!
+//! ```sql
+//! -- "name" ~* '^a'
+//! regexp_matches("name", '^a', 'i')
+//! ```
+
+use std::ops::ControlFlow;
+
+use datafusion::{
+    common::ScalarValue,
+    logical_expr::{BinaryExpr, Expr, Operator},
+    sql::sqlparser::ast::{self, visit_expressions_mut, BinaryOperator},
+};
+
+use super::dialect::function;
+
+/// How a regex match is written in the SQL of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegexSyntax {
+    /// `x ~ p`, `x ~* p`, `x !~ p` and `x !~* p`.
+    Postgres,
+    /// `regexp_matches(x, p)`, with the `'i'` option to ignore case.
+    DuckDB,
+    /// `x REGEXP p` and `x NOT REGEXP p`, without the matches that ignore case, as whether
+    /// `REGEXP` ignores case depends on the collation on MySQL and on the `regexp()` function on
+    /// SQLite.
+    Regexp,
+}
+
+impl RegexSyntax {
+    /// The syntax of the database of a table provider, by the name of the provider. SQLite has
+    /// none, as it has no `regexp()` function unless one is loaded.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<Self> {
+        match name {
+            "postgres" => Some(Self::Postgres),
+            "duckdb" => Some(Self::DuckDB),
+            "mysql" => Some(Self::Regexp),
+            _ => None,
+        }
+    }
+
+    /// Whether the database can write the matches that ignore case.
+    fn ignores_case(self) -> bool {
+        self != Self::Regexp
+    }
+
+    /// Rewrites the regex matches of `statement` to the operators of the database.
+    pub(crate) fn rewrite_statement(self, statement: &mut ast::Statement) {
+        let _ = visit_expressions_mut(statement, |expr| {
+            if let Some(sql) = regex_match(expr).and_then(|regex_match| self.to_sql(regex_match)) {
+                *expr = sql;
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+
+    fn to_sql(self, regex_match: RegexMatch) -> Option<ast::Expr> {
+        let RegexMatch {
+            value,
+            pattern,
+            negated,
+            case_insensitive,
+        } = regex_match;
+        match self {
+            Self::Postgres => Some(ast::Expr::BinaryOp {
+                left: Box::new(nested(value)),
+                op: match (negated, case_insensitive) {
+                    (false, false) => BinaryOperator::PGRegexMatch,
+                    (false, true) => BinaryOperator::PGRegexIMatch,
+                    (true, false) => BinaryOperator::PGRegexNotMatch,
+                    (true, true) => BinaryOperator::PGRegexNotIMatch,
+                },
+                right: Box::new(nested(pattern)),
+            }),
+            Self::DuckDB => {
+                let mut args = vec![value, pattern];
+                if case_insensitive {
+                    args.push(ast::Expr::Value(ast::Value::SingleQuotedString(
+                        "i".to_string(),
+                    )));
+                }
+                let matches = function("regexp_matches", args);
+                Some(if negated {
+                    ast::Expr::UnaryOp {
+                        op: ast::UnaryOperator::Not,
+                        expr: Box::new(matches),
+                    }
+                } else {
+                    matches
+                })
+            }
+            Self::Regexp if case_insensitive => None,
+            Self::Regexp => Some(ast::Expr::RLike {
+                negated,
+                expr: Box::new(nested(value)),
+                pattern: Box::new(nested(pattern)),
+                regexp: true,
+            }),
+        }
+    }
+}
+
+/// Whether the database of `syntax` can write all the regex matches of `filter`.
+pub(crate) fn supports_regexes(syntax: Option<RegexSyntax>, filter: &Expr) -> bool {
+    let ignores_case = syntax.is_some_and(RegexSyntax::ignores_case);
+    !filter
+        .exists(|expr| {
+            Ok(match expr {
+                Expr::BinaryExpr(BinaryExpr {
+                    op: Operator::RegexMatch | Operator::RegexNotMatch,
+                    ..
+                }) => syntax.is_none(),
+                Expr::BinaryExpr(BinaryExpr {
+                    op: Operator::RegexIMatch | Operator::RegexNotIMatch,
+                    ..
+                }) => !ignores_case,
+                Expr::ScalarFunction(call) if call.name() == "regexp_like" => {
+                    match call.args.as_slice() {
+                        [_, _] => syntax.is_none(),
+                        [_, _, Expr::Literal(
+                            ScalarValue::Utf8(Some(flags))
+                            | ScalarValue::LargeUtf8(Some(flags))
+                            | ScalarValue::Utf8View(Some(flags)),
+                        )] if flags == "i" => !ignores_case,
+                        _ => true,
+                    }
+                }
+                _ => false,
+            })
+        })
+        .unwrap_or(true)
+}
+
+/// A regex match in the SQL unparsed by DataFusion.
+struct RegexMatch {
+    value: ast::Expr,
+    pattern: ast::Expr,
+    negated: bool,
+    case_insensitive: bool,
+}
+
+/// The regex match of `expr` if it's one of the operators or a call of `regexp_like` without
+/// flags or with the `'i'` flag.
+fn regex_match(expr: &ast::Expr) -> Option<RegexMatch> {
+    match expr {
+        ast::Expr::BinaryOp { left, op, right } => {
+            let (negated, case_insensitive) = match op {
+                BinaryOperator::PGRegexMatch => (false, false),
+                BinaryOperator::PGRegexIMatch => (false, true),
+                BinaryOperator::PGRegexNotMatch => (true, false),
+                BinaryOperator::PGRegexNotIMatch => (true, true),
+                _ => return None,
+            };
+            Some(RegexMatch {
+                value: left.as_ref().clone(),
+                pattern: right.as_ref().clone(),
+                negated,
+                case_insensitive,
+            })
+        }
+        ast::Expr::Function(function) => {
+            let [name] = function.name.0.as_slice() else {
+                return None;
+            };
+            if !name.value.eq_ignore_ascii_case("regexp_like")
+                || function.filter.is_some()
+                || function.over.is_some()
+            {
+                return None;
+            }
+            let ast::FunctionArguments::List(args) = &function.args else {
+                return None;
+            };
+            let args = args
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg)) => Some(arg),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let case_insensitive = match args.as_slice() {
+                [_, _] => false,
+                [_, _, ast::Expr::Value(ast::Value::SingleQuotedString(flags))] if flags == "i" => {
+                    true
+                }
+                _ => return None,
+            };
+            Some(RegexMatch {
+                value: args[0].clone(),
+                pattern: args[1].clone(),
+                negated: false,
+                case_insensitive,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `expr` in parentheses, unless it's an operand of an operator as it is.
+fn nested(expr: ast::Expr) -> ast::Expr {
+    match expr {
+        ast::Expr::Identifier(_)
+        | ast::Expr::CompoundIdentifier(_)
+        | ast::Expr::Value(_)
+        | ast::Expr::Function(_)
+        | ast::Expr::Nested(_) => expr,
+        expr => ast::Expr::Nested(Box::new(expr)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        logical_expr::{binary_expr, col, lit},
+        sql::sqlparser::{dialect::GenericDialect, parser::Parser},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_rewrite_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let sql = r#"SELECT "name" FROM "users" WHERE "name" ~ 'a+' AND "name" !~* 'b' OR regexp_like("email", '@x$')"#;
+        for (syntax, expected) in [
+            (
+                RegexSyntax::Postgres,
+                r#"SELECT "name" FROM "users" WHERE "name" ~ 'a+' AND "name" !~* 'b' OR "email" ~ '@x$'"#,
+            ),
+            (
+                RegexSyntax::DuckDB,
+                r#"SELECT "name" FROM "users" WHERE regexp_matches("name", 'a+') AND NOT regexp_matches("name", 'b', 'i') OR regexp_matches("email", '@x$')"#,
+            ),
+            (
+                RegexSyntax::Regexp,
+                r#"SELECT "name" FROM "users" WHERE "name" REGEXP 'a+' AND "name" !~* 'b' OR "email" REGEXP '@x$'"#,
+            ),
+        ] {
+            let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+            syntax.rewrite_statement(&mut statement);
+            assert_eq!(statement.to_string(), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_supports_regexes() {
+        let matches = binary_expr(col("name"), Operator::RegexMatch, lit("a+"));
+        let imatches = binary_expr(col("name"), Operator::RegexNotIMatch, lit("b"));
+        let filter = col("id").gt(lit(5)).and(matches.clone());
+
+        assert!(supports_regexes(Some(RegexSyntax::Postgres), &imatches));
+        assert!(supports_regexes(Some(RegexSyntax::Regexp), &filter));
+        assert!(!supports_regexes(Some(RegexSyntax::Regexp), &imatches));
+        assert!(!supports_regexes(None, &filter));
+        assert!(supports_regexes(None, &col("id").gt(lit(5))));
+    }
+}
@@ -0,0 +1,201 @@
+//! Sampling the rows of remote tables with `TABLESAMPLE`, for fast approximate queries on tables
+//! that are too large to scan while exploring them.
+//!
+//! The sample is part of the scans of the table, so the filters, aggregates and joins that are
+//! pushed down with them only read the sampled rows:
+//!
+//! ```sql
+//! SELECT count(1) FROM "events" TABLESAMPLE SYSTEM (1) REPEATABLE (42) WHERE ...
+//! ```
+
+use std::ops::ControlFlow;
+
+use datafusion::sql::{sqlparser::ast, TableReference};
+
+/// How the sample of a table is written in the SQL of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleSyntax {
+    /// `TABLESAMPLE SYSTEM (10)`, with the percentage of the rows.
+    Postgres,
+    /// `TABLESAMPLE SYSTEM (10 PERCENT)`.
+    DuckDB,
+}
+
+impl SampleSyntax {
+    /// The syntax of the database of a table provider, by the name of the provider.
+    #[must_use]
+    pub fn for_database(name: &str) -> Option<Self> {
+        match name {
+            "postgres" => Some(Self::Postgres),
+            "duckdb" => Some(Self::DuckDB),
+            _ => None,
+        }
+    }
+}
+
+/// How the sampled rows are chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SampleMethod {
+    /// Whole pages or vectors of rows, the fastest but the rows that are stored together are
+    /// sampled together.
+    #[default]
+    System,
+    /// Each row independently, which reads the whole table.
+    Bernoulli,
+}
+
+/// A sample of a fraction of the rows of a table, see the [module documentation](self).
+///
+/// The sample is ignored for the databases without a [`SampleSyntax`], whose scans read all the
+/// rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableSample {
+    fraction: f64,
+    method: SampleMethod,
+    seed: Option<u64>,
+}
+
+impl TableSample {
+    /// A sample of `fraction` of the rows, between 0 and 1.
+    #[must_use]
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction: fraction.clamp(0.0, 1.0),
+            method: SampleMethod::default(),
+            seed: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_method(mut self, method: SampleMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Samples the same rows on every scan, as long as the table doesn't change.
+    #[must_use]
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    #[must_use]
+    pub fn method(&self) -> SampleMethod {
+        self.method
+    }
+
+    #[must_use]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Samples the scans of `table_reference` in `statement`.
+    ///
+    /// The sample is set before the other rewrites of the scans, like
+    /// [`super::sql_provider_datafusion::scan_only`], rename the table.
+    pub(crate) fn rewrite_statement(
+        &self,
+        statement: &mut ast::Statement,
+        table_reference: &TableReference,
+        syntax: SampleSyntax,
+    ) {
+        struct Sample<'a> {
+            table_parts: Vec<String>,
+            sample: &'a TableSample,
+            syntax: SampleSyntax,
+        }
+
+        impl ast::VisitorMut for Sample<'_> {
+            type Break = ();
+
+            fn post_visit_table_factor(
+                &mut self,
+                table_factor: &mut ast::TableFactor,
+            ) -> ControlFlow<Self::Break> {
+                if let ast::TableFactor::Table { name, sample, .. } = table_factor {
+                    let parts = name.0.iter().map(|ident| ident.value.as_str());
+                    if parts.eq(self.table_parts.iter().map(String::as_str)) {
+                        *sample = Some(ast::TableSampleKind::AfterTableAlias(Box::new(
+                            self.sample.to_sql(self.syntax),
+                        )));
+                    }
+                }
+                ControlFlow::Continue(())
+            }
+        }
+
+        let _ = ast::VisitMut::visit(
+            statement,
+            &mut Sample {
+                table_parts: table_reference.to_vec(),
+                sample: self,
+                syntax,
+            },
+        );
+    }
+
+    fn to_sql(self, syntax: SampleSyntax) -> ast::TableSample {
+        // rounded, so that a fraction like 0.07 isn't rendered as 7.000000000000001
+        let percent = ((self.fraction * 100.0 * 1e6).round() / 1e6).to_string();
+        ast::TableSample {
+            modifier: ast::TableSampleModifier::TableSample,
+            name: Some(match self.method {
+                SampleMethod::System => ast::TableSampleMethod::System,
+                SampleMethod::Bernoulli => ast::TableSampleMethod::Bernoulli,
+            }),
+            quantity: Some(ast::TableSampleQuantity {
+                parenthesized: true,
+                value: ast::Expr::Value(ast::Value::Number(percent, false)),
+                unit: match syntax {
+                    SampleSyntax::Postgres => None,
+                    SampleSyntax::DuckDB => Some(ast::TableSampleUnit::Percent),
+                },
+            }),
+            seed: self.seed.map(|seed| ast::TableSampleSeed {
+                modifier: ast::TableSampleSeedModifier::Repeatable,
+                value: ast::Value::Number(seed.to_string(), false),
+            }),
+            bucket: None,
+            offset: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::sql::sqlparser::{dialect::GenericDialect, parser::Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_rewrite_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let sql = r#"SELECT "events"."id" FROM "events" AS "e" JOIN "users" ON "e"."id" = "users"."id" WHERE "e"."id" > 5"#;
+        let table_reference = TableReference::bare("events");
+        let sample = TableSample::new(0.1)
+            .with_method(SampleMethod::Bernoulli)
+            .with_seed(Some(42));
+
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        sample.rewrite_statement(&mut statement, &table_reference, SampleSyntax::Postgres);
+        assert_eq!(
+            statement.to_string(),
+            r#"SELECT "events"."id" FROM "events" AS "e" TABLESAMPLE BERNOULLI (10) REPEATABLE (42) JOIN "users" ON "e"."id" = "users"."id" WHERE "e"."id" > 5"#
+        );
+
+        let mut statement = Parser::parse_sql(&GenericDialect {}, sql)?.remove(0);
+        TableSample::new(0.025).rewrite_statement(
+            &mut statement,
+            &table_reference,
+            SampleSyntax::DuckDB,
+        );
+        assert!(statement
+            .to_string()
+            .contains(r#"FROM "events" AS "e" TABLESAMPLE SYSTEM (2.5 PERCENT) JOIN"#));
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use datafusion::arrow::{
+    array::{ArrayRef, RecordBatch},
+    compute::cast,
+    datatypes::{DataType, Schema, SchemaRef},
+    error::ArrowError,
+};
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "The schema of the remote table changed during the scan: {changes}. Register the table again to pick up the new schema"
+    ))]
+    SchemaDrift { changes: String },
+
+    #[snafu(display(
+        "Unable to cast column '{column}' from {from} to {to} after the schema of the remote table changed: {source}"
+    ))]
+    UnableToCastDriftedColumn {
+        column: String,
+        from: DataType,
+        to: DataType,
+        source: ArrowError,
+    },
+
+    #[snafu(display("Unable to build the batch with the cast columns: {source}"))]
+    UnableToBuildCastBatch { source: ArrowError },
+
+    #[snafu(display(
+        "Invalid schema_drift value '{value}', expected 'ignore', 'fail', 'cast' or 'restart'"
+    ))]
+    InvalidSchemaDriftPolicy { value: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What a scan does when the remote database returns batches whose columns no longer match the
+/// schema the table was registered with, e.g. after an `ALTER TABLE` or a view was replaced.
+///
+/// Result columns are matched with the expected schema by position, because the scans select the
+/// columns in order and databases may report different names for them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaDriftPolicy {
+    /// Returns the batches as the database sends them. Operators that expect the registered
+    /// schema may then fail with unrelated errors.
+    #[default]
+    Ignore,
+    /// Fails the scan with [`Error::SchemaDrift`], which names the changed columns.
+    Fail,
+    /// Casts the changed columns to the registered types, failing the scan if a value can't be cast
+    /// or the number of columns changed.
+    Cast,
+    /// Runs the query again if the first batch has drifted, for schema changes that are still in
+    /// progress, like a DuckDB table being overwritten. Fails like [`Self::Fail`] if the schema
+    /// still doesn't match, or if it changes after rows were returned.
+    Restart,
+}
+
+impl TryFrom<&str> for SchemaDriftPolicy {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "ignore" => Ok(Self::Ignore),
+            "fail" => Ok(Self::Fail),
+            "cast" => Ok(Self::Cast),
+            "restart" => Ok(Self::Restart),
+            _ => InvalidSchemaDriftPolicySnafu { value }.fail(),
+        }
+    }
+}
+
+/// Describes how the columns of `batch` differ from `expected`, or returns `None` if they match.
+///
+/// Nullability and names are not compared. Strings are not reported for columns that are only
+/// dictionary-encoded locally, see [`crate::util::dictionary::encode_dictionaries`].
+#[must_use]
+pub fn schema_changes(expected: &SchemaRef, batch: &RecordBatch) -> Option<String> {
+    let found = batch.schema();
+    if expected.fields().len() != found.fields().len() {
+        return Some(format!(
+            "expected {} columns, found {}",
+            expected.fields().len(),
+            found.fields().len()
+        ));
+    }
+
+    let changes = expected
+        .fields()
+        .iter()
+        .zip(found.fields())
+        .filter(|(expected, found)| !is_compatible(expected.data_type(), found.data_type()))
+        .map(|(expected, found)| {
+            format!(
+                "column '{}' changed from {} to {}",
+                expected.name(),
+                expected.data_type(),
+                found.data_type()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    (!changes.is_empty()).then(|| changes.join(", "))
+}
+
+fn is_compatible(expected: &DataType, found: &DataType) -> bool {
+    match expected {
+        DataType::Dictionary(_, value_type) if value_type.as_ref() == found => true,
+        _ => expected.equals_datatype(found),
+    }
+}
+
+/// Applies `policy` to a batch of a scan. [`SchemaDriftPolicy::Restart`] is handled like
+/// [`SchemaDriftPolicy::Fail`] here, the scan decides whether it can restart instead.
+pub fn adapt_batch(
+    batch: RecordBatch,
+    expected: &SchemaRef,
+    policy: SchemaDriftPolicy,
+) -> Result<RecordBatch> {
+    if policy == SchemaDriftPolicy::Ignore {
+        return Ok(batch);
+    }
+    let Some(changes) = schema_changes(expected, &batch) else {
+        return Ok(batch);
+    };
+
+    if policy != SchemaDriftPolicy::Cast || expected.fields().len() != batch.num_columns() {
+        return SchemaDriftSnafu { changes }.fail();
+    }
+
+    tracing::debug!("Casting batch to the registered schema: {changes}");
+    let columns = expected
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            if is_compatible(field.data_type(), column.data_type()) {
+                return Ok(Arc::clone(column));
+            }
+            cast(column, field.data_type()).context(UnableToCastDriftedColumnSnafu {
+                column: field.name(),
+                from: column.data_type().clone(),
+                to: field.data_type().clone(),
+            })
+        })
+        .collect::<Result<Vec<ArrayRef>>>()?;
+
+    // keeps the names the database returned, like batches that haven't drifted
+    let schema = Arc::new(Schema::new_with_metadata(
+        batch
+            .schema()
+            .fields()
+            .iter()
+            .zip(&columns)
+            .map(|(field, column)| {
+                Arc::new(
+                    field
+                        .as_ref()
+                        .clone()
+                        .with_data_type(column.data_type().clone()),
+                )
+            })
+            .collect::<Vec<_>>(),
+        batch.schema().metadata().clone(),
+    ));
+    RecordBatch::try_new(schema, columns).context(UnableToBuildCastBatchSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::{
+        array::{Array, Int32Array, Int64Array, StringArray},
+        datatypes::Field,
+    };
+
+    use super::*;
+
+    fn expected_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]))
+    }
+
+    fn drifted_batch() -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("name", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["a", "b"])),
+            ],
+        )
+        .expect("record batch created")
+    }
+
+    #[test]
+    fn test_adapt_batch() {
+        let expected = expected_schema();
+
+        let error = adapt_batch(drifted_batch(), &expected, SchemaDriftPolicy::Fail)
+            .expect_err("schema drift");
+        assert_eq!(
+            error.to_string(),
+            "The schema of the remote table changed during the scan: column 'id' changed from Int64 to Int32. Register the table again to pick up the new schema"
+        );
+
+        let batch =
+            adapt_batch(drifted_batch(), &expected, SchemaDriftPolicy::Cast).expect("batch cast");
+        assert!(schema_changes(&expected, &batch).is_none());
+        assert_eq!(
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .expect("cast column"),
+            &Int64Array::from(vec![1, 2])
+        );
+
+        let batch = adapt_batch(drifted_batch(), &expected, SchemaDriftPolicy::Ignore)
+            .expect("batch returned");
+        assert_eq!(batch.column(0).data_type(), &DataType::Int32);
+    }
+
+    #[test]
+    fn test_schema_changes_column_count() {
+        let batch = drifted_batch().project(&[0]).expect("batch projected");
+        assert_eq!(
+            schema_changes(&expected_schema(), &batch).as_deref(),
+            Some("expected 2 columns, found 1")
+        );
+        assert!(adapt_batch(batch, &expected_schema(), SchemaDriftPolicy::Cast).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Plans queries without executing them, to get the SQL their scans would send to the remote
+//! databases. This tests what a query pushes down, federated or not, without running anything on
+//! the databases.
+//!
+//! ```rust,ignore
+//! let sql = dry_run(&ctx.state(), "SELECT name FROM users WHERE id > 5 LIMIT 10").await?;
+//! assert_eq!(sql, vec![r#"SELECT "name" FROM "users" WHERE ("id" > 5) LIMIT 10"#]);
+//! ```
+
+use std::sync::Arc;
+
+use datafusion::{
+    error::Result as DataFusionResult,
+    execution::context::SessionState,
+    physical_plan::{displayable, ExecutionPlan},
+};
+
+/// Plans `sql` and returns the SQL that its scans would run on the remote databases, in the
+/// order of the scans in the physical plan.
+///
+/// # Errors
+///
+/// Returns an error if `sql` can't be planned.
+pub async fn dry_run(state: &SessionState, sql: &str) -> DataFusionResult<Vec<String>> {
+    let logical_plan = state.create_logical_plan(sql).await?;
+    let plan = state.create_physical_plan(&logical_plan).await?;
+    Ok(remote_sql(&plan))
+}
+
+/// The SQL that the scans of `plan` run on the remote databases, without executing it.
+///
+/// Federated scans return the SQL rewritten for the remote database. Scans that bind literals as
+/// parameters return the SQL with its placeholders.
+#[must_use]
+pub fn remote_sql(plan: &Arc<dyn ExecutionPlan>) -> Vec<String> {
+    let mut sql = Vec::new();
+    collect_remote_sql(plan, &mut sql);
+    sql
+}
+
+fn collect_remote_sql(plan: &Arc<dyn ExecutionPlan>, sql: &mut Vec<String>) {
+    if let Some(scan_sql) = scan_sql(plan.as_ref()) {
+        sql.push(scan_sql);
+        return;
+    }
+    for child in plan.children() {
+        collect_remote_sql(child, sql);
+    }
+}
+
+fn scan_sql(plan: &dyn ExecutionPlan) -> Option<String> {
+    if plan.name() == "sql_federation_exec" {
+        return federated_sql(plan);
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(exec) = plan.as_any().downcast_ref::<super::SqlExec<
+        bb8::PooledConnection<
+            'static,
+            bb8_postgres::PostgresConnectionManager<postgres_native_tls::MakeTlsConnector>,
+        >,
+        &'static (dyn bb8_postgres::tokio_postgres::types::ToSql + Sync),
+    >>() {
+        return exec.sql().ok();
+    }
+    #[cfg(feature = "mysql")]
+    if let Some(sql) = crate::mysql::sql_table::MySQLTable::scan_sql(plan) {
+        return Some(sql);
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(sql) = crate::sqlite::sql_table::SQLiteTable::<
+        tokio_rusqlite::Connection,
+        &'static (dyn rusqlite::ToSql + Sync),
+    >::scan_sql(plan)
+    {
+        return Some(sql);
+    }
+    #[cfg(feature = "duckdb")]
+    if let Some(sql) = crate::duckdb::DuckDBTable::<
+        r2d2::PooledConnection<duckdb::DuckdbConnectionManager>,
+        Box<dyn crate::sql::db_connection_pool::dbconnection::duckdbconn::DuckDBSyncParameter>,
+    >::scan_sql(plan)
+    {
+        return Some(sql);
+    }
+    None
+}
+
+/// The rewritten SQL of a federated scan.
+///
+/// The federated scan isn't public, so the SQL is taken from its display, which shows the
+/// rewritten SQL between two copies of the unparsed SQL.
+fn federated_sql(plan: &dyn ExecutionPlan) -> Option<String> {
+    const REWRITTEN: &str = " rewritten_sql=";
+    const UNPARSED: &str = " sql=";
+
+    let display = displayable(plan).one_line().to_string();
+    let end = display.find(REWRITTEN)?;
+    let start = display[..end].rfind(UNPARSED)? + UNPARSED.len();
+    let unparsed_sql = &display[start..end];
+    display[end + REWRITTEN.len()..]
+        .trim_end()
+        .strip_suffix(&format!("{UNPARSED}{unparsed_sql}"))
+        .map(str::to_string)
+}
+
+#[cfg(all(test, feature = "duckdb"))]
+mod tests {
+    use super::*;
+    use crate::duckdb::DuckDBTable;
+    use crate::sql::db_connection_pool::{
+        dbconnection::duckdbconn::DuckDBSyncParameter, duckdbpool::DuckDbConnectionPool,
+        DbConnectionPool,
+    };
+    use datafusion::{
+        arrow::datatypes::{DataType, Field, Schema},
+        prelude::SessionContext,
+    };
+    use duckdb::DuckdbConnectionManager;
+
+    type DuckDbPool = Arc<
+        dyn DbConnectionPool<
+                r2d2::PooledConnection<DuckdbConnectionManager>,
+                Box<dyn DuckDBSyncParameter>,
+            > + Send
+            + Sync,
+    >;
+
+    fn table(
+    ) -> DuckDBTable<r2d2::PooledConnection<DuckdbConnectionManager>, Box<dyn DuckDBSyncParameter>>
+    {
+        // the table doesn't exist, the queries are only planned
+        let pool: DuckDbPool =
+            Arc::new(DuckDbConnectionPool::new_memory().expect("memory pool created"));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        DuckDBTable::new_with_schema(&pool, schema, "users", None, None)
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let ctx = SessionContext::new();
+        ctx.register_table("users", Arc::new(table()))?;
+
+        let sql = dry_run(&ctx.state(), "SELECT name FROM users WHERE id > 5 LIMIT 10").await?;
+        assert_eq!(sql.len(), 1);
+        assert!(sql[0].contains(r#"SELECT "name" FROM "users""#), "{sql:?}");
+        assert!(sql[0].contains(r#"("id" > 5)"#), "{sql:?}");
+        assert!(sql[0].contains("LIMIT 10"), "{sql:?}");
+        Ok(())
+    }
+
+    #[cfg(feature = "duckdb-federation")]
+    #[tokio::test]
+    async fn test_dry_run_federated() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        use datafusion::execution::SessionStateBuilder;
+
+        let provider = Arc::new(table()).create_federated_table_provider()?;
+        let state = SessionStateBuilder::new()
+            .with_optimizer_rules(datafusion_federation::default_optimizer_rules())
+            .with_query_planner(Arc::new(datafusion_federation::FederatedQueryPlanner::new()))
+            .with_default_features()
+            .build();
+        let ctx = SessionContext::new_with_state(state);
+        ctx.register_table("users", Arc::new(provider))?;
+
+        let sql = dry_run(
+            &ctx.state(),
+            "SELECT name, count(*) FROM users WHERE id > 5 GROUP BY name",
+        )
+        .await?;
+        assert_eq!(sql.len(), 1);
+        assert!(sql[0].contains("GROUP BY"), "{sql:?}");
+        assert!(sql[0].contains("users"), "{sql:?}");
+        Ok(())
+    }
+}
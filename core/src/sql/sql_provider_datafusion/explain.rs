@@ -0,0 +1,638 @@
+//! Plans of the remote database for the SQL of scans, shown in the `EXPLAIN` output of DataFusion.
+//!
+//! The remote plan is only retrieved when a plan is displayed, so queries that aren't explained
+//! never run the `EXPLAIN` on the remote database.
+
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use datafusion::{
+    arrow::util::display::{ArrayFormatter, FormatOptions},
+    error::Result as DataFusionResult,
+};
+use futures::{future::LocalBoxFuture, TryStreamExt};
+
+use crate::sql::db_connection_pool::{
+    dbconnection::query_arrow, runtime::block_on_runtime, DbConnectionPool,
+};
+
+use super::to_execution_error;
+
+/// Retrieves the plan of the remote database for a SQL statement.
+pub trait RemoteExplainer: Send + Sync {
+    fn explain(&self, sql: &str) -> LocalBoxFuture<'static, DataFusionResult<String>>;
+}
+
+/// Explains statements with a connection of a [`DbConnectionPool`].
+pub(crate) struct PoolExplainer<T, P> {
+    pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+    statement: &'static str,
+    prefix: String,
+}
+
+impl<T, P> PoolExplainer<T, P> {
+    /// `statement` is the `EXPLAIN` statement of the database, and `prefix` is prepended to the
+    /// explained SQL, like the CTEs of the table functions of DuckDB.
+    pub(crate) fn new(
+        pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+        statement: &'static str,
+        prefix: String,
+    ) -> Self {
+        Self {
+            pool,
+            statement,
+            prefix,
+        }
+    }
+}
+
+impl<T: 'static, P: 'static> RemoteExplainer for PoolExplainer<T, P> {
+    fn explain(&self, sql: &str) -> LocalBoxFuture<'static, DataFusionResult<String>> {
+        let pool = Arc::clone(&self.pool);
+        let sql = format!("{} {}{sql}", self.statement, self.prefix);
+        Box::pin(async move {
+            let conn = pool.connect().await.map_err(to_execution_error)?;
+            let batches = query_arrow(conn, sql, None)
+                .await
+                .map_err(to_execution_error)?
+                .try_collect::<Vec<_>>()
+                .await?;
+            format_explain(&batches)
+        })
+    }
+}
+
+/// Formats the rows of an `EXPLAIN` statement as one line, with the columns of a row separated by
+/// spaces and the rows separated by semicolons, so the plan stays on the line of its scan.
+fn format_explain(batches: &[datafusion::arrow::array::RecordBatch]) -> DataFusionResult<String> {
+    let options = FormatOptions::default();
+    let mut lines = Vec::new();
+    for batch in batches {
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|column| ArrayFormatter::try_new(column.as_ref(), &options))
+            .collect::<Result<Vec<_>, _>>()?;
+        for row in 0..batch.num_rows() {
+            let line = columns
+                .iter()
+                .map(|column| column.value(row).to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.extend(
+                line.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+    Ok(lines.join("; "))
+}
+
+/// The remote plan of the SQL of a scan, retrieved the first time it's displayed.
+#[derive(Clone)]
+pub struct RemotePlan {
+    explainer: Arc<dyn RemoteExplainer>,
+    sql: String,
+    plan: Arc<OnceLock<String>>,
+}
+
+impl RemotePlan {
+    pub fn new(explainer: Arc<dyn RemoteExplainer>, sql: impl Into<String>) -> Self {
+        Self {
+            explainer,
+            sql: sql.into(),
+            plan: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// The plan of the remote database, or the reason it's unavailable.
+    ///
+    /// A plan that can't be retrieved doesn't fail the query. The plan is also unavailable on a
+    /// current-thread runtime, which can't be blocked on while the plan is displayed.
+    pub fn plan(&self) -> &str {
+        self.plan
+            .get_or_init(|| explain_blocking(self.explainer.as_ref(), &self.sql))
+    }
+}
+
+impl fmt::Debug for RemotePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemotePlan")
+            .field("sql", &self.sql)
+            .field("plan", &self.plan.get())
+            .finish()
+    }
+}
+
+impl fmt::Display for RemotePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "remote_plan=[{}]", self.plan())
+    }
+}
+
+fn explain_blocking(explainer: &dyn RemoteExplainer, sql: &str) -> String {
+    match block_on_runtime(|| explainer.explain(sql)) {
+        Some(Ok(plan)) => plan,
+        Some(Err(e)) => {
+            tracing::warn!("Unable to explain '{sql}' on the remote database: {e}");
+            format!("unavailable: {e}")
+        }
+        None => "unavailable: not supported on a current-thread runtime".to_string(),
+    }
+}
+
+#[cfg(feature = "federation")]
+mod federation {
+    use std::{any::Any, fmt, sync::Arc};
+
+    use async_trait::async_trait;
+    use datafusion::{
+        common::tree_node::{TreeNode, TreeNodeRecursion},
+        datasource::source_as_provider,
+        error::Result as DataFusionResult,
+        execution::{
+            context::{QueryPlanner, SessionState},
+            SendableRecordBatchStream, TaskContext,
+        },
+        logical_expr::{LogicalPlan, UserDefinedLogicalNode},
+        physical_plan::{displayable, DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties},
+        physical_planner::{DefaultPhysicalPlanner, ExtensionPlanner, PhysicalPlanner},
+        sql::unparser::plan_to_sql,
+    };
+    use datafusion_federation::{
+        FederatedPlanNode, FederatedPlanner, FederatedTableProviderAdaptor,
+    };
+
+    use super::{RemoteExplainer, RemotePlan};
+
+    /// A [`QueryPlanner`] for sessions that federate with `datafusion-federation`, which shows
+    /// the remote plan of federated queries in the `EXPLAIN` output, next to their SQL.
+    ///
+    /// The remote plan of a federated query is shown for the first table of the query that has
+    /// remote explains enabled, and is only retrieved when the plan is displayed.
+    ///
+    /// ```rust,ignore
+    /// let state = SessionStateBuilder::new()
+    ///     .with_optimizer_rules(datafusion_federation::default_optimizer_rules())
+    ///     .with_query_planner(Arc::new(RemoteExplainQueryPlanner::new()))
+    ///     .with_default_features()
+    ///     .build();
+    /// ```
+    #[derive(Debug, Default)]
+    pub struct RemoteExplainQueryPlanner {}
+
+    impl RemoteExplainQueryPlanner {
+        #[must_use]
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    #[async_trait]
+    impl QueryPlanner for RemoteExplainQueryPlanner {
+        async fn create_physical_plan(
+            &self,
+            logical_plan: &LogicalPlan,
+            session_state: &SessionState,
+        ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+            let physical_planner = DefaultPhysicalPlanner::with_extension_planners(vec![Arc::new(
+                RemoteExplainPlanner::default(),
+            )]);
+            physical_planner
+                .create_physical_plan(logical_plan, session_state)
+                .await
+        }
+    }
+
+    #[derive(Default)]
+    struct RemoteExplainPlanner {
+        federated: FederatedPlanner,
+    }
+
+    #[async_trait]
+    impl ExtensionPlanner for RemoteExplainPlanner {
+        async fn plan_extension(
+            &self,
+            planner: &dyn PhysicalPlanner,
+            node: &dyn UserDefinedLogicalNode,
+            logical_inputs: &[&LogicalPlan],
+            physical_inputs: &[Arc<dyn ExecutionPlan>],
+            session_state: &SessionState,
+        ) -> DataFusionResult<Option<Arc<dyn ExecutionPlan>>> {
+            let Some(plan) = self
+                .federated
+                .plan_extension(
+                    planner,
+                    node,
+                    logical_inputs,
+                    physical_inputs,
+                    session_state,
+                )
+                .await?
+            else {
+                return Ok(None);
+            };
+            let Some(fed_node) = node.as_any().downcast_ref::<FederatedPlanNode>() else {
+                return Ok(Some(plan));
+            };
+            let Some(explainer) = find_explainer(fed_node.plan()) else {
+                return Ok(Some(plan));
+            };
+            let Ok(sql) = plan_to_sql(fed_node.plan()) else {
+                return Ok(Some(plan));
+            };
+
+            Ok(Some(Arc::new(RemoteExplainExec::new(
+                plan,
+                explainer,
+                sql.to_string(),
+            ))))
+        }
+    }
+
+    /// The explainer of the first table scanned by `plan` that has remote explains enabled.
+    fn find_explainer(plan: &LogicalPlan) -> Option<Arc<dyn RemoteExplainer>> {
+        let mut explainer = None;
+        let _ = plan.apply(|node| {
+            if let LogicalPlan::TableScan(scan) = node {
+                explainer = source_as_provider(&scan.source).ok().and_then(|provider| {
+                    let adaptor = provider
+                        .as_any()
+                        .downcast_ref::<FederatedTableProviderAdaptor>()?;
+                    remote_explainer(adaptor.table_provider.as_ref()?.as_any())
+                });
+            }
+            Ok(if explainer.is_some() {
+                TreeNodeRecursion::Stop
+            } else {
+                TreeNodeRecursion::Continue
+            })
+        });
+        explainer
+    }
+
+    fn remote_explainer(provider: &dyn Any) -> Option<Arc<dyn RemoteExplainer>> {
+        #[cfg(feature = "postgres")]
+        if let Some(table) = provider.downcast_ref::<crate::sql::sql_provider_datafusion::SqlTable<
+            bb8::PooledConnection<
+                'static,
+                bb8_postgres::PostgresConnectionManager<postgres_native_tls::MakeTlsConnector>,
+            >,
+            &'static (dyn bb8_postgres::tokio_postgres::types::ToSql + Sync),
+        >>() {
+            return table.remote_explainer();
+        }
+        #[cfg(feature = "mysql")]
+        if let Some(table) = provider.downcast_ref::<crate::mysql::sql_table::MySQLTable>() {
+            return table.remote_explainer();
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(table) = provider.downcast_ref::<crate::sqlite::sql_table::SQLiteTable<
+            tokio_rusqlite::Connection,
+            &'static (dyn rusqlite::ToSql + Sync),
+        >>() {
+            return table.remote_explainer();
+        }
+        #[cfg(feature = "duckdb")]
+        if let Some(table) = provider.downcast_ref::<crate::duckdb::DuckDBTable<
+            r2d2::PooledConnection<duckdb::DuckdbConnectionManager>,
+            Box<dyn crate::sql::db_connection_pool::dbconnection::duckdbconn::DuckDBSyncParameter>,
+        >>() {
+            return table.remote_explainer();
+        }
+        let _ = provider;
+        None
+    }
+
+    /// Shows the remote plan of the federated query of its input, which it executes unchanged.
+    pub struct RemoteExplainExec {
+        input: Arc<dyn ExecutionPlan>,
+        explainer: Arc<dyn RemoteExplainer>,
+        unparsed_sql: String,
+        remote_plan: std::sync::OnceLock<RemotePlan>,
+    }
+
+    impl RemoteExplainExec {
+        /// `unparsed_sql` is the SQL of the federated plan before it's rewritten for the remote
+        /// database, which the federated scan shows next to the rewritten SQL.
+        fn new(
+            input: Arc<dyn ExecutionPlan>,
+            explainer: Arc<dyn RemoteExplainer>,
+            unparsed_sql: String,
+        ) -> Self {
+            Self {
+                input,
+                explainer,
+                unparsed_sql,
+                remote_plan: std::sync::OnceLock::new(),
+            }
+        }
+
+        /// The remote plan of the SQL that the federated scan runs on the remote database.
+        ///
+        /// The federated scan isn't public, so the SQL is taken from its display, which shows
+        /// the rewritten SQL between two copies of the unparsed SQL.
+        fn remote_plan(&self) -> Option<&RemotePlan> {
+            if let Some(remote_plan) = self.remote_plan.get() {
+                return Some(remote_plan);
+            }
+            let sql = federated_sql(&self.input, &self.unparsed_sql)?;
+            Some(
+                self.remote_plan
+                    .get_or_init(|| RemotePlan::new(Arc::clone(&self.explainer), sql)),
+            )
+        }
+    }
+
+    impl fmt::Debug for RemoteExplainExec {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RemoteExplainExec")
+                .field("input", &self.input)
+                .field("remote_plan", &self.remote_plan.get())
+                .finish()
+        }
+    }
+
+    fn federated_sql(plan: &Arc<dyn ExecutionPlan>, unparsed_sql: &str) -> Option<String> {
+        if plan.name() == "sql_federation_exec" {
+            let display = displayable(plan.as_ref()).one_line().to_string();
+            let prefix = format!(" sql={unparsed_sql} rewritten_sql=");
+            let suffix = format!(" sql={unparsed_sql}");
+            let start = display.find(&prefix)? + prefix.len();
+            return display[start..]
+                .trim_end()
+                .strip_suffix(&suffix)
+                .map(str::to_string);
+        }
+        plan.children()
+            .into_iter()
+            .find_map(|child| federated_sql(child, unparsed_sql))
+    }
+
+    impl DisplayAs for RemoteExplainExec {
+        fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "RemoteExplainExec")?;
+            match self.remote_plan() {
+                Some(remote_plan) => write!(f, " {remote_plan}"),
+                None => write!(f, " remote_plan=[unavailable: no federated SQL]"),
+            }
+        }
+    }
+
+    impl ExecutionPlan for RemoteExplainExec {
+        fn name(&self) -> &'static str {
+            "RemoteExplainExec"
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn properties(&self) -> &PlanProperties {
+            self.input.properties()
+        }
+
+        fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+            vec![&self.input]
+        }
+
+        fn with_new_children(
+            self: Arc<Self>,
+            mut children: Vec<Arc<dyn ExecutionPlan>>,
+        ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+            Ok(Arc::new(Self::new(
+                children.swap_remove(0),
+                Arc::clone(&self.explainer),
+                self.unparsed_sql.clone(),
+            )))
+        }
+
+        fn execute(
+            &self,
+            partition: usize,
+            context: Arc<TaskContext>,
+        ) -> DataFusionResult<SendableRecordBatchStream> {
+            self.input.execute(partition, context)
+        }
+    }
+}
+
+#[cfg(feature = "federation")]
+pub use federation::{RemoteExplainExec, RemoteExplainQueryPlanner};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::{
+        array::{Int64Array, RecordBatch, StringArray},
+        datatypes::{DataType, Field, Schema},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct StaticExplainer {
+        plan: Result<String, String>,
+        calls: AtomicUsize,
+    }
+
+    impl RemoteExplainer for StaticExplainer {
+        fn explain(&self, sql: &str) -> LocalBoxFuture<'static, DataFusionResult<String>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let plan = match &self.plan {
+                Ok(plan) => Ok(format!("{plan} ({sql})")),
+                Err(e) => Err(datafusion::error::DataFusionError::Execution(e.clone())),
+            };
+            Box::pin(async move { plan })
+        }
+    }
+
+    fn explainer(plan: Result<String, String>) -> Arc<StaticExplainer> {
+        Arc::new(StaticExplainer {
+            plan,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    #[test]
+    fn test_format_explain() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("detail", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![2, 3])),
+                Arc::new(StringArray::from(vec![
+                    "SCAN t",
+                    "USE TEMP B-TREE\n  FOR ORDER BY",
+                ])),
+            ],
+        )
+        .expect("valid batch");
+
+        assert_eq!(
+            format_explain(&[batch]).expect("formatted"),
+            "2 SCAN t; 3 USE TEMP B-TREE; FOR ORDER BY"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_plan_is_explained_when_displayed() {
+        let explainer = explainer(Ok("SCAN t".to_string()));
+        let remote_plan = RemotePlan::new(
+            Arc::clone(&explainer) as Arc<dyn RemoteExplainer>,
+            "SELECT * FROM t",
+        );
+        assert_eq!(explainer.calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(
+            remote_plan.to_string(),
+            "remote_plan=[SCAN t (SELECT * FROM t)]"
+        );
+        assert_eq!(
+            remote_plan.clone().to_string(),
+            "remote_plan=[SCAN t (SELECT * FROM t)]"
+        );
+        assert_eq!(explainer.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_plan_unavailable() {
+        let explainer = explainer(Err("no such table".to_string()));
+        let remote_plan = RemotePlan::new(explainer, "SELECT * FROM t");
+
+        assert_eq!(
+            remote_plan.to_string(),
+            "remote_plan=[unavailable: Execution error: no such table]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_plan_current_thread_runtime() {
+        let explainer = explainer(Ok("SCAN t".to_string()));
+        let remote_plan = RemotePlan::new(
+            Arc::clone(&explainer) as Arc<dyn RemoteExplainer>,
+            "SELECT * FROM t",
+        );
+
+        assert_eq!(
+            remote_plan.to_string(),
+            "remote_plan=[unavailable: not supported on a current-thread runtime]"
+        );
+        assert_eq!(explainer.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_remote_plan_without_runtime() {
+        let remote_plan = RemotePlan::new(explainer(Ok("SCAN t".to_string())), "SELECT 1");
+
+        assert_eq!(remote_plan.to_string(), "remote_plan=[SCAN t (SELECT 1)]");
+    }
+
+    #[cfg(feature = "duckdb")]
+    mod duckdb_tests {
+        use super::*;
+        use crate::sql::db_connection_pool::dbconnection::duckdbconn::{
+            DuckDBSyncParameter, DuckDbConnection,
+        };
+        use crate::sql::db_connection_pool::duckdbpool::DuckDbConnectionPool;
+        use crate::sql::sql_provider_datafusion::SqlExec;
+        use datafusion::physical_plan::displayable;
+        use duckdb::DuckdbConnectionManager;
+
+        type DuckDbPool = Arc<
+            dyn DbConnectionPool<
+                    r2d2::PooledConnection<DuckdbConnectionManager>,
+                    Box<dyn DuckDBSyncParameter>,
+                > + Send
+                + Sync,
+        >;
+
+        async fn pool_with_table() -> Result<DuckDbPool, Box<dyn std::error::Error + Send + Sync>> {
+            let pool: DuckDbPool = Arc::new(DuckDbConnectionPool::new_memory()?);
+            let conn = pool.connect().await?;
+            let db_conn = conn
+                .as_any()
+                .downcast_ref::<DuckDbConnection>()
+                .expect("Unable to downcast to DuckDbConnection");
+            db_conn
+                .conn
+                .execute_batch("CREATE TABLE test AS SELECT * FROM range(10)")?;
+            Ok(pool)
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn test_sql_exec_display() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            let pool = pool_with_table().await?;
+            let schema = Arc::new(Schema::new(vec![Field::new(
+                "range",
+                DataType::Int64,
+                true,
+            )]));
+            let explainer = explainer(Ok("SEQ_SCAN test".to_string()));
+            let exec = SqlExec::new(None, &schema, pool, "SELECT * FROM test".to_string())?
+                .with_remote_plan(Some(RemotePlan::new(
+                    Arc::clone(&explainer) as Arc<dyn RemoteExplainer>,
+                    "SELECT * FROM test",
+                )));
+            assert_eq!(explainer.calls.load(Ordering::SeqCst), 0);
+
+            assert_eq!(
+                displayable(&exec).one_line().to_string().trim_end(),
+                "SqlExec sql=SELECT * FROM test remote_plan=[SEQ_SCAN test (SELECT * FROM test)]"
+            );
+            Ok(())
+        }
+
+        #[cfg(feature = "duckdb-federation")]
+        #[tokio::test(flavor = "multi_thread")]
+        async fn test_federated_remote_explain(
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            use crate::duckdb::DuckDBTable;
+            use datafusion::execution::SessionStateBuilder;
+            use datafusion::prelude::SessionContext;
+
+            let pool = pool_with_table().await?;
+            let schema = Arc::new(Schema::new(vec![Field::new(
+                "range",
+                DataType::Int64,
+                true,
+            )]));
+            let table = DuckDBTable::new_with_schema(&pool, schema, "test", None, None)
+                .with_remote_explain(true);
+            let provider = Arc::new(table).create_federated_table_provider()?;
+
+            let state = SessionStateBuilder::new()
+                .with_optimizer_rules(datafusion_federation::default_optimizer_rules())
+                .with_query_planner(Arc::new(RemoteExplainQueryPlanner::new()))
+                .with_default_features()
+                .build();
+            let ctx = SessionContext::new_with_state(state);
+            ctx.register_table("test", Arc::new(provider))?;
+
+            let batches = ctx
+                .sql("EXPLAIN SELECT * FROM test WHERE range > 5")
+                .await?
+                .collect()
+                .await?;
+            let plan =
+                datafusion::arrow::util::pretty::pretty_format_batches(&batches)?.to_string();
+            assert!(plan.contains("RemoteExplainExec remote_plan=["), "{plan}");
+            assert!(plan.contains("SEQ_SCAN"), "{plan}");
+            assert!(plan.contains("VirtualExecutionPlan"), "{plan}");
+
+            let batches = ctx
+                .sql("SELECT * FROM test WHERE range > 5")
+                .await?
+                .collect()
+                .await?;
+            let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows, 4);
+            Ok(())
+        }
+    }
+}
@@ -1,17 +1,29 @@
 use crate::sql::db_connection_pool::{dbconnection::get_schema, JoinPushDown};
+use crate::sql::like::CaseInsensitiveLike;
+use crate::sql::null_ordering::NullOrdering;
+use crate::sql::pushdown::{is_string_type, FilterSemantics, PushdownPolicy};
+use crate::sql::sample::SampleSyntax;
 use async_trait::async_trait;
-use datafusion_federation::sql::{SQLExecutor, SQLFederationProvider, SQLTableSource};
-use datafusion_federation::{FederatedTableProviderAdaptor, FederatedTableSource};
+use datafusion_federation::sql::{AstAnalyzer, SQLExecutor, SQLFederationProvider, SQLTableSource};
+use datafusion_federation::{
+    FederatedTableProviderAdaptor, FederatedTableSource, FederationProviderRef,
+};
 use futures::TryStreamExt;
 use snafu::prelude::*;
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
+use super::{sort::SortPushdown, subquery::SubqueryFederation, window::WindowPushdown};
 use crate::sql::sql_provider_datafusion::{
-    get_stream, to_execution_error, SqlTable, UnableToGetSchemaSnafu,
+    get_stream_with_params, scan_only, scan_query, to_execution_error, SqlTable,
+    UnableToGetSchemaSnafu,
 };
 use datafusion::{
     arrow::datatypes::SchemaRef,
+    common::tree_node::{Transformed, TreeNode, TreeNodeRecursion},
+    datasource::source_as_provider,
     error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{Expr, ExprSchemable, LogicalPlan, LogicalPlanBuilder, Sort, TableScan},
+    optimizer::{OptimizerConfig, OptimizerRule},
     physical_plan::{stream::RecordBatchStreamAdapter, SendableRecordBatchStream},
     sql::{
         unparser::dialect::{DefaultDialect, Dialect},
@@ -20,6 +32,10 @@ use datafusion::{
 };
 
 impl<T, P> SqlTable<T, P> {
+    fn pushdown_settings(&self) -> (PushdownPolicy, FilterSemantics) {
+        (self.pushdown_policy.clone(), self.filter_semantics)
+    }
+
     // Return the current memory location of the object as a unique identifier
     fn unique_id(&self) -> usize {
         std::ptr::from_ref(self) as usize
@@ -75,15 +91,73 @@ impl<T, P> SQLExecutor for SqlTable<T, P> {
         self.arc_dialect()
     }
 
+    fn ast_analyzer(&self) -> Option<AstAnalyzer> {
+        let null_ordering = NullOrdering::for_database(&self.name);
+        let like = CaseInsensitiveLike::for_database(&self.name);
+        if !self.only
+            && self.pushdown_policy.max_in_list_size().is_none()
+            && self.column_mapping.is_none()
+            && self.query.is_none()
+            && self.sample.is_none()
+            && self.approx_distinct.is_none()
+            && null_ordering.is_none()
+            && like.is_none()
+            && self.regex.is_none()
+        {
+            return None;
+        }
+
+        let sample = self.sample.zip(SampleSyntax::for_database(&self.name));
+        let approx_distinct = self.approx_distinct;
+        let regex = self.regex;
+        let only = self.only;
+        let table_reference = self.table_reference.clone();
+        let pushdown_policy = self.pushdown_policy.clone();
+        let column_mapping = self.column_mapping.clone();
+        let query = self.query.clone();
+        let dialect = self.arc_dialect();
+        Some(Box::new(move |mut statement| {
+            if let Some((sample, syntax)) = &sample {
+                sample.rewrite_statement(&mut statement, &table_reference, *syntax);
+            }
+            if let Some(query) = &query {
+                scan_query(&mut statement, &table_reference, query, dialect.as_ref());
+            }
+            if let Some(column_mapping) = &column_mapping {
+                column_mapping.rewrite_statement(&mut statement, dialect.as_ref());
+            }
+            if only {
+                scan_only(&mut statement, &table_reference);
+            }
+            pushdown_policy.rewrite_statement(&mut statement);
+            if let Some(approx_distinct) = approx_distinct {
+                approx_distinct.rewrite_statement(&mut statement);
+            }
+            if let Some(null_ordering) = null_ordering {
+                null_ordering.rewrite_statement(&mut statement);
+            }
+            if let Some(like) = like {
+                like.rewrite_statement(&mut statement);
+            }
+            if let Some(regex) = regex {
+                regex.rewrite_statement(&mut statement);
+            }
+            Ok(statement)
+        }))
+    }
+
     fn execute(
         &self,
         query: &str,
         schema: SchemaRef,
     ) -> DataFusionResult<SendableRecordBatchStream> {
-        let fut = get_stream(
+        let fut = get_stream_with_params(
             Arc::clone(&self.pool),
-            query.to_string(),
+            self.apply_query_settings(query),
+            Vec::new(),
+            self.current_remote_context(),
             Arc::clone(&schema),
+            self.schema_drift,
         );
 
         let stream = futures::stream::once(fut).try_flatten();
@@ -104,3 +178,204 @@ impl<T, P> SQLExecutor for SqlTable<T, P> {
             .map_err(to_execution_error)
     }
 }
+
+/// The name of the rule of DataFusion that rewrites `EXISTS` and `IN` subqueries into joins.
+const DECORRELATE_PREDICATE_SUBQUERY: &str = "decorrelate_predicate_subquery";
+
+/// The name of the federation optimizer rule of `datafusion-federation`.
+const FEDERATION_OPTIMIZER_RULE: &str = "federation_optimizer_rule";
+
+/// The optimizer rules of `datafusion-federation`, with the rules that push down whole subqueries,
+/// see [`SubqueryFederation`], keep the sorts by strings local for the tables that order strings
+/// by a collation, see [`SortPushdown`], and keep the windows local for the tables that disable
+/// them, see [`WindowPushdown`].
+#[must_use]
+pub fn federation_optimizer_rules() -> Vec<Arc<dyn OptimizerRule + Send + Sync>> {
+    let mut rules = datafusion_federation::default_optimizer_rules();
+    let position = rules
+        .iter()
+        .position(|rule| rule.name() == DECORRELATE_PREDICATE_SUBQUERY)
+        .unwrap_or(0);
+    rules.insert(position, Arc::new(SubqueryFederation::new()));
+    let position = rules
+        .iter()
+        .position(|rule| rule.name() == FEDERATION_OPTIMIZER_RULE)
+        .unwrap_or(rules.len());
+    rules.insert(position, Arc::new(SortPushdown::new()));
+    rules.insert(position + 1, Arc::new(WindowPushdown::new()));
+    rules
+}
+
+/// The federation provider of all the tables of `plan` and of its subqueries, or `None` if they
+/// don't share one or if a node of the plan can't be written as SQL.
+pub(crate) fn sole_provider(plan: &LogicalPlan) -> DataFusionResult<Option<FederationProviderRef>> {
+    let mut sole: Option<FederationProviderRef> = None;
+    let mut federated = true;
+    plan.apply_with_subqueries(|node| {
+        let provider = match node {
+            LogicalPlan::TableScan(scan) => federation_provider(scan),
+            LogicalPlan::Extension(_)
+            | LogicalPlan::RecursiveQuery(_)
+            | LogicalPlan::Dml(_)
+            | LogicalPlan::Ddl(_)
+            | LogicalPlan::Copy(_)
+            | LogicalPlan::Explain(_)
+            | LogicalPlan::Analyze(_)
+            | LogicalPlan::Statement(_)
+            | LogicalPlan::DescribeTable(_) => None,
+            _ => return Ok(TreeNodeRecursion::Continue),
+        };
+        let Some(provider) = provider else {
+            federated = false;
+            return Ok(TreeNodeRecursion::Stop);
+        };
+        if let Some(sole) = &sole {
+            if sole.name() != provider.name()
+                || sole.compute_context() != provider.compute_context()
+            {
+                federated = false;
+                return Ok(TreeNodeRecursion::Stop);
+            }
+        } else {
+            sole = Some(provider);
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(sole.filter(|_| federated))
+}
+
+/// Federates all of `plan` to `provider`, which runs all its tables.
+pub(crate) fn federate(
+    plan: LogicalPlan,
+    provider: &FederationProviderRef,
+    config: &dyn OptimizerConfig,
+) -> DataFusionResult<Transformed<LogicalPlan>> {
+    let Some(optimizer) = provider.optimizer() else {
+        return Ok(Transformed::no(plan));
+    };
+    // the columns of the federated plan are selected by name, like the federation optimizer
+    let plan = match plan {
+        LogicalPlan::Projection(_) => plan,
+        plan => {
+            let columns = plan.schema().columns().into_iter().map(Expr::Column);
+            LogicalPlanBuilder::from(plan).project(columns)?.build()?
+        }
+    };
+    Ok(Transformed::yes(optimizer.optimize(
+        plan,
+        config,
+        |_, _| {},
+    )?))
+}
+
+/// Whether `plan` computes a window function, outside of its subqueries.
+pub(crate) fn has_window(plan: &LogicalPlan) -> bool {
+    plan.exists(|node| Ok(matches!(node, LogicalPlan::Window(_))))
+        .unwrap_or(false)
+}
+
+/// Whether all the tables of `plan` and of its subqueries push down window functions, see
+/// [`PushdownPolicy::with_windows`].
+pub(crate) fn supports_windows(plan: &LogicalPlan) -> bool {
+    all_tables(plan, |policy, _| policy.windows())
+}
+
+/// Whether `sort` sorts by a string.
+pub(crate) fn sorts_strings(sort: &Sort) -> bool {
+    sort.expr.iter().any(|sort_expr| {
+        matches!(
+            sort_expr.expr.get_type(sort.input.schema()),
+            Ok(data_type) if is_string_type(&data_type)
+        )
+    })
+}
+
+/// Whether `plan` sorts by a string, outside of its subqueries.
+pub(crate) fn has_string_sort(plan: &LogicalPlan) -> bool {
+    plan.exists(|node| Ok(matches!(node, LogicalPlan::Sort(sort) if sorts_strings(sort))))
+        .unwrap_or(false)
+}
+
+/// Whether all the tables of `plan` and of its subqueries order strings by their bytes, see
+/// [`FilterSemantics::with_collated_ordering`].
+pub(crate) fn supports_string_sorts(plan: &LogicalPlan) -> bool {
+    all_tables(plan, |_, filter_semantics| {
+        filter_semantics.orders_strings_exactly()
+    })
+}
+
+/// Whether `plan` can be federated as a whole, without the windows and the sorts by strings that
+/// its tables keep local.
+pub(crate) fn can_federate_whole(plan: &LogicalPlan) -> bool {
+    (!has_window(plan) || supports_windows(plan))
+        && (!has_string_sort(plan) || supports_string_sorts(plan))
+}
+
+/// Whether the pushdown settings of all the tables of `plan` and of its subqueries that are
+/// tables of this crate satisfy `f`.
+fn all_tables(plan: &LogicalPlan, f: impl Fn(&PushdownPolicy, FilterSemantics) -> bool) -> bool {
+    let mut all = true;
+    let _ = plan.apply_with_subqueries(|node| {
+        if let LogicalPlan::TableScan(scan) = node {
+            all = pushdown_settings(scan)
+                .is_none_or(|(policy, filter_semantics)| f(&policy, filter_semantics));
+        }
+        Ok(if all {
+            TreeNodeRecursion::Continue
+        } else {
+            TreeNodeRecursion::Stop
+        })
+    });
+    all
+}
+
+fn federation_provider(scan: &TableScan) -> Option<FederationProviderRef> {
+    let provider = source_as_provider(&scan.source).ok()?;
+    let adaptor = provider
+        .as_any()
+        .downcast_ref::<FederatedTableProviderAdaptor>()?;
+    Some(adaptor.source.federation_provider())
+}
+
+/// The pushdown policy and the filter semantics of the federated table of `scan`, if it's a table
+/// of this crate.
+fn pushdown_settings(scan: &TableScan) -> Option<(PushdownPolicy, FilterSemantics)> {
+    let provider = source_as_provider(&scan.source).ok()?;
+    let adaptor = provider
+        .as_any()
+        .downcast_ref::<FederatedTableProviderAdaptor>()?;
+    table_pushdown_settings(adaptor.table_provider.as_ref()?.as_any())
+}
+
+fn table_pushdown_settings(provider: &dyn Any) -> Option<(PushdownPolicy, FilterSemantics)> {
+    #[cfg(feature = "postgres")]
+    if let Some(table) = provider.downcast_ref::<SqlTable<
+        bb8::PooledConnection<
+            'static,
+            bb8_postgres::PostgresConnectionManager<postgres_native_tls::MakeTlsConnector>,
+        >,
+        &'static (dyn bb8_postgres::tokio_postgres::types::ToSql + Sync),
+    >>() {
+        return Some(table.pushdown_settings());
+    }
+    #[cfg(feature = "mysql")]
+    if let Some(table) = provider.downcast_ref::<crate::mysql::sql_table::MySQLTable>() {
+        return Some(table.base_table.pushdown_settings());
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(table) = provider.downcast_ref::<crate::sqlite::sql_table::SQLiteTable<
+        tokio_rusqlite::Connection,
+        &'static (dyn rusqlite::ToSql + Sync),
+    >>() {
+        return Some(table.base_table.pushdown_settings());
+    }
+    #[cfg(feature = "duckdb")]
+    if let Some(table) = provider.downcast_ref::<crate::duckdb::DuckDBTable<
+        r2d2::PooledConnection<duckdb::DuckdbConnectionManager>,
+        Box<dyn crate::sql::db_connection_pool::dbconnection::duckdbconn::DuckDBSyncParameter>,
+    >>() {
+        return Some(table.base_table.pushdown_settings());
+    }
+    let _ = provider;
+    None
+}
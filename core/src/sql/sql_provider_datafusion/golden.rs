@@ -0,0 +1,329 @@
+//! Golden files of the SQL that federation sends to the databases of each dialect, to pin what a
+//! provider configuration pushes down across upgrades of this crate and DataFusion.
+//!
+//! The queries are planned against [`SqlTable`]s of a [`MockConnectionPool`] with the schemas of
+//! the tables, so nothing runs on a database. The tables of a dialect share a pool, so joins
+//! between them are pushed down as one query.
+//!
+//! ```rust,ignore
+//! GoldenSql::new()
+//!     .with_table("users", users_schema)
+//!     .with_query("filter", "SELECT name FROM users WHERE id > 5 LIMIT 10")
+//!     .with_table_config(|table| table.with_pushdown_policy(policy.clone()))
+//!     .verify("tests/golden/users.sql")
+//!     .await?;
+//! ```
+//!
+//! A missing golden file is written with the current SQL. Set `UPDATE_GOLDEN_SQL=1` to rewrite the
+//! golden files after an intended change.
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use datafusion::{
+    arrow::datatypes::SchemaRef,
+    error::DataFusionError,
+    execution::SessionStateBuilder,
+    prelude::SessionContext,
+    sql::{
+        unparser::dialect::{
+            Dialect, DuckDBDialect, MySqlDialect, PostgreSqlDialect, SqliteDialect,
+        },
+        TableReference,
+    },
+};
+use snafu::prelude::*;
+
+use super::{dry_run::dry_run, SqlTable};
+use crate::sql::db_connection_pool::{
+    dbconnection::mockconn::MockDatabase, mockpool::MockConnectionPool, DbConnectionPool,
+    JoinPushDown,
+};
+
+/// The environment variable that rewrites the golden files instead of comparing with them.
+pub const UPDATE_GOLDEN_SQL: &str = "UPDATE_GOLDEN_SQL";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to plan the query '{name}' for the {dialect} dialect: {source}"))]
+    UnableToPlanQuery {
+        name: String,
+        dialect: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Unable to register the table '{table_name}': {source}"))]
+    UnableToRegisterTable {
+        table_name: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display("Unable to read the golden file {}: {source}", path.display()))]
+    UnableToReadGoldenFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Unable to write the golden file {}: {source}", path.display()))]
+    UnableToWriteGoldenFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("The SQL differs from the golden file {}, run with {UPDATE_GOLDEN_SQL}=1 to update it.\nExpected:\n{expected}\nActual:\n{actual}", path.display()))]
+    GoldenSqlMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+type MockTable = SqlTable<MockDatabase, String>;
+type TableConfig = Box<dyn Fn(MockTable) -> MockTable + Send + Sync>;
+
+/// The tables, dialects and queries of a golden file.
+pub struct GoldenSql {
+    tables: Vec<(TableReference, SchemaRef)>,
+    dialects: Vec<(String, Arc<dyn Dialect + Send + Sync>)>,
+    queries: Vec<(String, String)>,
+    table_config: Option<TableConfig>,
+}
+
+impl Default for GoldenSql {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GoldenSql {
+    /// A golden file for the dialects of the databases of this crate: `postgres`, `mysql`,
+    /// `sqlite` and `duckdb`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            tables: Vec::new(),
+            dialects: vec![
+                ("postgres".to_string(), Arc::new(PostgreSqlDialect {})),
+                ("mysql".to_string(), Arc::new(MySqlDialect {})),
+                ("sqlite".to_string(), Arc::new(SqliteDialect {})),
+                ("duckdb".to_string(), Arc::new(DuckDBDialect::new())),
+            ],
+            queries: Vec::new(),
+            table_config: None,
+        }
+    }
+
+    /// Adds a table that the queries can use.
+    #[must_use]
+    pub fn with_table(
+        mut self,
+        table_reference: impl Into<TableReference>,
+        schema: SchemaRef,
+    ) -> Self {
+        self.tables.push((table_reference.into(), schema));
+        self
+    }
+
+    /// Adds a dialect, or replaces the one with the same name, like a dialect with the overrides of
+    /// [`crate::sql::dialect::DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect(
+        mut self,
+        name: impl Into<String>,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Self {
+        let name = name.into();
+        match self.dialects.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, d)) => *d = dialect,
+            None => self.dialects.push((name, dialect)),
+        }
+        self
+    }
+
+    /// Keeps only the dialects named `names`.
+    #[must_use]
+    pub fn with_only_dialects(mut self, names: &[&str]) -> Self {
+        self.dialects
+            .retain(|(name, _)| names.contains(&name.as_str()));
+        self
+    }
+
+    /// Adds a DataFusion query, identified by `name` in the golden file.
+    #[must_use]
+    pub fn with_query(mut self, name: impl Into<String>, sql: impl Into<String>) -> Self {
+        self.queries.push((name.into(), sql.into()));
+        self
+    }
+
+    /// Configures the tables like the providers under test, for example with their
+    /// [`crate::sql::pushdown::PushdownPolicy`]. The dialect is set before `config` runs.
+    #[must_use]
+    pub fn with_table_config(
+        mut self,
+        config: impl Fn(MockTable) -> MockTable + Send + Sync + 'static,
+    ) -> Self {
+        self.table_config = Some(Box::new(config));
+        self
+    }
+
+    /// The SQL that each query sends to the database of each dialect, in the format of the golden
+    /// files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a table can't be registered or a query can't be planned.
+    pub async fn render(&self) -> Result<String> {
+        let mut golden = String::new();
+        let contexts = self.contexts()?;
+        for (name, sql) in &self.queries {
+            let _ = writeln!(golden, "-- query: {name}\n{sql}");
+            for ((dialect, _), ctx) in self.dialects.iter().zip(&contexts) {
+                let remote_sql = dry_run(&ctx.state(), sql)
+                    .await
+                    .context(UnableToPlanQuerySnafu { name, dialect })?;
+                let _ = writeln!(golden, "-- {dialect}\n{}", remote_sql.join("\n"));
+            }
+            golden.push('\n');
+        }
+        Ok(golden)
+    }
+
+    /// Compares the SQL of [`GoldenSql::render`] with the golden file at `path`, which is written
+    /// if it's missing or if `UPDATE_GOLDEN_SQL` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::GoldenSqlMismatch`] if the SQL differs from the golden file, or an error if
+    /// the SQL can't be rendered or the golden file can't be read or written.
+    pub async fn verify(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let actual = self.render().await?;
+        if std::env::var_os(UPDATE_GOLDEN_SQL).is_some() || !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).context(UnableToWriteGoldenFileSnafu { path })?;
+            }
+            return std::fs::write(path, actual).context(UnableToWriteGoldenFileSnafu { path });
+        }
+
+        let expected =
+            std::fs::read_to_string(path).context(UnableToReadGoldenFileSnafu { path })?;
+        ensure!(
+            expected == actual,
+            GoldenSqlMismatchSnafu {
+                path,
+                expected,
+                actual
+            }
+        );
+        Ok(())
+    }
+
+    /// A federated context with the tables of each dialect.
+    fn contexts(&self) -> Result<Vec<SessionContext>> {
+        self.dialects
+            .iter()
+            .map(|(dialect_name, dialect)| {
+                let mut mock = MockConnectionPool::new()
+                    .with_join_push_down(JoinPushDown::AllowedFor(dialect_name.clone()));
+                for (table_reference, schema) in &self.tables {
+                    mock = mock.with_table(table_reference.clone(), Arc::clone(schema));
+                }
+                let pool: Arc<dyn DbConnectionPool<MockDatabase, String> + Send + Sync> =
+                    Arc::new(mock);
+
+                let state = SessionStateBuilder::new()
+                    .with_optimizer_rules(datafusion_federation::default_optimizer_rules())
+                    .with_query_planner(Arc::new(
+                        datafusion_federation::FederatedQueryPlanner::new(),
+                    ))
+                    .with_default_features()
+                    .build();
+                let ctx = SessionContext::new_with_state(state);
+                for (table_reference, schema) in &self.tables {
+                    let table = SqlTable::new_with_schema(
+                        dialect_name,
+                        &pool,
+                        Arc::clone(schema),
+                        table_reference.clone(),
+                    )
+                    .with_dialect(Arc::clone(dialect));
+                    let table = match &self.table_config {
+                        Some(config) => config(table),
+                        None => table,
+                    };
+                    Arc::new(table)
+                        .create_federated_table_provider()
+                        .and_then(|provider| {
+                            ctx.register_table(table_reference.clone(), Arc::new(provider))
+                        })
+                        .context(UnableToRegisterTableSnafu {
+                            table_name: table_reference.to_string(),
+                        })?;
+                }
+                Ok(ctx)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+
+    use super::*;
+
+    fn golden() -> GoldenSql {
+        let users = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let orders = Arc::new(Schema::new(vec![
+            Field::new("user_id", DataType::Int64, false),
+            Field::new("total", DataType::Float64, true),
+        ]));
+        GoldenSql::new()
+            .with_table("users", users)
+            .with_table("orders", orders)
+            .with_query("filter", "SELECT name FROM users WHERE id > 5 LIMIT 10")
+            .with_query(
+                "join",
+                "SELECT u.name, sum(o.total) FROM users u JOIN orders o ON u.id = o.user_id GROUP BY u.name",
+            )
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let golden = golden().render().await.expect("golden SQL rendered");
+
+        let filter = golden.split("\n\n").next().expect("filter query");
+        assert!(filter.starts_with("-- query: filter\n"), "{golden}");
+        assert!(filter.contains("\n-- mysql\nSELECT `users`.`name` FROM `users`"));
+        assert!(filter.contains("\n-- postgres\nSELECT \"users\".\"name\" FROM \"users\""));
+
+        // the tables share a pool, so the join is one query
+        let join = golden.split("\n\n").nth(1).expect("join query");
+        assert_eq!(join.matches("JOIN").count(), 5, "{golden}");
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let path = std::env::temp_dir().join(format!("golden_sql_{}.sql", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        golden().verify(&path).await.expect("golden file written");
+        golden().verify(&path).await.expect("golden SQL matches");
+
+        let error = golden()
+            .with_only_dialects(&["postgres"])
+            .verify(&path)
+            .await
+            .expect_err("golden SQL differs");
+        assert!(matches!(error, Error::GoldenSqlMismatch { .. }));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,149 @@
+//! The metrics of the scans of remote databases, which `EXPLAIN ANALYZE` shows for each partition
+//! of a scan:
+//!
+//! * `output_rows` and `output_bytes`, the rows returned by the database and the memory of their
+//!   batches
+//! * `remote_time`, the time spent waiting on the database: connecting, running the query and
+//!   fetching its batches
+//! * `time_to_first_batch`, the time from the start of the scan to its first batch
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use datafusion::{
+    arrow::{datatypes::SchemaRef, record_batch::RecordBatch},
+    error::Result as DataFusionResult,
+    execution::{RecordBatchStream, SendableRecordBatchStream},
+    physical_plan::metrics::{Count, ExecutionPlanMetricsSet, MetricBuilder, Time},
+};
+use futures::{Stream, TryStreamExt};
+
+/// The metrics of a partition of a scan.
+#[derive(Debug, Clone)]
+pub struct ScanMetrics {
+    output_rows: Count,
+    output_bytes: Count,
+    remote_time: Time,
+    time_to_first_batch: Time,
+}
+
+impl ScanMetrics {
+    #[must_use]
+    pub fn new(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Self {
+        Self {
+            output_rows: MetricBuilder::new(metrics).output_rows(partition),
+            output_bytes: MetricBuilder::new(metrics).counter("output_bytes", partition),
+            remote_time: MetricBuilder::new(metrics).subset_time("remote_time", partition),
+            time_to_first_batch: MetricBuilder::new(metrics)
+                .subset_time("time_to_first_batch", partition),
+        }
+    }
+
+    /// The stream of the batches of `query`, the future that runs the query of the scan, which
+    /// records the metrics of the scan. The scan starts when the stream is first polled.
+    #[must_use]
+    pub fn record_scan(
+        self,
+        schema: SchemaRef,
+        query: impl Future<Output = DataFusionResult<SendableRecordBatchStream>> + Send + 'static,
+    ) -> SendableRecordBatchStream {
+        Box::pin(ScanMetricsStream {
+            schema,
+            inner: Box::pin(futures::stream::once(query).try_flatten()),
+            metrics: self,
+            start: None,
+            first_batch: true,
+        })
+    }
+}
+
+struct ScanMetricsStream {
+    schema: SchemaRef,
+    inner: Pin<Box<dyn Stream<Item = DataFusionResult<RecordBatch>> + Send>>,
+    metrics: ScanMetrics,
+    start: Option<Instant>,
+    first_batch: bool,
+}
+
+impl Stream for ScanMetricsStream {
+    type Item = DataFusionResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let poll_start = Instant::now();
+        let poll = self.inner.as_mut().poll_next(cx);
+        self.metrics.remote_time.add_elapsed(poll_start);
+
+        if let Poll::Ready(Some(Ok(batch))) = &poll {
+            if self.first_batch {
+                self.first_batch = false;
+                self.metrics
+                    .time_to_first_batch
+                    .add_duration(start.elapsed());
+            }
+            self.metrics.output_rows.add(batch.num_rows());
+            self.metrics.output_bytes.add(batch.get_array_memory_size());
+        }
+        poll
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl RecordBatchStream for ScanMetricsStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::{
+            array::Int32Array,
+            datatypes::{DataType, Field, Schema},
+        },
+        physical_plan::stream::RecordBatchStreamAdapter,
+    };
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_scan() -> DataFusionResult<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )?;
+        let batches: Vec<DataFusionResult<_>> = vec![Ok(batch.clone()), Ok(batch)];
+        let query_schema = Arc::clone(&schema);
+        let query = async move {
+            let stream: SendableRecordBatchStream = Box::pin(RecordBatchStreamAdapter::new(
+                query_schema,
+                futures::stream::iter(batches),
+            ));
+            Ok(stream)
+        };
+
+        let metrics = ExecutionPlanMetricsSet::new();
+        let stream = ScanMetrics::new(&metrics, 0).record_scan(schema, query);
+        assert_eq!(stream.count().await, 2);
+
+        let metrics = metrics.clone_inner();
+        assert_eq!(metrics.output_rows(), Some(6));
+        assert!(metrics
+            .sum_by_name("output_bytes")
+            .is_some_and(|bytes| bytes.as_usize() > 0));
+        assert!(metrics.sum_by_name("remote_time").is_some());
+        assert!(metrics.sum_by_name("time_to_first_batch").is_some());
+        Ok(())
+    }
+}
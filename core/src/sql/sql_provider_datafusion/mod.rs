@@ -4,24 +4,52 @@
 //!
 //! This is used as a fallback if the `datafusion-federation` optimizer is not enabled.
 
+use self::explain::{PoolExplainer, RemoteExplainer, RemotePlan};
+use self::metrics::ScanMetrics;
+use crate::sql::approx_distinct::ApproxDistinct;
+use crate::sql::column_mapper::{self, ColumnMapper, ColumnMapping};
 use crate::sql::db_connection_pool::{
     self,
-    dbconnection::{get_schema, query_arrow},
+    dbconnection::{get_schema, query_arrow, query_arrow_with_string_params},
     DbConnectionPool,
 };
+use crate::sql::dialect::DialectOverrides;
+use crate::sql::like::CaseInsensitiveLike;
+use crate::sql::null_ordering::NullOrdering;
+use crate::sql::parameters::{bind_string_literals, BoundStatement, PlaceholderStyle};
+use crate::sql::pushdown::{FilterSemantics, PushdownPolicy};
+use crate::sql::query_attribution::attribute_sql;
+use crate::sql::query_context::{QueryContext, QueryContextMode, RemoteContext};
+use crate::sql::query_settings::{QuerySettings, SettingsSyntax};
+use crate::sql::regexp::{supports_regexes, RegexSyntax};
+use crate::sql::sample::{SampleSyntax, TableSample};
+use crate::sql::schema_drift::{adapt_batch, schema_changes, SchemaDriftPolicy};
+use crate::util::dictionary::{
+    dictionary_encode_schema, encode_dictionaries, has_dictionary_fields,
+};
+use crate::util::indexes::IndexAdvisor;
+use crate::util::memory::limit_stream_memory;
+use crate::util::redact::error_redaction;
 use async_trait::async_trait;
 use datafusion::{
     catalog::Session,
+    common::{DFSchema, Statistics},
     physical_plan::execution_plan::{Boundedness, EmissionType},
-    sql::unparser::dialect::{DefaultDialect, Dialect},
+    sql::{
+        sqlparser::ast::{self, visit_relations_mut},
+        unparser::dialect::{DefaultDialect, Dialect},
+    },
 };
-use futures::TryStreamExt;
+use futures::StreamExt;
 use snafu::prelude::*;
-use std::{any::Any, fmt, sync::Arc};
+use std::{any::Any, fmt, ops::ControlFlow, sync::Arc};
 use std::{
     fmt::{Display, Formatter},
+    future::Future,
     sync::LazyLock,
+    time::Duration,
 };
+use tracing::Instrument;
 
 use datafusion::{
     arrow::datatypes::{DataType, Field, Schema, SchemaRef},
@@ -34,14 +62,27 @@ use datafusion::{
     },
     physical_expr::EquivalenceProperties,
     physical_plan::{
-        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
-        Partitioning, PlanProperties, SendableRecordBatchStream,
+        metrics::{ExecutionPlanMetricsSet, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, PlanProperties,
+        SendableRecordBatchStream,
     },
     sql::{unparser::Unparser, TableReference},
 };
 
+pub mod dry_run;
+pub mod explain;
 #[cfg(feature = "federation")]
 pub mod federation;
+#[cfg(feature = "federation")]
+pub mod golden;
+pub mod metrics;
+#[cfg(feature = "federation")]
+pub mod sort;
+#[cfg(feature = "federation")]
+pub mod subquery;
+#[cfg(feature = "federation")]
+pub mod window;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -55,6 +96,17 @@ pub enum Error {
 
     #[snafu(display("Unable to generate SQL: {source}"))]
     UnableToGenerateSQL { source: DataFusionError },
+
+    #[snafu(display("Unable to map the column names: {source}"))]
+    UnableToMapColumnNames { source: column_mapper::Error },
+
+    #[snafu(display("Unable to get the schema of the query: {source}"))]
+    UnableToGetQuerySchema {
+        source: db_connection_pool::dbconnection::Error,
+    },
+
+    #[snafu(display("Unable to get the schema of the query, it returned no rows.\nCreate the table with SqlTable::new_with_schema and SqlTable::with_query instead."))]
+    EmptyQuerySchema {},
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -66,6 +118,23 @@ pub struct SqlTable<T: 'static, P: 'static> {
     schema: SchemaRef,
     pub table_reference: TableReference,
     dialect: Option<Arc<dyn Dialect + Send + Sync>>,
+    only: bool,
+    statistics: Option<Statistics>,
+    schema_drift: SchemaDriftPolicy,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    remote_explain: bool,
+    pushdown_policy: PushdownPolicy,
+    filter_semantics: FilterSemantics,
+    sample: Option<TableSample>,
+    approx_distinct: Option<ApproxDistinct>,
+    regex: Option<RegexSyntax>,
+    bind_literals: bool,
+    query_context: Option<QueryContextMode>,
+    query_settings: QuerySettings,
+    column_mapping: Option<Arc<ColumnMapping>>,
+    query: Option<Arc<str>>,
+    index_advisor: Option<Arc<IndexAdvisor>>,
 }
 
 impl<T, P> fmt::Debug for SqlTable<T, P> {
@@ -74,6 +143,22 @@ impl<T, P> fmt::Debug for SqlTable<T, P> {
             .field("name", &self.name)
             .field("schema", &self.schema)
             .field("table_reference", &self.table_reference)
+            .field("only", &self.only)
+            .field("schema_drift", &self.schema_drift)
+            .field("spill_buffer", &self.spill_buffer)
+            .field("memory_wait", &self.memory_wait)
+            .field("remote_explain", &self.remote_explain)
+            .field("pushdown_policy", &self.pushdown_policy)
+            .field("filter_semantics", &self.filter_semantics)
+            .field("sample", &self.sample)
+            .field("approx_distinct", &self.approx_distinct)
+            .field("regex", &self.regex)
+            .field("bind_literals", &self.bind_literals)
+            .field("query_context", &self.query_context)
+            .field("query_settings", &self.query_settings)
+            .field("column_mapping", &self.column_mapping)
+            .field("query", &self.query)
+            .field("index_advisor", &self.index_advisor.is_some())
             .finish()
     }
 }
@@ -109,21 +194,164 @@ impl<T, P> SqlTable<T, P> {
             schema: schema.into(),
             table_reference: table_reference.into(),
             dialect: None,
+            only: false,
+            statistics: None,
+            schema_drift: SchemaDriftPolicy::default(),
+            spill_buffer: None,
+            memory_wait: None,
+            remote_explain: false,
+            pushdown_policy: PushdownPolicy::default(),
+            filter_semantics: FilterSemantics::default(),
+            sample: None,
+            approx_distinct: ApproxDistinct::for_database(name),
+            regex: RegexSyntax::for_database(name),
+            bind_literals: false,
+            query_context: None,
+            query_settings: QuerySettings::default(),
+            column_mapping: None,
+            query: None,
+            index_advisor: None,
         }
     }
 
+    /// A table of the rows of `query`, a `SELECT` in the dialect of the database, like a view that
+    /// only exists on this side. Scans read from `query` as a derived table named after
+    /// `table_reference`, so their filters, projections and limits are pushed down around it:
+    ///
+    /// ```sql
+    /// SELECT "recent_orders"."id" FROM (SELECT * FROM orders WHERE ...) AS "recent_orders" WHERE ...
+    /// ```
+    ///
+    /// The schema is taken from the first row of `query`, see [`get_query_schema`]. Remote views
+    /// are tables like any other, [`SqlTable::new`] reads them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails, or returns no rows on a database that only knows the
+    /// types of the columns from the rows.
+    pub async fn from_query(
+        name: &str,
+        pool: &Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+        table_reference: impl Into<TableReference>,
+        query: &str,
+    ) -> Result<Self> {
+        let schema = get_query_schema(pool, query).await?;
+        Ok(Self::new_with_schema(name, pool, schema, table_reference).with_query(query))
+    }
+
     pub fn scan_to_sql(
         &self,
         projection: Option<&Vec<usize>>,
         filters: &[Expr],
         limit: Option<usize>,
     ) -> DataFusionResult<String> {
+        Ok(self
+            .scan_to_statement(projection, filters, limit)?
+            .to_string())
+    }
+
+    pub fn scan_to_statement(
+        &self,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<ast::Statement> {
+        if let Some(index_advisor) = &self.index_advisor {
+            index_advisor.record(&self.table_reference, filters);
+        }
+        let limit = limit.filter(|_| self.pushdown_policy.limit());
         let logical_plan = self.create_logical_plan(projection, filters, limit)?;
-        let sql = Unparser::new(self.dialect())
-            .plan_to_sql(&logical_plan)?
-            .to_string();
+        let mut statement = Unparser::new(self.dialect()).plan_to_sql(&logical_plan)?;
+        self.sample_statement(&mut statement);
+        self.rewrite_statement(&mut statement);
+        Ok(statement)
+    }
+
+    /// The SQL of the `aggregates` over all the rows of the table, without grouping, e.g. the
+    /// `count(1)` of a [`crate::sql::change_probe::ChangeProbe`]. The aggregates refer to the
+    /// columns of the table by the names of its schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the aggregates can't be planned or written as SQL.
+    pub fn aggregate_to_sql(&self, aggregates: Vec<Expr>) -> DataFusionResult<String> {
+        let table_source = LogicalTableSource::new(self.schema());
+        let logical_plan =
+            LogicalPlanBuilder::scan(self.table_reference.clone(), Arc::new(table_source), None)?
+                .aggregate(Vec::<Expr>::new(), aggregates)?
+                .build()?;
+        let mut statement = Unparser::new(self.dialect()).plan_to_sql(&logical_plan)?;
+        self.rewrite_statement(&mut statement);
+        Ok(statement.to_string())
+    }
+
+    /// Samples the scans of the table in `statement` with the sample of [`SqlTable::with_sample`],
+    /// if the database supports it.
+    fn sample_statement(&self, statement: &mut ast::Statement) {
+        if let (Some(sample), Some(syntax)) = (&self.sample, SampleSyntax::for_database(&self.name))
+        {
+            sample.rewrite_statement(statement, &self.table_reference, syntax);
+        }
+    }
 
-        Ok(sql)
+    /// Rewrites `statement`, unparsed from a plan over the table, to read the table as the remote
+    /// database names it.
+    fn rewrite_statement(&self, statement: &mut ast::Statement) {
+        if let Some(query) = &self.query {
+            scan_query(statement, &self.table_reference, query, self.dialect());
+        }
+        if let Some(column_mapping) = &self.column_mapping {
+            column_mapping.rewrite_statement(statement, self.dialect());
+        }
+        if self.only {
+            scan_only(statement, &self.table_reference);
+        }
+        self.pushdown_policy.rewrite_statement(statement);
+        if let Some(approx_distinct) = self.approx_distinct {
+            approx_distinct.rewrite_statement(statement);
+        }
+        if let Some(null_ordering) = NullOrdering::for_database(&self.name) {
+            null_ordering.rewrite_statement(statement);
+        }
+        if let Some(like) = CaseInsensitiveLike::for_database(&self.name) {
+            like.rewrite_statement(statement);
+        }
+        if let Some(regex) = self.regex {
+            regex.rewrite_statement(statement);
+        }
+    }
+
+    /// Renders `statement` with its string literals bound as parameters if literal binding is
+    /// enabled and the database supports it, see [`SqlTable::with_bind_literals`], and with the
+    /// settings of [`SqlTable::with_query_settings`].
+    #[must_use]
+    pub fn bind_statement(&self, statement: ast::Statement) -> BoundStatement {
+        let mut bound =
+            match PlaceholderStyle::for_database(&self.name).filter(|_| self.bind_literals) {
+                Some((style, max_params)) => bind_string_literals(statement, style, max_params),
+                None => BoundStatement {
+                    sql: statement.to_string(),
+                    params: Vec::new(),
+                },
+            };
+        bound.sql = self.apply_query_settings(&bound.sql);
+        bound
+    }
+
+    /// `sql` with the settings of [`SqlTable::with_query_settings`] that are part of the query.
+    #[must_use]
+    pub fn apply_query_settings(&self, sql: &str) -> String {
+        match SettingsSyntax::for_database(&self.name) {
+            Some(syntax) => self.query_settings.apply_to_sql(sql, syntax),
+            None => sql.to_string(),
+        }
+    }
+
+    /// The settings of [`SqlTable::with_query_settings`] that are set on the session.
+    fn session_settings(&self) -> Vec<(String, String)> {
+        SettingsSyntax::for_database(&self.name)
+            .map(|syntax| self.query_settings.session_settings(syntax))
+            .unwrap_or_default()
     }
 
     fn create_logical_plan(
@@ -146,14 +374,24 @@ impl<T, P> SqlTable<T, P> {
     fn create_physical_plan(
         &self,
         projection: Option<&Vec<usize>>,
-        sql: String,
+        bound: BoundStatement,
+        remote_plan: Option<RemotePlan>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(SqlExec::new(
-            projection,
-            &self.schema(),
-            Arc::clone(&self.pool),
-            sql,
-        )?))
+        Ok(Arc::new(
+            SqlExec::new(
+                projection,
+                &self.schema(),
+                Arc::clone(&self.pool),
+                bound.sql,
+            )?
+            .with_params(bound.params)
+            .with_query_context(self.query_context.clone())
+            .with_session_settings(self.session_settings())
+            .with_schema_drift(self.schema_drift)
+            .with_spill_buffer(self.spill_buffer)
+            .with_memory_wait(self.memory_wait)
+            .with_remote_plan(remote_plan),
+        ))
     }
 
     #[must_use]
@@ -164,11 +402,373 @@ impl<T, P> SqlTable<T, P> {
         }
     }
 
+    /// Unparses the functions of `overrides` with their overrides instead of the dialect of the
+    /// table, see [`DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect_overrides(self, overrides: &DialectOverrides) -> Self {
+        let dialect = self
+            .dialect
+            .clone()
+            .unwrap_or_else(|| Arc::new(DefaultDialect {}));
+        self.with_dialect(overrides.dialect(dialect))
+    }
+
+    /// Scans only the table itself with `FROM ONLY`, excluding the rows of the tables that
+    /// inherit from it. Only Postgres supports `ONLY`.
+    #[must_use]
+    pub fn with_only(self, only: bool) -> Self {
+        Self { only, ..self }
+    }
+
+    /// Sets the statistics reported to the DataFusion optimizer, e.g. an estimated row count.
+    #[must_use]
+    pub fn with_statistics(self, statistics: Statistics) -> Self {
+        Self {
+            statistics: Some(statistics),
+            ..self
+        }
+    }
+
+    /// Returns the given string columns dictionary-encoded, see [`dictionary_encode_schema`].
+    #[must_use]
+    pub fn with_dictionary_columns(self, columns: &[String]) -> Self {
+        Self {
+            schema: dictionary_encode_schema(&self.schema, columns),
+            ..self
+        }
+    }
+
+    /// Sets what scans do when the columns returned by the database no longer match the schema of
+    /// the table, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(self, schema_drift: SchemaDriftPolicy) -> Self {
+        Self {
+            schema_drift,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn schema_drift(&self) -> SchemaDriftPolicy {
+        self.schema_drift
+    }
+
+    /// Reads scans to the end as fast as the database sends the rows, so the connection is released
+    /// even if the consumer is slow. Up to `memory_batches` unread batches are kept in memory, the
+    /// others are written to disk, see [`crate::util::spill::spill_stream`]. `None`, the default,
+    /// streams the rows straight from the connection.
+    #[must_use]
+    pub fn with_spill_buffer(self, memory_batches: Option<usize>) -> Self {
+        Self {
+            spill_buffer: memory_batches,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn spill_buffer(&self) -> Option<usize> {
+        self.spill_buffer
+    }
+
+    /// Reserves the memory of every batch of scans in the memory pool of the session before it's
+    /// read, waiting up to `wait` for the pool to have it, see
+    /// [`crate::util::memory::reserve_stream_memory`]. Scans with a spill buffer write the
+    /// batches to disk instead of waiting. `None`, the default, doesn't account the batches.
+    #[must_use]
+    pub fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            memory_wait: wait,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn memory_wait(&self) -> Option<Duration> {
+        self.memory_wait
+    }
+
+    /// Sets what scans push down to the database, see [`PushdownPolicy`].
+    #[must_use]
+    pub fn with_pushdown_policy(self, pushdown_policy: PushdownPolicy) -> Self {
+        Self {
+            pushdown_policy,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn pushdown_policy(&self) -> &PushdownPolicy {
+        &self.pushdown_policy
+    }
+
+    /// Sets how the database evaluates the filters that are pushed down, which are evaluated again
+    /// by DataFusion when the database matches more rows, and whether it sorts strings like
+    /// DataFusion, see [`FilterSemantics`].
+    #[must_use]
+    pub fn with_filter_semantics(self, filter_semantics: FilterSemantics) -> Self {
+        Self {
+            filter_semantics,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn filter_semantics(&self) -> FilterSemantics {
+        self.filter_semantics
+    }
+
+    /// Reads a sample of the rows of the table, on the databases that support `TABLESAMPLE`, see
+    /// [`TableSample`]. The queries of the table are then approximate.
+    #[must_use]
+    pub fn with_sample(self, sample: Option<TableSample>) -> Self {
+        Self { sample, ..self }
+    }
+
+    #[must_use]
+    pub fn sample(&self) -> Option<&TableSample> {
+        self.sample.as_ref()
+    }
+
+    /// Sets how the `approx_distinct` aggregates of federated queries are pushed down, see
+    /// [`ApproxDistinct`]. Defaults to the estimate of the database, by the name of the table
+    /// provider; `None` pushes them down as they are. Databases without an estimate, like Postgres,
+    /// only count exactly with [`ApproxDistinct::Exact`], at the cost of a `count(DISTINCT ...)`.
+    #[must_use]
+    pub fn with_approx_distinct(self, approx_distinct: Option<ApproxDistinct>) -> Self {
+        Self {
+            approx_distinct,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn approx_distinct(&self) -> Option<ApproxDistinct> {
+        self.approx_distinct
+    }
+
+    /// Sets how the regex matches are pushed down, see [`RegexSyntax`]. Defaults to the syntax of
+    /// the database, by the name of the table provider; with `None`, the filters with regex
+    /// matches aren't pushed down.
+    #[must_use]
+    pub fn with_regex(self, regex: Option<RegexSyntax>) -> Self {
+        Self { regex, ..self }
+    }
+
+    #[must_use]
+    pub fn regex(&self) -> Option<RegexSyntax> {
+        self.regex
+    }
+
+    /// Shows the plan of the remote database next to the SQL of every scan in the `EXPLAIN` output
+    /// of DataFusion, see [`RemotePlan`].
+    ///
+    /// The remote plan is retrieved with an `EXPLAIN` on a connection of the pool when the plan is
+    /// displayed, so queries that aren't explained aren't affected.
+    #[must_use]
+    pub fn with_remote_explain(self, remote_explain: bool) -> Self {
+        Self {
+            remote_explain,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn remote_explain(&self) -> bool {
+        self.remote_explain
+    }
+
+    /// Binds the string literals of the SQL of scans as parameters of the query instead of quoting
+    /// them, for Postgres, DuckDB, SQLite and MySQL tables.
+    ///
+    /// Values with quotes, backslashes or other characters that the dialect escapes differently
+    /// are passed to the database as is, and the database can reuse the plan of statements that
+    /// only differ in their literals. Literals that are cast, like dates, stay inline.
+    #[must_use]
+    pub fn with_bind_literals(self, bind_literals: bool) -> Self {
+        Self {
+            bind_literals,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn bind_literals(&self) -> bool {
+        self.bind_literals
+    }
+
+    /// Passes the [`QueryContext`] of the session to the database with the queries of scans, see
+    /// [`QueryContextMode`].
+    #[must_use]
+    pub fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            query_context,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn query_context(&self) -> Option<&QueryContextMode> {
+        self.query_context.as_ref()
+    }
+
+    /// Passes `query_settings` to the database with the queries of scans, like Postgres session
+    /// settings or MySQL optimizer hints, see [`QuerySettings`].
+    ///
+    /// Postgres has no settings for a single query outside of a transaction, so they're set on the
+    /// pooled connection before every query of the table, and hold for the next queries on the
+    /// connection until they're set again.
+    #[must_use]
+    pub fn with_query_settings(self, query_settings: QuerySettings) -> Self {
+        Self {
+            query_settings,
+            ..self
+        }
+    }
+
+    /// Exposes the columns with the names of `column_mapper`, while the SQL of scans uses their
+    /// original names, see [`ColumnMapper`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if two columns are mapped to the same name.
+    pub fn with_column_mapper(self, column_mapper: &ColumnMapper) -> Result<Self> {
+        let (schema, column_mapping) = column_mapper
+            .map_schema(&self.schema)
+            .context(UnableToMapColumnNamesSnafu)?;
+        Ok(Self {
+            schema,
+            column_mapping: (!column_mapping.is_identity()).then(|| Arc::new(column_mapping)),
+            ..self
+        })
+    }
+
+    #[must_use]
+    pub fn column_mapping(&self) -> Option<&ColumnMapping> {
+        self.column_mapping.as_deref()
+    }
+
+    /// Counts the columns that the filters of scans use in `index_advisor`, which recommends
+    /// indexes on the columns that are filtered on the most, see [`IndexAdvisor`].
+    #[must_use]
+    pub fn with_index_advisor(self, index_advisor: Option<Arc<IndexAdvisor>>) -> Self {
+        Self {
+            index_advisor,
+            ..self
+        }
+    }
+
+    /// Reads the rows of `query` instead of the table, see [`SqlTable::from_query`]. The schema of
+    /// the table must be the one of the rows of `query`.
+    #[must_use]
+    pub fn with_query(self, query: impl Into<String>) -> Self {
+        let query: String = query.into();
+        let query = query.trim().trim_end_matches(';');
+        Self {
+            query: Some(Arc::from(query)),
+            ..self
+        }
+    }
+
+    /// The query the table reads from, see [`SqlTable::with_query`].
+    #[must_use]
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    #[must_use]
+    pub fn query_settings(&self) -> &QuerySettings {
+        &self.query_settings
+    }
+
+    /// The context of the federated query that is being created, see
+    /// [`crate::sql::query_context::QueryContextRule`].
+    #[must_use]
+    pub fn current_remote_context(&self) -> Option<RemoteContext> {
+        let context = self.query_context.as_ref().map(RemoteContext::current);
+        let settings = self.session_settings();
+        if settings.is_empty() {
+            return context;
+        }
+        Some(context.unwrap_or_default().with_settings(settings))
+    }
+
+    /// The remote plan of `statement`, if remote explains are enabled.
+    ///
+    /// `EXPLAIN` can't bind parameters, so the plan is of the statement with its literals inline.
+    pub fn remote_plan_of_statement(&self, statement: &ast::Statement) -> Option<RemotePlan>
+    where
+        T: 'static,
+        P: 'static,
+    {
+        self.remote_explainer()
+            .map(|explainer| RemotePlan::new(explainer, statement.to_string()))
+    }
+
+    /// Explains statements on the remote database, if remote explains are enabled.
+    pub fn remote_explainer(&self) -> Option<Arc<dyn RemoteExplainer>>
+    where
+        T: 'static,
+        P: 'static,
+    {
+        self.remote_explainer_with_prefix(String::new())
+    }
+
+    /// Explains statements on the remote database after prepending `prefix` to them.
+    pub(crate) fn remote_explainer_with_prefix(
+        &self,
+        prefix: String,
+    ) -> Option<Arc<dyn RemoteExplainer>>
+    where
+        T: 'static,
+        P: 'static,
+    {
+        if !self.remote_explain {
+            return None;
+        }
+        // a plain EXPLAIN returns the bytecode of the statement in SQLite
+        let statement = match self.name.as_str() {
+            "sqlite" => "EXPLAIN QUERY PLAN",
+            _ => "EXPLAIN",
+        };
+        Some(Arc::new(PoolExplainer::new(
+            Arc::clone(&self.pool),
+            statement,
+            prefix,
+        )))
+    }
+
+    /// The remote plan of `sql`, if remote explains are enabled.
+    pub fn remote_plan(&self, sql: &str) -> Option<RemotePlan>
+    where
+        T: 'static,
+        P: 'static,
+    {
+        self.remote_explainer()
+            .map(|explainer| RemotePlan::new(explainer, sql))
+    }
+
     #[must_use]
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// The metrics of the scans of the plan, shared by its clones.
+    #[must_use]
+    pub fn metrics_set(&self) -> &ExecutionPlanMetricsSet {
+        &self.metrics
+    }
+
+    /// The stream of the batches of `query`, the query of `partition`, which records the metrics
+    /// of the scan, see [`ScanMetrics`].
+    #[must_use]
+    pub fn scan_stream(
+        &self,
+        partition: usize,
+        query: impl Future<Output = DataFusionResult<SendableRecordBatchStream>> + Send + 'static,
+    ) -> SendableRecordBatchStream {
+        ScanMetrics::new(&self.metrics, partition).record_scan(self.schema(), query)
+    }
+
     #[must_use]
     pub fn clone_pool(&self) -> Arc<dyn DbConnectionPool<T, P> + Send + Sync> {
         Arc::clone(&self.pool)
@@ -193,18 +793,28 @@ impl<T, P> TableProvider for SqlTable<T, P> {
     }
 
     fn table_type(&self) -> TableType {
-        TableType::Base
+        if self.query.is_some() {
+            TableType::View
+        } else {
+            TableType::Base
+        }
     }
 
     fn supports_filters_pushdown(
         &self,
         filters: &[&Expr],
     ) -> DataFusionResult<Vec<TableProviderFilterPushDown>> {
+        let schema = DFSchema::try_from(Arc::clone(&self.schema))?;
         let filter_push_down: Vec<TableProviderFilterPushDown> = filters
             .iter()
-            .map(|f| match Unparser::new(self.dialect()).expr_to_sql(f) {
-                Ok(_) => TableProviderFilterPushDown::Exact,
-                Err(_) => TableProviderFilterPushDown::Unsupported,
+            .map(|f| {
+                if !self.pushdown_policy.supports_filter(f) || !supports_regexes(self.regex, f) {
+                    return TableProviderFilterPushDown::Unsupported;
+                }
+                match Unparser::new(self.dialect()).expr_to_sql(f) {
+                    Ok(_) => self.filter_semantics.pushdown(f, &schema),
+                    Err(_) => TableProviderFilterPushDown::Unsupported,
+                }
             })
             .collect();
 
@@ -218,11 +828,94 @@ impl<T, P> TableProvider for SqlTable<T, P> {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        let sql = self.scan_to_sql(projection, filters, limit)?;
-        return self.create_physical_plan(projection, sql);
+        let statement = self.scan_to_statement(projection, filters, limit)?;
+        let remote_plan = self.remote_plan_of_statement(&statement);
+        let bound = self.bind_statement(statement);
+        return self.create_physical_plan(projection, bound, remote_plan);
+    }
+
+    fn statistics(&self) -> Option<Statistics> {
+        self.statistics.clone()
     }
 }
 
+/// Prefixes the references to `table_reference` in `statement` with `ONLY`.
+///
+/// sqlparser has no `ONLY` in its table factors, so the name is replaced by one that is rendered as
+/// is: `ONLY` followed by the parts of the name, which are always quoted so that `ONLY` stays
+/// outside of the identifiers and names that need quoting keep their case.
+pub(crate) fn scan_only(statement: &mut ast::Statement, table_reference: &TableReference) {
+    let table_parts = table_reference.to_vec();
+    let _ = visit_relations_mut(statement, |name| {
+        let parts = name.0.iter().map(|ident| ident.value.as_str());
+        if parts.eq(table_parts.iter().map(String::as_str)) {
+            let quoted = name
+                .0
+                .iter()
+                .map(|ident| ast::Ident::with_quote('"', ident.value.as_str()).to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            *name = ast::ObjectName(vec![ast::Ident::new(format!("ONLY {quoted}"))]);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+/// Replaces the scans of `table_reference` in `statement` with `query` as a derived table, see
+/// [`SqlTable::from_query`].
+///
+/// The query is rendered as is, as the name of the table, like [`scan_only`] does, so that it
+/// doesn't have to be parsed with the dialect of the database. The derived table keeps the alias
+/// of the scan, or is named after the table.
+pub(crate) fn scan_query(
+    statement: &mut ast::Statement,
+    table_reference: &TableReference,
+    query: &str,
+    dialect: &dyn Dialect,
+) {
+    struct ScanQuery<'a> {
+        table_parts: Vec<String>,
+        query: &'a str,
+        dialect: &'a dyn Dialect,
+    }
+
+    impl ast::VisitorMut for ScanQuery<'_> {
+        type Break = ();
+
+        // after the children of the table factor, so that the query isn't visited
+        fn post_visit_table_factor(
+            &mut self,
+            table_factor: &mut ast::TableFactor,
+        ) -> ControlFlow<Self::Break> {
+            if let ast::TableFactor::Table { name, alias, .. } = table_factor {
+                let parts = name.0.iter().map(|ident| ident.value.as_str());
+                if parts.eq(self.table_parts.iter().map(String::as_str)) {
+                    if alias.is_none() {
+                        let table = self.table_parts.last().map_or("", String::as_str);
+                        let mut ident = ast::Ident::new(table);
+                        ident.quote_style = self.dialect.identifier_quote_style(table);
+                        *alias = Some(ast::TableAlias {
+                            name: ident,
+                            columns: Vec::new(),
+                        });
+                    }
+                    *name = ast::ObjectName(vec![ast::Ident::new(format!("({})", self.query))]);
+                }
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let _ = ast::VisitMut::visit(
+        statement,
+        &mut ScanQuery {
+            table_parts: table_reference.to_vec(),
+            query,
+            dialect,
+        },
+    );
+}
+
 impl<T, P> Display for SqlTable<T, P> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "SqlTable {}", self.name)
@@ -257,7 +950,15 @@ pub struct SqlExec<T, P> {
     projected_schema: SchemaRef,
     pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
     sql: String,
+    params: Vec<String>,
+    query_context: Option<QueryContextMode>,
+    session_settings: Vec<(String, String)>,
     properties: PlanProperties,
+    schema_drift: SchemaDriftPolicy,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    remote_plan: Option<RemotePlan>,
+    metrics: ExecutionPlanMetricsSet,
 }
 
 impl<T, P> SqlExec<T, P> {
@@ -273,15 +974,125 @@ impl<T, P> SqlExec<T, P> {
             projected_schema: Arc::clone(&projected_schema),
             pool,
             sql,
+            params: Vec::new(),
+            query_context: None,
+            session_settings: Vec::new(),
             properties: PlanProperties::new(
                 EquivalenceProperties::new(projected_schema),
                 Partitioning::UnknownPartitioning(1),
                 EmissionType::Incremental,
                 Boundedness::Bounded,
             ),
+            schema_drift: SchemaDriftPolicy::default(),
+            spill_buffer: None,
+            memory_wait: None,
+            remote_plan: None,
+            metrics: ExecutionPlanMetricsSet::new(),
         })
     }
 
+    /// Binds `params` to the placeholders of the SQL, see [`SqlTable::with_bind_literals`].
+    #[must_use]
+    pub fn with_params(self, params: Vec<String>) -> Self {
+        Self { params, ..self }
+    }
+
+    #[must_use]
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// Passes the [`QueryContext`] of the session to the database, see
+    /// [`SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            query_context,
+            ..self
+        }
+    }
+
+    /// Sets `settings` on the session before the query, see [`SqlTable::with_query_settings`].
+    #[must_use]
+    pub fn with_session_settings(self, session_settings: Vec<(String, String)>) -> Self {
+        Self {
+            session_settings,
+            ..self
+        }
+    }
+
+    /// The query context of the session of `context` as it's passed to the database.
+    #[must_use]
+    pub fn remote_context(&self, context: &TaskContext) -> Option<RemoteContext> {
+        let remote_context = self.query_context.as_ref().map(|mode| {
+            RemoteContext::new(
+                mode,
+                QueryContext::from_config(context.session_config().options()),
+            )
+        });
+        if self.session_settings.is_empty() {
+            return remote_context;
+        }
+        Some(
+            remote_context
+                .unwrap_or_default()
+                .with_settings(self.session_settings.clone()),
+        )
+    }
+
+    #[must_use]
+    pub fn with_schema_drift(self, schema_drift: SchemaDriftPolicy) -> Self {
+        Self {
+            schema_drift,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn schema_drift(&self) -> SchemaDriftPolicy {
+        self.schema_drift
+    }
+
+    #[must_use]
+    pub fn with_spill_buffer(self, memory_batches: Option<usize>) -> Self {
+        Self {
+            spill_buffer: memory_batches,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn spill_buffer(&self) -> Option<usize> {
+        self.spill_buffer
+    }
+
+    #[must_use]
+    pub fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            memory_wait: wait,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn memory_wait(&self) -> Option<Duration> {
+        self.memory_wait
+    }
+
+    /// Shows `remote_plan`, the plan of the remote database for the SQL, in the `EXPLAIN` output.
+    #[must_use]
+    pub fn with_remote_plan(self, remote_plan: Option<RemotePlan>) -> Self {
+        Self {
+            remote_plan,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn remote_plan(&self) -> Option<&RemotePlan> {
+        self.remote_plan.as_ref()
+    }
+
     #[must_use]
     pub fn clone_pool(&self) -> Arc<dyn DbConnectionPool<T, P> + Send + Sync> {
         Arc::clone(&self.pool)
@@ -290,6 +1101,14 @@ impl<T, P> SqlExec<T, P> {
     pub fn sql(&self) -> Result<String> {
         Ok(self.sql.clone())
     }
+
+    /// Writes the remote plan after the SQL in the display of an execution plan.
+    pub(crate) fn fmt_remote_plan(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
+        match &self.remote_plan {
+            Some(remote_plan) => write!(f, " {remote_plan}"),
+            None => Ok(()),
+        }
+    }
 }
 
 impl<T, P> std::fmt::Debug for SqlExec<T, P> {
@@ -302,7 +1121,8 @@ impl<T, P> std::fmt::Debug for SqlExec<T, P> {
 impl<T, P> DisplayAs for SqlExec<T, P> {
     fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> std::fmt::Result {
         let sql = self.sql().unwrap_or_default();
-        write!(f, "SqlExec sql={sql}")
+        write!(f, "SqlExec sql={sql}")?;
+        self.fmt_remote_plan(f)
     }
 }
 
@@ -334,40 +1154,238 @@ impl<T: 'static, P: 'static> ExecutionPlan for SqlExec<T, P> {
         Ok(self)
     }
 
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
     fn execute(
         &self,
-        _partition: usize,
-        _context: Arc<TaskContext>,
+        partition: usize,
+        context: Arc<TaskContext>,
     ) -> DataFusionResult<SendableRecordBatchStream> {
         let sql = self.sql().map_err(to_execution_error)?;
         tracing::debug!("SqlExec sql: {sql}");
 
-        let schema = self.schema();
-
-        let fut = get_stream(Arc::clone(&self.pool), sql, Arc::clone(&schema));
-
-        let stream = futures::stream::once(fut).try_flatten();
-        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+        let fut = get_stream_with_params(
+            Arc::clone(&self.pool),
+            sql,
+            self.params.clone(),
+            self.remote_context(&context),
+            self.schema(),
+            self.schema_drift,
+        );
+
+        Ok(limit_stream_memory(
+            self.scan_stream(partition, fut),
+            &context,
+            self.spill_buffer,
+            self.memory_wait,
+            format!("SqlExec[{partition}]"),
+        ))
     }
 }
 
+/// The schema of the rows of `query`, from the first of them, see [`SqlTable::from_query`].
+///
+/// # Errors
+///
+/// Returns an error if the query fails, or if it returns no columns, which the databases that
+/// only know the types of the columns from the rows do when it returns no rows.
+pub async fn get_query_schema<T, P>(
+    pool: &Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+    query: &str,
+) -> Result<SchemaRef> {
+    let conn = pool
+        .connect()
+        .await
+        .context(UnableToGetConnectionFromPoolSnafu)?;
+    let query = query.trim().trim_end_matches(';');
+    let stream = query_arrow(
+        conn,
+        format!("SELECT * FROM ({query}) AS query_schema LIMIT 1"),
+        None,
+    )
+    .await
+    .context(UnableToGetQuerySchemaSnafu)?;
+    let schema = stream.schema();
+    ensure!(!schema.fields().is_empty(), EmptyQuerySchemaSnafu);
+    Ok(schema)
+}
+
 pub async fn get_stream<T: 'static, P: 'static>(
     pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
     sql: String,
     projected_schema: SchemaRef,
+    schema_drift: SchemaDriftPolicy,
 ) -> DataFusionResult<SendableRecordBatchStream> {
-    let conn = pool.connect().await.map_err(to_execution_error)?;
+    get_stream_with_params(pool, sql, Vec::new(), None, projected_schema, schema_drift).await
+}
 
-    query_arrow(conn, sql, Some(projected_schema))
+/// Like [`get_stream`], with `params` bound to the placeholders of `sql` and the query context
+/// passed to the database with the query.
+///
+/// The comment of [`crate::sql::query_attribution::set_query_attribution`] is prepended to `sql`
+/// when this is called, on the thread that creates the query, rather than when the query runs.
+///
+/// The query runs in a `remote_query` span, a child of the current span that lasts until the
+/// stream is dropped, which the `tracing-opentelemetry` layer of an application exports as the
+/// client span of the query.
+pub fn get_stream_with_params<T: 'static, P: 'static>(
+    pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+    sql: String,
+    params: Vec<String>,
+    context: Option<RemoteContext>,
+    projected_schema: SchemaRef,
+    schema_drift: SchemaDriftPolicy,
+) -> impl Future<Output = DataFusionResult<SendableRecordBatchStream>> {
+    let span = tracing::info_span!("remote_query", db.statement = %sql, otel.kind = "client");
+    // the comment is made in the span of the query, for its traceparent
+    let sql = span.in_scope(|| attribute_sql(&sql));
+    let query_span = span.clone();
+    let stream = async move {
+        let context = context.as_ref();
+        let stream = query_stream(&pool, &sql, &params, context, &projected_schema).await?;
+        let stream = match schema_drift {
+            SchemaDriftPolicy::Ignore => stream,
+            SchemaDriftPolicy::Restart => {
+                restart_on_drift(stream, &pool, &sql, &params, context, &projected_schema).await?
+            }
+            policy => adapt_stream(stream, &projected_schema, policy),
+        };
+        if !has_dictionary_fields(&projected_schema) {
+            return Ok(stream);
+        }
+
+        // remote databases return the columns that are only dictionary-encoded locally as plain strings
+        let schema = Arc::clone(&projected_schema);
+        let stream = stream.map(move |batch| {
+            encode_dictionaries(batch?, &projected_schema).map_err(DataFusionError::from)
+        });
+        let stream: SendableRecordBatchStream =
+            Box::pin(RecordBatchStreamAdapter::new(schema, stream));
+        DataFusionResult::Ok(stream)
+    }
+    .instrument(query_span);
+    async move { Ok(hold_span(stream.await?, span)) }
+}
+
+/// `stream`, which keeps `span` open until it's dropped.
+fn hold_span(stream: SendableRecordBatchStream, span: tracing::Span) -> SendableRecordBatchStream {
+    let schema = stream.schema();
+    let stream = stream.map(move |batch| {
+        let _ = &span;
+        batch
+    });
+    Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+}
+
+async fn query_stream<T: 'static, P: 'static>(
+    pool: &Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+    sql: &str,
+    params: &[String],
+    context: Option<&RemoteContext>,
+    projected_schema: &SchemaRef,
+) -> DataFusionResult<SendableRecordBatchStream> {
+    let permit = pool
+        .acquire_query_permit()
         .await
-        .map_err(to_execution_error)
+        .map_err(to_execution_error)?;
+    let conn = pool.connect_read_only().await.map_err(to_execution_error)?;
+
+    let mut sql = sql.to_string();
+    if let Some(context) = context {
+        if let Some(variables) = context.session_variables() {
+            let Some(async_conn) = conn.as_async() else {
+                return Err(to_execution_error(
+                    db_connection_pool::dbconnection::Error::UnsupportedSessionVariables {},
+                ));
+            };
+            async_conn
+                .set_session_variables(&variables)
+                .await
+                .map_err(to_execution_error)?;
+        }
+        sql = context.prefix_sql(&sql);
+    }
+
+    let stream = query_arrow_with_string_params(
+        conn,
+        sql.clone(),
+        params,
+        Some(Arc::clone(projected_schema)),
+    )
+    .await
+    .map_err(|e| to_query_error(e, &sql))?;
+    Ok(match permit {
+        Some(permit) => permit.hold(stream),
+        None => stream,
+    })
+}
+
+fn adapt_stream(
+    stream: SendableRecordBatchStream,
+    projected_schema: &SchemaRef,
+    policy: SchemaDriftPolicy,
+) -> SendableRecordBatchStream {
+    let schema = stream.schema();
+    let projected_schema = Arc::clone(projected_schema);
+    let stream = stream.map(move |batch| {
+        adapt_batch(batch?, &projected_schema, policy)
+            .map_err(|e| DataFusionError::External(Box::new(e)))
+    });
+    Box::pin(RecordBatchStreamAdapter::new(schema, stream))
 }
 
+/// Runs the query again if its first batch doesn't match the projected schema. Nothing has been
+/// returned yet at that point, so restarting can't duplicate rows.
+async fn restart_on_drift<T: 'static, P: 'static>(
+    mut stream: SendableRecordBatchStream,
+    pool: &Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+    sql: &str,
+    params: &[String],
+    context: Option<&RemoteContext>,
+    projected_schema: &SchemaRef,
+) -> DataFusionResult<SendableRecordBatchStream> {
+    let first = match stream.next().await {
+        Some(batch) => batch?,
+        None => return Ok(stream),
+    };
+
+    if let Some(changes) = schema_changes(projected_schema, &first) {
+        tracing::warn!("Restarting the query, the schema of the remote table changed: {changes}");
+        drop(stream);
+        let stream = query_stream(pool, sql, params, context, projected_schema).await?;
+        return Ok(adapt_stream(
+            stream,
+            projected_schema,
+            SchemaDriftPolicy::Fail,
+        ));
+    }
+
+    let schema = stream.schema();
+    let stream = futures::stream::once(async { Ok(first) }).chain(stream);
+    Ok(adapt_stream(
+        Box::pin(RecordBatchStreamAdapter::new(schema, stream)),
+        projected_schema,
+        SchemaDriftPolicy::Fail,
+    ))
+}
+
+/// An execution error with the message of `e`, redacted as set by
+/// [`crate::util::redact::set_error_redaction`].
 #[allow(clippy::needless_pass_by_value)]
 pub fn to_execution_error(
     e: impl Into<Box<dyn std::error::Error + Send + Sync>>,
 ) -> DataFusionError {
-    DataFusionError::Execution(format!("{}", e.into()).to_string())
+    DataFusionError::Execution(error_redaction().redact(&e.into().to_string(), None))
+}
+
+/// Like [`to_execution_error`] for the error of `sql`, which is redacted from the message as well.
+fn to_query_error(
+    e: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    sql: &str,
+) -> DataFusionError {
+    DataFusionError::Execution(error_redaction().redact(&e.into().to_string(), Some(sql)))
 }
 
 #[cfg(test)]
@@ -394,7 +1412,7 @@ mod tests {
 
         use async_trait::async_trait;
         use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
-        use datafusion::sql::unparser::dialect::{Dialect, SqliteDialect};
+        use datafusion::sql::unparser::dialect::{Dialect, PostgreSqlDialect, SqliteDialect};
         use datafusion::{
             logical_expr::{col, lit},
             sql::TableReference,
@@ -485,6 +1503,214 @@ mod tests {
             );
             Ok(())
         }
+
+        #[tokio::test]
+        async fn test_sql_to_string_with_only() -> Result<(), Box<dyn Error + Send + Sync>> {
+            let sql_table = new_sql_table("public.events", Some(Arc::new(PostgreSqlDialect {})))?
+                .with_only(true);
+            let result = sql_table.scan_to_sql(None, &[], Some(10))?;
+            assert_eq!(result, r#"SELECT * FROM ONLY "public"."events" LIMIT 10"#);
+
+            let sql_table =
+                new_sql_table(r#"public."Events""#, Some(Arc::new(PostgreSqlDialect {})))?
+                    .with_only(true);
+            let result = sql_table.scan_to_sql(None, &[], Some(10))?;
+            assert_eq!(result, r#"SELECT * FROM ONLY "public"."Events" LIMIT 10"#);
+            Ok(())
+        }
+
+        #[test]
+        fn test_pushdown_policy() -> Result<(), Box<dyn Error + Send + Sync>> {
+            use crate::sql::pushdown::{FilterPushdown, PushdownPolicy};
+            use datafusion::datasource::TableProvider;
+            use datafusion::logical_expr::{Operator, TableProviderFilterPushDown};
+            use std::collections::HashSet;
+
+            let filters = [
+                col("age").eq(lit(30)),
+                col("name").like(lit("a%")),
+                col("age").gt(lit(30)),
+            ];
+            let filter_refs = filters.iter().collect::<Vec<_>>();
+
+            let sql_table = new_sql_table("users", None)?;
+            assert_eq!(
+                sql_table.supports_filters_pushdown(&filter_refs)?,
+                vec![TableProviderFilterPushDown::Exact; 3]
+            );
+
+            let sql_table = sql_table.with_pushdown_policy(
+                PushdownPolicy::new()
+                    .with_filters(FilterPushdown::Operators(HashSet::from([Operator::Eq])))
+                    .with_limit(false),
+            );
+            assert_eq!(
+                sql_table.supports_filters_pushdown(&filter_refs)?,
+                vec![
+                    TableProviderFilterPushDown::Exact,
+                    TableProviderFilterPushDown::Exact,
+                    TableProviderFilterPushDown::Unsupported,
+                ]
+            );
+            assert_eq!(
+                sql_table.scan_to_sql(None, &[], Some(10))?,
+                "SELECT * FROM users"
+            );
+
+            let sql_table = sql_table
+                .with_pushdown_policy(PushdownPolicy::new().with_filters(FilterPushdown::None));
+            assert_eq!(
+                sql_table.supports_filters_pushdown(&filter_refs)?,
+                vec![TableProviderFilterPushDown::Unsupported; 3]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_in_list_and_between_pushdown() -> Result<(), Box<dyn Error + Send + Sync>> {
+            use crate::sql::pushdown::PushdownPolicy;
+            use datafusion::datasource::TableProvider;
+            use datafusion::logical_expr::TableProviderFilterPushDown;
+
+            let in_list = col("age").in_list((0..2000).map(|age| lit(age as i16)).collect(), false);
+            let between = col("age").between(lit(18_i16), lit(65_i16));
+
+            let sql_table = new_sql_table("users", Some(Arc::new(SqliteDialect {})))?
+                .with_pushdown_policy(PushdownPolicy::new().with_max_in_list_size(Some(999)));
+            assert_eq!(
+                sql_table.supports_filters_pushdown(&[&in_list, &between])?,
+                vec![TableProviderFilterPushDown::Exact; 2]
+            );
+
+            let sql = sql_table.scan_to_sql(None, &[in_list, between], None)?;
+            assert_eq!(sql.matches("`users`.`age` IN (").count(), 3);
+            assert!(sql.starts_with("SELECT * FROM `users` WHERE ((`users`.`age` IN (0, 1, 2, "));
+            assert!(sql.contains(" 998) OR `users`.`age` IN (999, 1000, "));
+            assert!(sql.ends_with(
+                " 1997) OR `users`.`age` IN (1998, 1999)) AND (`users`.`age` BETWEEN 18 AND 65))"
+            ));
+            Ok(())
+        }
+
+        #[test]
+        fn test_bind_literals() -> Result<(), Box<dyn Error + Send + Sync>> {
+            let filters = vec![col("name").eq(lit("O'Brien")).and(col("age").gt(lit(30)))];
+            let sql_table =
+                new_sql_table("users", Some(Arc::new(SqliteDialect {})))?.with_bind_literals(true);
+
+            // the placeholders depend on the database, which isn't known for this table
+            let statement = sql_table.scan_to_statement(Some(&vec![0]), &filters, None)?;
+            let bound = sql_table.bind_statement(statement);
+            assert!(bound.params.is_empty());
+            assert!(bound.sql.contains("(`users`.`name` = 'O''Brien')"));
+
+            let sql_table = SqlTable {
+                name: "sqlite".to_string(),
+                ..sql_table
+            };
+            let statement = sql_table.scan_to_statement(Some(&vec![0]), &filters, None)?;
+            let bound = sql_table.bind_statement(statement);
+            assert_eq!(
+                bound.sql,
+                r#"SELECT `users`.`name` FROM `users` WHERE ((`users`.`name` = ?1) AND (`users`.`age` > 30))"#
+            );
+            assert_eq!(bound.params, vec!["O'Brien".to_string()]);
+            Ok(())
+        }
+
+        #[test]
+        fn test_query_settings() -> Result<(), Box<dyn Error + Send + Sync>> {
+            let sql_table = new_sql_table("users", Some(Arc::new(PostgreSqlDialect {})))?
+                .with_query_settings(QuerySettings::new().with_setting("work_mem", "256MB"));
+
+            // the syntax of the settings depends on the database
+            let statement = sql_table.scan_to_statement(Some(&vec![0]), &[], None)?;
+            let bound = sql_table.bind_statement(statement.clone());
+            assert_eq!(bound.sql, r#"SELECT "users"."name" FROM "users""#);
+            assert!(sql_table.current_remote_context().is_none());
+
+            let mysql_table = SqlTable {
+                name: "mysql".to_string(),
+                ..new_sql_table("users", Some(Arc::new(PostgreSqlDialect {})))?
+            }
+            .with_query_settings(QuerySettings::new().with_setting("sort_buffer_size", "16777216"));
+            let bound = mysql_table.bind_statement(statement.clone());
+            assert_eq!(
+                bound.sql,
+                r#"SELECT /*+ SET_VAR(sort_buffer_size = 16777216) */ "users"."name" FROM "users""#
+            );
+
+            let sql_table = SqlTable {
+                name: "postgres".to_string(),
+                ..sql_table
+            };
+            let bound = sql_table.bind_statement(statement);
+            assert_eq!(bound.sql, r#"SELECT "users"."name" FROM "users""#);
+            assert_eq!(
+                sql_table
+                    .current_remote_context()
+                    .and_then(|context| context.session_variables()),
+                Some(vec![("work_mem".to_string(), "256MB".to_string())])
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_column_mapper() -> Result<(), Box<dyn Error + Send + Sync>> {
+            use crate::sql::column_mapper::ColumnMapper;
+            use datafusion::datasource::TableProvider;
+
+            let sql_table = new_sql_table("users", Some(Arc::new(PostgreSqlDialect {})))?
+                .with_column_mapper(&ColumnMapper::new().with_snake_case(true))?;
+            assert_eq!(sql_table.schema().field(2).name(), "created_date");
+
+            // the SQL uses the names of the columns in the database
+            let filters = vec![col("user_id").eq(lit("x"))];
+            assert_eq!(
+                sql_table.scan_to_sql(Some(&vec![0, 2]), &filters, None)?,
+                r#"SELECT "users"."name", "users"."createdDate" AS "created_date" FROM "users" WHERE ("users"."userId" = 'x')"#
+            );
+            assert_eq!(
+                sql_table.scan_to_sql(None, &[], Some(1))?,
+                r#"SELECT "name", "age", "createdDate" AS "created_date", "userId" AS "user_id", "active", "5e48" FROM "users" LIMIT 1"#
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_query() -> Result<(), Box<dyn Error + Send + Sync>> {
+            use datafusion::datasource::{TableProvider, TableType};
+
+            let sql_table = new_sql_table("users", Some(Arc::new(PostgreSqlDialect {})))?
+                .with_query("SELECT * FROM accounts WHERE active;\n");
+            assert_eq!(
+                sql_table.query(),
+                Some("SELECT * FROM accounts WHERE active")
+            );
+            assert_eq!(sql_table.table_type(), TableType::View);
+
+            let filters = vec![col("age").gt(lit(30))];
+            assert_eq!(
+                sql_table.scan_to_sql(Some(&vec![0]), &filters, Some(10))?,
+                r#"SELECT "users"."name" FROM (SELECT * FROM accounts WHERE active) AS "users" WHERE ("users"."age" > 30) LIMIT 10"#
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_aggregate_to_sql() -> Result<(), Box<dyn Error + Send + Sync>> {
+            use datafusion::functions_aggregate::expr_fn::{count, max};
+
+            let sql_table = new_sql_table("users", Some(Arc::new(PostgreSqlDialect {})))?;
+            assert_eq!(
+                sql_table.aggregate_to_sql(vec![
+                    count(lit(1)).alias("row_count"),
+                    max(col("age")).alias("watermark")
+                ])?,
+                r#"SELECT count(1) AS "row_count", max("users"."age") AS "watermark" FROM "users""#
+            );
+            Ok(())
+        }
     }
 
     #[test]
@@ -501,6 +1727,8 @@ mod tests {
             DuckDBSyncParameter, DuckDbConnection,
         };
         use crate::sql::db_connection_pool::{duckdbpool::DuckDbConnectionPool, DbConnectionPool};
+        use crate::sql::schema_drift::SchemaDriftPolicy;
+        use datafusion::arrow::datatypes::DataType;
         use duckdb::DuckdbConnectionManager;
 
         #[tokio::test]
@@ -572,5 +1800,209 @@ mod tests {
             drop(t);
             Ok(())
         }
+
+        #[tokio::test]
+        async fn test_duckdb_table_dictionary_columns() -> Result<(), Box<dyn Error + Send + Sync>>
+        {
+            let ctx = SessionContext::new();
+            let pool: Arc<
+                dyn DbConnectionPool<
+                        r2d2::PooledConnection<DuckdbConnectionManager>,
+                        Box<dyn DuckDBSyncParameter>,
+                    > + Send
+                    + Sync,
+            > = Arc::new(DuckDbConnectionPool::new_memory()?);
+            let conn = pool.connect().await?;
+            let db_conn = conn
+                .as_any()
+                .downcast_ref::<DuckDbConnection>()
+                .expect("Unable to downcast to DuckDbConnection");
+            db_conn.conn.execute_batch(
+                "CREATE TABLE test (a INTEGER, b VARCHAR); INSERT INTO test VALUES (1, 'bar'), (2, 'bar');",
+            )?;
+            let duckdb_table = SqlTable::new("duckdb", &pool, "test")
+                .await?
+                .with_dictionary_columns(&["b".to_string()]);
+            ctx.register_table("test_datafusion", Arc::new(duckdb_table))?;
+
+            let batches = ctx
+                .sql("SELECT b FROM test_datafusion")
+                .await?
+                .collect()
+                .await?;
+            assert_eq!(
+                batches[0].schema().field(0).data_type(),
+                &DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_duckdb_table_schema_drift() -> Result<(), Box<dyn Error + Send + Sync>> {
+            let ctx = SessionContext::new();
+            let pool: Arc<
+                dyn DbConnectionPool<
+                        r2d2::PooledConnection<DuckdbConnectionManager>,
+                        Box<dyn DuckDBSyncParameter>,
+                    > + Send
+                    + Sync,
+            > = Arc::new(DuckDbConnectionPool::new_memory()?);
+            let conn = pool.connect().await?;
+            let db_conn = conn
+                .as_any()
+                .downcast_ref::<DuckDbConnection>()
+                .expect("Unable to downcast to DuckDbConnection");
+            db_conn.conn.execute_batch(
+                "CREATE TABLE test (a INTEGER, b VARCHAR); INSERT INTO test VALUES (1, 'foo'), (2, 'bar');",
+            )?;
+            for (name, schema_drift) in [
+                ("test_cast", SchemaDriftPolicy::Cast),
+                ("test_fail", SchemaDriftPolicy::Fail),
+            ] {
+                let table = SqlTable::new("duckdb", &pool, "test").await?;
+                ctx.register_table(name, Arc::new(table.with_schema_drift(schema_drift)))?;
+            }
+
+            db_conn
+                .conn
+                .execute_batch("ALTER TABLE test ALTER a TYPE BIGINT")?;
+
+            let batches = ctx.sql("SELECT a FROM test_cast").await?.collect().await?;
+            assert_eq!(batches[0].schema().field(0).data_type(), &DataType::Int32);
+
+            let error = ctx
+                .sql("SELECT a FROM test_fail")
+                .await?
+                .collect()
+                .await
+                .expect_err("schema drift");
+            assert!(
+                error
+                    .to_string()
+                    .contains("column 'a' changed from Int32 to Int64"),
+                "{error}"
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_duckdb_table_spill_buffer() -> Result<(), Box<dyn Error + Send + Sync>> {
+            let ctx = SessionContext::new();
+            let pool: Arc<
+                dyn DbConnectionPool<
+                        r2d2::PooledConnection<DuckdbConnectionManager>,
+                        Box<dyn DuckDBSyncParameter>,
+                    > + Send
+                    + Sync,
+            > = Arc::new(DuckDbConnectionPool::new_memory()?);
+            let conn = pool.connect().await?;
+            let db_conn = conn
+                .as_any()
+                .downcast_ref::<DuckDbConnection>()
+                .expect("Unable to downcast to DuckDbConnection");
+            db_conn
+                .conn
+                .execute_batch("CREATE TABLE test AS SELECT * FROM range(10000)")?;
+            let table = SqlTable::new("duckdb", &pool, "test")
+                .await?
+                .with_spill_buffer(Some(0));
+            ctx.register_table("test", Arc::new(table))?;
+
+            let batches = ctx.sql("SELECT * FROM test").await?.collect().await?;
+            let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows, 10000);
+            Ok(())
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn test_duckdb_table_remote_explain() -> Result<(), Box<dyn Error + Send + Sync>> {
+            let ctx = SessionContext::new();
+            let pool: Arc<
+                dyn DbConnectionPool<
+                        r2d2::PooledConnection<DuckdbConnectionManager>,
+                        Box<dyn DuckDBSyncParameter>,
+                    > + Send
+                    + Sync,
+            > = Arc::new(DuckDbConnectionPool::new_memory()?);
+            let conn = pool.connect().await?;
+            let db_conn = conn
+                .as_any()
+                .downcast_ref::<DuckDbConnection>()
+                .expect("Unable to downcast to DuckDbConnection");
+            db_conn
+                .conn
+                .execute_batch("CREATE TABLE test AS SELECT * FROM range(10)")?;
+            let table = SqlTable::new("duckdb", &pool, "test")
+                .await?
+                .with_remote_explain(true);
+            ctx.register_table("test", Arc::new(table))?;
+
+            let batches = ctx
+                .sql("EXPLAIN SELECT * FROM test WHERE range > 5")
+                .await?
+                .collect()
+                .await?;
+            let plan =
+                datafusion::arrow::util::pretty::pretty_format_batches(&batches)?.to_string();
+            assert!(plan.contains("remote_plan=["), "{plan}");
+            assert!(plan.contains("SEQ_SCAN"), "{plan}");
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_duckdb_table_query_context() -> Result<(), Box<dyn Error + Send + Sync>> {
+            use crate::sql::query_context::{QueryContext, QueryContextMode};
+            use datafusion::prelude::SessionConfig;
+
+            let config = SessionConfig::new()
+                .with_option_extension(QueryContext::new().with_variable("app.tenant_id", "42"));
+            let ctx = SessionContext::new_with_config(config);
+            let pool: Arc<
+                dyn DbConnectionPool<
+                        r2d2::PooledConnection<DuckdbConnectionManager>,
+                        Box<dyn DuckDBSyncParameter>,
+                    > + Send
+                    + Sync,
+            > = Arc::new(DuckDbConnectionPool::new_memory()?);
+            let conn = pool.connect().await?;
+            let db_conn = conn
+                .as_any()
+                .downcast_ref::<DuckDbConnection>()
+                .expect("Unable to downcast to DuckDbConnection");
+            db_conn
+                .conn
+                .execute_batch("CREATE TABLE test AS SELECT * FROM range(10)")?;
+            let table = SqlTable::new("duckdb", &pool, "test")
+                .await?
+                .with_query_context(Some(QueryContextMode::Comment));
+            ctx.register_table("test_comment", Arc::new(table))?;
+            let table = SqlTable::new("duckdb", &pool, "test")
+                .await?
+                .with_query_context(Some(QueryContextMode::SessionVariables(vec![
+                    "app.tenant_id".to_string(),
+                ])));
+            ctx.register_table("test_variables", Arc::new(table))?;
+
+            let batches = ctx
+                .sql("SELECT * FROM test_comment")
+                .await?
+                .collect()
+                .await?;
+            let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(rows, 10);
+
+            // DuckDB has no session variables that policies could read
+            let result = ctx
+                .sql("SELECT * FROM test_variables")
+                .await?
+                .collect()
+                .await;
+            let error = result.expect_err("session variables are unsupported");
+            assert!(
+                error.to_string().contains("can't set session variables"),
+                "{error}"
+            );
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,152 @@
+//! Keeping the sorts by strings local for the remote databases that order strings by a collation,
+//! like MySQL with `utf8mb4_general_ci`, instead of by their bytes like DataFusion.
+//!
+//! The federation optimizer pushes down the sorts of the sub-plans it federates. [`SortPushdown`]
+//! runs before it, and federates the input of each sort by a string whose tables order strings by
+//! a collation, see
+//! [`FilterSemantics::with_collated_ordering`](crate::sql::pushdown::FilterSemantics::with_collated_ordering),
+//! so that the rows are sorted locally and the rest of the plan is still federated. It's one of
+//! the rules of [`federation_optimizer_rules`](super::federation::federation_optimizer_rules).
+
+use std::sync::Arc;
+
+use datafusion::{
+    common::tree_node::Transformed,
+    error::Result as DataFusionResult,
+    logical_expr::{LogicalPlan, Sort},
+    optimizer::{optimizer::ApplyOrder, OptimizerConfig, OptimizerRule},
+};
+
+use super::federation::{
+    can_federate_whole, federate, sole_provider, sorts_strings, supports_string_sorts,
+};
+
+/// Federates the inputs of the sorts by strings whose tables don't order strings like DataFusion,
+/// see the [module documentation](self).
+#[derive(Debug, Default)]
+pub struct SortPushdown {}
+
+impl SortPushdown {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OptimizerRule for SortPushdown {
+    fn name(&self) -> &str {
+        "sort_pushdown"
+    }
+
+    fn apply_order(&self) -> Option<ApplyOrder> {
+        Some(ApplyOrder::TopDown)
+    }
+
+    fn supports_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(
+        &self,
+        plan: LogicalPlan,
+        config: &dyn OptimizerConfig,
+    ) -> DataFusionResult<Transformed<LogicalPlan>> {
+        let LogicalPlan::Sort(sort) = plan else {
+            return Ok(Transformed::no(plan));
+        };
+        // the sorts of the input are kept local when the rule reaches them
+        if !sorts_strings(&sort)
+            || supports_string_sorts(&sort.input)
+            || !can_federate_whole(&sort.input)
+        {
+            return Ok(Transformed::no(LogicalPlan::Sort(sort)));
+        }
+        let Some(provider) = sole_provider(&sort.input)? else {
+            return Ok(Transformed::no(LogicalPlan::Sort(sort)));
+        };
+
+        tracing::debug!(
+            "Keeping a sort by strings local, '{}' orders strings by a collation",
+            provider.name()
+        );
+        federate(Arc::unwrap_or_clone(sort.input), &provider, config)?.map_data(|input| {
+            Ok(LogicalPlan::Sort(Sort {
+                expr: sort.expr,
+                input: Arc::new(input),
+                fetch: sort.fetch,
+            }))
+        })
+    }
+}
+
+#[cfg(all(test, feature = "duckdb-federation"))]
+mod tests {
+    use datafusion::{
+        arrow::datatypes::{DataType, Field, Schema},
+        execution::SessionStateBuilder,
+        prelude::SessionContext,
+    };
+    use datafusion_federation::FederatedQueryPlanner;
+
+    use crate::{
+        duckdb::DuckDBTable,
+        sql::{
+            db_connection_pool::{
+                dbconnection::duckdbconn::DuckDBSyncParameter, duckdbpool::DuckDbConnectionPool,
+                DbConnectionPool,
+            },
+            pushdown::FilterSemantics,
+            sql_provider_datafusion::{dry_run::dry_run, federation::federation_optimizer_rules},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sort_pushdown() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // the table doesn't exist, the queries are only planned
+        let pool: Arc<
+            dyn DbConnectionPool<
+                    r2d2::PooledConnection<duckdb::DuckdbConnectionManager>,
+                    Box<dyn DuckDBSyncParameter>,
+                > + Send
+                + Sync,
+        > = Arc::new(DuckDbConnectionPool::new_memory()?);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("region", DataType::Utf8, true),
+        ]));
+
+        for collated_ordering in [false, true] {
+            let state = SessionStateBuilder::new()
+                .with_optimizer_rules(federation_optimizer_rules())
+                .with_query_planner(Arc::new(FederatedQueryPlanner::new()))
+                .with_default_features()
+                .build();
+            let ctx = SessionContext::new_with_state(state);
+            let table =
+                DuckDBTable::new_with_schema(&pool, Arc::clone(&schema), "orders", None, None)
+                    .with_filter_semantics(
+                        FilterSemantics::exact().with_collated_ordering(collated_ordering),
+                    );
+            ctx.register_table(
+                "orders",
+                Arc::new(Arc::new(table).create_federated_table_provider()?),
+            )?;
+
+            let sql = dry_run(
+                &ctx.state(),
+                "SELECT id, region FROM orders WHERE id > 5 ORDER BY region",
+            )
+            .await?;
+            assert_eq!(sql.len(), 1, "{sql:?}");
+            assert_eq!(sql[0].contains("ORDER BY"), !collated_ordering, "{sql:?}");
+            assert!(sql[0].contains("> 5"), "{sql:?}");
+
+            // the sorts by other types are pushed down
+            let sql = dry_run(&ctx.state(), "SELECT id, region FROM orders ORDER BY id").await?;
+            assert!(sql[0].contains("ORDER BY"), "{sql:?}");
+        }
+        Ok(())
+    }
+}
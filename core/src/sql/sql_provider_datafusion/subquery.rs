@@ -0,0 +1,154 @@
+//! Pushing down whole queries with `EXISTS`, `IN` and scalar subqueries when all the tables they
+//! read are federated to the same remote database.
+//!
+//! DataFusion rewrites subqueries into joins before the federation optimizer runs. The correlated
+//! subqueries that it can't rewrite stay in their filters, and some rewrites, like the mark joins
+//! of an `EXISTS` under an `OR`, can't be written as SQL, so the federation of these queries
+//! breaks up into scans of the tables that are joined locally. [`SubqueryFederation`] runs before
+//! these rewrites, and federates the sub-plans with subqueries as they are written. It's one of
+//! the rules of
+//! [`federation_optimizer_rules`](super::federation::federation_optimizer_rules):
+//!
+//! ```rust,ignore
+//! let state = SessionStateBuilder::new()
+//!     .with_optimizer_rules(federation_optimizer_rules())
+//!     .with_query_planner(Arc::new(FederatedQueryPlanner::new()))
+//!     .with_default_features()
+//!     .build();
+//! ```
+
+use datafusion::{
+    common::tree_node::{Transformed, TreeNode},
+    error::Result as DataFusionResult,
+    logical_expr::{Expr, LogicalPlan},
+    optimizer::{optimizer::ApplyOrder, OptimizerConfig, OptimizerRule},
+};
+
+use super::federation::{can_federate_whole, federate, sole_provider};
+
+/// Federates the largest sub-plans with subqueries whose tables, including those of the
+/// subqueries, are all federated to the same remote database, see the
+/// [module documentation](self). The other sub-plans are left to the federation optimizer.
+#[derive(Debug, Default)]
+pub struct SubqueryFederation {}
+
+impl SubqueryFederation {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OptimizerRule for SubqueryFederation {
+    fn name(&self) -> &str {
+        "federate_subqueries"
+    }
+
+    fn apply_order(&self) -> Option<ApplyOrder> {
+        Some(ApplyOrder::TopDown)
+    }
+
+    fn supports_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(
+        &self,
+        plan: LogicalPlan,
+        config: &dyn OptimizerConfig,
+    ) -> DataFusionResult<Transformed<LogicalPlan>> {
+        if !has_subquery(&plan) {
+            return Ok(Transformed::no(plan));
+        }
+        let Some(provider) = sole_provider(&plan)? else {
+            return Ok(Transformed::no(plan));
+        };
+        if !can_federate_whole(&plan) {
+            return Ok(Transformed::no(plan));
+        }
+
+        tracing::debug!("Federating a plan with subqueries to '{}'", provider.name());
+        federate(plan, &provider, config)
+    }
+}
+
+/// Whether a node of `plan` has a subquery in its expressions.
+fn has_subquery(plan: &LogicalPlan) -> bool {
+    plan.exists(|node| {
+        Ok(node.expressions().iter().any(|expr| {
+            expr.exists(|expr| {
+                Ok(matches!(
+                    expr,
+                    Expr::Exists(_) | Expr::InSubquery(_) | Expr::ScalarSubquery(_)
+                ))
+            })
+            .unwrap_or(false)
+        }))
+    })
+    .unwrap_or(false)
+}
+
+#[cfg(all(test, feature = "duckdb-federation"))]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::{
+        arrow::datatypes::{DataType, Field, Schema},
+        execution::SessionStateBuilder,
+        prelude::SessionContext,
+    };
+    use datafusion_federation::FederatedQueryPlanner;
+
+    use super::*;
+    use crate::{
+        duckdb::DuckDBTable,
+        sql::{
+            db_connection_pool::{
+                dbconnection::duckdbconn::DuckDBSyncParameter, duckdbpool::DuckDbConnectionPool,
+                DbConnectionPool,
+            },
+            sql_provider_datafusion::{dry_run::dry_run, federation::federation_optimizer_rules},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_subquery_federation() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // the tables don't exist, the queries are only planned
+        let pool: Arc<
+            dyn DbConnectionPool<
+                    r2d2::PooledConnection<duckdb::DuckdbConnectionManager>,
+                    Box<dyn DuckDBSyncParameter>,
+                > + Send
+                + Sync,
+        > = Arc::new(DuckDbConnectionPool::new_memory()?);
+        let state = SessionStateBuilder::new()
+            .with_optimizer_rules(federation_optimizer_rules())
+            .with_query_planner(Arc::new(FederatedQueryPlanner::new()))
+            .with_default_features()
+            .build();
+        let ctx = SessionContext::new_with_state(state);
+        for (name, column) in [("users", "id"), ("orders", "user_id")] {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new(column, DataType::Int64, false),
+                Field::new("name", DataType::Utf8, true),
+            ]));
+            let table = DuckDBTable::new_with_schema(&pool, schema, name, None, None);
+            ctx.register_table(
+                name,
+                Arc::new(Arc::new(table).create_federated_table_provider()?),
+            )?;
+        }
+
+        let sql = dry_run(
+            &ctx.state(),
+            "SELECT name FROM users \
+             WHERE EXISTS (SELECT 1 FROM orders WHERE orders.user_id = users.id) \
+             OR users.id IN (SELECT user_id FROM orders WHERE orders.name = 'gift')",
+        )
+        .await?;
+        assert_eq!(sql.len(), 1, "{sql:?}");
+        assert!(sql[0].contains("EXISTS"), "{sql:?}");
+        assert!(sql[0].contains(" IN (SELECT"), "{sql:?}");
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Keeping window functions local for the remote databases that can't run them, like MySQL 5.7.
+//!
+//! The federation optimizer pushes down the windows of the sub-plans it federates.
+//! [`WindowPushdown`] runs before it, and federates the input of each window whose tables disable
+//! windows with [`PushdownPolicy::with_windows`](crate::sql::pushdown::PushdownPolicy::with_windows),
+//! so that the window runs locally on the rows of its input and the rest of the plan is still
+//! federated. It's one of the rules of
+//! [`federation_optimizer_rules`](super::federation::federation_optimizer_rules).
+
+use std::sync::Arc;
+
+use datafusion::{
+    common::tree_node::Transformed,
+    error::Result as DataFusionResult,
+    logical_expr::{LogicalPlan, Window},
+    optimizer::{optimizer::ApplyOrder, OptimizerConfig, OptimizerRule},
+};
+
+use super::federation::{can_federate_whole, federate, sole_provider, supports_windows};
+
+/// Federates the inputs of the windows whose tables don't push down window functions, see the
+/// [module documentation](self).
+#[derive(Debug, Default)]
+pub struct WindowPushdown {}
+
+impl WindowPushdown {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OptimizerRule for WindowPushdown {
+    fn name(&self) -> &str {
+        "window_pushdown"
+    }
+
+    fn apply_order(&self) -> Option<ApplyOrder> {
+        Some(ApplyOrder::TopDown)
+    }
+
+    fn supports_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(
+        &self,
+        plan: LogicalPlan,
+        config: &dyn OptimizerConfig,
+    ) -> DataFusionResult<Transformed<LogicalPlan>> {
+        let LogicalPlan::Window(window) = plan else {
+            return Ok(Transformed::no(plan));
+        };
+        // the windows of the input are kept local when the rule reaches them
+        if supports_windows(&window.input) || !can_federate_whole(&window.input) {
+            return Ok(Transformed::no(LogicalPlan::Window(window)));
+        }
+        let Some(provider) = sole_provider(&window.input)? else {
+            return Ok(Transformed::no(LogicalPlan::Window(window)));
+        };
+
+        tracing::debug!(
+            "Keeping a window local, '{}' doesn't push down window functions",
+            provider.name()
+        );
+        federate(Arc::unwrap_or_clone(window.input), &provider, config)?.map_data(|input| {
+            Window::try_new(window.window_expr, Arc::new(input)).map(LogicalPlan::Window)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "duckdb-federation"))]
+mod tests {
+    use datafusion::{
+        arrow::datatypes::{DataType, Field, Schema},
+        execution::SessionStateBuilder,
+        prelude::SessionContext,
+    };
+    use datafusion_federation::FederatedQueryPlanner;
+
+    use crate::{
+        duckdb::DuckDBTable,
+        sql::{
+            db_connection_pool::{
+                dbconnection::duckdbconn::DuckDBSyncParameter, duckdbpool::DuckDbConnectionPool,
+                DbConnectionPool,
+            },
+            pushdown::PushdownPolicy,
+            sql_provider_datafusion::{dry_run::dry_run, federation::federation_optimizer_rules},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_window_pushdown() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // the table doesn't exist, the queries are only planned
+        let pool: Arc<
+            dyn DbConnectionPool<
+                    r2d2::PooledConnection<duckdb::DuckdbConnectionManager>,
+                    Box<dyn DuckDBSyncParameter>,
+                > + Send
+                + Sync,
+        > = Arc::new(DuckDbConnectionPool::new_memory()?);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("region", DataType::Utf8, true),
+        ]));
+        let query = "SELECT id, ROW_NUMBER() OVER (PARTITION BY region ORDER BY id) AS rank \
+                     FROM orders WHERE id > 5";
+
+        for windows in [true, false] {
+            let state = SessionStateBuilder::new()
+                .with_optimizer_rules(federation_optimizer_rules())
+                .with_query_planner(Arc::new(FederatedQueryPlanner::new()))
+                .with_default_features()
+                .build();
+            let ctx = SessionContext::new_with_state(state);
+            let table =
+                DuckDBTable::new_with_schema(&pool, Arc::clone(&schema), "orders", None, None)
+                    .with_pushdown_policy(PushdownPolicy::new().with_windows(windows));
+            ctx.register_table(
+                "orders",
+                Arc::new(Arc::new(table).create_federated_table_provider()?),
+            )?;
+
+            let sql = dry_run(&ctx.state(), query).await?;
+            assert_eq!(sql.len(), 1, "{sql:?}");
+            assert_eq!(sql[0].contains("ROW_NUMBER"), windows, "{sql:?}");
+            assert!(sql[0].contains("> 5"), "{sql:?}");
+        }
+        Ok(())
+    }
+}
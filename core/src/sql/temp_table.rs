@@ -0,0 +1,352 @@
+//! Temporary tables for the intermediate results of multi-step pipelines.
+//!
+//! A [`TempTableSession`] holds one connection of a pool for as long as it lives. Tables created
+//! with it are `CREATE TEMP TABLE`s of that connection, and the returned providers run their
+//! queries on the same connection, so the tables are never visible to other sessions.
+//!
+//! DuckDB isn't supported: its scans run every query on a new connection to the database, which
+//! can't see the temporary tables of the connection that created them.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{array::RecordBatch, datatypes::SchemaRef},
+    datasource::TableProvider,
+    error::DataFusionError,
+    execution::SendableRecordBatchStream,
+    physical_plan::common::collect,
+    sql::{unparser::dialect::Dialect, TableReference},
+};
+use snafu::prelude::*;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::sql::arrow_sql_gen::statement::{self, CreateTableBuilder, InsertBuilder};
+use crate::sql::db_connection_pool::{
+    self,
+    dbconnection::{AsyncDbConnection, DbConnection, GenericError, SyncDbConnection},
+    DbConnectionPool, JoinPushDown,
+};
+use crate::sql::sql_provider_datafusion::SqlTable;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Unable to get a connection for the temporary tables: {source}"))]
+    UnableToConnect { source: db_connection_pool::Error },
+
+    #[snafu(display("Unable to read the data for temporary table '{table_name}': {source}"))]
+    UnableToReadData {
+        table_name: String,
+        source: DataFusionError,
+    },
+
+    #[snafu(display(
+        "Unable to build the insert statement for temporary table '{table_name}': {source}"
+    ))]
+    UnableToBuildInsertStatement {
+        table_name: String,
+        source: statement::Error,
+    },
+
+    #[snafu(display("Failed to run '{sql}' for temporary table '{table_name}': {source}"))]
+    UnableToExecuteStatement {
+        table_name: String,
+        sql: String,
+        source: GenericError,
+    },
+
+    #[snafu(display("The connection doesn't support running statements"))]
+    UnsupportedConnection,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The database the temporary tables are created in, which decides the SQL that is generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TempTableDialect {
+    #[cfg(feature = "postgres")]
+    Postgres,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl TempTableDialect {
+    fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgres => "postgres",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => "sqlite",
+        }
+    }
+
+    fn unparser_dialect(self) -> Arc<dyn Dialect + Send + Sync> {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgres => crate::postgres::postgres_dialect(None),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => Arc::new(datafusion::sql::unparser::dialect::SqliteDialect {}),
+        }
+    }
+
+    fn create_statements(self, schema: SchemaRef, table_name: &str) -> Vec<String> {
+        let builder = CreateTableBuilder::new(schema, table_name).temporary();
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgres => builder.build_postgres(),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => vec![builder.build_sqlite()],
+        }
+    }
+
+    fn insert_statement(
+        self,
+        table: &TableReference,
+        batch: RecordBatch,
+    ) -> Result<String, statement::Error> {
+        let builder = InsertBuilder::new(table, vec![batch]);
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgres => builder.build_postgres(None),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => builder.build_sqlite(None),
+        }
+    }
+}
+
+/// A session of one pooled connection, in which temporary tables can be created from the results
+/// of an earlier step and read by the next ones.
+///
+/// The tables are dropped by [`Self::close`]. A session that is dropped without being closed
+/// returns its connection to the pool with the tables still in it, until the database closes the
+/// connection.
+pub struct TempTableSession<T: 'static, P: 'static> {
+    pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+    dialect: TempTableDialect,
+    tables: std::sync::Mutex<Vec<TableReference>>,
+}
+
+impl<T: 'static, P: 'static> std::fmt::Debug for TempTableSession<T, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TempTableSession")
+            .field("dialect", &self.dialect)
+            .field("tables", &self.tables)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: 'static, P: 'static> TempTableSession<T, P> {
+    /// Takes a connection from `pool` for the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no connection can be taken from the pool.
+    pub async fn connect(
+        pool: &Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
+        dialect: TempTableDialect,
+    ) -> Result<Self> {
+        let conn = pool.connect().await.context(UnableToConnectSnafu)?;
+        Ok(Self {
+            pool: Arc::new(SessionConnectionPool {
+                conn: Arc::new(Mutex::new(conn)),
+            }),
+            dialect,
+            tables: std::sync::Mutex::default(),
+        })
+    }
+
+    /// Creates the temporary table `table_name` with the batches of `stream` and returns a provider
+    /// that reads it on the connection of the session.
+    ///
+    /// The stream is read to the end before the table is written, so it can come from another
+    /// temporary table of the same session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream fails or the table can't be created or written.
+    pub async fn create_table(
+        &self,
+        table_name: &str,
+        stream: SendableRecordBatchStream,
+    ) -> Result<Arc<dyn TableProvider>> {
+        let schema = stream.schema();
+        let batches = collect(stream)
+            .await
+            .context(UnableToReadDataSnafu { table_name })?;
+
+        let table = TableReference::bare(table_name);
+        for sql in self
+            .dialect
+            .create_statements(Arc::clone(&schema), table_name)
+        {
+            self.execute(table_name, sql).await?;
+        }
+        self.tables
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(table.clone());
+
+        for batch in batches.into_iter().filter(|batch| batch.num_rows() > 0) {
+            let sql = self
+                .dialect
+                .insert_statement(&table, batch)
+                .context(UnableToBuildInsertStatementSnafu { table_name })?;
+            self.execute(table_name, sql).await?;
+        }
+
+        Ok(Arc::new(
+            SqlTable::new_with_schema(self.dialect.name(), &self.pool, schema, table)
+                .with_dialect(self.dialect.unparser_dialect()),
+        ))
+    }
+
+    /// Drops the temporary tables of the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a table can't be dropped.
+    pub async fn close(self) -> Result<()> {
+        let tables = std::mem::take(
+            &mut *self
+                .tables
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        for table in tables {
+            let table_name = table.table().to_string();
+            self.execute(
+                &table_name,
+                format!("DROP TABLE IF EXISTS {}", table.to_quoted_string()),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, table_name: &str, sql: String) -> Result<u64> {
+        tracing::debug!("{sql}");
+        let conn = self.pool.connect().await.context(UnableToConnectSnafu)?;
+        let result = if let Some(conn) = conn.as_sync() {
+            conn.execute(&sql, &[])
+        } else if let Some(conn) = conn.as_async() {
+            conn.execute(&sql, &[]).await
+        } else {
+            return UnsupportedConnectionSnafu.fail();
+        };
+        result.context(UnableToExecuteStatementSnafu { table_name, sql })
+    }
+}
+
+/// Hands out the same connection every time, waiting until the previous user is done with it.
+///
+/// Queries only hold the connection until they have started, so the streams of several scans can be
+/// read at the same time.
+struct SessionConnectionPool<T: 'static, P: 'static> {
+    conn: Arc<Mutex<Box<dyn DbConnection<T, P>>>>,
+}
+
+#[async_trait]
+impl<T: 'static, P: 'static> DbConnectionPool<T, P> for SessionConnectionPool<T, P> {
+    async fn connect(
+        &self,
+    ) -> std::result::Result<Box<dyn DbConnection<T, P>>, db_connection_pool::Error> {
+        Ok(Box::new(SessionConnection {
+            conn: Arc::clone(&self.conn).lock_owned().await,
+        }))
+    }
+
+    fn join_push_down(&self) -> JoinPushDown {
+        // the temporary tables can only be joined with each other, not with the tables of the pool
+        JoinPushDown::Disallow
+    }
+}
+
+struct SessionConnection<T: 'static, P: 'static> {
+    conn: OwnedMutexGuard<Box<dyn DbConnection<T, P>>>,
+}
+
+impl<T: 'static, P: 'static> DbConnection<T, P> for SessionConnection<T, P> {
+    fn as_any(&self) -> &dyn Any {
+        self.conn.as_any()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self.conn.as_any_mut()
+    }
+
+    fn as_sync(&self) -> Option<&dyn SyncDbConnection<T, P>> {
+        self.conn.as_sync()
+    }
+
+    fn as_async(&self) -> Option<&dyn AsyncDbConnection<T, P>> {
+        self.conn.as_async()
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use std::time::Duration;
+
+    use datafusion::{
+        arrow::{
+            array::{Int64Array, StringArray},
+            datatypes::{DataType, Field, Schema},
+        },
+        physical_plan::stream::RecordBatchStreamAdapter,
+        prelude::SessionContext,
+    };
+
+    use super::*;
+    use crate::sql::db_connection_pool::{sqlitepool::SqliteConnectionPoolFactory, Mode};
+    use crate::sqlite::DynSqliteConnectionPool;
+
+    #[tokio::test]
+    async fn test_sqlite_temp_table_session() {
+        let pool =
+            SqliteConnectionPoolFactory::new("./temp.sqlite", Mode::Memory, Duration::from_secs(5))
+                .build()
+                .await
+                .expect("pool created");
+        let pool: Arc<DynSqliteConnectionPool> = Arc::new(pool);
+        let session = TempTableSession::connect(&pool, TempTableDialect::Sqlite)
+            .await
+            .expect("session started");
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )
+        .expect("record batch created");
+        let stream = Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            futures::stream::iter(vec![Ok(batch)]),
+        ));
+
+        let table = session
+            .create_table("step_1", stream)
+            .await
+            .expect("temporary table created");
+
+        let ctx = SessionContext::new();
+        ctx.register_table("step_1", table)
+            .expect("table registered");
+        let batches = ctx
+            .sql("SELECT id FROM step_1 WHERE name IS NULL")
+            .await
+            .expect("query planned")
+            .collect()
+            .await
+            .expect("query ran");
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 1);
+
+        session.close().await.expect("session closed");
+    }
+}
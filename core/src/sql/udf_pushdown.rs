@@ -0,0 +1,323 @@
+use std::{collections::HashMap, ops::ControlFlow};
+
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::Expr,
+    sql::{
+        sqlparser::{
+            ast::{self, visit_expressions, visit_expressions_mut},
+            dialect::GenericDialect,
+            parser::{Parser, ParserError},
+        },
+        unparser::Unparser,
+    },
+};
+use snafu::prelude::*;
+
+use super::dialect::DialectOverrides;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Unable to parse the SQL template '{template}' of function '{name}': {source}"
+    ))]
+    InvalidTemplate {
+        name: String,
+        template: String,
+        source: ParserError,
+    },
+
+    #[snafu(display(
+        "The SQL template '{template}' of function '{name}' refers to argument ${index}, arguments are numbered from $1"
+    ))]
+    InvalidPlaceholder {
+        name: String,
+        template: String,
+        index: String,
+    },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The SQL a scalar function is pushed down as, with `$1`, `$2`, ... standing for its arguments,
+/// e.g. `REGEXP_SUBSTR($1, $2)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlTemplate {
+    template: String,
+    expr: ast::Expr,
+}
+
+impl SqlTemplate {
+    /// Parses `template` as a SQL expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `template` isn't a SQL expression, or refers to an argument `$0` or
+    /// to a placeholder that isn't numbered.
+    pub fn try_new(name: &str, template: impl Into<String>) -> Result<Self> {
+        let template = template.into();
+        let expr = Parser::new(&GenericDialect {})
+            .try_with_sql(&template)
+            .and_then(|mut parser| parser.parse_expr())
+            .context(InvalidTemplateSnafu {
+                name,
+                template: &template,
+            })?;
+
+        let mut invalid = None;
+        let _ = visit_expressions(&expr, |expr| {
+            if let ast::Expr::Value(ast::Value::Placeholder(placeholder)) = expr {
+                if argument_index(placeholder).is_none() {
+                    invalid = Some(placeholder.trim_start_matches('$').to_string());
+                    return ControlFlow::Break(());
+                }
+            }
+            ControlFlow::Continue(())
+        });
+        if let Some(index) = invalid {
+            return InvalidPlaceholderSnafu {
+                name,
+                template,
+                index,
+            }
+            .fail();
+        }
+
+        Ok(Self { template, expr })
+    }
+
+    #[must_use]
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Substitutes the unparsed `args` for the placeholders of the template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an argument can't be unparsed, or the template refers to more arguments
+    /// than the function was called with.
+    pub fn to_sql(&self, unparser: &Unparser, args: &[Expr]) -> DataFusionResult<ast::Expr> {
+        let args = args
+            .iter()
+            .map(|arg| unparser.expr_to_sql(arg))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let mut expr = self.expr.clone();
+        let mut missing = None;
+        let _ = visit_expressions_mut(&mut expr, |expr| {
+            if let ast::Expr::Value(ast::Value::Placeholder(placeholder)) = expr {
+                match argument_index(placeholder).and_then(|index| args.get(index)) {
+                    Some(arg) => *expr = arg.clone(),
+                    None => {
+                        missing = Some(placeholder.clone());
+                        return ControlFlow::Break(());
+                    }
+                }
+            }
+            ControlFlow::Continue(())
+        });
+        if let Some(placeholder) = missing {
+            return Err(DataFusionError::Plan(format!(
+                "The SQL template '{}' refers to {placeholder}, but the function was called with {} arguments",
+                self.template,
+                args.len()
+            )));
+        }
+
+        Ok(expr)
+    }
+}
+
+/// The 0-based index of the argument of a `$n` placeholder.
+fn argument_index(placeholder: &str) -> Option<usize> {
+    placeholder
+        .strip_prefix('$')?
+        .parse::<usize>()
+        .ok()?
+        .checked_sub(1)
+}
+
+/// The remote SQL of scalar functions, per backend, so that calls of user-registered UDFs with a
+/// remote equivalent are pushed down instead of being evaluated locally.
+///
+/// Backends are named like the engines of the providers: `postgres`, `mysql`, `sqlite` and
+/// `duckdb`. The templates of a backend are applied through the [`DialectOverrides`] of its
+/// providers:
+///
+/// ```rust,ignore
+/// let registry = UdfPushdownRegistry::new()
+///     .with_function("mysql", "regexp_extract", "REGEXP_SUBSTR($1, $2)")?
+///     .with_function("duckdb", "regexp_extract", "regexp_extract($1, $2)")?;
+/// let factory = MySQLTableFactory::new(pool)
+///     .with_dialect_overrides(registry.dialect_overrides("mysql"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UdfPushdownRegistry {
+    functions: HashMap<String, HashMap<String, SqlTemplate>>,
+}
+
+impl UdfPushdownRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes calls of the scalar function `name` down to `backend` as `template`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `template` can't be parsed, see [`SqlTemplate::try_new`].
+    pub fn with_function(
+        mut self,
+        backend: impl Into<String>,
+        name: impl Into<String>,
+        template: impl Into<String>,
+    ) -> Result<Self> {
+        let name = name.into();
+        let template = SqlTemplate::try_new(&name, template)?;
+        self.functions
+            .entry(backend.into())
+            .or_default()
+            .insert(name, template);
+        Ok(self)
+    }
+
+    /// The template `name` is pushed down to `backend` as.
+    #[must_use]
+    pub fn template(&self, backend: &str, name: &str) -> Option<&SqlTemplate> {
+        self.functions.get(backend)?.get(name)
+    }
+
+    /// The overrides that unparse the functions registered for `backend` with their templates.
+    #[must_use]
+    pub fn dialect_overrides(&self, backend: &str) -> DialectOverrides {
+        self.extend_dialect_overrides(backend, DialectOverrides::new())
+    }
+
+    /// Adds the functions registered for `backend` to `overrides`.
+    #[must_use]
+    pub fn extend_dialect_overrides(
+        &self,
+        backend: &str,
+        overrides: DialectOverrides,
+    ) -> DialectOverrides {
+        let Some(functions) = self.functions.get(backend) else {
+            return overrides;
+        };
+        functions
+            .iter()
+            .fold(overrides, |overrides, (name, template)| {
+                let template = template.clone();
+                overrides.with_scalar_function(name.clone(), move |unparser, args| {
+                    template.to_sql(unparser, args).map(Some)
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::Any, sync::Arc};
+
+    use datafusion::{
+        arrow::datatypes::DataType,
+        logical_expr::{
+            ColumnarValue, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature, Volatility,
+        },
+        prelude::{col, lit},
+        sql::unparser::dialect::{DuckDBDialect, MySqlDialect},
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct RegexpExtract {
+        signature: Signature,
+    }
+
+    impl ScalarUDFImpl for RegexpExtract {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn name(&self) -> &str {
+            "regexp_extract"
+        }
+
+        fn signature(&self) -> &Signature {
+            &self.signature
+        }
+
+        fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+            Ok(DataType::Utf8)
+        }
+
+        fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+            unimplemented!("only unparsed")
+        }
+    }
+
+    fn regexp_extract(args: Vec<Expr>) -> Expr {
+        ScalarUDF::new_from_impl(RegexpExtract {
+            signature: Signature::variadic_any(Volatility::Immutable),
+        })
+        .call(args)
+    }
+
+    #[test]
+    fn test_udf_pushdown_registry() {
+        let registry = UdfPushdownRegistry::new()
+            .with_function("mysql", "regexp_extract", "REGEXP_SUBSTR($1, $2)")
+            .and_then(|registry| {
+                registry.with_function(
+                    "duckdb",
+                    "regexp_extract",
+                    "coalesce(regexp_extract($1, $2), $1)",
+                )
+            })
+            .expect("templates parsed");
+        let expr = regexp_extract(vec![col("name"), lit("[0-9]+")]);
+
+        let dialect = registry
+            .dialect_overrides("mysql")
+            .dialect(Arc::new(MySqlDialect {}));
+        let sql = Unparser::new(dialect.as_ref())
+            .expr_to_sql(&expr)
+            .expect("expression unparsed");
+        assert_eq!(sql.to_string(), "REGEXP_SUBSTR(`name`, '[0-9]+')");
+
+        let dialect = registry
+            .dialect_overrides("duckdb")
+            .dialect(Arc::new(DuckDBDialect::new()));
+        let sql = Unparser::new(dialect.as_ref())
+            .expr_to_sql(&expr)
+            .expect("expression unparsed");
+        assert_eq!(
+            sql.to_string(),
+            r#"coalesce(regexp_extract("name", '[0-9]+'), "name")"#
+        );
+
+        assert!(registry.template("sqlite", "regexp_extract").is_none());
+
+        // a call with too few arguments for the template isn't pushed down
+        let dialect = registry
+            .dialect_overrides("mysql")
+            .dialect(Arc::new(MySqlDialect {}));
+        assert!(Unparser::new(dialect.as_ref())
+            .expr_to_sql(&regexp_extract(vec![col("name")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_sql_template() {
+        assert!(matches!(
+            SqlTemplate::try_new("f", "REGEXP_SUBSTR($1,"),
+            Err(Error::InvalidTemplate { .. })
+        ));
+        assert!(matches!(
+            SqlTemplate::try_new("f", "f($0)"),
+            Err(Error::InvalidPlaceholder { .. })
+        ));
+    }
+}
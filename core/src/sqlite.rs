@@ -1,4 +1,7 @@
+use crate::sql::approx_distinct::ApproxDistinct;
 use crate::sql::arrow_sql_gen::statement::{CreateTableBuilder, IndexBuilder, InsertBuilder};
+use crate::sql::column_expressions::{self, ColumnExpressions};
+use crate::sql::datetime::DateTimeSyntax;
 use crate::sql::db_connection_pool::dbconnection::{self, get_schema, AsyncDbConnection};
 use crate::sql::db_connection_pool::sqlitepool::SqliteConnectionPoolFactory;
 use crate::sql::db_connection_pool::DbInstanceKey;
@@ -8,13 +11,23 @@ use crate::sql::db_connection_pool::{
     sqlitepool::SqliteConnectionPool,
     DbConnectionPool, Mode,
 };
+use crate::sql::dialect::DialectOverrides;
+use crate::sql::dml::{self, quote_table_reference, DmlOperation};
+use crate::sql::full_text::FullTextSearch;
+use crate::sql::null_ordering::NullOrdering;
+use crate::sql::permissions::{probe_permissions, PermissionReport};
+use crate::sql::pushdown::PushdownPolicy;
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::regexp::RegexSyntax;
+use crate::sql::schema_drift::SchemaDriftPolicy;
 use crate::sql::sql_provider_datafusion;
 use crate::util::schema::SchemaValidator;
 use crate::UnsupportedTypeAction;
 use arrow::array::{Int64Array, StringArray};
-use arrow::{array::RecordBatch, datatypes::SchemaRef};
+use arrow::{array::RecordBatch, datatypes::SchemaRef, error::ArrowError};
 use async_trait::async_trait;
 use datafusion::catalog::Session;
+use datafusion::sql::unparser::dialect::{Dialect, SqliteDialect};
 use datafusion::{
     catalog::TableProviderFactory,
     common::Constraints,
@@ -37,8 +50,12 @@ use crate::util::{
     self,
     column_reference::{self, ColumnReference},
     constraints::{self, get_primary_keys_from_constraints},
-    indexes::IndexType,
+    dedup::{self, Deduplicator},
+    identifier::IdentifierCase,
+    indexes::{IndexAdvisor, IndexType},
     on_conflict::{self, OnConflict},
+    schema_mismatch::{self, SchemaMismatchMode},
+    validation,
 };
 
 use self::write::SqliteTableWriter;
@@ -85,6 +102,14 @@ pub enum Error {
     #[snafu(display("Unable to deleta all table data in Sqlite: {source}"))]
     UnableToDeleteAllTableData { source: rusqlite::Error },
 
+    #[snafu(display("Unable to create the DML statement for the Sqlite table: {source}"))]
+    UnableToCreateDmlStatement { source: dml::Error },
+
+    #[snafu(display("Unable to execute the DML statement on the Sqlite table: {source}"))]
+    UnableToExecuteDmlStatement {
+        source: db_connection_pool::dbconnection::GenericError,
+    },
+
     #[snafu(display("There is a dangling reference to the Sqlite struct in TableProviderFactory.create. This is a bug."))]
     DanglingReferenceToSqlite,
 
@@ -97,6 +122,27 @@ pub enum Error {
     #[snafu(display("Error parsing on_conflict: {source}"))]
     UnableToParseOnConflict { source: on_conflict::Error },
 
+    #[snafu(display("Unable to drop duplicate rows from the data to insert: {source}"))]
+    UnableToDeduplicateBatch { source: ArrowError },
+
+    #[snafu(display("The data to insert is invalid: {source}"))]
+    InvalidBatch { source: validation::Error },
+
+    #[snafu(display("Error parsing validate_batches: {source}"))]
+    UnableToParseValidateBatches { source: validation::Error },
+
+    #[snafu(display("Error parsing schema_mismatch: {source}"))]
+    UnableToParseSchemaMismatchMode { source: schema_mismatch::Error },
+
+    #[snafu(display("Unable to write to the Sqlite table '{table_name}': {source}"))]
+    SchemaMismatch {
+        table_name: String,
+        source: schema_mismatch::Error,
+    },
+
+    #[snafu(display("Invalid column defaults or generated columns: {source}"))]
+    InvalidColumnExpressions { source: column_expressions::Error },
+
     #[snafu(display("Unable to infer schema: {source}"))]
     UnableToInferSchema { source: dbconnection::Error },
 
@@ -270,6 +316,43 @@ impl TableProviderFactory for SqliteTableProviderFactory {
             );
         }
 
+        let dedup_columns = options
+            .remove("dedup_columns")
+            .map(|dedup_columns| {
+                ColumnReference::try_from(dedup_columns.as_str())
+                    .context(UnableToParseColumnReferenceSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?;
+
+        let validate_batches = options
+            .remove("validate_batches")
+            .map(|value| {
+                validation::parse_validate_batches(&value)
+                    .context(UnableToParseValidateBatchesSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let schema_mismatch = options
+            .remove("schema_mismatch")
+            .map(|value| {
+                SchemaMismatchMode::try_from(value.as_str())
+                    .context(UnableToParseSchemaMismatchModeSnafu)
+                    .map_err(to_datafusion_error)
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let column_expressions = ColumnExpressions::try_from_table_options(
+            &cmd.column_defaults,
+            &mut options,
+            &SqliteDialect {},
+        )
+        .context(InvalidColumnExpressionsSnafu)
+        .map_err(to_datafusion_error)?;
+
         let busy_timeout = self
             .sqlite_busy_timeout(&cmd.options)
             .map_err(to_datafusion_error)?;
@@ -301,13 +384,24 @@ impl TableProviderFactory for SqliteTableProviderFactory {
         let schema: SchemaRef =
             SqliteConnection::handle_unsupported_schema(&schema, UnsupportedTypeAction::Error)
                 .map_err(|e| DataFusionError::External(e.into()))?;
+        column_expressions
+            .validate(&schema)
+            .context(InvalidColumnExpressionsSnafu)
+            .map_err(to_datafusion_error)?;
 
-        let sqlite = Arc::new(Sqlite::new(
+        let mut sqlite = Sqlite::new(
             name.clone(),
             Arc::clone(&schema),
             Arc::clone(&pool),
             cmd.constraints.clone(),
-        ));
+        )
+        .with_batch_validation(validate_batches)
+        .with_schema_mismatch_mode(schema_mismatch)
+        .with_column_expressions(column_expressions);
+        if let Some(dedup_columns) = dedup_columns {
+            sqlite = sqlite.with_dedup_columns(dedup_columns.iter().map(String::from).collect());
+        }
+        let sqlite = Arc::new(sqlite);
 
         let mut db_conn = sqlite.connect().await.map_err(to_datafusion_error)?;
         let sqlite_conn = Sqlite::sqlite_conn(&mut db_conn).map_err(to_datafusion_error)?;
@@ -377,12 +471,156 @@ impl TableProviderFactory for SqliteTableProviderFactory {
 
 pub struct SqliteTableFactory {
     pool: Arc<SqliteConnectionPool>,
+    identifier_case: IdentifierCase,
+    full_text_search: Option<FullTextSearch>,
+    dictionary_columns: Vec<String>,
+    schema_drift: SchemaDriftPolicy,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    dialect_overrides: DialectOverrides,
+    remote_explain: bool,
+    pushdown_policy: PushdownPolicy,
+    regex: Option<RegexSyntax>,
+    approx_distinct: Option<ApproxDistinct>,
+    bind_literals: bool,
+    query_context: Option<QueryContextMode>,
+    index_advisor: Option<Arc<IndexAdvisor>>,
 }
 
 impl SqliteTableFactory {
     #[must_use]
     pub fn new(pool: Arc<SqliteConnectionPool>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            identifier_case: IdentifierCase::default(),
+            full_text_search: None,
+            dictionary_columns: Vec::new(),
+            schema_drift: SchemaDriftPolicy::default(),
+            spill_buffer: None,
+            memory_wait: None,
+            dialect_overrides: DialectOverrides::default(),
+            remote_explain: false,
+            pushdown_policy: PushdownPolicy::default(),
+            regex: None,
+            approx_distinct: None,
+            bind_literals: false,
+            query_context: None,
+            index_advisor: None,
+        }
+    }
+
+    /// Sets how table references are normalized before they are looked up. SQLite compares
+    /// table names case-insensitively, so this mostly affects how identifiers are quoted.
+    #[must_use]
+    pub fn with_identifier_case(mut self, identifier_case: IdentifierCase) -> Self {
+        self.identifier_case = identifier_case;
+        self
+    }
+
+    /// Enables pushing down `match()` predicates on the given columns of FTS5 virtual tables as
+    /// `MATCH` queries, see [`crate::sql::full_text::match_udf`].
+    #[must_use]
+    pub fn with_full_text_search(mut self, full_text_search: FullTextSearch) -> Self {
+        self.full_text_search = Some(full_text_search);
+        self
+    }
+
+    /// Customizes how scalar functions are unparsed for the remote database, see
+    /// [`DialectOverrides`].
+    #[must_use]
+    pub fn with_dialect_overrides(mut self, dialect_overrides: DialectOverrides) -> Self {
+        self.dialect_overrides = dialect_overrides;
+        self
+    }
+
+    /// Returns the given string columns as dictionary-encoded arrays, see
+    /// [`crate::util::dictionary::dictionary_encode_schema`].
+    #[must_use]
+    pub fn with_dictionary_columns(mut self, columns: Vec<String>) -> Self {
+        self.dictionary_columns = columns;
+        self
+    }
+
+    /// Sets what scans of the tables do when the columns returned by the database no longer match
+    /// the schema the table was registered with, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(mut self, schema_drift: SchemaDriftPolicy) -> Self {
+        self.schema_drift = schema_drift;
+        self
+    }
+
+    /// Buffers the unread batches of scans of the tables, keeping up to `memory_batches` in memory
+    /// and the rest on disk, so slow consumers don't hold on to connections, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(mut self, memory_batches: Option<usize>) -> Self {
+        self.spill_buffer = memory_batches;
+        self
+    }
+
+    /// Waits up to `wait` for the memory pool to have the memory of every batch of scans of the
+    /// tables, see [`sql_provider_datafusion::SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(mut self, wait: Option<Duration>) -> Self {
+        self.memory_wait = wait;
+        self
+    }
+
+    /// Shows the `EXPLAIN QUERY PLAN` of every scan in the `EXPLAIN` output, see
+    /// [`sql_provider_datafusion::SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(mut self, remote_explain: bool) -> Self {
+        self.remote_explain = remote_explain;
+        self
+    }
+
+    /// Sets what scans of the tables push down to the database, see [`PushdownPolicy`].
+    #[must_use]
+    pub fn with_pushdown_policy(mut self, pushdown_policy: PushdownPolicy) -> Self {
+        self.pushdown_policy = pushdown_policy;
+        self
+    }
+
+    /// Pushes down the regex matches, e.g. as `REGEXP` with [`RegexSyntax::Regexp`] for databases
+    /// whose connections load a `regexp()` function. SQLite has none by default, so the filters
+    /// with regex matches aren't pushed down.
+    #[must_use]
+    pub fn with_regex(mut self, regex: Option<RegexSyntax>) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    /// Counts the `approx_distinct` aggregates of federated queries exactly on the database with
+    /// [`ApproxDistinct::Exact`], as it has no estimate of its own, see
+    /// [`sql_provider_datafusion::SqlTable::with_approx_distinct`].
+    #[must_use]
+    pub fn with_approx_distinct(mut self, approx_distinct: Option<ApproxDistinct>) -> Self {
+        self.approx_distinct = approx_distinct;
+        self
+    }
+
+    /// Binds the string literals of scans as parameters instead of quoting them, see
+    /// [`sql_provider_datafusion::SqlTable::with_bind_literals`].
+    #[must_use]
+    pub fn with_bind_literals(mut self, bind_literals: bool) -> Self {
+        self.bind_literals = bind_literals;
+        self
+    }
+
+    /// Passes the query context of the session to the database with the queries of the tables,
+    /// see [`sql_provider_datafusion::SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(mut self, query_context: Option<QueryContextMode>) -> Self {
+        self.query_context = query_context;
+        self
+    }
+
+    /// Counts the columns that the scans of the tables filter on in `index_advisor`, which
+    /// recommends indexes for [`Self::create_recommended_indexes`], see [`IndexAdvisor`].
+    #[must_use]
+    pub fn with_index_advisor(mut self, index_advisor: Option<Arc<IndexAdvisor>>) -> Self {
+        self.index_advisor = index_advisor;
+        self
     }
 
     pub async fn table_provider(
@@ -390,26 +628,134 @@ impl SqliteTableFactory {
         table_reference: TableReference,
     ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
         let pool = Arc::clone(&self.pool);
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
 
         let conn = pool.connect().await.context(DbConnectionSnafu)?;
         let schema = get_schema(conn, &table_reference)
             .await
             .context(UnableToInferSchemaSnafu)?;
 
-        let dyn_pool: Arc<DynSqliteConnectionPool> = pool;
+        Ok(self.read_provider(schema, table_reference, None))
+    }
 
-        let read_provider = Arc::new(SQLiteTable::new_with_schema(
-            &dyn_pool,
-            Arc::clone(&schema),
-            table_reference,
-        ));
+    /// A read-only table of the rows of `query`, a SQLite `SELECT`, named `table_reference` in the
+    /// SQL of its scans, see [`sql_provider_datafusion::SqlTable::from_query`].
+    pub async fn query_table_provider(
+        &self,
+        table_reference: TableReference,
+        query: &str,
+    ) -> Result<Arc<dyn TableProvider + 'static>, Box<dyn std::error::Error + Send + Sync>> {
+        let dyn_pool: Arc<DynSqliteConnectionPool> = Arc::clone(&self.pool);
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+
+        let schema = sql_provider_datafusion::get_query_schema(&dyn_pool, query).await?;
+
+        Ok(self.read_provider(schema, table_reference, Some(query)))
+    }
+
+    fn read_provider(
+        &self,
+        schema: SchemaRef,
+        table_reference: TableReference,
+        query: Option<&str>,
+    ) -> Arc<dyn TableProvider + 'static> {
+        let dyn_pool: Arc<DynSqliteConnectionPool> = Arc::clone(&self.pool);
+
+        let mut read_provider = SQLiteTable::new_with_schema(&dyn_pool, schema, table_reference)
+            .with_dialect(self.dialect())
+            .with_dictionary_columns(&self.dictionary_columns)
+            .with_schema_drift(self.schema_drift)
+            .with_spill_buffer(self.spill_buffer)
+            .with_memory_wait(self.memory_wait)
+            .with_remote_explain(self.remote_explain)
+            .with_pushdown_policy(self.pushdown_policy.clone())
+            .with_regex(self.regex)
+            .with_approx_distinct(self.approx_distinct)
+            .with_bind_literals(self.bind_literals)
+            .with_query_context(self.query_context.clone())
+            .with_index_advisor(self.index_advisor.clone());
+        if let Some(query) = query {
+            read_provider = read_provider.with_query(query);
+        }
+
+        Arc::new(read_provider)
+    }
+
+    /// Creates the `declared` indexes of the table and the ones that the index advisor recommends
+    /// on the columns that its scans filter on the most, see [`IndexAdvisor::recommend`], unless
+    /// they exist. Indexes are cheaper to build over the loaded rows than to update with each
+    /// insert, so this is called once a load of the table completes.
+    ///
+    /// Returns the indexes of the table, the declared ones first.
+    pub async fn create_recommended_indexes(
+        &self,
+        table_reference: TableReference,
+        declared: &[(ColumnReference, IndexType)],
+    ) -> Result<Vec<(ColumnReference, IndexType)>, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let indexes = match &self.index_advisor {
+            Some(index_advisor) => index_advisor.recommend(&table_reference, declared),
+            None => declared.to_vec(),
+        };
+        if indexes.is_empty() {
+            return Ok(indexes);
+        }
+
+        let mut db_conn = self.pool.connect().await.context(DbConnectionSnafu)?;
+        let sqlite_conn = Sqlite::sqlite_conn(&mut db_conn)?;
+        for (columns, index_type) in &indexes {
+            let mut index_builder =
+                IndexBuilder::new(table_reference.table(), columns.iter().collect());
+            if *index_type == IndexType::Unique {
+                index_builder = index_builder.unique();
+            }
+            let sql = index_builder.build_sqlite();
+            tracing::debug!("Creating index: {sql}");
+            sqlite_conn.execute(&sql, &[]).await?;
+        }
+        Ok(indexes)
+    }
+
+    /// Checks whether the table can be read, and written to if `writable`, by running statements
+    /// against the table that match no rows. Writes fail on databases opened read-only.
+    pub async fn validate_permissions(
+        &self,
+        table_reference: TableReference,
+        writable: bool,
+    ) -> Result<PermissionReport, Box<dyn std::error::Error + Send + Sync>> {
+        let table_reference = self
+            .identifier_case
+            .normalize_table_reference(table_reference);
+        let conn = self.pool.connect().await.context(DbConnectionSnafu)?;
+        let table = quote_table_reference(&table_reference, self.dialect().as_ref());
+
+        Ok(probe_permissions(conn, table_reference, &table, writable).await)
+    }
 
-        Ok(read_provider)
+    fn dialect(&self) -> Arc<dyn Dialect + Send + Sync> {
+        let dialect = sqlite_dialect();
+        let dialect = match &self.full_text_search {
+            Some(full_text_search) => full_text_search.dialect(dialect),
+            None => dialect,
+        };
+        self.identifier_case
+            .dialect(self.dialect_overrides.dialect(dialect))
     }
 }
 
+/// The dialect of SQLite tables, with the date functions and the sorts of SQLite.
+pub(crate) fn sqlite_dialect() -> Arc<dyn Dialect + Send + Sync> {
+    NullOrdering::Smallest.dialect(DateTimeSyntax::Sqlite.dialect(Arc::new(SqliteDialect {})))
+}
+
 fn to_datafusion_error(error: Error) -> DataFusionError {
-    DataFusionError::External(Box::new(error))
+    DataFusionError::External(crate::util::redact::redact_error(Box::new(error), None))
 }
 
 #[derive(Clone)]
@@ -418,6 +764,10 @@ pub struct Sqlite {
     schema: SchemaRef,
     pool: Arc<SqliteConnectionPool>,
     constraints: Constraints,
+    dedup_columns: Option<Vec<String>>,
+    validate_batches: bool,
+    schema_mismatch: SchemaMismatchMode,
+    column_expressions: ColumnExpressions,
 }
 
 impl std::fmt::Debug for Sqlite {
@@ -426,6 +776,10 @@ impl std::fmt::Debug for Sqlite {
             .field("table_name", &self.table)
             .field("schema", &self.schema)
             .field("constraints", &self.constraints)
+            .field("dedup_columns", &self.dedup_columns)
+            .field("validate_batches", &self.validate_batches)
+            .field("schema_mismatch", &self.schema_mismatch)
+            .field("column_expressions", &self.column_expressions)
             .finish()
     }
 }
@@ -443,9 +797,50 @@ impl Sqlite {
             schema,
             pool,
             constraints,
+            dedup_columns: None,
+            validate_batches: false,
+            schema_mismatch: SchemaMismatchMode::default(),
+            column_expressions: ColumnExpressions::default(),
         }
     }
 
+    /// Checks each batch with [`validation::validate_batch`] before it is inserted, so malformed
+    /// arrays are rejected with the offending column instead of failing when their values are bound.
+    #[must_use]
+    pub fn with_batch_validation(mut self, validate_batches: bool) -> Self {
+        self.validate_batches = validate_batches;
+        self
+    }
+
+    /// Sets what writes do with batches whose columns differ from the ones of the table: fail
+    /// with the added, missing and retyped columns, or cast the retyped ones.
+    #[must_use]
+    pub fn with_schema_mismatch_mode(mut self, schema_mismatch: SchemaMismatchMode) -> Self {
+        self.schema_mismatch = schema_mismatch;
+        self
+    }
+
+    /// Sets the column defaults and generated columns the table is created with. Generated columns
+    /// can't be written in SQLite, so they are removed from the batches before they are inserted.
+    #[must_use]
+    pub fn with_column_expressions(mut self, column_expressions: ColumnExpressions) -> Self {
+        self.column_expressions = column_expressions;
+        self
+    }
+
+    #[must_use]
+    pub fn column_expressions(&self) -> &ColumnExpressions {
+        &self.column_expressions
+    }
+
+    /// Drops incoming rows that match an earlier incoming row or an existing row of the table on `dedup_columns`.
+    /// If the writer has an `on_conflict` clause, it decides what happens to rows that match existing rows instead.
+    #[must_use]
+    pub fn with_dedup_columns(mut self, dedup_columns: Vec<String>) -> Self {
+        self.dedup_columns = Some(dedup_columns);
+        self
+    }
+
     #[must_use]
     pub fn table_name(&self) -> &str {
         self.table.table()
@@ -471,6 +866,22 @@ impl Sqlite {
             .ok_or_else(|| UnableToDowncastDbConnectionSnafu {}.build())
     }
 
+    /// Runs a `DELETE` or `UPDATE` against the table, returning the number of affected rows.
+    pub async fn execute_dml(&self, operation: &DmlOperation) -> Result<u64> {
+        let sql = operation
+            .to_sql(&self.table, &SqliteDialect {})
+            .context(UnableToCreateDmlStatementSnafu)?;
+        tracing::trace!("{sql}");
+
+        let mut db_conn = self.connect().await?;
+        let sqlite_conn = Sqlite::sqlite_conn(&mut db_conn)?;
+
+        sqlite_conn
+            .execute(&sql, &[])
+            .await
+            .context(UnableToExecuteDmlStatementSnafu)
+    }
+
     async fn table_exists(&self, sqlite_conn: &mut SqliteConnection) -> bool {
         let sql = format!(
             r#"SELECT EXISTS (
@@ -500,10 +911,104 @@ impl Sqlite {
         batch: RecordBatch,
         on_conflict: Option<&OnConflict>,
     ) -> rusqlite::Result<()> {
-        let insert_table_builder = InsertBuilder::new(&self.table, vec![batch]);
+        let batch = self
+            .column_expressions
+            .remove_generated_columns(batch)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
 
-        let sea_query_on_conflict =
-            on_conflict.map(|oc| oc.build_sea_query_on_conflict(&self.schema));
+        let Some(dedup_columns) = self
+            .dedup_columns
+            .as_deref()
+            .filter(|_| on_conflict.is_none())
+        else {
+            return self.insert_batch_into(transaction, &self.table, batch, on_conflict);
+        };
+
+        match dedup::on_conflict_for_dedup(&self.schema, &self.constraints, dedup_columns) {
+            Some(on_conflict) => {
+                self.insert_batch_into(transaction, &self.table, batch, Some(&on_conflict))
+            }
+            None => self.insert_batch_anti_join(transaction, batch, dedup_columns),
+        }
+    }
+
+    /// Inserts the rows of the batch that don't already exist in the table, by loading the batch
+    /// into a temporary staging table that is anti-joined with the table.
+    fn insert_batch_anti_join(
+        &self,
+        transaction: &Transaction<'_>,
+        batch: RecordBatch,
+        dedup_columns: &[String],
+    ) -> rusqlite::Result<()> {
+        let staging_table =
+            TableReference::partial("temp", format!("__dedup_{}", self.table.table()));
+        let staging = staging_table.to_quoted_string();
+        let target = self.table.to_quoted_string();
+
+        transaction.execute(
+            &format!("CREATE TEMP TABLE {staging} AS SELECT * FROM {target} WHERE 0"),
+            [],
+        )?;
+        // the staging table copies the generated columns as plain columns, which can't be inserted
+        let columns = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| format!(r#""{}""#, field.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.insert_batch_into(transaction, &staging_table, batch, None)?;
+
+        let not_exists =
+            dedup::not_exists_predicate(&target, &staging, &self.schema, dedup_columns, "IS");
+        transaction.execute(
+            &format!(
+                "INSERT INTO {target} ({columns}) SELECT {columns} FROM {staging} WHERE {not_exists}"
+            ),
+            [],
+        )?;
+        transaction.execute(&format!("DROP TABLE {staging}"), [])?;
+
+        Ok(())
+    }
+
+    /// Checks that the batch has the same column names and types as the table, casting the
+    /// retyped columns if the schema mismatch mode is lenient.
+    fn reconcile_batch_schema(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        schema_mismatch::reconcile_batch(batch, &self.schema, self.schema_mismatch).context(
+            SchemaMismatchSnafu {
+                table_name: self.table.to_string(),
+            },
+        )
+    }
+
+    fn validate_batch(&self, batch: &RecordBatch) -> Result<()> {
+        if self.validate_batches {
+            validation::validate_batch(batch).context(InvalidBatchSnafu)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a [`Deduplicator`] for the rows of a single write, if the table has dedup columns.
+    fn deduplicator(&self) -> Result<Option<Deduplicator>> {
+        self.dedup_columns
+            .as_deref()
+            .map(|dedup_columns| Deduplicator::try_new(&self.schema, dedup_columns))
+            .transpose()
+            .context(UnableToDeduplicateBatchSnafu)
+    }
+
+    fn insert_batch_into(
+        &self,
+        transaction: &Transaction<'_>,
+        table: &TableReference,
+        batch: RecordBatch,
+        on_conflict: Option<&OnConflict>,
+    ) -> rusqlite::Result<()> {
+        let schema = batch.schema();
+        let insert_table_builder = InsertBuilder::new(table, vec![batch]);
+
+        let sea_query_on_conflict = on_conflict.map(|oc| oc.build_sea_query_on_conflict(&schema));
 
         let sql = insert_table_builder
             .build_sqlite(sea_query_on_conflict)
@@ -530,7 +1035,8 @@ impl Sqlite {
     ) -> rusqlite::Result<()> {
         let create_table_statement =
             CreateTableBuilder::new(Arc::clone(&self.schema), self.table.table())
-                .primary_keys(primary_keys);
+                .primary_keys(primary_keys)
+                .column_expressions(self.column_expressions.clone());
         let sql = create_table_statement.build_sqlite();
 
         transaction.execute(&sql, [])?;
@@ -802,4 +1308,63 @@ pub(crate) mod tests {
                 .collect::<HashSet<String>>()
         );
     }
+
+    #[tokio::test]
+    async fn test_create_recommended_indexes(
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let pool = Arc::new(
+            SqliteConnectionPoolFactory::new(":memory:", Mode::Memory, Duration::ZERO)
+                .build()
+                .await?,
+        );
+        let mut db_conn = pool.connect().await?;
+        Sqlite::sqlite_conn(&mut db_conn)?
+            .execute("CREATE TABLE orders (id INTEGER, customer_id INTEGER)", &[])
+            .await?;
+
+        let factory = SqliteTableFactory::new(Arc::clone(&pool))
+            .with_index_advisor(Some(Arc::new(IndexAdvisor::new().with_min_filter_count(1))));
+        let ctx = SessionContext::new();
+        ctx.register_table(
+            "orders",
+            factory
+                .table_provider(TableReference::bare("orders"))
+                .await?,
+        )?;
+        ctx.sql("SELECT * FROM orders WHERE customer_id = 7")
+            .await?
+            .collect()
+            .await?;
+
+        let declared = vec![(
+            ColumnReference::new(vec!["id".to_string()]),
+            IndexType::Unique,
+        )];
+        let indexes = factory
+            .create_recommended_indexes(TableReference::bare("orders"), &declared)
+            .await?;
+        assert_eq!(indexes.len(), 2);
+        assert_eq!(
+            indexes[1].0,
+            ColumnReference::new(vec!["customer_id".to_string()])
+        );
+
+        let sqlite = Sqlite::new(
+            TableReference::bare("orders"),
+            Arc::new(Schema::empty()),
+            pool,
+            Constraints::empty(),
+        );
+        let retrieved_indexes = sqlite
+            .get_indexes(Sqlite::sqlite_conn(&mut db_conn)?)
+            .await?;
+        assert_eq!(
+            retrieved_indexes,
+            HashSet::from([
+                "i_orders_id".to_string(),
+                "i_orders_customer_id".to_string()
+            ])
+        );
+        Ok(())
+    }
 }
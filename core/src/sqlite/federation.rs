@@ -1,5 +1,5 @@
 use crate::sql::db_connection_pool::dbconnection::{get_schema, Error as DbError};
-use crate::sql::sql_provider_datafusion::{get_stream, to_execution_error};
+use crate::sql::sql_provider_datafusion::{get_stream_with_params, to_execution_error};
 use async_trait::async_trait;
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::sql::sqlparser::ast::{self, VisitMut};
@@ -77,7 +77,12 @@ impl<T, P> SQLExecutor for SQLiteTable<T, P> {
     }
 
     fn ast_analyzer(&self) -> Option<AstAnalyzer> {
-        Some(Box::new(sqlite_ast_analyzer))
+        let Some(base_analyzer) = self.base_table.ast_analyzer() else {
+            return Some(Box::new(sqlite_ast_analyzer));
+        };
+        Some(Box::new(move |ast| {
+            base_analyzer(sqlite_ast_analyzer(ast)?)
+        }))
     }
 
     fn execute(
@@ -85,10 +90,13 @@ impl<T, P> SQLExecutor for SQLiteTable<T, P> {
         query: &str,
         schema: SchemaRef,
     ) -> DataFusionResult<SendableRecordBatchStream> {
-        let fut = get_stream(
+        let fut = get_stream_with_params(
             self.base_table.clone_pool(),
             query.to_string(),
+            Vec::new(),
+            self.base_table.current_remote_context(),
             Arc::clone(&schema),
+            self.base_table.schema_drift(),
         );
 
         let stream = futures::stream::once(fut).try_flatten();
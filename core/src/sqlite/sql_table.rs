@@ -1,13 +1,22 @@
+use crate::sql::approx_distinct::ApproxDistinct;
 use crate::sql::db_connection_pool::DbConnectionPool;
+use crate::sql::parameters::BoundStatement;
+use crate::sql::pushdown::{FilterSemantics, PushdownPolicy};
+use crate::sql::query_context::QueryContextMode;
+use crate::sql::regexp::RegexSyntax;
+use crate::sql::schema_drift::SchemaDriftPolicy;
+use crate::sqlite::sqlite_dialect;
+use crate::util::indexes::IndexAdvisor;
+use crate::util::memory::limit_stream_memory;
 use async_trait::async_trait;
 use datafusion::catalog::Session;
-use datafusion::sql::unparser::dialect::SqliteDialect;
-use futures::TryStreamExt;
+use datafusion::sql::unparser::dialect::Dialect;
 use std::fmt::Display;
-use std::{any::Any, fmt, sync::Arc};
+use std::{any::Any, fmt, sync::Arc, time::Duration};
 
 use crate::sql::sql_provider_datafusion::{
-    get_stream, to_execution_error, Result as SqlResult, SqlExec, SqlTable,
+    explain::{RemoteExplainer, RemotePlan},
+    get_stream_with_params, to_execution_error, Result as SqlResult, SqlExec, SqlTable,
 };
 use datafusion::{
     arrow::datatypes::SchemaRef,
@@ -16,8 +25,8 @@ use datafusion::{
     execution::TaskContext,
     logical_expr::{Expr, TableProviderFilterPushDown, TableType},
     physical_plan::{
-        stream::RecordBatchStreamAdapter, DisplayAs, DisplayFormatType, ExecutionPlan,
-        PlanProperties, SendableRecordBatchStream,
+        metrics::MetricsSet, DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties,
+        SendableRecordBatchStream,
     },
     sql::TableReference,
 };
@@ -41,23 +50,172 @@ impl<T, P> SQLiteTable<T, P> {
         table_reference: impl Into<TableReference>,
     ) -> Self {
         let base_table = SqlTable::new_with_schema("sqlite", pool, schema, table_reference)
-            .with_dialect(Arc::new(SqliteDialect {}));
+            .with_dialect(sqlite_dialect())
+            .with_filter_semantics(FilterSemantics::sqlite());
 
         Self { base_table }
     }
 
+    #[must_use]
+    pub fn with_dialect(self, dialect: Arc<dyn Dialect + Send + Sync>) -> Self {
+        Self {
+            base_table: self.base_table.with_dialect(dialect),
+        }
+    }
+
+    /// Returns the given string columns dictionary-encoded, see
+    /// [`crate::util::dictionary::dictionary_encode_schema`].
+    #[must_use]
+    pub fn with_dictionary_columns(self, columns: &[String]) -> Self {
+        Self {
+            base_table: self.base_table.with_dictionary_columns(columns),
+        }
+    }
+
+    /// Sets what scans do when the columns returned by the database no longer match the schema of
+    /// the table, see [`SchemaDriftPolicy`].
+    #[must_use]
+    pub fn with_schema_drift(self, schema_drift: SchemaDriftPolicy) -> Self {
+        Self {
+            base_table: self.base_table.with_schema_drift(schema_drift),
+        }
+    }
+
+    /// Buffers up to `memory_batches` batches of each scan in memory and the rest on disk, see
+    /// [`crate::util::spill::spill_stream`].
+    #[must_use]
+    pub fn with_spill_buffer(self, memory_batches: Option<usize>) -> Self {
+        Self {
+            base_table: self.base_table.with_spill_buffer(memory_batches),
+        }
+    }
+
+    /// Waits up to `wait` for the memory of every batch of scans, see
+    /// [`SqlTable::with_memory_wait`].
+    #[must_use]
+    pub fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            base_table: self.base_table.with_memory_wait(wait),
+        }
+    }
+
+    /// Shows the `EXPLAIN QUERY PLAN` of every scan in the `EXPLAIN` output, see
+    /// [`SqlTable::with_remote_explain`].
+    #[must_use]
+    pub fn with_remote_explain(self, remote_explain: bool) -> Self {
+        Self {
+            base_table: self.base_table.with_remote_explain(remote_explain),
+        }
+    }
+
+    /// Explains the SQL of the scans of the table on the remote database, if remote explains are
+    /// enabled.
+    pub(crate) fn remote_explainer(&self) -> Option<Arc<dyn RemoteExplainer>> {
+        self.base_table.remote_explainer()
+    }
+
+    /// The SQL that `plan` runs on the database, if it's a scan of a table of this type.
+    pub(crate) fn scan_sql(plan: &dyn ExecutionPlan) -> Option<String>
+    where
+        T: 'static,
+        P: 'static,
+    {
+        plan.as_any()
+            .downcast_ref::<SQLiteSqlExec<T, P>>()?
+            .sql()
+            .ok()
+    }
+
+    /// Sets what scans push down to the database, see [`SqlTable::with_pushdown_policy`].
+    #[must_use]
+    pub fn with_pushdown_policy(self, pushdown_policy: PushdownPolicy) -> Self {
+        Self {
+            base_table: self.base_table.with_pushdown_policy(pushdown_policy),
+        }
+    }
+
+    /// Sets how SQLite compares the strings of the filters that are pushed down, e.g. with a `LIKE`
+    /// that doesn't ignore case for databases with `PRAGMA case_sensitive_like`, see
+    /// [`SqlTable::with_filter_semantics`].
+    #[must_use]
+    pub fn with_filter_semantics(self, filter_semantics: FilterSemantics) -> Self {
+        Self {
+            base_table: self.base_table.with_filter_semantics(filter_semantics),
+        }
+    }
+
+    /// Pushes down the regex matches as `REGEXP`, for databases with a `regexp()` function, see
+    /// [`SqlTable::with_regex`].
+    #[must_use]
+    pub fn with_regex(self, regex: Option<RegexSyntax>) -> Self {
+        Self {
+            base_table: self.base_table.with_regex(regex),
+        }
+    }
+
+    /// Sets how the `approx_distinct` aggregates of federated queries are pushed down, see
+    /// [`SqlTable::with_approx_distinct`].
+    #[must_use]
+    pub fn with_approx_distinct(self, approx_distinct: Option<ApproxDistinct>) -> Self {
+        Self {
+            base_table: self.base_table.with_approx_distinct(approx_distinct),
+        }
+    }
+
+    /// Binds the string literals of scans as parameters, see [`SqlTable::with_bind_literals`].
+    #[must_use]
+    pub fn with_bind_literals(self, bind_literals: bool) -> Self {
+        Self {
+            base_table: self.base_table.with_bind_literals(bind_literals),
+        }
+    }
+
+    /// Passes the query context of the session to the database, see
+    /// [`SqlTable::with_query_context`].
+    #[must_use]
+    pub fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            base_table: self.base_table.with_query_context(query_context),
+        }
+    }
+
+    /// Counts the columns that the filters of scans use, see [`SqlTable::with_index_advisor`].
+    #[must_use]
+    pub fn with_index_advisor(self, index_advisor: Option<Arc<IndexAdvisor>>) -> Self {
+        Self {
+            base_table: self.base_table.with_index_advisor(index_advisor),
+        }
+    }
+
+    /// Reads the rows of `query` instead of the table, see [`SqlTable::with_query`].
+    #[must_use]
+    pub fn with_query(self, query: impl Into<String>) -> Self {
+        Self {
+            base_table: self.base_table.with_query(query),
+        }
+    }
+
     fn create_physical_plan(
         &self,
         projection: Option<&Vec<usize>>,
         schema: &SchemaRef,
-        sql: String,
+        bound: BoundStatement,
+        remote_plan: Option<RemotePlan>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(SQLiteSqlExec::new(
-            projection,
-            schema,
-            self.base_table.clone_pool(),
-            sql,
-        )?))
+        Ok(Arc::new(
+            SQLiteSqlExec::new(
+                projection,
+                schema,
+                self.base_table.clone_pool(),
+                bound.sql,
+                self.base_table.schema_drift(),
+                self.base_table.spill_buffer(),
+            )?
+            .with_params(bound.params)
+            .with_query_context(self.base_table.query_context().cloned())
+            .with_memory_wait(self.base_table.memory_wait())
+            .with_remote_plan(remote_plan),
+        ))
     }
 }
 
@@ -89,8 +247,12 @@ impl<T, P> TableProvider for SQLiteTable<T, P> {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        let sql = self.base_table.scan_to_sql(projection, filters, limit)?;
-        return self.create_physical_plan(projection, &self.schema(), sql);
+        let statement = self
+            .base_table
+            .scan_to_statement(projection, filters, limit)?;
+        let remote_plan = self.base_table.remote_plan_of_statement(&statement);
+        let bound = self.base_table.bind_statement(statement);
+        return self.create_physical_plan(projection, &self.schema(), bound, remote_plan);
     }
 }
 
@@ -111,12 +273,40 @@ impl<T, P> SQLiteSqlExec<T, P> {
         schema: &SchemaRef,
         pool: Arc<dyn DbConnectionPool<T, P> + Send + Sync>,
         sql: String,
+        schema_drift: SchemaDriftPolicy,
+        spill_buffer: Option<usize>,
     ) -> DataFusionResult<Self> {
-        let base_exec = SqlExec::new(projection, schema, pool, sql)?;
+        let base_exec = SqlExec::new(projection, schema, pool, sql)?
+            .with_schema_drift(schema_drift)
+            .with_spill_buffer(spill_buffer);
 
         Ok(Self { base_exec })
     }
 
+    fn with_params(self, params: Vec<String>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_params(params),
+        }
+    }
+
+    fn with_memory_wait(self, wait: Option<Duration>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_memory_wait(wait),
+        }
+    }
+
+    fn with_query_context(self, query_context: Option<QueryContextMode>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_query_context(query_context),
+        }
+    }
+
+    fn with_remote_plan(self, remote_plan: Option<RemotePlan>) -> Self {
+        Self {
+            base_exec: self.base_exec.with_remote_plan(remote_plan),
+        }
+    }
+
     fn sql(&self) -> SqlResult<String> {
         self.base_exec.sql()
     }
@@ -132,7 +322,8 @@ impl<T, P> std::fmt::Debug for SQLiteSqlExec<T, P> {
 impl<T, P> DisplayAs for SQLiteSqlExec<T, P> {
     fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> std::fmt::Result {
         let sql = self.sql().unwrap_or_default();
-        write!(f, "SQLiteSqlExec sql={sql}")
+        write!(f, "SQLiteSqlExec sql={sql}")?;
+        self.base_exec.fmt_remote_plan(f)
     }
 }
 
@@ -164,18 +355,33 @@ impl<T: 'static, P: 'static> ExecutionPlan for SQLiteSqlExec<T, P> {
         Ok(self)
     }
 
+    fn metrics(&self) -> Option<MetricsSet> {
+        self.base_exec.metrics()
+    }
+
     fn execute(
         &self,
-        _partition: usize,
-        _context: Arc<TaskContext>,
+        partition: usize,
+        context: Arc<TaskContext>,
     ) -> DataFusionResult<SendableRecordBatchStream> {
         let sql = self.sql().map_err(to_execution_error)?;
         tracing::debug!("SQLiteSqlExec sql: {sql}");
 
-        let fut = get_stream(self.base_exec.clone_pool(), sql, Arc::clone(&self.schema()));
+        let fut = get_stream_with_params(
+            self.base_exec.clone_pool(),
+            sql,
+            self.base_exec.params().to_vec(),
+            self.base_exec.remote_context(&context),
+            Arc::clone(&self.schema()),
+            self.base_exec.schema_drift(),
+        );
 
-        let stream = futures::stream::once(fut).try_flatten();
-        let schema = Arc::clone(&self.schema());
-        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+        Ok(limit_stream_memory(
+            self.base_exec.scan_stream(partition, fut),
+            &context,
+            self.base_exec.spill_buffer(),
+            self.base_exec.memory_wait(),
+            format!("SQLiteSqlExec[{partition}]"),
+        ))
     }
 }
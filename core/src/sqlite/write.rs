@@ -18,6 +18,7 @@ use datafusion::{
 use futures::StreamExt;
 use snafu::prelude::*;
 
+use crate::sql::dml::{DmlOperation, DmlTableProvider};
 use crate::util::{
     constraints,
     on_conflict::OnConflict,
@@ -69,6 +70,10 @@ impl TableProvider for SqliteTableWriter {
         Some(self.sqlite.constraints())
     }
 
+    fn get_column_default(&self, column: &str) -> Option<&Expr> {
+        self.sqlite.column_expressions().default_expr(column)
+    }
+
     async fn scan(
         &self,
         state: &dyn Session,
@@ -100,6 +105,16 @@ impl TableProvider for SqliteTableWriter {
     }
 }
 
+#[async_trait]
+impl DmlTableProvider for SqliteTableWriter {
+    async fn execute_dml(&self, operation: &DmlOperation) -> datafusion::error::Result<u64> {
+        self.sqlite
+            .execute_dml(operation)
+            .await
+            .map_err(to_datafusion_error)
+    }
+}
+
 #[derive(Clone)]
 struct SqliteDataSink {
     sqlite: Arc<Sqlite>,
@@ -142,11 +157,25 @@ impl DataSink for SqliteDataSink {
             Sqlite::sqlite_conn(&mut db_conn).map_err(to_retriable_data_write_error)?;
 
         let constraints = self.sqlite.constraints().clone();
+        let mut deduplicator = self.sqlite.deduplicator().map_err(to_datafusion_error)?;
+        let validator = Arc::clone(&self.sqlite);
         let mut data = data;
         let task = tokio::spawn(async move {
             let mut num_rows: u64 = 0;
             while let Some(data_batch) = data.next().await {
                 let data_batch = data_batch.map_err(check_and_mark_retriable_error)?;
+                let mut data_batch = validator
+                    .reconcile_batch_schema(data_batch)
+                    .map_err(to_datafusion_error)?;
+                validator
+                    .validate_batch(&data_batch)
+                    .map_err(to_datafusion_error)?;
+                if let Some(deduplicator) = &mut deduplicator {
+                    data_batch = deduplicator
+                        .dedup(&data_batch)
+                        .context(super::UnableToDeduplicateBatchSnafu)
+                        .map_err(to_datafusion_error)?;
+                }
                 num_rows += u64::try_from(data_batch.num_rows()).map_err(|e| {
                     DataFusionError::Execution(format!("Unable to convert num_rows() to u64: {e}"))
                 })?;
@@ -260,7 +289,7 @@ mod tests {
     use std::{collections::HashMap, sync::Arc};
 
     use datafusion::arrow::{
-        array::{Int64Array, RecordBatch, StringArray},
+        array::{Int32Array, Int64Array, RecordBatch, StringArray},
         datatypes::{DataType, Schema},
     };
     use datafusion::{
@@ -323,4 +352,66 @@ mod tests {
             .await
             .expect("insert successful");
     }
+
+    #[tokio::test]
+    async fn test_insert_with_schema_mismatch() {
+        let schema = Arc::new(Schema::new(vec![
+            datafusion::arrow::datatypes::Field::new("id", DataType::Int64, false),
+            datafusion::arrow::datatypes::Field::new("name", DataType::Utf8, false),
+        ]));
+        let retyped_schema = Arc::new(Schema::new(vec![
+            datafusion::arrow::datatypes::Field::new("id", DataType::Int32, false),
+            datafusion::arrow::datatypes::Field::new("name", DataType::Utf8, false),
+        ]));
+        let data = RecordBatch::try_new(
+            Arc::clone(&retyped_schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["a", "b"])),
+            ],
+        )
+        .expect("data should be created");
+
+        for (mode, table_name) in [("strict", "strict_table"), ("lenient", "lenient_table")] {
+            let external_table = CreateExternalTable {
+                schema: ToDFSchema::to_dfschema_ref(Arc::clone(&schema)).expect("df schema"),
+                name: TableReference::bare(table_name),
+                location: String::new(),
+                file_type: String::new(),
+                table_partition_cols: vec![],
+                if_not_exists: true,
+                definition: None,
+                order_exprs: vec![],
+                unbounded: false,
+                options: HashMap::from([("schema_mismatch".to_string(), mode.to_string())]),
+                constraints: Constraints::empty(),
+                column_defaults: HashMap::default(),
+                temporary: false,
+            };
+            let ctx = SessionContext::new();
+            let table = SqliteTableProviderFactory::default()
+                .create(&ctx.state(), &external_table)
+                .await
+                .expect("table should be created");
+
+            let exec = MockExec::new(vec![Ok(data.clone())], Arc::clone(&retyped_schema));
+            let insertion = table
+                .insert_into(&ctx.state(), Arc::new(exec), InsertOp::Append)
+                .await
+                .expect("insertion should be planned");
+            let result = collect(insertion, ctx.task_ctx()).await;
+
+            if mode == "strict" {
+                let error = result.expect_err("retyped column should fail the insert");
+                assert!(
+                    error
+                        .to_string()
+                        .contains("retyped columns 'id' (Int32 instead of Int64)"),
+                    "unexpected error: {error}"
+                );
+            } else {
+                result.expect("retyped column should be cast");
+            }
+        }
+    }
 }
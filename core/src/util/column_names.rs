@@ -0,0 +1,739 @@
+//! The checks of the column names of the tables that the providers create from Arrow schemas.
+//!
+//! A backend can reject a column name, or take it differently than it's written: Postgres
+//! truncates identifiers longer than 63 bytes, so two long names with the same prefix collide,
+//! and a reserved word like `order` or `user` has to be quoted in every query. The
+//! [`ColumnNamePolicy`] of a table decides whether such names are kept, rejected, or renamed, and
+//! the renames are reported in [`ColumnRenames`].
+
+use std::{collections::HashSet, fmt::Display, str::FromStr, sync::Arc};
+
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use sha2::{Digest, Sha256};
+use snafu::prelude::*;
+
+use super::{column_reference::ColumnReference, on_conflict::OnConflict};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Invalid column name policy '{value}', expected one of: allow, reject, rename"
+    ))]
+    InvalidColumnNamePolicy { value: String },
+
+    #[snafu(display("The column name '{name}' {problem}.\nRename the column, or create the table with the column_name_policy 'rename'."))]
+    InvalidColumnName { name: String, problem: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The reserved key words of Postgres, which can't be column names without quotes.
+const POSTGRES_RESERVED_WORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "binary",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "false",
+    "fetch",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "intersect",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "natural",
+    "not",
+    "notnull",
+    "null",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "outer",
+    "overlaps",
+    "placing",
+    "primary",
+    "references",
+    "returning",
+    "right",
+    "select",
+    "session_user",
+    "similar",
+    "some",
+    "symmetric",
+    "system_user",
+    "table",
+    "tablesample",
+    "then",
+    "to",
+    "trailing",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+/// The reserved key words of MySQL 8, which can't be column names without quotes.
+const MYSQL_RESERVED_WORDS: &[&str] = &[
+    "accessible",
+    "add",
+    "all",
+    "alter",
+    "analyze",
+    "and",
+    "as",
+    "asc",
+    "asensitive",
+    "before",
+    "between",
+    "bigint",
+    "binary",
+    "blob",
+    "both",
+    "by",
+    "call",
+    "cascade",
+    "case",
+    "change",
+    "char",
+    "character",
+    "check",
+    "collate",
+    "column",
+    "condition",
+    "constraint",
+    "continue",
+    "convert",
+    "create",
+    "cross",
+    "cube",
+    "cume_dist",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "cursor",
+    "database",
+    "databases",
+    "day_hour",
+    "day_microsecond",
+    "day_minute",
+    "day_second",
+    "dec",
+    "decimal",
+    "declare",
+    "default",
+    "delayed",
+    "delete",
+    "dense_rank",
+    "desc",
+    "describe",
+    "deterministic",
+    "distinct",
+    "distinctrow",
+    "div",
+    "double",
+    "drop",
+    "dual",
+    "each",
+    "else",
+    "elseif",
+    "empty",
+    "enclosed",
+    "escaped",
+    "except",
+    "exists",
+    "exit",
+    "explain",
+    "false",
+    "fetch",
+    "first_value",
+    "float",
+    "float4",
+    "float8",
+    "for",
+    "force",
+    "foreign",
+    "from",
+    "fulltext",
+    "function",
+    "generated",
+    "get",
+    "grant",
+    "group",
+    "grouping",
+    "groups",
+    "having",
+    "high_priority",
+    "hour_microsecond",
+    "hour_minute",
+    "hour_second",
+    "if",
+    "ignore",
+    "in",
+    "index",
+    "infile",
+    "inner",
+    "inout",
+    "insensitive",
+    "insert",
+    "int",
+    "int1",
+    "int2",
+    "int3",
+    "int4",
+    "int8",
+    "integer",
+    "intersect",
+    "interval",
+    "into",
+    "io_after_gtids",
+    "io_before_gtids",
+    "is",
+    "iterate",
+    "join",
+    "json_table",
+    "key",
+    "keys",
+    "kill",
+    "lag",
+    "last_value",
+    "lateral",
+    "lead",
+    "leading",
+    "leave",
+    "left",
+    "like",
+    "limit",
+    "linear",
+    "lines",
+    "load",
+    "localtime",
+    "localtimestamp",
+    "lock",
+    "long",
+    "longblob",
+    "longtext",
+    "loop",
+    "low_priority",
+    "master_bind",
+    "master_ssl_verify_server_cert",
+    "match",
+    "maxvalue",
+    "mediumblob",
+    "mediumint",
+    "mediumtext",
+    "middleint",
+    "minute_microsecond",
+    "minute_second",
+    "mod",
+    "modifies",
+    "natural",
+    "not",
+    "no_write_to_binlog",
+    "nth_value",
+    "ntile",
+    "null",
+    "numeric",
+    "of",
+    "on",
+    "optimize",
+    "optimizer_costs",
+    "option",
+    "optionally",
+    "or",
+    "order",
+    "out",
+    "outer",
+    "outfile",
+    "over",
+    "partition",
+    "percent_rank",
+    "precision",
+    "primary",
+    "procedure",
+    "purge",
+    "range",
+    "rank",
+    "read",
+    "reads",
+    "read_write",
+    "real",
+    "recursive",
+    "references",
+    "regexp",
+    "release",
+    "rename",
+    "repeat",
+    "replace",
+    "require",
+    "resignal",
+    "restrict",
+    "return",
+    "revoke",
+    "right",
+    "rlike",
+    "row",
+    "rows",
+    "row_number",
+    "schema",
+    "schemas",
+    "second_microsecond",
+    "select",
+    "sensitive",
+    "separator",
+    "set",
+    "show",
+    "signal",
+    "smallint",
+    "spatial",
+    "specific",
+    "sql",
+    "sqlexception",
+    "sqlstate",
+    "sqlwarning",
+    "sql_big_result",
+    "sql_calc_found_rows",
+    "sql_small_result",
+    "ssl",
+    "starting",
+    "stored",
+    "straight_join",
+    "system",
+    "table",
+    "terminated",
+    "then",
+    "tinyblob",
+    "tinyint",
+    "tinytext",
+    "to",
+    "trailing",
+    "trigger",
+    "true",
+    "undo",
+    "union",
+    "unique",
+    "unlock",
+    "unsigned",
+    "update",
+    "usage",
+    "use",
+    "using",
+    "utc_date",
+    "utc_time",
+    "utc_timestamp",
+    "values",
+    "varbinary",
+    "varchar",
+    "varcharacter",
+    "varying",
+    "virtual",
+    "when",
+    "where",
+    "while",
+    "window",
+    "with",
+    "write",
+    "xor",
+    "year_month",
+    "zerofill",
+];
+
+/// What a backend takes as a column name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdentifierRules {
+    max_length: Option<usize>,
+    /// Whether the length is counted in characters rather than bytes.
+    length_in_chars: bool,
+    reserved_words: &'static [&'static str],
+}
+
+impl IdentifierRules {
+    /// Identifiers of at most 63 bytes, which aren't reserved words of Postgres.
+    #[must_use]
+    pub const fn postgres() -> Self {
+        Self {
+            max_length: Some(63),
+            length_in_chars: false,
+            reserved_words: POSTGRES_RESERVED_WORDS,
+        }
+    }
+
+    /// Identifiers of at most 64 characters, which aren't reserved words of MySQL.
+    #[must_use]
+    pub const fn mysql() -> Self {
+        Self {
+            max_length: Some(64),
+            length_in_chars: true,
+            reserved_words: MYSQL_RESERVED_WORDS,
+        }
+    }
+
+    fn length(&self, name: &str) -> usize {
+        if self.length_in_chars {
+            name.chars().count()
+        } else {
+            name.len()
+        }
+    }
+
+    fn is_reserved(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.reserved_words.contains(&name.as_str())
+    }
+
+    /// Why `name` can't be a column name as it is, if it can't.
+    fn problem(&self, name: &str) -> Option<String> {
+        if let Some(max_length) = self.max_length {
+            if self.length(name) > max_length {
+                let unit = if self.length_in_chars {
+                    "characters"
+                } else {
+                    "bytes"
+                };
+                return Some(format!("is longer than {max_length} {unit}"));
+            }
+        }
+        self.is_reserved(name)
+            .then(|| "is a reserved word".to_string())
+    }
+
+    /// The longest prefix of `name` that is at most `max_length` long.
+    fn truncate<'a>(&self, name: &'a str, max_length: usize) -> &'a str {
+        let end = name
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .take_while(|end| self.length(&name[..*end]) <= max_length)
+            .last()
+            .unwrap_or(0);
+        &name[..end]
+    }
+
+    /// `name` renamed to a valid column name: reserved words get a `_` suffix, and long names are
+    /// truncated with a hash of the whole name, which keeps names with the same prefix apart.
+    fn rename(&self, name: &str) -> String {
+        let mut renamed = name.to_string();
+        if self.is_reserved(&renamed) {
+            renamed.push('_');
+        }
+        match self.max_length {
+            Some(max_length) if self.length(&renamed) > max_length => {
+                let digest = Sha256::digest(name.as_bytes());
+                let hash: String = digest.iter().take(4).map(|b| format!("{b:02x}")).collect();
+                let prefix = self.truncate(&renamed, max_length.saturating_sub(hash.len() + 1));
+                format!("{prefix}_{hash}")
+            }
+            _ => renamed,
+        }
+    }
+
+    /// `name` with a `_{n}` suffix, truncated so that it stays valid.
+    fn with_suffix(&self, name: &str, n: usize) -> String {
+        let suffix = format!("_{n}");
+        match self.max_length {
+            Some(max_length) => format!(
+                "{}{suffix}",
+                self.truncate(name, max_length.saturating_sub(suffix.len()))
+            ),
+            None => format!("{name}{suffix}"),
+        }
+    }
+}
+
+/// What table creation does with the column names that a backend can't take as they are, see
+/// [`IdentifierRules`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnNamePolicy {
+    /// Keep the names, which the backend may truncate or require quotes for.
+    #[default]
+    Allow,
+    /// Fail to create the table.
+    Reject,
+    /// Rename the columns and report the renames, see [`ColumnRenames`].
+    Rename,
+}
+
+impl ColumnNamePolicy {
+    /// `schema` with its columns named as the policy says for a backend of `rules`, and the
+    /// columns that were renamed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidColumnName`] if the policy rejects a column name.
+    pub fn apply(
+        self,
+        schema: &SchemaRef,
+        rules: &IdentifierRules,
+    ) -> Result<(SchemaRef, ColumnRenames)> {
+        match self {
+            Self::Allow => Ok((Arc::clone(schema), ColumnRenames::default())),
+            Self::Reject => {
+                for field in schema.fields() {
+                    if let Some(problem) = rules.problem(field.name()) {
+                        return InvalidColumnNameSnafu {
+                            name: field.name(),
+                            problem,
+                        }
+                        .fail();
+                    }
+                }
+                Ok((Arc::clone(schema), ColumnRenames::default()))
+            }
+            Self::Rename => Ok(rename_columns(schema, rules)),
+        }
+    }
+}
+
+fn rename_columns(schema: &SchemaRef, rules: &IdentifierRules) -> (SchemaRef, ColumnRenames) {
+    // the valid names are kept, the renamed columns can't take them
+    let mut taken: HashSet<String> = schema
+        .fields()
+        .iter()
+        .filter(|field| rules.problem(field.name()).is_none())
+        .map(|field| field.name().to_lowercase())
+        .collect();
+
+    let mut renames = Vec::new();
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            if rules.problem(field.name()).is_none() {
+                return Arc::clone(field);
+            }
+            let base = rules.rename(field.name());
+            let mut renamed = base.clone();
+            let mut n = 2;
+            while !taken.insert(renamed.to_lowercase()) {
+                renamed = rules.with_suffix(&base, n);
+                n += 1;
+            }
+            renames.push(ColumnRename {
+                original: field.name().clone(),
+                renamed: renamed.clone(),
+            });
+            Arc::new(Field::clone(field).with_name(renamed))
+        })
+        .collect::<Vec<_>>();
+
+    let schema = Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()));
+    (schema, ColumnRenames { renames })
+}
+
+impl FromStr for ColumnNamePolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "reject" => Ok(Self::Reject),
+            "rename" => Ok(Self::Rename),
+            _ => InvalidColumnNamePolicySnafu { value }.fail(),
+        }
+    }
+}
+
+impl Display for ColumnNamePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Reject => write!(f, "reject"),
+            Self::Rename => write!(f, "rename"),
+        }
+    }
+}
+
+/// A column that was renamed when its table was created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnRename {
+    /// The name of the column in the schema the table was created from.
+    pub original: String,
+    /// The name of the column in the table.
+    pub renamed: String,
+}
+
+/// The columns that were renamed when a table was created, see [`ColumnNamePolicy::Rename`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnRenames {
+    renames: Vec<ColumnRename>,
+}
+
+impl ColumnRenames {
+    #[must_use]
+    pub fn renames(&self) -> &[ColumnRename] {
+        &self.renames
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// The name in the table of the column named `name` in the original schema.
+    #[must_use]
+    pub fn renamed<'a>(&'a self, name: &'a str) -> &'a str {
+        self.renames
+            .iter()
+            .find(|rename| rename.original == name)
+            .map_or(name, |rename| rename.renamed.as_str())
+    }
+
+    /// `columns` with the names of the columns in the table.
+    #[must_use]
+    pub fn column_reference(&self, columns: &ColumnReference) -> ColumnReference {
+        ColumnReference::new(
+            columns
+                .iter()
+                .map(|column| self.renamed(column).to_string())
+                .collect(),
+        )
+    }
+
+    /// `on_conflict` with the names of the columns in the table.
+    #[must_use]
+    pub fn on_conflict(&self, on_conflict: &OnConflict) -> OnConflict {
+        match on_conflict {
+            OnConflict::DoNothingAll => OnConflict::DoNothingAll,
+            OnConflict::DoNothing(columns) => OnConflict::DoNothing(self.column_reference(columns)),
+            OnConflict::Upsert(columns) => OnConflict::Upsert(self.column_reference(columns)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::datatypes::DataType;
+
+    use super::*;
+
+    fn schema(names: &[&str]) -> SchemaRef {
+        Arc::new(Schema::new(
+            names
+                .iter()
+                .map(|name| Field::new(*name, DataType::Utf8, true))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    #[test]
+    fn test_reject_column_names() {
+        let rules = IdentifierRules::postgres();
+        let long = "a".repeat(64);
+
+        assert!(ColumnNamePolicy::Reject
+            .apply(&schema(&["id", "name"]), &rules)
+            .is_ok());
+        assert!(matches!(
+            ColumnNamePolicy::Reject.apply(&schema(&["id", "Order"]), &rules),
+            Err(Error::InvalidColumnName { name, .. }) if name == "Order"
+        ));
+        assert!(ColumnNamePolicy::Reject
+            .apply(&schema(&[&long]), &rules)
+            .is_err());
+        assert!(ColumnNamePolicy::Allow
+            .apply(&schema(&[&long, "order"]), &rules)
+            .is_ok_and(|(_, renames)| renames.is_empty()));
+    }
+
+    #[test]
+    fn test_rename_column_names() {
+        let rules = IdentifierRules::postgres();
+        let long_a = format!("{}_a", "x".repeat(70));
+        let long_b = format!("{}_b", "x".repeat(70));
+        let (renamed, renames) = ColumnNamePolicy::Rename
+            .apply(
+                &schema(&["id", "order", "order_", &long_a, &long_b]),
+                &rules,
+            )
+            .expect("renamed");
+
+        let names = renamed
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names[..2], ["id", "order__2"]);
+        assert_eq!(names[2], "order_");
+        assert!(names[3..].iter().all(|name| name.len() == 63));
+        assert_ne!(names[3], names[4]);
+
+        assert_eq!(renames.renames().len(), 3);
+        assert_eq!(renames.renamed("order"), "order__2");
+        assert_eq!(renames.renamed("id"), "id");
+        assert_eq!(renames.renamed(&long_a), names[3]);
+    }
+
+    #[test]
+    fn test_parse_column_name_policy() {
+        for policy in [
+            ColumnNamePolicy::Allow,
+            ColumnNamePolicy::Reject,
+            ColumnNamePolicy::Rename,
+        ] {
+            assert_eq!(
+                policy.to_string().parse::<ColumnNamePolicy>().unwrap(),
+                policy
+            );
+        }
+        assert!("truncate".parse::<ColumnNamePolicy>().is_err());
+    }
+}
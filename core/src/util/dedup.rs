@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+
+use datafusion::arrow::{
+    array::{BooleanArray, RecordBatch},
+    compute::filter_record_batch,
+    datatypes::{Schema, SchemaRef},
+    error::ArrowError,
+    row::{OwnedRow, RowConverter, SortField},
+};
+use datafusion::common::{utils::quote_identifier, Constraint, Constraints};
+use itertools::Itertools;
+
+use super::{column_reference::ColumnReference, on_conflict::OnConflict};
+
+/// Drops incoming rows that repeat the values of the dedup columns of a row seen earlier in the same write.
+///
+/// Rows are compared the same way `IS NOT DISTINCT FROM` does, so nulls are equal to each other.
+/// The values of every distinct row are kept in memory until the write finishes.
+pub struct Deduplicator {
+    columns: Vec<usize>,
+    converter: RowConverter,
+    seen: HashSet<OwnedRow>,
+}
+
+impl Deduplicator {
+    /// # Errors
+    ///
+    /// Returns an error if any of the columns are missing from the schema, or have a type that can't be compared.
+    pub fn try_new(schema: &SchemaRef, columns: &[String]) -> Result<Self, ArrowError> {
+        let columns = columns
+            .iter()
+            .map(|column| schema.index_of(column))
+            .collect::<Result<Vec<_>, _>>()?;
+        let converter = RowConverter::new(
+            columns
+                .iter()
+                .map(|&index| SortField::new(schema.field(index).data_type().clone()))
+                .collect(),
+        )?;
+
+        Ok(Self {
+            columns,
+            converter,
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Returns the rows of `batch` that haven't been seen before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the dedup columns of the batch can't be converted for comparison.
+    pub fn dedup(&mut self, batch: &RecordBatch) -> Result<RecordBatch, ArrowError> {
+        let columns = self
+            .columns
+            .iter()
+            .map(|&index| batch.column(index).clone())
+            .collect::<Vec<_>>();
+        let rows = self.converter.convert_columns(&columns)?;
+
+        let keep: BooleanArray = rows
+            .iter()
+            .map(|row| Some(self.seen.insert(row.owned())))
+            .collect();
+
+        if keep.true_count() == batch.num_rows() {
+            return Ok(batch.clone());
+        }
+
+        filter_record_batch(batch, &keep)
+    }
+}
+
+/// Returns an `ON CONFLICT DO NOTHING` clause for the dedup columns when the table has a primary key
+/// or unique constraint on exactly those columns, so existing rows are skipped by the database itself.
+pub(crate) fn on_conflict_for_dedup(
+    schema: &SchemaRef,
+    constraints: &Constraints,
+    columns: &[String],
+) -> Option<OnConflict> {
+    let dedup_columns = ColumnReference::new(columns.to_vec());
+
+    constraints
+        .iter()
+        .any(|constraint| {
+            let (Constraint::PrimaryKey(indices) | Constraint::Unique(indices)) = constraint;
+            ColumnReference::new(
+                indices
+                    .iter()
+                    .map(|&index| schema.field(index).name().to_string())
+                    .collect(),
+            ) == dedup_columns
+        })
+        .then_some(OnConflict::DoNothing(dedup_columns))
+}
+
+/// Builds a `NOT EXISTS` predicate that filters out rows of `source` that already exist in `table`,
+/// for an anti-join insert from a staging table. Both names must already be quoted.
+///
+/// Non-nullable columns are compared with `=` so that an index on the table can be used, and the
+/// other columns with `null_safe_eq`.
+pub(crate) fn not_exists_predicate(
+    table: &str,
+    source: &str,
+    schema: &Schema,
+    columns: &[String],
+    null_safe_eq: &str,
+) -> String {
+    let conditions = columns
+        .iter()
+        .map(|column| {
+            let nullable = schema
+                .field_with_name(column)
+                .map_or(true, |field| field.is_nullable());
+            let eq = if nullable { null_safe_eq } else { "=" };
+            let column = quote_identifier(column);
+            format!("{table}.{column} {eq} {source}.{column}")
+        })
+        .join(" AND ");
+
+    format!("NOT EXISTS (SELECT 1 FROM {table} WHERE {conditions})")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::{
+        array::{Int32Array, StringArray},
+        datatypes::{DataType, Field, Schema},
+    };
+
+    use super::*;
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("name", DataType::Utf8, true),
+        ]))
+    }
+
+    #[test]
+    fn test_dedup_across_batches() {
+        let schema = schema();
+        let mut deduplicator =
+            Deduplicator::try_new(&schema, &["id".to_string()]).expect("valid columns");
+
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![
+                    Some(1),
+                    Some(2),
+                    Some(1),
+                    None,
+                    None,
+                ])),
+                Arc::new(StringArray::from(vec!["a", "b", "c", "d", "e"])),
+            ],
+        )
+        .expect("valid batch");
+        let deduped = deduplicator.dedup(&batch).expect("dedup succeeds");
+        assert_eq!(
+            deduped.column(1).as_ref(),
+            &StringArray::from(vec!["a", "b", "d"])
+        );
+
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![2, 3])),
+                Arc::new(StringArray::from(vec!["f", "g"])),
+            ],
+        )
+        .expect("valid batch");
+        let deduped = deduplicator.dedup(&batch).expect("dedup succeeds");
+        assert_eq!(deduped.column(1).as_ref(), &StringArray::from(vec!["g"]));
+    }
+
+    #[test]
+    fn test_missing_dedup_column() {
+        assert!(Deduplicator::try_new(&schema(), &["missing".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_on_conflict_for_dedup() {
+        let schema = schema();
+        let constraints = Constraints::new_unverified(vec![Constraint::PrimaryKey(vec![0])]);
+
+        assert_eq!(
+            on_conflict_for_dedup(&schema, &constraints, &["id".to_string()]),
+            Some(OnConflict::DoNothing(ColumnReference::new(vec![
+                "id".to_string()
+            ])))
+        );
+        assert_eq!(
+            on_conflict_for_dedup(
+                &schema,
+                &constraints,
+                &["id".to_string(), "name".to_string()]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_not_exists_predicate() {
+        assert_eq!(
+            not_exists_predicate(
+                r#""users""#,
+                "s",
+                &schema(),
+                &["id".to_string(), "name".to_string()],
+                "IS NOT DISTINCT FROM"
+            ),
+            r#"NOT EXISTS (SELECT 1 FROM "users" WHERE "users".id IS NOT DISTINCT FROM s.id AND "users".name IS NOT DISTINCT FROM s.name)"#
+        );
+    }
+
+    #[test]
+    fn test_not_exists_predicate_non_nullable_and_quoted() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new(r#"a"b"#, DataType::Utf8, true),
+        ]);
+
+        assert_eq!(
+            not_exists_predicate(
+                r#""users""#,
+                "s",
+                &schema,
+                &["id".to_string(), r#"a"b"#.to_string()],
+                "IS"
+            ),
+            r#"NOT EXISTS (SELECT 1 FROM "users" WHERE "users".id = s.id AND "users"."a""b" IS s."a""b")"#
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use datafusion::sql::{unparser::dialect::Dialect, TableReference};
+use snafu::prelude::*;
+
+use crate::sql::dialect::ExtendedDialect;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display(
+        "Invalid identifier case '{value}', expected one of: preserve, lower, upper, always_quote"
+    ))]
+    InvalidIdentifierCase { value: String },
+}
+
+/// How a provider normalizes the identifiers of a table before it looks the table up.
+///
+/// Databases fold unquoted identifiers differently: Postgres and DuckDB fold them to lower case,
+/// Oracle and Snowflake to upper case, and MySQL and SQLite compare them case-insensitively.
+/// Most dialects quote table references in the generated SQL, so a name like `MyTable` only
+/// matches a table created with exactly that case. Choosing the case the database folds to lets
+/// mixed-case names find tables that were created unquoted.
+///
+/// The normalized table reference is used both to reflect the table schema and in every query
+/// unparsed for the table, so the two always agree. Column names are taken from the reflected
+/// schema as-is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdentifierCase {
+    /// Leave identifiers untouched and quote them the way the provider's dialect does.
+    #[default]
+    Preserve,
+    /// Fold identifiers to lower case and always quote them.
+    Lower,
+    /// Fold identifiers to upper case and always quote them.
+    Upper,
+    /// Leave identifiers untouched but always quote them, even where the dialect wouldn't.
+    AlwaysQuote,
+}
+
+impl IdentifierCase {
+    #[must_use]
+    pub fn normalize(&self, identifier: &str) -> String {
+        match self {
+            Self::Preserve | Self::AlwaysQuote => identifier.to_string(),
+            Self::Lower => identifier.to_lowercase(),
+            Self::Upper => identifier.to_uppercase(),
+        }
+    }
+
+    #[must_use]
+    pub fn normalize_table_reference(&self, table_reference: TableReference) -> TableReference {
+        match self {
+            Self::Preserve | Self::AlwaysQuote => table_reference,
+            Self::Lower | Self::Upper => match table_reference {
+                TableReference::Bare { table } => TableReference::bare(self.normalize(&table)),
+                TableReference::Partial { schema, table } => {
+                    TableReference::partial(self.normalize(&schema), self.normalize(&table))
+                }
+                TableReference::Full {
+                    catalog,
+                    schema,
+                    table,
+                } => TableReference::full(
+                    self.normalize(&catalog),
+                    self.normalize(&schema),
+                    self.normalize(&table),
+                ),
+            },
+        }
+    }
+
+    /// Wraps `dialect` so that it quotes identifiers according to this policy.
+    #[must_use]
+    pub fn dialect(
+        &self,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Arc<dyn Dialect + Send + Sync> {
+        match self {
+            Self::Preserve => dialect,
+            Self::Lower | Self::Upper | Self::AlwaysQuote => {
+                Arc::new(ExtendedDialect::new(dialect).with_quote_all_identifiers())
+            }
+        }
+    }
+}
+
+impl FromStr for IdentifierCase {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "preserve" => Ok(Self::Preserve),
+            "lower" => Ok(Self::Lower),
+            "upper" => Ok(Self::Upper),
+            "always_quote" => Ok(Self::AlwaysQuote),
+            _ => InvalidIdentifierCaseSnafu { value }.fail(),
+        }
+    }
+}
+
+impl Display for IdentifierCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Preserve => write!(f, "preserve"),
+            Self::Lower => write!(f, "lower"),
+            Self::Upper => write!(f, "upper"),
+            Self::AlwaysQuote => write!(f, "always_quote"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::sql::unparser::dialect::DefaultDialect;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize_table_reference() {
+        let table_reference = TableReference::partial("Sales", "MyTable");
+
+        assert_eq!(
+            IdentifierCase::Lower.normalize_table_reference(table_reference.clone()),
+            TableReference::partial("sales", "mytable")
+        );
+        assert_eq!(
+            IdentifierCase::Upper.normalize_table_reference(table_reference.clone()),
+            TableReference::partial("SALES", "MYTABLE")
+        );
+        assert_eq!(
+            IdentifierCase::AlwaysQuote.normalize_table_reference(table_reference.clone()),
+            table_reference
+        );
+    }
+
+    #[test]
+    fn test_always_quote_dialect() {
+        let default_dialect: Arc<dyn Dialect + Send + Sync> = Arc::new(DefaultDialect {});
+        assert_eq!(default_dialect.identifier_quote_style("users"), None);
+
+        for case in [
+            IdentifierCase::Lower,
+            IdentifierCase::Upper,
+            IdentifierCase::AlwaysQuote,
+        ] {
+            let dialect = case.dialect(Arc::clone(&default_dialect));
+            assert_eq!(dialect.identifier_quote_style("users"), Some('"'));
+        }
+
+        let dialect = IdentifierCase::Preserve.dialect(Arc::clone(&default_dialect));
+        assert_eq!(dialect.identifier_quote_style("users"), None);
+    }
+
+    #[test]
+    fn test_parse_identifier_case() {
+        for case in [
+            IdentifierCase::Preserve,
+            IdentifierCase::Lower,
+            IdentifierCase::Upper,
+            IdentifierCase::AlwaysQuote,
+        ] {
+            assert_eq!(case.to_string().parse::<IdentifierCase>().unwrap(), case);
+        }
+        assert!("title".parse::<IdentifierCase>().is_err());
+    }
+}
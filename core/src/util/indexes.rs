@@ -1,4 +1,15 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Display, Formatter},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use datafusion::{
+    logical_expr::{utils::split_conjunction, Between, BinaryExpr, Expr, Operator},
+    sql::TableReference,
+};
+
+use super::column_reference::ColumnReference;
 
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum IndexType {
@@ -25,6 +36,164 @@ impl Display for IndexType {
     }
 }
 
+/// The default number of scans that must filter on a column before it's indexed.
+const DEFAULT_MIN_FILTER_COUNT: u64 = 10;
+
+/// The default number of indexes recommended for a table, on top of the declared ones.
+const DEFAULT_MAX_INDEXES: usize = 3;
+
+/// Counts the columns that the scans of local tables, like the DuckDB and SQLite tables that
+/// remote tables are accelerated into, filter on, and recommends indexes on the most common ones.
+///
+/// A column is counted once per scan whose pushed-down filters compare it with literals, with
+/// `=`, `<`, `>`, `IN` or `BETWEEN`, which an index can answer. The advisor is shared by the tables
+/// of a factory, and keeps the counts of each table apart:
+///
+/// ```rust,ignore
+/// let advisor = Arc::new(IndexAdvisor::new().with_min_filter_count(100));
+/// let factory = DuckDBTableFactory::new(pool).with_index_advisor(Some(Arc::clone(&advisor)));
+/// // after the table is loaded again
+/// factory.create_recommended_indexes(TableReference::bare("orders"), &declared)?;
+/// ```
+#[derive(Debug)]
+pub struct IndexAdvisor {
+    min_filter_count: u64,
+    max_indexes: usize,
+    filter_counts: Mutex<HashMap<TableReference, HashMap<String, u64>>>,
+}
+
+impl Default for IndexAdvisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IndexAdvisor {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            min_filter_count: DEFAULT_MIN_FILTER_COUNT,
+            max_indexes: DEFAULT_MAX_INDEXES,
+            filter_counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The number of scans that must filter on a column before it's indexed, 10 by default.
+    #[must_use]
+    pub fn with_min_filter_count(mut self, min_filter_count: u64) -> Self {
+        self.min_filter_count = min_filter_count;
+        self
+    }
+
+    /// The number of indexes recommended for a table on top of the declared ones, 3 by default.
+    #[must_use]
+    pub fn with_max_indexes(mut self, max_indexes: usize) -> Self {
+        self.max_indexes = max_indexes;
+        self
+    }
+
+    /// Counts the columns that `filters`, the filters of a scan of `table`, can use an index of.
+    pub fn record(&self, table: &TableReference, filters: &[Expr]) {
+        let columns = filters
+            .iter()
+            .flat_map(split_conjunction)
+            .filter_map(indexable_column)
+            .collect::<BTreeSet<_>>();
+        if columns.is_empty() {
+            return;
+        }
+        let mut filter_counts = self.lock();
+        let counts = filter_counts.entry(table.clone()).or_default();
+        for column in columns {
+            *counts.entry(column.to_string()).or_default() += 1;
+        }
+    }
+
+    /// The columns of `table` that scans filtered on, with the number of scans, most filtered
+    /// first.
+    #[must_use]
+    pub fn filter_counts(&self, table: &TableReference) -> Vec<(String, u64)> {
+        let mut counts = self
+            .lock()
+            .get(table)
+            .map(|counts| {
+                counts
+                    .iter()
+                    .map(|(column, count)| (column.clone(), *count))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        counts
+    }
+
+    /// The `declared` indexes of `table`, followed by indexes on the columns that enough scans
+    /// filtered on and that aren't indexed on their own yet.
+    #[must_use]
+    pub fn recommend(
+        &self,
+        table: &TableReference,
+        declared: &[(ColumnReference, IndexType)],
+    ) -> Vec<(ColumnReference, IndexType)> {
+        let mut indexes = declared.to_vec();
+        let recommended = self
+            .filter_counts(table)
+            .into_iter()
+            .filter(|(_, count)| *count >= self.min_filter_count)
+            .map(|(column, _)| ColumnReference::new(vec![column]))
+            .filter(|columns| !declared.iter().any(|(indexed, _)| indexed == columns))
+            .take(self.max_indexes)
+            .map(|columns| (columns, IndexType::Enabled))
+            .collect::<Vec<_>>();
+        indexes.extend(recommended);
+        indexes
+    }
+
+    /// Forgets the counts of `table`, e.g. once it's dropped.
+    pub fn reset(&self, table: &TableReference) {
+        self.lock().remove(table);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<TableReference, HashMap<String, u64>>> {
+        self.filter_counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The column that `predicate` compares with literals, if an index on it can answer it.
+fn indexable_column(predicate: &Expr) -> Option<&str> {
+    let column = |expr: &Expr| match expr {
+        Expr::Column(column) => Some(column.name.as_str()),
+        _ => None,
+    };
+    let is_literal = |expr: &Expr| matches!(expr, Expr::Literal(_));
+    match predicate {
+        Expr::BinaryExpr(BinaryExpr { left, op, right })
+            if matches!(
+                op,
+                Operator::Eq | Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq
+            ) =>
+        {
+            match (column(left), column(right)) {
+                (Some(name), None) if is_literal(right) => Some(name),
+                (None, Some(name)) if is_literal(left) => Some(name),
+                _ => None,
+            }
+        }
+        Expr::InList(in_list) if !in_list.negated && in_list.list.iter().all(is_literal) => {
+            column(&in_list.expr)
+        }
+        Expr::Between(Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        }) if is_literal(low) && is_literal(high) => column(expr),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -39,6 +208,61 @@ mod tests {
         assert_eq!(IndexType::from("ENABLED"), IndexType::Enabled);
     }
 
+    #[test]
+    fn test_index_advisor() {
+        use datafusion::logical_expr::{col, lit};
+
+        let orders = TableReference::bare("orders");
+        let advisor = IndexAdvisor::new()
+            .with_min_filter_count(2)
+            .with_max_indexes(1);
+        for _ in 0..3 {
+            advisor.record(
+                &orders,
+                &[
+                    col("customer_id")
+                        .eq(lit(7))
+                        .and(col("status").not_eq(lit("open"))),
+                    col("created_at").gt(lit(0_i64)),
+                ],
+            );
+        }
+        advisor.record(&orders, &[col("region").in_list(vec![lit("EU")], false)]);
+        advisor.record(&orders, &[col("region").between(lit("A"), lit("F"))]);
+        advisor.record(&TableReference::bare("items"), &[col("sku").eq(lit("a"))]);
+
+        assert_eq!(
+            advisor.filter_counts(&orders),
+            [
+                ("created_at".to_string(), 3),
+                ("customer_id".to_string(), 3),
+                ("region".to_string(), 2),
+            ]
+        );
+
+        let declared = vec![(
+            ColumnReference::new(vec!["created_at".to_string()]),
+            IndexType::Unique,
+        )];
+        assert_eq!(
+            advisor.recommend(&orders, &declared),
+            [
+                declared[0].clone(),
+                (
+                    ColumnReference::new(vec!["customer_id".to_string()]),
+                    IndexType::Enabled
+                ),
+            ]
+        );
+
+        advisor.reset(&orders);
+        assert!(advisor.filter_counts(&orders).is_empty());
+        assert_eq!(
+            advisor.filter_counts(&TableReference::bare("items")).len(),
+            1
+        );
+    }
+
     #[test]
     fn test_indexes_from_option_string() {
         let indexes_option_str = "index1:unique;index2";
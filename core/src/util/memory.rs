@@ -0,0 +1,208 @@
+use std::time::{Duration, Instant};
+
+use datafusion::{
+    error::Result as DataFusionResult,
+    execution::{
+        memory_pool::{MemoryConsumer, MemoryReservation},
+        SendableRecordBatchStream, TaskContext,
+    },
+    physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::StreamExt;
+
+use super::spill::spill_stream;
+
+/// How long a waiting scan waits at most before it asks the memory pool again.
+const MAX_MEMORY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Holds the batches of a remote scan in the memory pool of `context`, as configured by
+/// `spill_buffer` and `memory_wait`:
+///
+/// - with a `spill_buffer`, the scan reads ahead into a buffer that reserves the batches it keeps
+///   in memory and writes the others to disk once the pool is full, see [`spill_stream`]. The scan
+///   never waits for memory.
+/// - with a `memory_wait` but no `spill_buffer`, the scan reserves the memory of every batch before
+///   it reads it, and waits up to `memory_wait` for the pool to have it, see
+///   [`reserve_stream_memory`].
+/// - with neither, the default, the batches aren't accounted in the pool.
+#[must_use]
+pub fn limit_stream_memory(
+    stream: SendableRecordBatchStream,
+    context: &TaskContext,
+    spill_buffer: Option<usize>,
+    memory_wait: Option<Duration>,
+    name: impl Into<String>,
+) -> SendableRecordBatchStream {
+    match (spill_buffer, memory_wait) {
+        (Some(memory_batches), _) => spill_stream(stream, context, memory_batches),
+        (None, Some(wait)) => reserve_stream_memory(stream, context, name, wait),
+        (None, None) => stream,
+    }
+}
+
+/// Accounts the batches of a remote scan in the memory pool of `context`, so that the database is
+/// only read as fast as the pool has memory for the batches.
+///
+/// The size of a batch is only known once it's read, so the memory of the next batch is estimated
+/// by the size of the last one and reserved before the next one is read, on top of the memory of
+/// the last batch, which the consumer is still working on. The reservation is then resized to the
+/// size of the batch that was read. If the pool can't grant the memory, the scan asks again at
+/// growing intervals, and fails with `ResourcesExhausted` if the other consumers of the pool don't
+/// release it within `wait`. Scans that wait for each other, like the two inputs of a join, can
+/// only be resolved by failing, so `wait` should be short.
+///
+/// The reservation is released when the stream is dropped.
+#[must_use]
+pub fn reserve_stream_memory(
+    stream: SendableRecordBatchStream,
+    context: &TaskContext,
+    name: impl Into<String>,
+    wait: Duration,
+) -> SendableRecordBatchStream {
+    let reservation = MemoryConsumer::new(name).register(context.memory_pool());
+    let schema = stream.schema();
+    let stream = futures::stream::unfold(
+        (stream, reservation),
+        move |(mut stream, mut reservation)| async move {
+            let estimate = reservation.size();
+            if let Err(e) = grow(&mut reservation, estimate, wait).await {
+                return Some((Err(e), (stream, reservation)));
+            }
+            let batch = match stream.next().await? {
+                Ok(batch) => batch,
+                Err(e) => return Some((Err(e), (stream, reservation))),
+            };
+            // the previous batch is released once the next one has been read
+            reservation.resize(batch.get_array_memory_size());
+            Some((Ok(batch), (stream, reservation)))
+        },
+    );
+    Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+}
+
+/// Grows `reservation` by `size`, waiting up to `wait` for the pool to have the memory.
+async fn grow(
+    reservation: &mut MemoryReservation,
+    size: usize,
+    wait: Duration,
+) -> DataFusionResult<()> {
+    let deadline = Instant::now() + wait;
+    let mut interval = Duration::from_millis(1);
+    loop {
+        match reservation.try_grow(size) {
+            Ok(()) => return Ok(()),
+            Err(e) if Instant::now() >= deadline => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(
+                    interval.min(deadline.saturating_duration_since(Instant::now())),
+                )
+                .await;
+                interval = (interval * 2).min(MAX_MEMORY_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::{
+        arrow::{
+            array::{Int64Array, RecordBatch},
+            datatypes::{DataType, Field, Schema},
+        },
+        error::DataFusionError,
+        execution::{memory_pool::GreedyMemoryPool, runtime_env::RuntimeEnvBuilder},
+        prelude::{SessionConfig, SessionContext},
+    };
+
+    use super::*;
+
+    fn task_context(limit: usize) -> Arc<TaskContext> {
+        let runtime = RuntimeEnvBuilder::new()
+            .with_memory_pool(Arc::new(GreedyMemoryPool::new(limit)))
+            .build_arc()
+            .expect("runtime created");
+        SessionContext::new_with_config_rt(SessionConfig::new(), runtime).task_ctx()
+    }
+
+    fn stream(rows: usize) -> SendableRecordBatchStream {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int64Array::from_iter_values(0..rows as i64))],
+        )
+        .expect("record batch created");
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            futures::stream::iter(vec![Ok(batch.clone()), Ok(batch)]),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_limit_stream_memory() {
+        // the batches aren't accounted by default
+        let context = task_context(1);
+        let batches = limit_stream_memory(stream(1000), &context, None, None, "test")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(batches.len(), 2);
+        assert!(batches.iter().all(Result::is_ok));
+
+        // the batches that don't fit in the pool are spilled instead of waiting for memory
+        let batches = limit_stream_memory(
+            stream(1000),
+            &context,
+            Some(2),
+            Some(Duration::from_secs(30)),
+            "test",
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(batches.len(), 2);
+        assert!(batches.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_reserve_stream_memory() {
+        let context = task_context(1024 * 1024);
+        let batches = reserve_stream_memory(stream(1000), &context, "test", Duration::ZERO)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(batches.len(), 2);
+        assert!(batches.iter().all(Result::is_ok));
+        // the reservation is released with the stream
+        assert_eq!(context.memory_pool().reserved(), 0);
+
+        // the second batch isn't read while the pool has no memory for it
+        let context = task_context(1024);
+        let mut stream =
+            reserve_stream_memory(stream(1000), &context, "test", Duration::from_millis(50));
+        assert!(matches!(stream.next().await, Some(Ok(_))));
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(DataFusionError::ResourcesExhausted(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reserve_stream_memory_waits() {
+        let context = task_context(16 * 1024);
+        let mut stream =
+            reserve_stream_memory(stream(1000), &context, "test", Duration::from_secs(30));
+        assert!(matches!(stream.next().await, Some(Ok(_))));
+
+        let mut other = MemoryConsumer::new("other").register(context.memory_pool());
+        other
+            .try_grow(16 * 1024 - context.memory_pool().reserved())
+            .expect("memory reserved");
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(other);
+        });
+        assert!(matches!(stream.next().await, Some(Ok(_))));
+        assert!(stream.next().await.is_none());
+        release.await.expect("memory released");
+    }
+}
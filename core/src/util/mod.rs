@@ -6,17 +6,27 @@ use std::collections::HashMap;
 
 use crate::UnsupportedTypeAction;
 
+pub mod column_names;
 pub mod column_reference;
 pub mod constraints;
+pub mod dedup;
+pub mod dictionary;
+pub mod identifier;
 pub mod indexes;
+pub mod memory;
+pub mod normalize;
 pub mod ns_lookup;
 pub mod on_conflict;
+pub mod redact;
 pub mod retriable_error;
 
 #[cfg(any(feature = "sqlite", feature = "duckdb", feature = "postgres"))]
 pub mod schema;
+pub mod schema_mismatch;
 pub mod secrets;
+pub mod spill;
 pub mod test;
+pub mod validation;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -61,12 +71,14 @@ pub fn remove_prefix_from_hashmap_keys<V>(
         .collect()
 }
 
+/// Wraps `error` in a [`DataFusionError`], with its message redacted as set by
+/// [`redact::set_error_redaction`].
 #[must_use]
 pub fn to_datafusion_error<E>(error: E) -> DataFusionError
 where
     E: std::error::Error + Send + Sync + 'static,
 {
-    DataFusionError::External(Box::new(error))
+    DataFusionError::External(redact::redact_error(Box::new(error), None))
 }
 
 /// If the `UnsupportedTypeAction` is `Error` or `String`, the function will return an error.
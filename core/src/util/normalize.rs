@@ -0,0 +1,254 @@
+//! Converts the Arrow encodings a writer doesn't take into ones it does, so that inserts work
+//! whichever encoding the upstream plan chose.
+//!
+//! Run-end encoded arrays are always decoded. Dictionaries, the large types like `LargeUtf8` and
+//! the view types like `Utf8View` are converted to the plain types unless the writer takes them
+//! as they are, see [`SupportedEncodings`].
+
+use std::sync::Arc;
+
+use datafusion::arrow::{
+    array::{Array, ArrayRef, AsArray, RecordBatch, RunArray, UInt32Array},
+    compute::{cast, take},
+    datatypes::{
+        DataType, Field, Int16Type, Int32Type, Int64Type, RunEndIndexType, Schema, SchemaRef,
+    },
+    error::ArrowError,
+};
+
+/// The Arrow encodings a writer takes as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SupportedEncodings {
+    /// `Dictionary` arrays.
+    pub dictionaries: bool,
+    /// `LargeUtf8`, `LargeBinary` and `LargeList` arrays.
+    pub large_types: bool,
+    /// `Utf8View` and `BinaryView` arrays.
+    pub view_types: bool,
+}
+
+impl SupportedEncodings {
+    /// The encodings DuckDB reads from an Arrow stream and appends. Dictionaries are decoded, as
+    /// the appender doesn't take them.
+    #[must_use]
+    pub fn duckdb() -> Self {
+        Self {
+            dictionaries: false,
+            large_types: true,
+            view_types: false,
+        }
+    }
+
+    /// The encodings of the `INSERT` statements built by
+    /// [`InsertBuilder`](crate::sql::arrow_sql_gen::statement::InsertBuilder), for SQLite, Postgres
+    /// and MySQL.
+    #[must_use]
+    pub fn insert_statement() -> Self {
+        Self {
+            dictionaries: false,
+            large_types: true,
+            view_types: true,
+        }
+    }
+
+    /// `data_type` in the encodings the writer takes.
+    #[must_use]
+    pub fn normalize_data_type(self, data_type: &DataType) -> DataType {
+        match data_type {
+            DataType::RunEndEncoded(_, values) => self.normalize_data_type(values.data_type()),
+            DataType::Dictionary(_, value_type) if !self.dictionaries => {
+                self.normalize_data_type(value_type)
+            }
+            DataType::LargeUtf8 if !self.large_types => DataType::Utf8,
+            DataType::LargeBinary if !self.large_types => DataType::Binary,
+            DataType::LargeList(item) if !self.large_types => {
+                DataType::List(self.normalize_field(item))
+            }
+            DataType::Utf8View if !self.view_types => DataType::Utf8,
+            DataType::BinaryView if !self.view_types => DataType::Binary,
+            DataType::List(item) => DataType::List(self.normalize_field(item)),
+            DataType::LargeList(item) => DataType::LargeList(self.normalize_field(item)),
+            DataType::FixedSizeList(item, size) => {
+                DataType::FixedSizeList(self.normalize_field(item), *size)
+            }
+            _ => data_type.clone(),
+        }
+    }
+
+    fn normalize_field(self, field: &Arc<Field>) -> Arc<Field> {
+        Arc::new(
+            field
+                .as_ref()
+                .clone()
+                .with_data_type(self.normalize_data_type(field.data_type())),
+        )
+    }
+
+    /// Returns true if some columns of `schema` aren't in the encodings the writer takes.
+    #[must_use]
+    pub fn needs_normalization(self, schema: &SchemaRef) -> bool {
+        schema
+            .fields()
+            .iter()
+            .any(|field| &self.normalize_data_type(field.data_type()) != field.data_type())
+    }
+
+    /// `schema` with its columns in the encodings the writer takes.
+    #[must_use]
+    pub fn normalize_schema(self, schema: &SchemaRef) -> SchemaRef {
+        if !self.needs_normalization(schema) {
+            return Arc::clone(schema);
+        }
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|field| self.normalize_field(field))
+            .collect::<Vec<_>>();
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    }
+
+    /// Converts the columns of `batch` to the encodings the writer takes. Other columns are
+    /// returned unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column can't be converted.
+    pub fn normalize_batch(self, batch: RecordBatch) -> Result<RecordBatch, ArrowError> {
+        let schema = batch.schema();
+        if !self.needs_normalization(&schema) {
+            return Ok(batch);
+        }
+
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|column| self.normalize_array(column))
+            .collect::<Result<Vec<_>, _>>()?;
+        RecordBatch::try_new(self.normalize_schema(&schema), columns)
+    }
+
+    fn normalize_array(self, array: &ArrayRef) -> Result<ArrayRef, ArrowError> {
+        if let DataType::RunEndEncoded(run_ends, _) = array.data_type() {
+            let decoded = match run_ends.data_type() {
+                DataType::Int16 => decode_runs(array.as_run::<Int16Type>())?,
+                DataType::Int32 => decode_runs(array.as_run::<Int32Type>())?,
+                DataType::Int64 => decode_runs(array.as_run::<Int64Type>())?,
+                data_type => {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Run ends of type {data_type} aren't supported"
+                    )))
+                }
+            };
+            return self.normalize_array(&decoded);
+        }
+
+        let data_type = self.normalize_data_type(array.data_type());
+        if &data_type == array.data_type() {
+            return Ok(Arc::clone(array));
+        }
+        cast(array, &data_type)
+    }
+}
+
+/// The values of the runs of `array`, repeated over the length of each run.
+fn decode_runs<R: RunEndIndexType>(array: &RunArray<R>) -> Result<ArrayRef, ArrowError> {
+    let logical_indices = (0..array.len())
+        .map(|i| u32::try_from(i).map_err(|e| ArrowError::ComputeError(e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let physical_indices = array
+        .get_physical_indices(&logical_indices)?
+        .into_iter()
+        .map(|i| u32::try_from(i).map_err(|e| ArrowError::ComputeError(e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    take(
+        array.values().as_ref(),
+        &UInt32Array::from(physical_indices),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::array::{
+        BinaryViewArray, DictionaryArray, Int32Array, Int64Array, LargeStringArray, StringArray,
+        StringViewArray,
+    };
+
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        let run_ends = Int32Array::from(vec![2, 3]);
+        let values = StringArray::from(vec![Some("a"), None]);
+        let status = RunArray::<Int32Type>::try_new(&run_ends, &values).expect("run array created");
+        let mood: DictionaryArray<Int32Type> = vec!["happy", "sad", "happy"].into_iter().collect();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("status", status.data_type().clone(), true),
+            Field::new("mood", mood.data_type().clone(), true),
+            Field::new("name", DataType::LargeUtf8, true),
+            Field::new("note", DataType::Utf8View, true),
+            Field::new("payload", DataType::BinaryView, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(status),
+                Arc::new(mood),
+                Arc::new(LargeStringArray::from(vec!["x", "y", "z"])),
+                Arc::new(StringViewArray::from(vec![Some("n"), None, Some("o")])),
+                Arc::new(BinaryViewArray::from(vec![
+                    Some(b"p".as_ref()),
+                    None,
+                    Some(b"q".as_ref()),
+                ])),
+            ],
+        )
+        .expect("record batch created")
+    }
+
+    #[test]
+    fn test_normalize_batch_for_duckdb() {
+        let normalized = SupportedEncodings::duckdb()
+            .normalize_batch(batch())
+            .expect("batch normalized");
+        let schema = normalized.schema();
+        assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(2).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(3).data_type(), &DataType::LargeUtf8);
+        assert_eq!(schema.field(4).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(5).data_type(), &DataType::Binary);
+
+        let status = normalized.column(1).as_string::<i32>();
+        assert_eq!(status.value(0), "a");
+        assert_eq!(status.value(1), "a");
+        assert!(status.is_null(2));
+    }
+
+    #[test]
+    fn test_normalize_batch_for_insert_statement() {
+        let normalized = SupportedEncodings::insert_statement()
+            .normalize_batch(batch())
+            .expect("batch normalized");
+        let schema = normalized.schema();
+        assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(2).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(3).data_type(), &DataType::LargeUtf8);
+        assert_eq!(schema.field(4).data_type(), &DataType::Utf8View);
+        assert_eq!(normalized.column(2).as_string::<i32>().value(1), "sad");
+    }
+
+    #[test]
+    fn test_normalize_data_type_in_lists() {
+        let list = DataType::LargeList(Arc::new(Field::new("item", DataType::LargeUtf8, true)));
+        assert_eq!(
+            SupportedEncodings::default().normalize_data_type(&list),
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+        );
+        assert_eq!(
+            SupportedEncodings::default().normalize_data_type(&DataType::Int32),
+            DataType::Int32
+        );
+    }
+}
@@ -1,8 +1,28 @@
 use std::error::Error;
+use std::time::Duration;
 
 use datafusion::error::DataFusionError;
 use snafu::Snafu;
 
+/// The number of times a single batch is retried under a savepoint before the write transaction is aborted.
+pub const MAX_BATCH_RETRIES: usize = 3;
+
+const BATCH_RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+const BATCH_RETRY_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// The delay before retry `attempt`, counted from 1, of a batch: doubles from 50ms up to 2s, plus
+/// up to 50% of random jitter so the transactions of a deadlock don't retry in lockstep.
+#[must_use]
+pub fn batch_retry_backoff(attempt: usize) -> Duration {
+    let exponent = u32::try_from(attempt.saturating_sub(1))
+        .unwrap_or(u32::MAX)
+        .min(16);
+    let delay = BATCH_RETRY_BASE_DELAY
+        .saturating_mul(1 << exponent)
+        .min(BATCH_RETRY_MAX_DELAY);
+    delay + delay.mul_f64(rand::random::<f64>() / 2.0)
+}
+
 #[derive(Debug, Snafu)]
 pub enum RetriableError {
     #[snafu(display("{source}"))]
@@ -63,3 +83,26 @@ fn is_invalid_query_error(error: &DataFusionError) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_retry_backoff() {
+        for (attempt, delay) in [
+            (1, 50),
+            (2, 100),
+            (3, 200),
+            (10, 2_000),
+            (usize::MAX, 2_000),
+        ] {
+            let delay = Duration::from_millis(delay);
+            let backoff = batch_retry_backoff(attempt);
+            assert!(
+                backoff >= delay && backoff <= delay + delay / 2,
+                "attempt {attempt}: {backoff:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Compares the batches written to a table with the schema of the table, so that writes of data
+//! with added, missing or retyped columns fail with the differences instead of an error from deep
+//! inside the insert.
+
+use std::{fmt, sync::Arc};
+
+use datafusion::arrow::{
+    array::{ArrayRef, RecordBatch},
+    compute::{can_cast_types, cast},
+    datatypes::{DataType, Schema, SchemaRef},
+    error::ArrowError,
+};
+use snafu::prelude::*;
+
+use super::normalize::SupportedEncodings;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("The data to insert doesn't match the schema of the table: {diff}"))]
+    SchemaMismatch { diff: SchemaDiff },
+
+    #[snafu(display("Unable to cast column '{column}' from {from} to {to}: {source}"))]
+    UnableToCastColumn {
+        column: String,
+        from: DataType,
+        to: DataType,
+        source: ArrowError,
+    },
+
+    #[snafu(display("Unable to build the batch with the cast columns: {source}"))]
+    UnableToBuildCastBatch { source: ArrowError },
+
+    #[snafu(display("Invalid schema_mismatch value '{value}', expected 'strict' or 'lenient'"))]
+    InvalidSchemaMismatchMode { value: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What a write does with a batch whose columns differ from the ones of the table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaMismatchMode {
+    /// Fails the write with [`Error::SchemaMismatch`].
+    #[default]
+    Strict,
+    /// Casts the retyped columns to the types of the table when Arrow can cast between them.
+    /// Writes with added or missing columns still fail.
+    Lenient,
+}
+
+impl TryFrom<&str> for SchemaMismatchMode {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "strict" => Ok(Self::Strict),
+            "lenient" => Ok(Self::Lenient),
+            _ => InvalidSchemaMismatchModeSnafu { value }.fail(),
+        }
+    }
+}
+
+/// A column whose type in the batch differs from its type in the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetypedColumn {
+    pub name: String,
+    pub expected: DataType,
+    pub found: DataType,
+}
+
+/// How the columns of a batch differ from the columns of a table, matched by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Columns of the batch that the table doesn't have.
+    pub added: Vec<String>,
+    /// Columns of the table that the batch doesn't have.
+    pub missing: Vec<String>,
+    /// Columns with another type in the batch.
+    pub retyped: Vec<RetypedColumn>,
+}
+
+impl SchemaDiff {
+    /// Compares the columns of `batch` with the ones of `table`, or returns `None` if they match.
+    ///
+    /// Nullability, nested field names and the column order are not compared. Neither are the
+    /// encodings of a type, like `LargeUtf8` or a dictionary of `Utf8` for `Utf8`, which the
+    /// writers convert, see [`SupportedEncodings`].
+    #[must_use]
+    pub fn between(table: &Schema, batch: &Schema) -> Option<Self> {
+        let mut diff = Self::default();
+        for field in table.fields() {
+            match batch.field_with_name(field.name()) {
+                Ok(found) if !is_equivalent(field.data_type(), found.data_type()) => {
+                    diff.retyped.push(RetypedColumn {
+                        name: field.name().clone(),
+                        expected: field.data_type().clone(),
+                        found: found.data_type().clone(),
+                    });
+                }
+                Ok(_) => {}
+                Err(_) => diff.missing.push(field.name().clone()),
+            }
+        }
+        diff.added = batch
+            .fields()
+            .iter()
+            .filter(|field| table.field_with_name(field.name()).is_err())
+            .map(|field| field.name().clone())
+            .collect();
+
+        (!diff.is_empty()).then_some(diff)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.missing.is_empty() && self.retyped.is_empty()
+    }
+
+    /// Whether [`SchemaMismatchMode::Lenient`] can write the batch, by casting its retyped
+    /// columns.
+    #[must_use]
+    pub fn is_castable(&self) -> bool {
+        self.added.is_empty()
+            && self.missing.is_empty()
+            && self
+                .retyped
+                .iter()
+                .all(|column| can_cast_types(&column.found, &column.expected))
+    }
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut changes = Vec::new();
+        if !self.added.is_empty() {
+            changes.push(format!("added columns {}", quoted(&self.added)));
+        }
+        if !self.missing.is_empty() {
+            changes.push(format!("missing columns {}", quoted(&self.missing)));
+        }
+        if !self.retyped.is_empty() {
+            let retyped = self
+                .retyped
+                .iter()
+                .map(|column| {
+                    format!(
+                        "'{}' ({} instead of {})",
+                        column.name, column.found, column.expected
+                    )
+                })
+                .collect::<Vec<_>>();
+            changes.push(format!("retyped columns {}", retyped.join(", ")));
+        }
+        write!(f, "{}", changes.join("; "))
+    }
+}
+
+fn quoted(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|column| format!("'{column}'"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn is_equivalent(expected: &DataType, found: &DataType) -> bool {
+    let plain = SupportedEncodings::default();
+    plain
+        .normalize_data_type(expected)
+        .equals_datatype(&plain.normalize_data_type(found))
+}
+
+/// Checks `batch` against the schema of the table it's written to, casting its retyped columns
+/// in [`SchemaMismatchMode::Lenient`].
+///
+/// # Errors
+///
+/// Returns [`Error::SchemaMismatch`] if the columns differ and `mode` can't reconcile them, or an
+/// error if a value can't be cast.
+pub fn reconcile_batch(
+    batch: RecordBatch,
+    table: &SchemaRef,
+    mode: SchemaMismatchMode,
+) -> Result<RecordBatch> {
+    let Some(diff) = SchemaDiff::between(table, &batch.schema()) else {
+        return Ok(batch);
+    };
+    if mode == SchemaMismatchMode::Strict || !diff.is_castable() {
+        return SchemaMismatchSnafu { diff }.fail();
+    }
+
+    tracing::debug!("Casting the batch to the schema of the table: {diff}");
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.num_columns());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match diff
+            .retyped
+            .iter()
+            .find(|retyped| &retyped.name == field.name())
+        {
+            Some(retyped) => {
+                let column = cast(column, &retyped.expected).context(UnableToCastColumnSnafu {
+                    column: field.name(),
+                    from: retyped.found.clone(),
+                    to: retyped.expected.clone(),
+                })?;
+                fields.push(Arc::new(
+                    field
+                        .as_ref()
+                        .clone()
+                        .with_data_type(retyped.expected.clone()),
+                ));
+                columns.push(column);
+            }
+            None => {
+                fields.push(Arc::clone(field));
+                columns.push(Arc::clone(column));
+            }
+        }
+    }
+
+    RecordBatch::try_new(
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())),
+        columns,
+    )
+    .context(UnableToBuildCastBatchSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::{
+        array::{Array, Int32Array, Int64Array, LargeStringArray, StringArray},
+        datatypes::Field,
+    };
+
+    use super::*;
+
+    fn table_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]))
+    }
+
+    fn batch(fields: Vec<Field>, columns: Vec<ArrayRef>) -> RecordBatch {
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).expect("record batch created")
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let batch = batch(
+            vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("email", DataType::Utf8, true),
+            ],
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(StringArray::from(vec!["a@example.com"])),
+            ],
+        );
+
+        let diff = SchemaDiff::between(&table_schema(), &batch.schema()).expect("schemas differ");
+        assert_eq!(diff.added, vec!["email".to_string()]);
+        assert_eq!(diff.missing, vec!["name".to_string()]);
+        assert_eq!(
+            diff.retyped,
+            vec![RetypedColumn {
+                name: "id".to_string(),
+                expected: DataType::Int64,
+                found: DataType::Int32,
+            }]
+        );
+        assert!(!diff.is_castable());
+
+        let error = reconcile_batch(batch, &table_schema(), SchemaMismatchMode::Lenient)
+            .expect_err("columns are missing");
+        assert_eq!(
+            error.to_string(),
+            "The data to insert doesn't match the schema of the table: added columns 'email'; missing columns 'name'; retyped columns 'id' (Int32 instead of Int64)"
+        );
+    }
+
+    #[test]
+    fn test_encodings_are_not_retyped() {
+        let batch = batch(
+            vec![
+                Field::new("name", DataType::LargeUtf8, true),
+                Field::new("id", DataType::Int64, true),
+            ],
+            vec![
+                Arc::new(LargeStringArray::from(vec!["a"])),
+                Arc::new(Int64Array::from(vec![1])),
+            ],
+        );
+        assert!(SchemaDiff::between(&table_schema(), &batch.schema()).is_none());
+    }
+
+    #[test]
+    fn test_reconcile_batch() {
+        let drifted = || {
+            batch(
+                vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("name", DataType::Utf8, true),
+                ],
+                vec![
+                    Arc::new(Int32Array::from(vec![1, 2])),
+                    Arc::new(StringArray::from(vec!["a", "b"])),
+                ],
+            )
+        };
+
+        let error = reconcile_batch(drifted(), &table_schema(), SchemaMismatchMode::Strict)
+            .expect_err("schema mismatch");
+        assert!(matches!(error, Error::SchemaMismatch { .. }));
+
+        let batch = reconcile_batch(drifted(), &table_schema(), SchemaMismatchMode::Lenient)
+            .expect("batch cast");
+        assert!(SchemaDiff::between(&table_schema(), &batch.schema()).is_none());
+        assert_eq!(
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .expect("cast column"),
+            &Int64Array::from(vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn test_parse_schema_mismatch_mode() {
+        assert_eq!(
+            SchemaMismatchMode::try_from("strict").expect("valid mode"),
+            SchemaMismatchMode::Strict
+        );
+        assert_eq!(
+            SchemaMismatchMode::try_from("lenient").expect("valid mode"),
+            SchemaMismatchMode::Lenient
+        );
+        assert!(SchemaMismatchMode::try_from("cast").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs::File;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        ipc::{reader::FileReader, writer::FileWriter},
+    },
+    common::runtime::SpawnedTask,
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::{
+        disk_manager::{DiskManager, RefCountedTempFile},
+        memory_pool::{MemoryConsumer, MemoryReservation},
+        SendableRecordBatchStream, TaskContext,
+    },
+    physical_plan::stream::RecordBatchStreamAdapter,
+};
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc;
+
+enum Buffered {
+    /// A batch and the memory reserved for it, released once it's read.
+    Memory(RecordBatch, MemoryReservation),
+    Disk(RefCountedTempFile),
+}
+
+/// Reads `stream` to the end as fast as the remote database sends it, buffering the batches the
+/// consumer hasn't pulled yet.
+///
+/// Without a buffer, a slow consumer keeps the connection (and, for some databases, a snapshot or
+/// transaction) open for as long as it takes to process the results. With it, the connection is
+/// released as soon as the last batch is read. Up to `memory_batches` batches are buffered in
+/// memory, as long as the memory pool of `context` grants their memory, further batches are
+/// written to Arrow IPC files in the temporary directory of the [`DiskManager`] of `context`. The
+/// memory and the files are released once their batch has been read, or when the stream is
+/// dropped.
+#[must_use]
+pub fn spill_stream(
+    stream: SendableRecordBatchStream,
+    context: &TaskContext,
+    memory_batches: usize,
+) -> SendableRecordBatchStream {
+    let schema = stream.schema();
+    let disk_manager = Arc::clone(&context.runtime_env().disk_manager);
+    let reservation = MemoryConsumer::new("remote scan buffer")
+        .with_can_spill(true)
+        .register(context.memory_pool());
+    // the reader is spawned when the stream is first polled, which happens on the Tokio runtime
+    let stream = futures::stream::once(async move {
+        buffer_stream(stream, disk_manager, reservation, memory_batches)
+    })
+    .flatten();
+
+    Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+}
+
+fn buffer_stream(
+    mut stream: SendableRecordBatchStream,
+    disk_manager: Arc<DiskManager>,
+    reservation: MemoryReservation,
+    memory_batches: usize,
+) -> impl Stream<Item = DataFusionResult<RecordBatch>> {
+    let (batch_tx, batch_rx) = mpsc::unbounded_channel::<DataFusionResult<Buffered>>();
+    let in_memory = Arc::new(AtomicUsize::new(0));
+
+    let buffered_in_memory = Arc::clone(&in_memory);
+    let reader = SpawnedTask::spawn(async move {
+        while let Some(batch) = stream.next().await {
+            let buffered = match batch {
+                Ok(batch) if buffered_in_memory.load(Ordering::Acquire) < memory_batches => {
+                    let mut batch_reservation = reservation.new_empty();
+                    match batch_reservation.try_grow(batch.get_array_memory_size()) {
+                        Ok(()) => {
+                            buffered_in_memory.fetch_add(1, Ordering::AcqRel);
+                            Ok(Buffered::Memory(batch, batch_reservation))
+                        }
+                        // spilled once the pool is out of memory
+                        Err(_) => spill_batch(Arc::clone(&disk_manager), batch).await,
+                    }
+                }
+                // spilled once the buffer is full
+                Ok(batch) => spill_batch(Arc::clone(&disk_manager), batch).await,
+                Err(e) => Err(e),
+            };
+            let failed = buffered.is_err();
+            if batch_tx.send(buffered).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    // the reader is aborted if the stream is dropped before the end
+    futures::stream::unfold(
+        (batch_rx, reader, in_memory),
+        |(mut batch_rx, reader, in_memory)| async move {
+            let batch = match batch_rx.recv().await? {
+                // the memory of the batch is reserved by its consumer from now on
+                Ok(Buffered::Memory(batch, _reservation)) => {
+                    in_memory.fetch_sub(1, Ordering::AcqRel);
+                    Ok(batch)
+                }
+                Ok(Buffered::Disk(file)) => read_spilled_batch(file).await,
+                Err(e) => Err(e),
+            };
+            Some((batch, (batch_rx, reader, in_memory)))
+        },
+    )
+}
+
+/// Writes `batch` to a temporary file on a blocking thread, so the IO doesn't hold up the runtime.
+async fn spill_batch(
+    disk_manager: Arc<DiskManager>,
+    batch: RecordBatch,
+) -> DataFusionResult<Buffered> {
+    let file = tokio::task::spawn_blocking(move || {
+        let file = disk_manager.create_tmp_file("remote scan buffer")?;
+        let mut writer = FileWriter::try_new(File::create(file.path())?, &batch.schema())?;
+        writer.write(&batch)?;
+        writer.finish()?;
+        Ok::<_, DataFusionError>(file)
+    })
+    .await
+    .map_err(|e| DataFusionError::Execution(format!("Unable to buffer a batch on disk: {e}")))??;
+    Ok(Buffered::Disk(file))
+}
+
+/// Reads the batch of `file` on a blocking thread, deleting the file once it's read.
+async fn read_spilled_batch(file: RefCountedTempFile) -> DataFusionResult<RecordBatch> {
+    tokio::task::spawn_blocking(move || {
+        let mut reader = FileReader::try_new(File::open(file.path())?, None)?;
+        reader.next().transpose()?.ok_or_else(|| {
+            DataFusionError::Execution(format!(
+                "The buffered batch in {} is missing",
+                file.path().display()
+            ))
+        })
+    })
+    .await
+    .map_err(|e| DataFusionError::Execution(format!("Unable to read a buffered batch: {e}")))?
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        arrow::{
+            array::Int64Array,
+            datatypes::{DataType, Field, Schema},
+        },
+        physical_plan::common::collect,
+        prelude::{SessionConfig, SessionContext},
+    };
+
+    use super::*;
+
+    fn batches() -> Vec<RecordBatch> {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        (0..5)
+            .map(|i| {
+                RecordBatch::try_new(
+                    Arc::clone(&schema),
+                    vec![Arc::new(Int64Array::from(vec![i, i + 1]))],
+                )
+                .expect("record batch created")
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_spill_stream() {
+        let context = SessionContext::new().task_ctx();
+        for memory_batches in [0, 2, 10] {
+            let batches = batches();
+            let stream = Box::pin(RecordBatchStreamAdapter::new(
+                batches[0].schema(),
+                futures::stream::iter(batches.clone().into_iter().map(Ok)),
+            ));
+
+            let buffered = collect(spill_stream(stream, &context, memory_batches))
+                .await
+                .expect("batches read");
+            assert_eq!(buffered, batches);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spill_stream_memory_pool() {
+        use datafusion::execution::{
+            memory_pool::GreedyMemoryPool, runtime_env::RuntimeEnvBuilder,
+        };
+
+        let runtime = RuntimeEnvBuilder::new()
+            .with_memory_pool(Arc::new(GreedyMemoryPool::new(1)))
+            .build_arc()
+            .expect("runtime created");
+        let context = SessionContext::new_with_config_rt(SessionConfig::new(), runtime).task_ctx();
+        let batches = batches();
+        let stream = Box::pin(RecordBatchStreamAdapter::new(
+            batches[0].schema(),
+            futures::stream::iter(batches.clone().into_iter().map(Ok)),
+        ));
+
+        // the batches that don't fit in the pool are spilled instead of failing
+        let buffered = collect(spill_stream(stream, &context, 10))
+            .await
+            .expect("batches read");
+        assert_eq!(buffered, batches);
+        assert_eq!(context.memory_pool().reserved(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_spill_stream_error() {
+        let context = SessionContext::new().task_ctx();
+        let batches = batches();
+        let stream = Box::pin(RecordBatchStreamAdapter::new(
+            batches[0].schema(),
+            futures::stream::iter(vec![
+                Ok(batches[0].clone()),
+                Err(DataFusionError::Execution("connection lost".to_string())),
+                Ok(batches[1].clone()),
+            ]),
+        ));
+
+        let results = spill_stream(stream, &context, 0).collect::<Vec<_>>().await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use datafusion::arrow::{
+    array::{Array, RecordBatch},
+    datatypes::DataType,
+    error::ArrowError,
+};
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Invalid data in column '{column}' ({data_type}): {source}"))]
+    InvalidColumnData {
+        column: String,
+        data_type: DataType,
+        source: ArrowError,
+    },
+
+    #[snafu(display(
+        "Column '{column}' is not nullable but has {null_count} null values, the first at row {row}"
+    ))]
+    UnexpectedNulls {
+        column: String,
+        null_count: usize,
+        row: usize,
+    },
+
+    #[snafu(display("Invalid value for validate_batches: '{value}', expected 'true' or 'false'"))]
+    InvalidValidateBatchesOption { value: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Parses the `validate_batches` table option.
+pub fn parse_validate_batches(value: &str) -> Result<bool> {
+    value
+        .parse()
+        .ok()
+        .context(InvalidValidateBatchesOptionSnafu { value })
+}
+
+/// Checks that the arrays of `batch` are well-formed before they are written.
+///
+/// Arrow arrays built by UDFs, FFI or `unsafe` code can skip the checks done when
+/// arrays are constructed, and the database drivers read them assuming they hold. A malformed array
+/// then fails deep inside the driver (or crashes the DuckDB appender) instead of returning an error.
+/// This validates each column fully, including nested children:
+/// - string and binary offsets are monotonic and within the values buffer
+/// - strings are valid UTF-8
+/// - the null count matches the validity buffer
+/// - dictionary keys are within the dictionary values
+///
+/// It also checks that non-nullable columns hold no nulls. Validation reads all of the data, so it is
+/// opt-in with the `validate_batches` option of the table writers.
+pub fn validate_batch(batch: &RecordBatch) -> Result<()> {
+    let schema = batch.schema();
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        column
+            .to_data()
+            .validate_full()
+            .context(InvalidColumnDataSnafu {
+                column: field.name(),
+                data_type: field.data_type().clone(),
+            })?;
+
+        if !field.is_nullable() && column.null_count() > 0 {
+            let row = (0..column.len())
+                .find(|&i| column.is_null(i))
+                .unwrap_or_default();
+            return UnexpectedNullsSnafu {
+                column: field.name(),
+                null_count: column.null_count(),
+                row,
+            }
+            .fail();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::{
+        array::{make_array, ArrayData, Int32Array, StringArray, StructArray},
+        buffer::Buffer,
+        datatypes::{Field, Schema},
+    };
+
+    use super::*;
+
+    fn batch_with(field: Field, data: ArrayData) -> RecordBatch {
+        RecordBatch::try_new(Arc::new(Schema::new(vec![field])), vec![make_array(data)])
+            .expect("record batch created")
+    }
+
+    /// Builds a string array without validating it, like a broken FFI producer would.
+    fn unchecked_strings(offsets: &[i32], values: &[u8]) -> ArrayData {
+        let builder = ArrayData::builder(DataType::Utf8)
+            .len(offsets.len() - 1)
+            .add_buffer(Buffer::from_slice_ref(offsets))
+            .add_buffer(Buffer::from_slice_ref(values));
+        unsafe { builder.build_unchecked() }
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        let batch = batch_with(
+            Field::new("name", DataType::Utf8, false),
+            StringArray::from(vec!["a", "b"]).into_data(),
+        );
+        assert!(validate_batch(&batch).is_ok());
+
+        let batch = batch_with(
+            Field::new("name", DataType::Utf8, true),
+            unchecked_strings(&[0, 2], &[0xff, 0xfe]),
+        );
+        let error = validate_batch(&batch).expect_err("invalid UTF-8");
+        assert!(matches!(error, Error::InvalidColumnData { ref column, .. } if column == "name"));
+
+        let batch = batch_with(
+            Field::new("name", DataType::Utf8, true),
+            unchecked_strings(&[0, 3, 1], b"abc"),
+        );
+        assert!(validate_batch(&batch).is_err());
+
+        // RecordBatch::try_new rejects nulls in non-nullable columns, producers over FFI don't
+        let strings = StringArray::from(vec![Some("a"), None, None]);
+        let batch = RecordBatch::from(unsafe {
+            StructArray::new_unchecked(
+                vec![Field::new("name", DataType::Utf8, false)].into(),
+                vec![Arc::new(strings) as _],
+                None,
+            )
+        });
+        assert_eq!(
+            validate_batch(&batch).expect_err("nulls").to_string(),
+            "Column 'name' is not nullable but has 2 null values, the first at row 1"
+        );
+    }
+
+    #[test]
+    fn test_validate_dictionary_keys() {
+        let data_type = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+        let builder = ArrayData::builder(data_type.clone())
+            .len(2)
+            .add_buffer(Int32Array::from(vec![0, 5]).into_data().buffers()[0].clone())
+            .add_child_data(StringArray::from(vec!["a"]).into_data());
+        let data = unsafe { builder.build_unchecked() };
+
+        let batch = batch_with(Field::new("status", data_type, true), data);
+        assert!(matches!(
+            validate_batch(&batch),
+            Err(Error::InvalidColumnData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_validate_batches() {
+        assert!(parse_validate_batches("true").expect("valid option"));
+        assert!(!parse_validate_batches("false").expect("valid option"));
+        assert!(parse_validate_batches("yes").is_err());
+    }
+}
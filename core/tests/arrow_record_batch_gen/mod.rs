@@ -3,10 +3,11 @@ use datafusion::arrow::array::RecordBatch;
 use datafusion::arrow::{
     array::*,
     datatypes::{
-        i256, DataType, Date32Type, Date64Type, Field, Int8Type, IntervalDayTime,
+        i256, DataType, Date32Type, Date64Type, Field, Int32Type, Int8Type, IntervalDayTime,
         IntervalMonthDayNano, IntervalUnit, Schema, SchemaRef, TimeUnit,
     },
 };
+use datafusion_table_providers::sql::arrow_sql_gen::enum_type::{enum_field, EnumType};
 use std::sync::Arc;
 
 // Helper functions to create arrow record batches of different types
@@ -766,6 +767,34 @@ pub(crate) fn get_arrow_dictionary_array_record_batch() -> (RecordBatch, SchemaR
     (record_batch, schema)
 }
 
+// INT32_DICTIONARY_ARRAY, like the Postgres enums are read
+pub(crate) fn get_arrow_int32_dictionary_array_record_batch() -> (RecordBatch, SchemaRef) {
+    let mut builder = StringDictionaryBuilder::<Int32Type>::new();
+    builder.append_value("happy");
+    builder.append_value("sad");
+    builder.append_value("neutral");
+    let array: DictionaryArray<Int32Type> = builder.finish();
+
+    let enum_type = EnumType::new(
+        "mood",
+        vec![
+            "happy".to_string(),
+            "sad".to_string(),
+            "neutral".to_string(),
+        ],
+    );
+    let schema = Arc::new(Schema::new(vec![enum_field(
+        "mood_status",
+        &enum_type,
+        true,
+    )]));
+
+    let record_batch = RecordBatch::try_new(Arc::clone(&schema), vec![Arc::new(array)])
+        .expect("Failed to created arrow int32 dictionary array record batch");
+
+    (record_batch, schema)
+}
+
 fn parse_json_to_batch(json_data: &str, schema: SchemaRef) -> RecordBatch {
     let reader = arrow_json::ReaderBuilder::new(schema)
         .build(std::io::Cursor::new(json_data))
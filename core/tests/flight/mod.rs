@@ -12,8 +12,10 @@ use arrow_flight::{
     FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest, HandshakeResponse, Ticket,
 };
 use async_trait::async_trait;
-use datafusion::arrow::array::{Array, Float32Array, Int64Array, Int8Array, RecordBatch};
-use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::array::{
+    Array, DictionaryArray, Float32Array, Int64Array, Int8Array, RecordBatch,
+};
+use datafusion::arrow::datatypes::{DataType, Field, Int32Type, Schema};
 use datafusion::prelude::SessionContext;
 use futures::{stream, Stream, TryStreamExt};
 use prost::Message;
@@ -233,3 +235,68 @@ async fn test_flight_sql_data_source() -> datafusion::common::Result<()> {
     assert_eq!(arr.iter().next().unwrap().unwrap(), 300);
     Ok(())
 }
+
+#[rstest]
+#[test_log::test(tokio::test)]
+async fn test_flight_sql_influxdb_profile() -> datafusion::common::Result<()> {
+    let partition_data = RecordBatch::try_new(
+        Arc::new(Schema::new([
+            Arc::new(Field::new(
+                "host",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                false,
+            )),
+            Arc::new(Field::new("usage", DataType::Float32, false)),
+        ])),
+        vec![
+            Arc::new(DictionaryArray::<Int32Type>::from_iter(["a", "b", "a"])),
+            Arc::new(Float32Array::from(vec![0.1, 0.2, 0.3])),
+        ],
+    )?;
+
+    let query = "SELECT * FROM cpu";
+    let ticket_payload = TicketStatementQuery::default().as_any().encode_to_vec();
+    let endpoint = FlightEndpoint::default().with_ticket(Ticket::new(ticket_payload));
+    let flight_info = FlightInfo::default()
+        .try_with_schema(partition_data.schema().as_ref())?
+        .with_endpoint(endpoint);
+    let (tx, rx) = channel();
+    let service = TestFlightSqlService {
+        flight_info,
+        partition_data,
+        // no handshake, the token is sent with every call instead
+        expected_handshake_headers: HashMap::new(),
+        expected_flight_info_query: query.into(),
+        shutdown_sender: Some(tx),
+    };
+    let port = service.run_in_background(rx).await.port();
+    let ctx = SessionContext::new();
+    ctx.state_ref().write().table_factories_mut().insert(
+        "FLIGHT_SQL".into(),
+        Arc::new(FlightTableFactory::new(Arc::new(FlightSqlDriver::new()))),
+    );
+    let _ = ctx
+        .sql(&format!(
+            r#"
+        CREATE EXTERNAL TABLE influx STORED AS FLIGHT_SQL
+        LOCATION 'http://localhost:{port}'
+        OPTIONS(
+            'server_profile' 'influxdb',
+            'flight.sql.password' 'flight-sql-token',
+            'flight.sql.query' '{query}',
+        )"#
+        ))
+        .await
+        .unwrap();
+    let rb = ctx
+        .sql("select host from influx")
+        .await?
+        .collect()
+        .await?
+        .first()
+        .cloned()
+        .expect("no record batch");
+    assert_eq!(rb.schema().field(0).data_type(), &DataType::Utf8);
+    assert_eq!(rb.num_rows(), 3);
+    Ok(())
+}
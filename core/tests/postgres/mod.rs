@@ -6,7 +6,9 @@ use arrow::{
 use datafusion::execution::context::SessionContext;
 use datafusion::logical_expr::CreateExternalTable;
 use datafusion::physical_plan::collect;
-use datafusion::{catalog::TableProviderFactory, logical_expr::dml::InsertOp};
+use datafusion::{
+    catalog::TableProviderFactory, datasource::TableProvider, logical_expr::dml::InsertOp,
+};
 use datafusion::{
     common::{Constraints, ToDFSchema},
     datasource::memory::MemorySourceConfig,
@@ -15,7 +17,10 @@ use datafusion::{
 use datafusion_federation::schema_cast::record_convert::try_cast_to;
 
 use datafusion_table_providers::{
-    postgres::{DynPostgresConnectionPool, PostgresTableProviderFactory},
+    postgres::{
+        transaction::WriteTransaction, write::PostgresTableWriter, DynPostgresConnectionPool,
+        OverwriteMode, PostgresTableFactory, PostgresTableProviderFactory,
+    },
     sql::sql_provider_datafusion::SqlTable,
     UnsupportedTypeAction,
 };
@@ -157,6 +162,7 @@ async fn start_container(manager: &mut MutexGuard<'_, ContainerManager>) {
 #[case::list(get_arrow_list_record_batch(), "list")]
 #[case::null(get_arrow_null_record_batch(), "null")]
 #[case::bytea_array(get_arrow_bytea_array_record_batch(), "bytea_array")]
+#[case::dictionary(get_arrow_dictionary_array_record_batch(), "dictionary")]
 #[test_log::test(tokio::test)]
 async fn test_arrow_postgres_roundtrip(
     container_manager: &Mutex<ContainerManager>,
@@ -190,6 +196,11 @@ async fn test_arrow_postgres_one_way(container_manager: &Mutex<ContainerManager>
     test_postgres_enum_type(container_manager.port).await;
     test_postgres_numeric_type(container_manager.port).await;
     test_postgres_jsonb_type(container_manager.port).await;
+    test_postgres_insert_overwrite(container_manager.port).await;
+    test_postgres_overwrite_modes(container_manager.port).await;
+    test_postgres_write_transaction(container_manager.port).await;
+    test_postgres_inherited_tables(container_manager.port).await;
+    test_postgres_session_variables(container_manager.port).await;
 }
 
 async fn test_postgres_enum_type(port: usize) {
@@ -203,7 +214,7 @@ async fn test_postgres_enum_type(port: usize) {
     INSERT INTO person_mood (mood_status) VALUES ('happy'), ('sad'), ('neutral');
     ";
 
-    let (expected_record, _) = get_arrow_dictionary_array_record_batch();
+    let (expected_record, _) = get_arrow_int32_dictionary_array_record_batch();
 
     arrow_postgres_one_way(
         port,
@@ -289,11 +300,7 @@ async fn test_postgres_jsonb_type(port: usize) {
     ('{"nested": {"key": "value"}}');
     "#;
 
-    let schema = Arc::new(Schema::new(vec![Field::new(
-        "data",
-        DataType::Utf8,
-        true,
-    )]));
+    let schema = Arc::new(Schema::new(vec![Field::new("data", DataType::Utf8, true)]));
 
     // Parse and re-serialize the JSON to ensure consistent ordering
     let expected_values = vec![
@@ -314,9 +321,7 @@ async fn test_postgres_jsonb_type(port: usize) {
 
     let expected_record = RecordBatch::try_new(
         Arc::clone(&schema),
-        vec![Arc::new(arrow::array::StringArray::from(
-            expected_values,
-        ))],
+        vec![Arc::new(arrow::array::StringArray::from(expected_values))],
     )
     .expect("Failed to create arrow record batch");
 
@@ -332,6 +337,392 @@ async fn test_postgres_jsonb_type(port: usize) {
     .await;
 }
 
+async fn test_postgres_insert_overwrite(port: usize) {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, true),
+    ]));
+    let batch = |ids: Vec<i64>, names: Vec<&str>| {
+        RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(arrow::array::Int64Array::from(ids)),
+                Arc::new(arrow::array::StringArray::from(names)),
+            ],
+        )
+        .expect("record batch created")
+    };
+
+    let factory = PostgresTableProviderFactory::new();
+    let ctx = SessionContext::new();
+    let cmd = CreateExternalTable {
+        schema: Arc::clone(&schema).to_dfschema_ref().expect("to df schema"),
+        name: "overwrite_values".into(),
+        location: "".to_string(),
+        file_type: "".to_string(),
+        table_partition_cols: vec![],
+        if_not_exists: false,
+        definition: None,
+        order_exprs: vec![],
+        unbounded: false,
+        options: common::get_pg_params(port),
+        constraints: Constraints::empty(),
+        column_defaults: HashMap::new(),
+        temporary: false,
+    };
+    let table_provider = factory
+        .create(&ctx.state(), &cmd)
+        .await
+        .expect("table provider created");
+
+    for (op, record) in [
+        (InsertOp::Append, batch(vec![1, 2, 3], vec!["a", "b", "c"])),
+        (InsertOp::Overwrite, batch(vec![4, 5], vec!["d", "e"])),
+    ] {
+        let mem_exec = MemorySourceConfig::try_new_exec(&[vec![record]], Arc::clone(&schema), None)
+            .expect("memory exec created");
+        let insert_plan = table_provider
+            .insert_into(&ctx.state(), mem_exec, op)
+            .await
+            .expect("insert plan created");
+        collect(insert_plan, ctx.task_ctx())
+            .await
+            .expect("insert done");
+    }
+
+    ctx.register_table("overwrite_values", table_provider)
+        .expect("Table should be registered");
+    let record_batch = ctx
+        .sql("SELECT id FROM overwrite_values ORDER BY id")
+        .await
+        .expect("DataFrame should be created from query")
+        .collect()
+        .await
+        .expect("RecordBatch should be collected");
+
+    let ids = record_batch
+        .iter()
+        .flat_map(|batch| {
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<arrow::array::Int64Array>()
+                .expect("id column")
+                .values()
+                .to_vec()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec![4, 5]);
+}
+
+async fn test_postgres_overwrite_modes(port: usize) {
+    let pool = Arc::new(
+        common::get_postgres_connection_pool(port)
+            .await
+            .expect("Postgres connection pool should be created"),
+    );
+
+    let db_conn = pool
+        .connect_direct()
+        .await
+        .expect("Connection should be established");
+    db_conn
+        .conn
+        .batch_execute(
+            "CREATE TABLE overwrite_serial (id SERIAL PRIMARY KEY, name TEXT);
+            CREATE VIEW overwrite_serial_names AS SELECT name FROM overwrite_serial;
+            INSERT INTO overwrite_serial (name) VALUES ('a'), ('b'), ('c');
+            CREATE TABLE overwrite_plain (name TEXT);
+            INSERT INTO overwrite_plain VALUES ('a'), ('b'), ('c');",
+        )
+        .await
+        .expect("Postgres tables should be created");
+
+    let overwrite = |mode: OverwriteMode, table: &'static str, batch: RecordBatch| {
+        let pool = Arc::clone(&pool);
+        async move {
+            let ctx = SessionContext::new();
+            let provider = PostgresTableFactory::new(pool)
+                .with_overwrite_mode(mode)
+                .read_write_table_provider(table.into())
+                .await
+                .expect("table provider created");
+            let mem_exec =
+                MemorySourceConfig::try_new_exec(&[vec![batch]], provider.schema(), None)
+                    .expect("memory exec created");
+            let insert_plan = provider
+                .insert_into(&ctx.state(), mem_exec, InsertOp::Overwrite)
+                .await
+                .expect("insert plan created");
+            collect(insert_plan, ctx.task_ctx())
+                .await
+                .expect("insert done");
+        }
+    };
+    let count = |sql: &'static str| {
+        let conn = &db_conn.conn;
+        async move {
+            conn.query_one(sql, &[])
+                .await
+                .expect("query ran")
+                .get::<_, i64>(0)
+        }
+    };
+
+    let serial_batch = |ids: Vec<i32>, names: Vec<&str>| {
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("name", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(arrow::array::Int32Array::from(ids)),
+                Arc::new(arrow::array::StringArray::from(names)),
+            ],
+        )
+        .expect("record batch created")
+    };
+
+    // the view and the sequence owned by the serial column keep working with either mode,
+    // the swap falls back to deleting the rows of a table with dependents
+    for mode in [OverwriteMode::Delete, OverwriteMode::Swap] {
+        overwrite(
+            mode,
+            "overwrite_serial",
+            serial_batch(vec![100, 101], vec!["x", "y"]),
+        )
+        .await;
+        assert_eq!(
+            count("SELECT COUNT(*) FROM overwrite_serial_names").await,
+            2
+        );
+    }
+    db_conn
+        .conn
+        .execute("INSERT INTO overwrite_serial (name) VALUES ('z')", &[])
+        .await
+        .expect("serial column still has its sequence");
+
+    let plain_batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![Field::new("name", DataType::Utf8, true)])),
+        vec![Arc::new(arrow::array::StringArray::from(vec!["x"]))],
+    )
+    .expect("record batch created");
+    overwrite(OverwriteMode::Swap, "overwrite_plain", plain_batch).await;
+    assert_eq!(
+        count("SELECT COUNT(*) FROM overwrite_plain WHERE name = 'x'").await,
+        1
+    );
+    assert_eq!(count("SELECT COUNT(*) FROM overwrite_plain").await, 1);
+}
+
+async fn test_postgres_write_transaction(port: usize) {
+    let pool = Arc::new(
+        common::get_postgres_connection_pool(port)
+            .await
+            .expect("Postgres connection pool should be created"),
+    );
+
+    let db_conn = pool
+        .connect_direct()
+        .await
+        .expect("Connection should be established");
+    db_conn
+        .conn
+        .batch_execute(
+            "CREATE TABLE tx_parents (id BIGINT PRIMARY KEY);
+            CREATE TABLE tx_children (id BIGINT PRIMARY KEY, parent_id BIGINT NOT NULL REFERENCES tx_parents (id));",
+        )
+        .await
+        .expect("Postgres tables should be created");
+
+    let factory = PostgresTableFactory::new(Arc::clone(&pool));
+    let parents = factory
+        .read_write_table_provider("tx_parents".into())
+        .await
+        .expect("table provider created");
+    let children = factory
+        .read_write_table_provider("tx_children".into())
+        .await
+        .expect("table provider created");
+    let writer = |provider: &Arc<dyn TableProvider>| {
+        provider
+            .as_any()
+            .downcast_ref::<PostgresTableWriter>()
+            .expect("downcast to PostgresTableWriter")
+            .clone()
+    };
+    let (parents, children) = (writer(&parents), writer(&children));
+
+    let parent_batch = RecordBatch::try_new(
+        parents.schema(),
+        vec![Arc::new(arrow::array::Int64Array::from(vec![1, 2]))],
+    )
+    .expect("record batch created");
+    let child_batch = |parent_id: i64| {
+        RecordBatch::try_new(
+            children.schema(),
+            vec![
+                Arc::new(arrow::array::Int64Array::from(vec![10, 11])),
+                Arc::new(arrow::array::Int64Array::from(vec![1, parent_id])),
+            ],
+        )
+        .expect("record batch created")
+    };
+
+    // a child referencing a missing parent fails the whole transaction
+    let mut transaction = WriteTransaction::new(Arc::clone(&pool));
+    transaction
+        .stage(&parents, InsertOp::Append, vec![parent_batch.clone()])
+        .expect("parents staged")
+        .stage(&children, InsertOp::Append, vec![child_batch(3)])
+        .expect("children staged");
+    assert!(transaction.commit().await.is_err());
+
+    let count = |table: &'static str| {
+        let db_conn = &db_conn;
+        async move {
+            db_conn
+                .conn
+                .query_one(&format!("SELECT COUNT(*) FROM {table}"), &[])
+                .await
+                .expect("count queried")
+                .get::<_, i64>(0)
+        }
+    };
+    assert_eq!(count("tx_parents").await, 0);
+
+    let mut transaction = WriteTransaction::new(Arc::clone(&pool));
+    transaction
+        .stage(&parents, InsertOp::Append, vec![parent_batch])
+        .expect("parents staged")
+        .stage(&children, InsertOp::Append, vec![child_batch(2)])
+        .expect("children staged");
+    assert_eq!(transaction.commit().await.expect("committed"), 4);
+
+    assert_eq!(count("tx_parents").await, 2);
+    assert_eq!(count("tx_children").await, 2);
+}
+
+async fn test_postgres_session_variables(port: usize) {
+    use datafusion_table_providers::sql::db_connection_pool::dbconnection::AsyncDbConnection;
+
+    let pool = common::get_postgres_connection_pool(port)
+        .await
+        .expect("Postgres connection pool should be created");
+    let db_conn = pool
+        .connect_direct()
+        .await
+        .expect("Connection should be established");
+
+    let tenant_id = || {
+        let db_conn = &db_conn;
+        async move {
+            let stream = db_conn
+                .query_arrow(
+                    "SELECT current_setting('app.tenant_id', true) AS tenant_id",
+                    &[],
+                    None,
+                )
+                .await
+                .expect("query run");
+            let batches = collect_batches(stream).await;
+            let tenant_ids = batches[0]
+                .column(0)
+                .as_any()
+                .downcast_ref::<arrow::array::StringArray>()
+                .expect("text column");
+            tenant_ids
+                .is_valid(0)
+                .then(|| tenant_ids.value(0).to_string())
+                .filter(|tenant_id| !tenant_id.is_empty())
+        }
+    };
+
+    db_conn
+        .set_session_variables(&[("app.tenant_id".to_string(), "42".to_string())])
+        .await
+        .expect("variables set");
+    assert_eq!(tenant_id().await.as_deref(), Some("42"));
+
+    // the variables end with the query they were set for
+    assert_eq!(tenant_id().await, None);
+}
+
+async fn collect_batches(
+    stream: datafusion::execution::SendableRecordBatchStream,
+) -> Vec<RecordBatch> {
+    datafusion::physical_plan::common::collect(stream)
+        .await
+        .expect("rows read")
+}
+
+async fn test_postgres_inherited_tables(port: usize) {
+    let pool = Arc::new(
+        common::get_postgres_connection_pool(port)
+            .await
+            .expect("Postgres connection pool should be created"),
+    );
+
+    let db_conn = pool
+        .connect_direct()
+        .await
+        .expect("Connection should be established");
+    db_conn
+        .conn
+        .batch_execute(
+            "CREATE TABLE measurements (id BIGINT NOT NULL, year INT NOT NULL) PARTITION BY RANGE (year);
+            CREATE TABLE measurements_2024 PARTITION OF measurements FOR VALUES FROM (2024) TO (2025);
+            CREATE TABLE measurements_2025 PARTITION OF measurements FOR VALUES FROM (2025) TO (2026);
+            INSERT INTO measurements VALUES (1, 2024), (2, 2025), (3, 2025);
+            CREATE TABLE cities (id BIGINT NOT NULL);
+            CREATE TABLE capitals (country TEXT) INHERITS (cities);
+            INSERT INTO cities VALUES (1);
+            INSERT INTO capitals VALUES (2, 'fr');
+            ANALYZE measurements_2024, measurements_2025, cities, capitals;",
+        )
+        .await
+        .expect("Postgres tables should be created");
+
+    let count = |factory: PostgresTableFactory, table: &'static str| async move {
+        let ctx = SessionContext::new();
+        let provider = factory
+            .table_provider(table.into())
+            .await
+            .expect("table provider created");
+        ctx.register_table(table, provider)
+            .expect("Table should be registered");
+        let record_batch = ctx
+            .sql(&format!("SELECT COUNT(*) FROM {table}"))
+            .await
+            .expect("DataFrame should be created from query")
+            .collect()
+            .await
+            .expect("RecordBatch should be collected");
+        record_batch[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<arrow::array::Int64Array>()
+            .expect("count column")
+            .value(0)
+    };
+
+    let factory = || PostgresTableFactory::new(Arc::clone(&pool));
+    assert_eq!(count(factory(), "measurements").await, 3);
+    assert_eq!(count(factory(), "cities").await, 2);
+
+    let provider = factory()
+        .table_provider("measurements".into())
+        .await
+        .expect("table provider created");
+    assert_eq!(
+        provider.statistics().map(|statistics| statistics.num_rows),
+        Some(datafusion::common::stats::Precision::Inexact(3))
+    );
+
+    assert_eq!(count(factory().with_only(true), "cities").await, 1);
+}
+
 async fn arrow_postgres_one_way(
     port: usize,
     table_name: &str,
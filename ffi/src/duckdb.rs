@@ -0,0 +1,48 @@
+use std::{ffi::c_char, ptr, str::FromStr, sync::Arc};
+
+use datafusion_table_providers::{
+    duckdb::DuckDBTableFactory, sql::db_connection_pool::duckdbpool::DuckDbConnectionPool,
+};
+use duckdb::AccessMode;
+use futures::FutureExt;
+
+use crate::{set_error, to_str, TableFactory};
+
+/// Creates a table factory of the DuckDB database at `path`, opened with `access_mode`
+/// (`AUTOMATIC`, `READ_ONLY` or `READ_WRITE`), or of an in-memory database if `path` is null.
+///
+/// # Safety
+///
+/// `path` must be null or a valid C string, `access_mode` must be a valid C string unless `path`
+/// is null and `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn dftp_duckdb_factory_new(
+    path: *const c_char,
+    access_mode: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut TableFactory {
+    let pool = if path.is_null() {
+        DuckDbConnectionPool::new_memory().map_err(|e| e.to_string())
+    } else {
+        to_str(path).and_then(|path| {
+            let access_mode =
+                AccessMode::from_str(to_str(access_mode)?).map_err(|e| e.to_string())?;
+            DuckDbConnectionPool::new_file(path, &access_mode).map_err(|e| e.to_string())
+        })
+    };
+
+    match pool {
+        Ok(pool) => {
+            let factory = Arc::new(DuckDBTableFactory::new(Arc::new(pool)));
+            TableFactory::new(move |table_reference| {
+                let factory = Arc::clone(&factory);
+                async move { factory.table_provider(table_reference).await }.boxed()
+            })
+            .into_raw()
+        }
+        Err(e) => {
+            set_error(error, e);
+            ptr::null_mut()
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! C ABI of the table providers, for hosts like datafusion-c or the Java bindings that load this
+//! crate as a cdylib.
+//!
+//! A host creates a table factory of a database with one of the `dftp_*_factory_new` functions,
+//! gets the providers of its tables as DataFusion [`FFI_TableProvider`]s with
+//! [`dftp_table_factory_table_provider`] and frees the factory with [`dftp_table_factory_free`].
+//! The providers hold on to the pool of the factory, so they outlive it.
+//!
+//! Functions that can fail return null or `false` and store an error message in `error`, which
+//! the host frees with [`dftp_error_free`].
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+    sync::Arc,
+};
+
+use datafusion::{catalog::TableProvider, sql::TableReference};
+use datafusion_ffi::table_provider::FFI_TableProvider;
+use datafusion_table_providers::sql::db_connection_pool::runtime::{
+    execute_in_tokio, tokio_handle,
+};
+use futures::future::BoxFuture;
+
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+#[cfg(feature = "mysql")]
+pub mod mysql;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type TableProviderFn = dyn Fn(TableReference) -> BoxFuture<'static, Result<Arc<dyn TableProvider>, BoxError>>
+    + Send
+    + Sync;
+
+/// Table provider factory of a database, handed to the host as an opaque pointer.
+pub struct TableFactory {
+    table_provider: Box<TableProviderFn>,
+}
+
+impl TableFactory {
+    fn new(
+        table_provider: impl Fn(TableReference) -> BoxFuture<'static, Result<Arc<dyn TableProvider>, BoxError>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            table_provider: Box::new(table_provider),
+        }
+    }
+
+    fn into_raw(self) -> *mut TableFactory {
+        Box::into_raw(Box::new(self))
+    }
+}
+
+/// Stores the provider of the table `table_reference` of `factory` in `out`.
+///
+/// # Safety
+///
+/// `factory` must come from a `dftp_*_factory_new` function, `table_reference` must be a valid C
+/// string, `out` must be valid for writes and `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn dftp_table_factory_table_provider(
+    factory: *const TableFactory,
+    table_reference: *const c_char,
+    out: *mut FFI_TableProvider,
+    error: *mut *mut c_char,
+) -> bool {
+    let Some(factory) = factory.as_ref() else {
+        set_error(error, "The table factory is null");
+        return false;
+    };
+    let table_reference = match to_str(table_reference) {
+        Ok(table_reference) => TableReference::from(table_reference),
+        Err(e) => {
+            set_error(error, e);
+            return false;
+        }
+    };
+
+    match execute_in_tokio(|| (factory.table_provider)(table_reference)) {
+        Ok(table) => {
+            let provider = FFI_TableProvider::new(table, true, Some(tokio_handle()));
+            ptr::write(out, provider);
+            true
+        }
+        Err(e) => {
+            set_error(error, e);
+            false
+        }
+    }
+}
+
+/// Frees a table factory, whose providers stay usable.
+///
+/// # Safety
+///
+/// `factory` must be null or come from a `dftp_*_factory_new` function and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dftp_table_factory_free(factory: *mut TableFactory) {
+    if !factory.is_null() {
+        drop(Box::from_raw(factory));
+    }
+}
+
+/// Frees an error message stored by one of the functions of this crate.
+///
+/// # Safety
+///
+/// `error` must be null or an error message of this crate that is not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dftp_error_free(error: *mut c_char) {
+    if !error.is_null() {
+        drop(CString::from_raw(error));
+    }
+}
+
+/// Stores the message of `err` in `error`, unless the host passed null.
+unsafe fn set_error(error: *mut *mut c_char, err: impl ToString) {
+    if error.is_null() {
+        return;
+    }
+    // a message with a NUL byte is cut at the first one rather than lost
+    let mut message = err.to_string();
+    if let Some(nul) = message.find('\0') {
+        message.truncate(nul);
+    }
+    *error = CString::new(message).unwrap_or_default().into_raw();
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("Expected a string but got null".to_string());
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| format!("Expected a UTF-8 string: {e}"))
+}
+
+/// Collects the `len` connection parameters of the `keys` and `values` arrays.
+#[cfg(any(feature = "mysql", feature = "postgres"))]
+unsafe fn to_params(
+    keys: *const *const c_char,
+    values: *const *const c_char,
+    len: usize,
+) -> Result<std::collections::HashMap<String, String>, String> {
+    let mut params = std::collections::HashMap::with_capacity(len);
+    if len == 0 {
+        return Ok(params);
+    }
+    if keys.is_null() || values.is_null() {
+        return Err("Expected connection parameters but got null".to_string());
+    }
+    for i in 0..len {
+        let key = to_str(*keys.add(i))?;
+        let value = to_str(*values.add(i))?;
+        params.insert(key.to_string(), value.to_string());
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_error_cuts_message_at_nul() {
+        let mut error = ptr::null_mut();
+        unsafe {
+            set_error(&mut error, "connection failed\0 secret");
+            assert_eq!(CStr::from_ptr(error).to_str().unwrap(), "connection failed");
+            dftp_error_free(error);
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_table_provider() {
+        let path = CString::new("../core/examples/sqlite_example.db").unwrap();
+        let mode = CString::new("file").unwrap();
+        let table = CString::new("companies").unwrap();
+        let mut error = ptr::null_mut();
+        let mut provider = std::mem::MaybeUninit::<FFI_TableProvider>::uninit();
+
+        unsafe {
+            let factory =
+                sqlite::dftp_sqlite_factory_new(path.as_ptr(), mode.as_ptr(), 5000, &mut error);
+            assert!(!factory.is_null());
+            assert!(dftp_table_factory_table_provider(
+                factory,
+                table.as_ptr(),
+                provider.as_mut_ptr(),
+                &mut error,
+            ));
+            dftp_table_factory_free(factory);
+            drop(provider.assume_init());
+        }
+        assert!(error.is_null());
+    }
+}
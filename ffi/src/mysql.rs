@@ -0,0 +1,48 @@
+use std::{ffi::c_char, ptr, sync::Arc};
+
+use datafusion_table_providers::{
+    mysql::MySQLTableFactory,
+    sql::db_connection_pool::{mysqlpool::MySQLConnectionPool, runtime::execute_in_tokio},
+    util::secrets::to_secret_map,
+};
+use futures::FutureExt;
+
+use crate::{set_error, to_params, TableFactory};
+
+/// Creates a table factory of the MySQL database of the `len` connection parameters in
+/// `keys` and `values`, the same ones as the ones of [`MySQLConnectionPool::new`].
+///
+/// # Safety
+///
+/// `keys` and `values` must point to `len` valid C strings each and `error` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn dftp_mysql_factory_new(
+    keys: *const *const c_char,
+    values: *const *const c_char,
+    len: usize,
+    error: *mut *mut c_char,
+) -> *mut TableFactory {
+    let params = match to_params(keys, values, len) {
+        Ok(params) => to_secret_map(params),
+        Err(e) => {
+            set_error(error, e);
+            return ptr::null_mut();
+        }
+    };
+
+    match execute_in_tokio(|| MySQLConnectionPool::new(params)) {
+        Ok(pool) => {
+            let factory = Arc::new(MySQLTableFactory::new(Arc::new(pool)));
+            TableFactory::new(move |table_reference| {
+                let factory = Arc::clone(&factory);
+                async move { factory.table_provider(table_reference).await }.boxed()
+            })
+            .into_raw()
+        }
+        Err(e) => {
+            set_error(error, e);
+            ptr::null_mut()
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::{ffi::c_char, ptr, sync::Arc, time::Duration};
+
+use datafusion_table_providers::{
+    sql::db_connection_pool::{runtime::execute_in_tokio, sqlitepool::SqliteConnectionPoolFactory},
+    sqlite::SqliteTableFactory,
+};
+use futures::FutureExt;
+
+use crate::{set_error, to_str, TableFactory};
+
+/// Creates a table factory of the SQLite database at `path`, opened in `mode` (`file` or
+/// `memory`).
+///
+/// # Safety
+///
+/// `path` and `mode` must be valid C strings and `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn dftp_sqlite_factory_new(
+    path: *const c_char,
+    mode: *const c_char,
+    busy_timeout_ms: u64,
+    error: *mut *mut c_char,
+) -> *mut TableFactory {
+    let (path, mode) = match (to_str(path), to_str(mode)) {
+        (Ok(path), Ok(mode)) => (path, mode),
+        (Err(e), _) | (_, Err(e)) => {
+            set_error(error, e);
+            return ptr::null_mut();
+        }
+    };
+    let factory =
+        SqliteConnectionPoolFactory::new(path, mode.into(), Duration::from_millis(busy_timeout_ms));
+
+    match execute_in_tokio(|| factory.build()) {
+        Ok(pool) => {
+            let factory = Arc::new(SqliteTableFactory::new(Arc::new(pool)));
+            TableFactory::new(move |table_reference| {
+                let factory = Arc::clone(&factory);
+                async move { factory.table_provider(table_reference).await }.boxed()
+            })
+            .into_raw()
+        }
+        Err(e) => {
+            set_error(error, e);
+            ptr::null_mut()
+        }
+    }
+}
@@ -16,7 +16,8 @@
 # under the License.
 """Python interface for Postgres table provider."""
 
-from typing import Any, List
+from datafusion import SessionContext
+from typing import Any, List, Optional
 from . import _internal
 
 class PostgresTableFactory:
@@ -37,3 +38,15 @@ class PostgresTableFactory:
             table_reference (str): table name
         """
         return self._raw.get_table(table_reference)
+
+    def register_tables(
+        self, ctx: SessionContext, tables: Optional[List[str]] = None
+    ) -> None:
+        """Register tables with the session under their names.
+
+        Args:
+            ctx: session to register the tables with
+            tables: names of the tables, all the tables by default
+        """
+        for table in self.tables() if tables is None else tables:
+            ctx.register_table_provider(table, self.get_table(table))
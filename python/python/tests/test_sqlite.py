@@ -17,6 +17,14 @@ class TestSqliteIntegration:
         assert len(tables) == 2
         assert tables == ["companies", "projects"]
         
+    def test_register_tables(self):
+        """Test registering all the tables with the session"""
+        self.pool.register_tables(self.ctx)
+
+        df = self.ctx.sql("SELECT count(*) AS count FROM companies, projects")
+        result = df.collect()
+        assert len(result) == 1
+
     def test_query_companies(self):
         """Test querying companies table with SQL"""
         self.ctx.register_table_provider("companies", self.pool.get_table("companies"))
@@ -28,42 +28,50 @@ impl RawTableProvider {
     }
 }
 
+#[cfg(feature = "duckdb")]
 pub mod duckdb;
+#[cfg(feature = "flight")]
 pub mod flight;
+#[cfg(feature = "mysql")]
 pub mod mysql;
+#[cfg(feature = "odbc")]
 pub mod odbc;
+#[cfg(feature = "postgres")]
 pub mod postgres;
+#[cfg(feature = "sqlite")]
 pub mod sqlite;
 pub mod utils;
 
+/// Adds the submodule `name` of a database, whose classes `init_module` adds.
+fn add_submodule(
+    py: Python,
+    m: &Bound<'_, PyModule>,
+    name: &str,
+    init_module: fn(&Bound<'_, PyModule>) -> PyResult<()>,
+) -> PyResult<()> {
+    let submodule = PyModule::new(py, name)?;
+    init_module(&submodule)?;
+    m.add_submodule(&submodule)
+}
+
 #[pymodule]
 // module name need to match project name
 fn _internal(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<RawTableProvider>()?;
 
-    let sqlite = PyModule::new(py, "sqlite")?;
-    sqlite::init_module(&sqlite)?;
-    m.add_submodule(&sqlite)?;
-
-    let duckdb = PyModule::new(py, "duckdb")?;
-    duckdb::init_module(&duckdb)?;
-    m.add_submodule(&duckdb)?;
-
-    let odbc = PyModule::new(py, "odbc")?;
-    odbc::init_module(&odbc)?;
-    m.add_submodule(&odbc)?;
-
-    let mysql = PyModule::new(py, "mysql")?;
-    mysql::init_module(&mysql)?;
-    m.add_submodule(&mysql)?;
-
-    let postgres = PyModule::new(py, "postgres")?;
-    postgres::init_module(&postgres)?;
-    m.add_submodule(&postgres)?;
-
-    let flight = PyModule::new(py, "flight")?;
-    flight::init_module(&flight)?;
-    m.add_submodule(&flight)?;
+    // only the databases whose features are enabled are part of the module
+    #[cfg(feature = "sqlite")]
+    add_submodule(py, m, "sqlite", sqlite::init_module)?;
+    #[cfg(feature = "duckdb")]
+    add_submodule(py, m, "duckdb", duckdb::init_module)?;
+    #[cfg(feature = "odbc")]
+    add_submodule(py, m, "odbc", odbc::init_module)?;
+    #[cfg(feature = "mysql")]
+    add_submodule(py, m, "mysql", mysql::init_module)?;
+    #[cfg(feature = "postgres")]
+    add_submodule(py, m, "postgres", postgres::init_module)?;
+    #[cfg(feature = "flight")]
+    add_submodule(py, m, "flight", flight::init_module)?;
 
     Ok(())
 }